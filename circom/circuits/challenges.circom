@@ -1,6 +1,7 @@
 pragma circom 2.0.9;
 include "./constants.circom";
 include "./poseidon.circom";
+include "./keccak.circom";
 
 template GetChallenges() {
   signal input wires_cap[NUM_WIRES_CAP()][4];
@@ -185,71 +186,129 @@ template GetChallenges() {
   }
 
   /// batch 5
-  var num_inputs_batch_5 = NUM_FRI_FINAL_POLY_EXT_V() * 2 + 1;
+  // With FRI_POW_HASH() == 1, the PoW witness is hashed with Keccak-256 instead of being observed.
+  var num_inputs_batch_5 = NUM_FRI_FINAL_POLY_EXT_V() * 2 + (FRI_POW_HASH() == 0 ? 1 : 0);
   component observe_batch_5 = HashNoPad_BN(num_inputs_batch_5, SPONGE_WIDTH());
   for (var i = 0; i < NUM_FRI_FINAL_POLY_EXT_V(); i++) {
     observe_batch_5.in[i * 2] <== fri_final_poly_ext_v[i][0];
     observe_batch_5.in[i * 2 + 1] <== fri_final_poly_ext_v[i][1];
   }
-  observe_batch_5.in[num_inputs_batch_5 - 1] <== fri_pow_witness;
+  if (FRI_POW_HASH() == 0) {
+    observe_batch_5.in[num_inputs_batch_5 - 1] <== fri_pow_witness;
+  }
   for (var i = 0; i < 4; i++) {
     observe_batch_5.capacity[i] <== observe_batch_4[NUM_FRI_COMMIT_ROUND() - 1].out[SPONGE_RATE() + i];
   }
-  fri_pow_response <== observe_batch_5.out[SPONGE_RATE() - 1];
-  // log(fri_pow_response);
 
-  /// Get fri_query_indices
-  // First SPONGE_RATE() - 1 = 7 indices
   component mod_lde_size[NUM_FRI_QUERY_ROUND()];
-  for (var i = 0; i < NUM_FRI_QUERY_ROUND(); i++) {
-    mod_lde_size[i] = LastNBits(DEGREE_BITS() + FRI_RATE_BITS());
-  }
-  for (var i = 1; i < SPONGE_RATE(); i++) {
-    mod_lde_size[i - 1].x <== observe_batch_5.out[SPONGE_RATE() - 1 - i];
-    fri_query_indices[i - 1] <== mod_lde_size[i - 1].out;
-    // log(fri_query_indices[i - 1]);
-  }
+  component observe_batch_6;
+  component observe_batch_7;
+  component observe_batch_8;
+  component pow_input_bits[5];
+  component pow_keccak;
+  component query_keccak[(NUM_FRI_QUERY_ROUND() + 3) \ 4];
+  if (FRI_POW_HASH() == 0) {
+    fri_pow_response <== observe_batch_5.out[SPONGE_RATE() - 1];
+    // log(fri_pow_response);
 
-  assert(NUM_FRI_QUERY_ROUND() <= 7 + 3 * SPONGE_RATE());
-  component observe_batch_6 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
-  for (var i = 0; i < SPONGE_RATE(); i++) {
-    observe_batch_6.in[i] <== observe_batch_5.out[i];
-  }
-  for (var i = 0; i < 4; i++) {
-    observe_batch_6.capacity[i] <== observe_batch_5.out[SPONGE_RATE() + i];
-  }
-  for (var i = 7; i < NUM_FRI_QUERY_ROUND() && i < 7 + SPONGE_RATE(); i++) {
-    mod_lde_size[i].x <== observe_batch_6.out[SPONGE_RATE() - 1 - (i - 7)];
-    fri_query_indices[i] <== mod_lde_size[i].out;
-    // log(fri_query_indices[i]);
-  }
+    /// Get fri_query_indices
+    // First SPONGE_RATE() - 1 = 7 indices
+    for (var i = 0; i < NUM_FRI_QUERY_ROUND(); i++) {
+      mod_lde_size[i] = LastNBits(DEGREE_BITS() + FRI_RATE_BITS());
+    }
+    for (var i = 1; i < SPONGE_RATE(); i++) {
+      mod_lde_size[i - 1].x <== observe_batch_5.out[SPONGE_RATE() - 1 - i];
+      fri_query_indices[i - 1] <== mod_lde_size[i - 1].out;
+      // log(fri_query_indices[i - 1]);
+    }
 
-  component observe_batch_7 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
-  if (NUM_FRI_QUERY_ROUND() - 7 > SPONGE_RATE()) {
+    assert(NUM_FRI_QUERY_ROUND() <= 7 + 3 * SPONGE_RATE());
+    observe_batch_6 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
     for (var i = 0; i < SPONGE_RATE(); i++) {
-      observe_batch_7.in[i] <== observe_batch_6.out[i];
+      observe_batch_6.in[i] <== observe_batch_5.out[i];
     }
     for (var i = 0; i < 4; i++) {
-      observe_batch_7.capacity[i] <== observe_batch_6.out[SPONGE_RATE() + i];
+      observe_batch_6.capacity[i] <== observe_batch_5.out[SPONGE_RATE() + i];
     }
-    for (var i = 7 + SPONGE_RATE(); i < NUM_FRI_QUERY_ROUND() && i < 7 + 2 * SPONGE_RATE(); i++) {
-      mod_lde_size[i].x <== observe_batch_7.out[SPONGE_RATE() - 1 - (i - 7 - SPONGE_RATE())];
+    for (var i = 7; i < NUM_FRI_QUERY_ROUND() && i < 7 + SPONGE_RATE(); i++) {
+      mod_lde_size[i].x <== observe_batch_6.out[SPONGE_RATE() - 1 - (i - 7)];
       fri_query_indices[i] <== mod_lde_size[i].out;
       // log(fri_query_indices[i]);
     }
-  }
 
-  component observe_batch_8 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
-  if (NUM_FRI_QUERY_ROUND() - 7 > 2 * SPONGE_RATE()) {
-    for (var i = 0; i < SPONGE_RATE(); i++) {
-      observe_batch_8.in[i] <== observe_batch_7.out[i];
+    if (NUM_FRI_QUERY_ROUND() - 7 > SPONGE_RATE()) {
+      observe_batch_7 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
+      for (var i = 0; i < SPONGE_RATE(); i++) {
+        observe_batch_7.in[i] <== observe_batch_6.out[i];
+      }
+      for (var i = 0; i < 4; i++) {
+        observe_batch_7.capacity[i] <== observe_batch_6.out[SPONGE_RATE() + i];
+      }
+      for (var i = 7 + SPONGE_RATE(); i < NUM_FRI_QUERY_ROUND() && i < 7 + 2 * SPONGE_RATE(); i++) {
+        mod_lde_size[i].x <== observe_batch_7.out[SPONGE_RATE() - 1 - (i - 7 - SPONGE_RATE())];
+        fri_query_indices[i] <== mod_lde_size[i].out;
+        // log(fri_query_indices[i]);
+      }
     }
-    for (var i = 0; i < 4; i++) {
-      observe_batch_8.capacity[i] <== observe_batch_7.out[SPONGE_RATE() + i];
+
+    if (NUM_FRI_QUERY_ROUND() - 7 > 2 * SPONGE_RATE()) {
+      observe_batch_8 = HashNoPad_BN(SPONGE_RATE(), SPONGE_WIDTH());
+      for (var i = 0; i < SPONGE_RATE(); i++) {
+        observe_batch_8.in[i] <== observe_batch_7.out[i];
+      }
+      for (var i = 0; i < 4; i++) {
+        observe_batch_8.capacity[i] <== observe_batch_7.out[SPONGE_RATE() + i];
+      }
+      for (var i = 7 + 2 * SPONGE_RATE(); i < NUM_FRI_QUERY_ROUND() && i < 7 + 3 * SPONGE_RATE(); i++) {
+        mod_lde_size[i].x <== observe_batch_8.out[SPONGE_RATE() - 1 - (i - 7 - 2 * SPONGE_RATE())];
+        fri_query_indices[i] <== mod_lde_size[i].out;
+        // log(fri_query_indices[i]);
+      }
     }
-    for (var i = 7 + 2 * SPONGE_RATE(); i < NUM_FRI_QUERY_ROUND() && i < 7 + 3 * SPONGE_RATE(); i++) {
-      mod_lde_size[i].x <== observe_batch_8.out[SPONGE_RATE() - 1 - (i - 7 - 2 * SPONGE_RATE())];
-      fri_query_indices[i] <== mod_lde_size[i].out;
+  } else {
+    // digest = keccak256(seed || fri_pow_witness), where the seed is the next 4 challenges.
+    pow_keccak = Keccak256(40);
+    for (var i = 0; i < 5; i++) {
+      pow_input_bits[i] = Num2Bits(64);
+      if (i < 4) {
+        pow_input_bits[i].in <== observe_batch_5.out[SPONGE_RATE() - 1 - i];
+      } else {
+        pow_input_bits[i].in <== fri_pow_witness;
+      }
+      for (var j = 0; j < 64; j++) {
+        pow_keccak.in[i * 64 + j] <== pow_input_bits[i].out[j];
+      }
+    }
+
+    // The PoW response is the first 8 bytes of the digest, as a big-endian integer, shifted right
+    // by one bit.
+    var response = 0;
+    for (var byte = 0; byte < 8; byte++) {
+      for (var bit = 0; bit < 8; bit++) {
+        if (byte < 7 || bit > 0) {
+          response += pow_keccak.out[byte * 8 + bit] * 2 ** (8 * (7 - byte) + bit - 1);
+        }
+      }
+    }
+    fri_pow_response <== response;
+
+    // Query index i is the (i % 4)-th little-endian u64 of keccak256(digest || i \ 4), modulo the
+    // LDE size.
+    for (var block = 0; block < (NUM_FRI_QUERY_ROUND() + 3) \ 4; block++) {
+      query_keccak[block] = Keccak256(40);
+      for (var j = 0; j < 256; j++) {
+        query_keccak[block].in[j] <== pow_keccak.out[j];
+      }
+      for (var j = 0; j < 64; j++) {
+        query_keccak[block].in[256 + j] <== (block >> j) & 1;
+      }
+    }
+    for (var i = 0; i < NUM_FRI_QUERY_ROUND(); i++) {
+      var index = 0;
+      for (var j = 0; j < DEGREE_BITS() + FRI_RATE_BITS(); j++) {
+        index += query_keccak[i \ 4].out[(i % 4) * 64 + j] * 2 ** j;
+      }
+      fri_query_indices[i] <== index;
       // log(fri_query_indices[i]);
     }
   }
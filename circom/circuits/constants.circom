@@ -21,88 +21,88 @@ function NUM_OPENINGS_QUOTIENT_POLYS() { return 16; }
 
 function NUM_FRI_COMMIT_ROUND() { return 2; }
 function FRI_COMMIT_MERKLE_CAP_HEIGHT() { return 16; }
-function NUM_FRI_QUERY_ROUND() { return 28; }
+function NUM_FRI_QUERY_ROUND() { return 20; }
 function NUM_FRI_QUERY_INIT_CONSTANTS_SIGMAS_V() { return 85; }
-function NUM_FRI_QUERY_INIT_CONSTANTS_SIGMAS_P() { return 12; }
+function NUM_FRI_QUERY_INIT_CONSTANTS_SIGMAS_P() { return 13; }
 function NUM_FRI_QUERY_INIT_WIRES_V() { return 135; }
-function NUM_FRI_QUERY_INIT_WIRES_P() { return 12; }
+function NUM_FRI_QUERY_INIT_WIRES_P() { return 13; }
 function NUM_FRI_QUERY_INIT_ZS_PARTIAL_V() { return 20; }
-function NUM_FRI_QUERY_INIT_ZS_PARTIAL_P() { return 12; }
+function NUM_FRI_QUERY_INIT_ZS_PARTIAL_P() { return 13; }
 function NUM_FRI_QUERY_INIT_QUOTIENT_V() { return 16; }
-function NUM_FRI_QUERY_INIT_QUOTIENT_P() { return 12; }
+function NUM_FRI_QUERY_INIT_QUOTIENT_P() { return 13; }
 function NUM_FRI_QUERY_STEP0_V() { return 16; }
-function NUM_FRI_QUERY_STEP0_P() { return 8; }
+function NUM_FRI_QUERY_STEP0_P() { return 9; }
 function NUM_FRI_QUERY_STEP1_V() { return 16; }
-function NUM_FRI_QUERY_STEP1_P() { return 4; }
+function NUM_FRI_QUERY_STEP1_P() { return 5; }
 function NUM_FRI_FINAL_POLY_EXT_V() { return 32; }
 
 function NUM_SIGMA_CAPS() { return 16; }
 function GET_SIGMA_CAP(i) {
   var sc[16][4];
-  sc[0][0] = 8735702079412807595;
-  sc[0][1] = 12651190851383984775;
-  sc[0][2] = 2546044947937764573;
-  sc[0][3] = 13303149370071437337;
-  sc[1][0] = 1941777143505273894;
-  sc[1][1] = 1523132012139272777;
-  sc[1][2] = 654970703618853568;
-  sc[1][3] = 8494067862840677008;
-  sc[2][0] = 504758325302982850;
-  sc[2][1] = 15923684185052641802;
-  sc[2][2] = 897408763642323954;
-  sc[2][3] = 15943700718876250333;
-  sc[3][0] = 16884133298135290929;
-  sc[3][1] = 10845920204240776697;
-  sc[3][2] = 5623162835562036198;
-  sc[3][3] = 18379416514882264692;
-  sc[4][0] = 9809697234694253159;
-  sc[4][1] = 9383107996724564505;
-  sc[4][2] = 2800070043027272556;
-  sc[4][3] = 7347692118327279144;
-  sc[5][0] = 9505580737584231578;
-  sc[5][1] = 15957763120896208704;
-  sc[5][2] = 18142437950855303249;
-  sc[5][3] = 3796865203211701215;
-  sc[6][0] = 10534116546231372807;
-  sc[6][1] = 6008299718402670972;
-  sc[6][2] = 8662258389532651474;
-  sc[6][3] = 15292985489972271705;
-  sc[7][0] = 2983041562430116982;
-  sc[7][1] = 14523674620239675802;
-  sc[7][2] = 3756567260705265536;
-  sc[7][3] = 15006128645451054089;
-  sc[8][0] = 423210246080588966;
-  sc[8][1] = 8184182124156218686;
-  sc[8][2] = 17750533266129551747;
-  sc[8][3] = 3303200713389221904;
-  sc[9][0] = 2125604167821864057;
-  sc[9][1] = 2807816633171631907;
-  sc[9][2] = 2029480249001036830;
-  sc[9][3] = 10200617759087041502;
-  sc[10][0] = 15157200106576764467;
-  sc[10][1] = 14434163364665655304;
-  sc[10][2] = 7173115970192263475;
-  sc[10][3] = 6700662149320907205;
-  sc[11][0] = 13796013847151746454;
-  sc[11][1] = 16969426037898461546;
-  sc[11][2] = 8036826954704656594;
-  sc[11][3] = 11746393849448736807;
-  sc[12][0] = 12680788311394547826;
-  sc[12][1] = 13714914068488009677;
-  sc[12][2] = 10508691874279314639;
-  sc[12][3] = 4017840374288926812;
-  sc[13][0] = 6277851273824060483;
-  sc[13][1] = 13808296804695958919;
-  sc[13][2] = 3543884313623306890;
-  sc[13][3] = 14208201263968657411;
-  sc[14][0] = 13169529975109019662;
-  sc[14][1] = 13867889664568270051;
-  sc[14][2] = 8963622937222525694;
-  sc[14][3] = 3013097044026155407;
-  sc[15][0] = 11807537875098994460;
-  sc[15][1] = 2347865808533457735;
-  sc[15][2] = 4001046819983693305;
-  sc[15][3] = 7659201705118783865;
+  sc[0][0] = 16481811772047319127;
+  sc[0][1] = 5456492392283256911;
+  sc[0][2] = 644643976611734353;
+  sc[0][3] = 15735768278672113999;
+  sc[1][0] = 15610790923138242083;
+  sc[1][1] = 6349569207516597727;
+  sc[1][2] = 14206804512296819227;
+  sc[1][3] = 11717579756531232150;
+  sc[2][0] = 8802349757917954780;
+  sc[2][1] = 4986501808452053286;
+  sc[2][2] = 7959326562197656776;
+  sc[2][3] = 6625232874726144908;
+  sc[3][0] = 11653146745132314423;
+  sc[3][1] = 5899736183064983823;
+  sc[3][2] = 3062247184921298486;
+  sc[3][3] = 11267972014979240644;
+  sc[4][0] = 15825175796208684508;
+  sc[4][1] = 2927321284426109013;
+  sc[4][2] = 3142075163554062412;
+  sc[4][3] = 2368380708285871043;
+  sc[5][0] = 8889727426422753346;
+  sc[5][1] = 2855938422292007984;
+  sc[5][2] = 15181069726996462226;
+  sc[5][3] = 11197504437159794336;
+  sc[6][0] = 12268270025098963026;
+  sc[6][1] = 5195635000585496277;
+  sc[6][2] = 16079936252049572846;
+  sc[6][3] = 11336362527921381553;
+  sc[7][0] = 15668954516189412921;
+  sc[7][1] = 1311817697346018262;
+  sc[7][2] = 15765987517502849048;
+  sc[7][3] = 698786651227999477;
+  sc[8][0] = 1654389525652507106;
+  sc[8][1] = 14746437316273780366;
+  sc[8][2] = 4341966761120288379;
+  sc[8][3] = 11543790464897589271;
+  sc[9][0] = 16613566656273448659;
+  sc[9][1] = 16239748966463779793;
+  sc[9][2] = 792364696391856790;
+  sc[9][3] = 10239388162304448883;
+  sc[10][0] = 17077286994357113517;
+  sc[10][1] = 3182672480142177197;
+  sc[10][2] = 11846469780368415401;
+  sc[10][3] = 18309410691121481772;
+  sc[11][0] = 6701031504470729438;
+  sc[11][1] = 16311765803256666481;
+  sc[11][2] = 3062768407962054542;
+  sc[11][3] = 5911743973110310933;
+  sc[12][0] = 13239479494960285808;
+  sc[12][1] = 12016991322170902112;
+  sc[12][2] = 2804985495456941188;
+  sc[12][3] = 8748064332024901373;
+  sc[13][0] = 5449623165910104715;
+  sc[13][1] = 15015605908149566222;
+  sc[13][2] = 2722893439003786900;
+  sc[13][3] = 13182322626330759277;
+  sc[14][0] = 5577754708329717286;
+  sc[14][1] = 5600764124472785672;
+  sc[14][2] = 3807828878469255731;
+  sc[14][3] = 14825587603485203420;
+  sc[15][0] = 3776871107050398326;
+  sc[15][1] = 13518120625624214116;
+  sc[15][2] = 7312491691745454787;
+  sc[15][3] = 14689730374956508954;
   return sc[i];
 }
 
@@ -131,23 +131,24 @@ function G_FROM_DEGREE_BITS() {
 }
 
 function MULTIPLICATIVE_GROUP_GENERATOR() { return 7; }
-function PRIMITIVE_ROOT_OF_UNITY_LDE() { return 6115771955107415310; }
-function LOG_SIZE_OF_LDE_DOMAIN() { return 16; }
+function PRIMITIVE_ROOT_OF_UNITY_LDE() { return 12380578893860276750; }
+function LOG_SIZE_OF_LDE_DOMAIN() { return 17; }
 function NUM_CHALLENGES() { return 2; }
-function MIN_FRI_POW_RESPONSE() { return 16; }
+function MIN_FRI_POW_RESPONSE() { return 20; }
+function FRI_POW_HASH() { return 0; }
 function CIRCUIT_DIGEST() {
   var cd[4];
-  cd[0] = 846466281868400341;
-  cd[1] = 13238426685112614553;
-  cd[2] = 17467000616145489607;
-  cd[3] = 11528505106269646781;
+  cd[0] = 52437167253835512;
+  cd[1] = 4860166902219810073;
+  cd[2] = 4838359110708561728;
+  cd[3] = 1583129843604157927;
   return cd;
 }
 function SPONGE_RATE() { return 8; }
 function SPONGE_CAPACITY() { return 4; }
 function SPONGE_WIDTH() { return 12; }
 function DEGREE_BITS() { return 13; }
-function FRI_RATE_BITS() { return 3; }
+function FRI_RATE_BITS() { return 4; }
 function NUM_GATE_CONSTRAINTS() { return 123; }
 function NUM_PARTIAL_PRODUCTS_TERMS() { return (NUM_OPENINGS_PLONK_SIGMAS() + QUOTIENT_DEGREE_FACTOR() - 1) \ QUOTIENT_DEGREE_FACTOR(); }
 function QUOTIENT_DEGREE_FACTOR() { return 8; }
@@ -235,4 +236,5 @@ function K_IS(i) {
   k_is[79] = 9767480182670369297;
   return k_is[i];
 }
-function NUM_PUBLIC_INPUTS() { return 8; }
+function NUM_PUBLIC_INPUTS() { return 28; }
+function PUBLIC_INPUTS_COMMITMENT() { return 0; }
@@ -0,0 +1,131 @@
+pragma circom 2.0.9;
+
+// Keccak-256, as used by Ethereum, for the FRI proof-of-work when FRI_POW_HASH() == 1 and for the
+// public inputs digest when PUBLIC_INPUTS_COMMITMENT() == 1.
+// Bytes are arrays of 8 bits, least significant first, so a little-endian u64 is 64 bits in order.
+
+function KECCAK_ROUND_CONSTANT(i) {
+  var rc[24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008
+  ];
+  return rc[i];
+}
+
+// The rotation of lane x + 5 * y in the rho step.
+function KECCAK_ROTATION(lane) {
+  var rot[25] = [
+    0, 1, 62, 28, 27,
+    36, 44, 6, 55, 20,
+    3, 10, 43, 25, 39,
+    41, 45, 15, 21, 8,
+    18, 2, 61, 56, 14
+  ];
+  return rot[lane];
+}
+
+// One round of Keccak-f[1600]. Lane x + 5 * y of the state is in[x + 5 * y].
+template KeccakRound(r) {
+  signal input in[25][64];
+  signal output out[25][64];
+
+  // theta
+  signal c[5][64][4];
+  for (var x = 0; x < 5; x++) {
+    for (var z = 0; z < 64; z++) {
+      c[x][z][0] <== in[x][z] + in[x + 5][z] - 2 * in[x][z] * in[x + 5][z];
+      for (var y = 2; y < 5; y++) {
+        c[x][z][y - 1] <== c[x][z][y - 2] + in[x + 5 * y][z] - 2 * c[x][z][y - 2] * in[x + 5 * y][z];
+      }
+    }
+  }
+  signal d[5][64];
+  for (var x = 0; x < 5; x++) {
+    for (var z = 0; z < 64; z++) {
+      var left = c[(x + 4) % 5][z][3];
+      var right = c[(x + 1) % 5][(z + 63) % 64][3];
+      d[x][z] <== left + right - 2 * left * right;
+    }
+  }
+  signal theta[25][64];
+  for (var lane = 0; lane < 25; lane++) {
+    for (var z = 0; z < 64; z++) {
+      theta[lane][z] <== in[lane][z] + d[lane % 5][z] - 2 * in[lane][z] * d[lane % 5][z];
+    }
+  }
+
+  // rho and pi
+  var b[25][64];
+  for (var x = 0; x < 5; x++) {
+    for (var y = 0; y < 5; y++) {
+      for (var z = 0; z < 64; z++) {
+        b[y + 5 * ((2 * x + 3 * y) % 5)][z] = theta[x + 5 * y][(z - KECCAK_ROTATION(x + 5 * y) + 64) % 64];
+      }
+    }
+  }
+
+  // chi and iota
+  signal t[25][64];
+  for (var x = 0; x < 5; x++) {
+    for (var y = 0; y < 5; y++) {
+      for (var z = 0; z < 64; z++) {
+        var lane = x + 5 * y;
+        t[lane][z] <== (1 - b[(x + 1) % 5 + 5 * y][z]) * b[(x + 2) % 5 + 5 * y][z];
+        if (lane == 0 && ((KECCAK_ROUND_CONSTANT(r) >> z) & 1) == 1) {
+          out[lane][z] <== 1 - b[lane][z] - t[lane][z] + 2 * b[lane][z] * t[lane][z];
+        } else {
+          out[lane][z] <== b[lane][z] + t[lane][z] - 2 * b[lane][z] * t[lane][z];
+        }
+      }
+    }
+  }
+}
+
+// Keccak-256 of n_bytes bytes, absorbed in blocks of 136 bytes.
+template Keccak256(n_bytes) {
+  signal input in[n_bytes * 8];
+  signal output out[256];
+
+  var n_blocks = n_bytes \ 136 + 1;
+  component rounds[n_blocks][24];
+  for (var b = 0; b < n_blocks; b++) {
+    for (var r = 0; r < 24; r++) {
+      rounds[b][r] = KeccakRound(r);
+    }
+  }
+  // The message is padded with a 1 bit, zeros, and a final 1 bit at the end of the last block.
+  // Each block is XORed into the first 1088 bits (the rate) of the state.
+  for (var b = 0; b < n_blocks; b++) {
+    for (var lane = 0; lane < 25; lane++) {
+      for (var z = 0; z < 64; z++) {
+        var i = b * 1088 + lane * 64 + z;
+        var from_message = lane < 17 && i < n_bytes * 8;
+        var pad = lane < 17 && (i == n_bytes * 8 || i == n_blocks * 1088 - 1) ? 1 : 0;
+        if (b == 0) {
+          if (from_message) {
+            rounds[b][0].in[lane][z] <== in[i];
+          } else {
+            rounds[b][0].in[lane][z] <== pad;
+          }
+        } else {
+          var prev = rounds[b - 1][23].out[lane][z];
+          if (from_message) {
+            rounds[b][0].in[lane][z] <== prev + in[i] - 2 * prev * in[i];
+          } else {
+            rounds[b][0].in[lane][z] <== prev + pad - 2 * prev * pad;
+          }
+        }
+      }
+    }
+    for (var r = 1; r < 24; r++) {
+      rounds[b][r].in <== rounds[b][r - 1].out;
+    }
+  }
+  for (var i = 0; i < 256; i++) {
+    out[i] <== rounds[n_blocks - 1][23].out[i \ 64][i % 64];
+  }
+}
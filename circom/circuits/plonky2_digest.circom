@@ -0,0 +1,6 @@
+pragma circom 2.1.0;
+include "./verifier.circom";
+
+// Needs a constants.circom generated with a public inputs commitment, and a proof.json with the
+// matching public_inputs_digest.
+component main = VerifyPlonky2ProofWithDigest();
@@ -1,5 +1,6 @@
-CIRCUIT_NAME=plonky2
-CIRCUIT_PATH=../circuits/plonky2.circom
+# Set CIRCUIT_NAME=plonky2_digest for proofs exported with a public inputs commitment.
+CIRCUIT_NAME=${CIRCUIT_NAME:-plonky2}
+CIRCUIT_PATH=../circuits/${CIRCUIT_NAME}.circom
 INPUT_PATH=../test/data/proof.json
 POT_PATH=~/Downloads/powersOfTau28_hez_final_25.ptau
 RAPIDSNARK_PATH=../../../rapidsnark/build/prover
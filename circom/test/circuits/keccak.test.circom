@@ -0,0 +1,39 @@
+pragma circom 2.0.9;
+include "../../circuits/keccak.circom";
+
+template KeccakTest() {
+  signal input in;
+  signal output out;
+
+  // Dummy input/output
+  in === 1;
+  out <== 1;
+
+  // keccak256 of the bytes 0, 1, ..., 39.
+  var expected[32] = [
+    218, 34, 112, 151, 195, 155, 37, 245, 30, 187, 178, 85, 193, 123, 14, 230,
+    36, 188, 52, 240, 206, 161, 66, 205, 154, 129, 27, 150, 211, 212, 31, 50
+  ];
+  component k = Keccak256(40);
+  for (var i = 0; i < 320; i++) {
+    k.in[i] <== ((i \ 8) >> (i % 8)) & 1;
+  }
+  for (var i = 0; i < 256; i++) {
+    k.out[i] === (expected[i \ 8] >> (i % 8)) & 1;
+  }
+
+  // keccak256 of the bytes 0, 1, ..., 199, which spans two blocks.
+  var expected_long[32] = [
+    191, 176, 170, 151, 134, 62, 121, 121, 67, 207, 124, 51, 187, 126, 136, 11,
+    180, 84, 63, 61, 39, 3, 192, 146, 60, 105, 1, 194, 175, 87, 184, 144
+  ];
+  component k_long = Keccak256(200);
+  for (var i = 0; i < 1600; i++) {
+    k_long.in[i] <== ((i \ 8) >> (i % 8)) & 1;
+  }
+  for (var i = 0; i < 256; i++) {
+    k_long.out[i] === (expected_long[i \ 8] >> (i % 8)) & 1;
+  }
+}
+
+component main = KeccakTest();
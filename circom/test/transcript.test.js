@@ -0,0 +1,91 @@
+const path = require("path");
+const fs = require("fs");
+const assert = require("assert");
+const proof = require("./data/proof.json");
+const vectors = require("./data/transcript_vectors.json");
+
+const wasm_tester = require("circom_tester").wasm;
+
+// The values the Rust verifier observes and squeezes for `proof.json`, written by the `private_tx`
+// example (see `TranscriptLog::to_fixture`).
+const observed = vectors.filter((event) => event.kind === "observe").map((event) => event.value);
+const challenges = vectors.filter((event) => event.kind === "challenge").map((event) => event.value);
+
+const constants = fs.readFileSync(path.join(__dirname, "..", "circuits", "constants.circom")).toString();
+
+function constant(name) {
+    return BigInt(constants.match(new RegExp(`function ${name}\\(\\) { return (\\d+); }`))[1]);
+}
+
+// The elements of the proof in the order the verifier observes them, as in `get_challenges`.
+function expectedObservations() {
+    const caps = (cap) => cap.flat();
+    return [
+        ...caps(proof.wires_cap),
+        ...caps(proof.plonk_zs_partial_products_cap),
+        ...caps(proof.quotient_polys_cap),
+        ...proof.openings_constants.flat(),
+        ...proof.openings_plonk_sigmas.flat(),
+        ...proof.openings_wires.flat(),
+        ...proof.openings_plonk_zs.flat(),
+        ...proof.openings_partial_products.flat(),
+        ...proof.openings_quotient_polys.flat(),
+        ...proof.openings_plonk_zs_next.flat(),
+        ...proof.fri_commit_phase_merkle_caps.map(caps).flat(),
+        ...proof.fri_final_poly_ext_v.flat(),
+        proof.fri_pow_witness,
+    ].map(String);
+}
+
+describe("Transcript Vectors Test", function () {
+    this.timeout(10000000);
+
+    it("Should observe the circuit digest and the proof", () => {
+        const digest = [...constants.matchAll(/cd\[\d] = (\d+);/g)].map((match) => match[1]);
+        assert.deepStrictEqual(observed.slice(0, 4), digest);
+        // `observed[4..8]` is the hash of the public inputs, checked by the circuit test below.
+        assert.deepStrictEqual(observed.slice(8), expectedObservations());
+    });
+
+    it("Should squeeze the same challenges in circom", async () => {
+        const circuit = await wasm_tester(path.join(__dirname, "circuits", "challenges.test.circom"), {});
+        const input = {
+            wires_cap: proof.wires_cap,
+            plonk_zs_partial_products_cap: proof.plonk_zs_partial_products_cap,
+            quotient_polys_cap: proof.quotient_polys_cap,
+
+            openings_constants: proof.openings_constants,
+            openings_plonk_sigmas: proof.openings_plonk_sigmas,
+            openings_wires: proof.openings_wires,
+            openings_plonk_zs: proof.openings_plonk_zs,
+            openings_plonk_zs_next: proof.openings_plonk_zs_next,
+            openings_partial_products: proof.openings_partial_products,
+            openings_quotient_polys: proof.openings_quotient_polys,
+
+            fri_commit_phase_merkle_caps: proof.fri_commit_phase_merkle_caps,
+            fri_final_poly_ext_v: proof.fri_final_poly_ext_v,
+            fri_pow_witness: proof.fri_pow_witness,
+            public_input_hash: observed.slice(4, 8),
+        };
+        const w = await circuit.calculateWitness(input, true);
+
+        // The challenges in the order `get_challenges` squeezes them, with two per Plonk challenge
+        // and two per extension element.
+        let next = 0;
+        const take = (n) => challenges.slice(next, (next += n));
+        const numFriRounds = proof.fri_commit_phase_merkle_caps.length;
+        const ldeSize = 1n << (constant("DEGREE_BITS") + constant("FRI_RATE_BITS"));
+        await circuit.assertOut(w, {
+            plonk_betas: take(2),
+            plonk_gammas: take(2),
+            plonk_alphas: take(2),
+            plonk_zeta: take(2),
+            fri_alpha: take(2),
+            fri_betas: Array.from({length: numFriRounds}, () => take(2)),
+            fri_pow_response: take(1)[0],
+            fri_query_indices: take(Number(constant("NUM_FRI_QUERY_ROUND")))
+                .map((challenge) => (BigInt(challenge) % ldeSize).toString()),
+        });
+        assert.strictEqual(next, challenges.length);
+    });
+});
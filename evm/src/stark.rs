@@ -91,6 +91,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let trace_oracle = FriOracleInfo {
             num_polys: Self::COLUMNS,
             blinding: false,
+            degree_bits: None,
         };
         let trace_info = FriPolynomialInfo::from_range(TRACE_ORACLE_INDEX, 0..Self::COLUMNS);
 
@@ -99,6 +100,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let permutation_ctl_oracle = FriOracleInfo {
             num_polys: num_perutation_ctl_polys,
             blinding: false,
+            degree_bits: None,
         };
         let permutation_ctl_zs_info = FriPolynomialInfo::from_range(
             PERMUTATION_CTL_ORACLE_INDEX,
@@ -114,6 +116,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let quotient_oracle = FriOracleInfo {
             num_polys: num_quotient_polys,
             blinding: false,
+            degree_bits: None,
         };
         let quotient_info =
             FriPolynomialInfo::from_range(QUOTIENT_ORACLE_INDEX, 0..num_quotient_polys);
@@ -154,6 +157,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let trace_oracle = FriOracleInfo {
             num_polys: Self::COLUMNS,
             blinding: false,
+            degree_bits: None,
         };
         let trace_info = FriPolynomialInfo::from_range(TRACE_ORACLE_INDEX, 0..Self::COLUMNS);
 
@@ -162,6 +166,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let permutation_ctl_oracle = FriOracleInfo {
             num_polys: num_perutation_ctl_polys,
             blinding: false,
+            degree_bits: None,
         };
         let permutation_ctl_zs_info = FriPolynomialInfo::from_range(
             PERMUTATION_CTL_ORACLE_INDEX,
@@ -177,6 +182,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let quotient_oracle = FriOracleInfo {
             num_polys: num_quotient_polys,
             blinding: false,
+            degree_bits: None,
         };
         let quotient_info =
             FriPolynomialInfo::from_range(QUOTIENT_ORACLE_INDEX, 0..num_quotient_polys);
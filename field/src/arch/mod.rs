@@ -1,2 +1,2 @@
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(target_arch = "x86_64", not(feature = "forbid-unsafe")))]
 pub mod x86_64;
@@ -0,0 +1,163 @@
+//! Constant-time helpers for code handling secret field elements, such as spend keys.
+//!
+//! With the `constant_time` feature, `GoldilocksField::try_inverse` uses `ct_try_inverse_u64`
+//! instead of the faster, variable-time algorithm in `inversion`, and Goldilocks reduction avoids
+//! its data-dependent branch.
+
+use core::hint::black_box;
+
+use crate::extension::{Extendable, FieldExtension};
+use crate::types::PrimeField64;
+
+/// Returns a mask with all bits set if `choice` is true, and no bits set otherwise. The mask goes
+/// through `black_box` so that the compiler can't turn selects using it back into branches.
+#[inline(always)]
+fn mask(choice: bool) -> u64 {
+    black_box((choice as u64).wrapping_neg())
+}
+
+/// Returns `if_true` if `choice` is true and `if_false` otherwise, without branching on `choice`.
+#[inline]
+pub fn ct_select_u64(choice: bool, if_true: u64, if_false: u64) -> u64 {
+    let mask = mask(choice);
+    (if_true & mask) | (if_false & !mask)
+}
+
+/// Returns `if_true` if `choice` is true and `if_false` otherwise, without branching on `choice`.
+#[inline]
+pub fn ct_select<F: PrimeField64>(choice: bool, if_true: F, if_false: F) -> F {
+    F::from_noncanonical_u64(ct_select_u64(
+        choice,
+        if_true.to_noncanonical_u64(),
+        if_false.to_noncanonical_u64(),
+    ))
+}
+
+/// Returns `if_true` if `choice` is true and `if_false` otherwise, without branching on `choice`.
+pub fn ct_select_extension<F: PrimeField64 + Extendable<D>, const D: usize>(
+    choice: bool,
+    if_true: F::Extension,
+    if_false: F::Extension,
+) -> F::Extension {
+    let if_true = if_true.to_basefield_array();
+    let if_false = if_false.to_basefield_array();
+    F::Extension::from_basefield_array(core::array::from_fn(|i| {
+        ct_select(choice, if_true[i], if_false[i])
+    }))
+}
+
+/// Inverts `x` with the same sequence of field operations for every nonzero input, by computing
+/// `x^(p - 2)`. Whether `x` is zero is revealed, as it is by the return type.
+pub fn ct_try_inverse_u64<F: PrimeField64>(x: &F) -> Option<F> {
+    // The exponent is public, so the square-and-multiply chain of `exp_u64` does not depend on x.
+    let inverse = x.exp_u64(F::ORDER - 2);
+    (!x.is_zero()).then_some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constant_time::{ct_select, ct_select_extension, ct_select_u64, ct_try_inverse_u64};
+    use crate::extension::Extendable;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::inversion::try_inverse_u64;
+    use crate::types::{Field, Sample};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_ct_select() {
+        assert_eq!(ct_select_u64(true, 3, 5), 3);
+        assert_eq!(ct_select_u64(false, 3, 5), 5);
+
+        let (a, b) = (F::rand(), F::rand());
+        assert_eq!(ct_select(true, a, b), a);
+        assert_eq!(ct_select(false, a, b), b);
+
+        type FE = <F as Extendable<2>>::Extension;
+        let (a, b) = (FE::rand(), FE::rand());
+        assert_eq!(ct_select_extension::<F, 2>(true, a, b), a);
+        assert_eq!(ct_select_extension::<F, 2>(false, a, b), b);
+    }
+
+    #[test]
+    fn test_ct_try_inverse() {
+        assert_eq!(ct_try_inverse_u64(&F::ZERO), None);
+        assert_eq!(ct_try_inverse_u64(&F::ONE), Some(F::ONE));
+        for x in F::rand_vec(100) {
+            assert_eq!(ct_try_inverse_u64(&x), try_inverse_u64(&x));
+        }
+    }
+
+    /// A dudect-style leakage test: times inversions of a fixed input against inversions of random
+    /// inputs, and checks with Welch's t-test that the two timing distributions are
+    /// indistinguishable. The fixed input is one, which the variable-time algorithm inverts much
+    /// faster than random inputs.
+    ///
+    /// This measures wall-clock time, so it is ignored by default to keep it off shared CI runners;
+    /// run it with `cargo test --features constant_time -- --ignored` on a quiet machine.
+    #[cfg(feature = "constant_time")]
+    #[test]
+    #[ignore]
+    fn test_inverse_timing_leakage() {
+        use std::time::Instant;
+
+        use rand::rngs::OsRng;
+        use rand::Rng;
+
+        const NUM_SAMPLES: usize = 1 << 16;
+        // dudect reports definite leakage above 10 and treats values below 4.5 as no evidence of
+        // leakage; we leave headroom for noisy machines.
+        const T_THRESHOLD: f64 = 10.0;
+
+        let inputs = (0..NUM_SAMPLES)
+            .map(|_| {
+                let fixed = OsRng.gen::<bool>();
+                (fixed, if fixed { F::ONE } else { F::rand() })
+            })
+            .collect::<Vec<_>>();
+
+        let mut timings = (Vec::new(), Vec::new());
+        for (fixed, x) in inputs {
+            let start = Instant::now();
+            core::hint::black_box(core::hint::black_box(x).try_inverse());
+            let elapsed = start.elapsed().as_nanos() as f64;
+            if fixed {
+                timings.0.push(elapsed);
+            } else {
+                timings.1.push(elapsed);
+            }
+        }
+
+        // As in dudect, discard the slowest measurements, which are dominated by interrupts and
+        // scheduling rather than by the code being measured.
+        let mut all = timings
+            .0
+            .iter()
+            .chain(&timings.1)
+            .copied()
+            .collect::<Vec<_>>();
+        all.sort_by(f64::total_cmp);
+        let cutoff = all[all.len() * 9 / 10];
+        timings.0.retain(|&t| t <= cutoff);
+        timings.1.retain(|&t| t <= cutoff);
+
+        let t = welch_t(&timings.0, &timings.1);
+        assert!(
+            t.abs() < T_THRESHOLD,
+            "Inversion timing depends on the input (t = {t})"
+        );
+    }
+
+    #[cfg(feature = "constant_time")]
+    fn welch_t(xs: &[f64], ys: &[f64]) -> f64 {
+        let mean_var = |v: &[f64]| {
+            let n = v.len() as f64;
+            let mean = v.iter().sum::<f64>() / n;
+            let var = v.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+            (mean, var, n)
+        };
+        let (mean_x, var_x, n_x) = mean_var(xs);
+        let (mean_y, var_y, n_y) = mean_var(ys);
+        (mean_x - mean_y) / (var_x / n_x + var_y / n_y).sqrt()
+    }
+}
@@ -0,0 +1,262 @@
+use core::fmt::{self, Debug, Display, Formatter};
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num::bigint::BigUint;
+use num::traits::Pow;
+use serde::{Deserialize, Serialize};
+
+use crate::extension::{Extendable, FieldExtension, Frobenius, OEF};
+use crate::ops::Square;
+use crate::types::{Field, Sample};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct CubicExtension<F: Extendable<3>>(pub [F; 3]);
+
+impl<F: Extendable<3>> Default for CubicExtension<F> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<F: Extendable<3>> OEF<3> for CubicExtension<F> {
+    const W: F = F::W;
+    const DTH_ROOT: F = F::DTH_ROOT;
+}
+
+impl<F: Extendable<3>> Frobenius<3> for CubicExtension<F> {}
+
+impl<F: Extendable<3>> FieldExtension<3> for CubicExtension<F> {
+    type BaseField = F;
+
+    fn to_basefield_array(&self) -> [F; 3] {
+        self.0
+    }
+
+    fn from_basefield_array(arr: [F; 3]) -> Self {
+        Self(arr)
+    }
+
+    fn from_basefield(x: F) -> Self {
+        x.into()
+    }
+}
+
+impl<F: Extendable<3>> From<F> for CubicExtension<F> {
+    fn from(x: F) -> Self {
+        Self([x, F::ZERO, F::ZERO])
+    }
+}
+
+impl<F: Extendable<3>> Sample for CubicExtension<F> {
+    #[inline]
+    fn sample<R>(rng: &mut R) -> Self
+    where
+        R: rand::RngCore + ?Sized,
+    {
+        Self::from_basefield_array([F::sample(rng), F::sample(rng), F::sample(rng)])
+    }
+}
+
+impl<F: Extendable<3>> Field for CubicExtension<F> {
+    const ZERO: Self = Self([F::ZERO; 3]);
+    const ONE: Self = Self([F::ONE, F::ZERO, F::ZERO]);
+    const TWO: Self = Self([F::TWO, F::ZERO, F::ZERO]);
+    const NEG_ONE: Self = Self([F::NEG_ONE, F::ZERO, F::ZERO]);
+    const MONTGOMERY_INV: Self = todo!();
+
+    // `p^3 - 1 = (p - 1)(p^2 + p + 1)`. The `p - 1` term has a two-adicity of `F::TWO_ADICITY` and
+    // the term `p^2 + p + 1` is odd since it is the sum of an odd number of odd terms. Hence the
+    // two-adicity of `p^3 - 1` is the same as for `p - 1`.
+    const TWO_ADICITY: usize = F::TWO_ADICITY;
+    const CHARACTERISTIC_TWO_ADICITY: usize = F::CHARACTERISTIC_TWO_ADICITY;
+
+    const NONRESIDUE: Self = Self(F::EXT_NONRESIDUE);
+    const MULTIPLICATIVE_GROUP_GENERATOR: Self = Self(F::EXT_MULTIPLICATIVE_GROUP_GENERATOR);
+    const POWER_OF_TWO_GENERATOR: Self = Self(F::EXT_POWER_OF_TWO_GENERATOR);
+
+    const BITS: usize = F::BITS * 3;
+
+    fn order() -> BigUint {
+        F::order().pow(3u32)
+    }
+    fn characteristic() -> BigUint {
+        F::characteristic()
+    }
+
+    fn mul_by_nonresidue(&self) -> Self {
+        todo!()
+    }
+
+    // Algorithm 11.3.4 in Handbook of Elliptic and Hyperelliptic Curve Cryptography.
+    fn try_inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+
+        // Writing 'a' for self:
+        let d = self.frobenius(); // d = a^p
+        let f = d * d.frobenius(); // f = a^(p + p^2)
+
+        // f contains a^(r-1) and a^r is in the base field.
+        debug_assert!(FieldExtension::<3>::is_in_basefield(&(*self * f)));
+
+        // g = a^r is in the base field, so only compute that coefficient rather than the full
+        // product. The equation is extracted from Mul::mul(...) below.
+        let Self([a0, a1, a2]) = *self;
+        let Self([b0, b1, b2]) = f;
+        let g = a0 * b0 + <Self as OEF<3>>::W * (a1 * b2 + a2 * b1);
+
+        Some(FieldExtension::<3>::scalar_mul(&f, g.inverse()))
+    }
+
+    fn from_noncanonical_biguint(n: BigUint) -> Self {
+        F::from_noncanonical_biguint(n).into()
+    }
+
+    fn from_canonical_u64(n: u64) -> Self {
+        F::from_canonical_u64(n).into()
+    }
+
+    fn from_noncanonical_u128(n: u128) -> Self {
+        F::from_noncanonical_u128(n).into()
+    }
+}
+
+impl<F: Extendable<3>> Display for CubicExtension<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {}*a + {}*a^2", self.0[0], self.0[1], self.0[2])
+    }
+}
+
+impl<F: Extendable<3>> Debug for CubicExtension<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<F: Extendable<3>> Neg for CubicExtension<F> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self([-self.0[0], -self.0[1], -self.0[2]])
+    }
+}
+
+impl<F: Extendable<3>> Add for CubicExtension<F> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self([
+            self.0[0] + rhs.0[0],
+            self.0[1] + rhs.0[1],
+            self.0[2] + rhs.0[2],
+        ])
+    }
+}
+
+impl<F: Extendable<3>> AddAssign for CubicExtension<F> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<F: Extendable<3>> Sum for CubicExtension<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl<F: Extendable<3>> Sub for CubicExtension<F> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self([
+            self.0[0] - rhs.0[0],
+            self.0[1] - rhs.0[1],
+            self.0[2] - rhs.0[2],
+        ])
+    }
+}
+
+impl<F: Extendable<3>> SubAssign for CubicExtension<F> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<F: Extendable<3>> Mul for CubicExtension<F> {
+    type Output = Self;
+
+    #[inline]
+    default fn mul(self, rhs: Self) -> Self {
+        let Self([a0, a1, a2]) = self;
+        let Self([b0, b1, b2]) = rhs;
+        let w = <Self as OEF<3>>::W;
+
+        let c0 = a0 * b0 + w * (a1 * b2 + a2 * b1);
+        let c1 = a0 * b1 + a1 * b0 + w * a2 * b2;
+        let c2 = a0 * b2 + a1 * b1 + a2 * b0;
+
+        Self([c0, c1, c2])
+    }
+}
+
+impl<F: Extendable<3>> MulAssign for CubicExtension<F> {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<F: Extendable<3>> Square for CubicExtension<F> {
+    #[inline(always)]
+    fn square(&self) -> Self {
+        let Self([a0, a1, a2]) = *self;
+        let w = <Self as OEF<3>>::W;
+
+        let c0 = a0.square() + w * (a1 * a2).double();
+        let c1 = (a0 * a1).double() + w * a2.square();
+        let c2 = a1.square() + (a0 * a2).double();
+
+        Self([c0, c1, c2])
+    }
+}
+
+impl<F: Extendable<3>> Product for CubicExtension<F> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |acc, x| acc * x)
+    }
+}
+
+impl<F: Extendable<3>> Div for CubicExtension<F> {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl<F: Extendable<3>> DivAssign for CubicExtension<F> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod goldilocks {
+        use crate::{test_field_arithmetic, test_field_extension};
+
+        test_field_extension!(crate::goldilocks_field::GoldilocksField, 3);
+        test_field_arithmetic!(
+            crate::extension::cubic::CubicExtension<crate::goldilocks_field::GoldilocksField>
+        );
+    }
+}
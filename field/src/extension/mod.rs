@@ -3,6 +3,7 @@ use alloc::vec::Vec;
 use crate::types::Field;
 
 pub mod algebra;
+pub mod cubic;
 pub mod quadratic;
 pub mod quartic;
 pub mod quintic;
@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cmp::{max, min};
 
@@ -9,6 +10,8 @@ use crate::packed::PackedField;
 use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::types::Field;
 
+/// Roots of unity used by the FFT. Row `i` of the table for size `2^lg_n` only depends on `i`, so
+/// the table for a size can be used for any smaller size too.
 pub type FftRootTable<F> = Vec<Vec<F>>;
 
 pub fn fft_root_table<F: Field>(n: usize) -> FftRootTable<F> {
@@ -48,6 +51,16 @@ fn fft_dispatch<F: Field>(
     fft_classic(input, zero_factor.unwrap_or(0), used_root_table);
 }
 
+/// Like `fft_with_options`, but transforms `values` in place.
+#[inline]
+pub fn fft_in_place<F: Field>(
+    values: &mut [F],
+    zero_factor: Option<usize>,
+    root_table: Option<&FftRootTable<F>>,
+) {
+    fft_dispatch(values, zero_factor, root_table);
+}
+
 #[inline]
 pub fn fft<F: Field>(poly: PolynomialCoeffs<F>) -> PolynomialValues<F> {
     fft_with_options(poly, None, None)
@@ -94,13 +107,108 @@ pub fn ifft_with_options<F: Field>(
     PolynomialCoeffs { coeffs: buffer }
 }
 
+/// Caches FFT root tables across transforms, so that repeated FFTs of the same sizes, e.g. when
+/// proving the same circuit many times, don't recompute their twiddle factors. At most `capacity`
+/// tables are kept; the least recently used one is evicted when a new size is requested.
+///
+/// Bit reversal uses a fixed lookup table shared by all sizes, so there is nothing to cache for it.
+#[derive(Clone, Debug)]
+pub struct FftPlanner<F: Field> {
+    capacity: usize,
+    /// Cached tables with their `lg_n`, least recently used first.
+    tables: Vec<(usize, Arc<FftRootTable<F>>)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<F: Field> Default for FftPlanner<F> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl<F: Field> FftPlanner<F> {
+    pub const DEFAULT_CAPACITY: usize = 8;
+
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "An FFT planner must be able to hold a table");
+        Self {
+            capacity,
+            tables: Vec::with_capacity(capacity),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the root table for FFTs of size `n`, computing it if it isn't cached.
+    pub fn root_table(&mut self, n: usize) -> Arc<FftRootTable<F>> {
+        let lg_n = log2_strict(n);
+        if let Some(i) = self.tables.iter().position(|(lg, _)| *lg == lg_n) {
+            self.hits += 1;
+            let entry = self.tables.remove(i);
+            let table = entry.1.clone();
+            self.tables.push(entry);
+            return table;
+        }
+
+        self.misses += 1;
+        if self.tables.len() == self.capacity {
+            self.tables.remove(0);
+        }
+        let table = Arc::new(fft_root_table(n));
+        self.tables.push((lg_n, table.clone()));
+        table
+    }
+
+    pub fn fft(&mut self, poly: PolynomialCoeffs<F>) -> PolynomialValues<F> {
+        self.fft_with_options(poly, None)
+    }
+
+    pub fn fft_with_options(
+        &mut self,
+        poly: PolynomialCoeffs<F>,
+        zero_factor: Option<usize>,
+    ) -> PolynomialValues<F> {
+        let root_table = self.root_table(poly.len());
+        fft_with_options(poly, zero_factor, Some(&root_table))
+    }
+
+    pub fn ifft(&mut self, poly: PolynomialValues<F>) -> PolynomialCoeffs<F> {
+        let root_table = self.root_table(poly.len());
+        ifft_with_options(poly, None, Some(&root_table))
+    }
+
+    /// The sizes, as `lg_n`, of the cached tables, least recently used first.
+    pub fn cached_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tables.iter().map(|(lg_n, _)| *lg_n)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of requested tables that were already cached.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of requested tables that had to be computed.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn clear(&mut self) {
+        self.tables.clear();
+    }
+}
+
 /// Generic FFT implementation that works with both scalar and packed inputs.
 #[unroll_for_loops]
 fn fft_classic_simd<P: PackedField>(
     values: &mut [P::Scalar],
     r: usize,
     lg_n: usize,
-    root_table: &FftRootTable<P::Scalar>,
+    root_table: &[Vec<P::Scalar>],
 ) {
     let lg_packed_width = log2_strict(P::WIDTH); // 0 when P is a scalar.
     let packed_values = P::pack_slice_mut(values);
@@ -166,19 +274,22 @@ fn fft_classic_simd<P: PackedField>(
 /// The parameter r signifies that the first 1/2^r of the entries of
 /// input may be non-zero, but the last 1 - 1/2^r entries are
 /// definitely zero.
+///
+/// `root_table` may be the table of a larger FFT, in which case only its first `lg_n` rows are used.
 pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &FftRootTable<F>) {
     reverse_index_bits_in_place(values);
 
     let n = values.len();
     let lg_n = log2_strict(n);
 
-    if root_table.len() != lg_n {
+    if root_table.len() < lg_n {
         panic!(
-            "Expected root table of length {}, but it was {}.",
+            "Expected root table of length at least {}, but it was {}.",
             lg_n,
             root_table.len()
         );
     }
+    let root_table = &root_table[..lg_n];
 
     // After reverse_index_bits, the only non-zero elements of values
     // are at indices i*2^r for i = 0..n/2^r.  The loop below copies
@@ -211,10 +322,10 @@ mod tests {
 
     use plonky2_util::{log2_ceil, log2_strict};
 
-    use crate::fft::{fft, fft_with_options, ifft};
+    use crate::fft::{fft, fft_root_table, fft_with_options, ifft, FftPlanner};
     use crate::goldilocks_field::GoldilocksField;
     use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
-    use crate::types::Field;
+    use crate::types::{Field, Sample};
 
     #[test]
     fn fft_and_ifft() {
@@ -252,6 +363,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fft_planner() {
+        type F = GoldilocksField;
+        let mut planner = FftPlanner::<F>::new(2);
+
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(16));
+        let values = planner.fft(coeffs.clone());
+        assert_eq!(values, fft(coeffs.clone()));
+        assert_eq!(planner.ifft(values), coeffs);
+        assert_eq!((planner.hits(), planner.misses()), (1, 1));
+
+        // Using size 8 then 16 makes size 8 the least recently used table, so it is evicted.
+        planner.root_table(8);
+        planner.root_table(16);
+        planner.root_table(32);
+        assert_eq!(planner.cached_sizes().collect::<Vec<_>>(), [4, 5]);
+        assert_eq!((planner.hits(), planner.misses()), (2, 3));
+    }
+
+    #[test]
+    fn fft_with_larger_root_table() {
+        type F = GoldilocksField;
+        let root_table = fft_root_table(64);
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(16));
+        assert_eq!(
+            fft_with_options(coeffs.clone(), None, Some(&root_table)),
+            fft(coeffs)
+        );
+    }
+
     fn evaluate_naive<F: Field>(coefficients: &PolynomialCoeffs<F>) -> PolynomialValues<F> {
         let degree = coefficients.len();
         let degree_padded = 1 << log2_ceil(degree);
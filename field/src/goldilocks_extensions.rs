@@ -1,12 +1,17 @@
+#[cfg(not(feature = "u32_arithmetic"))]
 use core::ops::Mul;
 
+#[cfg(not(feature = "u32_arithmetic"))]
 use static_assertions::const_assert;
 
+use crate::extension::cubic::CubicExtension;
 use crate::extension::quadratic::QuadraticExtension;
 use crate::extension::quartic::QuarticExtension;
 use crate::extension::quintic::QuinticExtension;
 use crate::extension::{Extendable, Frobenius};
-use crate::goldilocks_field::{reduce160, GoldilocksField};
+#[cfg(not(feature = "u32_arithmetic"))]
+use crate::goldilocks_field::reduce160;
+use crate::goldilocks_field::GoldilocksField;
 use crate::types::Field;
 
 impl Frobenius<1> for GoldilocksField {}
@@ -29,6 +34,7 @@ impl Extendable<2> for GoldilocksField {
     const EXT_POWER_OF_TWO_GENERATOR: [Self; 2] = [Self(0), Self(15659105665374529263)];
 }
 
+#[cfg(not(feature = "u32_arithmetic"))]
 impl Mul for QuadraticExtension<GoldilocksField> {
     #[inline]
     fn mul(self, rhs: Self) -> Self {
@@ -39,6 +45,27 @@ impl Mul for QuadraticExtension<GoldilocksField> {
     }
 }
 
+impl Extendable<3> for GoldilocksField {
+    type Extension = CubicExtension<Self>;
+
+    // Verifiable in Sage with
+    // `R.<x> = GF(p)[]; assert (x^3 - 2).is_irreducible()`.
+    const W: Self = Self(2);
+
+    // DTH_ROOT = W^((ORDER - 1)/3)
+    const DTH_ROOT: Self = Self(4294967295);
+
+    const EXT_NONRESIDUE: [Self; 3] = todo!();
+
+    const EXT_MULTIPLICATIVE_GROUP_GENERATOR: [Self; 3] = [
+        Self(7391191192304426301),
+        Self(5780268447436543855),
+        Self(176609343193545520),
+    ];
+
+    const EXT_POWER_OF_TWO_GENERATOR: [Self; 3] = [Self::POWER_OF_TWO_GENERATOR, Self(0), Self(0)];
+}
+
 impl Extendable<4> for GoldilocksField {
     type Extension = QuarticExtension<Self>;
 
@@ -60,6 +87,7 @@ impl Extendable<4> for GoldilocksField {
         [Self(0), Self(0), Self(0), Self(12587610116473453104)];
 }
 
+#[cfg(not(feature = "u32_arithmetic"))]
 impl Mul for QuarticExtension<GoldilocksField> {
     #[inline]
     fn mul(self, rhs: Self) -> Self {
@@ -97,6 +125,7 @@ impl Extendable<5> for GoldilocksField {
     ];
 }
 
+#[cfg(not(feature = "u32_arithmetic"))]
 impl Mul for QuinticExtension<GoldilocksField> {
     #[inline]
     fn mul(self, rhs: Self) -> Self {
@@ -111,6 +140,9 @@ impl Mul for QuinticExtension<GoldilocksField> {
 }
 
 /*
+ * With the `u32_arithmetic` feature, the generic multiplications are used instead of the ones
+ * below, which accumulate products in u128s.
+ *
  * The functions extD_add_prods[0-4] are helper functions for
  * computing products for extensions of degree D over the Goldilocks
  * field. They are faster than the generic method because all
@@ -120,6 +152,7 @@ impl Mul for QuinticExtension<GoldilocksField> {
 
 /// Return `a`, `b` such that `a + b*2^128 = 3*(x + y*2^128)` with `a < 2^128` and `b < 2^32`.
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn u160_times_3(x: u128, y: u32) -> (u128, u32) {
     let (s, cy) = x.overflowing_add(x << 1);
     (s, 3 * y + (x >> 127) as u32 + cy as u32)
@@ -127,6 +160,7 @@ fn u160_times_3(x: u128, y: u32) -> (u128, u32) {
 
 /// Return `a`, `b` such that `a + b*2^128 = 7*(x + y*2^128)` with `a < 2^128` and `b < 2^32`.
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn u160_times_7(x: u128, y: u32) -> (u128, u32) {
     let (d, br) = (x << 3).overflowing_sub(x);
     // NB: subtracting the borrow can't underflow
@@ -138,6 +172,7 @@ fn u160_times_7(x: u128, y: u32) -> (u128, u32) {
  */
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext2_add_prods0(a: &[u64; 2], b: &[u64; 2]) -> GoldilocksField {
     // Computes a0 * b0 + W * a1 * b1;
     let [a0, a1] = *a;
@@ -152,10 +187,11 @@ fn ext2_add_prods0(a: &[u64; 2], b: &[u64; 2]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a0 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext2_add_prods1(a: &[u64; 2], b: &[u64; 2]) -> GoldilocksField {
     // Computes a0 * b1 + a1 * b0;
     let [a0, a1] = *a;
@@ -170,11 +206,12 @@ fn ext2_add_prods1(a: &[u64; 2], b: &[u64; 2]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a1 as u128) * (b0 as u128));
     let cumul_hi = cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 /// Multiply a and b considered as elements of GF(p^2).
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 pub(crate) fn ext2_mul(a: [u64; 2], b: [u64; 2]) -> [GoldilocksField; 2] {
     // The code in ext2_add_prods[01] assumes the quadratic extension
     // generator is 7.
@@ -190,6 +227,7 @@ pub(crate) fn ext2_mul(a: [u64; 2], b: [u64; 2]) -> [GoldilocksField; 2] {
  */
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext4_add_prods0(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     // Computes c0 = a0 * b0 + W * (a1 * b3 + a2 * b2 + a3 * b1)
 
@@ -216,10 +254,11 @@ fn ext4_add_prods0(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a0 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext4_add_prods1(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     // Computes c1 = a0 * b1 + a1 * b0 + W * (a2 * b3 + a3 * b2);
 
@@ -246,10 +285,11 @@ fn ext4_add_prods1(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a1 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext4_add_prods2(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     // Computes c2 = a0 * b2 + a1 * b1 + a2 * b0 + W * a3 * b3;
 
@@ -273,10 +313,11 @@ fn ext4_add_prods2(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a2 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext4_add_prods3(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     // Computes c3 = a0 * b3 + a1 * b2 + a2 * b1 + a3 * b0;
 
@@ -300,11 +341,12 @@ fn ext4_add_prods3(a: &[u64; 4], b: &[u64; 4]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a3 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 /// Multiply a and b considered as elements of GF(p^4).
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 pub(crate) fn ext4_mul(a: [u64; 4], b: [u64; 4]) -> [GoldilocksField; 4] {
     // The code in ext4_add_prods[0-3] assumes the quartic extension
     // generator is 7.
@@ -322,6 +364,7 @@ pub(crate) fn ext4_mul(a: [u64; 4], b: [u64; 4]) -> [GoldilocksField; 4] {
  */
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext5_add_prods0(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     // Computes c0 = a0 * b0 + W * (a1 * b4 + a2 * b3 + a3 * b2 + a4 * b1)
 
@@ -352,10 +395,11 @@ fn ext5_add_prods0(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a0 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext5_add_prods1(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     // Computes c1 = a0 * b1 + a1 * b0 + W * (a2 * b4 + a3 * b3 + a4 * b2);
 
@@ -386,10 +430,11 @@ fn ext5_add_prods1(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a1 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext5_add_prods2(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     // Computes c2 = a0 * b2 + a1 * b1 + a2 * b0 + W * (a3 * b4 + a4 * b3);
 
@@ -420,10 +465,11 @@ fn ext5_add_prods2(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a2 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext5_add_prods3(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     // Computes c3 = a0 * b3 + a1 * b2 + a2 * b1 + a3 * b0 + W * a4 * b4;
 
@@ -451,10 +497,11 @@ fn ext5_add_prods3(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a3 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 fn ext5_add_prods4(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     // Computes c4 = a0 * b4 + a1 * b3 + a2 * b2 + a3 * b1 + a4 * b0;
 
@@ -482,11 +529,12 @@ fn ext5_add_prods4(a: &[u64; 5], b: &[u64; 5]) -> GoldilocksField {
     (cumul_lo, cy) = cumul_lo.overflowing_add((a4 as u128) * (b0 as u128));
     cumul_hi += cy as u32;
 
-    unsafe { reduce160(cumul_lo, cumul_hi) }
+    reduce160(cumul_lo, cumul_hi)
 }
 
 /// Multiply a and b considered as elements of GF(p^5).
 #[inline(always)]
+#[cfg(not(feature = "u32_arithmetic"))]
 pub(crate) fn ext5_mul(a: [u64; 5], b: [u64; 5]) -> [GoldilocksField; 5] {
     // The code in ext5_add_prods[0-4] assumes the quintic extension
     // generator is 3.
@@ -499,3 +547,46 @@ pub(crate) fn ext5_mul(a: [u64; 5], b: [u64; 5]) -> [GoldilocksField; 5] {
     let c4 = ext5_add_prods4(&a, &b);
     [c0, c1, c2, c3, c4]
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::extension::Extendable;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::types::{Field, Field64};
+
+    /// Checks that `X^D - W` is irreducible, using Capelli's criterion: `W` isn't a `q`th power
+    /// for any prime `q` dividing `D`, and isn't in `-4 F^4` if `4` divides `D`. Also checks that
+    /// `DTH_ROOT` is `W^((p - 1)/D)`.
+    fn test_irreducible<const D: usize>(prime_divisors: &[u64])
+    where
+        GoldilocksField: Extendable<D>,
+    {
+        type F = GoldilocksField;
+        let w = <F as Extendable<D>>::W;
+        let p_minus_1 = F::ORDER - 1;
+        for &q in prime_divisors {
+            assert_eq!(p_minus_1 % q, 0);
+            assert_ne!(w.exp_u64(p_minus_1 / q), F::ONE, "W is a {}th power", q);
+        }
+        if D % 4 == 0 {
+            let minus_w_over_4 = -w / F::from_canonical_u64(4);
+            assert_ne!(
+                minus_w_over_4.exp_u64(p_minus_1 / 4),
+                F::ONE,
+                "W is in -4 F^4"
+            );
+        }
+        assert_eq!(
+            <F as Extendable<D>>::DTH_ROOT,
+            w.exp_u64(p_minus_1 / D as u64)
+        );
+    }
+
+    #[test]
+    fn test_irreducible_polynomials() {
+        test_irreducible::<2>(&[2]);
+        test_irreducible::<3>(&[3]);
+        test_irreducible::<4>(&[2]);
+        test_irreducible::<5>(&[5]);
+    }
+}
@@ -7,6 +7,7 @@ use num::{BigUint, Integer};
 use plonky2_util::{assume, branch_hint};
 use serde::{Deserialize, Serialize};
 
+use crate::constant_time::ct_try_inverse_u64;
 use crate::inversion::try_inverse_u64;
 use crate::types::{Field, Field64, PrimeField, PrimeField64, Sample};
 
@@ -104,7 +105,11 @@ impl Field for GoldilocksField {
 
     #[inline(always)]
     fn try_inverse(&self) -> Option<Self> {
-        try_inverse_u64(self)
+        if cfg!(feature = "constant_time") {
+            ct_try_inverse_u64(self)
+        } else {
+            try_inverse_u64(self)
+        }
     }
 
     fn from_noncanonical_biguint(n: BigUint) -> Self {
@@ -123,8 +128,7 @@ impl Field for GoldilocksField {
 
     #[inline]
     fn multiply_accumulate(&self, x: Self, y: Self) -> Self {
-        // u64 + u64 * u64 cannot overflow.
-        reduce128((self.0 as u128) + (x.0 as u128) * (y.0 as u128))
+        mul_add(x.0, y.0, self.0)
     }
 }
 
@@ -155,20 +159,44 @@ impl Field64 for GoldilocksField {
     }
 
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     unsafe fn add_canonical_u64(&self, rhs: u64) -> Self {
-        let (res_wrapped, carry) = self.0.overflowing_add(rhs);
-        // Add EPSILON * carry cannot overflow unless rhs is not in canonical form.
-        Self(res_wrapped + EPSILON * (carry as u64))
+        add_canonical_u64(*self, rhs)
+    }
+
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    fn add_canonical_u64(&self, rhs: u64) -> Self {
+        add_canonical_u64(*self, rhs)
     }
 
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     unsafe fn sub_canonical_u64(&self, rhs: u64) -> Self {
-        let (res_wrapped, borrow) = self.0.overflowing_sub(rhs);
-        // Sub EPSILON * carry cannot underflow unless rhs is not in canonical form.
-        Self(res_wrapped - EPSILON * (borrow as u64))
+        sub_canonical_u64(*self, rhs)
+    }
+
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    fn sub_canonical_u64(&self, rhs: u64) -> Self {
+        sub_canonical_u64(*self, rhs)
     }
 }
 
+#[inline]
+fn add_canonical_u64(x: GoldilocksField, rhs: u64) -> GoldilocksField {
+    let (res_wrapped, carry) = x.0.overflowing_add(rhs);
+    // Add EPSILON * carry cannot overflow unless rhs is not in canonical form.
+    GoldilocksField(res_wrapped + EPSILON * (carry as u64))
+}
+
+#[inline]
+fn sub_canonical_u64(x: GoldilocksField, rhs: u64) -> GoldilocksField {
+    let (res_wrapped, borrow) = x.0.overflowing_sub(rhs);
+    // Sub EPSILON * carry cannot underflow unless rhs is not in canonical form.
+    GoldilocksField(res_wrapped - EPSILON * (borrow as u64))
+}
+
 impl PrimeField64 for GoldilocksField {
     #[inline]
     fn to_canonical_u64(&self) -> u64 {
@@ -272,7 +300,7 @@ impl Mul for GoldilocksField {
 
     #[inline]
     fn mul(self, rhs: Self) -> Self {
-        reduce128((self.0 as u128) * (rhs.0 as u128))
+        mul_add(self.0, rhs.0, 0)
     }
 }
 
@@ -305,30 +333,33 @@ impl DivAssign for GoldilocksField {
 }
 
 /// Fast addition modulo ORDER for x86-64.
-/// This function is marked unsafe for the following reasons:
+/// This function must be used with care, for the following reasons:
 ///   - It is only correct if x + y < 2**64 + ORDER = 0x1ffffffff00000001.
 ///   - It is only faster in some circumstances. In particular, on x86 it overwrites both inputs in
 ///     the registers, so its use is not recommended when either input will be used again.
 #[inline(always)]
-#[cfg(target_arch = "x86_64")]
-unsafe fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
+#[cfg(all(target_arch = "x86_64", not(feature = "forbid-unsafe")))]
+fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
     let res_wrapped: u64;
     let adjustment: u64;
-    core::arch::asm!(
-        "add {0}, {1}",
-        // Trick. The carry flag is set iff the addition overflowed.
-        // sbb x, y does x := x - y - CF. In our case, x and y are both {1:e}, so it simply does
-        // {1:e} := 0xffffffff on overflow and {1:e} := 0 otherwise. {1:e} is the low 32 bits of
-        // {1}; the high 32-bits are zeroed on write. In the end, we end up with 0xffffffff in {1}
-        // on overflow; this happens be EPSILON.
-        // Note that the CPU does not realize that the result of sbb x, x does not actually depend
-        // on x. We must write the result to a register that we know to be ready. We have a
-        // dependency on {1} anyway, so let's use it.
-        "sbb {1:e}, {1:e}",
-        inlateout(reg) x => res_wrapped,
-        inlateout(reg) y => adjustment,
-        options(pure, nomem, nostack),
-    );
+    // SAFETY: the assembly only reads and writes the two registers it is given.
+    unsafe {
+        core::arch::asm!(
+            "add {0}, {1}",
+            // Trick. The carry flag is set iff the addition overflowed.
+            // sbb x, y does x := x - y - CF. In our case, x and y are both {1:e}, so it simply
+            // does {1:e} := 0xffffffff on overflow and {1:e} := 0 otherwise. {1:e} is the low 32
+            // bits of {1}; the high 32-bits are zeroed on write. In the end, we end up with
+            // 0xffffffff in {1} on overflow; this happens be EPSILON.
+            // Note that the CPU does not realize that the result of sbb x, x does not actually
+            // depend on x. We must write the result to a register that we know to be ready. We
+            // have a dependency on {1} anyway, so let's use it.
+            "sbb {1:e}, {1:e}",
+            inlateout(reg) x => res_wrapped,
+            inlateout(reg) y => adjustment,
+            options(pure, nomem, nostack),
+        );
+    }
     assume(x != 0 || (res_wrapped == y && adjustment == 0));
     assume(y != 0 || (res_wrapped == x && adjustment == 0));
     // Add EPSILON == subtract ORDER.
@@ -337,8 +368,8 @@ unsafe fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
 }
 
 #[inline(always)]
-#[cfg(not(target_arch = "x86_64"))]
-unsafe fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
+#[cfg(any(not(target_arch = "x86_64"), feature = "forbid-unsafe"))]
+fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
     let (res_wrapped, carry) = x.overflowing_add(y);
     // Below cannot overflow unless the assumption if x + y < 2**64 + ORDER is incorrect.
     res_wrapped + EPSILON * (carry as u64)
@@ -349,16 +380,25 @@ unsafe fn add_no_canonicalize_trashing_input(x: u64, y: u64) -> u64 {
 #[inline]
 fn reduce128(x: u128) -> GoldilocksField {
     let (x_lo, x_hi) = split(x); // This is a no-op
+    reduce_wide(x_lo, x_hi)
+}
+
+/// Like `reduce128`, for the value `x_lo + x_hi * 2^64`.
+#[inline]
+fn reduce_wide(x_lo: u64, x_hi: u64) -> GoldilocksField {
     let x_hi_hi = x_hi >> 32;
     let x_hi_lo = x_hi & EPSILON;
 
     let (mut t0, borrow) = x_lo.overflowing_sub(x_hi_hi);
-    if borrow {
+    if cfg!(feature = "constant_time") {
+        // Don't branch on the (secret) borrow.
+        t0 -= EPSILON * (borrow as u64); // Cannot underflow.
+    } else if borrow {
         branch_hint(); // A borrow is exceedingly rare. It is faster to branch.
         t0 -= EPSILON; // Cannot underflow.
     }
     let t1 = x_hi_lo * EPSILON;
-    let t2 = unsafe { add_no_canonicalize_trashing_input(t0, t1) };
+    let t2 = add_no_canonicalize_trashing_input(t0, t1);
     GoldilocksField(t2)
 }
 
@@ -367,14 +407,53 @@ fn split(x: u128) -> (u64, u64) {
     (x as u64, (x >> 64) as u64)
 }
 
+/// Reduces `x * y + z`, which can't overflow 128 bits.
+#[inline]
+#[cfg(not(feature = "u32_arithmetic"))]
+fn mul_add(x: u64, y: u64, z: u64) -> GoldilocksField {
+    reduce128((x as u128) * (y as u128) + (z as u128))
+}
+
+/// Reduces `x * y + z`, without `u128` arithmetic.
+#[inline]
+#[cfg(feature = "u32_arithmetic")]
+fn mul_add(x: u64, y: u64, z: u64) -> GoldilocksField {
+    let (lo, hi) = mul_u64_wide(x, y);
+    let (lo, carry) = lo.overflowing_add(z);
+    reduce_wide(lo, hi + carry as u64)
+}
+
+/// The product of `x` and `y`, as its low and high 64 bits, computed with 32-bit multiplications
+/// only. On targets without a 64-bit multiplier, such as wasm32, this is much faster than a `u128`
+/// product.
+#[inline]
+#[cfg(any(feature = "u32_arithmetic", test))]
+fn mul_u64_wide(x: u64, y: u64) -> (u64, u64) {
+    const MASK: u64 = (1 << 32) - 1;
+    let (x_lo, x_hi) = (x & MASK, x >> 32);
+    let (y_lo, y_hi) = (y & MASK, y >> 32);
+
+    let lo_lo = x_lo * y_lo;
+    let lo_hi = x_lo * y_hi;
+    let hi_lo = x_hi * y_lo;
+    let hi_hi = x_hi * y_hi;
+
+    // Less than 3 * 2^32, so it can't overflow.
+    let mid = (lo_lo >> 32) + (lo_hi & MASK) + (hi_lo & MASK);
+    let lo = (lo_lo & MASK) | (mid << 32);
+    let hi = hi_hi + (lo_hi >> 32) + (hi_lo >> 32) + (mid >> 32);
+    (lo, hi)
+}
+
 /// Reduce the value x_lo + x_hi * 2^128 to an element in the
 /// Goldilocks field.
 ///
-/// This function is marked 'unsafe' because correctness relies on the
-/// unchecked assumption that x < 2^160 - 2^128 + 2^96. Further,
+/// Correctness relies on the unchecked assumption that
+/// x < 2^160 - 2^128 + 2^96. Further,
 /// performance may degrade as x_hi increases beyond 2**40 or so.
 #[inline(always)]
-pub(crate) unsafe fn reduce160(x_lo: u128, x_hi: u32) -> GoldilocksField {
+#[cfg(not(feature = "u32_arithmetic"))]
+pub(crate) fn reduce160(x_lo: u128, x_hi: u32) -> GoldilocksField {
     let x_hi = (x_lo >> 96) as u64 + ((x_hi as u64) << 32); // shld to form x_hi
     let x_mid = (x_lo >> 64) as u32; // shr to form x_mid
     let x_lo = x_lo as u64;
@@ -398,8 +477,29 @@ pub(crate) unsafe fn reduce160(x_lo: u128, x_hi: u32) -> GoldilocksField {
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::goldilocks_field::{mul_add, mul_u64_wide, reduce128, reduce_wide, split};
     use crate::{test_field_arithmetic, test_prime_field_arithmetic};
 
     test_prime_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
     test_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
+
+    #[test]
+    fn test_u32_arithmetic() {
+        let mut rng = OsRng;
+        let edge_cases = [0, 1, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX];
+        let random = (0..1000).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+        for &x in edge_cases.iter().chain(&random) {
+            for &y in edge_cases.iter().chain(&random[..10]) {
+                let product = (x as u128) * (y as u128);
+                assert_eq!(mul_u64_wide(x, y), split(product));
+                let (lo, hi) = split(product);
+                assert_eq!(reduce_wide(lo, hi).0, reduce128(product).0);
+                let z = x ^ y;
+                assert_eq!(mul_add(x, y, z).0, reduce128(product + z as u128).0);
+            }
+        }
+    }
 }
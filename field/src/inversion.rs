@@ -34,7 +34,7 @@ fn safe_iteration(f: &mut u64, g: &mut u64, c: &mut i128, d: &mut i128, k: &mut
 /// algorithm. It is unsafe in the sense that it might produce the
 /// wrong answer if f + g >= 2^64.
 #[inline(always)]
-unsafe fn unsafe_iteration(f: &mut u64, g: &mut u64, c: &mut i128, d: &mut i128, k: &mut u32) {
+fn unsafe_iteration(f: &mut u64, g: &mut u64, c: &mut i128, d: &mut i128, k: &mut u32) {
     if *f < *g {
         core::mem::swap(f, g);
         core::mem::swap(c, d);
@@ -108,9 +108,7 @@ pub(crate) fn try_inverse_u64<F: PrimeField64>(x: &F) -> Option<F> {
 
     // Remaining iterations:
     while f != 1 {
-        unsafe {
-            unsafe_iteration(&mut f, &mut g, &mut c, &mut d, &mut k);
-        }
+        unsafe_iteration(&mut f, &mut g, &mut c, &mut d, &mut k);
     }
 
     // The following two loops adjust c so it's in the canonical range
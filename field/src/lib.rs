@@ -9,6 +9,7 @@
 #![feature(stdsimd)]
 #![feature(specialization)]
 #![cfg_attr(not(test), no_std)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
 
 extern crate alloc;
 
@@ -17,6 +18,7 @@ mod inversion;
 pub(crate) mod arch;
 
 pub mod batch_util;
+pub mod constant_time;
 pub mod cosets;
 pub mod extension;
 pub mod fft;
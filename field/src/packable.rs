@@ -3,7 +3,8 @@ use crate::types::Field;
 
 /// Points us to the default packing for a particular field. There may me multiple choices of
 /// PackedField for a particular Field (e.g. every Field is also a PackedField), but this is the
-/// recommended one. The recommended packing varies by target_arch and target_feature.
+/// recommended one. The recommended packing varies by target_arch and target_feature. With the
+/// `forbid-unsafe` feature, it is always the field itself.
 pub trait Packable: Field {
     type Packing: PackedField<Scalar = Self>;
 }
@@ -14,6 +15,7 @@ impl<F: Field> Packable for F {
 
 #[cfg(all(
     target_arch = "x86_64",
+    not(feature = "forbid-unsafe"),
     target_feature = "avx2",
     not(all(
         target_feature = "avx512bw",
@@ -29,6 +31,7 @@ impl Packable for crate::goldilocks_field::GoldilocksField {
 
 #[cfg(all(
     target_arch = "x86_64",
+    not(feature = "forbid-unsafe"),
     target_feature = "avx512bw",
     target_feature = "avx512cd",
     target_feature = "avx512dq",
@@ -12,7 +12,7 @@ use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
 use crate::extension::{Extendable, FieldExtension};
-use crate::fft::{fft, fft_with_options, ifft, FftRootTable};
+use crate::fft::{fft, fft_in_place, fft_with_options, ifft, ifft_with_options, FftRootTable};
 use crate::types::Field;
 
 /// A polynomial in point-value form.
@@ -59,9 +59,21 @@ impl<F: Field> PolynomialValues<F> {
         ifft(self)
     }
 
+    pub fn ifft_with_options(self, root_table: Option<&FftRootTable<F>>) -> PolynomialCoeffs<F> {
+        ifft_with_options(self, None, root_table)
+    }
+
     /// Returns the polynomial whose evaluation on the coset `shift*H` is `self`.
     pub fn coset_ifft(self, shift: F) -> PolynomialCoeffs<F> {
-        let mut shifted_coeffs = self.ifft();
+        self.coset_ifft_with_options(shift, None)
+    }
+
+    pub fn coset_ifft_with_options(
+        self,
+        shift: F,
+        root_table: Option<&FftRootTable<F>>,
+    ) -> PolynomialCoeffs<F> {
+        let mut shifted_coeffs = self.ifft_with_options(root_table);
         shifted_coeffs
             .coeffs
             .iter_mut()
@@ -294,6 +306,25 @@ impl<F: Field> PolynomialCoeffs<F> {
         modified_poly.fft_with_options(zero_factor, root_table)
     }
 
+    /// Low-degree extends `self` by a factor of `2^rate_bits` and evaluates it on the coset
+    /// `shift*H`, like `self.lde(rate_bits).coset_fft(shift)`, but writes the values into `out`
+    /// instead of allocating.
+    pub fn coset_lde_into(
+        &self,
+        shift: F,
+        rate_bits: usize,
+        root_table: Option<&FftRootTable<F>>,
+        out: &mut [F],
+    ) {
+        assert_eq!(out.len(), self.len() << rate_bits);
+        let (shifted, padding) = out.split_at_mut(self.len());
+        for ((o, r), &c) in shifted.iter_mut().zip(shift.powers()).zip(&self.coeffs) {
+            *o = r * c;
+        }
+        padding.fill(F::ZERO);
+        fft_in_place(out, Some(rate_bits), root_table);
+    }
+
     pub fn to_extension<const D: usize>(&self) -> PolynomialCoeffs<F::Extension>
     where
         F: Extendable<D>,
@@ -496,6 +527,19 @@ mod tests {
         assert_eq!(poly, ifft_coeffs);
     }
 
+    #[test]
+    fn test_coset_lde_into() {
+        type F = GoldilocksField;
+
+        let rate_bits = 2;
+        let poly = PolynomialCoeffs::new(F::rand_vec(64));
+        let shift = F::rand();
+        // Start from a dirty buffer, as when it is reused.
+        let mut out = F::rand_vec(64 << rate_bits);
+        poly.coset_lde_into(shift, rate_bits, None, &mut out);
+        assert_eq!(out, poly.lde(rate_bits).coset_fft(shift).values);
+    }
+
     #[test]
     fn test_coset_ifft() {
         type F = GoldilocksField;
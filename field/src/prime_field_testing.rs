@@ -73,7 +73,7 @@ macro_rules! test_prime_field_arithmetic {
             use core::ops::{Add, Mul, Neg, Sub};
 
             use $crate::ops::Square;
-            use $crate::types::{Field, Field64};
+            use $crate::types::{Field, Field64, PrimeField64};
 
             #[test]
             fn arithmetic_addition() {
@@ -178,6 +178,23 @@ macro_rules! test_prime_field_arithmetic {
 
                 assert_eq!(c, d);
             }
+
+            #[test]
+            fn canonical_bytes() {
+                type F = $field;
+
+                for x in $crate::prime_field_testing::test_inputs(F::ORDER) {
+                    let bytes = F::from_canonical_u64(x).canonical_bytes();
+                    assert_eq!(bytes, x.to_le_bytes());
+                    assert_eq!(
+                        F::from_canonical_bytes(bytes),
+                        Some(F::from_canonical_u64(x))
+                    );
+                }
+                for x in [F::ORDER, u64::MAX] {
+                    assert_eq!(F::from_canonical_bytes(x.to_le_bytes()), None);
+                }
+            }
         }
     };
 }
@@ -22,6 +22,34 @@ pub trait Sample: Sized {
     where
         R: rand::RngCore + ?Sized;
 
+    /// Samples a [`Vec`] of values of length `n` using `rng`.
+    #[inline]
+    fn sample_vec<R>(rng: &mut R, n: usize) -> Vec<Self>
+    where
+        R: rand::RngCore + ?Sized,
+    {
+        (0..n).map(|_| Self::sample(rng)).collect()
+    }
+
+    /// Samples an array of values of length `N` using `rng`.
+    #[inline]
+    fn sample_array<R, const N: usize>(rng: &mut R) -> [Self; N]
+    where
+        R: rand::RngCore + ?Sized,
+    {
+        core::array::from_fn(|_| Self::sample(rng))
+    }
+
+    /// Samples secret values, e.g. a private key, using `rng`, which must be cryptographically
+    /// secure: the [`OsRng`], or e.g. a source backed by a hardware security module.
+    #[inline]
+    fn sample_secret<R, const N: usize>(rng: &mut R) -> [Self; N]
+    where
+        R: rand::RngCore + rand::CryptoRng + ?Sized,
+    {
+        Self::sample_array(rng)
+    }
+
     /// Samples a single value using the [`OsRng`].
     #[inline]
     fn rand() -> Self {
@@ -31,16 +59,13 @@ pub trait Sample: Sized {
     /// Samples a [`Vec`] of values of length `n` using [`OsRng`].
     #[inline]
     fn rand_vec(n: usize) -> Vec<Self> {
-        (0..n).map(|_| Self::rand()).collect()
+        Self::sample_vec(&mut OsRng, n)
     }
 
     /// Samples an array of values of length `N` using [`OsRng`].
     #[inline]
     fn rand_array<const N: usize>() -> [Self; N] {
-        Self::rand_vec(N)
-            .try_into()
-            .ok()
-            .expect("This conversion can never fail.")
+        Self::sample_array(&mut OsRng)
     }
 }
 
@@ -514,6 +539,15 @@ pub trait Field64: Field {
     // TODO: Move to `Field`.
     fn from_noncanonical_i64(n: i64) -> Self;
 
+    /// Decodes the little-endian encoding of an element written by
+    /// `PrimeField64::canonical_bytes`, or returns `None` if it encodes a value of at least
+    /// `Self::ORDER`, so that each element has a single encoding.
+    #[inline]
+    fn from_canonical_bytes(bytes: [u8; 8]) -> Option<Self> {
+        let n = u64::from_le_bytes(bytes);
+        (n < Self::ORDER).then(|| Self::from_canonical_u64(n))
+    }
+
     /// Returns `n` as an element of this field. Assumes that `0 <= n < Self::ORDER`.
     // TODO: Move to `Field`.
     // TODO: Should probably be unsafe.
@@ -525,36 +559,70 @@ pub trait Field64: Field {
     #[inline]
     // TODO: Move to `Field`.
     fn add_one(&self) -> Self {
-        unsafe { self.add_canonical_u64(1) }
+        #[cfg(not(feature = "forbid-unsafe"))]
+        let res = unsafe { self.add_canonical_u64(1) };
+        #[cfg(feature = "forbid-unsafe")]
+        let res = self.add_canonical_u64(1);
+        res
     }
 
     #[inline]
     // TODO: Move to `Field`.
     fn sub_one(&self) -> Self {
-        unsafe { self.sub_canonical_u64(1) }
+        #[cfg(not(feature = "forbid-unsafe"))]
+        let res = unsafe { self.sub_canonical_u64(1) };
+        #[cfg(feature = "forbid-unsafe")]
+        let res = self.sub_canonical_u64(1);
+        res
     }
 
     /// # Safety
     /// Equivalent to *self + Self::from_canonical_u64(rhs), but may be cheaper. The caller must
     /// ensure that 0 <= rhs < Self::ORDER. The function may return incorrect results if this
-    /// precondition is not met. It is marked unsafe for this reason.
+    /// precondition is not met. It is marked unsafe for this reason, except with the
+    /// `forbid-unsafe` feature.
     // TODO: Move to `Field`.
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     unsafe fn add_canonical_u64(&self, rhs: u64) -> Self {
         // Default implementation.
         *self + Self::from_canonical_u64(rhs)
     }
 
+    /// Equivalent to *self + Self::from_canonical_u64(rhs), but may be cheaper. The caller must
+    /// ensure that 0 <= rhs < Self::ORDER. The function may return incorrect results if this
+    /// precondition is not met.
+    // TODO: Move to `Field`.
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    fn add_canonical_u64(&self, rhs: u64) -> Self {
+        // Default implementation.
+        *self + Self::from_canonical_u64(rhs)
+    }
+
     /// # Safety
     /// Equivalent to *self - Self::from_canonical_u64(rhs), but may be cheaper. The caller must
     /// ensure that 0 <= rhs < Self::ORDER. The function may return incorrect results if this
-    /// precondition is not met. It is marked unsafe for this reason.
+    /// precondition is not met. It is marked unsafe for this reason, except with the
+    /// `forbid-unsafe` feature.
     // TODO: Move to `Field`.
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     unsafe fn sub_canonical_u64(&self, rhs: u64) -> Self {
         // Default implementation.
         *self - Self::from_canonical_u64(rhs)
     }
+
+    /// Equivalent to *self - Self::from_canonical_u64(rhs), but may be cheaper. The caller must
+    /// ensure that 0 <= rhs < Self::ORDER. The function may return incorrect results if this
+    /// precondition is not met.
+    // TODO: Move to `Field`.
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    fn sub_canonical_u64(&self, rhs: u64) -> Self {
+        // Default implementation.
+        *self - Self::from_canonical_u64(rhs)
+    }
 }
 
 /// A finite field of prime order less than 2^64.
@@ -563,6 +631,14 @@ pub trait PrimeField64: PrimeField + Field64 {
 
     fn to_noncanonical_u64(&self) -> u64;
 
+    /// The canonical encoding of this element: its canonical value as 8 little-endian bytes. This
+    /// is how elements are serialized in proofs and hashed to bytes; `Field64::from_canonical_bytes`
+    /// decodes it.
+    #[inline]
+    fn canonical_bytes(&self) -> [u8; 8] {
+        self.to_canonical_u64().to_le_bytes()
+    }
+
     #[inline(always)]
     fn to_canonical(&self) -> Self {
         Self::from_canonical_u64(self.to_canonical_u64())
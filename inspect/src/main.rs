@@ -0,0 +1,243 @@
+//! Prints the shape and costs of serialized proofs and circuits. See the README for the formats.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::plonk::summary::{CircuitSummary, ProofSummary};
+use serde_json::json;
+use structopt::StructOpt;
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "plonky2-inspect")]
+struct Options {
+    /// Print JSON rather than text.
+    #[structopt(long)]
+    json: bool,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Inspect a proof, encoded by `ProofWithPublicInputs::to_bytes` or as JSON.
+    Proof {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// The proof's circuit, to check the proof's shape against: its verifier data, which a
+        /// binary proof needs to be read, or the JSON of its `CircuitSummary`.
+        #[structopt(long, parse(from_os_str))]
+        circuit: Option<PathBuf>,
+    },
+    /// Inspect a circuit, from its verifier data or the JSON of its `CircuitSummary`.
+    Circuit {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Can't read {:?}", path))
+}
+
+fn is_json(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    bytes[start..].starts_with(b"{")
+}
+
+/// Reads the summary of a circuit, along with its verifier data if that's what the file holds.
+fn read_circuit(path: &Path) -> Result<(CircuitSummary, Option<VerifierCircuitData<F, C, D>>)> {
+    let bytes = read(path)?;
+    if is_json(&bytes) {
+        let summary =
+            serde_json::from_slice(&bytes).with_context(|| format!("Can't parse {:?}", path))?;
+        Ok((summary, None))
+    } else {
+        let verifier_data = VerifierCircuitData::from_bytes(bytes)
+            .with_context(|| format!("Can't parse the verifier data {:?}", path))?;
+        Ok((
+            CircuitSummary::new(&verifier_data.common),
+            Some(verifier_data),
+        ))
+    }
+}
+
+fn read_proof(
+    path: &Path,
+    verifier_data: Option<&VerifierCircuitData<F, C, D>>,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let bytes = read(path)?;
+    if is_json(&bytes) {
+        return serde_json::from_slice(&bytes).with_context(|| format!("Can't parse {:?}", path));
+    }
+    let verifier_data = verifier_data.ok_or_else(|| {
+        anyhow!(
+            "Reading the binary proof {:?} needs its circuit's verifier data, see --circuit",
+            path
+        )
+    })?;
+    ProofWithPublicInputs::from_bytes(bytes, &verifier_data.common)
+        .with_context(|| format!("Can't parse the proof {:?}", path))
+}
+
+/// The ways in which `proof` doesn't have the shape `circuit` expects.
+fn mismatches(proof: &ProofSummary, circuit: &CircuitSummary) -> Vec<String> {
+    let mut mismatches = vec![];
+    let mut check = |name: &str, proof_value: String, circuit_value: String| {
+        if proof_value != circuit_value {
+            mismatches.push(format!(
+                "{}: {} in the proof, {} in the circuit",
+                name, proof_value, circuit_value
+            ));
+        }
+    };
+    check(
+        "public inputs",
+        proof.public_inputs.len().to_string(),
+        circuit.num_public_inputs.to_string(),
+    );
+    check(
+        "cap height",
+        proof.cap_height.to_string(),
+        circuit.fri.cap_height.to_string(),
+    );
+    check(
+        "query rounds",
+        proof.num_query_rounds.to_string(),
+        circuit.fri.num_query_rounds.to_string(),
+    );
+    check(
+        "reduction arity bits",
+        format!("{:?}", proof.reduction_arity_bits),
+        format!("{:?}", circuit.fri.reduction_arity_bits),
+    );
+    check(
+        "final polynomial length",
+        proof.final_poly_len.to_string(),
+        circuit.fri.final_poly_len.to_string(),
+    );
+    mismatches
+}
+
+fn print_proof(proof: &ProofSummary) {
+    println!("Proof");
+    println!("  size: {} bytes", proof.size_bytes);
+    println!("  cap height: {}", proof.cap_height);
+    println!("  query rounds: {}", proof.num_query_rounds);
+    println!("  reduction arity bits: {:?}", proof.reduction_arity_bits);
+    println!("  final polynomial length: {}", proof.final_poly_len);
+    println!(
+        "  estimated verifier cost: {} permutations",
+        proof.estimated_verifier_permutations
+    );
+    println!("  public inputs ({}):", proof.public_inputs.len());
+    for (i, x) in proof.public_inputs.iter().enumerate() {
+        println!("    {:>4}: {}", i, x);
+    }
+}
+
+fn print_circuit(circuit: &CircuitSummary) {
+    let fri = &circuit.fri;
+    println!("Circuit");
+    println!(
+        "  degree: 2^{} = {} rows",
+        circuit.degree_bits,
+        1usize << circuit.degree_bits
+    );
+    println!(
+        "  wires: {} ({} routed)",
+        circuit.num_wires, circuit.num_routed_wires
+    );
+    println!("  constants: {}", circuit.num_constants);
+    println!("  public inputs: {}", circuit.num_public_inputs);
+    println!("  challenges: {}", circuit.num_challenges);
+    println!(
+        "  quotient degree factor: {}",
+        circuit.quotient_degree_factor
+    );
+    println!("  partial products: {}", circuit.num_partial_products);
+    println!("  zero knowledge: {}", circuit.zero_knowledge);
+    println!("  FRI");
+    println!("    rate bits: {}", fri.rate_bits);
+    println!("    cap height: {}", fri.cap_height);
+    println!("    proof of work bits: {}", fri.proof_of_work_bits);
+    println!("    proof of work hash: {:?}", fri.pow_hash);
+    println!("    query rounds: {}", fri.num_query_rounds);
+    println!("    reduction arity bits: {:?}", fri.reduction_arity_bits);
+    println!("    final polynomial length: {}", fri.final_poly_len);
+    println!("    hiding: {}", fri.hiding);
+    println!(
+        "    conjectured security: {} bits",
+        fri.conjectured_security_bits
+    );
+    println!(
+        "  gates ({}, {} constraints at most):",
+        circuit.gates.len(),
+        circuit.num_gate_constraints
+    );
+    for gate in &circuit.gates {
+        println!(
+            "    {} (degree {}, {} constraints)",
+            gate.id, gate.degree, gate.num_constraints
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args();
+    match options.command {
+        Command::Proof { path, circuit } => {
+            let (circuit, verifier_data) = match circuit {
+                Some(path) => {
+                    let (summary, verifier_data) = read_circuit(&path)?;
+                    (Some(summary), verifier_data)
+                }
+                None => (None, None),
+            };
+            let proof = ProofSummary::new(&read_proof(&path, verifier_data.as_ref())?);
+            let mismatches = circuit
+                .as_ref()
+                .map(|circuit| mismatches(&proof, circuit))
+                .unwrap_or_default();
+            if options.json {
+                let output = json!({
+                    "proof": proof,
+                    "circuit": circuit,
+                    "mismatches": mismatches,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                print_proof(&proof);
+                if let Some(circuit) = &circuit {
+                    print_circuit(circuit);
+                }
+                for mismatch in &mismatches {
+                    println!("Mismatch: {}", mismatch);
+                }
+            }
+            ensure!(mismatches.is_empty(), "The proof doesn't match the circuit");
+        }
+        Command::Circuit { path } => {
+            let (circuit, _) = read_circuit(&path)?;
+            if options.json {
+                println!("{}", serde_json::to_string_pretty(&circuit)?);
+            } else {
+                print_circuit(&circuit);
+            }
+        }
+    }
+    Ok(())
+}
@@ -280,3 +280,87 @@ where
 {
     (oper_a(), oper_b())
 }
+
+/// The number of threads parallel iterators run on: that of the pool the caller is running in (see
+/// `ThreadPool::install`), or of the global pool otherwise. Always 1 without the `parallel`
+/// feature.
+#[cfg(feature = "parallel")]
+pub fn current_num_threads() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn current_num_threads() -> usize {
+    1
+}
+
+#[cfg(feature = "parallel")]
+pub type ThreadPoolBuildError = rayon::ThreadPoolBuildError;
+
+#[cfg(not(feature = "parallel"))]
+pub type ThreadPoolBuildError = core::convert::Infallible;
+
+/// Sets the number of threads of the global pool, used by parallel iterators outside of any
+/// `ThreadPool::install`. This fails if the global pool was already initialized, which happens the
+/// first time it is used, so it should be called early. Does nothing without the `parallel`
+/// feature.
+#[cfg(feature = "parallel")]
+pub fn set_global_num_threads(num_threads: usize) -> Result<(), ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_global()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn set_global_num_threads(_num_threads: usize) -> Result<(), ThreadPoolBuildError> {
+    Ok(())
+}
+
+/// A dedicated pool of worker threads. Without the `parallel` feature, work installed in the pool
+/// runs on the calling thread.
+#[derive(Debug)]
+pub struct ThreadPool {
+    #[cfg(feature = "parallel")]
+    pool: rayon::ThreadPool,
+}
+
+impl ThreadPool {
+    #[cfg(feature = "parallel")]
+    pub fn new(num_threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()?;
+        Ok(Self { pool })
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub fn new(_num_threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        Ok(Self {})
+    }
+
+    pub fn num_threads(&self) -> usize {
+        #[cfg(feature = "parallel")]
+        return self.pool.current_num_threads();
+        #[cfg(not(feature = "parallel"))]
+        return 1;
+    }
+
+    /// Runs `op` in this pool. All parallel iterators and `join`s started by `op`, however deeply
+    /// nested, share the pool's threads instead of spawning more.
+    #[cfg(feature = "parallel")]
+    pub fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        self.pool.install(op)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R,
+    {
+        op()
+    }
+}
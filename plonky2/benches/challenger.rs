@@ -0,0 +1,107 @@
+mod allocator;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use plonky2::field::extension::Extendable;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::hash::hash_types::RichField;
+use plonky2::hash::keccak::KeccakHash;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{AlgebraicHasher, Hasher};
+use tynm::type_name;
+
+const NUM_ELEMENTS: [usize; 3] = [16, 256, 4096];
+const NUM_CHALLENGES: [usize; 3] = [1, 16, 256];
+
+/// Throughput of the native challenger: absorbing `n` elements, then squeezing one challenge.
+pub(crate) fn bench_challenger<F: RichField, H: Hasher<F>>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(&format!(
+        "challenger<{}, {}>",
+        type_name::<F>(),
+        type_name::<H>()
+    ));
+
+    for n in NUM_ELEMENTS {
+        group.bench_with_input(BenchmarkId::new("observe", n), &n, |b, &n| {
+            b.iter_batched(
+                || F::rand_vec(n),
+                |elements| {
+                    let mut challenger = Challenger::<F, H>::new();
+                    challenger.observe_elements(&elements);
+                    challenger.get_challenge()
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    for n in NUM_CHALLENGES {
+        group.bench_with_input(BenchmarkId::new("squeeze", n), &n, |b, &n| {
+            b.iter_batched(
+                || {
+                    let mut challenger = Challenger::<F, H>::new();
+                    challenger.observe_element(F::rand());
+                    challenger
+                },
+                |mut challenger| challenger.get_n_challenges(n),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+/// The number of gates the recursive challenger adds to absorb `num_elements` elements and squeeze
+/// `num_challenges` challenges.
+fn recursive_challenger_gates<
+    F: RichField + Extendable<D>,
+    H: AlgebraicHasher<F>,
+    const D: usize,
+>(
+    num_elements: usize,
+    num_challenges: usize,
+) -> usize {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let elements = builder.add_virtual_targets(num_elements);
+    let before = builder.num_gates();
+    let mut challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+    challenger.observe_elements(&elements);
+    challenger.get_n_challenges(&mut builder, num_challenges);
+    builder.num_gates() - before
+}
+
+/// Gate counts aren't timings, so they are printed rather than measured by criterion.
+pub(crate) fn report_recursive_challenger<
+    F: RichField + Extendable<D>,
+    H: AlgebraicHasher<F>,
+    const D: usize,
+>() {
+    let name = format!(
+        "recursive-challenger<{}, {}>",
+        type_name::<F>(),
+        type_name::<H>()
+    );
+    for n in NUM_ELEMENTS {
+        let gates = recursive_challenger_gates::<F, H, D>(n, 1);
+        println!(
+            "{name}: absorbing {n} elements: {gates} gates, {:.3} per element",
+            gates as f64 / n as f64
+        );
+    }
+    for n in NUM_CHALLENGES {
+        let gates = recursive_challenger_gates::<F, H, D>(1, n);
+        println!(
+            "{name}: squeezing {n} challenges: {gates} gates, {:.3} per challenge",
+            gates as f64 / n as f64
+        );
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    bench_challenger::<GoldilocksField, PoseidonHash>(c);
+    bench_challenger::<GoldilocksField, KeccakHash<25>>(c);
+    report_recursive_challenger::<GoldilocksField, PoseidonHash, 2>();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+mod allocator;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plonky2::field::types::Sample;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::witness::{PartialWitness, WitnessLayout, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::prover::ProverOptions;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Proving time with each witness layout, for a chain of Poseidon hashes of `2^log_num_hashes`
+/// rows with the standard recursion config's 80 routed wires.
+fn bench_witness_layout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("witness_layout");
+    group.sample_size(10);
+
+    for log_num_hashes in [12, 14] {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_targets(4);
+        let mut hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs.clone());
+        for _ in 1..1 << log_num_hashes {
+            hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(hash.elements.to_vec());
+        }
+        builder.register_public_inputs(&hash.elements);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for (&target, value) in inputs.iter().zip(F::rand_vec(4)) {
+            pw.set_target(target, value);
+        }
+
+        for witness_layout in [WitnessLayout::ColumnMajor, WitnessLayout::RowMajor] {
+            let options = ProverOptions {
+                witness_layout,
+                ..ProverOptions::default()
+            };
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", witness_layout), log_num_hashes),
+                &options,
+                |b, options| b.iter(|| data.prove_with_options(pw.clone(), options).unwrap()),
+            );
+        }
+    }
+}
+
+criterion_group!(benches, bench_witness_layout);
+criterion_main!(benches);
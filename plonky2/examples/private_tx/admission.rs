@@ -0,0 +1,289 @@
+//! Admission policies, which the server consults before verifying a submitted proof.
+//!
+//! Verifying a proof is the expensive part of accepting a transaction, so a relayer open to anyone
+//! must turn away spam before doing it: by sender quotas, a minimum fee or a cap on the proofs
+//! waiting to be aggregated. `TokenBucketPolicy` does all three; other policies only need to
+//! implement `AdmissionPolicy`.
+//!
+//! A submission is signed by its sender, over the transaction's public inputs and the fee, and the
+//! server checks the signature before consulting the policy. The sender is the hash of the signing
+//! key, so a quota can't be charged to someone else, nor a fee changed on the way. Anyone can make
+//! new keys, though: a relayer which must bound each user registers their keys with
+//! `TokenBucketPolicy::allowed_senders`.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Result};
+use plonky2::curve::ecgfp5::CurvePoint;
+use plonky2::curve::stealth::hash_curve_point;
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
+use plonky2::prelude::*;
+use rand::Rng;
+
+use crate::circuit::PublicInputs;
+use crate::signing::{verify_signature, Signature, SigningKey};
+
+type F = GoldilocksField;
+
+/// Identifies the sender of a submission: the hash of the key it's signed with, see
+/// `sender_id`.
+pub type SenderId = HashOut<F>;
+
+/// The sender whose submissions are signed with `public_key`.
+pub fn sender_id(public_key: &CurvePoint<F>) -> SenderId {
+    hash_curve_point::<F, PoseidonHash>(public_key)
+}
+
+/// What the server knows of a submitted proof before verifying it.
+#[derive(Clone, Debug)]
+pub struct Submission {
+    /// The key the submission is signed with.
+    pub sender_key: CurvePoint<F>,
+    /// The fee offered for the transaction, in the relayer's unit.
+    pub fee: u64,
+    /// The sender's signature of the transaction's public inputs and `fee`.
+    pub signature: Signature,
+    pub received_at: Instant,
+}
+
+impl Submission {
+    /// A submission of the transaction with `public_inputs` received now, offering `fee` and
+    /// signed with `key`.
+    pub fn sign<R: Rng + ?Sized>(
+        public_inputs: &PublicInputs<F>,
+        fee: u64,
+        key: &SigningKey,
+        rng: &mut R,
+    ) -> Self {
+        Self {
+            sender_key: key.public_key(),
+            fee,
+            signature: key.sign(&message(public_inputs, fee), rng),
+            received_at: Instant::now(),
+        }
+    }
+
+    pub fn sender(&self) -> SenderId {
+        sender_id(&self.sender_key)
+    }
+
+    /// Checks that the submission is signed by its sender for the transaction with
+    /// `public_inputs`, so that neither it nor its fee can be replayed on another transaction.
+    pub fn verify(&self, public_inputs: &PublicInputs<F>) -> Result<()> {
+        verify_signature(
+            &self.sender_key,
+            &message(public_inputs, self.fee),
+            &self.signature,
+        )
+    }
+}
+
+/// The message signed for a submission: the transaction's public inputs, then the fee as two
+/// 32-bit limbs.
+fn message(public_inputs: &PublicInputs<F>, fee: u64) -> Vec<F> {
+    let mut message = public_inputs.to_public_inputs();
+    message.extend([fee as u32, (fee >> 32) as u32].map(F::from_canonical_u32));
+    message
+}
+
+/// A policy is cloned with the rest of the server by `Server::snapshot`, so that a rollback
+/// restores its quotas too; `Clone` policies get `clone_box` for free. Policies are `Send`, as the
+/// server is.
+pub trait AdmissionPolicy: AdmissionPolicyClone + Send {
+    /// Decides whether to verify `submission`, with `pending_proofs` proofs waiting to be
+    /// aggregated. An error rejects it, with the reason; the policy may count the submission
+    /// against the sender either way.
+    fn admit(&mut self, submission: &Submission, pending_proofs: usize) -> Result<()>;
+}
+
+pub trait AdmissionPolicyClone {
+    fn clone_box(&self) -> Box<dyn AdmissionPolicy>;
+}
+
+impl<P: AdmissionPolicy + Clone + 'static> AdmissionPolicyClone for P {
+    fn clone_box(&self) -> Box<dyn AdmissionPolicy> {
+        Box::new(self.clone())
+    }
+}
+
+/// Admits every submission, as a server without a policy does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdmitAll;
+
+impl AdmissionPolicy for AdmitAll {
+    fn admit(&mut self, _submission: &Submission, _pending_proofs: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Gives each sender a bucket of `capacity` tokens, refilled at one token per `refill_interval`,
+/// and spends a token per admitted submission; also rejects submissions with a fee under `min_fee`,
+/// if `max_pending_proofs` is set, while that many proofs are pending, and if `allowed_senders`
+/// is set, from any other sender.
+#[derive(Clone, Debug)]
+pub struct TokenBucketPolicy {
+    pub capacity: u32,
+    pub refill_interval: Duration,
+    pub min_fee: u64,
+    pub max_pending_proofs: Option<usize>,
+    /// The registered senders, e.g. one per user of the relayer; by default any key may submit,
+    /// with a bucket of its own.
+    pub allowed_senders: Option<HashSet<SenderId>>,
+    buckets: HashMap<SenderId, Bucket>,
+}
+
+impl TokenBucketPolicy {
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity,
+            refill_interval,
+            min_fee: 0,
+            max_pending_proofs: None,
+            allowed_senders: None,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// The tokens left to `sender` at `now`.
+    pub fn tokens(&self, sender: SenderId, now: Instant) -> f64 {
+        self.buckets
+            .get(&sender)
+            .map_or(self.capacity as f64, |bucket| {
+                self.refill(bucket, now).tokens
+            })
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> Bucket {
+        // A submission received before the last one, e.g. handled out of order, refills nothing.
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        let refilled = elapsed.as_secs_f64() / self.refill_interval.as_secs_f64();
+        Bucket {
+            tokens: (bucket.tokens + refilled).min(self.capacity as f64),
+            updated_at: bucket.updated_at.max(now),
+        }
+    }
+}
+
+impl Default for TokenBucketPolicy {
+    /// Bursts of 10 submissions per sender, then one per second.
+    fn default() -> Self {
+        Self::new(10, Duration::from_secs(1))
+    }
+}
+
+impl AdmissionPolicy for TokenBucketPolicy {
+    fn admit(&mut self, submission: &Submission, pending_proofs: usize) -> Result<()> {
+        let sender = submission.sender();
+        if let Some(allowed) = &self.allowed_senders {
+            ensure!(allowed.contains(&sender), "unknown sender {}", sender);
+        }
+        ensure!(
+            submission.fee >= self.min_fee,
+            "the fee {} is below the minimum of {}",
+            submission.fee,
+            self.min_fee
+        );
+        if let Some(max) = self.max_pending_proofs {
+            ensure!(
+                pending_proofs < max,
+                "{} proofs are already pending",
+                pending_proofs
+            );
+        }
+        let bucket = self.buckets.get(&sender).map_or(
+            Bucket {
+                tokens: self.capacity as f64,
+                updated_at: submission.received_at,
+            },
+            |bucket| self.refill(bucket, submission.received_at),
+        );
+        ensure!(bucket.tokens >= 1.0, "sender {} is over its quota", sender);
+        self.buckets.insert(
+            sender,
+            Bucket {
+                tokens: bucket.tokens - 1.0,
+                ..bucket
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    fn public_inputs() -> PublicInputs<F> {
+        PublicInputs {
+            nullifier_value: HashOut::rand(),
+            new_leaf_value: HashOut::rand(),
+            merkle_root_value: HashOut::rand(),
+            current_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut policy = TokenBucketPolicy::new(2, Duration::from_secs(10));
+        policy.min_fee = 5;
+        policy.max_pending_proofs = Some(3);
+        let start = Instant::now();
+        let keys = [SigningKey::new(&mut OsRng), SigningKey::new(&mut OsRng)];
+        let senders = keys.each_ref().map(|key| sender_id(&key.public_key()));
+        let submission = |sender: usize, fee, secs| Submission {
+            received_at: start + Duration::from_secs(secs),
+            ..Submission::sign(&public_inputs(), fee, &keys[sender], &mut OsRng)
+        };
+
+        assert!(policy.admit(&submission(0, 4, 0), 0).is_err());
+        assert!(policy.admit(&submission(0, 5, 0), 3).is_err());
+        // Rejections don't cost tokens.
+        assert_eq!(policy.tokens(senders[0], start), 2.0);
+
+        assert!(policy.admit(&submission(0, 5, 0), 0).is_ok());
+        assert!(policy.admit(&submission(0, 5, 1), 0).is_ok());
+        assert!(policy.admit(&submission(0, 5, 2), 0).is_err());
+        // Other senders have their own bucket.
+        assert!(policy.admit(&submission(1, 5, 2), 0).is_ok());
+        // The bucket refills over time, up to its capacity.
+        assert!(policy.admit(&submission(0, 5, 11), 0).is_ok());
+        assert!(policy.admit(&submission(0, 5, 11), 0).is_err());
+        assert_eq!(
+            policy.tokens(senders[0], start + Duration::from_secs(1000)),
+            2.0
+        );
+
+        // Once senders are registered, other keys are turned away.
+        policy.allowed_senders = Some(HashSet::from([senders[0]]));
+        assert!(policy.admit(&submission(1, 5, 1000), 0).is_err());
+        assert!(policy.admit(&submission(0, 5, 1000), 0).is_ok());
+    }
+
+    #[test]
+    fn test_signed_submission() -> Result<()> {
+        let key = SigningKey::new(&mut OsRng);
+        let public_inputs = public_inputs();
+        let submission = Submission::sign(&public_inputs, 5, &key, &mut OsRng);
+        submission.verify(&public_inputs)?;
+        assert_eq!(submission.sender(), sender_id(&key.public_key()));
+
+        // The signature covers the fee and the transaction, and binds the sender.
+        let mut raised = submission.clone();
+        raised.fee = 6;
+        assert!(raised.verify(&public_inputs).is_err());
+        assert!(submission.verify(&self::public_inputs()).is_err());
+        let mut impersonated = submission;
+        impersonated.sender_key = SigningKey::new(&mut OsRng).public_key();
+        assert!(impersonated.verify(&public_inputs).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use core::fmt;
+
+use anyhow::{anyhow, ensure, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field, Field64, PrimeField64};
+
+/// An amount of a token, as held in a note. Amounts are the `u64`s below the Goldilocks order, so
+/// that an amount and the field element committed in its note are the same number; arithmetic on
+/// them fails rather than wrapping, natively or modulo the order.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TokenAmount(u64);
+
+impl TokenAmount {
+    pub const ZERO: Self = Self(0);
+
+    pub fn new(amount: u64) -> Result<Self> {
+        ensure!(
+            amount < GoldilocksField::ORDER,
+            "amount {} doesn't fit in a field element",
+            amount
+        );
+        Ok(Self(amount))
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
+        let sum = self
+            .0
+            .checked_add(rhs.0)
+            .ok_or_else(|| anyhow!("amount overflow: {} + {}", self, rhs))?;
+        Self::new(sum)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Result<Self> {
+        self.0
+            .checked_sub(rhs.0)
+            .map(Self)
+            .ok_or_else(|| anyhow!("insufficient amount: {} - {}", self, rhs))
+    }
+
+    pub fn to_field(self) -> GoldilocksField {
+        GoldilocksField::from_canonical_u64(self.0)
+    }
+}
+
+impl TryFrom<u64> for TokenAmount {
+    type Error = anyhow::Error;
+
+    fn try_from(amount: u64) -> Result<Self> {
+        Self::new(amount)
+    }
+}
+
+impl From<GoldilocksField> for TokenAmount {
+    fn from(amount: GoldilocksField) -> Self {
+        Self(amount.to_canonical_u64())
+    }
+}
+
+impl From<TokenAmount> for GoldilocksField {
+    fn from(amount: TokenAmount) -> Self {
+        amount.to_field()
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::{Field, Field64};
+
+    use crate::amount::TokenAmount;
+
+    #[test]
+    fn test_token_amount() -> Result<()> {
+        let max = TokenAmount::new(GoldilocksField::ORDER - 1)?;
+        let one = TokenAmount::new(1)?;
+        assert!(TokenAmount::new(GoldilocksField::ORDER).is_err());
+        assert!(TokenAmount::try_from(u64::MAX).is_err());
+
+        // Sums must stay below the order, not only fit in a u64.
+        assert!(max.checked_add(one).is_err());
+        assert!(max.checked_add(max).is_err());
+        assert_eq!(max.checked_sub(max)?, TokenAmount::ZERO);
+        assert!(one.checked_sub(max).is_err());
+
+        assert_eq!(max.to_field(), GoldilocksField::NEG_ONE);
+        assert_eq!(TokenAmount::from(GoldilocksField::NEG_ONE), max);
+        Ok(())
+    }
+}
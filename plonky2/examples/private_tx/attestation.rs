@@ -0,0 +1,171 @@
+//! The operator's attestations of the batches of transactions it accepts.
+//!
+//! Whenever the server aggregates its pending proofs, it signs a batch made of the transactions
+//! applied since the previous batch: their range in `Server::events`, the note tree roots before
+//! and after them, the batch's index and the hash of the aggregated proof. Batches follow each
+//! other, each starting at the root the previous one ends at. A light client checking the
+//! signature against the operator's key learns the new root at the cost of a few scalar
+//! multiplications, and can decide later, or never, to verify the proof itself.
+
+use std::ops::Range;
+
+use anyhow::{ensure, Result};
+use keccak_hash::keccak;
+use plonky2::curve::ecgfp5::CurvePoint;
+use plonky2::prelude::*;
+use rand::Rng;
+
+use crate::signing::{bytes_message, verify_signature, Signature, SigningKey};
+
+type F = GoldilocksField;
+
+/// An operator's signature of `(old_root, new_root, batch_id, events, aggregate_proof_hash)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchAttestation {
+    /// The index of the batch among those the server accepted.
+    pub batch_id: usize,
+    /// The indices in `Server::events` of the transactions of the batch, starting where the
+    /// previous batch ends.
+    pub events: Range<usize>,
+    /// The note tree root before the first transaction of the batch, i.e. the `new_root` of the
+    /// previous batch.
+    pub old_root: HashOut<F>,
+    /// The note tree root after the last transaction of the batch.
+    pub new_root: HashOut<F>,
+    /// The Keccak-256 of the aggregated proof, as `ProvenCircuitOutput::to_bytes`. The server folds
+    /// each aggregate into the next, so the proof covers the earlier batches too, and proves the
+    /// transition to `new_root` from the root before the first of them.
+    pub aggregate_proof_hash: [u8; 32],
+    pub signature: Signature,
+}
+
+impl BatchAttestation {
+    pub fn sign<R: Rng + ?Sized>(
+        batch_id: usize,
+        events: Range<usize>,
+        old_root: HashOut<F>,
+        new_root: HashOut<F>,
+        aggregate_proof: &ProvenCircuitOutput<F, PoseidonGoldilocksConfig, 2>,
+        key: &SigningKey,
+        rng: &mut R,
+    ) -> Self {
+        let aggregate_proof_hash = keccak(aggregate_proof.to_bytes()).0;
+        let message = message(
+            batch_id,
+            &events,
+            &old_root,
+            &new_root,
+            &aggregate_proof_hash,
+        );
+        Self {
+            batch_id,
+            events,
+            old_root,
+            new_root,
+            aggregate_proof_hash,
+            signature: key.sign(&message, rng),
+        }
+    }
+
+    /// Checks that the attestation is signed by the operator whose public key is `operator_key`.
+    /// This says nothing of the validity of the aggregated proof, only that the operator vouches
+    /// for it.
+    pub fn verify(&self, operator_key: &CurvePoint<F>) -> Result<()> {
+        let message = message(
+            self.batch_id,
+            &self.events,
+            &self.old_root,
+            &self.new_root,
+            &self.aggregate_proof_hash,
+        );
+        verify_signature(operator_key, &message, &self.signature)
+    }
+
+    /// Checks that `aggregate_proof` is the proof of the batch, e.g. before verifying it in full.
+    pub fn check_proof(
+        &self,
+        aggregate_proof: &ProvenCircuitOutput<F, PoseidonGoldilocksConfig, 2>,
+    ) -> Result<()> {
+        ensure!(
+            keccak(aggregate_proof.to_bytes()).0 == self.aggregate_proof_hash,
+            "the proof isn't the one of batch {}",
+            self.batch_id
+        );
+        Ok(())
+    }
+}
+
+fn message(
+    batch_id: usize,
+    events: &Range<usize>,
+    old_root: &HashOut<F>,
+    new_root: &HashOut<F>,
+    aggregate_proof_hash: &[u8; 32],
+) -> Vec<F> {
+    let mut message = old_root.elements.to_vec();
+    message.extend(new_root.elements);
+    message.push(F::from_canonical_usize(batch_id));
+    message.push(F::from_canonical_usize(events.start));
+    message.push(F::from_canonical_usize(events.end));
+    message.extend(bytes_message(aggregate_proof_hash));
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::amount::TokenAmount;
+    use crate::client_emulation::Client;
+    use crate::server_emulation::Server;
+    use crate::state::State;
+
+    #[test]
+    fn test_batch_attestation() -> Result<()> {
+        let priv_key = F::rand_array();
+        let token_id = F::ONE;
+        let balance = TokenAmount::new(1000)?;
+        let (state, _) = State::new_demo_state(priv_key, token_id, balance, 10);
+        let mut client = Client::new(priv_key, token_id, balance, 0);
+        let mut server = Server::new(state);
+        server.set_max_pending_proofs(Some(1));
+        client.get_state_from_server(&server);
+        let old_root = server.get_state().private_utxo_tree.cap.0[0];
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+
+        // A light client only checks the signature to learn the new root.
+        let attestation = server.attestations()[0].clone();
+        attestation.verify(&server.operator_public_key())?;
+        assert_eq!(attestation.batch_id, 0);
+        assert_eq!(attestation.events, 0..2);
+        assert_eq!(attestation.old_root, old_root);
+        assert_eq!(
+            attestation.new_root,
+            server.get_state().private_utxo_tree.cap.0[0]
+        );
+        attestation.check_proof(&server.proofs[0].proof)?;
+
+        // The next batch starts where the first one ends, although its proof covers both.
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        let next = &server.attestations()[1];
+        next.verify(&server.operator_public_key())?;
+        assert_eq!(next.batch_id, 1);
+        assert_eq!(next.events, 2..3);
+        assert_eq!(next.old_root, attestation.new_root);
+        assert_eq!(next.new_root, server.get_state().private_utxo_tree.cap.0[0]);
+        next.check_proof(&server.proofs[0].proof)?;
+        assert_eq!(server.proofs[0].events, 0..3);
+
+        let mut tampered = attestation.clone();
+        tampered.new_root = HashOut::rand();
+        assert!(tampered.verify(&server.operator_public_key()).is_err());
+        let mut tampered = attestation.clone();
+        tampered.events = 1..2;
+        assert!(tampered.verify(&server.operator_public_key()).is_err());
+        let other_key = SigningKey::new(&mut rand::rngs::OsRng);
+        assert!(attestation.verify(&other_key.public_key()).is_err());
+        Ok(())
+    }
+}
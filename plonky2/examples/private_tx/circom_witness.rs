@@ -0,0 +1,268 @@
+//! Computes the witness of a compiled circom circuit from Rust and writes it as a `.wtns` file for
+//! snarkjs or rapidsnark, instead of going through snarkjs's JavaScript witness calculator.
+//!
+//! The signals are still computed by the WebAssembly witness calculator which `circom --wasm`
+//! emits, since it's the only description of the circuit's witness computation; it's compiled to
+//! native code with wasmer and driven as `witness_calculator.js` drives it, without converting every
+//! signal through JavaScript big integers.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use num::BigUint;
+use wasmer::{imports, Function, Instance, Module, Store};
+
+/// The witness calculator of a circuit, loaded from the `<name>_js/<name>.wasm` file emitted by
+/// `circom --wasm`.
+pub struct WitnessCalculator {
+    instance: Instance,
+    /// The number of 32-bit words of a field element.
+    n32: u32,
+    prime: BigUint,
+}
+
+impl WitnessCalculator {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let store = Store::default();
+        let module = Module::from_file(&store, path.as_ref())
+            .with_context(|| format!("failed to load {}", path.as_ref().display()))?;
+        // Errors are reported by the trap following these calls.
+        let import_object = imports! {
+            "runtime" => {
+                "exceptionHandler" => Function::new_native(&store, |_code: i32| {}),
+                "printErrorMessage" => Function::new_native(&store, || {}),
+                "writeBufferMessage" => Function::new_native(&store, || {}),
+                "showSharedRWMemory" => Function::new_native(&store, || {}),
+            }
+        };
+        let instance = Instance::new(&module, &import_object)?;
+
+        let mut calculator = Self {
+            instance,
+            n32: 0,
+            prime: BigUint::default(),
+        };
+        calculator.n32 = calculator.call0("getFieldNumLen32")? as u32;
+        calculator.call0_void("getRawPrime")?;
+        calculator.prime = calculator.read_shared_memory()?;
+        Ok(calculator)
+    }
+
+    /// The order of the circuit's field.
+    pub fn prime(&self) -> &BigUint {
+        &self.prime
+    }
+
+    /// Computes all signals of the circuit from the values of its input signals, given by name
+    /// with arrays flattened in row-major order. Fails if an input is missing or has the wrong
+    /// size, or if an assertion of the circuit fails.
+    pub fn calculate(&self, inputs: &[(String, Vec<BigUint>)]) -> Result<Vec<BigUint>> {
+        let exports = &self.instance.exports;
+        exports
+            .get_native_function::<i32, ()>("init")?
+            .call(1)
+            .context("failed to initialize the witness calculator")?;
+
+        let get_input_signal_size =
+            exports.get_native_function::<(i32, i32), i32>("getInputSignalSize")?;
+        let set_input_signal =
+            exports.get_native_function::<(i32, i32, i32), ()>("setInputSignal")?;
+        let mut num_set = 0;
+        for (name, values) in inputs {
+            let hash = fnv1a(name);
+            let (h_msb, h_lsb) = ((hash >> 32) as i32, hash as i32);
+            let size = get_input_signal_size.call(h_msb, h_lsb)?;
+            ensure!(size >= 0, "the circuit has no input signal {name}");
+            ensure!(
+                values.len() == size as usize,
+                "input signal {name} has {size} values, but {} were given",
+                values.len()
+            );
+            for (i, value) in values.iter().enumerate() {
+                self.write_shared_memory(&(value % &self.prime))?;
+                set_input_signal
+                    .call(h_msb, h_lsb, i as i32)
+                    .with_context(|| format!("failed to set {name}[{i}]"))?;
+            }
+            num_set += values.len();
+        }
+        let input_size = self.call0("getInputSize")? as usize;
+        ensure!(
+            num_set == input_size,
+            "only {num_set} of the {input_size} input values were set"
+        );
+
+        let witness_size = self.call0("getWitnessSize")?;
+        let get_witness = exports.get_native_function::<i32, ()>("getWitness")?;
+        (0..witness_size)
+            .map(|i| {
+                get_witness.call(i)?;
+                self.read_shared_memory()
+            })
+            .collect()
+    }
+
+    fn call0(&self, name: &str) -> Result<i32> {
+        Ok(self
+            .instance
+            .exports
+            .get_native_function::<(), i32>(name)?
+            .call()?)
+    }
+
+    fn call0_void(&self, name: &str) -> Result<()> {
+        Ok(self
+            .instance
+            .exports
+            .get_native_function::<(), ()>(name)?
+            .call()?)
+    }
+
+    /// Reads a field element from the shared memory, as 32-bit words, least significant first.
+    fn read_shared_memory(&self) -> Result<BigUint> {
+        let read = self
+            .instance
+            .exports
+            .get_native_function::<i32, i32>("readSharedRWMemory")?;
+        let words = (0..self.n32)
+            .map(|j| Ok(read.call(j as i32)? as u32))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BigUint::from_slice(&words))
+    }
+
+    fn write_shared_memory(&self, value: &BigUint) -> Result<()> {
+        let write = self
+            .instance
+            .exports
+            .get_native_function::<(i32, i32), ()>("writeSharedRWMemory")?;
+        let mut words = value.to_u32_digits();
+        words.resize(self.n32 as usize, 0);
+        for (j, word) in words.into_iter().enumerate() {
+            write.call(j as i32, word as i32)?;
+        }
+        Ok(())
+    }
+}
+
+/// The 64-bit FNV-1a hash of a signal name, by which the witness calculator looks up input
+/// signals.
+fn fnv1a(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `ParseBigIntError` isn't a `std::error::Error` without the `std` feature of `num`.
+fn parse_uint(s: &str) -> Result<BigUint> {
+    s.parse()
+        .map_err(|err| anyhow!("invalid input value {s}: {err}"))
+}
+
+/// Reads the inputs of a circuit from a JSON object such as `proof.json`, whose values are decimal
+/// strings or numbers, or arrays of them.
+pub fn inputs_from_json(json: &str) -> Result<Vec<(String, Vec<BigUint>)>> {
+    fn flatten(value: &serde_json::Value, out: &mut Vec<BigUint>) -> Result<()> {
+        match value {
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    flatten(value, out)?;
+                }
+            }
+            serde_json::Value::String(s) => out.push(parse_uint(s)?),
+            serde_json::Value::Number(n) => out.push(parse_uint(&n.to_string())?),
+            _ => return Err(anyhow!("unexpected input value {value}")),
+        }
+        Ok(())
+    }
+
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)?;
+    object
+        .iter()
+        .map(|(name, value)| {
+            let mut values = Vec::new();
+            flatten(value, &mut values)?;
+            Ok((name.clone(), values))
+        })
+        .collect()
+}
+
+/// Writes `witness` in the `.wtns` format read by snarkjs and rapidsnark: a header section with the
+/// size of field elements, the field order and the number of signals, then a section with the
+/// signals, each as a little-endian integer.
+pub fn write_wtns<W: Write>(mut writer: W, prime: &BigUint, witness: &[BigUint]) -> Result<()> {
+    let n8 = ((prime.bits() + 63) / 64 * 8) as usize;
+    let to_bytes = |x: &BigUint| {
+        let mut bytes = x.to_bytes_le();
+        bytes.resize(n8, 0);
+        bytes
+    };
+
+    writer.write_all(b"wtns")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+
+    writer.write_all(&1u32.to_le_bytes())?;
+    writer.write_all(&(4 + n8 as u64 + 4).to_le_bytes())?;
+    writer.write_all(&(n8 as u32).to_le_bytes())?;
+    writer.write_all(&to_bytes(prime))?;
+    writer.write_all(&(witness.len() as u32).to_le_bytes())?;
+
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&((n8 * witness.len()) as u64).to_le_bytes())?;
+    for x in witness {
+        ensure!(x < prime, "witness value {x} isn't reduced");
+        writer.write_all(&to_bytes(x))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_inputs_from_json() -> Result<()> {
+        let inputs = inputs_from_json(r#"{"a": [["1", "2"], ["3", "4"]], "b": "5", "c": 6}"#)?;
+        let values = |xs: &[u32]| xs.iter().map(|&x| BigUint::from(x)).collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            vec![
+                ("a".to_string(), values(&[1, 2, 3, 4])),
+                ("b".to_string(), values(&[5])),
+                ("c".to_string(), values(&[6])),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_wtns() -> Result<()> {
+        let prime = BigUint::from(0xffffffff00000001u64);
+        let witness = [1u32, 2, 3].map(BigUint::from);
+        let mut bytes = Vec::new();
+        write_wtns(&mut bytes, &prime, &witness)?;
+
+        assert_eq!(&bytes[..4], b"wtns");
+        // The header, with 8-byte field elements.
+        assert_eq!(&bytes[12..16], &1u32.to_le_bytes());
+        assert_eq!(&bytes[16..24], &16u64.to_le_bytes());
+        assert_eq!(&bytes[24..28], &8u32.to_le_bytes());
+        assert_eq!(&bytes[28..36], &0xffffffff00000001u64.to_le_bytes());
+        assert_eq!(&bytes[36..40], &3u32.to_le_bytes());
+        // The signals.
+        assert_eq!(&bytes[40..44], &2u32.to_le_bytes());
+        assert_eq!(&bytes[44..52], &24u64.to_le_bytes());
+        assert_eq!(&bytes[52..60], &1u64.to_le_bytes());
+        assert_eq!(bytes.len(), 52 + 24);
+
+        assert!(write_wtns(&mut Vec::new(), &prime, &[prime.clone()]).is_err());
+        Ok(())
+    }
+}
@@ -1,42 +1,240 @@
-use anyhow::Result;
+use core::num::NonZeroU32;
+
+use anyhow::{anyhow, ensure, Result};
 use log::{info, Level};
 use plonky2::gates::noop::NoopGate;
-use plonky2::hash::hash_types::{HashOut, HashOutTarget, RichField};
-use plonky2::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::iop::target::{BoolTarget, Target};
-use plonky2::iop::witness::{PartialWitness, Witness, WitnessWrite};
-use plonky2::plonk::circuit_builder::CircuitBuilder;
-use plonky2::plonk::circuit_data::{
-    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
-};
-use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
-use plonky2::plonk::proof::{Proof, ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use plonky2::hash::hash_chain::hash_chain;
 use plonky2::plonk::prover::prove;
+use plonky2::plonk::public_inputs_layout::{PublicInputSlot, PublicInputsLayout};
+use plonky2::prelude::*;
 use plonky2::util::timing::TimingTree;
-use plonky2_field::extension::Extendable;
-use plonky2_field::goldilocks_field::GoldilocksField;
+#[cfg(feature = "zeroize")]
+use plonky2::util::zeroize::zeroize_field_elements;
 
-pub type ProofTuple<F, C, const D: usize> = (
-    ProofWithPublicInputs<F, C, D>,
-    VerifierOnlyCircuitData<C, D>,
-    CommonCircuitData<F, D>,
-);
+/// The epochs in which a note can be spent, e.g. to vest a payment: from `valid_after` on, and
+/// before `valid_before` if it's set. Epochs are counted by the server, see
+/// `Server::set_current_epoch`. The default has no bounds.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Timelock {
+    pub valid_after: u32,
+    pub valid_before: Option<NonZeroU32>,
+}
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+impl Timelock {
+    pub fn is_open_at(&self, epoch: u32) -> bool {
+        epoch >= self.valid_after
+            && self
+                .valid_before
+                .map_or(true, |before| epoch < before.get())
+    }
+
+    /// The bounds as they are committed to in the note's leaf, a missing `valid_before` being 0.
+    pub fn to_fields<F: Field>(&self) -> [F; 2] {
+        [
+            F::from_canonical_u32(self.valid_after),
+            F::from_canonical_u32(self.valid_before.map_or(0, NonZeroU32::get)),
+        ]
+    }
+}
+
+/// The owner tag a note's leaf commits to: the hash of its spend key. Knowing it is enough to
+/// recognize the note and prove what it holds, see `note_audit_circuit`, but not to spend it, as
+/// `private_tx_circuit` takes the key itself.
+pub fn note_owner<F: RichField>(private_key: [F; 4]) -> HashOut<F> {
+    PoseidonHash::hash_no_pad(&private_key)
+}
+
+/// The leaf of a note, as `State` stores it and `private_tx_circuit` opens it.
+pub fn note_leaf<F: RichField>(
+    private_key: [F; 4],
+    timelock: Timelock,
+    token_id: F,
+    token_amount: F,
+) -> HashOut<F> {
+    owned_note_leaf(note_owner(private_key), timelock, token_id, token_amount)
+}
+
+/// The leaf of the note with owner tag `owner`, as `note_leaf`.
+pub fn owned_note_leaf<F: RichField>(
+    owner: HashOut<F>,
+    timelock: Timelock,
+    token_id: F,
+    token_amount: F,
+) -> HashOut<F> {
+    let [valid_after, valid_before] = timelock.to_fields();
+    PoseidonHash::hash_no_pad(
+        &[
+            owner.elements,
+            [valid_after, valid_before, token_id, token_amount],
+        ]
+        .concat(),
+    )
+}
+
+#[derive(Clone, Eq, PartialEq)]
 pub struct PrivateWitness<F: RichField> {
     pub private_key: [F; 4],
     pub index: usize,
     pub token_id: F,
     pub token_amount: F,
+    pub timelock: Timelock,
     pub merkle_proof: MerkleProof<F, PoseidonHash>,
 }
 
+/// The spend key is left out, so that witnesses can be logged, e.g. by a remote prover.
+impl<F: RichField> core::fmt::Debug for PrivateWitness<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PrivateWitness")
+            .field("private_key", &"<redacted>")
+            .field("index", &self.index)
+            .field("token_id", &self.token_id)
+            .field("token_amount", &self.token_amount)
+            .field("timelock", &self.timelock)
+            .field("merkle_proof", &self.merkle_proof)
+            .finish()
+    }
+}
+
+/// With the `zeroize` feature, the spend key and note opening are erased once the witness is
+/// dropped, i.e. right after proving.
+#[cfg(feature = "zeroize")]
+impl<F: RichField> zeroize::Zeroize for PrivateWitness<F> {
+    fn zeroize(&mut self) {
+        zeroize_field_elements(&mut self.private_key);
+        self.index.zeroize();
+        zeroize_field_elements(core::slice::from_mut(&mut self.token_id));
+        zeroize_field_elements(core::slice::from_mut(&mut self.token_amount));
+        self.timelock.valid_after.zeroize();
+        self.timelock.valid_before = None;
+        for sibling in self.merkle_proof.siblings.iter_mut() {
+            zeroize_field_elements(&mut sibling.elements);
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: RichField> Drop for PrivateWitness<F> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PublicInputs<F: RichField> {
     pub(crate) nullifier_value: HashOut<F>,
     pub(crate) new_leaf_value: HashOut<F>,
     pub merkle_root_value: HashOut<F>,
+    /// The epoch in which the transaction is made, which the spent note's timelock must allow.
+    pub current_epoch: u32,
+}
+
+impl<F: RichField> PublicInputs<F> {
+    pub const MERKLE_ROOT: PublicInputSlot = PublicInputSlot::first(4);
+    pub const NULLIFIER: PublicInputSlot = Self::MERKLE_ROOT.after(4);
+    pub const NEW_LEAF: PublicInputSlot = Self::NULLIFIER.after(4);
+    pub const CURRENT_EPOCH: PublicInputSlot = Self::NEW_LEAF.after(1);
+}
+
+impl<F: RichField> PublicInputsLayout<F> for PublicInputs<F> {
+    const NUM_PUBLIC_INPUTS: usize = Self::CURRENT_EPOCH.end();
+
+    fn write_public_inputs(&self, public_inputs: &mut [F]) {
+        Self::MERKLE_ROOT.set(public_inputs, &self.merkle_root_value.elements);
+        Self::NULLIFIER.set(public_inputs, &self.nullifier_value.elements);
+        Self::NEW_LEAF.set(public_inputs, &self.new_leaf_value.elements);
+        Self::CURRENT_EPOCH.set(public_inputs, &[F::from_canonical_u32(self.current_epoch)]);
+    }
+
+    fn read_public_inputs(public_inputs: &[F]) -> Result<Self> {
+        let current_epoch = Self::CURRENT_EPOCH.get(public_inputs)?[0].to_canonical_u64();
+        ensure!(current_epoch <= u32::MAX as u64, "epoch out of range");
+        Ok(Self {
+            merkle_root_value: HashOut::from_partial(Self::MERKLE_ROOT.get(public_inputs)?),
+            nullifier_value: HashOut::from_partial(Self::NULLIFIER.get(public_inputs)?),
+            new_leaf_value: HashOut::from_partial(Self::NEW_LEAF.get(public_inputs)?),
+            current_epoch: current_epoch as u32,
+        })
+    }
+}
+
+/// The targets of a note's leaf preimage, kept together so that the spend key, timelock, token and
+/// amount are always hashed and assigned in the same order.
+#[derive(Clone, Copy, Debug)]
+pub struct NoteTarget {
+    pub private_key: [Target; 4],
+    pub valid_after: Target,
+    pub valid_before: Target,
+    pub token_id: Target,
+    pub token_amount: Target,
+}
+
+impl NoteTarget {
+    fn add_virtual<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self {
+        let private_key = builder.add_virtual_targets(4).try_into().unwrap();
+        let valid_after = builder.add_virtual_target();
+        let valid_before = builder.add_virtual_target();
+        let token_id = builder.add_virtual_target();
+        let token_amount = builder.add_virtual_target();
+        Self {
+            private_key,
+            valid_after,
+            valid_before,
+            token_id,
+            token_amount,
+        }
+    }
+
+    /// The elements of the note's leaf, as `note_leaf` hashes them.
+    fn leaf<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Vec<Target> {
+        let owner = builder.hash_n_to_hash_no_pad::<PoseidonHash>(self.private_key.to_vec());
+        [
+            owner.elements,
+            [
+                self.valid_after,
+                self.valid_before,
+                self.token_id,
+                self.token_amount,
+            ],
+        ]
+        .concat()
+    }
+
+    /// Constrains the note's timelock to allow `current_epoch`, a 32-bit epoch.
+    fn constrain_timelock<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        current_epoch: Target,
+    ) {
+        // With all three in 32 bits, a difference is in 32 bits iff it isn't negative.
+        builder.range_check(current_epoch, 32);
+        builder.range_check(self.valid_after, 32);
+        builder.range_check(self.valid_before, 32);
+
+        let since = builder.sub(current_epoch, self.valid_after);
+        builder.range_check(since, 32);
+
+        // `valid_before - current_epoch - 1`, which is unconstrained when `valid_before` is 0.
+        let until = builder.sub(self.valid_before, current_epoch);
+        let until = builder.add_const(until, F::NEG_ONE);
+        let zero = builder.zero();
+        let no_bound = builder.is_equal(self.valid_before, zero);
+        let until = builder.select(no_bound, zero, until);
+        builder.range_check(until, 32);
+    }
+
+    fn set<F: RichField>(&self, pw: &mut PartialWitness<F>, witness: &PrivateWitness<F>) {
+        let [valid_after, valid_before] = witness.timelock.to_fields();
+        pw.set_target_arr(self.private_key, witness.private_key);
+        pw.set_target(self.valid_after, valid_after);
+        pw.set_target(self.valid_before, valid_before);
+        pw.set_target(self.token_id, witness.token_id);
+        pw.set_target(self.token_amount, witness.token_amount);
+    }
 }
 
 pub struct WiringTarget {
@@ -44,10 +242,9 @@ pub struct WiringTarget {
     pub nulifier_target: HashOutTarget,
     pub new_leaf_target: HashOutTarget,
     pub merkle_proof_target: MerkleProofTarget,
-    pub private_key_target: [Target; 4],
-    pub token_id_target: Target,
-    pub balance_target: Target,
+    pub note_target: NoteTarget,
     pub public_key_index_target: Target,
+    pub current_epoch_target: Target,
 }
 
 /// dont touch this unless there is agreement to do so
@@ -64,14 +261,17 @@ pub fn private_tx_circuit<
     // public data:
     // - merkle root
     let merkle_root_target = builder.add_virtual_hash();
-    builder.register_public_inputs(&merkle_root_target.elements);
+    builder.register_public_inputs_at(PublicInputs::<F>::MERKLE_ROOT, &merkle_root_target.elements);
     // - nullify
     info!("merkle root target is {:?}", merkle_root_target);
 
     let nulifier_target = builder.add_virtual_hash();
-    builder.register_public_inputs(&nulifier_target.elements); // - new leaf root
+    builder.register_public_inputs_at(PublicInputs::<F>::NULLIFIER, &nulifier_target.elements); // - new leaf root
     let new_leaf_target = builder.add_virtual_hash();
-    builder.register_public_inputs(&new_leaf_target.elements);
+    builder.register_public_inputs_at(PublicInputs::<F>::NEW_LEAF, &new_leaf_target.elements);
+    // - current epoch
+    let current_epoch_target = builder.add_virtual_target();
+    builder.register_public_inputs_at(PublicInputs::<F>::CURRENT_EPOCH, &[current_epoch_target]);
     // - Merkle proof
     let merkle_proof_target = MerkleProofTarget {
         siblings: builder.add_virtual_hashes(tree_height),
@@ -79,19 +279,13 @@ pub fn private_tx_circuit<
     info!("1 merkle root target is {:?}", merkle_root_target);
 
     // Prepare the hash data for UTXO tree
-    let private_key_target: [Target; 4] = builder.add_virtual_targets(4).try_into().unwrap();
-    let token_id_target = builder.add_virtual_target();
-    let balance_target = builder.add_virtual_target();
+    let note_target = NoteTarget::add_virtual(&mut builder);
     let public_key_index_target = builder.add_virtual_target();
     let public_key_index_bits_target = builder.split_le(public_key_index_target, tree_height);
-    let zero_target = builder.zero();
 
+    let note = note_target.leaf(&mut builder);
     builder.verify_merkle_proof::<PoseidonHash>(
-        [
-            private_key_target,
-            [zero_target, zero_target, token_id_target, balance_target],
-        ]
-        .concat(),
+        note.clone(),
         &public_key_index_bits_target,
         merkle_root_target,
         &merkle_proof_target,
@@ -99,17 +293,12 @@ pub fn private_tx_circuit<
 
     info!("2 merkle root target is {:?}", merkle_root_target);
 
-    let old_leaf = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
-        [
-            private_key_target,
-            [zero_target, zero_target, token_id_target, balance_target],
-        ]
-        .concat(),
-    );
+    let old_leaf = builder.hash_n_to_hash_no_pad::<PoseidonHash>(note);
     // enforce nullifer == old_leaf
-    for i in 0..4 {
-        builder.connect(nulifier_target.elements[i], old_leaf.elements[i]);
-    }
+    builder.connect_hashes(nulifier_target, old_leaf);
+
+    // the note can be spent in the current epoch
+    note_target.constrain_timelock(&mut builder, current_epoch_target);
 
     info!("3 merkle root target is {:?}", merkle_root_target);
 
@@ -125,10 +314,9 @@ pub fn private_tx_circuit<
             nulifier_target,
             new_leaf_target,
             merkle_proof_target,
-            private_key_target,
-            token_id_target,
-            balance_target,
+            note_target,
             public_key_index_target,
+            current_epoch_target,
         },
     )
 }
@@ -138,11 +326,54 @@ pub fn gen_private_proof<
     C: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    data: CircuitData<F, C, D>,
+    data: &CircuitData<F, C, D>,
     public_input: PublicInputs<F>,
     witness: PrivateWitness<F>,
-    wiring: WiringTarget,
-) -> Result<ProofTuple<F, C, D>> {
+    wiring: &WiringTarget,
+) -> Result<ProvenCircuitOutput<F, C, D>> {
+    let pw = private_tx_witness(&public_input, &witness, wiring);
+
+    let mut timing = TimingTree::new("prove", Level::Debug);
+    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+    timing.print();
+    info!("finish proving");
+    data.verify(proof.clone())?;
+
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only.clone(),
+        data.common.clone(),
+    ))
+}
+
+/// Checks that `witness` satisfies the transaction circuit with `public_input`, as proving would
+/// require, without proving.
+pub fn check_private_tx<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    data: &CircuitData<F, C, D>,
+    public_input: &PublicInputs<F>,
+    witness: &PrivateWitness<F>,
+    wiring: &WiringTarget,
+) -> Result<()> {
+    let pw = private_tx_witness(public_input, witness, wiring);
+    let public_inputs = data.check_witness(pw)?;
+    ensure!(
+        &PublicInputs::from_public_inputs(&public_inputs)? == public_input,
+        "the circuit computes other public inputs"
+    );
+    Ok(())
+}
+
+/// Assigns the public inputs and the private witness of a transaction to the targets of
+/// `private_tx_circuit`.
+pub fn private_tx_witness<F: RichField>(
+    public_input: &PublicInputs<F>,
+    witness: &PrivateWitness<F>,
+    wiring: &WiringTarget,
+) -> PartialWitness<F> {
     let mut pw = PartialWitness::new();
     //public witness
     info!("merkle root target is {:?}", wiring.merkle_root_target);
@@ -150,6 +381,10 @@ pub fn gen_private_proof<
     pw.set_hash_target(wiring.merkle_root_target, public_input.merkle_root_value);
     pw.set_hash_target(wiring.nulifier_target, public_input.nullifier_value);
     pw.set_hash_target(wiring.new_leaf_target, public_input.new_leaf_value);
+    pw.set_target(
+        wiring.current_epoch_target,
+        F::from_canonical_u32(public_input.current_epoch),
+    );
 
     info!(
         "what::: {:?} {:?}",
@@ -164,45 +399,460 @@ pub fn gen_private_proof<
         wiring.new_leaf_target, public_input.new_leaf_value
     );
 
-    for (ht, h) in wiring
-        .merkle_proof_target
-        .siblings
-        .into_iter()
-        .zip(witness.merkle_proof.siblings.clone())
-    {
-        pw.set_hash_target(ht, h);
-    }
+    pw.set_merkle_proof_target(&wiring.merkle_proof_target, &witness.merkle_proof);
 
     //private witness
-    pw.set_target_arr(wiring.private_key_target, witness.private_key);
-    pw.set_target(wiring.token_id_target, witness.token_id);
-    pw.set_target(wiring.balance_target, witness.token_amount);
+    wiring.note_target.set(&mut pw, witness);
     pw.set_target(
         wiring.public_key_index_target,
         F::from_canonical_u64(witness.index as u64),
     );
 
-    info!("{:?} {:?}", wiring.private_key_target, witness.private_key);
-    info!("{:?} {:?}", wiring.token_id_target, witness.token_id);
-    info!("{:?} {:?}", wiring.balance_target, witness.token_amount);
+    info!("{:?} {:?}", wiring.note_target.token_id, witness.token_id);
+    info!(
+        "{:?} {:?}",
+        wiring.note_target.token_amount, witness.token_amount
+    );
     info!("{:?} {:?}", wiring.public_key_index_target, witness.index);
 
     info!("finished setting target");
+    pw
+}
 
-    let mut timing = TimingTree::new("prove", Level::Debug);
+/// The openings linking a note of an archived epoch, see `State::set_epoch_size`, to the current
+/// note tree. `note.merkle_proof` is the note's path in the tree of its epoch, saved by its owner
+/// before the epoch ended.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArchivedNoteWitness<F: RichField> {
+    pub note: PrivateWitness<F>,
+    pub archived_root: HashOut<F>,
+    pub epoch: usize,
+    pub epoch_proof: MerkleProof<F, PoseidonHash>,
+    pub epoch_root: HashOut<F>,
+    pub checkpoint_proof: MerkleProof<F, PoseidonHash>,
+}
+
+pub struct ArchivedNoteWiringTarget {
+    /// The targets shared with `private_tx_circuit`; `merkle_proof_target` opens the archived tree.
+    pub wiring: WiringTarget,
+    pub archived_root_target: HashOutTarget,
+    pub epoch_target: Target,
+    pub epoch_proof_target: MerkleProofTarget,
+    pub epoch_root_target: HashOutTarget,
+    pub checkpoint_proof_target: MerkleProofTarget,
+}
+
+/// Like `private_tx_circuit`, with the same public inputs, for a note of an archived epoch: the
+/// note is in a tree whose root is leaf `epoch` of the tree of archived roots, and the root of that
+/// tree is the checkpoint at leaf 0 of the current tree. The archived roots stay private.
+pub fn archived_note_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    tree_height: usize,
+    epoch_tree_height: usize,
+) -> (CircuitData<F, C, D>, ArchivedNoteWiringTarget) {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+
+    let merkle_root_target = builder.add_virtual_hash();
+    builder.register_public_inputs_at(PublicInputs::<F>::MERKLE_ROOT, &merkle_root_target.elements);
+    let nulifier_target = builder.add_virtual_hash();
+    builder.register_public_inputs_at(PublicInputs::<F>::NULLIFIER, &nulifier_target.elements);
+    let new_leaf_target = builder.add_virtual_hash();
+    builder.register_public_inputs_at(PublicInputs::<F>::NEW_LEAF, &new_leaf_target.elements);
+    let current_epoch_target = builder.add_virtual_target();
+    builder.register_public_inputs_at(PublicInputs::<F>::CURRENT_EPOCH, &[current_epoch_target]);
+
+    let merkle_proof_target = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(tree_height),
+    };
+    let note_target = NoteTarget::add_virtual(&mut builder);
+    let public_key_index_target = builder.add_virtual_target();
+    let public_key_index_bits_target = builder.split_le(public_key_index_target, tree_height);
+    let note = note_target.leaf(&mut builder);
+
+    // The note is in the archived tree.
+    let archived_root_target = builder.add_virtual_hash();
+    builder.verify_merkle_proof::<PoseidonHash>(
+        note.clone(),
+        &public_key_index_bits_target,
+        archived_root_target,
+        &merkle_proof_target,
+    );
+
+    // The archived root is in the tree of archived roots.
+    let epoch_target = builder.add_virtual_target();
+    let epoch_bits_target = builder.split_le(epoch_target, epoch_tree_height);
+    let epoch_proof_target = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(epoch_tree_height),
+    };
+    let epoch_root_target = builder.add_virtual_hash();
+    builder.verify_merkle_proof::<PoseidonHash>(
+        archived_root_target.elements.to_vec(),
+        &epoch_bits_target,
+        epoch_root_target,
+        &epoch_proof_target,
+    );
+
+    // The root of archived roots is the checkpoint, leaf 0 of the current tree.
+    let checkpoint_proof_target = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(tree_height),
+    };
+    let checkpoint_index_bits = vec![builder._false(); tree_height];
+    builder.verify_merkle_proof::<PoseidonHash>(
+        epoch_root_target.elements.to_vec(),
+        &checkpoint_index_bits,
+        merkle_root_target,
+        &checkpoint_proof_target,
+    );
+
+    let old_leaf = builder.hash_n_to_hash_no_pad::<PoseidonHash>(note);
+    builder.connect_hashes(nulifier_target, old_leaf);
+    note_target.constrain_timelock(&mut builder, current_epoch_target);
+
+    (
+        builder.build::<C>(),
+        ArchivedNoteWiringTarget {
+            wiring: WiringTarget {
+                merkle_root_target,
+                nulifier_target,
+                new_leaf_target,
+                merkle_proof_target,
+                note_target,
+                public_key_index_target,
+                current_epoch_target,
+            },
+            archived_root_target,
+            epoch_target,
+            epoch_proof_target,
+            epoch_root_target,
+            checkpoint_proof_target,
+        },
+    )
+}
+
+/// Assigns the public inputs and the witness of a spend of an archived note to the targets of
+/// `archived_note_circuit`.
+pub fn archived_note_witness<F: RichField>(
+    public_input: &PublicInputs<F>,
+    witness: &ArchivedNoteWitness<F>,
+    wiring: &ArchivedNoteWiringTarget,
+) -> PartialWitness<F> {
+    let mut pw = private_tx_witness(public_input, &witness.note, &wiring.wiring);
+    pw.set_hash_target(wiring.archived_root_target, witness.archived_root);
+    pw.set_target(
+        wiring.epoch_target,
+        F::from_canonical_u64(witness.epoch as u64),
+    );
+    pw.set_merkle_proof_target(&wiring.epoch_proof_target, &witness.epoch_proof);
+    pw.set_hash_target(wiring.epoch_root_target, witness.epoch_root);
+    pw.set_merkle_proof_target(&wiring.checkpoint_proof_target, &witness.checkpoint_proof);
+    pw
+}
+
+pub fn gen_archived_note_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    data: &CircuitData<F, C, D>,
+    public_input: PublicInputs<F>,
+    witness: ArchivedNoteWitness<F>,
+    wiring: &ArchivedNoteWiringTarget,
+) -> Result<ProvenCircuitOutput<F, C, D>> {
+    let pw = archived_note_witness(&public_input, &witness, wiring);
+
+    let mut timing = TimingTree::new("prove archived note", Level::Debug);
     let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
     timing.print();
-    info!("finish proving");
     data.verify(proof.clone())?;
 
-    Ok((proof, data.verifier_only, data.common))
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only.clone(),
+        data.common.clone(),
+    ))
+}
+
+/// The public inputs of `note_audit_circuit`: the note with leaf `leaf_value` is in the tree with
+/// root `merkle_root_value` and holds `token_amount` of `token_id`. An auditor also checks that the
+/// leaf isn't nullified, as the nullifier of a note is its leaf.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditPublicInputs<F: RichField> {
+    pub merkle_root_value: HashOut<F>,
+    pub leaf_value: HashOut<F>,
+    pub token_id: F,
+    pub token_amount: F,
+}
+
+impl<F: RichField> AuditPublicInputs<F> {
+    pub const MERKLE_ROOT: PublicInputSlot = PublicInputSlot::first(4);
+    pub const LEAF: PublicInputSlot = Self::MERKLE_ROOT.after(4);
+    pub const TOKEN_ID: PublicInputSlot = Self::LEAF.after(1);
+    pub const TOKEN_AMOUNT: PublicInputSlot = Self::TOKEN_ID.after(1);
+}
+
+impl<F: RichField> PublicInputsLayout<F> for AuditPublicInputs<F> {
+    const NUM_PUBLIC_INPUTS: usize = Self::TOKEN_AMOUNT.end();
+
+    fn write_public_inputs(&self, public_inputs: &mut [F]) {
+        Self::MERKLE_ROOT.set(public_inputs, &self.merkle_root_value.elements);
+        Self::LEAF.set(public_inputs, &self.leaf_value.elements);
+        Self::TOKEN_ID.set(public_inputs, &[self.token_id]);
+        Self::TOKEN_AMOUNT.set(public_inputs, &[self.token_amount]);
+    }
+
+    fn read_public_inputs(public_inputs: &[F]) -> Result<Self> {
+        Ok(Self {
+            merkle_root_value: HashOut::from_partial(Self::MERKLE_ROOT.get(public_inputs)?),
+            leaf_value: HashOut::from_partial(Self::LEAF.get(public_inputs)?),
+            token_id: Self::TOKEN_ID.get(public_inputs)?[0],
+            token_amount: Self::TOKEN_AMOUNT.get(public_inputs)?[0],
+        })
+    }
+}
+
+/// The witness of an audit proof. The owner tag and timelock stay private; the index and Merkle
+/// proof don't reveal more than the public leaf does, as anyone can find the leaf in the tree.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditWitness<F: RichField> {
+    pub owner: HashOut<F>,
+    pub timelock: Timelock,
+    pub index: usize,
+    pub merkle_proof: MerkleProof<F, PoseidonHash>,
+}
+
+pub struct AuditWiringTarget {
+    pub merkle_root_target: HashOutTarget,
+    pub leaf_target: HashOutTarget,
+    pub token_id_target: Target,
+    pub token_amount_target: Target,
+    pub owner_target: HashOutTarget,
+    pub valid_after_target: Target,
+    pub valid_before_target: Target,
+    pub index_target: Target,
+    pub merkle_proof_target: MerkleProofTarget,
+}
+
+/// Proves what a note holds from its owner tag rather than its spend key, so that a view-only
+/// client can prove its balance to an auditor, see `Client::audit_proof`, without being able to
+/// spend.
+pub fn note_audit_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    tree_height: usize,
+) -> (CircuitData<F, C, D>, AuditWiringTarget) {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+
+    let merkle_root_target = builder.add_virtual_hash();
+    builder.register_public_inputs_at(
+        AuditPublicInputs::<F>::MERKLE_ROOT,
+        &merkle_root_target.elements,
+    );
+    let leaf_target = builder.add_virtual_hash();
+    builder.register_public_inputs_at(AuditPublicInputs::<F>::LEAF, &leaf_target.elements);
+    let token_id_target = builder.add_virtual_target();
+    builder.register_public_inputs_at(AuditPublicInputs::<F>::TOKEN_ID, &[token_id_target]);
+    let token_amount_target = builder.add_virtual_target();
+    builder.register_public_inputs_at(AuditPublicInputs::<F>::TOKEN_AMOUNT, &[token_amount_target]);
+
+    let owner_target = builder.add_virtual_hash();
+    let valid_after_target = builder.add_virtual_target();
+    let valid_before_target = builder.add_virtual_target();
+    let note = [
+        owner_target.elements,
+        [
+            valid_after_target,
+            valid_before_target,
+            token_id_target,
+            token_amount_target,
+        ],
+    ]
+    .concat();
+
+    let index_target = builder.add_virtual_target();
+    let index_bits_target = builder.split_le(index_target, tree_height);
+    let merkle_proof_target = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(tree_height),
+    };
+    builder.verify_merkle_proof::<PoseidonHash>(
+        note.clone(),
+        &index_bits_target,
+        merkle_root_target,
+        &merkle_proof_target,
+    );
+    let leaf = builder.hash_n_to_hash_no_pad::<PoseidonHash>(note);
+    builder.connect_hashes(leaf_target, leaf);
+
+    (
+        builder.build::<C>(),
+        AuditWiringTarget {
+            merkle_root_target,
+            leaf_target,
+            token_id_target,
+            token_amount_target,
+            owner_target,
+            valid_after_target,
+            valid_before_target,
+            index_target,
+            merkle_proof_target,
+        },
+    )
+}
+
+/// Assigns the public inputs and the witness of an audit proof to the targets of
+/// `note_audit_circuit`.
+pub fn audit_witness<F: RichField>(
+    public_input: &AuditPublicInputs<F>,
+    witness: &AuditWitness<F>,
+    wiring: &AuditWiringTarget,
+) -> PartialWitness<F> {
+    let mut pw = PartialWitness::new();
+    pw.set_hash_target(wiring.merkle_root_target, public_input.merkle_root_value);
+    pw.set_hash_target(wiring.leaf_target, public_input.leaf_value);
+    pw.set_target(wiring.token_id_target, public_input.token_id);
+    pw.set_target(wiring.token_amount_target, public_input.token_amount);
+
+    let [valid_after, valid_before] = witness.timelock.to_fields();
+    pw.set_hash_target(wiring.owner_target, witness.owner);
+    pw.set_target(wiring.valid_after_target, valid_after);
+    pw.set_target(wiring.valid_before_target, valid_before);
+    pw.set_target(
+        wiring.index_target,
+        F::from_canonical_u64(witness.index as u64),
+    );
+    pw.set_merkle_proof_target(&wiring.merkle_proof_target, &witness.merkle_proof);
+    pw
+}
+
+pub fn gen_audit_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    data: &CircuitData<F, C, D>,
+    public_input: AuditPublicInputs<F>,
+    witness: AuditWitness<F>,
+    wiring: &AuditWiringTarget,
+) -> Result<ProvenCircuitOutput<F, C, D>> {
+    let pw = audit_witness(&public_input, &witness, wiring);
+
+    let mut timing = TimingTree::new("prove audit", Level::Debug);
+    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+    timing.print();
+    data.verify(proof.clone())?;
+
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only.clone(),
+        data.common.clone(),
+    ))
 }
 
 pub fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     data: &CircuitData<F, C, D>,
-    proof: ProofTuple<F, C, D>,
+    proof: ProvenCircuitOutput<F, C, D>,
 ) -> Result<()> {
-    data.verify(proof.0.clone())
+    data.verify(proof.proof)
+}
+
+/// The public inputs of `recursive_circuit`: the circuit digests of its two inner proofs, and the
+/// nullifier accumulators before and after the transactions it covers, see `hash_chain`. The
+/// accumulator stands for the nullifiers themselves, which a verifier holding them checks by
+/// recomputing the chain with `nullifier_accumulator`. Then comes the root of the tree over the
+/// public inputs of the transactions, see `PublicInputsTree`, and last the note tree roots before
+/// and after the transactions, which the circuit proves from the insertion of each new note, see
+/// `NoteInsertion`.
+pub const INNER_DIGEST_1: PublicInputSlot = PublicInputSlot::first(4);
+pub const INNER_DIGEST_2: PublicInputSlot = INNER_DIGEST_1.after(4);
+pub const NULLIFIER_ACCUMULATOR_START: PublicInputSlot = INNER_DIGEST_2.after(4);
+pub const NULLIFIER_ACCUMULATOR_END: PublicInputSlot = NULLIFIER_ACCUMULATOR_START.after(4);
+pub const PUBLIC_INPUTS_ROOT: PublicInputSlot = NULLIFIER_ACCUMULATOR_END.after(4);
+pub const OLD_ROOT: PublicInputSlot = PUBLIC_INPUTS_ROOT.after(4);
+pub const NEW_ROOT: PublicInputSlot = OLD_ROOT.after(4);
+
+/// The leaf of the unused slots of the note tree.
+pub fn empty_leaf<F: RichField>() -> HashOut<F> {
+    PoseidonHash::hash_no_pad(&[F::ZERO; 8])
+}
+
+/// The insertion of a transaction's new note into the note tree, from which `recursive_circuit`
+/// proves the roots before and after the transaction: leaf `index` is empty under `old_root`, and
+/// is the new note under `new_root`, with the same siblings. As the note tree is replaced when an
+/// epoch starts, see `State::set_epoch_size`, the proven roots don't chain across epochs.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NoteInsertion<F: RichField> {
+    pub old_root: HashOut<F>,
+    pub new_root: HashOut<F>,
+    pub index: usize,
+    pub merkle_proof: MerkleProof<F, PoseidonHash>,
+}
+
+impl<F: RichField> NoteInsertion<F> {
+    /// The insertion of `new_leaf` at `index`, whose siblings are those of `merkle_proof`.
+    pub fn new(
+        index: usize,
+        new_leaf: HashOut<F>,
+        merkle_proof: MerkleProof<F, PoseidonHash>,
+    ) -> Self {
+        let root = |leaf: HashOut<F>| {
+            merkle_proof
+                .siblings
+                .iter()
+                .enumerate()
+                .fold(leaf, |node, (level, &sibling)| {
+                    if (index >> level) & 1 == 0 {
+                        PoseidonHash::two_to_one(node, sibling)
+                    } else {
+                        PoseidonHash::two_to_one(sibling, node)
+                    }
+                })
+        };
+        Self {
+            old_root: root(empty_leaf()),
+            new_root: root(new_leaf),
+            index,
+            merkle_proof,
+        }
+    }
+}
+
+pub struct NoteInsertionTarget {
+    pub old_root: HashOutTarget,
+    pub new_root: HashOutTarget,
+    pub index: Target,
+    pub merkle_proof: MerkleProofTarget,
+}
+
+/// Absorbs `nullifiers`, in order, into the nullifier accumulator `accumulator`, as
+/// `recursive_circuit` does. The accumulator before the first transaction is zero.
+pub fn nullifier_accumulator<'a, F: RichField>(
+    accumulator: HashOut<F>,
+    nullifiers: impl IntoIterator<Item = &'a HashOut<F>>,
+) -> HashOut<F> {
+    hash_chain::<F, PoseidonHash>(accumulator, nullifiers)
+}
+
+/// What an inner proof of `recursive_circuit` proves, which fixes how the circuit reads its public
+/// inputs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ProofKind {
+    /// A transaction proof, with the public inputs of `PublicInputs`, e.g. of `private_tx_circuit`
+    /// or `archived_note_circuit`.
+    Transaction,
+    /// A proof of `recursive_circuit`.
+    Aggregate,
+}
+
+impl ProofKind {
+    /// The number of public inputs of proofs of this kind.
+    pub fn num_public_inputs<F: RichField>(self) -> usize {
+        match self {
+            ProofKind::Transaction => PublicInputs::<F>::NUM_PUBLIC_INPUTS,
+            ProofKind::Aggregate => NEW_ROOT.end(),
+        }
+    }
 }
 
 pub struct RecursiveWiringTargets<const D: usize> {
@@ -210,26 +860,121 @@ pub struct RecursiveWiringTargets<const D: usize> {
     pub pt2: ProofWithPublicInputsTarget<D>,
     pub vc1: VerifierCircuitTarget,
     pub vc2: VerifierCircuitTarget,
+    /// The nullifier accumulator before the transactions of the first inner proof.
+    pub nullifier_accumulator: HashOutTarget,
+    /// The insertions of the new notes of the inner proofs which are transaction proofs.
+    pub insertions: [Option<NoteInsertionTarget>; 2],
+}
+
+/// The nullifier accumulator after the transactions of the inner proof of `recursive_circuit` of
+/// kind `kind` with public inputs `public_inputs`, given the accumulator `start` before them: a
+/// transaction proof absorbs its nullifier, and an aggregate exposes both accumulators.
+fn inner_nullifier_accumulator<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    kind: ProofKind,
+    public_inputs: &[Target],
+    start: HashOutTarget,
+) -> HashOutTarget {
+    let slot_hash =
+        |slot: PublicInputSlot| HashOutTarget::from_vec(public_inputs[slot.range()].to_vec());
+    match kind {
+        ProofKind::Transaction => {
+            let nullifier = slot_hash(PublicInputs::<F>::NULLIFIER);
+            builder.hash_chain::<PoseidonHash>(start, &[nullifier])
+        }
+        ProofKind::Aggregate => {
+            builder.connect_hashes(start, slot_hash(NULLIFIER_ACCUMULATOR_START));
+            slot_hash(NULLIFIER_ACCUMULATOR_END)
+        }
+    }
+}
+
+/// The node of the inner proof of `recursive_circuit` of kind `kind` with public inputs
+/// `public_inputs` in the tree over the public inputs of the transactions: the hash of a
+/// transaction proof's public inputs, or the root an aggregate exposes.
+fn inner_public_inputs_node<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    kind: ProofKind,
+    public_inputs: &[Target],
+) -> HashOutTarget {
+    match kind {
+        ProofKind::Transaction => builder.hash_or_noop::<PoseidonHash>(public_inputs.to_vec()),
+        ProofKind::Aggregate => {
+            HashOutTarget::from_vec(public_inputs[PUBLIC_INPUTS_ROOT.range()].to_vec())
+        }
+    }
+}
+
+/// The note tree roots before and after the transactions of the inner proof of `recursive_circuit`
+/// of kind `kind` with public inputs `public_inputs`: those of the insertion of a transaction
+/// proof's new note, whose targets are returned too, or the roots an aggregate exposes.
+fn inner_roots<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    kind: ProofKind,
+    public_inputs: &[Target],
+    tree_height: usize,
+) -> (HashOutTarget, HashOutTarget, Option<NoteInsertionTarget>) {
+    let slot_hash =
+        |slot: PublicInputSlot| HashOutTarget::from_vec(public_inputs[slot.range()].to_vec());
+    if kind == ProofKind::Transaction {
+        let insertion = NoteInsertionTarget {
+            old_root: builder.add_virtual_hash(),
+            new_root: builder.add_virtual_hash(),
+            index: builder.add_virtual_target(),
+            merkle_proof: MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(tree_height),
+            },
+        };
+        let index_bits = builder.split_le(insertion.index, tree_height);
+        let empty_leaf = builder.constant_hash(empty_leaf());
+        builder.verify_merkle_proof::<PoseidonHash>(
+            empty_leaf.elements.to_vec(),
+            &index_bits,
+            insertion.old_root,
+            &insertion.merkle_proof,
+        );
+        let new_leaf = slot_hash(PublicInputs::<F>::NEW_LEAF);
+        builder.verify_merkle_proof::<PoseidonHash>(
+            new_leaf.elements.to_vec(),
+            &index_bits,
+            insertion.new_root,
+            &insertion.merkle_proof,
+        );
+        (insertion.old_root, insertion.new_root, Some(insertion))
+    } else {
+        (slot_hash(OLD_ROOT), slot_hash(NEW_ROOT), None)
+    }
 }
 
 /// recursive_circuit is a specific circuit to recursively
 /// reunion 2 proofs and prove that it was generated correctly.
+/// `kinds` are the kinds of `inner1` and `inner2`.
 pub fn recursive_circuit<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     InnerC: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    inner1: &ProofTuple<F, InnerC, D>,
-    inner2: &ProofTuple<F, InnerC, D>,
+    inner1: &ProvenCircuitOutput<F, InnerC, D>,
+    inner2: &ProvenCircuitOutput<F, InnerC, D>,
+    kinds: [ProofKind; 2],
     config: &CircuitConfig,
+    tree_height: usize,
     min_degree_bits: Option<usize>,
 ) -> (CircuitData<F, C, D>, RecursiveWiringTargets<D>)
 where
     InnerC::Hasher: AlgebraicHasher<F>,
 {
-    let (_, _, inner_cd1) = inner1;
-    let (_, _, inner_cd2) = inner2;
+    let inner_cd1 = inner1.common();
+    let inner_cd2 = inner2.common();
+    for (inner_cd, kind) in [inner_cd1, inner_cd2].into_iter().zip(kinds) {
+        assert_eq!(
+            inner_cd.num_public_inputs,
+            kind.num_public_inputs::<F>(),
+            "an inner proof doesn't have the public inputs of a {:?} proof",
+            kind
+        );
+    }
 
     let mut builder = CircuitBuilder::<F, D>::new(config.clone());
     let pt1 = builder.add_virtual_proof_with_pis::<InnerC>(inner_cd1);
@@ -238,13 +983,40 @@ where
         constants_sigmas_cap: builder.add_virtual_cap(inner_cd1.config.fri_config.cap_height),
         circuit_digest: builder.add_virtual_hash(),
     };
-    builder.register_public_inputs(vc1.circuit_digest.elements.as_slice());
+    builder.register_public_inputs_at(INNER_DIGEST_1, &vc1.circuit_digest.elements);
 
     let vc2 = VerifierCircuitTarget {
         constants_sigmas_cap: builder.add_virtual_cap(inner_cd2.config.fri_config.cap_height),
         circuit_digest: builder.add_virtual_hash(),
     };
-    builder.register_public_inputs(vc2.circuit_digest.elements.as_slice());
+    builder.register_public_inputs_at(INNER_DIGEST_2, &vc2.circuit_digest.elements);
+
+    // The second proof's transactions follow the first's, so its accumulator starts where the
+    // first one's ends.
+    let nullifier_accumulator = builder.add_virtual_hash();
+    builder.register_public_inputs_at(NULLIFIER_ACCUMULATOR_START, &nullifier_accumulator.elements);
+    let middle = inner_nullifier_accumulator(
+        &mut builder,
+        kinds[0],
+        &pt1.public_inputs,
+        nullifier_accumulator,
+    );
+    let end = inner_nullifier_accumulator(&mut builder, kinds[1], &pt2.public_inputs, middle);
+    builder.register_public_inputs_at(NULLIFIER_ACCUMULATOR_END, &end.elements);
+
+    let left = inner_public_inputs_node(&mut builder, kinds[0], &pt1.public_inputs);
+    let right = inner_public_inputs_node(&mut builder, kinds[1], &pt2.public_inputs);
+    let root = builder.two_to_one::<PoseidonHash>(left, right);
+    builder.register_public_inputs_at(PUBLIC_INPUTS_ROOT, &root.elements);
+
+    // The second proof's transactions are applied to the note tree the first one's lead to.
+    let (old_root, middle_root, insertion1) =
+        inner_roots(&mut builder, kinds[0], &pt1.public_inputs, tree_height);
+    let (middle_root2, new_root, insertion2) =
+        inner_roots(&mut builder, kinds[1], &pt2.public_inputs, tree_height);
+    builder.connect_hashes(middle_root, middle_root2);
+    builder.register_public_inputs_at(OLD_ROOT, &old_root.elements);
+    builder.register_public_inputs_at(NEW_ROOT, &new_root.elements);
 
     builder.verify_proof::<InnerC>(&pt1, &vc1, inner_cd1);
     builder.verify_proof::<InnerC>(&pt2, &vc2, inner_cd2);
@@ -263,33 +1035,55 @@ where
 
     (
         builder.build::<C>(),
-        RecursiveWiringTargets { pt1, pt2, vc1, vc2 },
+        RecursiveWiringTargets {
+            pt1,
+            pt2,
+            vc1,
+            vc2,
+            nullifier_accumulator,
+            insertions: [insertion1, insertion2],
+        },
     )
 }
 
+/// Proves `recursive_circuit` for `inner1` and `inner2`, whose transactions follow those absorbed
+/// into `nullifier_accumulator`. `insertions` are those of the new notes of the inner proofs which
+/// are transaction proofs.
 pub fn gen_recursive_circuit<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     InnerC: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    inner1: &ProofTuple<F, InnerC, D>,
-    inner2: &ProofTuple<F, InnerC, D>,
-    data: CircuitData<F, C, D>,
-    wiring: RecursiveWiringTargets<D>,
-) -> Result<ProofTuple<F, C, D>>
+    inner1: &ProvenCircuitOutput<F, InnerC, D>,
+    inner2: &ProvenCircuitOutput<F, InnerC, D>,
+    data: &CircuitData<F, C, D>,
+    wiring: &RecursiveWiringTargets<D>,
+    nullifier_accumulator: HashOut<F>,
+    insertions: [Option<&NoteInsertion<F>>; 2],
+) -> Result<ProvenCircuitOutput<F, C, D>>
 where
     InnerC::Hasher: AlgebraicHasher<F>,
 {
-    let (inner_proof1, inner_vd1, inner_cd1) = inner1;
-    let (inner_proof2, inner_vd2, inner_cd2) = inner2;
-
     let mut pw = PartialWitness::new();
 
-    pw.set_proof_with_pis_target(&wiring.pt1, &inner_proof1);
-    pw.set_verifier_data_target(&wiring.vc1, &inner_vd1);
-    pw.set_proof_with_pis_target(&wiring.pt2, inner_proof2);
-    pw.set_verifier_data_target(&wiring.vc2, inner_vd2);
+    pw.set_proof_with_pis_target(&wiring.pt1, inner1.proof());
+    pw.set_verifier_data_target(&wiring.vc1, inner1.verifier_only());
+    pw.set_proof_with_pis_target(&wiring.pt2, inner2.proof());
+    pw.set_verifier_data_target(&wiring.vc2, inner2.verifier_only());
+    pw.set_hash_target(wiring.nullifier_accumulator, nullifier_accumulator);
+    for (target, insertion) in wiring.insertions.iter().zip(insertions) {
+        match (target, insertion) {
+            (Some(target), Some(insertion)) => {
+                pw.set_hash_target(target.old_root, insertion.old_root);
+                pw.set_hash_target(target.new_root, insertion.new_root);
+                pw.set_target(target.index, F::from_canonical_usize(insertion.index));
+                pw.set_merkle_proof_target(&target.merkle_proof, &insertion.merkle_proof);
+            }
+            (None, None) => {}
+            _ => return Err(anyhow!("the note insertions don't match the inner proofs")),
+        }
+    }
 
     let mut timing = TimingTree::new("prove", Level::Debug);
     let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
@@ -297,5 +1091,9 @@ where
 
     data.verify(proof.clone())?;
 
-    Ok((proof, data.verifier_only, data.common))
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only.clone(),
+        data.common.clone(),
+    ))
 }
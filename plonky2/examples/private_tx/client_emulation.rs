@@ -1,157 +1,1126 @@
+use std::cell::OnceCell;
 use std::sync::Arc;
+use std::{iter, mem};
 
-use anyhow::Result;
-use plonky2::hash::hash_types::{HashOut, RichField};
-use plonky2::hash::merkle_tree::{MerkleCap, MerkleTree};
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::plonk::circuit_data::CircuitConfig;
-use plonky2::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
-use plonky2_field::goldilocks_field::GoldilocksField;
-use plonky2_field::types::Field;
+use anyhow::{anyhow, ensure, Result};
+use maybe_rayon::*;
+use plonky2::plonk::proof_format;
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
+use plonky2::prelude::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 
+use crate::admission::{sender_id, SenderId, Submission};
+use crate::amount::TokenAmount;
 use crate::circuit;
-use crate::circuit::{PrivateWitness, ProofTuple, PublicInputs};
+use crate::circuit::{
+    note_leaf, note_owner, AuditPublicInputs, AuditWiringTarget, AuditWitness, PrivateWitness,
+    PublicInputs, Timelock, WiringTarget,
+};
+use crate::hd_keys;
+use crate::proof_cache::{witness_commitment, ProofCache};
+use crate::remote_proving::{DelegatedWitness, ProvingRequest, RemoteProver};
 use crate::server_emulation::Server;
+use crate::signing::SigningKey;
 use crate::state::State;
+use crate::viewing::{NoteMemo, NoteOpening, ViewingKey};
 
-pub struct Client {
-    state: State,
+/// The number of one-time keys `Client::restore` tries to match a note's owner tag against.
+const MAX_RESTORED_NONCE: u64 = 1 << 16;
+
+/// The position of an account among a client's accounts, in the order they were added.
+pub type AccountId = usize;
+
+/// The change note of a transaction, once it's accepted.
+struct NewNote {
+    account: AccountId,
+    // the index of the note the transaction spends
+    spent_index: usize,
+    key: [GoldilocksField; 4],
+    nonce: u64,
+    balance: TokenAmount,
+}
+
+/// A transaction queued by `Client::queue_split`, to be proven along with the others.
+struct QueuedTransaction {
+    public_inputs: PublicInputs<GoldilocksField>,
+    witness: PrivateWitness<GoldilocksField>,
+    new_note: NewNote,
+}
+
+/// A note held by an account.
+struct HeldNote {
+    index: usize,
+    // the spend key the note commits to: the account's priv_key for the notes it was given, a
+    // one-time key derived from it for the notes the client makes
+    key: [GoldilocksField; 4],
+    timelock: Timelock,
+    balance: TokenAmount,
+}
+
+/// A long-term key and the notes of one token held with it. Accounts are independent: each has its
+/// own viewing key, and its notes can only be spent with its key.
+struct Account {
     //mock, this should be from server
     priv_key: [GoldilocksField; 4],
     token_id: GoldilocksField,
-    balance: u64,
-    priv_index: usize,
-    config: CircuitConfig,
-    tree_height: usize,
+    notes: Vec<HeldNote>,
+    next_note_nonce: u64,
+}
+
+impl Account {
+    /// The one-time spend key of the account's note with nonce `nonce`. It can only spend that
+    /// note, so it can be shown to a delegated prover without revealing `priv_key`.
+    fn one_time_key(&self, nonce: u64) -> [GoldilocksField; 4] {
+        PoseidonHash::hash_no_pad(
+            &[
+                self.priv_key.as_slice(),
+                &[GoldilocksField::from_canonical_u64(nonce)],
+            ]
+            .concat(),
+        )
+        .elements
+    }
+
+    fn balance(&self) -> Result<TokenAmount> {
+        self.notes
+            .iter()
+            .try_fold(TokenAmount::ZERO, |sum, note| sum.checked_add(note.balance))
+    }
+}
+
+/// What a client needs to spend, and which a view-only client doesn't have: the keys and notes of
+/// its accounts and the circuit their spends are proven with.
+pub struct SpendCapability {
+    accounts: Vec<Account>,
+    // the account `split_and_submit` and the delegated spends spend from
+    selected: AccountId,
+    // the change notes of the transactions handed to a delegated prover or submitted to the
+    // mempool, with their leaves, until they're found on the server
+    pending_notes: Vec<(HashOut<GoldilocksField>, NewNote)>,
+    // transactions built by `queue_split` and not proven yet, against distinct notes
+    queued: Vec<QueuedTransaction>,
+    // signs the client's submissions, and identifies it to the server's admission policy
+    sender_key: SigningKey,
+    fee: u64,
+    // built once and reused for every transaction
+    circuit_data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    wiring: WiringTarget,
+    // the proofs of transactions the server hasn't accepted yet, for retries
+    proof_cache: ProofCache,
+}
+
+/// The capability of a client made by `Client::new_view_only`, which can't spend.
+pub struct ViewOnly;
+
+/// A note found with a viewing key, see `Client::scan_notes`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ViewedNote {
+    /// The account whose viewing key found the note.
+    pub account: AccountId,
+    pub index: usize,
+    pub note: NoteOpening,
+}
+
+/// A client of the server. The default, `Client<SpendCapability>`, holds notes in one or more
+/// accounts and spends them; a `Client<ViewOnly>` only finds the notes of a viewing key and proves
+/// what they hold, as spending methods are only defined for the former.
+pub struct Client<S = SpendCapability> {
+    state: State,
+    // one per account
+    viewing_keys: Vec<ViewingKey>,
+    // the server's epoch, as of the last `get_state_from_server`
+    current_epoch: u32,
+    // built by the first audit proof and reused
+    audit_circuit: OnceCell<(
+        CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        AuditWiringTarget,
+    )>,
+    spend: S,
+}
+
+/// The client's view of the server's state until its first `get_state_from_server`.
+fn empty_state() -> State {
+    State {
+        private_utxo_tree: MerkleTree {
+            leaves: vec![],
+            digests: vec![],
+            cap: MerkleCap(vec![]),
+        },
+        next_index_utxo: 0,
+        nullify_utxo_tree: MerkleTree {
+            leaves: vec![],
+            digests: vec![],
+            cap: MerkleCap(vec![]),
+        },
+        next_index_nullify: 0,
+        merkle_cap_height: 0,
+        epoch_size: None,
+        epoch_tree: MerkleTree {
+            leaves: vec![],
+            digests: vec![],
+            cap: MerkleCap(vec![]),
+        },
+        num_archived_epochs: 0,
+        archived_nullifiers: Default::default(),
+    }
+}
+
+impl<S> Client<S> {
+    pub fn get_state_from_server(&mut self, server: &Server) {
+        self.state = server.get_state();
+        self.current_epoch = server.current_epoch();
+    }
+
+    /// The key with which the notes of `account` can be found, e.g. to hand to
+    /// `Client::new_view_only`.
+    pub fn viewing_key(&self, account: AccountId) -> Option<ViewingKey> {
+        self.viewing_keys.get(account).copied()
+    }
+
+    /// The unspent notes whose memos on `server` are encrypted under one of the client's viewing
+    /// keys, as of the last `get_state_from_server`.
+    pub fn scan_notes(&self, server: &Server) -> Vec<ViewedNote> {
+        server
+            .memos()
+            .iter()
+            .filter_map(|memo| {
+                let (account, note) = self
+                    .viewing_keys
+                    .iter()
+                    .enumerate()
+                    .find_map(|(account, key)| Some((account, memo.open(key)?)))?;
+                let index = self.state.find_private_utxo(&memo.leaf)?;
+                // The nullifier of a note is its leaf.
+                (!self.state.is_nullified(memo.leaf)).then_some(ViewedNote {
+                    account,
+                    index,
+                    note,
+                })
+            })
+            .collect()
+    }
+
+    /// The amount of `token_id` held in the notes found by `scan_notes`, in all accounts.
+    pub fn balance(&self, server: &Server, token_id: GoldilocksField) -> Result<TokenAmount> {
+        self.scan_notes(server)
+            .iter()
+            .filter(|viewed| viewed.note.token_id == token_id)
+            .try_fold(TokenAmount::ZERO, |sum, viewed| {
+                sum.checked_add(viewed.note.token_amount)
+            })
+    }
+
+    /// Proves that `viewed`, a note found by `scan_notes`, is in the current note tree and holds
+    /// its amount, without revealing its owner tag or timelock. The auditor reads the root, leaf
+    /// and amount from the proof's `AuditPublicInputs`, and checks them against the server. The
+    /// leaf is public, so the auditor can also look up the note's position in the tree.
+    pub fn audit_proof(
+        &self,
+        viewed: &ViewedNote,
+    ) -> Result<ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+        let (circuit_data, wiring) = self.audit_circuit.get_or_init(|| {
+            let config = CircuitConfig::standard_recursion_config();
+            circuit::note_audit_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(&config, 10)
+        });
+        let public_input = AuditPublicInputs {
+            merkle_root_value: self.state.private_utxo_tree.cap.0[0],
+            leaf_value: viewed.note.leaf(),
+            token_id: viewed.note.token_id,
+            token_amount: viewed.note.token_amount.to_field(),
+        };
+        let witness = AuditWitness {
+            owner: viewed.note.owner,
+            timelock: viewed.note.timelock,
+            index: viewed.index,
+            merkle_proof: self.state.private_utxo_merkle_proof(viewed.index),
+        };
+        circuit::gen_audit_proof(circuit_data, public_input, witness, wiring)
+    }
+}
+
+impl Client<ViewOnly> {
+    /// A client which finds the notes whose memos are encrypted under `viewing_key`, e.g. for a
+    /// custodian monitoring a wallet, but can't spend them.
+    pub fn new_view_only(viewing_key: ViewingKey) -> Self {
+        Self {
+            state: empty_state(),
+            viewing_keys: vec![viewing_key],
+            current_epoch: 0,
+            audit_circuit: OnceCell::new(),
+            spend: ViewOnly,
+        }
+    }
 }
 
 impl Client {
-    //state must include a leaf with priv_key
+    /// Samples a long-term private key from `rng`, e.g. `OsRng` or a source backed by a hardware
+    /// security module.
+    pub fn generate_key<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> [GoldilocksField; 4] {
+        GoldilocksField::sample_secret(rng)
+    }
+
+    /// A client with no accounts, with the transaction circuit built.
+    fn without_accounts() -> Self {
+        const D: usize = 2;
+        let config = CircuitConfig::standard_recursion_config();
+        let tree_height = 10;
+        let (circuit_data, wiring) = circuit::private_tx_circuit::<
+            GoldilocksField,
+            PoseidonGoldilocksConfig,
+            D,
+        >(&config, tree_height);
+
+        Self {
+            state: empty_state(),
+            viewing_keys: vec![],
+            current_epoch: 0,
+            audit_circuit: OnceCell::new(),
+            spend: SpendCapability {
+                accounts: vec![],
+                selected: 0,
+                pending_notes: vec![],
+                queued: vec![],
+                sender_key: SigningKey::new(&mut OsRng),
+                fee: 0,
+                circuit_data,
+                wiring,
+                proof_cache: ProofCache::default(),
+            },
+        }
+    }
+
+    /// A client with a single account, which holds the note of `token_id` at `priv_index`.
     pub fn new(
         priv_key: [GoldilocksField; 4],
         token_id: GoldilocksField,
-        balance: u64,
+        balance: TokenAmount,
         priv_index: usize,
     ) -> Self {
-        Self {
-            state: State {
-                private_utxo_tree: MerkleTree {
-                    leaves: vec![],
-                    digests: vec![],
-                    cap: MerkleCap(vec![]),
-                },
-                next_index_utxo: 0,
-                nullify_utxo_tree: MerkleTree {
-                    leaves: vec![],
-                    digests: vec![],
-                    cap: MerkleCap(vec![]),
-                },
-                next_index_nullify: 0,
-                merkle_cap_height: 0,
-            },
+        let mut client = Self::without_accounts();
+        client.add_account(priv_key, token_id, balance, priv_index);
+        client
+    }
+
+    /// Adds an account with key `priv_key`, holding the note of `token_id` at `priv_index`, and
+    /// returns its id. The spending methods keep spending from the selected account.
+    pub fn add_account(
+        &mut self,
+        priv_key: [GoldilocksField; 4],
+        token_id: GoldilocksField,
+        balance: TokenAmount,
+        priv_index: usize,
+    ) -> AccountId {
+        self.spend.accounts.push(Account {
             priv_key,
             token_id,
+            notes: vec![HeldNote {
+                index: priv_index,
+                key: priv_key,
+                timelock: Timelock::default(),
+                balance,
+            }],
+            next_note_nonce: 0,
+        });
+        self.viewing_keys
+            .push(ViewingKey::from_private_key(priv_key));
+        self.spend.accounts.len() - 1
+    }
+
+    /// Adds the note at `priv_index`, committing to the long-term key of `account`, to the notes
+    /// the account holds, e.g. a second payment it's given.
+    pub fn add_note(
+        &mut self,
+        account: AccountId,
+        balance: TokenAmount,
+        priv_index: usize,
+    ) -> Result<()> {
+        let account = self.account_mut(account)?;
+        let key = account.priv_key;
+        account.notes.push(HeldNote {
+            index: priv_index,
+            key,
+            timelock: Timelock::default(),
             balance,
-            priv_index,
-            config: CircuitConfig::standard_recursion_config(),
-            tree_height: 10,
+        });
+        Ok(())
+    }
+
+    /// Restores accounts `0..num_accounts` of the wallet with seed phrase `phrase`, see `hd_keys`,
+    /// with their unspent notes of `token_id` on `server`. The notes are found from their memos,
+    /// so the client must have published the memos of the notes its accounts were given.
+    pub fn restore(
+        phrase: &str,
+        num_accounts: u32,
+        token_id: GoldilocksField,
+        server: &Server,
+    ) -> Result<Self> {
+        let seed = hd_keys::from_seed_phrase(phrase)?;
+        let mut client = Self::without_accounts();
+        for account in 0..num_accounts {
+            let priv_key = hd_keys::account_key(&seed, account)?;
+            client.spend.accounts.push(Account {
+                priv_key,
+                token_id,
+                notes: vec![],
+                next_note_nonce: 0,
+            });
+            client
+                .viewing_keys
+                .push(ViewingKey::from_private_key(priv_key));
         }
+        client.resync(server)?;
+        Ok(client)
     }
 
-    pub fn get_state_from_server(&mut self, server: &Server) {
-        self.state = server.get_state()
+    /// Brings the notes of the client's accounts in line with `server`: drops those which aren't
+    /// in its note tree or are spent, and takes on the unspent notes whose memos it finds. E.g.
+    /// after a rollback, this takes back the notes spent by reverted transactions, provided their
+    /// memos were published, and drops the notes those transactions made.
+    pub fn resync(&mut self, server: &Server) -> Result<()> {
+        self.get_state_from_server(server);
+        self.spend.pending_notes.clear();
+        self.spend.queued.clear();
+        // The cached proofs are against roots which may no longer exist.
+        self.spend.proof_cache.clear();
+        let state = &self.state;
+        for account in &mut self.spend.accounts {
+            let token_id = account.token_id;
+            account.notes.retain(|note| {
+                let leaf = note_leaf(note.key, note.timelock, token_id, note.balance.to_field());
+                state.find_private_utxo(&leaf) == Some(note.index) && !state.is_nullified(leaf)
+            });
+        }
+
+        for viewed in self.scan_notes(server) {
+            let account = &mut self.spend.accounts[viewed.account];
+            if viewed.note.token_id != account.token_id
+                || account.notes.iter().any(|note| note.index == viewed.index)
+            {
+                continue;
+            }
+            // The note commits to the long-term key or to the one-time key of some nonce, and the
+            // account's next note uses the nonce after the last one it used.
+            let key = if note_owner(account.priv_key) == viewed.note.owner {
+                account.priv_key
+            } else {
+                let (key, nonce) = (0..MAX_RESTORED_NONCE)
+                    .map(|nonce| (account.one_time_key(nonce), nonce))
+                    .find(|(key, _)| note_owner(*key) == viewed.note.owner)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "The key of note {} isn't derived from account {}",
+                            viewed.index,
+                            viewed.account
+                        )
+                    })?;
+                account.next_note_nonce = account.next_note_nonce.max(nonce + 1);
+                key
+            };
+            account.notes.push(HeldNote {
+                index: viewed.index,
+                key,
+                timelock: viewed.note.timelock,
+                balance: viewed.note.token_amount,
+            });
+        }
+        Ok(())
     }
 
-    pub fn split_and_submit(&mut self, delta: u64, server: &mut Server) -> Result<()> {
-        const D: usize = 2;
+    fn account(&self, account: AccountId) -> Result<&Account> {
+        self.spend
+            .accounts
+            .get(account)
+            .ok_or_else(|| anyhow!("no account {account}"))
+    }
 
-        assert!(delta <= self.balance, "can't split more than what you have");
-        let old_private_tree_hash = PoseidonHash::hash_no_pad(
-            &[
-                self.priv_key,
-                [
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    self.token_id,
-                    GoldilocksField::from_canonical_u64(self.balance),
-                ],
-            ]
-            .concat(),
+    fn account_mut(&mut self, account: AccountId) -> Result<&mut Account> {
+        self.spend
+            .accounts
+            .get_mut(account)
+            .ok_or_else(|| anyhow!("no account {account}"))
+    }
+
+    /// Makes `split_and_submit`, `split_and_submit_remote` and `prepare_delegated_witness` spend
+    /// from `account`. A new client spends from its first account.
+    pub fn select_account(&mut self, account: AccountId) -> Result<()> {
+        self.account(account)?;
+        self.spend.selected = account;
+        Ok(())
+    }
+
+    /// The amount held in the notes of `account`.
+    pub fn account_balance(&self, account: AccountId) -> Result<TokenAmount> {
+        self.account(account)?.balance()
+    }
+
+    /// The amount of `token_id` held in the notes of all accounts.
+    pub fn total_balance(&self, token_id: GoldilocksField) -> Result<TokenAmount> {
+        self.spend
+            .accounts
+            .iter()
+            .filter(|account| account.token_id == token_id)
+            .try_fold(TokenAmount::ZERO, |sum, account| {
+                sum.checked_add(account.balance()?)
+            })
+    }
+
+    /// Sets the timelock of the notes the selected account was given, e.g. a vesting payment, which
+    /// the client is given along with the notes. Its change notes aren't timelocked.
+    pub fn set_note_timelock(&mut self, timelock: Timelock) {
+        let account = &mut self.spend.accounts[self.spend.selected];
+        let priv_key = account.priv_key;
+        for note in account.notes.iter_mut().filter(|note| note.key == priv_key) {
+            note.timelock = timelock;
+        }
+    }
+
+    /// Offers `fee` with later submissions.
+    pub fn set_fee(&mut self, fee: u64) {
+        self.spend.fee = fee;
+    }
+
+    /// The sender the server's admission policy knows the client as, e.g. to register it with
+    /// `TokenBucketPolicy::allowed_senders`.
+    pub fn sender(&self) -> SenderId {
+        sender_id(&self.spend.sender_key.public_key())
+    }
+
+    /// A submission of the transaction with `public_inp`, offering the client's fee.
+    fn submission(&self, public_inp: &PublicInputs<GoldilocksField>) -> Submission {
+        Submission::sign(
+            public_inp,
+            self.spend.fee,
+            &self.spend.sender_key,
+            &mut OsRng,
+        )
+    }
+
+    /// Proves later transactions with `circuit_data`, e.g. once the server has upgraded to it.
+    pub fn set_circuit(
+        &mut self,
+        circuit_data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        wiring: WiringTarget,
+    ) {
+        self.spend.circuit_data = circuit_data;
+        self.spend.wiring = wiring;
+    }
+
+    /// The memo of `note` of `account`, under the account's viewing key.
+    fn note_memo(&self, account: AccountId, note: &HeldNote) -> NoteMemo {
+        let opening = NoteOpening {
+            owner: note_owner(note.key),
+            timelock: note.timelock,
+            token_id: self.spend.accounts[account].token_id,
+            token_amount: note.balance,
+        };
+        NoteMemo::new(&self.viewing_keys[account], &opening)
+    }
+
+    /// Publishes the memos of the notes the client's accounts were given on `server`, for its
+    /// view-only clients. The memos of change notes are published as they are taken on; those of
+    /// the initial notes are only published by calling this.
+    pub fn publish_note_memo(&self, server: &mut Server) -> Result<()> {
+        for (id, account) in self.spend.accounts.iter().enumerate() {
+            for note in account
+                .notes
+                .iter()
+                .filter(|note| note.key == account.priv_key)
+            {
+                server.publish_memo(self.note_memo(id, note))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The position among the notes of `account` of the note to spend `amount` from: the smallest
+    /// which holds it and isn't timelocked. Delegated spends can only spend notes committing to
+    /// one-time keys, see `prepare_delegated_witness`.
+    fn select_note(
+        &self,
+        account: AccountId,
+        amount: TokenAmount,
+        delegated: bool,
+    ) -> Result<usize> {
+        let holder = self.account(account)?;
+        holder
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| {
+                note.balance >= amount
+                    && note.timelock.is_open_at(self.current_epoch)
+                    && !(delegated && note.key == holder.priv_key)
+                    && !self.is_being_spent(account, note.index)
+            })
+            .min_by_key(|(_, note)| note.balance)
+            .map(|(position, _)| position)
+            .ok_or_else(|| {
+                anyhow!(
+                    "account {} has no note holding {} which it can spend in epoch {}{}",
+                    account,
+                    amount,
+                    self.current_epoch,
+                    if delegated {
+                        " with a one-time key; spend notes bound to the long-term key locally"
+                    } else {
+                        ""
+                    }
+                )
+            })
+    }
+
+    /// The change notes of the transactions queued or pending.
+    fn unapplied_notes(&self) -> impl Iterator<Item = &NewNote> {
+        self.spend
+            .queued
+            .iter()
+            .map(|transaction| &transaction.new_note)
+            .chain(
+                self.spend
+                    .pending_notes
+                    .iter()
+                    .map(|(_, new_note)| new_note),
+            )
+    }
+
+    /// Whether a transaction queued or pending spends the note at `index` of `account`.
+    fn is_being_spent(&self, account: AccountId, index: usize) -> bool {
+        self.unapplied_notes()
+            .any(|new_note| new_note.account == account && new_note.spent_index == index)
+    }
+
+    /// The nonce of the next change note of `account`, after those of the transactions queued or
+    /// pending.
+    fn next_note_nonce(&self, account: AccountId) -> Result<u64> {
+        let next_nonce = self.account(account)?.next_note_nonce;
+        Ok(self
+            .unapplied_notes()
+            .filter(|new_note| new_note.account == account)
+            .map(|new_note| new_note.nonce + 1)
+            .fold(next_nonce, u64::max))
+    }
+
+    /// The public inputs and witness of a transaction spending the note at `position` of `account`,
+    /// with the change note, which holds `new_balance` and commits to a fresh one-time key.
+    fn spend_witness(
+        &self,
+        account: AccountId,
+        position: usize,
+        new_balance: TokenAmount,
+    ) -> Result<(
+        PublicInputs<GoldilocksField>,
+        PrivateWitness<GoldilocksField>,
+        NewNote,
+    )> {
+        let holder = self.account(account)?;
+        let note = &holder.notes[position];
+        let nonce = self.next_note_nonce(account)?;
+        let new_note = NewNote {
+            account,
+            spent_index: note.index,
+            key: holder.one_time_key(nonce),
+            nonce,
+            balance: new_balance,
+        };
+        ensure!(
+            note.timelock.is_open_at(self.current_epoch),
+            "the note is timelocked in epoch {}",
+            self.current_epoch
+        );
+        let old_private_tree_hash = note_leaf(
+            note.key,
+            note.timelock,
+            holder.token_id,
+            note.balance.to_field(),
         );
-        let merkle_proof = self.state.private_utxo_merkle_proof(self.priv_index);
+        let merkle_proof = self.state.private_utxo_merkle_proof(note.index);
         let old_root = self.state.private_utxo_tree.cap.0[0];
-        let new_private_tree_hash = PoseidonHash::hash_no_pad(
-            &[
-                self.priv_key,
-                [
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    self.token_id,
-                    GoldilocksField::from_canonical_u64(self.balance - delta),
-                ],
-            ]
-            .concat(),
+        let new_private_tree_hash = note_leaf(
+            new_note.key,
+            Timelock::default(),
+            holder.token_id,
+            new_note.balance.to_field(),
         );
-        //TODO: Credit an account
         let p_witness = PrivateWitness {
-            private_key: self.priv_key,
-            index: self.priv_index,
-            token_id: self.token_id,
-            token_amount: GoldilocksField::from_canonical_u64(self.balance),
+            private_key: note.key,
+            index: note.index,
+            token_id: holder.token_id,
+            token_amount: note.balance.to_field(),
+            timelock: note.timelock,
             merkle_proof,
         };
         let public_inp = PublicInputs {
             nullifier_value: old_private_tree_hash,
             merkle_root_value: old_root,
             new_leaf_value: new_private_tree_hash,
+            current_epoch: self.current_epoch,
         };
 
         println!(
             "{:?} {:?} {:?}",
             p_witness.token_amount, public_inp.nullifier_value, public_inp.new_leaf_value
         );
+        Ok((public_inp, p_witness, new_note))
+    }
+
+    /// The public inputs and witness of a transaction splitting `delta` off a note of the selected
+    /// account, with the change note.
+    fn split_witness(
+        &self,
+        delta: TokenAmount,
+        delegated: bool,
+    ) -> Result<(
+        PublicInputs<GoldilocksField>,
+        PrivateWitness<GoldilocksField>,
+        NewNote,
+    )> {
+        let account = self.spend.selected;
+        let position = self.select_note(account, delta, delegated)?;
+        let new_balance = self.account(account)?.notes[position]
+            .balance
+            .checked_sub(delta)?;
+        self.spend_witness(account, position, new_balance)
+    }
+
+    /// Replaces the note `new_note` spends, among the notes of its account, with `new_note`, at
+    /// `index`, and publishes its memo.
+    fn use_note(&mut self, index: usize, new_note: NewNote, server: &mut Server) -> Result<()> {
+        let account = &mut self.spend.accounts[new_note.account];
+        account
+            .notes
+            .retain(|note| note.index != new_note.spent_index);
+        let note = HeldNote {
+            index,
+            key: new_note.key,
+            timelock: Timelock::default(),
+            balance: new_note.balance,
+        };
+        account.next_note_nonce = account.next_note_nonce.max(new_note.nonce + 1);
+        let memo = self.note_memo(new_note.account, &note);
+        self.spend.accounts[new_note.account].notes.push(note);
+        self.get_state_from_server(server);
+        server.publish_memo(memo)
+    }
+
+    /// Sends `proof` to `server` in a format it reads, so that either side can upgrade first, and
+    /// returns the proof as the server decodes it.
+    fn send_proof(
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        server: &Server,
+    ) -> Result<ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+        let version = proof_format::negotiate_version(server.proof_format_versions())
+            .ok_or_else(|| anyhow!("the server reads none of our proof formats"))?;
+        let bytes = proof.proof().to_versioned_bytes_as(version)?;
+        server.decode_proof(&proof.verifier_only().circuit_digest, &bytes)
+    }
+
+    fn submit(
+        &mut self,
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        public_inp: PublicInputs<GoldilocksField>,
+        new_note: NewNote,
+        server: &mut Server,
+    ) -> Result<()> {
+        let proof = Self::send_proof(proof, server)?;
+
+        // //  re-update state
+        let submission = self.submission(&public_inp);
+        let index = server.verify_and_update_state(&submission, proof, public_inp)?;
+        self.use_note(index, new_note, server)
+    }
+
+    /// Proves a transaction locally, unless a proof of it is cached, e.g. from an attempt the
+    /// server rejected. The proof is cached until `proof_accepted`, or until a rejection shows the
+    /// server can no longer accept it, see `evict_stale_proofs`.
+    fn prove(
+        &mut self,
+        public_inp: &PublicInputs<GoldilocksField>,
+        p_witness: PrivateWitness<GoldilocksField>,
+    ) -> Result<(
+        HashOut<GoldilocksField>,
+        ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    )> {
+        let commitment = witness_commitment(
+            self.spend.circuit_data.verifier_only.circuit_digest,
+            public_inp,
+            &p_witness,
+        );
+        if let Some(proof) = self.spend.proof_cache.get(&commitment) {
+            return Ok((commitment, proof.clone()));
+        }
 
         //Generate a proof of our privateTX
-        let (circuit_data, wiring) = circuit::private_tx_circuit::<
-            GoldilocksField,
-            PoseidonGoldilocksConfig,
-            D,
-        >(&self.config, self.tree_height);
-        let proof = circuit::gen_private_proof::<GoldilocksField, PoseidonGoldilocksConfig, D>(
-            circuit_data,
+        let proof = circuit::gen_private_proof::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+            &self.spend.circuit_data,
             public_inp.clone(),
             p_witness,
-            wiring,
+            &self.spend.wiring,
         )?;
+        self.spend.proof_cache.insert(commitment, proof.clone());
+        Ok((commitment, proof))
+    }
 
-        // //  re-update state
-        self.priv_index = server
-            .verify_and_update_state(proof, public_inp.clone())
-            .unwrap();
-        self.balance = self.balance - delta;
-        self.get_state_from_server(server);
+    /// Drops the cached proof with key `commitment`, once the server has accepted it.
+    fn proof_accepted(&mut self, commitment: &HashOut<GoldilocksField>) {
+        self.spend.proof_cache.remove(commitment);
+    }
 
+    /// Drops the cached proofs the server can no longer accept, once it has rejected a submission:
+    /// those of retired circuits, against roots it no longer has or spending spent notes. The
+    /// others, e.g. of a submission an admission policy turned away, are kept for a retry.
+    fn evict_stale_proofs(&mut self, server: &Server) {
+        self.spend.proof_cache.retain(|proof| {
+            server.accepts_circuit(&proof.verifier_only.circuit_digest)
+                && PublicInputs::from_public_inputs(proof.public_inputs())
+                    .is_ok_and(|public_inp| server.check_transaction(&public_inp).is_ok())
+        });
+    }
+
+    /// The proofs of the transactions the server hasn't accepted yet, to keep in a local store
+    /// with `ProofCache::to_bytes`.
+    pub fn proof_cache(&self) -> &ProofCache {
+        &self.spend.proof_cache
+    }
+
+    /// Restores the proofs cached by an earlier run, as `ProofCache::to_bytes`, so that the
+    /// transactions it failed to submit are retried without proving.
+    pub fn restore_proof_cache(&mut self, bytes: &[u8]) -> Result<()> {
+        self.spend.proof_cache = ProofCache::from_bytes(bytes, &self.spend.circuit_data)?;
         Ok(())
+    }
+
+    /// Proves a transaction locally and submits it.
+    fn prove_and_submit(
+        &mut self,
+        (public_inp, p_witness, new_note): (
+            PublicInputs<GoldilocksField>,
+            PrivateWitness<GoldilocksField>,
+            NewNote,
+        ),
+        server: &mut Server,
+    ) -> Result<()> {
+        let (commitment, proof) = self.prove(&public_inp, p_witness)?;
         //We don't need to verify this. let's the server do it.
+        if let Err(err) = self.submit(proof, public_inp, new_note, server) {
+            self.evict_stale_proofs(server);
+            return Err(err);
+        }
+        self.proof_accepted(&commitment);
+        Ok(())
+    }
+
+    /// Splits `delta` off a note of the selected account, see `select_account`.
+    pub fn split_and_submit(&mut self, delta: TokenAmount, server: &mut Server) -> Result<()> {
+        let transaction = self.split_witness(delta, false)?;
+        self.prove_and_submit(transaction, server)
+    }
+
+    /// Like `split_and_submit`, but submits the transaction to the server's mempool, to be applied
+    /// with the next batch. Call `complete_pending` once the batch has been applied.
+    pub fn split_and_submit_to_mempool(
+        &mut self,
+        delta: TokenAmount,
+        server: &mut Server,
+    ) -> Result<()> {
+        ensure!(
+            self.spend.pending_notes.is_empty(),
+            "a transaction is already pending"
+        );
+        let (public_inp, p_witness, new_note) = self.split_witness(delta, false)?;
+        let (commitment, proof) = self.prove(&public_inp, p_witness)?;
+        let leaf = public_inp.new_leaf_value;
+        let submission = self.submission(&public_inp);
+        let result = Self::send_proof(proof, server)
+            .and_then(|proof| server.submit_to_mempool(&submission, proof, public_inp));
+        if let Err(err) = result {
+            self.evict_stale_proofs(server);
+            return Err(err);
+        }
+        self.proof_accepted(&commitment);
+        self.spend.pending_notes.push((leaf, new_note));
+        Ok(())
+    }
+
+    /// Builds a transaction splitting `delta` off a note of the selected account, as
+    /// `split_and_submit` does, and queues it for `prove_queued`. Each queued transaction spends
+    /// a different note, so that they can all be applied in one batch.
+    pub fn queue_split(&mut self, delta: TokenAmount) -> Result<()> {
+        let (public_inputs, witness, new_note) = self.split_witness(delta, false)?;
+        self.spend.queued.push(QueuedTransaction {
+            public_inputs,
+            witness,
+            new_note,
+        });
+        Ok(())
+    }
+
+    /// Proves the queued transactions in parallel, on the rayon thread pool, then submits them to the
+    /// server's mempool in the order they were queued. Call `complete_pending` once the batch has
+    /// been applied. If a submission fails, it and the transactions after it stay queued, and
+    /// their proofs cached unless the server can no longer accept them, so that calling this again
+    /// retries them without proving.
+    pub fn prove_queued(&mut self, server: &mut Server) -> Result<()> {
+        let queued = mem::take(&mut self.spend.queued);
+        let circuit_digest = self.spend.circuit_data.verifier_only.circuit_digest;
+        let commitments: Vec<_> = queued
+            .iter()
+            .map(|tx| witness_commitment(circuit_digest, &tx.public_inputs, &tx.witness))
+            .collect();
+
+        let (circuit_data, wiring) = (&self.spend.circuit_data, &self.spend.wiring);
+        let proof_cache = &self.spend.proof_cache;
+        let proofs: Vec<Result<_>> = queued
+            .par_iter()
+            .zip(commitments.par_iter())
+            .map(|(tx, commitment)| match proof_cache.get(commitment) {
+                Some(proof) => Ok(proof.clone()),
+                None => circuit::gen_private_proof::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                    circuit_data,
+                    tx.public_inputs.clone(),
+                    tx.witness.clone(),
+                    wiring,
+                ),
+            })
+            .collect();
+        for (commitment, proof) in commitments.iter().zip(&proofs) {
+            if let Ok(proof) = proof {
+                self.spend.proof_cache.insert(*commitment, proof.clone());
+            }
+        }
+
+        let mut transactions = queued.into_iter().zip(commitments).zip(proofs);
+        while let Some(((tx, commitment), proof)) = transactions.next() {
+            let result = proof.and_then(|proof| {
+                let proof = Self::send_proof(proof, server)?;
+                let submission = self.submission(&tx.public_inputs);
+                server.submit_to_mempool(&submission, proof, tx.public_inputs.clone())
+            });
+            if let Err(err) = result {
+                self.spend.queued = iter::once(tx)
+                    .chain(transactions.map(|((tx, _), _)| tx))
+                    .collect();
+                self.evict_stale_proofs(server);
+                return Err(err);
+            }
+            self.proof_accepted(&commitment);
+            self.spend
+                .pending_notes
+                .push((tx.public_inputs.new_leaf_value, tx.new_note));
+        }
+        Ok(())
+    }
+
+    /// Checks whether `split_and_submit` would go through, without proving: that the selected
+    /// account has a note to split `delta` off, that the witness satisfies the circuit, and that
+    /// the server would accept the transaction's public inputs, e.g. that the client's root isn't
+    /// stale. Returns the public inputs the proof would have.
+    pub fn simulate(
+        &self,
+        delta: TokenAmount,
+        server: &Server,
+    ) -> Result<PublicInputs<GoldilocksField>> {
+        let (public_inp, p_witness, _) = self.split_witness(delta, false)?;
+        circuit::check_private_tx(
+            &self.spend.circuit_data,
+            &public_inp,
+            &p_witness,
+            &self.spend.wiring,
+        )?;
+        server.check_transaction(&public_inp)?;
+        Ok(public_inp)
+    }
+
+    /// Moves `amount` from account `from` to account `to`, which hold the same token, in two
+    /// transactions: the first splits `amount` off a note of `from`, the second replaces a note of
+    /// `to` with one holding `amount` more. `to` must already hold a note it can spend. Both are
+    /// proven against the current root and applied together by the server, so that neither goes
+    /// through without the other. As `private_tx_circuit` doesn't constrain the new leaf yet, only
+    /// the client ties the credit to the debit.
+    pub fn transfer(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        amount: TokenAmount,
+        server: &mut Server,
+    ) -> Result<()> {
+        ensure!(from != to, "can't transfer from account {from} to itself");
+        ensure!(
+            self.account(from)?.token_id == self.account(to)?.token_id,
+            "accounts {from} and {to} hold different tokens"
+        );
+        let debited = self.select_note(from, amount, false)?;
+        let credited = self.select_note(to, TokenAmount::ZERO, false)?;
+        let debited_balance = self.account(from)?.notes[debited]
+            .balance
+            .checked_sub(amount)?;
+        let credited_balance = self.account(to)?.notes[credited]
+            .balance
+            .checked_add(amount)?;
+        let legs = [
+            self.spend_witness(from, debited, debited_balance)?,
+            self.spend_witness(to, credited, credited_balance)?,
+        ];
+
+        // Nothing changes on either side until both legs are proven and accepted.
+        let mut commitments = vec![];
+        let mut new_notes = vec![];
+        let mut transactions = vec![];
+        for (public_inp, p_witness, new_note) in legs {
+            let (commitment, proof) = self.prove(&public_inp, p_witness)?;
+            let proof = Self::send_proof(proof, server)?;
+            transactions.push((self.submission(&public_inp), proof, public_inp));
+            commitments.push(commitment);
+            new_notes.push(new_note);
+        }
+        let new_indices = match server.verify_and_update_state_all(transactions) {
+            Ok(new_indices) => new_indices,
+            Err(err) => {
+                self.evict_stale_proofs(server);
+                return Err(err);
+            }
+        };
+        for ((index, new_note), commitment) in
+            new_indices.into_iter().zip(new_notes).zip(commitments)
+        {
+            self.proof_accepted(&commitment);
+            self.use_note(index, new_note, server)?;
+        }
+        Ok(())
+    }
+
+    /// Like `split_and_submit`, but has `prover` make the proof, sending it the witness encrypted to
+    /// its key.
+    pub fn split_and_submit_remote(
+        &mut self,
+        delta: TokenAmount,
+        prover: &RemoteProver,
+        server: &mut Server,
+    ) -> Result<()> {
+        let (public_inp, p_witness, new_note) = self.split_witness(delta, false)?;
+        let request = ProvingRequest::new(
+            &prover.public_key(),
+            public_inp.clone(),
+            &p_witness,
+            &mut OsRng,
+        );
+        drop(p_witness);
+
+        let proof = prover.prove(&request)?;
+        self.submit(proof, public_inp, new_note, server)
+    }
+
+    /// The witness of a transaction splitting `delta` off a note of the selected account, for a
+    /// prover which proves and submits it on the client's behalf. It holds the one-time key of the
+    /// note being spent, and the amounts, but not `priv_key`: the change note commits to a fresh
+    /// one-time key. Notes bound to `priv_key`, i.e. those the account was given, aren't spent this
+    /// way. Call `complete_delegated` once the transaction has been accepted.
+    pub fn prepare_delegated_witness(&mut self, delta: TokenAmount) -> Result<DelegatedWitness> {
+        ensure!(
+            self.spend.pending_notes.is_empty(),
+            "a transaction is already pending"
+        );
+        let (public_input, witness, new_note) = self.split_witness(delta, true)?;
+        self.spend
+            .pending_notes
+            .push((public_input.new_leaf_value, new_note));
+        Ok(DelegatedWitness {
+            public_input,
+            witness,
+        })
+    }
+
+    /// Takes on the change notes of the pending transactions, delegated or in the server's
+    /// mempool, once the server has applied them. Fails, keeping the transactions pending, if the
+    /// server doesn't have one of the notes yet.
+    pub fn complete_pending(&mut self, server: &mut Server) -> Result<()> {
+        ensure!(
+            !self.spend.pending_notes.is_empty(),
+            "no transaction is pending"
+        );
+        let indices = self
+            .spend
+            .pending_notes
+            .iter()
+            .map(|(leaf, _)| {
+                server
+                    .find_note(leaf)
+                    .ok_or_else(|| anyhow!("a pending transaction hasn't been applied"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (index, (_, new_note)) in indices
+            .into_iter()
+            .zip(mem::take(&mut self.spend.pending_notes))
+        {
+            self.use_note(index, new_note, server)?;
+        }
+        Ok(())
+    }
+
+    /// Takes on the change note of a delegated transaction once the server has accepted it, as
+    /// `complete_pending` does; kept for callers from before the mempool.
+    pub fn complete_delegated(&mut self, server: &mut Server) -> Result<()> {
+        self.complete_pending(server)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Account {
+    fn drop(&mut self) {
+        plonky2::util::zeroize::zeroize_field_elements(&mut self.priv_key);
+        for note in &mut self.notes {
+            plonky2::util::zeroize::zeroize_field_elements(&mut note.key);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use core::num::NonZeroU32;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
     use anyhow::Result;
+    use plonky2::hash::hash_types::HashOut;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
     use plonky2_field::goldilocks_field::GoldilocksField;
     use plonky2_field::types::{Field, Sample};
+    use rand::rngs::OsRng;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
+    use crate::admission::{Submission, TokenBucketPolicy};
+    use crate::amount::TokenAmount;
+    use crate::circuit;
+    use crate::circuit::{
+        note_leaf, ArchivedNoteWitness, AuditPublicInputs, PrivateWitness, PublicInputs, Timelock,
+    };
     use crate::client_emulation::Client;
+    use crate::hd_keys;
+    use crate::remote_proving::{ProvingRequest, RemoteProver};
     use crate::server_emulation::Server;
-    use crate::state::State;
+    use crate::signing::SigningKey;
+    use crate::state::{State, EPOCH_TREE_HEIGHT};
+    use crate::viewing::ViewingKey;
+
+    /// A submission of `public_inputs` by a fresh sender, offering no fee.
+    fn submission(public_inputs: &PublicInputs<GoldilocksField>) -> Submission {
+        Submission::sign(public_inputs, 0, &SigningKey::new(&mut OsRng), &mut OsRng)
+    }
 
     #[test]
     fn test_client_split() -> Result<()> {
         let tree_height = 10;
-        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        // A seeded generator makes the keys, hence the notes, reproducible.
+        let prive_key = Client::generate_key(&mut ChaCha20Rng::seed_from_u64(0));
+        assert_eq!(
+            prive_key,
+            Client::generate_key(&mut ChaCha20Rng::seed_from_u64(0))
+        );
         let token_id = GoldilocksField::from_canonical_u64(1);
-        let balance: u64 = 1000;
+        let balance = TokenAmount::new(1000)?;
         let (demoState, index) = State::new_demo_state(prive_key, token_id, balance, 10);
         let proof = demoState.private_utxo_tree.prove(index);
         let token_id = GoldilocksField(1);
@@ -159,7 +1128,696 @@ mod tests {
         let mut client = Client::new(prive_key, token_id, balance, 0);
         let mut server = Server::new(demoState.clone());
         client.get_state_from_server(&server);
-        client.split_and_submit(12, &mut server).unwrap();
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        // Splitting off more than the balance fails before proving.
+        assert!(client
+            .split_and_submit(TokenAmount::new(1000)?, &mut server)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_from_seed_phrase() -> Result<()> {
+        let phrase = hd_keys::generate_seed_phrase(&mut OsRng);
+        let prive_key = hd_keys::account_key(&hd_keys::from_seed_phrase(&phrase)?, 0)?;
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut server = Server::new(demoState);
+        // Notes are only found once their memos are published.
+        let restored = Client::restore(&phrase, 1, token_id, &server)?;
+        assert_eq!(restored.total_balance(token_id)?, TokenAmount::ZERO);
+
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        client.get_state_from_server(&server);
+        client.publish_note_memo(&mut server)?;
+        let restored = Client::restore(&phrase, 1, token_id, &server)?;
+        assert_eq!(restored.spend.accounts[0].notes[0].key, prive_key);
+        assert_eq!(restored.spend.accounts[0].next_note_nonce, 0);
+
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        client.split_and_submit(TokenAmount::new(13)?, &mut server)?;
+        let mut restored = Client::restore(&phrase, 2, token_id, &server)?;
+        assert_eq!(restored.account_balance(0)?, TokenAmount::new(975)?);
+        let note = &restored.spend.accounts[0].notes[0];
+        let original = &client.spend.accounts[0].notes[0];
+        assert_eq!((note.index, note.key), (original.index, original.key));
+        assert_eq!(restored.spend.accounts[0].next_note_nonce, 2);
+        restored.split_and_submit(TokenAmount::new(14)?, &mut server)?;
+
+        // Another account of the same wallet holds nothing.
+        assert_eq!(restored.account_balance(1)?, TokenAmount::ZERO);
+        Ok(())
+    }
+
+    #[test]
+    fn test_accounts() -> Result<()> {
+        let keys: [[GoldilocksField; 4]; 2] =
+            [GoldilocksField::rand_array(), GoldilocksField::rand_array()];
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let (mut state, index) =
+            State::new_demo_state(keys[0], token_id, TokenAmount::new(1000)?, 10);
+        let mut note_of = |balance| {
+            state
+                .add_private_utxo(note_leaf(
+                    keys[1],
+                    Timelock::default(),
+                    token_id,
+                    TokenAmount::new(balance).unwrap().to_field(),
+                ))
+                .unwrap()
+        };
+        let (large_index, small_index) = (note_of(500), note_of(20));
+
+        let mut client = Client::new(keys[0], token_id, TokenAmount::new(1000)?, index);
+        let second = client.add_account(keys[1], token_id, TokenAmount::new(500)?, large_index);
+        client.add_note(second, TokenAmount::new(20)?, small_index)?;
+        let mut server = Server::new(state);
+        client.get_state_from_server(&server);
+        client.publish_note_memo(&mut server)?;
+        assert_eq!(client.total_balance(token_id)?, TokenAmount::new(1520)?);
+        assert_eq!(
+            client.total_balance(GoldilocksField::TWO)?,
+            TokenAmount::ZERO
+        );
+
+        // The smallest note which holds the amount is spent.
+        client.select_account(second)?;
+        client.split_and_submit(TokenAmount::new(15)?, &mut server)?;
+        assert_eq!(client.account_balance(second)?, TokenAmount::new(505)?);
+        assert!(client.spend.accounts[second]
+            .notes
+            .iter()
+            .any(|note| note.index == large_index));
+
+        client.transfer(0, second, TokenAmount::new(100)?, &mut server)?;
+        assert_eq!(client.account_balance(0)?, TokenAmount::new(900)?);
+        assert_eq!(client.account_balance(second)?, TokenAmount::new(605)?);
+        assert_eq!(client.total_balance(token_id)?, TokenAmount::new(1505)?);
+        assert!(client
+            .transfer(0, second, TokenAmount::new(1000)?, &mut server)
+            .is_err());
+        assert!(client
+            .transfer(0, 0, TokenAmount::new(1)?, &mut server)
+            .is_err());
+        assert!(client.select_account(2).is_err());
+
+        // A transfer whose credit is rejected doesn't debit either.
+        server.set_admission_policy(TokenBucketPolicy::new(
+            1,
+            std::time::Duration::from_secs(3600),
+        ));
+        let num_events = server.events().len();
+        assert!(client
+            .transfer(0, second, TokenAmount::new(100)?, &mut server)
+            .is_err());
+        assert_eq!(server.events().len(), num_events);
+        assert_eq!(client.account_balance(0)?, TokenAmount::new(900)?);
+        assert_eq!(client.account_balance(second)?, TokenAmount::new(605)?);
+
+        // A view-only client with the second account's viewing key finds its notes only.
+        let mut viewer = Client::new_view_only(client.viewing_key(second).unwrap());
+        viewer.get_state_from_server(&server);
+        assert_eq!(viewer.balance(&server, token_id)?, TokenAmount::new(605)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulate() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        let mut server = Server::new(demoState);
+        client.get_state_from_server(&server);
+
+        let public_inp = client.simulate(TokenAmount::new(12)?, &server)?;
+        assert_eq!(
+            public_inp.merkle_root_value,
+            server.get_state().private_utxo_tree.cap.0[0]
+        );
+        assert!(client.simulate(TokenAmount::new(1001)?, &server).is_err());
+        assert!(server.events().is_empty());
+
+        // Once the server's tree has moved on, the client's root is stale until it syncs.
+        let mut other = Client::new(prive_key, token_id, balance, 0);
+        other.get_state_from_server(&server);
+        other.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        assert!(client.simulate(TokenAmount::new(12)?, &server).is_err());
+        client.get_state_from_server(&server);
+        // The note is now spent, too.
+        assert!(client.simulate(TokenAmount::new(12)?, &server).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mempool_batch() -> Result<()> {
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let keys: Vec<[GoldilocksField; 4]> =
+            (0..3).map(|_| GoldilocksField::rand_array()).collect();
+        let (mut state, first_index) = State::new_demo_state(keys[0], token_id, balance, 10);
+        let mut indices = vec![first_index];
+        for key in &keys[1..] {
+            indices.push(state.add_private_utxo(note_leaf(
+                *key,
+                Timelock::default(),
+                token_id,
+                balance.to_field(),
+            ))?);
+        }
+        let mut server = Server::new(state);
+        let mut clients: Vec<Client> = keys
+            .iter()
+            .zip(&indices)
+            .map(|(key, &index)| Client::new(*key, token_id, balance, index))
+            .collect();
+        for (client, fee) in clients.iter_mut().zip([1, 5, 1]) {
+            client.set_fee(fee);
+            client.get_state_from_server(&server);
+            client.split_and_submit_to_mempool(TokenAmount::new(12)?, &mut server)?;
+        }
+        assert_eq!(server.mempool().len(), 3);
+
+        // A second spend of the same note conflicts, and nothing is applied before the batch.
+        let mut double_spender = Client::new(keys[0], token_id, balance, indices[0]);
+        double_spender.get_state_from_server(&server);
+        assert!(double_spender
+            .split_and_submit_to_mempool(TokenAmount::new(13)?, &mut server)
+            .is_err());
+        assert!(double_spender
+            .split_and_submit(TokenAmount::new(13)?, &mut server)
+            .is_err());
+        assert!(clients[0].complete_pending(&mut server).is_err());
+
+        let batch = server.apply_batch()?;
+        assert_eq!(batch, 0..3);
+        let events = server.events();
+        // The highest fee goes first, and every transaction was proven against the same root.
+        assert_eq!(
+            events[0].nullifier_value,
+            note_leaf(keys[1], Timelock::default(), token_id, balance.to_field())
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.merkle_root_value == events[0].merkle_root_value));
+        for client in &mut clients {
+            client.complete_pending(&mut server)?;
+        }
+        assert!(server.mempool().is_empty());
+        clients[1].split_and_submit(TokenAmount::new(13)?, &mut server)?;
+        assert_eq!(server.events().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_queued() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let other_balance = TokenAmount::new(500)?;
+        let (mut state, index) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let other_index = state.add_private_utxo(note_leaf(
+            prive_key,
+            Timelock::default(),
+            token_id,
+            other_balance.to_field(),
+        ))?;
+        let mut server = Server::new(state);
+        let mut client = Client::new(prive_key, token_id, balance, index);
+        client.add_note(0, other_balance, other_index)?;
+        client.get_state_from_server(&server);
+
+        // Each queued transaction spends its own note, so a third has none left.
+        client.queue_split(TokenAmount::new(12)?)?;
+        client.queue_split(TokenAmount::new(13)?)?;
+        assert!(client.queue_split(TokenAmount::new(14)?).is_err());
+        client.prove_queued(&mut server)?;
+        assert_eq!(server.mempool().len(), 2);
+        assert!(client.proof_cache().is_empty());
+
+        server.apply_batch()?;
+        client.complete_pending(&mut server)?;
+        assert_eq!(client.account_balance(0)?, TokenAmount::new(1475)?);
+        // The change notes have distinct one-time keys, and are spent as any other.
+        client.split_and_submit(TokenAmount::new(14)?, &mut server)?;
+        assert_eq!(server.events().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, index) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut server = Server::new(demoState);
+        let rollbacks = Arc::new(Mutex::new(vec![]));
+        let notified = rollbacks.clone();
+        server.on_rollback(move |rollback| notified.lock().unwrap().push(rollback.clone()));
+        let mut client = Client::new(prive_key, token_id, balance, index);
+        client.get_state_from_server(&server);
+        client.publish_note_memo(&mut server)?;
+        let old_root = server.get_state().private_utxo_tree.cap.0[0];
+
+        client.split_and_submit_to_mempool(TokenAmount::new(12)?, &mut server)?;
+        server.apply_batch()?;
+        client.complete_pending(&mut server)?;
+        let batch_root = server.get_state().private_utxo_tree.cap.0[0];
+        assert_eq!(client.account_balance(0)?, TokenAmount::new(988)?);
+
+        // The batch is undone, and the hooks are told which roots are gone.
+        let rollback = server.rollback_last_batch()?;
+        assert_eq!(rollback.reverted_events, 0..1);
+        assert_eq!(rollback.restored_root, old_root);
+        assert_eq!(rollback.invalidated_roots, [batch_root]);
+        assert_eq!(rollbacks.lock().unwrap().len(), 1);
+        assert!(server.events().is_empty());
+        assert!(server.proofs.is_empty());
+        assert!(server.rollback_last_batch().is_err());
+
+        // The client takes its spent note back and spends it again.
+        client.resync(&server)?;
+        assert_eq!(client.account_balance(0)?, balance);
+        client.split_and_submit(TokenAmount::new(13)?, &mut server)?;
+        assert_eq!(server.events().len(), 1);
+
+        // Only the latest snapshots are kept.
+        server.set_max_snapshots(2);
+        let oldest = server.snapshot();
+        server.snapshot();
+        server.snapshot();
+        assert!(server.rollback_to(oldest).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_admission_policy() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, index) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, index);
+        let mut server = Server::new(demoState);
+        let mut policy = TokenBucketPolicy::new(1, std::time::Duration::from_secs(3600));
+        policy.min_fee = 5;
+        policy.allowed_senders = Some(HashSet::from([client.sender()]));
+        server.set_admission_policy(policy);
+        client.get_state_from_server(&server);
+        client.publish_note_memo(&mut server)?;
+        let snapshot = server.snapshot();
+
+        // Rejected submissions are neither verified nor applied.
+        assert!(client
+            .split_and_submit(TokenAmount::new(12)?, &mut server)
+            .is_err());
+        // The proof of the rejected attempt is reused by the retry, then dropped.
+        assert_eq!(client.proof_cache().len(), 1);
+        let cached = client
+            .proof_cache()
+            .proofs()
+            .next()
+            .unwrap()
+            .proof()
+            .clone();
+        client.set_fee(5);
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        assert_eq!(server.proofs[0].proof.proof(), &cached);
+        assert!(client.proof_cache().is_empty());
+        assert!(client
+            .split_and_submit(TokenAmount::new(13)?, &mut server)
+            .is_err());
+        assert_eq!(server.events().len(), 1);
+
+        // A rollback gives the sender back the token spent on the reverted transaction.
+        server.rollback_to(snapshot)?;
+        client.resync(&server)?;
+        client.split_and_submit(TokenAmount::new(13)?, &mut server)?;
+
+        // The server, its policy included, can be handed to another thread.
+        std::thread::spawn(move || server).join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_cache_store() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, index) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut server = Server::new(demoState);
+        let mut policy = TokenBucketPolicy::default();
+        policy.min_fee = 5;
+        server.set_admission_policy(policy);
+        let mut client = Client::new(prive_key, token_id, balance, index);
+        client.get_state_from_server(&server);
+        assert!(client
+            .split_and_submit(TokenAmount::new(12)?, &mut server)
+            .is_err());
+        let stored = client.proof_cache().to_bytes();
+
+        // A restarted client retries with the stored proof rather than proving again.
+        let mut client = Client::new(prive_key, token_id, balance, index);
+        client.get_state_from_server(&server);
+        client.restore_proof_cache(&stored)?;
+        let cached = client
+            .proof_cache()
+            .proofs()
+            .next()
+            .unwrap()
+            .proof()
+            .clone();
+        client.set_fee(5);
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        assert_eq!(server.proofs[0].proof.proof(), &cached);
+        assert!(client.proof_cache().is_empty());
+
+        // A rejection evicts the proofs the server can no longer accept: the stored one, against a
+        // root it no longer has, and the new one, spending a spent note.
+        let mut stale = Client::new(prive_key, token_id, balance, index);
+        stale.get_state_from_server(&server);
+        stale.restore_proof_cache(&stored)?;
+        assert_eq!(stale.proof_cache().len(), 1);
+        assert!(stale
+            .split_and_submit(TokenAmount::new(13)?, &mut server)
+            .is_err());
+        assert!(stale.proof_cache().is_empty());
+        Ok(())
+    }
+
+    const TIMELOCK: Timelock = Timelock {
+        valid_after: 5,
+        valid_before: NonZeroU32::new(10),
+    };
+
+    /// A state with a note of `prive_key` locked by `TIMELOCK`, and the note's index.
+    fn timelocked_note_state(
+        prive_key: [GoldilocksField; 4],
+        token_id: GoldilocksField,
+        balance: TokenAmount,
+    ) -> (State, usize) {
+        let (mut state, _) =
+            State::new_demo_state(GoldilocksField::rand_array(), token_id, balance, 10);
+        let index = state
+            .add_private_utxo(note_leaf(prive_key, TIMELOCK, token_id, balance.to_field()))
+            .unwrap();
+        (state, index)
+    }
+
+    #[test]
+    fn test_timelocked_note() -> Result<()> {
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        for epoch in [4, 5, 9, 10] {
+            let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+            let (state, index) = timelocked_note_state(prive_key, token_id, balance);
+            let mut client = Client::new(prive_key, token_id, balance, index);
+            client.set_note_timelock(TIMELOCK);
+            let mut server = Server::new(state);
+            server.set_current_epoch(epoch);
+            client.get_state_from_server(&server);
+            let result = client.split_and_submit(TokenAmount::new(12)?, &mut server);
+            assert_eq!(result.is_ok(), TIMELOCK.is_open_at(epoch));
+        }
+        Ok(())
+    }
+
+    /// Proves a spend of a note locked by `TIMELOCK` in epoch `epoch`, which the client would
+    /// refuse to do if the note is locked.
+    fn prove_timelocked_spend(epoch: u32) -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (state, index) = timelocked_note_state(prive_key, token_id, balance);
+        let public_input = PublicInputs {
+            nullifier_value: note_leaf(prive_key, TIMELOCK, token_id, balance.to_field()),
+            new_leaf_value: HashOut::rand(),
+            merkle_root_value: state.private_utxo_tree.cap.0[0],
+            current_epoch: epoch,
+        };
+        let witness = PrivateWitness {
+            private_key: prive_key,
+            index,
+            token_id,
+            token_amount: balance.to_field(),
+            timelock: TIMELOCK,
+            merkle_proof: state.private_utxo_merkle_proof(index),
+        };
+        let config = CircuitConfig::standard_recursion_config();
+        let (circuit_data, wiring) =
+            circuit::private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                &config, 10,
+            );
+        circuit::gen_private_proof(&circuit_data, public_input, witness, &wiring)?;
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_timelocked_note_too_early() {
+        prove_timelocked_spend(4).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_timelocked_note_expired() {
+        prove_timelocked_spend(10).unwrap();
+    }
+
+    #[test]
+    fn test_circuit_upgrade() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        let mut server = Server::new(demoState);
+        client.get_state_from_server(&server);
+
+        let config = CircuitConfig {
+            zero_knowledge: true,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let new_circuit = || {
+            circuit::private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(&config, 10)
+        };
+        let old_digest = server.current_circuit();
+        let new_digest = server.upgrade_circuit(new_circuit().0, 1);
+        assert_ne!(old_digest, new_digest);
+        let snapshot = server.snapshot();
+
+        // Proofs of the old version are accepted during the migration window only.
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        assert!(!server.accepts_circuit(&old_digest));
+        assert!(client
+            .split_and_submit(TokenAmount::new(13)?, &mut server)
+            .is_err());
+
+        let (circuit_data, wiring) = new_circuit();
+        client.set_circuit(circuit_data, wiring);
+        client.split_and_submit(TokenAmount::new(13)?, &mut server)?;
+
+        let aggregate = server.get_recursive_proof(0, 1);
+        assert_eq!(aggregate.circuit_versions, vec![old_digest, new_digest]);
+
+        // Aggregating proofs of the same shapes again reuses the recursion circuit.
+        let num_recursion_circuits = server.num_recursion_circuits();
+        server.get_recursive_proof(0, 1);
+        assert_eq!(server.num_recursion_circuits(), num_recursion_circuits);
+
+        // Rolling back the transactions reopens the migration window of the old version.
+        server.rollback_to(snapshot)?;
+        assert!(server.accepts_circuit(&old_digest));
+        Ok(())
+    }
+
+    #[test]
+    fn test_archived_note_spend() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (mut state, index) = State::new_demo_state(prive_key, token_id, balance, 10);
+        state.set_epoch_size(Some(2));
+
+        // The note's path has to be saved when its epoch is full, before it is archived.
+        state.add_private_utxo(HashOut::rand())?;
+        let note_proof = state.private_utxo_merkle_proof(index);
+        state.add_private_utxo(HashOut::rand())?;
+        assert_eq!(state.num_archived_epochs, 1);
+
+        let mut server = Server::new(state.clone());
+        let config = CircuitConfig::standard_recursion_config();
+        let archived_note_circuit = || {
+            circuit::archived_note_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                &config,
+                10,
+                EPOCH_TREE_HEIGHT,
+            )
+        };
+        server.add_circuit(archived_note_circuit().0);
+        let (circuit_data, wiring) = archived_note_circuit();
+
+        let public_input = PublicInputs {
+            nullifier_value: note_leaf(
+                prive_key,
+                Timelock::default(),
+                token_id,
+                balance.to_field(),
+            ),
+            new_leaf_value: HashOut::rand(),
+            merkle_root_value: state.private_utxo_tree.cap.0[0],
+            current_epoch: 0,
+        };
+        let witness = ArchivedNoteWitness {
+            note: PrivateWitness {
+                private_key: prive_key,
+                index,
+                token_id,
+                token_amount: balance.to_field(),
+                timelock: Timelock::default(),
+                merkle_proof: note_proof,
+            },
+            archived_root: state.archived_root(0),
+            epoch: 0,
+            epoch_proof: state.epoch_merkle_proof(0),
+            epoch_root: state.epoch_tree.cap.0[0],
+            checkpoint_proof: state.checkpoint_merkle_proof(),
+        };
+        let proof = circuit::gen_archived_note_proof(
+            &circuit_data,
+            public_input.clone(),
+            witness,
+            &wiring,
+        )?;
+        server.verify_and_update_state(
+            &submission(&public_input),
+            proof.clone(),
+            public_input.clone(),
+        )?;
+
+        // Spending it again is rejected, even though the note tree has changed since.
+        let mut public_input = public_input;
+        public_input.merkle_root_value = server.get_state().private_utxo_tree.cap.0[0];
+        assert!(server
+            .verify_and_update_state(&submission(&public_input), proof, public_input)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_proving() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        let mut server = Server::new(demoState);
+        client.get_state_from_server(&server);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let (circuit_data, wiring) =
+            circuit::private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                &config, 10,
+            );
+        let prover = RemoteProver::new(&mut OsRng, circuit_data, wiring);
+        client.split_and_submit_remote(TokenAmount::new(12)?, &prover, &mut server)?;
+        assert_eq!(server.events().len(), 1);
+
+        // Only the designated prover can decrypt a request, and it isn't malleable.
+        let (public_inp, witness, _) = client.split_witness(TokenAmount::new(13)?, false)?;
+        assert!(!format!("{:?}", witness).contains(&format!("{:?}", prive_key[0])));
+        let mut request =
+            ProvingRequest::new(&prover.public_key(), public_inp, &witness, &mut OsRng);
+        let (circuit_data, wiring) =
+            circuit::private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                &config, 10,
+            );
+        let other = RemoteProver::new(&mut OsRng, circuit_data, wiring);
+        assert!(other.prove(&request).is_err());
+        // Nor can a relay swap its public inputs, e.g. to send the change note elsewhere.
+        let mut redirected = request.clone();
+        redirected.public_input.new_leaf_value = HashOut::rand();
+        assert!(prover.prove(&redirected).is_err());
+        request.encrypted_witness.ciphertext[0] += GoldilocksField::ONE;
+        assert!(prover.prove(&request).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_delegated_proving() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        let mut server = Server::new(demoState);
+        client.get_state_from_server(&server);
+
+        // The initial note is bound to the long-term key, so it is spent locally.
+        assert!(client
+            .prepare_delegated_witness(TokenAmount::new(12)?)
+            .is_err());
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let (circuit_data, wiring) =
+            circuit::private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, 2>(
+                &config, 10,
+            );
+        let prover = RemoteProver::new(&mut OsRng, circuit_data, wiring);
+        for delta in [13, 14] {
+            let delegated = client.prepare_delegated_witness(TokenAmount::new(delta)?)?;
+            assert_ne!(delegated.witness.private_key, prive_key);
+            let public_input = delegated.public_input.clone();
+            let proof = prover.prove_delegated(delegated)?;
+            assert!(client.complete_delegated(&mut server).is_err());
+            server.verify_and_update_state(&submission(&public_input), proof, public_input)?;
+            client.complete_delegated(&mut server)?;
+        }
+        assert_eq!(server.events().len(), 3);
+        assert!(client.complete_delegated(&mut server).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_only_client() -> Result<()> {
+        let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::from_canonical_u64(1);
+        let balance = TokenAmount::new(1000)?;
+        let (demoState, _) = State::new_demo_state(prive_key, token_id, balance, 10);
+        let mut client = Client::new(prive_key, token_id, balance, 0);
+        let mut server = Server::new(demoState);
+        client.get_state_from_server(&server);
+        client.publish_note_memo(&mut server)?;
+
+        let mut viewer = Client::new_view_only(client.viewing_key(0).unwrap());
+        viewer.get_state_from_server(&server);
+        assert_eq!(viewer.balance(&server, token_id)?, balance);
+
+        // The spent note is nullified, and the change note is found from its memo.
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+        viewer.get_state_from_server(&server);
+        let notes = viewer.scan_notes(&server);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(viewer.balance(&server, token_id)?, TokenAmount::new(988)?);
+
+        let mut other =
+            Client::new_view_only(ViewingKey::from_private_key(GoldilocksField::rand_array()));
+        other.get_state_from_server(&server);
+        assert!(other.scan_notes(&server).is_empty());
+
+        // The auditor learns the amount of an unspent note of the current tree.
+        let proof = viewer.audit_proof(&notes[0])?;
+        let public_input = AuditPublicInputs::from_public_inputs(&proof.proof.public_inputs)?;
+        let state = server.get_state();
+        assert_eq!(
+            public_input.merkle_root_value,
+            state.private_utxo_tree.cap.0[0]
+        );
+        assert_eq!(public_input.token_id, token_id);
+        assert_eq!(public_input.token_amount, TokenAmount::new(988)?.to_field());
+        assert!(!state.is_nullified(public_input.leaf_value));
         Ok(())
     }
 }
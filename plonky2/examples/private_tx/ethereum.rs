@@ -0,0 +1,362 @@
+//! The calldata with which a relayer posts the server's state updates to an Ethereum light-client
+//! contract.
+//!
+//! The contract checks the Groth16 proof of the circom verifier, made by snarkjs from `proof.json`,
+//! and records the new note tree root and an accumulator of the nullifiers spent so far. It is
+//! called as
+//!
+//! ```solidity
+//! function submitStateUpdate(
+//!     bytes32 oldRoot,
+//!     bytes32 newRoot,
+//!     bytes32 nullifierAccumulator,
+//!     uint256[2] calldata a,
+//!     uint256[2][2] calldata b,
+//!     uint256[2] calldata c,
+//!     uint256[] calldata publicSignals
+//! ) external;
+//! ```
+//!
+//! Roots are their four elements as big-endian `uint64`s, i.e. `abi.encodePacked`, and so is the
+//! nullifier accumulator, the Poseidon hash chain of the nullifiers spent so far which the final
+//! proof exposes, see `circuit::nullifier_accumulator`. `a`, `b` and `c` are in the order of the
+//! verifier contracts snarkjs exports.
+//!
+//! The public signals are the public inputs of the final proof, one element each, which prove the
+//! roots and the accumulator: the contract checks that `oldRoot` is the root it holds and that the
+//! signals at `NULLIFIER_ACCUMULATOR_START` are the accumulator it holds, and that `oldRoot`,
+//! `newRoot` and `nullifierAccumulator` are the signals at `OLD_ROOT`, `NEW_ROOT` and
+//! `NULLIFIER_ACCUMULATOR_END`, as `StateUpdate::check_public_signals` does, before recording
+//! `newRoot` and `nullifierAccumulator`.
+
+use anyhow::{anyhow, ensure, Result};
+use keccak_hash::keccak;
+use num::BigUint;
+use plonky2::plonk::public_inputs_layout::PublicInputSlot;
+use plonky2::prelude::*;
+use serde::Deserialize;
+
+use crate::circuit::{NEW_ROOT, NULLIFIER_ACCUMULATOR_END, OLD_ROOT};
+
+type F = GoldilocksField;
+
+/// The signature of the contract function the calldata is for.
+pub const SUBMIT_STATE_UPDATE: &str =
+    "submitStateUpdate(bytes32,bytes32,bytes32,uint256[2],uint256[2][2],uint256[2],uint256[])";
+
+/// A value of the Solidity ABI, as far as state updates need.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AbiValue {
+    /// A `uint256`, which must fit in 256 bits.
+    Uint(BigUint),
+    Bytes32([u8; 32]),
+    /// `T[k]`, which is dynamic iff `T` is.
+    FixedArray(Vec<AbiValue>),
+    /// `T[]`.
+    Array(Vec<AbiValue>),
+}
+
+impl AbiValue {
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Self::Uint(_) | Self::Bytes32(_) => false,
+            Self::FixedArray(values) => values.iter().any(Self::is_dynamic),
+            Self::Array(_) => true,
+        }
+    }
+
+    /// The size of the encoding of a static value, or of its offset if it's dynamic.
+    fn head_len(&self) -> usize {
+        match self {
+            Self::FixedArray(values) if !self.is_dynamic() => {
+                values.iter().map(Self::head_len).sum()
+            }
+            _ => 32,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Uint(x) => {
+                let bytes = x.to_bytes_be();
+                assert!(bytes.len() <= 32, "{x} doesn't fit in a uint256");
+                let mut word = vec![0; 32 - bytes.len()];
+                word.extend(bytes);
+                word
+            }
+            Self::Bytes32(bytes) => bytes.to_vec(),
+            Self::FixedArray(values) => abi_encode(values),
+            Self::Array(values) => {
+                let mut encoded = uint_word(values.len());
+                encoded.extend(abi_encode(values));
+                encoded
+            }
+        }
+    }
+}
+
+fn uint_word(x: usize) -> Vec<u8> {
+    AbiValue::Uint(BigUint::from(x)).encode()
+}
+
+fn uint_array(xs: &[BigUint]) -> AbiValue {
+    AbiValue::FixedArray(xs.iter().cloned().map(AbiValue::Uint).collect())
+}
+
+/// The ABI encoding of the tuple `values`, e.g. the arguments of a function call: the static values
+/// and the offsets of the dynamic ones, followed by the dynamic values.
+pub fn abi_encode(values: &[AbiValue]) -> Vec<u8> {
+    let head_len = values.iter().map(AbiValue::head_len).sum::<usize>();
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = vec![];
+    for value in values {
+        if value.is_dynamic() {
+            head.extend(uint_word(head_len + tail.len()));
+            tail.extend(value.encode());
+        } else {
+            head.extend(value.encode());
+        }
+    }
+    head.extend(tail);
+    head
+}
+
+/// The first four bytes of the Keccak-256 of a function's signature, e.g.
+/// `transfer(address,uint256)`, which select the function in calldata.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    keccak(signature.as_bytes()).0[..4].try_into().unwrap()
+}
+
+/// A hash's four elements as big-endian `uint64`s, i.e. `abi.encodePacked` of them in Solidity.
+pub fn hash_to_bytes32(hash: &HashOut<F>) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, x) in bytes.chunks_mut(8).zip(hash.elements) {
+        chunk.copy_from_slice(&x.to_canonical_u64().to_be_bytes());
+    }
+    bytes
+}
+
+/// `proof.json` as written by `snarkjs groth16 prove`, with points in projective coordinates.
+#[derive(Deserialize)]
+struct SnarkjsProof {
+    pi_a: Vec<String>,
+    pi_b: Vec<Vec<String>>,
+    pi_c: Vec<String>,
+    protocol: String,
+}
+
+/// A Groth16 proof of the circom verifier over BN254, with its public signals.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Groth16Proof {
+    pub a: [BigUint; 2],
+    /// The coordinates of `b` are in the order of the contract, each pair being `(c1, c0)`.
+    pub b: [[BigUint; 2]; 2],
+    pub c: [BigUint; 2],
+    pub public_signals: Vec<BigUint>,
+}
+
+fn parse_uint(x: &str) -> Result<BigUint> {
+    let x = x
+        .parse::<BigUint>()
+        .map_err(|err| anyhow!("invalid integer {x}: {err}"))?;
+    ensure!(x.bits() <= 256, "{x} doesn't fit in a uint256");
+    Ok(x)
+}
+
+/// The affine coordinates of a point written by snarkjs, whose third coordinate is 1.
+fn parse_g1(point: &[String]) -> Result<[BigUint; 2]> {
+    ensure!(
+        point.len() == 3 && point[2] == "1",
+        "expected a G1 point in affine form"
+    );
+    Ok([parse_uint(&point[0])?, parse_uint(&point[1])?])
+}
+
+/// The affine coordinates of a point written by snarkjs, each pair swapped to `(c1, c0)`.
+fn parse_g2(point: &[Vec<String>]) -> Result<[[BigUint; 2]; 2]> {
+    ensure!(
+        point.len() == 3 && point.iter().all(|x| x.len() == 2) && point[2] == ["1", "0"],
+        "expected a G2 point in affine form"
+    );
+    Ok([
+        [parse_uint(&point[0][1])?, parse_uint(&point[0][0])?],
+        [parse_uint(&point[1][1])?, parse_uint(&point[1][0])?],
+    ])
+}
+
+impl Groth16Proof {
+    /// Reads the `proof.json` and `public.json` written by `snarkjs groth16 prove`.
+    pub fn from_snarkjs(proof_json: &str, public_json: &str) -> Result<Self> {
+        let proof: SnarkjsProof = serde_json::from_str(proof_json)?;
+        ensure!(
+            proof.protocol == "groth16",
+            "not a Groth16 proof: {}",
+            proof.protocol
+        );
+        let public_signals: Vec<String> = serde_json::from_str(public_json)?;
+        Ok(Self {
+            a: parse_g1(&proof.pi_a)?,
+            b: parse_g2(&proof.pi_b)?,
+            c: parse_g1(&proof.pi_c)?,
+            public_signals: public_signals
+                .iter()
+                .map(|x| parse_uint(x))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// A state update of the light-client contract, see `Server::state_update`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StateUpdate {
+    pub old_root: HashOut<F>,
+    pub new_root: HashOut<F>,
+    /// The accumulator of every nullifier up to the new root, as the final proof exposes it.
+    pub nullifier_accumulator: HashOut<F>,
+    pub proof: Groth16Proof,
+}
+
+impl StateUpdate {
+    /// Checks that the public signals prove the roots and the nullifier accumulator of the update,
+    /// as the contract does.
+    pub fn check_public_signals(&self) -> Result<()> {
+        let check = |name: &str, slot: PublicInputSlot, root: &HashOut<F>| {
+            let signals = self
+                .proof
+                .public_signals
+                .get(slot.range())
+                .ok_or_else(|| anyhow!("the public signals don't include the {name}"))?;
+            let expected = root.elements.map(|x| BigUint::from(x.to_canonical_u64()));
+            ensure!(
+                signals == expected,
+                "the public signals don't prove the {name}"
+            );
+            Ok(())
+        };
+        check("old root", OLD_ROOT, &self.old_root)?;
+        check("new root", NEW_ROOT, &self.new_root)?;
+        check(
+            "nullifier accumulator",
+            NULLIFIER_ACCUMULATOR_END,
+            &self.nullifier_accumulator,
+        )
+    }
+
+    /// The calldata of the `submitStateUpdate` call posting the update.
+    pub fn calldata(&self) -> Vec<u8> {
+        let mut calldata = function_selector(SUBMIT_STATE_UPDATE).to_vec();
+        calldata.extend(abi_encode(&[
+            AbiValue::Bytes32(hash_to_bytes32(&self.old_root)),
+            AbiValue::Bytes32(hash_to_bytes32(&self.new_root)),
+            AbiValue::Bytes32(hash_to_bytes32(&self.nullifier_accumulator)),
+            uint_array(&self.proof.a),
+            AbiValue::FixedArray(self.proof.b.iter().map(|x| uint_array(x)).collect()),
+            uint_array(&self.proof.c),
+            AbiValue::Array(
+                self.proof
+                    .public_signals
+                    .iter()
+                    .cloned()
+                    .map(AbiValue::Uint)
+                    .collect(),
+            ),
+        ]));
+        calldata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(x: u64) -> Vec<u8> {
+        AbiValue::Uint(BigUint::from(x)).encode()
+    }
+
+    #[test]
+    fn test_abi_encode() {
+        // The example of the Solidity documentation.
+        assert_eq!(
+            function_selector("baz(uint32,bool)"),
+            [0xcd, 0xcd, 0x77, 0xc0]
+        );
+
+        let uint = |x: u64| AbiValue::Uint(BigUint::from(x));
+        let encoded = abi_encode(&[
+            uint(69),
+            AbiValue::Array(vec![uint(3), uint(4)]),
+            AbiValue::FixedArray(vec![uint(1), uint(2)]),
+        ]);
+        // The static values and the offset of the array, then the array's length and elements.
+        let expected = [69, 4 * 32, 1, 2, 2, 3, 4]
+            .into_iter()
+            .flat_map(word)
+            .collect::<Vec<_>>();
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_state_update_calldata() -> Result<()> {
+        let proof_json = r#"{
+            "pi_a": ["1", "2", "1"],
+            "pi_b": [["3", "4"], ["5", "6"], ["1", "0"]],
+            "pi_c": ["7", "8", "1"],
+            "protocol": "groth16",
+            "curve": "bn128"
+        }"#;
+        let proof = Groth16Proof::from_snarkjs(proof_json, r#"["9", "10"]"#)?;
+        assert_eq!(proof.b, [[4u32, 3], [6, 5]].map(|x| x.map(BigUint::from)));
+
+        let accumulator = HashOut::rand();
+        let update = StateUpdate {
+            old_root: HashOut::rand(),
+            new_root: HashOut::rand(),
+            nullifier_accumulator: accumulator,
+            proof,
+        };
+        let calldata = update.calldata();
+        assert_eq!(calldata[..4], function_selector(SUBMIT_STATE_UPDATE));
+        assert_eq!(calldata[4..36], hash_to_bytes32(&update.old_root));
+        assert_eq!(calldata[68..100], hash_to_bytes32(&accumulator));
+        // `b` follows `a`, and the public signals' offset follows `c`.
+        assert_eq!(calldata[4 + 5 * 32..4 + 6 * 32], word(4));
+        assert_eq!(calldata[4 + 11 * 32..4 + 12 * 32], word(12 * 32));
+        assert_eq!(calldata[4 + 12 * 32..], [2, 9, 10].map(word).concat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_update_public_signals() {
+        let (old_root, new_root, accumulator) = (HashOut::rand(), HashOut::rand(), HashOut::rand());
+        let mut public_inputs = vec![F::ZERO; NEW_ROOT.end()];
+        OLD_ROOT.set(&mut public_inputs, &old_root.elements);
+        NEW_ROOT.set(&mut public_inputs, &new_root.elements);
+        NULLIFIER_ACCUMULATOR_END.set(&mut public_inputs, &accumulator.elements);
+        let uint = |x: u64| BigUint::from(x);
+        let mut update = StateUpdate {
+            old_root,
+            new_root,
+            nullifier_accumulator: accumulator,
+            proof: Groth16Proof {
+                a: [uint(1), uint(2)],
+                b: [[uint(3), uint(4)], [uint(5), uint(6)]],
+                c: [uint(7), uint(8)],
+                public_signals: public_inputs
+                    .iter()
+                    .map(|x| uint(x.to_canonical_u64()))
+                    .collect(),
+            },
+        };
+        update.check_public_signals().unwrap();
+
+        // The contract rejects roots the Groth16 proof doesn't prove.
+        update.new_root = HashOut::rand();
+        assert!(update.check_public_signals().is_err());
+        update.new_root = new_root;
+        update.nullifier_accumulator = HashOut::rand();
+        assert!(update.check_public_signals().is_err());
+        update.nullifier_accumulator = accumulator;
+        update.proof.public_signals.truncate(OLD_ROOT.end());
+        assert!(update.check_public_signals().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Hierarchical deterministic derivation of shielded account keys from a seed, so that a wallet can
+//! be restored from a seed phrase rather than by backing up each `[F; 4]` key.
+//!
+//! As in BIP32, an extended key is a key and a chain code, the master key is hashed from the seed,
+//! and each child is hashed from its parent's key and chain code and its index, here with Poseidon.
+//! Only hardened derivation exists: BIP32's public derivation relies on the keys being curve
+//! scalars, and the spend keys of `private_tx_circuit` aren't. The spending key of account `n` is
+//! the key at `m/0'/n'`, and its viewing key is derived from it by `ViewingKey::from_private_key`.
+//!
+//! A seed phrase encodes the seed's bytes, followed by the first byte of their Keccak-256 as a
+//! checksum, as one word of `WORDS` per byte.
+
+use anyhow::{anyhow, ensure, Result};
+use keccak_hash::keccak;
+use plonky2::prelude::*;
+use rand::{CryptoRng, RngCore};
+
+type F = GoldilocksField;
+
+/// The number of bytes of the seeds `generate_seed_phrase` makes.
+pub const SEED_LEN: usize = 16;
+
+/// The index of the first hardened child, as in BIP32.
+const HARDENED: u64 = 1 << 31;
+
+/// A key and the chain code from which its children are derived.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExtendedKey {
+    pub key: [F; 4],
+    pub chain_code: [F; 4],
+}
+
+impl ExtendedKey {
+    /// The master key of `seed`, of 16 to 64 bytes.
+    pub fn from_seed(seed: &[u8]) -> Result<Self> {
+        ensure!(
+            (16..=64).contains(&seed.len()),
+            "Seeds have 16 to 64 bytes, not {}",
+            seed.len()
+        );
+        // Seven bytes per element, so that each is canonical, and the length so that seeds which
+        // only differ by trailing zeros have different keys.
+        let mut inputs = vec![F::from_canonical_usize(seed.len())];
+        inputs.extend(seed.chunks(7).map(|chunk| {
+            let mut bytes = [0; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            F::from_canonical_u64(u64::from_le_bytes(bytes))
+        }));
+        Ok(Self::hash(&inputs))
+    }
+
+    /// The hardened child with index `index`, written `index'` in paths.
+    pub fn derive_hardened(&self, index: u32) -> Self {
+        let index = F::from_canonical_u64(HARDENED + index as u64);
+        Self::hash(&[self.chain_code.as_slice(), &self.key, &[index]].concat())
+    }
+
+    /// The descendant at `path`, e.g. `m/0'/3'`, relative to this key, which should be a master key.
+    pub fn derive_path(&self, path: &str) -> Result<Self> {
+        let mut components = path.split('/');
+        ensure!(components.next() == Some("m"), "Paths start with m");
+        components.try_fold(*self, |key, component| {
+            let index = component
+                .strip_suffix('\'')
+                .or_else(|| component.strip_suffix('h'))
+                .ok_or_else(|| anyhow!("Only hardened derivation is supported, not {component}"))?
+                .parse::<u32>()?;
+            ensure!((index as u64) < HARDENED, "Index {index}' is out of range");
+            Ok(key.derive_hardened(index))
+        })
+    }
+
+    /// The key and the chain code are hashed from `inputs` with different domain separators.
+    fn hash(inputs: &[F]) -> Self {
+        let hash = |tag: F| PoseidonHash::hash_no_pad(&[&[tag], inputs].concat()).elements;
+        Self {
+            key: hash(F::ZERO),
+            chain_code: hash(F::ONE),
+        }
+    }
+}
+
+/// The spending key of account `account` of the wallet with seed `seed`.
+pub fn account_key(seed: &[u8], account: u32) -> Result<[F; 4]> {
+    Ok(ExtendedKey::from_seed(seed)?
+        .derive_path(&format!("m/0'/{account}'"))?
+        .key)
+}
+
+/// Samples a seed from `rng`, which must be cryptographically secure, and returns its phrase.
+pub fn generate_seed_phrase<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> String {
+    let mut seed = [0; SEED_LEN];
+    rng.fill_bytes(&mut seed);
+    to_seed_phrase(&seed)
+}
+
+/// Encodes `seed` as a seed phrase.
+pub fn to_seed_phrase(seed: &[u8]) -> String {
+    let checksum = keccak(seed).0[0];
+    seed.iter()
+        .chain([&checksum])
+        .map(|&byte| WORDS[byte as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decodes a seed phrase, checking its checksum. Words are matched case-insensitively.
+pub fn from_seed_phrase(phrase: &str) -> Result<Vec<u8>> {
+    let mut bytes = phrase
+        .split_whitespace()
+        .map(|word| {
+            let word = word.to_lowercase();
+            WORDS
+                .binary_search(&word.as_str())
+                .map(|index| index as u8)
+                .map_err(|_| anyhow!("Unknown word {word}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let checksum = bytes.pop().ok_or_else(|| anyhow!("Empty seed phrase"))?;
+    ensure!(
+        keccak(&bytes).0[0] == checksum,
+        "Invalid seed phrase checksum"
+    );
+    Ok(bytes)
+}
+
+/// The words of seed phrases, in alphabetical order, with distinct four-letter prefixes.
+const WORDS: [&str; 256] = [
+    "able", "acid", "acorn", "actor", "adult", "agent", "alarm", "album", "alert", "alley",
+    "alpha", "amber", "angle", "ankle", "apple", "april", "apron", "arena", "argue", "arrow",
+    "aspen", "atlas", "audio", "aunt", "autumn", "avoid", "awake", "badge", "bagel", "baker",
+    "bamboo", "banana", "banjo", "barn", "basil", "basket", "beach", "beard", "beetle", "bench",
+    "berry", "birch", "bison", "blade", "board", "bonus", "border", "bottle", "brave", "bread",
+    "brick", "bridge", "broom", "brush", "bubble", "bucket", "bundle", "butter", "cabin", "cable",
+    "cactus", "camel", "candle", "canoe", "canyon", "carbon", "cargo", "carpet", "carrot",
+    "castle", "cedar", "cellar", "chalk", "cherry", "chess", "circle", "citrus", "clay", "cliff",
+    "clock", "cloud", "clover", "coast", "cobalt", "cocoa", "comet", "copper", "coral", "cotton",
+    "cousin", "coyote", "crane", "crayon", "cricket", "dairy", "daisy", "dance", "dawn", "delta",
+    "denim", "desert", "diamond", "dinner", "dolphin", "donkey", "dragon", "drum", "eagle",
+    "earth", "echo", "elbow", "ember", "engine", "falcon", "feather", "fence", "fern", "fiddle",
+    "finch", "flame", "flute", "forest", "fossil", "fox", "frost", "galaxy", "garden", "garlic",
+    "gecko", "giant", "ginger", "glacier", "globe", "goose", "granite", "grape", "gravel",
+    "guitar", "hammer", "harbor", "harvest", "hazel", "helmet", "heron", "hollow", "honey",
+    "horizon", "hotel", "igloo", "iris", "island", "ivory", "jacket", "jaguar", "jelly", "jungle",
+    "kayak", "kettle", "kitten", "koala", "ladder", "lagoon", "lantern", "lemon", "lily", "lizard",
+    "lotus", "magnet", "mango", "maple", "marble", "meadow", "melon", "mint", "mirror", "monkey",
+    "moose", "mosaic", "muffin", "museum", "napkin", "nectar", "nest", "noodle", "nutmeg", "oasis",
+    "ocean", "olive", "onion", "orange", "orbit", "orchid", "otter", "owl", "paddle", "palace",
+    "panda", "panther", "paper", "parrot", "peach", "pebble", "pepper", "piano", "pigeon",
+    "pillow", "pine", "planet", "plum", "pocket", "pony", "poppy", "potato", "pumpkin", "puzzle",
+    "quartz", "quilt", "rabbit", "radar", "raven", "reef", "ribbon", "river", "robin", "rocket",
+    "saddle", "salmon", "sandal", "satin", "scarf", "shadow", "shell", "silver", "sketch",
+    "sparrow", "spider", "spruce", "stone", "sunset", "swan", "tiger", "tomato", "tulip", "tunnel",
+    "turtle", "valley", "velvet", "violin", "walnut", "whale", "willow", "window", "winter",
+    "wizard", "yacht", "zebra",
+];
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_seed_phrase() -> Result<()> {
+        assert!(WORDS.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let phrase = generate_seed_phrase(&mut OsRng);
+        assert_eq!(phrase.split(' ').count(), SEED_LEN + 1);
+        let seed = from_seed_phrase(&phrase)?;
+        assert_eq!(to_seed_phrase(&seed), phrase);
+        assert_eq!(from_seed_phrase(&phrase.to_uppercase())?, seed);
+
+        // Swapping two different words breaks the checksum, or at least changes the seed.
+        let mut words: Vec<_> = phrase.split(' ').collect();
+        if let Some(i) = (1..SEED_LEN).find(|&i| words[i] != words[0]) {
+            words.swap(0, i);
+            assert!(from_seed_phrase(&words.join(" ")).map_or(true, |other| other != seed));
+        }
+        assert!(from_seed_phrase("able acid zebra").is_err());
+        assert!(from_seed_phrase("able notaword").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_derivation() -> Result<()> {
+        let seed = [7; SEED_LEN];
+        let master = ExtendedKey::from_seed(&seed)?;
+        assert_eq!(
+            master.derive_path("m/0'/1'")?,
+            master.derive_hardened(0).derive_hardened(1)
+        );
+        assert_eq!(
+            master.derive_path("m/0h/1h")?,
+            master.derive_path("m/0'/1'")?
+        );
+        assert_eq!(master.derive_path("m")?, master);
+        assert!(master.derive_path("m/0").is_err());
+        assert!(master.derive_path("0'").is_err());
+
+        assert_eq!(account_key(&seed, 0)?, account_key(&seed, 0)?);
+        assert_ne!(account_key(&seed, 0)?, account_key(&seed, 1)?);
+        assert_ne!(account_key(&seed, 0)?, account_key(&[8; SEED_LEN], 0)?);
+        // Trailing zeros count.
+        assert_ne!(
+            ExtendedKey::from_seed(&[0; 16])?,
+            ExtendedKey::from_seed(&[0; 17])?
+        );
+        assert!(ExtendedKey::from_seed(&[0; 15]).is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,21 @@
-mod bench_recursion_fork;
+mod admission;
+mod amount;
+mod attestation;
+mod circom_witness;
 mod circuit;
 mod client_emulation;
+mod ethereum;
+mod hd_keys;
+mod manifest;
+mod mempool;
+mod proof_cache;
+mod public_inputs_tree;
+mod remote_proving;
 mod server_emulation;
+mod signing;
 mod state;
 mod utxo;
+mod viewing;
 
 use std::fs::File;
 use std::io::Write;
@@ -12,27 +24,71 @@ use std::path::Path;
 use anyhow::Result;
 use log::info;
 use plonky2::hash::hash_types::RichField;
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierOnlyCircuitData};
-use plonky2::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+use plonky2::iop::transcript_log::TranscriptLog;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 use plonky2::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+use plonky2::plonk::prover::prove_with_transcript_log;
+#[cfg(feature = "reference_verifier")]
+use plonky2::plonk::reference_verifier::reference_verify;
+#[cfg(not(feature = "forbid-unsafe"))]
+use plonky2::util::memory::TrackingAllocator;
+use plonky2::util::timing::TimingTree;
 use plonky2_field::extension::Extendable;
 use plonky2_field::goldilocks_field::GoldilocksField;
-use plonky2_field::types::{Field, Sample};
-
-use crate::bench_recursion_fork::{
-    generate_circom_verifier, generate_proof_base64, generate_verifier_config, test_serialization,
+use plonky2_field::types::{Field, PrimeField64};
+use plonky2_recursive_tools::{
+    circom_final_config, generate_circom_verifier, generate_proof_base64, generate_verifier_config,
+    test_serialization, PublicInputsCommitment,
 };
+use rand::rngs::OsRng;
+
+use crate::admission::TokenBucketPolicy;
+use crate::amount::TokenAmount;
+use crate::circom_witness::{inputs_from_json, write_wtns, WitnessCalculator};
 use crate::circuit::{
-    gen_private_proof, private_tx_circuit, verify_proof, PrivateWitness, PublicInputs,
+    gen_private_proof, note_leaf, private_tx_circuit, private_tx_witness, verify_proof,
+    PrivateWitness, PublicInputs, Timelock, NULLIFIER_ACCUMULATOR_END, PUBLIC_INPUTS_ROOT,
 };
 use crate::client_emulation::Client;
+use crate::public_inputs_tree::verify_inclusion;
 use crate::server_emulation::Server;
 use crate::state::State;
 
+// Counts the bytes in use, so that the timing trees printed by the provers report the peak memory
+// of each phase.
+#[cfg(not(feature = "forbid-unsafe"))]
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator<std::alloc::System> =
+    TrackingAllocator::new(std::alloc::System);
+
+/// The number of notes the demo client spends in parallel.
+const NUM_DEMO_NOTES: usize = 3;
+
 fn main() {
     env_logger::init();
 
+    // With `--transcript-dump <dir>`, the Fiat-Shamir transcripts of a transaction proof and of the
+    // final aggregated proof are written to `<dir>`, to localize mismatches between the prover, the
+    // native verifier and the exported circom verifier.
+    let transcript_dump_dir = std::env::args()
+        .skip_while(|arg| arg != "--transcript-dump")
+        .nth(1);
+    // With `--public-inputs-commitment keccak` or `sha256`, the circom verifier exposes a single
+    // digest of the final proof's public inputs instead of each of them.
+    let public_inputs_commitment = std::env::args()
+        .skip_while(|arg| arg != "--public-inputs-commitment")
+        .nth(1)
+        .map_or(Ok(PublicInputsCommitment::None), |arg| arg.parse())
+        .unwrap();
+    // With `--circom-wasm <path>`, the witness of the circom verifier for `proof.json` is computed
+    // with the witness calculator compiled by `circom --wasm` and written to `witness.wtns`.
+    let circom_wasm = std::env::args()
+        .skip_while(|arg| arg != "--circom-wasm")
+        .nth(1);
+
     info!("starting test");
     const D: usize = 2;
     const TREE_HEIGHT: usize = 10;
@@ -40,76 +96,158 @@ fn main() {
     let (data, wr) =
         private_tx_circuit::<GoldilocksField, PoseidonGoldilocksConfig, D>(&zk_config, TREE_HEIGHT);
     let token_id = GoldilocksField::from_canonical_u64(1);
-    let balance: u64 = 1000;
-    let delta: u64 = 100;
-    let priv_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
+    let balance = TokenAmount::new(1000).unwrap();
+    let delta = TokenAmount::new(100).unwrap();
+    let priv_key = Client::generate_key(&mut OsRng);
     let (demo, index) = State::new_demo_state(priv_key, token_id, balance, 10);
     let merkle_proof = demo.private_utxo_tree.prove(index);
 
-    let old_private_tree_hash = PoseidonHash::hash_no_pad(
-        &[
-            priv_key,
-            [
-                GoldilocksField::ZERO,
-                GoldilocksField::ZERO,
-                token_id,
-                GoldilocksField::from_canonical_u64(balance),
-            ],
-        ]
-        .concat(),
-    );
-    info!("old private hash {:?}", old_private_tree_hash);
+    let old_private_tree_hash =
+        note_leaf(priv_key, Timelock::default(), token_id, balance.to_field());
+    info!("old private hash {}", old_private_tree_hash);
     let old_root = demo.private_utxo_tree.cap.0[0];
-    let new_private_tree_hash = PoseidonHash::hash_no_pad(
-        &[
-            priv_key,
-            [
-                GoldilocksField::ZERO,
-                GoldilocksField::ZERO,
-                token_id,
-                GoldilocksField::from_canonical_u64(balance - delta),
-            ],
-        ]
-        .concat(),
+    let new_private_tree_hash = note_leaf(
+        priv_key,
+        Timelock::default(),
+        token_id,
+        balance.checked_sub(delta).unwrap().to_field(),
     );
     let pub_input = PublicInputs {
         nullifier_value: old_private_tree_hash,
         new_leaf_value: new_private_tree_hash,
         merkle_root_value: old_root,
+        current_epoch: 0,
     };
     let private_witness = PrivateWitness {
         private_key: priv_key,
         index,
         token_id,
-        token_amount: GoldilocksField(balance),
+        token_amount: balance.to_field(),
+        timelock: Timelock::default(),
         merkle_proof,
     };
 
-    info!("nullifier_value: {:?}", old_private_tree_hash);
-    info!("new_leaf_value: {:?}", new_private_tree_hash);
+    info!("nullifier_value: {}", old_private_tree_hash);
+    info!("new_leaf_value: {}", new_private_tree_hash);
     info!("pub_input: {:?}", pub_input);
     //
     info!("witness: {:?}", private_witness);
 
-    let mut client = Client::new(priv_key, token_id, 1000, 0);
-    let mut server = Server::new(demo.clone());
+    if let Some(dir) = &transcript_dump_dir {
+        let pw = private_tx_witness(&pub_input, &private_witness, &wr);
+        let (proof, prover_log) = prove_with_transcript_log(
+            &data.prover_only,
+            &data.common,
+            pw,
+            &mut TimingTree::default(),
+        )
+        .unwrap();
+        let verifier_log = proof
+            .transcript_log(&data.verifier_only.circuit_digest, &data.common)
+            .unwrap();
+        match prover_log.first_divergence(&verifier_log) {
+            None => info!("prover and verifier transcripts match"),
+            Some(i) => info!("prover and verifier transcripts diverge at event {}", i),
+        }
+        write_transcript_dump(Path::new(dir), "private_tx_prover", &prover_log).unwrap();
+        write_transcript_dump(Path::new(dir), "private_tx_verifier", &verifier_log).unwrap();
+    }
+
+    // The client holds a few notes, so that it can spend them in parallel. Their balances differ,
+    // as notes with the same key, token and balance would have the same nullifier.
+    let mut client = Client::new(priv_key, token_id, balance, 0);
+    let mut state = demo.clone();
+    for i in 1..NUM_DEMO_NOTES {
+        let note_balance = balance
+            .checked_add(TokenAmount::new(i as u64).unwrap())
+            .unwrap();
+        let index = state.add_private_utxo(note_leaf(
+            priv_key,
+            Timelock::default(),
+            token_id,
+            note_balance.to_field(),
+        ))
+        .unwrap();
+        client.add_note(0, note_balance, index).unwrap();
+    }
+    let mut server = Server::new(state);
+    server
+        .set_final_config(circom_final_config(&zk_config).unwrap())
+        .unwrap();
+    // Keep at most 5 transaction proofs around; older ones are folded into an aggregate.
+    server.set_max_pending_proofs(Some(5));
+    // As a relayer would, limit each sender to bursts of 10 transactions.
+    server.set_admission_policy(TokenBucketPolicy::default());
 
     client.get_state_from_server(&server);
-    client.split_and_submit(12, &mut server).unwrap();
-    client.split_and_submit(13, &mut server).unwrap();
-    client.split_and_submit(14, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-    client.split_and_submit(15, &mut server).unwrap();
-
-    let (final_proof, vd, cd) = server.get_recursive_proof(0, server.proofs.len() - 1);
+    // Each round spends every note once: its transactions are proven in parallel and applied as
+    // one batch, and the next round spends their change notes.
+    for deltas in [12, 13, 14, 15, 15, 15, 15, 15, 15].chunks(NUM_DEMO_NOTES) {
+        for &delta in deltas {
+            client
+                .queue_split(TokenAmount::new(delta).unwrap())
+                .unwrap();
+        }
+        client.prove_queued(&mut server).unwrap();
+        server.apply_batch().unwrap();
+        client.complete_pending(&mut server).unwrap();
+    }
+
+    info!(
+        "{} transactions, {} pending proofs, {} attested batches",
+        server.events().len(),
+        server.proofs.len(),
+        server.attestations().len()
+    );
+    let final_proof = server.get_recursive_proof(0, server.proofs.len() - 1);
+    info!(
+        "final proof covers circuit versions {:?}",
+        final_proof.circuit_versions
+    );
+    // The final proof exposes an accumulator of the nullifiers it covers rather than the
+    // nullifiers themselves, which anyone holding them checks by recomputing the chain.
+    assert_eq!(
+        final_proof.proof.proof().public_inputs[NULLIFIER_ACCUMULATOR_END.range()],
+        server
+            .nullifier_accumulator(final_proof.events.end)
+            .elements
+    );
+    // It also exposes the root of the tree over the transactions' public inputs, against which a
+    // light client checks that its own transaction is covered.
+    let public_inputs_root = final_proof.public_inputs_tree.root();
+    assert_eq!(
+        final_proof.proof.proof().public_inputs[PUBLIC_INPUTS_ROOT.range()],
+        public_inputs_root.elements
+    );
+    // And the note tree roots before and after its transactions, which it proves from the
+    // insertion of each new note.
+    assert_eq!(
+        final_proof.roots().1,
+        server.get_state().private_utxo_tree.cap.0[0]
+    );
+    let last_event = final_proof.events.end - 1;
+    let inclusion_proof = server.inclusion_proof(&final_proof, last_event).unwrap();
+    verify_inclusion(
+        &server.events()[last_event],
+        &inclusion_proof,
+        public_inputs_root,
+    )
+    .unwrap();
+    info!(
+        "transaction {} is covered by the final proof, with {} siblings",
+        last_event,
+        inclusion_proof.siblings.len()
+    );
+    let manifest = server.manifest(&final_proof);
+    let (final_proof, vd, cd) = final_proof.proof.into_parts();
 
     test_serialization(&final_proof, &vd, &cd).unwrap();
+    // The proof exported for the circom verifier must also pass the reference verifier, so that
+    // the two can be compared on it.
+    #[cfg(feature = "reference_verifier")]
+    reference_verify(&final_proof, &vd, &cd).unwrap();
 
-    let conf = generate_verifier_config(&final_proof).unwrap();
+    let conf = generate_verifier_config(&final_proof, public_inputs_commitment).unwrap();
     let (circom_constants, circom_gates) = generate_circom_verifier(&conf, &cd, &vd).unwrap();
 
     let mut circom_file = File::create("./circom/circuits/constants.circom").unwrap();
@@ -126,7 +264,251 @@ fn main() {
     let mut proof_file = File::create("./circom/test/data/proof.json").unwrap();
     proof_file.write_all(proof_json.as_bytes()).unwrap();
 
+    if let Some(wasm) = &circom_wasm {
+        let calculator = WitnessCalculator::from_file(wasm).unwrap();
+        let witness = calculator
+            .calculate(&inputs_from_json(&proof_json).unwrap())
+            .unwrap();
+        let wtns_file = File::create("./circom/test/data/witness.wtns").unwrap();
+        write_wtns(wtns_file, calculator.prime(), &witness).unwrap();
+        info!("circom witness has {} signals", witness.len());
+    }
+
+    // Inputs for `plonky2-verify`, with which deployment scripts check the final proof.
+    let verifier_data = VerifierCircuitData {
+        verifier_only: vd.clone(),
+        common: cd.clone(),
+    };
+    let public_inputs = final_proof
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64().to_string())
+        .collect::<Vec<_>>();
+    std::fs::write("./circom/test/data/final_proof.bin", final_proof.to_bytes()).unwrap();
+    std::fs::write(
+        "./circom/test/data/verifier_data.bin",
+        verifier_data.to_bytes(),
+    )
+    .unwrap();
+    std::fs::write(
+        "./circom/test/data/public_inputs.json",
+        serde_json::to_string(&public_inputs).unwrap(),
+    )
+    .unwrap();
+    // What the final proof covers, signed by the operator.
+    std::fs::write(
+        "./circom/test/data/manifest.json",
+        serde_json::to_string_pretty(&manifest).unwrap(),
+    )
+    .unwrap();
+
+    // The values the circom verifier is expected to observe and squeeze for `proof.json`, as test
+    // vectors for `circom/test/transcript.test.js`.
+    let final_transcript = final_proof.transcript_log(&vd.circuit_digest, &cd).unwrap();
+    std::fs::write(
+        "./circom/test/data/transcript_vectors.json",
+        serde_json::to_string_pretty(&final_transcript.to_fixture()).unwrap(),
+    )
+    .unwrap();
+    if let Some(dir) = &transcript_dump_dir {
+        write_transcript_dump(Path::new(dir), "final_verifier", &final_transcript).unwrap();
+    }
+
     //input for snarkjs
     // let mut conf_file = File::create("./circom/test/data/conf.json").unwrap();
     // conf_file.write_all(serde_json::to_string(&conf)?.as_ref())?;
 }
+
+/// Writes `log` to `<dir>/<name>.bin`, in the format of `TranscriptLog::to_bytes`, and to
+/// `<dir>/<name>.json`, as the test vectors of `TranscriptLog::to_fixture`.
+fn write_transcript_dump<F: RichField>(
+    dir: &Path,
+    name: &str,
+    log: &TranscriptLog<F>,
+) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    File::create(dir.join(format!("{name}.bin")))?.write_all(&log.to_bytes())?;
+    File::create(dir.join(format!("{name}.json")))?
+        .write_all(serde_json::to_string(&log.to_fixture())?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use anyhow::{ensure, Context as _};
+    use plonky2_field::types::Sample;
+    use plonky2_recursive_tools::TEMPLATE_DIR;
+
+    use super::*;
+    use crate::ethereum::Groth16Proof;
+
+    /// Runs `program` in `dir`, failing if it fails, and returns its stdout and stderr.
+    fn run(dir: &Path, program: &str, args: &[&str]) -> Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .with_context(|| format!("failed to run {program}"))?;
+        let out = String::from_utf8_lossy(&output.stdout).into_owned()
+            + &String::from_utf8_lossy(&output.stderr);
+        ensure!(
+            output.status.success(),
+            "{program} {} failed: {out}",
+            args.join(" ")
+        );
+        Ok(out)
+    }
+
+    fn is_installed(program: &str) -> bool {
+        Command::new(program).arg("--version").output().is_ok()
+    }
+
+    /// Exports an aggregated proof of a few transactions to circom as `main` does, then compiles
+    /// the circom verifier, proves it with Groth16 and checks the proof with snarkjs.
+    ///
+    /// It needs `circom`, `snarkjs` and `node` on the path, the node modules of `circom/`, and a
+    /// powers of tau file large enough for the verifier (2^25 constraints) in `PLONKY2_CIRCOM_PTAU`,
+    /// and is skipped if any is missing. It takes hours and tens of GB of memory.
+    #[test]
+    #[ignore]
+    #[cfg(unix)]
+    fn test_circom_pipeline() -> Result<()> {
+        let repo = Path::new(TEMPLATE_DIR);
+        let missing = ["circom", "snarkjs", "node"]
+            .into_iter()
+            .filter(|program| !is_installed(program))
+            .collect::<Vec<_>>();
+        let ptau = std::env::var("PLONKY2_CIRCOM_PTAU");
+        if !missing.is_empty() || ptau.is_err() || !repo.join("circom/node_modules").is_dir() {
+            eprintln!("skipping: needs PLONKY2_CIRCOM_PTAU, circom/node_modules and {missing:?}");
+            return Ok(());
+        }
+        let ptau = ptau?;
+
+        let priv_key = GoldilocksField::rand_array();
+        let token_id = GoldilocksField::ONE;
+        let balance = TokenAmount::new(1000)?;
+        let (state, _) = State::new_demo_state(priv_key, token_id, balance, 10);
+        let mut client = Client::new(priv_key, token_id, balance, 0);
+        let mut server = Server::new(state);
+        server.set_final_config(circom_final_config(
+            &CircuitConfig::standard_recursion_config(),
+        )?)?;
+        client.get_state_from_server(&server);
+        for delta in [12, 13] {
+            client.split_and_submit(TokenAmount::new(delta)?, &mut server)?;
+        }
+        let final_proof = server.get_recursive_proof(0, server.proofs.len() - 1);
+        let (proof, vd, cd) = final_proof.proof.clone().into_parts();
+
+        // The verifier is compiled in a copy of `circom/circuits`, with the generated constants
+        // and gates, so that the checked-in ones are left alone.
+        let dir = std::env::temp_dir().join(format!("plonky2_circom_e2e_{}", std::process::id()));
+        let circuits = dir.join("circuits");
+        std::fs::create_dir_all(&circuits)?;
+        for entry in std::fs::read_dir(repo.join("circom/circuits"))? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "circom") {
+                std::fs::copy(&path, circuits.join(path.file_name().unwrap()))?;
+            }
+        }
+        std::os::unix::fs::symlink(repo.join("circom/node_modules"), dir.join("node_modules"))?;
+
+        let conf = generate_verifier_config(&proof, PublicInputsCommitment::None)?;
+        let (constants, gates) = generate_circom_verifier(&conf, &cd, &vd)?;
+        std::fs::write(circuits.join("constants.circom"), constants)?;
+        std::fs::write(circuits.join("gates.circom"), gates)?;
+        std::fs::write(
+            dir.join("input.json"),
+            generate_proof_base64(&proof, &conf)?,
+        )?;
+
+        run(
+            &dir,
+            "circom",
+            &["circuits/plonky2.circom", "--r1cs", "--wasm", "-o", "."],
+        )?;
+        // The witness is computed from Rust rather than by snarkjs.
+        let calculator = WitnessCalculator::from_file(dir.join("plonky2_js/plonky2.wasm"))?;
+        let witness = calculator.calculate(&inputs_from_json(&std::fs::read_to_string(
+            dir.join("input.json"),
+        )?)?)?;
+        write_wtns(
+            File::create(dir.join("witness.wtns"))?,
+            calculator.prime(),
+            &witness,
+        )?;
+        run(
+            &dir,
+            "snarkjs",
+            &["groth16", "setup", "plonky2.r1cs", &ptau, "plonky2.zkey"],
+        )?;
+        run(
+            &dir,
+            "snarkjs",
+            &[
+                "zkey",
+                "export",
+                "verificationkey",
+                "plonky2.zkey",
+                "verification_key.json",
+            ],
+        )?;
+        run(
+            &dir,
+            "snarkjs",
+            &[
+                "groth16",
+                "prove",
+                "plonky2.zkey",
+                "witness.wtns",
+                "proof.json",
+                "public.json",
+            ],
+        )?;
+        let verify = run(
+            &dir,
+            "snarkjs",
+            &[
+                "groth16",
+                "verify",
+                "verification_key.json",
+                "public.json",
+                "proof.json",
+            ],
+        )?;
+        ensure!(verify.contains("OK"), "Groth16 proof rejected: {verify}");
+
+        // The public signals of the Groth16 proof are the public inputs of the plonky2 proof.
+        let public: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("public.json"))?)?;
+        let expected = proof
+            .public_inputs
+            .iter()
+            .map(|x| x.to_canonical_u64().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(public, expected);
+
+        // What a relayer posts to the light-client contract.
+        let groth16 = Groth16Proof::from_snarkjs(
+            &std::fs::read_to_string(dir.join("proof.json"))?,
+            &std::fs::read_to_string(dir.join("public.json"))?,
+        )?;
+        assert_eq!(groth16.public_signals.len(), expected.len());
+        let update = server.state_update(&final_proof, groth16)?;
+        assert_eq!(
+            update.new_root,
+            server.get_state().private_utxo_tree.cap.0[0]
+        );
+        assert_eq!(
+            update.nullifier_accumulator,
+            server.nullifier_accumulator(final_proof.events.end)
+        );
+        assert_eq!(update.calldata().len(), 4 + 32 * (13 + expected.len()));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
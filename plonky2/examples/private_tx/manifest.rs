@@ -0,0 +1,246 @@
+//! The manifest of an aggregated proof, which binds it to what it attests and is signed by the
+//! server operator.
+//!
+//! An aggregated proof only exposes the circuit digests of the proofs it aggregates, hashes of
+//! their nullifiers and public inputs and the note tree roots before and after them, so a verifier
+//! of the final proof, e.g. a contract checking the circom verifier's Groth16 proof, can't tell
+//! from the proof alone which transactions it covers. The manifest lists them: the circuit digest
+//! and a hash of the common data of the proof, the layout of its public inputs, the range of
+//! transactions covered and the note tree roots before and after them.
+
+use anyhow::{anyhow, ensure, Result};
+use keccak_hash::keccak;
+use plonky2::curve::ecgfp5::CurvePoint;
+use plonky2::plonk::public_inputs_layout::PublicInputSlot;
+use plonky2::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{
+    ProofKind, PublicInputs, INNER_DIGEST_1, INNER_DIGEST_2, NEW_ROOT, NULLIFIER_ACCUMULATOR_END,
+    NULLIFIER_ACCUMULATOR_START, OLD_ROOT, PUBLIC_INPUTS_ROOT,
+};
+use crate::server_emulation::TaggedProof;
+use crate::signing::{
+    bytes_message, point_from_strings, point_to_strings, verify_signature, Signature, SigningKey,
+};
+
+type F = GoldilocksField;
+
+/// A named value of the public inputs of a proof.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PublicInputField {
+    pub name: String,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl PublicInputField {
+    fn new(name: &str, slot: PublicInputSlot) -> Self {
+        Self {
+            name: name.to_string(),
+            offset: slot.offset,
+            len: slot.len,
+        }
+    }
+}
+
+/// Field elements are decimal strings, as in the `proof.json` read by the circom verifier.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProofManifest {
+    /// The digest of the circuit of the proof.
+    pub circuit_digest: Vec<String>,
+    /// The Keccak-256, in hex, of the proof's `CommonCircuitData::to_bytes`, which includes its
+    /// config.
+    pub config_hash: String,
+    pub public_inputs: Vec<PublicInputField>,
+    /// The digests of the transaction circuits whose proofs are covered.
+    pub transaction_circuits: Vec<Vec<String>>,
+    /// The transactions covered are `Server::events()[first_event..end_event]`.
+    pub first_event: usize,
+    pub end_event: usize,
+    /// The note tree root before the first transaction covered.
+    pub old_root: Vec<String>,
+    /// The note tree root after the last transaction covered.
+    pub new_root: Vec<String>,
+}
+
+fn hash_to_strings(hash: &HashOut<F>) -> Vec<String> {
+    hash.elements
+        .iter()
+        .map(|x| x.to_canonical_u64().to_string())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+impl ProofManifest {
+    pub fn new(proof: &TaggedProof, old_root: HashOut<F>, new_root: HashOut<F>) -> Self {
+        let public_inputs = if proof.kind() == ProofKind::Transaction {
+            vec![
+                PublicInputField::new("merkle_root", PublicInputs::<F>::MERKLE_ROOT),
+                PublicInputField::new("nullifier", PublicInputs::<F>::NULLIFIER),
+                PublicInputField::new("new_leaf", PublicInputs::<F>::NEW_LEAF),
+                PublicInputField::new("current_epoch", PublicInputs::<F>::CURRENT_EPOCH),
+            ]
+        } else {
+            vec![
+                PublicInputField::new("inner_circuit_digest_1", INNER_DIGEST_1),
+                PublicInputField::new("inner_circuit_digest_2", INNER_DIGEST_2),
+                PublicInputField::new("nullifier_accumulator_start", NULLIFIER_ACCUMULATOR_START),
+                PublicInputField::new("nullifier_accumulator_end", NULLIFIER_ACCUMULATOR_END),
+                PublicInputField::new("public_inputs_root", PUBLIC_INPUTS_ROOT),
+                PublicInputField::new("old_root", OLD_ROOT),
+                PublicInputField::new("new_root", NEW_ROOT),
+            ]
+        };
+        Self {
+            circuit_digest: hash_to_strings(&proof.proof.verifier_only.circuit_digest),
+            config_hash: to_hex(&keccak(proof.proof.common.to_bytes()).0),
+            public_inputs,
+            transaction_circuits: proof.circuit_versions.iter().map(hash_to_strings).collect(),
+            first_event: proof.events.start,
+            end_event: proof.events.end,
+            old_root: hash_to_strings(&old_root),
+            new_root: hash_to_strings(&new_root),
+        }
+    }
+
+    /// The message the operator signs: the Keccak-256 of the manifest's JSON, as eight 32-bit
+    /// limbs.
+    fn message(&self) -> Vec<F> {
+        let json = serde_json::to_vec(self).expect("Serializing a manifest cannot fail.");
+        bytes_message(&json)
+    }
+
+    /// Checks that this is the manifest of `proof`, as far as the proof alone can tell: its circuit,
+    /// config and number of public inputs.
+    pub fn check_proof(
+        &self,
+        proof: &ProvenCircuitOutput<F, PoseidonGoldilocksConfig, 2>,
+    ) -> Result<()> {
+        ensure!(
+            self.circuit_digest == hash_to_strings(&proof.verifier_only.circuit_digest),
+            "the manifest is for another circuit"
+        );
+        ensure!(
+            self.config_hash == to_hex(&keccak(proof.common.to_bytes()).0),
+            "the manifest is for another circuit config"
+        );
+        let num_public_inputs = proof.proof.public_inputs.len();
+        let num_fields = self
+            .public_inputs
+            .iter()
+            .map(|field| field.offset + field.len)
+            .max()
+            .unwrap_or(0);
+        ensure!(
+            num_fields == num_public_inputs,
+            "the manifest describes {} public inputs, but the proof has {}",
+            num_fields,
+            num_public_inputs
+        );
+        Ok(())
+    }
+}
+
+/// A manifest with the operator's signature of it, as published.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedManifest {
+    pub manifest: ProofManifest,
+    /// The operator's public key, as `point_to_strings`.
+    pub operator_key: Vec<String>,
+    /// The signature `(R, s)`, with `R` as `point_to_strings` and `s` in decimal.
+    pub signature_r: Vec<String>,
+    pub signature_s: String,
+}
+
+impl SignedManifest {
+    pub fn sign<R: Rng + ?Sized>(manifest: ProofManifest, key: &SigningKey, rng: &mut R) -> Self {
+        let signature = key.sign(&manifest.message(), rng);
+        Self {
+            manifest,
+            operator_key: point_to_strings(&key.public_key()),
+            signature_r: point_to_strings(&signature.r),
+            signature_s: signature.s.to_string(),
+        }
+    }
+
+    /// Checks that the manifest is signed by the operator whose public key is `operator_key`,
+    /// which must come from a trusted source rather than from the manifest itself.
+    pub fn verify(&self, operator_key: &CurvePoint<F>) -> Result<()> {
+        ensure!(
+            self.operator_key == point_to_strings(operator_key),
+            "the manifest is signed by another operator"
+        );
+        let signature = Signature {
+            r: point_from_strings(&self.signature_r)?,
+            s: self
+                .signature_s
+                .parse()
+                .map_err(|err| anyhow!("invalid signature {}: {err}", self.signature_s))?,
+        };
+        verify_signature(operator_key, &self.manifest.message(), &signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::amount::TokenAmount;
+    use crate::client_emulation::Client;
+    use crate::server_emulation::Server;
+    use crate::state::State;
+
+    #[test]
+    fn test_signed_manifest() -> Result<()> {
+        let priv_key = F::rand_array();
+        let token_id = F::ONE;
+        let balance = TokenAmount::new(1000)?;
+        let (state, _) = State::new_demo_state(priv_key, token_id, balance, 10);
+        let mut client = Client::new(priv_key, token_id, balance, 0);
+        let mut server = Server::new(state);
+        let operator_key = SigningKey::new(&mut rand::rngs::OsRng).to_bytes();
+        server.set_operator_key(SigningKey::from_bytes(&operator_key)?);
+        client.get_state_from_server(&server);
+        let old_root = server.get_state().private_utxo_tree.cap.0[0];
+        client.split_and_submit(TokenAmount::new(12)?, &mut server)?;
+
+        let proof = &server.proofs[0];
+        let signed = server.manifest(proof);
+        signed.verify(&server.operator_public_key())?;
+        let manifest = &signed.manifest;
+        assert_eq!((manifest.first_event, manifest.end_event), (0, 1));
+        assert_eq!(manifest.old_root, hash_to_strings(&old_root));
+        assert_eq!(
+            manifest.new_root,
+            hash_to_strings(&server.get_state().private_utxo_tree.cap.0[0])
+        );
+        manifest.check_proof(&proof.proof)?;
+        let mut other_layout = manifest.clone();
+        other_layout.public_inputs.pop();
+        assert!(other_layout.check_proof(&proof.proof).is_err());
+
+        // The manifest survives a round trip through JSON, and any change breaks the signature.
+        let json = serde_json::to_string(&signed)?;
+        let parsed: SignedManifest = serde_json::from_str(&json)?;
+        parsed.verify(&server.operator_public_key())?;
+        let mut tampered = parsed.clone();
+        tampered.manifest.end_event = 2;
+        assert!(tampered.verify(&server.operator_public_key()).is_err());
+        let other_key = SigningKey::new(&mut rand::rngs::OsRng);
+        assert!(parsed.verify(&other_key.public_key()).is_err());
+
+        // After a restart, the operator's earlier manifests stay checkable once its key is restored.
+        let mut restarted = Server::new(server.get_state());
+        assert!(signed.verify(&restarted.operator_public_key()).is_err());
+        restarted.set_operator_key(SigningKey::from_bytes(&operator_key)?);
+        signed.verify(&restarted.operator_public_key())?;
+        restarted.manifest(proof).verify(&server.operator_public_key())?;
+        Ok(())
+    }
+}
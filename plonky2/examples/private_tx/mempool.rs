@@ -0,0 +1,82 @@
+//! The server's mempool: transactions which have been verified but not applied yet, and which are
+//! applied together as a batch.
+//!
+//! The state only changes when a batch is applied, so all the transactions of a batch are proven
+//! against the same note tree root, and whether one is valid doesn't depend on the others. Two
+//! transactions spending the same note have the same nullifier and conflict, as only one of them
+//! can be applied; the mempool rejects the second. A batch is applied in a deterministic order, by
+//! decreasing fee and then by nullifier, rather than in arrival order, so that the notes it adds
+//! get the same indices whichever order the transactions arrived in.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use anyhow::{ensure, Result};
+use plonky2::hash::hash_types::HashOut;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProvenCircuitOutput;
+use plonky2_field::goldilocks_field::GoldilocksField;
+use plonky2_field::types::PrimeField64;
+
+use crate::admission::Submission;
+use crate::circuit::PublicInputs;
+
+/// A verified transaction waiting in the mempool.
+#[derive(Clone)]
+pub struct PendingTransaction {
+    pub submission: Submission,
+    pub proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    pub public_inputs: PublicInputs<GoldilocksField>,
+}
+
+impl PendingTransaction {
+    /// The position of the transaction in its batch, which doesn't depend on when it arrived.
+    fn order(&self) -> (Reverse<u64>, [u64; 4]) {
+        let nullifier = self.public_inputs.nullifier_value.elements;
+        (
+            Reverse(self.submission.fee),
+            nullifier.map(|x| x.to_canonical_u64()),
+        )
+    }
+}
+
+/// The transactions verified by `Server::submit_to_mempool`, until `Server::apply_batch`.
+#[derive(Clone, Default)]
+pub struct Mempool {
+    // by nullifier, so conflicting transactions can't both be in
+    transactions: HashMap<HashOut<GoldilocksField>, PendingTransaction>,
+}
+
+impl Mempool {
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Whether a pending transaction spends the note with nullifier `nullifier`.
+    pub fn contains(&self, nullifier: &HashOut<GoldilocksField>) -> bool {
+        self.transactions.contains_key(nullifier)
+    }
+
+    /// Adds `transaction`, unless it conflicts with a pending one.
+    pub fn insert(&mut self, transaction: PendingTransaction) -> Result<()> {
+        let nullifier = transaction.public_inputs.nullifier_value;
+        ensure!(
+            !self.contains(&nullifier),
+            "conflicts with a pending transaction: nullifier {}",
+            nullifier
+        );
+        self.transactions.insert(nullifier, transaction);
+        Ok(())
+    }
+
+    /// Removes all the pending transactions, in the order they're to be applied in.
+    pub fn take_batch(&mut self) -> Vec<PendingTransaction> {
+        let mut batch: Vec<_> = self.transactions.drain().map(|(_, tx)| tx).collect();
+        batch.sort_by_key(PendingTransaction::order);
+        batch
+    }
+}
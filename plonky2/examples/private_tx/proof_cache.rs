@@ -0,0 +1,158 @@
+//! A client-side cache of transaction proofs, so that a transaction retried after the server
+//! rejected it, e.g. for a transient error, isn't proven again.
+//!
+//! Proofs are keyed by a commitment to everything they are generated from: the circuit, the public
+//! inputs and the private witness. A retried transaction has the same witness as long as the
+//! client's view of the state hasn't changed, and then reuses the proof of the first attempt. The
+//! commitment is a Poseidon hash, which reveals nothing of the spend key in the witness.
+//!
+//! The cache is kept in a local store across restarts with `to_bytes` and `from_bytes`. Proofs the
+//! server can no longer accept, e.g. against a root it no longer has, are evicted when a
+//! submission is rejected, see `Client::prove`.
+
+use std::collections::HashMap;
+
+use anyhow::{ensure, Result};
+use plonky2::plonk::circuit_data::CircuitData;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProvenCircuitOutput;
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
+use plonky2::prelude::*;
+use plonky2_field::types::Field64;
+
+use crate::circuit::{PrivateWitness, PublicInputs};
+
+type Proof = ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>;
+
+/// The key of the proof of a transaction with `public_inputs` and `witness`, by the circuit with
+/// digest `circuit_digest`.
+pub fn witness_commitment(
+    circuit_digest: HashOut<GoldilocksField>,
+    public_inputs: &PublicInputs<GoldilocksField>,
+    witness: &PrivateWitness<GoldilocksField>,
+) -> HashOut<GoldilocksField> {
+    let mut preimage = circuit_digest.elements.to_vec();
+    preimage.extend(public_inputs.to_public_inputs());
+    preimage.extend(witness.private_key);
+    preimage.extend([
+        GoldilocksField::from_canonical_usize(witness.index),
+        witness.token_id,
+        witness.token_amount,
+    ]);
+    preimage.extend(witness.timelock.to_fields::<GoldilocksField>());
+    for sibling in &witness.merkle_proof.siblings {
+        preimage.extend(sibling.elements);
+    }
+    let commitment = PoseidonHash::hash_no_pad(&preimage);
+    // The preimage holds the spend key.
+    #[cfg(feature = "zeroize")]
+    {
+        let mut preimage = preimage;
+        plonky2::util::zeroize::zeroize_field_elements(&mut preimage);
+    }
+    commitment
+}
+
+/// The proofs generated but not accepted yet, by `witness_commitment`.
+#[derive(Clone, Default)]
+pub struct ProofCache {
+    proofs: HashMap<HashOut<GoldilocksField>, Proof>,
+}
+
+impl ProofCache {
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    pub fn proofs(&self) -> impl Iterator<Item = &Proof> {
+        self.proofs.values()
+    }
+
+    pub fn get(&self, commitment: &HashOut<GoldilocksField>) -> Option<&Proof> {
+        self.proofs.get(commitment)
+    }
+
+    pub fn insert(&mut self, commitment: HashOut<GoldilocksField>, proof: Proof) {
+        self.proofs.insert(commitment, proof);
+    }
+
+    /// Drops the proof with key `commitment`, e.g. once the server has accepted it.
+    pub fn remove(&mut self, commitment: &HashOut<GoldilocksField>) -> Option<Proof> {
+        self.proofs.remove(commitment)
+    }
+
+    /// Keeps only the proofs for which `keep` holds.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Proof) -> bool) {
+        self.proofs.retain(|_, proof| keep(proof));
+    }
+
+    pub fn clear(&mut self) {
+        self.proofs.clear();
+    }
+
+    /// Serializes the cache for a local store: for each proof, its circuit digest and key, then the
+    /// length of the proof and the proof, as `ProvenCircuitOutput::to_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (commitment, proof) in &self.proofs {
+            let digest = proof.verifier_only.circuit_digest;
+            for x in digest.elements.iter().chain(&commitment.elements) {
+                bytes.extend(x.to_canonical_u64().to_le_bytes());
+            }
+            let proof = proof.to_bytes();
+            bytes.extend((proof.len() as u64).to_le_bytes());
+            bytes.extend(proof);
+        }
+        bytes
+    }
+
+    /// Reads a cache written by `to_bytes`, keeping the proofs by `circuit_data`: those by other
+    /// circuits, e.g. before an upgrade, would never be reused.
+    pub fn from_bytes(
+        mut bytes: &[u8],
+        circuit_data: &CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    ) -> Result<Self> {
+        let mut cache = Self::default();
+        while !bytes.is_empty() {
+            let digest = read_hash(&mut bytes)?;
+            let commitment = read_hash(&mut bytes)?;
+            let len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+            let proof = take(&mut bytes, usize::try_from(len)?)?;
+            if digest != circuit_data.verifier_only.circuit_digest {
+                continue;
+            }
+            let proof = Proof::from_bytes(proof, circuit_data.common.clone())?;
+            ensure!(
+                proof.verifier_only == circuit_data.verifier_only,
+                "cached proof with the wrong verifier data"
+            );
+            cache.insert(commitment, proof);
+        }
+        Ok(cache)
+    }
+}
+
+/// Splits the first `len` bytes off `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    ensure!(bytes.len() >= len, "truncated proof cache");
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn read_hash(bytes: &mut &[u8]) -> Result<HashOut<GoldilocksField>> {
+    let mut elements = [GoldilocksField::ZERO; 4];
+    for x in &mut elements {
+        let limb = u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap());
+        ensure!(
+            limb < GoldilocksField::ORDER,
+            "non-canonical field element in proof cache"
+        );
+        *x = GoldilocksField::from_canonical_u64(limb);
+    }
+    Ok(HashOut { elements })
+}
@@ -0,0 +1,152 @@
+//! A binary hash tree over the public inputs of the transactions an aggregated proof covers, an
+//! alternative to the nullifier accumulator for light clients.
+//!
+//! Each aggregation hashes together the nodes of its two inner proofs: the hash of a transaction
+//! proof's public inputs, or the root an aggregate exposes. The final proof exposes the root of the
+//! whole tree, so a client holding the public inputs of its own transaction and an inclusion proof
+//! checks that the final proof covers it without downloading the other transactions. The tree has
+//! the shape of the aggregation, which is unbalanced once batches are folded into the first pending
+//! proof, so inclusion proofs are as long as the depth of their leaf. Nodes share their children,
+//! so that building a node or cloning a tree doesn't copy the subtrees.
+
+use std::sync::Arc;
+
+use anyhow::{ensure, Result};
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
+use plonky2::prelude::*;
+
+use crate::circuit::PublicInputs;
+
+type F = GoldilocksField;
+
+/// The tree over the transactions covered by a proof, in order.
+#[derive(Clone, Debug)]
+pub enum PublicInputsTree {
+    /// The hash of a transaction's public inputs.
+    Leaf(HashOut<F>),
+    Node {
+        root: HashOut<F>,
+        num_leaves: usize,
+        children: Arc<[PublicInputsTree; 2]>,
+    },
+}
+
+/// The siblings of a leaf, from the bottom of the tree, and whether the leaf's path goes right at
+/// each of them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InclusionProof {
+    pub siblings: Vec<HashOut<F>>,
+    pub is_right: Vec<bool>,
+}
+
+impl PublicInputsTree {
+    pub fn leaf(public_inputs: &PublicInputs<F>) -> Self {
+        Self::Leaf(PoseidonHash::hash_or_noop(
+            &public_inputs.to_public_inputs(),
+        ))
+    }
+
+    pub fn node(left: &Self, right: &Self) -> Self {
+        Self::Node {
+            root: PoseidonHash::two_to_one(left.root(), right.root()),
+            num_leaves: left.num_leaves() + right.num_leaves(),
+            children: Arc::new([left.clone(), right.clone()]),
+        }
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        match self {
+            Self::Leaf(digest) => *digest,
+            Self::Node { root, .. } => *root,
+        }
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        match self {
+            Self::Leaf(_) => 1,
+            Self::Node { num_leaves, .. } => *num_leaves,
+        }
+    }
+
+    /// The inclusion proof of the `index`th transaction, or `None` if there are fewer.
+    pub fn prove(&self, mut index: usize) -> Option<InclusionProof> {
+        if index >= self.num_leaves() {
+            return None;
+        }
+        let mut siblings = Vec::new();
+        let mut is_right = Vec::new();
+        let mut tree = self;
+        while let Self::Node { children, .. } = tree {
+            let right = index >= children[0].num_leaves();
+            if right {
+                index -= children[0].num_leaves();
+            }
+            siblings.push(children[!right as usize].root());
+            is_right.push(right);
+            tree = &children[right as usize];
+        }
+        siblings.reverse();
+        is_right.reverse();
+        Some(InclusionProof { siblings, is_right })
+    }
+}
+
+/// Checks that `proof` proves the transaction with public inputs `public_inputs` to be in the tree
+/// with root `root`.
+pub fn verify_inclusion(
+    public_inputs: &PublicInputs<F>,
+    proof: &InclusionProof,
+    root: HashOut<F>,
+) -> Result<()> {
+    ensure!(
+        proof.siblings.len() == proof.is_right.len(),
+        "Malformed inclusion proof."
+    );
+    let mut digest = PublicInputsTree::leaf(public_inputs).root();
+    for (&sibling, &right) in proof.siblings.iter().zip(&proof.is_right) {
+        digest = if right {
+            PoseidonHash::two_to_one(sibling, digest)
+        } else {
+            PoseidonHash::two_to_one(digest, sibling)
+        };
+    }
+    ensure!(digest == root, "Invalid inclusion proof.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public_inputs(epoch: u32) -> PublicInputs<F> {
+        PublicInputs {
+            nullifier_value: HashOut::rand(),
+            new_leaf_value: HashOut::rand(),
+            merkle_root_value: HashOut::rand(),
+            current_epoch: epoch,
+        }
+    }
+
+    #[test]
+    fn test_inclusion_proofs() -> Result<()> {
+        let transactions: Vec<_> = (0..4).map(public_inputs).collect();
+        let leaf = |i: usize| PublicInputsTree::leaf(&transactions[i]);
+        // An earlier batch folded into the first proof of the next one, as `aggregate_and_prune`
+        // does.
+        let batch = PublicInputsTree::node(&leaf(0), &leaf(1));
+        let tree = PublicInputsTree::node(&PublicInputsTree::node(&batch, &leaf(2)), &leaf(3));
+        assert_eq!(tree.num_leaves(), 4);
+
+        for (i, transaction) in transactions.iter().enumerate() {
+            let proof = tree.prove(i).unwrap();
+            verify_inclusion(transaction, &proof, tree.root())?;
+            // A proof of one transaction doesn't prove another.
+            let other = &transactions[(i + 1) % transactions.len()];
+            assert!(verify_inclusion(other, &proof, tree.root()).is_err());
+        }
+        assert_eq!(tree.prove(0).unwrap().siblings.len(), 3);
+        assert_eq!(tree.prove(3).unwrap().siblings.len(), 1);
+        assert!(tree.prove(4).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Outsourced proving: a client which can't afford to prove, e.g. on a phone, sends its witness to
+//! a designated prover, encrypted so that whoever relays it, e.g. the server operator, can't read
+//! it or log it.
+//!
+//! The prover publishes an ecGFp5 public key `K = k G`. For each request, the client samples an
+//! ephemeral scalar `r` and encrypts its `PrivateWitness` with `duplex_encrypt`, under the session
+//! key hashed from `r K`, which the prover recovers from `R = r G` as `k R`. The public inputs of the
+//! request are absorbed into the session key as associated data, so that a relay swapping them for
+//! others, e.g. to redirect the change note, makes decryption fail. The prover necessarily
+//! learns the witness; the session key and the decrypted witness are erased once the proof is made
+//! with the `zeroize` feature.
+//!
+//! A client can also hand a prover its witness in the clear, as a `DelegatedWitness`: the note it
+//! spends and the change note are bound to one-time keys, so the prover learns the amounts and a
+//! key which only spends that note, but not the client's long-term key.
+
+use core::num::NonZeroU32;
+
+use anyhow::{anyhow, ensure, Result};
+use num::BigUint;
+use plonky2::curve::ecgfp5::{sample_scalar, CurvePoint};
+use plonky2::curve::stealth::hash_curve_point;
+use plonky2::hash::duplex::{duplex_decrypt, duplex_encrypt, DuplexCiphertext};
+use plonky2::hash::poseidon::PoseidonPermutation;
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
+use plonky2::prelude::*;
+#[cfg(feature = "zeroize")]
+use plonky2::util::zeroize::zeroize_field_elements;
+use rand::Rng;
+
+use crate::circuit;
+use crate::circuit::{PrivateWitness, PublicInputs, Timelock, WiringTarget};
+
+type F = GoldilocksField;
+
+/// A witness encrypted to a `RemoteProver`, with the public inputs it proves.
+#[derive(Clone, Debug)]
+pub struct ProvingRequest {
+    pub ephemeral_key: CurvePoint<F>,
+    pub public_input: PublicInputs<F>,
+    pub encrypted_witness: DuplexCiphertext<F>,
+}
+
+/// A witness made by `Client::prepare_delegated_witness`, which a delegated prover proves and
+/// submits to the server.
+#[derive(Clone, Debug)]
+pub struct DelegatedWitness {
+    pub public_input: PublicInputs<F>,
+    pub witness: PrivateWitness<F>,
+}
+
+/// Erases secret values, with the `zeroize` feature.
+fn erase(values: &mut [F]) {
+    #[cfg(feature = "zeroize")]
+    zeroize_field_elements(values);
+    #[cfg(not(feature = "zeroize"))]
+    let _ = values;
+}
+
+/// The key encrypting a witness proving `public_input`, bound to it as associated data.
+fn session_key(shared_point: &CurvePoint<F>, public_input: &PublicInputs<F>) -> HashOut<F> {
+    let mut elements = hash_curve_point::<F, PoseidonHash>(shared_point)
+        .elements
+        .to_vec();
+    elements.extend(public_input.to_public_inputs());
+    let key = PoseidonHash::hash_no_pad(&elements);
+    erase(&mut elements);
+    key
+}
+
+fn witness_to_elements(witness: &PrivateWitness<F>) -> Vec<F> {
+    let mut elements = witness.private_key.to_vec();
+    elements.extend([
+        F::from_canonical_usize(witness.index),
+        witness.token_id,
+        witness.token_amount,
+    ]);
+    elements.extend(witness.timelock.to_fields::<F>());
+    for sibling in &witness.merkle_proof.siblings {
+        elements.extend(sibling.elements);
+    }
+    elements
+}
+
+fn witness_from_elements(elements: &[F], tree_height: usize) -> Result<PrivateWitness<F>> {
+    ensure!(
+        elements.len() == 9 + 4 * tree_height,
+        "the witness isn't for a tree of height {}",
+        tree_height
+    );
+    let index = elements[4].to_canonical_u64();
+    ensure!(index < 1 << tree_height, "leaf index out of range");
+    let [valid_after, valid_before] = [elements[7], elements[8]].map(|x| x.to_canonical_u64());
+    ensure!(
+        valid_after <= u32::MAX as u64 && valid_before <= u32::MAX as u64,
+        "timelock out of range"
+    );
+    Ok(PrivateWitness {
+        private_key: elements[..4].try_into().unwrap(),
+        index: index as usize,
+        token_id: elements[5],
+        token_amount: elements[6],
+        timelock: Timelock {
+            valid_after: valid_after as u32,
+            valid_before: NonZeroU32::new(valid_before as u32),
+        },
+        merkle_proof: MerkleProof {
+            siblings: elements[9..].chunks(4).map(HashOut::from_partial).collect(),
+        },
+    })
+}
+
+impl ProvingRequest {
+    /// Encrypts `witness` to the prover whose public key is `prover_key`, with a fresh session key.
+    pub fn new<R: Rng + ?Sized>(
+        prover_key: &CurvePoint<F>,
+        public_input: PublicInputs<F>,
+        witness: &PrivateWitness<F>,
+        rng: &mut R,
+    ) -> Self {
+        let ephemeral_secret = sample_scalar(rng);
+        let mut key = session_key(&prover_key.mul(&ephemeral_secret), &public_input);
+        let mut plaintext = witness_to_elements(witness);
+        // Each session key encrypts a single message, so the nonce can be fixed.
+        let encrypted_witness = duplex_encrypt::<F, PoseidonPermutation>(key, F::ZERO, &plaintext);
+        erase(&mut plaintext);
+        erase(&mut key.elements);
+        Self {
+            ephemeral_key: CurvePoint::generator().mul(&ephemeral_secret),
+            public_input,
+            encrypted_witness,
+        }
+    }
+}
+
+/// A prover to which clients outsource their transaction proofs.
+pub struct RemoteProver {
+    secret: BigUint,
+    public_key: CurvePoint<F>,
+    circuit_data: CircuitData<F, PoseidonGoldilocksConfig, 2>,
+    wiring: WiringTarget,
+}
+
+impl RemoteProver {
+    pub fn new<R: Rng + ?Sized>(
+        rng: &mut R,
+        circuit_data: CircuitData<F, PoseidonGoldilocksConfig, 2>,
+        wiring: WiringTarget,
+    ) -> Self {
+        let secret = sample_scalar(rng);
+        Self {
+            public_key: CurvePoint::generator().mul(&secret),
+            secret,
+            circuit_data,
+            wiring,
+        }
+    }
+
+    /// The key clients encrypt their witnesses to.
+    pub fn public_key(&self) -> CurvePoint<F> {
+        self.public_key
+    }
+
+    /// Decrypts the witness of `request` and proves it. Fails if the request wasn't encrypted to
+    /// this prover or was tampered with, including its public inputs.
+    pub fn prove(
+        &self,
+        request: &ProvingRequest,
+    ) -> Result<ProvenCircuitOutput<F, PoseidonGoldilocksConfig, 2>> {
+        ensure!(
+            request.ephemeral_key.is_in_subgroup() && !request.ephemeral_key.is_identity,
+            "invalid ephemeral key"
+        );
+        let mut key = session_key(
+            &request.ephemeral_key.mul(&self.secret),
+            &request.public_input,
+        );
+        let plaintext =
+            duplex_decrypt::<F, PoseidonPermutation>(key, F::ZERO, &request.encrypted_witness);
+        erase(&mut key.elements);
+        let mut plaintext = plaintext
+            .map_err(|err| anyhow!("the witness or the public inputs were tampered with: {err}"))?;
+        let tree_height = self.wiring.merkle_proof_target.siblings.len();
+        let witness = witness_from_elements(&plaintext, tree_height);
+        erase(&mut plaintext);
+        circuit::gen_private_proof(
+            &self.circuit_data,
+            request.public_input.clone(),
+            witness?,
+            &self.wiring,
+        )
+    }
+
+    /// Proves a witness delegated in the clear.
+    pub fn prove_delegated(
+        &self,
+        delegated: DelegatedWitness,
+    ) -> Result<ProvenCircuitOutput<F, PoseidonGoldilocksConfig, 2>> {
+        circuit::gen_private_proof(
+            &self.circuit_data,
+            delegated.public_input,
+            delegated.witness,
+            &self.wiring,
+        )
+    }
+}
@@ -1,27 +1,166 @@
 use std::borrow::Borrow;
-use std::ops::{Deref, Index};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::{Deref, Index, Range, RangeInclusive};
 
-use anyhow::{Error, Result};
+use anyhow::{anyhow, ensure, Error, Result};
 use log::info;
+use plonky2::curve::ecgfp5::CurvePoint;
 use plonky2::hash::hash_types::{HashOut, RichField};
-use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData, CommonCircuitData};
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProvenCircuitOutput};
+use plonky2::plonk::proof_format;
+use plonky2::plonk::public_inputs_layout::PublicInputsLayout;
 use plonky2_field::extension::Extendable;
 use plonky2_field::goldilocks_field::GoldilocksField;
+use rand::rngs::OsRng;
 
+use crate::admission::{AdmissionPolicy, AdmitAll, Submission};
+use crate::attestation::BatchAttestation;
 use crate::circuit;
 use crate::circuit::{
-    gen_recursive_circuit, recursive_circuit, ProofTuple, PublicInputs, WiringTarget,
+    gen_recursive_circuit, nullifier_accumulator, recursive_circuit, NoteInsertion, ProofKind,
+    PublicInputs, RecursiveWiringTargets, WiringTarget, NEW_ROOT, NULLIFIER_ACCUMULATOR_END,
+    OLD_ROOT,
 };
+use crate::ethereum::{Groth16Proof, StateUpdate};
+use crate::manifest::{ProofManifest, SignedManifest};
+use crate::mempool::{Mempool, PendingTransaction};
+use crate::public_inputs_tree::{InclusionProof, PublicInputsTree};
+use crate::signing::SigningKey;
 use crate::state::State;
+use crate::viewing::NoteMemo;
+
+/// A version of the transaction circuit whose proofs the server accepts.
+struct CircuitVersion {
+    data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    // proofs of this version are rejected once this many transactions have been accepted; `None`
+    // for the current version
+    accepted_until: Option<usize>,
+}
+
+/// A circuit aggregating two proofs, built once for each shape and kind of inner proofs and config
+/// and reused by every aggregation of that shape.
+struct RecursionCircuit {
+    inner_common: [CommonCircuitData<GoldilocksField, 2>; 2],
+    kinds: [ProofKind; 2],
+    config: CircuitConfig,
+    data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    wiring: RecursiveWiringTargets<2>,
+}
+
+/// A proof held by the server, with the digests of the transaction circuits whose proofs it covers.
+#[derive(Clone)]
+pub struct TaggedProof {
+    pub proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    pub circuit_versions: Vec<HashOut<GoldilocksField>>,
+    /// The indices in `Server::events` of the transactions it covers.
+    pub events: Range<usize>,
+    /// The tree over the public inputs of those transactions, whose root an aggregate exposes.
+    pub public_inputs_tree: PublicInputsTree,
+    /// For a transaction proof, the insertion of its new note, which aggregating it proves.
+    pub insertion: Option<NoteInsertion<GoldilocksField>>,
+}
+
+impl TaggedProof {
+    /// Whether it is a transaction proof, as applied, or an aggregate.
+    pub fn kind(&self) -> ProofKind {
+        match self.insertion {
+            Some(_) => ProofKind::Transaction,
+            None => ProofKind::Aggregate,
+        }
+    }
+
+    /// The note tree roots before and after the transactions it covers, as proven by an aggregate.
+    pub fn roots(&self) -> (HashOut<GoldilocksField>, HashOut<GoldilocksField>) {
+        match &self.insertion {
+            Some(insertion) => (insertion.old_root, insertion.new_root),
+            None => {
+                let public_inputs = &self.proof.proof().public_inputs;
+                (
+                    HashOut::from_partial(&public_inputs[OLD_ROOT.range()]),
+                    HashOut::from_partial(&public_inputs[NEW_ROOT.range()]),
+                )
+            }
+        }
+    }
+}
+
+/// Identifies a state of the server taken by `Server::snapshot`, which it can be rolled back to.
+pub type SnapshotId = usize;
+
+/// The number of snapshots a server keeps by default, see `Server::set_max_snapshots`.
+pub const DEFAULT_MAX_SNAPSHOTS: usize = 16;
+
+/// What a snapshot keeps of the server, for `Server::rollback_to` to restore.
+struct Snapshot {
+    id: SnapshotId,
+    state: State,
+    proofs: Vec<TaggedProof>,
+    num_events: usize,
+    num_attestations: usize,
+    admission_policy: Box<dyn AdmissionPolicy>,
+}
+
+/// A rollback of the server to an earlier snapshot, as passed to the hooks registered with
+/// `Server::on_rollback`.
+#[derive(Clone, Debug)]
+pub struct Rollback {
+    pub snapshot: SnapshotId,
+    /// The note tree root the server is back at.
+    pub restored_root: HashOut<GoldilocksField>,
+    /// The indices in `Server::events` of the transactions reverted.
+    pub reverted_events: Range<usize>,
+    /// The note tree roots which no longer exist: proofs made against them are rejected, and
+    /// clients must prove their transactions again.
+    pub invalidated_roots: Vec<HashOut<GoldilocksField>>,
+    /// The number of transactions which were waiting in the mempool, and must be submitted again.
+    pub num_dropped_transactions: usize,
+}
 
 pub struct Server {
     state: State,
 
     config: CircuitConfig,
+    // config of the outermost recursion layer, whose proof is the one verified on chain
+    final_config: CircuitConfig,
     tree_height: usize,
-    circuit_data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
-    pub proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    // the transaction circuits accepted, by circuit digest
+    circuits: HashMap<HashOut<GoldilocksField>, CircuitVersion>,
+    // the circuits no longer accepted, kept while a snapshot from before their retirement remains
+    retired_circuits: HashMap<HashOut<GoldilocksField>, CircuitVersion>,
+    current_circuit: HashOut<GoldilocksField>,
+    // proofs not aggregated yet; the first one may be the aggregate of all earlier proofs
+    pub proofs: Vec<TaggedProof>,
+    // public inputs of every accepted transaction, in order, which remain after their proofs are
+    // pruned
+    events: Vec<PublicInputs<GoldilocksField>>,
+    // the note tree root before each event
+    event_roots: Vec<HashOut<GoldilocksField>>,
+    // transactions verified but not applied yet
+    mempool: Mempool,
+    // once there are more proofs than this, they are aggregated into one
+    max_pending_proofs: Option<usize>,
+    // the recursion circuits built so far; aggregations only need `&self`, hence the `RefCell`
+    recursion_circuits: RefCell<Vec<RecursionCircuit>>,
+    // signs what the server publishes, e.g. proof manifests
+    // drawn at random by `new`, see `set_operator_key`
+    operator_key: SigningKey,
+    // the operator's attestation of each batch aggregated by `aggregate_and_prune`, by batch id
+    attestations: Vec<BatchAttestation>,
+    // consulted before verifying each submission
+    admission_policy: Box<dyn AdmissionPolicy>,
+    // the epoch transactions are made in, which timelocked notes are checked against
+    current_epoch: u32,
+    // the memos published by note owners for their viewing keys, in order
+    memos: Vec<NoteMemo>,
+    // the states which can be rolled back to, oldest first; there are at most `max_snapshots`
+    snapshots: VecDeque<Snapshot>,
+    max_snapshots: usize,
+    next_snapshot_id: SnapshotId,
+    // called after each rollback, e.g. to notify clients
+    rollback_hooks: Vec<Box<dyn FnMut(&Rollback) + Send>>,
 }
 
 impl Server {
@@ -36,65 +175,765 @@ impl Server {
             { D },
         >(&config, tree_height);
 
+        let current_circuit = circuit_data.verifier_only.circuit_digest;
+        let circuits = HashMap::from([(
+            current_circuit,
+            CircuitVersion {
+                data: circuit_data,
+                accepted_until: None,
+            },
+        )]);
+
         Self {
             state,
+            final_config: config.clone(),
             config,
             tree_height,
-            circuit_data,
+            circuits,
+            retired_circuits: HashMap::new(),
+            current_circuit,
             proofs: vec![],
+            events: vec![],
+            event_roots: vec![],
+            mempool: Mempool::default(),
+            max_pending_proofs: None,
+            recursion_circuits: RefCell::new(vec![]),
+            operator_key: SigningKey::new(&mut OsRng),
+            attestations: vec![],
+            admission_policy: Box::new(AdmitAll),
+            current_epoch: 0,
+            memos: vec![],
+            snapshots: VecDeque::new(),
+            max_snapshots: DEFAULT_MAX_SNAPSHOTS,
+            next_snapshot_id: 0,
+            rollback_hooks: vec![],
         }
     }
 
+    /// Verifies a submitted transaction and applies it, returning the index of its new note.
+    /// Submissions not signed by their sender, or which the admission policy rejects, are never
+    /// verified. Fails while transactions are waiting in the mempool: they are proven against the
+    /// current root, which applying this one would change, so the mempool's batch must be applied
+    /// first, with `apply_batch`. A server either applies transactions as they come or batches
+    /// them.
     pub fn verify_and_update_state(
         &mut self,
-        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        submission: &Submission,
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
         public_inp: PublicInputs<GoldilocksField>,
     ) -> Result<(usize)> {
-        let current_utxo_root = self.state.private_utxo_tree.cap.0[0];
+        ensure!(
+            self.mempool.is_empty(),
+            "transactions are waiting in the mempool; apply them first"
+        );
+        let proof = self.verify_transaction(submission, proof, &public_inp)?;
+        let new_index = self.apply_transaction(proof, public_inp)?;
+        self.prune_if_full()?;
+        Ok(new_index)
+    }
+
+    /// Verifies submitted transactions and applies them together, e.g. the two legs of a transfer,
+    /// returning the indices of their new notes. They must all be proven against the current root
+    /// and spend distinct notes, and none is applied unless all are accepted. Fails while
+    /// transactions are waiting in the mempool, as `verify_and_update_state` does.
+    pub fn verify_and_update_state_all(
+        &mut self,
+        transactions: Vec<(
+            Submission,
+            ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+            PublicInputs<GoldilocksField>,
+        )>,
+    ) -> Result<Vec<usize>> {
+        ensure!(
+            self.mempool.is_empty(),
+            "transactions are waiting in the mempool; apply them first"
+        );
+        let nullifiers: HashSet<_> = transactions
+            .iter()
+            .map(|(_, _, public_inp)| public_inp.nullifier_value)
+            .collect();
+        ensure!(
+            nullifiers.len() == transactions.len(),
+            "two of the transactions spend the same note"
+        );
+        ensure!(
+            self.state.remaining_capacity() >= transactions.len(),
+            "the note tree is full"
+        );
+        // Every transaction is checked against the state before the first is applied.
+        let verified = transactions
+            .into_iter()
+            .map(|(submission, proof, public_inp)| {
+                let proof = self.verify_transaction(&submission, proof, &public_inp)?;
+                Ok((proof, public_inp))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let new_indices = verified
+            .into_iter()
+            .map(|(proof, public_inp)| self.apply_transaction(proof, public_inp))
+            .collect::<Result<_>>()?;
+        self.prune_if_full()?;
+        Ok(new_indices)
+    }
+
+    /// Verifies a submitted transaction and adds it to the mempool, to be applied with the rest of
+    /// the batch by `apply_batch`. It must be proven against the current root, like every other
+    /// transaction of the batch, and not spend the same note as any of them.
+    pub fn submit_to_mempool(
+        &mut self,
+        submission: &Submission,
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        public_inp: PublicInputs<GoldilocksField>,
+    ) -> Result<()> {
+        // Checking for a conflict first saves verifying a transaction which can't go in.
+        ensure!(
+            !self.mempool.contains(&public_inp.nullifier_value),
+            "conflicts with a pending transaction: nullifier {}",
+            public_inp.nullifier_value
+        );
+        let proof = self.verify_transaction(submission, proof, &public_inp)?;
+        self.mempool.insert(PendingTransaction {
+            submission: submission.clone(),
+            proof,
+            public_inputs: public_inp,
+        })
+    }
+
+    /// Applies the transactions in the mempool, in the mempool's order, then aggregates the pending
+    /// proofs if there are too many of them. Returns the indices in `events` of the transactions
+    /// applied. The state before the batch is snapshotted, so that `rollback_last_batch` can undo
+    /// it.
+    pub fn apply_batch(&mut self) -> Result<Range<usize>> {
+        self.snapshot();
+        let start = self.events.len();
+        for transaction in self.mempool.take_batch() {
+            self.apply_transaction(transaction.proof, transaction.public_inputs)?;
+        }
+        info!(
+            "applied a batch of {} transactions",
+            self.events.len() - start
+        );
+        self.prune_if_full()?;
+        Ok(start..self.events.len())
+    }
+
+    /// The transactions verified but not applied yet.
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
 
+    /// Records the current state, for `rollback_to` to restore. Once there are more than
+    /// `max_snapshots`, the oldest one is dropped.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.push_back(Snapshot {
+            id,
+            state: self.state.clone(),
+            proofs: self.proofs.clone(),
+            num_events: self.events.len(),
+            num_attestations: self.attestations.len(),
+            admission_policy: self.admission_policy.clone_box(),
+        });
+        if self.snapshots.len() > self.max_snapshots {
+            self.snapshots.pop_front();
+            self.drop_retired_circuits();
+        }
+        id
+    }
+
+    /// The latest snapshot, e.g. the one taken before the last batch.
+    pub fn last_snapshot(&self) -> Option<SnapshotId> {
+        self.snapshots.back().map(|snapshot| snapshot.id)
+    }
+
+    /// Keeps only the latest `max_snapshots` snapshots, `DEFAULT_MAX_SNAPSHOTS` by default. Each
+    /// holds a copy of the state, and the circuits retired since it was taken.
+    pub fn set_max_snapshots(&mut self, max_snapshots: usize) {
+        self.max_snapshots = max_snapshots;
+        let excess = self.snapshots.len().saturating_sub(max_snapshots);
+        self.snapshots.drain(..excess);
+        self.drop_retired_circuits();
+    }
+
+    /// Reverts the server to the state of snapshot `id`, as if the transactions applied since had
+    /// never been, e.g. after a reorg of the chain the batches were published on. The note and
+    /// nullifier trees, the proofs, the attestations, the admission policy's quotas and the
+    /// circuits retired since are restored, the memos of the reverted notes dropped, and the
+    /// mempool emptied, as its transactions are proven against a root which no longer exists. Later
+    /// snapshots are discarded, while `id` itself can be rolled back to again. The hooks registered
+    /// with `on_rollback` are then called.
+    pub fn rollback_to(&mut self, id: SnapshotId) -> Result<Rollback> {
+        let position = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.id == id)
+            .ok_or_else(|| anyhow!("unknown snapshot {}", id))?;
+        self.snapshots.truncate(position + 1);
+        let snapshot = &self.snapshots[position];
+
+        let mut invalidated_roots = self.event_roots[snapshot.num_events..].to_vec();
+        invalidated_roots.push(self.state.private_utxo_tree.cap.0[0]);
+        // The root at the snapshot is the first one above, unless nothing happened since.
+        invalidated_roots.retain(|root| *root != snapshot.state.private_utxo_tree.cap.0[0]);
+        invalidated_roots.dedup();
+
+        let reverted_events = snapshot.num_events..self.events.len();
+        self.state = snapshot.state.clone();
+        self.proofs = snapshot.proofs.clone();
+        self.events.truncate(snapshot.num_events);
+        self.event_roots.truncate(snapshot.num_events);
+        self.attestations.truncate(snapshot.num_attestations);
+        self.admission_policy = snapshot.admission_policy.clone_box();
+        // The circuits accepted at the snapshot are accepted again, until the same transaction.
+        let num_events = snapshot.num_events;
+        let restored: Vec<_> = self
+            .retired_circuits
+            .iter()
+            .filter(|(_, version)| {
+                version
+                    .accepted_until
+                    .map_or(false, |until| num_events < until)
+            })
+            .map(|(digest, _)| *digest)
+            .collect();
+        for digest in restored {
+            let version = self.retired_circuits.remove(&digest).unwrap();
+            self.circuits.insert(digest, version);
+        }
+        let state = &self.state;
+        self.memos
+            .retain(|memo| state.find_private_utxo(&memo.leaf).is_some());
+        let num_dropped_transactions = self.mempool.take_batch().len();
+        info!(
+            "rolled back to snapshot {}: reverted {} transactions, dropped {} pending",
+            id,
+            reverted_events.len(),
+            num_dropped_transactions
+        );
+
+        let rollback = Rollback {
+            snapshot: id,
+            restored_root: self.state.private_utxo_tree.cap.0[0],
+            reverted_events,
+            invalidated_roots,
+            num_dropped_transactions,
+        };
+        for hook in &mut self.rollback_hooks {
+            hook(&rollback);
+        }
+        Ok(rollback)
+    }
+
+    /// Rolls back to the latest snapshot, i.e. undoes the last batch applied by `apply_batch` unless
+    /// a snapshot was taken since.
+    pub fn rollback_last_batch(&mut self) -> Result<Rollback> {
+        let id = self
+            .last_snapshot()
+            .ok_or_else(|| anyhow!("no batch to roll back"))?;
+        let rollback = self.rollback_to(id)?;
+        // Rolling back again undoes the batch before it.
+        self.snapshots.pop_back();
+        self.drop_retired_circuits();
+        Ok(rollback)
+    }
+
+    /// Registers `hook` to be called after each rollback, e.g. for clients to drop the notes and
+    /// proofs of reverted transactions, see `Client::resync`.
+    pub fn on_rollback(&mut self, hook: impl FnMut(&Rollback) + Send + 'static) {
+        self.rollback_hooks.push(Box::new(hook));
+    }
+
+    /// Checks the submission's signature and the admission policy, then verifies the proof,
+    /// returning it with the verifier data of the circuit it was checked against.
+    fn verify_transaction(
+        &mut self,
+        submission: &Submission,
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        public_inp: &PublicInputs<GoldilocksField>,
+    ) -> Result<ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+        // The signature binds the sender and the fee to this transaction, so that the policy charges
+        // the right sender.
+        submission.verify(public_inp)?;
+        self.admission_policy
+            .admit(submission, self.proofs.len() + self.mempool.len())
+            .map_err(|err| anyhow!("submission rejected: {}", err))?;
+
+        // The proof is checked against the registered circuit with the digest it claims, never
+        // against the verifier data it comes with.
+        let digest = proof.verifier_only.circuit_digest;
+        let version = self
+            .circuits
+            .get(&digest)
+            .ok_or_else(|| anyhow!("unknown or retired circuit version {}", digest))?;
+        let circuit_data = &version.data;
+
+        // Reject malformed proofs before doing any work on them: a client could otherwise send
+        // absurdly large vectors which are only rejected after hashing them.
+        proof.proof.validate_shape(&circuit_data.common)?;
+
+        circuit_data.verify_with(proof.proof.clone(), |public_inputs| {
+            self.check_public_inputs(public_inp, public_inputs)
+        })?;
+        Ok(ProvenCircuitOutput::new(
+            proof.proof,
+            circuit_data.verifier_only.clone(),
+            circuit_data.common.clone(),
+        ))
+    }
+
+    /// Applies a verified transaction, returning the index of its new note.
+    fn apply_transaction(
+        &mut self,
+        proof: ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        public_inp: PublicInputs<GoldilocksField>,
+    ) -> Result<usize> {
+        let old_root = self.state.private_utxo_tree.cap.0[0];
+        let new_index = self.state.add_private_utxo(public_inp.new_leaf_value)?;
+        self.event_roots.push(old_root);
+        self.state.add_nullify_utxo(public_inp.nullifier_value);
+        let insertion = NoteInsertion::new(
+            new_index,
+            public_inp.new_leaf_value,
+            self.state.private_utxo_merkle_proof(new_index),
+        );
+        info!(
+            "transaction {}: nullifier {}, new note {} at {}",
+            self.events.len(),
+            public_inp.nullifier_value,
+            public_inp.new_leaf_value,
+            new_index
+        );
+        //  push proof to vec
+        self.proofs.push(TaggedProof {
+            circuit_versions: vec![proof.verifier_only.circuit_digest],
+            proof,
+            events: self.events.len()..self.events.len() + 1,
+            public_inputs_tree: PublicInputsTree::leaf(&public_inp),
+            insertion: Some(insertion),
+        });
+        self.events.push(public_inp);
+        self.drop_expired_circuits();
+        Ok(new_index)
+    }
+
+    /// Aggregates and prunes the pending proofs if there are more than `max_pending_proofs`.
+    fn prune_if_full(&mut self) -> Result<()> {
+        if self
+            .max_pending_proofs
+            .map_or(false, |max| self.proofs.len() > max)
+        {
+            self.aggregate_and_prune()?;
+        }
+        Ok(())
+    }
+
+    /// Makes `circuit_data` the current version of the transaction circuit. Proofs of the previous
+    /// version are still accepted for the next `migration_window` transactions, so that clients
+    /// have time to upgrade. Returns the digest of the new version.
+    pub fn upgrade_circuit(
+        &mut self,
+        circuit_data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        migration_window: usize,
+    ) -> HashOut<GoldilocksField> {
+        let digest = circuit_data.verifier_only.circuit_digest;
+        if digest != self.current_circuit {
+            let previous = self.circuits.get_mut(&self.current_circuit).unwrap();
+            previous.accepted_until = Some(self.events.len() + migration_window);
+        }
+        self.circuits.insert(
+            digest,
+            CircuitVersion {
+                data: circuit_data,
+                accepted_until: None,
+            },
+        );
+        self.current_circuit = digest;
+        self.drop_expired_circuits();
+        info!("circuit version {} is now current", digest);
+        digest
+    }
+
+    /// Accepts proofs of `circuit_data` alongside the current version, e.g. `archived_note_circuit`,
+    /// until it is retired by `upgrade_circuit`. Returns its digest.
+    pub fn add_circuit(
+        &mut self,
+        circuit_data: CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    ) -> HashOut<GoldilocksField> {
+        let digest = circuit_data.verifier_only.circuit_digest;
+        self.circuits.insert(
+            digest,
+            CircuitVersion {
+                data: circuit_data,
+                accepted_until: None,
+            },
+        );
+        digest
+    }
+
+    /// The digest of the current version of the transaction circuit.
+    pub fn current_circuit(&self) -> HashOut<GoldilocksField> {
+        self.current_circuit
+    }
+
+    /// Whether proofs of the transaction circuit with digest `digest` are accepted.
+    pub fn accepts_circuit(&self, digest: &HashOut<GoldilocksField>) -> bool {
+        self.circuits.contains_key(digest)
+    }
+
+    /// The versions of the proof format the server reads, for clients to negotiate theirs.
+    pub fn proof_format_versions(&self) -> RangeInclusive<u16> {
+        proof_format::supported_versions()
+    }
+
+    /// Decodes a proof of the transaction circuit with digest `digest`, sent as
+    /// `ProofWithPublicInputs::to_versioned_bytes`. Formats the server doesn't read are rejected
+    /// rather than misread.
+    pub fn decode_proof(
+        &self,
+        digest: &HashOut<GoldilocksField>,
+        bytes: &[u8],
+    ) -> Result<ProvenCircuitOutput<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+        let version = self
+            .circuits
+            .get(digest)
+            .ok_or_else(|| anyhow!("unknown or retired circuit version {}", digest))?;
+        let proof = ProofWithPublicInputs::from_versioned_bytes(bytes, &version.data.common)?;
+        Ok(ProvenCircuitOutput::new(
+            proof,
+            version.data.verifier_only.clone(),
+            version.data.common.clone(),
+        ))
+    }
+
+    fn drop_expired_circuits(&mut self) {
+        let num_events = self.events.len();
+        let expired: Vec<_> = self
+            .circuits
+            .iter()
+            .filter(|(_, version)| {
+                version
+                    .accepted_until
+                    .map_or(false, |until| until <= num_events)
+            })
+            .map(|(digest, _)| *digest)
+            .collect();
+        for digest in expired {
+            let version = self.circuits.remove(&digest).unwrap();
+            self.retired_circuits.insert(digest, version);
+        }
+        self.drop_retired_circuits();
+    }
+
+    /// Drops the retired circuits which no snapshot accepts, as no rollback can restore them.
+    fn drop_retired_circuits(&mut self) {
+        let oldest = self.snapshots.front().map(|snapshot| snapshot.num_events);
+        self.retired_circuits.retain(|_, version| {
+            oldest.map_or(false, |num_events| {
+                version
+                    .accepted_until
+                    .map_or(false, |until| num_events < until)
+            })
+        });
+    }
+
+    /// The application-level checks on a submission, which run before the proof is verified.
+    fn check_public_inputs(
+        &self,
+        public_inp: &PublicInputs<GoldilocksField>,
+        public_inputs: &[GoldilocksField],
+    ) -> Result<()> {
+        // The claimed public inputs must be the ones the proof commits to.
+        if &PublicInputs::from_public_inputs(public_inputs)? != public_inp {
+            return Err(Error::msg("public inputs don't match the proof"));
+        }
+        self.check_transaction(public_inp)
+    }
+
+    /// Checks the public inputs of a transaction against the current state, as
+    /// `verify_and_update_state` does, e.g. to find out before proving that the root is stale or
+    /// the note is already spent.
+    pub fn check_transaction(&self, public_inp: &PublicInputs<GoldilocksField>) -> Result<()> {
+        let current_utxo_root = self.state.private_utxo_tree.cap.0[0];
         if current_utxo_root != public_inp.merkle_root_value {
             return Err(Error::msg("wrong merkle roof value"));
         }
 
-        match self.circuit_data.verify(proof.0.clone()) {
-            Ok(..) => {
-                self.state.add_nullify_utxo(public_inp.nullifier_value);
-                let new_index = self.state.add_private_utxo(public_inp.new_leaf_value);
-                //  push proof to vec
-                self.proofs.push(proof);
-                Ok(new_index)
-            }
-            Err(err) => Err(err),
+        if self.state.is_nullified(public_inp.nullifier_value) {
+            return Err(anyhow!(
+                "utxo already spent: nullifier {}",
+                public_inp.nullifier_value
+            ));
+        }
+
+        if public_inp.current_epoch != self.current_epoch {
+            return Err(Error::msg("wrong epoch"));
+        }
+
+        // The transactions in the mempool take their notes' slots first.
+        if self.state.remaining_capacity() <= self.mempool.len() {
+            return Err(Error::msg("the note tree is full"));
+        }
+        Ok(())
+    }
+
+    /// Sets the config of the outermost recursion layer. It can trade query rounds for more
+    /// proof-of-work or a higher rate to make the final proof cheaper to verify, as long as it keeps
+    /// the security of the inner layers.
+    pub fn set_final_config(&mut self, final_config: CircuitConfig) -> Result<()> {
+        let security_bits = final_config.fri_config.conjectured_security_bits();
+        if security_bits < self.config.security_bits {
+            return Err(Error::msg(format!(
+                "final config has {} bits of security, less than the {} required",
+                security_bits, self.config.security_bits
+            )));
         }
+        self.final_config = final_config;
+        Ok(())
+    }
+
+    pub fn get_recursive_proof(&self, left: usize, right: usize) -> TaggedProof {
+        self.aggregate_proofs(left, right, &self.final_config)
+            .unwrap()
     }
 
-    pub fn get_recursive_proof(
+    /// Aggregates all pending proofs into one and drops them, keeping only the aggregate and the
+    /// events. The aggregate is the first pending proof from then on, so later aggregations cover
+    /// it along with newer proofs. The pending proofs are kept if aggregation fails.
+    ///
+    /// The operator signs the transactions applied since the previous aggregate as a new batch, and
+    /// the attestation is returned and kept in `attestations`.
+    pub fn aggregate_and_prune(&mut self) -> Result<Option<&BatchAttestation>> {
+        if self.proofs.len() < 2 {
+            return Ok(None);
+        }
+        // Each recursive proof is verified as it's generated, so the aggregate is known valid.
+        let aggregate = self.aggregate_proofs(0, self.proofs.len() - 1, &self.config)?;
+        info!("pruned {} proofs into an aggregate", self.proofs.len());
+        // The aggregate also covers the earlier batches, which were attested already.
+        let start = self
+            .attestations
+            .last()
+            .map_or(aggregate.events.start, |attestation| attestation.events.end);
+        let events = start..aggregate.events.end;
+        let old_root = self.event_roots[start];
+        let (_, new_root) = aggregate.roots();
+        let attestation = BatchAttestation::sign(
+            self.attestations.len(),
+            events,
+            old_root,
+            new_root,
+            &aggregate.proof,
+            &self.operator_key,
+            &mut OsRng,
+        );
+        self.attestations.push(attestation);
+        self.proofs = vec![aggregate];
+        Ok(self.attestations.last())
+    }
+
+    /// The operator's attestations of the batches aggregated so far, by batch id.
+    pub fn attestations(&self) -> &[BatchAttestation] {
+        &self.attestations
+    }
+
+    /// The epoch in which transactions are made, as used by note timelocks.
+    pub fn current_epoch(&self) -> u32 {
+        self.current_epoch
+    }
+
+    /// Moves to epoch `epoch`, e.g. as time passes. Later transactions must claim it, and can only
+    /// spend notes whose timelock allows it.
+    pub fn set_current_epoch(&mut self, epoch: u32) {
+        self.current_epoch = epoch;
+    }
+
+    /// Has `verify_and_update_state` consult `policy` before verifying each submission. The
+    /// default admits every submission.
+    pub fn set_admission_policy(&mut self, policy: impl AdmissionPolicy + 'static) {
+        self.admission_policy = Box::new(policy);
+    }
+
+    /// Makes `verify_and_update_state` aggregate and prune the pending proofs once there are more
+    /// than `max_pending_proofs` of them. `None`, the default, never prunes.
+    pub fn set_max_pending_proofs(&mut self, max_pending_proofs: Option<usize>) {
+        self.max_pending_proofs = max_pending_proofs;
+    }
+
+    /// The public inputs of every accepted transaction, in order, including those whose proofs
+    /// were pruned.
+    pub fn events(&self) -> &[PublicInputs<GoldilocksField>] {
+        &self.events
+    }
+
+    /// The nullifier accumulator of `recursive_circuit` after the first `num_events` transactions,
+    /// which the final proof exposes in place of their nullifiers.
+    pub fn nullifier_accumulator(&self, num_events: usize) -> HashOut<GoldilocksField> {
+        let nullifiers = self.events[..num_events]
+            .iter()
+            .map(|event| &event.nullifier_value);
+        nullifier_accumulator(HashOut::ZERO, nullifiers)
+    }
+
+    /// The proof that `proof` covers the transaction `event`, against the root of its
+    /// `public_inputs_tree`, for a light client to check with `verify_inclusion`.
+    pub fn inclusion_proof(&self, proof: &TaggedProof, event: usize) -> Option<InclusionProof> {
+        let index = event.checked_sub(proof.events.start)?;
+        proof.public_inputs_tree.prove(index)
+    }
+
+    fn aggregate_proofs(
         &self,
         left: usize,
         right: usize,
-    ) -> ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2> {
+        config: &CircuitConfig,
+    ) -> Result<TaggedProof> {
         const D: usize = 2;
         type C = PoseidonGoldilocksConfig;
         type F = GoldilocksField;
         info!("recur: {:?} {:?}", left, right);
         return if left == right {
-            (
-                self.proofs[left].0.clone(),
-                VerifierOnlyCircuitData {
-                    constants_sigmas_cap: self.proofs[left].1.constants_sigmas_cap.clone(),
-                    circuit_digest: self.proofs[left].1.circuit_digest.clone(),
-                },
-                self.proofs[left].2.clone(),
-            )
+            Ok(self.proofs[left].clone())
         } else {
             let mid = (left + right) / 2;
-            let inner1 = &self.get_recursive_proof(left, mid);
-            let inner2 = &self.get_recursive_proof(mid + 1, right);
+            let inner1 = &self.aggregate_proofs(left, mid, &self.config)?;
+            let inner2 = &self.aggregate_proofs(mid + 1, right, &self.config)?;
+
+            let index = self.recursion_circuit_index(inner1, inner2, config);
+            let recursion_circuits = self.recursion_circuits.borrow();
+            let circuit = &recursion_circuits[index];
+            let proof = gen_recursive_circuit::<F, C, C, D>(
+                &inner1.proof,
+                &inner2.proof,
+                &circuit.data,
+                &circuit.wiring,
+                self.nullifier_accumulator(inner1.events.start),
+                [inner1.insertion.as_ref(), inner2.insertion.as_ref()],
+            )?;
+            let mut circuit_versions = inner1.circuit_versions.clone();
+            for digest in &inner2.circuit_versions {
+                if !circuit_versions.contains(digest) {
+                    circuit_versions.push(*digest);
+                }
+            }
+            Ok(TaggedProof {
+                proof,
+                circuit_versions,
+                events: inner1.events.start..inner2.events.end,
+                public_inputs_tree: PublicInputsTree::node(
+                    &inner1.public_inputs_tree,
+                    &inner2.public_inputs_tree,
+                ),
+                insertion: None,
+            })
+        };
+    }
+
+    /// The index in `recursion_circuits` of the circuit aggregating proofs shaped like `inner1` and
+    /// `inner2` under `config`, building it if there is none yet.
+    fn recursion_circuit_index(
+        &self,
+        inner1: &TaggedProof,
+        inner2: &TaggedProof,
+        config: &CircuitConfig,
+    ) -> usize {
+        let inner_common = [inner1.proof.common().clone(), inner2.proof.common().clone()];
+        let kinds = [inner1.kind(), inner2.kind()];
+        let mut recursion_circuits = self.recursion_circuits.borrow_mut();
+        if let Some(index) = recursion_circuits.iter().position(|circuit| {
+            circuit.inner_common == inner_common
+                && circuit.kinds == kinds
+                && &circuit.config == config
+        }) {
+            return index;
+        }
+        info!("building a recursion circuit for a new shape of inner proofs");
+        let (data, wiring) = recursive_circuit::<_, PoseidonGoldilocksConfig, _, 2>(
+            &inner1.proof,
+            &inner2.proof,
+            kinds,
+            config,
+            self.tree_height,
+            None,
+        );
+        recursion_circuits.push(RecursionCircuit {
+            inner_common,
+            kinds,
+            config: config.clone(),
+            data,
+            wiring,
+        });
+        recursion_circuits.len() - 1
+    }
+
+    /// The number of recursion circuits built so far, one for each shape of aggregated proofs.
+    pub fn num_recursion_circuits(&self) -> usize {
+        self.recursion_circuits.borrow().len()
+    }
+
+    /// The index of the note with leaf `leaf`, e.g. for a client to find the change note of a
+    /// transaction a delegated prover submitted for it.
+    pub fn find_note(&self, leaf: &HashOut<GoldilocksField>) -> Option<usize> {
+        self.state.find_private_utxo(leaf)
+    }
+
+    /// Publishes `memo`, for the view-only clients of the note's owner to find the note. The note
+    /// must be in the note tree.
+    pub fn publish_memo(&mut self, memo: NoteMemo) -> Result<()> {
+        ensure!(
+            self.find_note(&memo.leaf).is_some(),
+            "the memo is for an unknown note"
+        );
+        self.memos.push(memo);
+        Ok(())
+    }
+
+    /// The memos published so far, in order.
+    pub fn memos(&self) -> &[NoteMemo] {
+        &self.memos
+    }
+
+    /// Makes the server sign with `key` from then on. `new` draws a random key, so a server
+    /// restarting must restore the key it used before, persisted with `SigningKey::to_bytes`, for
+    /// its earlier attestations and manifests to keep matching `operator_public_key`.
+    pub fn set_operator_key(&mut self, key: SigningKey) {
+        self.operator_key = key;
+    }
+
+    /// The public key of the operator, with which downstream verifiers check what the server
+    /// signs.
+    pub fn operator_public_key(&self) -> CurvePoint<GoldilocksField> {
+        self.operator_key.public_key()
+    }
+
+    /// The manifest of `proof`, e.g. the final proof exported to the circom verifier, signed by
+    /// the operator.
+    pub fn manifest(&self, proof: &TaggedProof) -> SignedManifest {
+        let (old_root, new_root) = proof.roots();
+        let manifest = ProofManifest::new(proof, old_root, new_root);
+        SignedManifest::sign(manifest, &self.operator_key, &mut OsRng)
+    }
 
-            let (data1, wiring1) =
-                recursive_circuit::<F, C, C, D>(inner1, inner2, &self.config, None);
-            return gen_recursive_circuit::<F, C, C, D>(inner1, inner2, data1, wiring1).unwrap();
+    /// The update of the light-client contract posting `proof`, an aggregate, as `groth16`, the
+    /// Groth16 proof of the circom verifier for it. The nullifier accumulator is the one `proof`
+    /// exposes, which covers every transaction up to its last one. Fails if the public signals of
+    /// `groth16` don't prove the roots and accumulator posted, as the contract would.
+    pub fn state_update(&self, proof: &TaggedProof, groth16: Groth16Proof) -> Result<StateUpdate> {
+        ensure!(
+            proof.kind() == ProofKind::Aggregate,
+            "only aggregates expose the roots a state update posts"
+        );
+        let (old_root, new_root) = proof.roots();
+        let update = StateUpdate {
+            old_root,
+            new_root,
+            nullifier_accumulator: HashOut::from_partial(
+                &proof.proof.proof().public_inputs[NULLIFIER_ACCUMULATOR_END.range()],
+            ),
+            proof: groth16,
         };
+        update.check_public_signals()?;
+        Ok(update)
     }
 
     pub fn get_state(&self) -> State {
@@ -0,0 +1,193 @@
+//! Schnorr signatures over ecGFp5 with a Poseidon challenge, with which the server operator signs
+//! what it publishes, so that downstream verifiers can check it comes from the operator.
+//!
+//! A key is a scalar `x` with public key `X = x G`. The signature of a message `m`, a list of field
+//! elements, is `(R, s)` with `R = k G` for a fresh scalar `k`, `s = k + e x mod n` and the
+//! challenge `e = H(H(R) || H(X) || m)`, read as a 256-bit integer as in `stealth_tweak`. It's valid
+//! if `s G = R + e X`.
+
+use anyhow::{anyhow, ensure, Result};
+use keccak_hash::keccak;
+use num::BigUint;
+use plonky2::curve::ecgfp5::{group_order, sample_scalar, CurvePoint, GFp5};
+use plonky2::curve::stealth::hash_curve_point;
+use plonky2::prelude::*;
+use plonky2_field::types::Field64;
+use rand::Rng;
+
+type F = GoldilocksField;
+
+/// A Schnorr signature `(R, s)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature {
+    pub r: CurvePoint<F>,
+    pub s: BigUint,
+}
+
+/// An operator's signing key. An operator keeps the same key across restarts, persisted with
+/// `to_bytes`, so that what it signed before stays checkable against its public key.
+pub struct SigningKey {
+    secret: BigUint,
+    public_key: CurvePoint<F>,
+}
+
+impl SigningKey {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let secret = sample_scalar(rng);
+        Self {
+            public_key: CurvePoint::generator().mul(&secret),
+            secret,
+        }
+    }
+
+    /// The key whose secret scalar is `bytes`, little-endian, as written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let secret = BigUint::from_bytes_le(bytes);
+        ensure!(
+            secret > BigUint::from(0u32) && secret < group_order(),
+            "the secret key isn't a nonzero scalar"
+        );
+        Ok(Self {
+            public_key: CurvePoint::generator().mul(&secret),
+            secret,
+        })
+    }
+
+    /// The secret scalar, little-endian. Whoever reads it can sign as the operator.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.secret.to_bytes_le()
+    }
+
+    pub fn public_key(&self) -> CurvePoint<F> {
+        self.public_key
+    }
+
+    pub fn sign<R: Rng + ?Sized>(&self, message: &[F], rng: &mut R) -> Signature {
+        let nonce = sample_scalar(rng);
+        let r = CurvePoint::generator().mul(&nonce);
+        let e = challenge(&r, &self.public_key, message);
+        Signature {
+            r,
+            s: (nonce + e * &self.secret) % group_order(),
+        }
+    }
+}
+
+fn challenge(r: &CurvePoint<F>, public_key: &CurvePoint<F>, message: &[F]) -> BigUint {
+    let mut inputs = hash_curve_point::<F, PoseidonHash>(r).elements.to_vec();
+    inputs.extend(hash_curve_point::<F, PoseidonHash>(public_key).elements);
+    inputs.extend_from_slice(message);
+    let limbs = PoseidonHash::hash_no_pad(&inputs)
+        .elements
+        .iter()
+        .flat_map(|x| {
+            let x = x.to_canonical_u64();
+            [x as u32, (x >> 32) as u32]
+        })
+        .collect();
+    BigUint::new(limbs)
+}
+
+/// Checks that `signature` is a signature of `message` by the key whose public key is
+/// `public_key`.
+pub fn verify_signature(
+    public_key: &CurvePoint<F>,
+    message: &[F],
+    signature: &Signature,
+) -> Result<()> {
+    ensure!(
+        public_key.is_on_curve() && public_key.is_in_subgroup() && !public_key.is_identity,
+        "invalid public key"
+    );
+    ensure!(
+        signature.r.is_on_curve() && !signature.r.is_identity && signature.s < group_order(),
+        "malformed signature"
+    );
+    let e = challenge(&signature.r, public_key, message);
+    ensure!(
+        CurvePoint::generator().mul(&signature.s) == signature.r.add(&public_key.mul(&e)),
+        "invalid signature"
+    );
+    Ok(())
+}
+
+/// The message signed for `bytes`: their Keccak-256, as eight 32-bit limbs.
+pub fn bytes_message(bytes: &[u8]) -> Vec<F> {
+    keccak(bytes)
+        .0
+        .chunks(4)
+        .map(|limb| F::from_canonical_u32(u32::from_le_bytes(limb.try_into().unwrap())))
+        .collect()
+}
+
+/// The coordinates of a point, as the decimal strings of their base field coefficients, e.g. to
+/// publish a public key in JSON.
+pub fn point_to_strings(point: &CurvePoint<F>) -> Vec<String> {
+    [point.x, point.y]
+        .iter()
+        .flat_map(|c| FieldExtension::<5>::to_basefield_array(c))
+        .map(|x| x.to_canonical_u64().to_string())
+        .collect()
+}
+
+/// The inverse of `point_to_strings`, which checks that the point is on the curve.
+pub fn point_from_strings(strings: &[String]) -> Result<CurvePoint<F>> {
+    ensure!(strings.len() == 10, "a point has 10 coordinates");
+    let elements = strings
+        .iter()
+        .map(|s| {
+            let x = F::from_noncanonical_u64(s.parse()?);
+            ensure!(
+                x.to_canonical_u64().to_string() == *s,
+                "coordinate {s} isn't canonical"
+            );
+            Ok(x)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let x = <GFp5<F> as FieldExtension<5>>::from_basefield_array(elements[..5].try_into().unwrap());
+    let y = <GFp5<F> as FieldExtension<5>>::from_basefield_array(elements[5..].try_into().unwrap());
+    let point = CurvePoint::new(x, y);
+    point
+        .is_on_curve()
+        .then_some(point)
+        .ok_or_else(|| anyhow!("the point isn't on the curve"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() -> Result<()> {
+        let key = SigningKey::new(&mut OsRng);
+        let message = F::rand_vec(5);
+        let signature = key.sign(&message, &mut OsRng);
+        verify_signature(&key.public_key(), &message, &signature)?;
+
+        let mut other_message = message.clone();
+        other_message[0] += F::ONE;
+        assert!(verify_signature(&key.public_key(), &other_message, &signature).is_err());
+        let other_key = SigningKey::new(&mut OsRng);
+        assert!(verify_signature(&other_key.public_key(), &message, &signature).is_err());
+        let mut bad_signature = signature.clone();
+        bad_signature.s += 1u32;
+        assert!(verify_signature(&key.public_key(), &message, &bad_signature).is_err());
+
+        let strings = point_to_strings(&signature.r);
+        assert_eq!(point_from_strings(&strings)?, signature.r);
+
+        // A key read back from its bytes signs as the original.
+        let restored = SigningKey::from_bytes(&key.to_bytes())?;
+        assert_eq!(restored.public_key(), key.public_key());
+        verify_signature(
+            &key.public_key(),
+            &message,
+            &restored.sign(&message, &mut OsRng),
+        )?;
+        assert!(SigningKey::from_bytes(&[]).is_err());
+        assert!(SigningKey::from_bytes(&group_order().to_bytes_le()).is_err());
+        Ok(())
+    }
+}
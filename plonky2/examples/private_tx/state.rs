@@ -1,24 +1,53 @@
+use std::collections::HashSet;
+
+use anyhow::{ensure, Result};
 use itertools::Itertools;
 use log::info;
-use plonky2::hash::merkle_proofs::MerkleProof;
-use plonky2::hash::merkle_tree::MerkleTree;
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::plonk::config::{GenericHashOut, Hasher};
-use plonky2_field::goldilocks_field::GoldilocksField;
-use plonky2_field::types::Field;
+use plonky2::prelude::*;
+
+use crate::amount::TokenAmount;
+use crate::circuit;
+use crate::circuit::{note_leaf, Timelock};
+
+/// The height of the tree of archived epoch roots, which bounds the number of epochs.
+pub const EPOCH_TREE_HEIGHT: usize = 8;
+
+/// The leaf of unused slots of the trees, which `recursive_circuit` relies on for the note tree.
+fn empty_leaf() -> Vec<GoldilocksField> {
+    circuit::empty_leaf().elements.to_vec()
+}
+
+/// The key of an archived nullifier: its first element, a quarter of its size. Two nullifiers
+/// sharing it only happens with probability about `n / 2^64` for `n` archived nullifiers, and
+/// then rejects a valid spend rather than accepting a double spend.
+fn archived_nullifier_key(nullifier: HashOut<GoldilocksField>) -> u64 {
+    nullifier.elements[0].to_canonical_u64()
+}
 
 #[derive(Clone)]
 pub struct State {
-    //private_utxo_tree stores Hash (privateKey, 0,0, tokenID, token_amount) of currently available tree
+    //private_utxo_tree stores Hash (Hash(privateKey), valid_after, valid_before, tokenID, amount)
+    //of currently available tree, see `note_leaf`
     pub private_utxo_tree: MerkleTree<GoldilocksField, PoseidonHash>,
     // next_index_utxo is the next index which is used to store new leaf
     pub next_index_utxo: usize,
-    //nullify_utxo_tree stores Hash (privateKey, 0,0, tokenID, token_amount) of the used tree
+    //nullify_utxo_tree stores Hash (Hash(privateKey), valid_after, valid_before, tokenID, amount)
+    //of the used tree
     pub nullify_utxo_tree: MerkleTree<GoldilocksField, PoseidonHash>,
     //  next_index_nullify is the next index which is used to store new leaf
     pub next_index_nullify: usize,
     //cap height h is the h-th layer from the root of the intermedia hashes.
     pub merkle_cap_height: usize,
+    // number of leaves after which private_utxo_tree is archived and a new one started; `None`
+    // keeps a single tree
+    pub epoch_size: Option<usize>,
+    // roots of the archived private_utxo_trees, leaf i being the root of epoch i; leaf 0 of the
+    // current private_utxo_tree is the root of this tree, once an epoch has been archived
+    pub epoch_tree: MerkleTree<GoldilocksField, PoseidonHash>,
+    pub num_archived_epochs: usize,
+    // nullifiers of the nullify_utxo_trees which were full and have been replaced, compacted to
+    // their first element, see `archived_nullifier_key`
+    pub archived_nullifiers: HashSet<u64>,
 }
 
 impl State {
@@ -35,24 +64,123 @@ impl State {
             merkle_cap_height: 0,
             next_index_utxo: 0,
             next_index_nullify: 0,
+            epoch_size: None,
+            epoch_tree: Self::new_epoch_tree(),
+            num_archived_epochs: 0,
+            archived_nullifiers: HashSet::new(),
+        }
+    }
+
+    fn new_epoch_tree() -> MerkleTree<GoldilocksField, PoseidonHash> {
+        MerkleTree::new_with_padding(vec![], 1 << EPOCH_TREE_HEIGHT, empty_leaf(), 0)
+    }
+
+    /// Starts a new private_utxo_tree once `epoch_size` leaves have been added to the current one,
+    /// including the checkpoint leaf. Archived trees are dropped, so spending a note of an
+    /// archived epoch needs a Merkle proof saved before it ended, and `archived_note_circuit`.
+    pub fn set_epoch_size(&mut self, epoch_size: Option<usize>) {
+        if let Some(epoch_size) = epoch_size {
+            assert!(
+                (2..=self.private_utxo_tree.leaves.len()).contains(&epoch_size),
+                "an epoch must fit the checkpoint and a note in the tree"
+            );
         }
+        self.epoch_size = epoch_size;
     }
 
+    /// The number of notes which can still be added, in the current private_utxo_tree and, with
+    /// epochs, in those of the epochs left, the tree of archived roots holding `2^EPOCH_TREE_HEIGHT`
+    /// of them.
+    pub fn remaining_capacity(&self) -> usize {
+        let tree_size = self.private_utxo_tree.leaves.len();
+        match self.epoch_size {
+            Some(epoch_size) => {
+                let epochs_left = (1 << EPOCH_TREE_HEIGHT) - self.num_archived_epochs;
+                // Each later epoch starts with the checkpoint.
+                epoch_size.saturating_sub(self.next_index_utxo) + epochs_left * (epoch_size - 1)
+            }
+            None => tree_size - self.next_index_utxo,
+        }
+    }
+
+    /// Archives the root of the current private_utxo_tree and replaces it with a tree whose leaf 0
+    /// is the checkpoint, i.e. the root of the tree of archived roots.
+    fn start_epoch(&mut self) {
+        let root = self.private_utxo_tree.cap.0[0];
+        self.epoch_tree
+            .update(root.elements.to_vec(), self.num_archived_epochs, 0);
+        self.num_archived_epochs += 1;
+        info!(
+            "archived epoch {} with root {:?}",
+            self.num_archived_epochs - 1,
+            root
+        );
+
+        self.private_utxo_tree = MerkleTree::new_with_padding(
+            vec![self.epoch_tree.cap.0[0].elements.to_vec()],
+            self.private_utxo_tree.leaves.len(),
+            empty_leaf(),
+            self.merkle_cap_height,
+        );
+        self.next_index_utxo = 1;
+    }
+
+    /// The root of the private_utxo_tree of the archived epoch `epoch`.
+    pub fn archived_root(&self, epoch: usize) -> HashOut<GoldilocksField> {
+        assert!(
+            epoch < self.num_archived_epochs,
+            "epoch {} isn't archived",
+            epoch
+        );
+        HashOut::from_partial(&self.epoch_tree.leaves[epoch])
+    }
+
+    /// The proof that the root of epoch `epoch` is in the tree of archived roots.
+    pub fn epoch_merkle_proof(&self, epoch: usize) -> MerkleProof<GoldilocksField, PoseidonHash> {
+        self.epoch_tree.prove(epoch)
+    }
+
+    /// The proof that the checkpoint is leaf 0 of the current private_utxo_tree.
+    pub fn checkpoint_merkle_proof(&self) -> MerkleProof<GoldilocksField, PoseidonHash> {
+        self.private_utxo_tree.prove(0)
+    }
+
+    /// Adds a note, returning its index, or fails if the tree is full, see `remaining_capacity`.
     pub fn add_private_utxo(
         &mut self,
         h: <PoseidonHash as Hasher<GoldilocksField>>::Hash,
-    ) -> usize {
+    ) -> Result<usize> {
+        ensure!(self.remaining_capacity() > 0, "the note tree is full");
+        if self.epoch_size == Some(self.next_index_utxo) {
+            self.start_epoch();
+        }
         self.private_utxo_tree
             .update(h.to_vec(), self.next_index_utxo, self.merkle_cap_height);
         self.next_index_utxo = self.next_index_utxo + 1;
         //  return the index of new leaf
-        self.next_index_utxo - 1
+        Ok(self.next_index_utxo - 1)
     }
 
     pub fn add_nullify_utxo(
         &mut self,
         h: <PoseidonHash as Hasher<GoldilocksField>>::Hash,
     ) -> usize {
+        // Full trees are replaced rather than archived: only their nullifiers are kept.
+        if self.next_index_nullify == self.nullify_utxo_tree.leaves.len() {
+            self.archived_nullifiers.extend(
+                self.nullify_utxo_tree
+                    .leaves
+                    .iter()
+                    .map(|leaf| archived_nullifier_key(HashOut::from_partial(leaf))),
+            );
+            self.nullify_utxo_tree = MerkleTree::new_with_padding(
+                vec![],
+                self.nullify_utxo_tree.leaves.len(),
+                empty_leaf(),
+                self.merkle_cap_height,
+            );
+            self.next_index_nullify = 0;
+        }
         self.nullify_utxo_tree
             .update(h.to_vec(), self.next_index_nullify, self.merkle_cap_height);
         self.next_index_nullify = self.next_index_nullify + 1;
@@ -60,6 +188,22 @@ impl State {
         self.next_index_nullify - 1
     }
 
+    // whether h has been added to the nullify tree, i.e. the utxo it nullifies is spent
+    pub fn is_nullified(&self, h: <PoseidonHash as Hasher<GoldilocksField>>::Hash) -> bool {
+        self.archived_nullifiers
+            .contains(&archived_nullifier_key(h))
+            || self.nullify_utxo_tree.leaves[..self.next_index_nullify]
+                .iter()
+                .any(|leaf| leaf[..] == h.elements[..])
+    }
+
+    /// The index of the note with leaf `leaf` in the current private_utxo_tree, if any.
+    pub fn find_private_utxo(&self, leaf: &HashOut<GoldilocksField>) -> Option<usize> {
+        self.private_utxo_tree.leaves[..self.next_index_utxo]
+            .iter()
+            .position(|l| l[..] == leaf.elements[..])
+    }
+
     //call this from client to get its proof
     pub fn private_utxo_merkle_proof(
         &self,
@@ -68,73 +212,88 @@ impl State {
         self.private_utxo_tree.prove(index)
     }
 
+    /// Updates `old_proof`, a proof of the note at `index`, for a tree where `new_leaves` are the
+    /// leaves after it, e.g. the new leaves of the transactions the client has seen since its own.
+    /// Leaves are only ever appended, so the siblings left of the note's path don't change; the
+    /// ones right of it are recomputed from `new_leaves`, the rest of the tree being empty. This
+    /// doesn't hold across epochs, see `set_epoch_size`.
+    pub fn refresh_merkle_proof(
+        old_proof: &MerkleProof<GoldilocksField, PoseidonHash>,
+        index: usize,
+        new_leaves: &[HashOut<GoldilocksField>],
+    ) -> MerkleProof<GoldilocksField, PoseidonHash> {
+        let height = old_proof.siblings.len();
+        assert!(
+            index + new_leaves.len() < 1 << height,
+            "more leaves than the tree can hold"
+        );
+        // empty_digests[i] is the digest of an empty subtree of height i.
+        let mut empty_digests = vec![PoseidonHash::hash_or_noop(&empty_leaf())];
+        for i in 0..height {
+            empty_digests.push(PoseidonHash::two_to_one(empty_digests[i], empty_digests[i]));
+        }
+        // The digest of the subtree of height `level` whose first leaf is `start`.
+        fn digest(
+            level: usize,
+            start: usize,
+            new_leaves: &[HashOut<GoldilocksField>],
+            empty_digests: &[HashOut<GoldilocksField>],
+        ) -> HashOut<GoldilocksField> {
+            if start >= new_leaves.len() {
+                empty_digests[level]
+            } else if level == 0 {
+                PoseidonHash::hash_or_noop(&new_leaves[start].elements)
+            } else {
+                let half = 1 << (level - 1);
+                PoseidonHash::two_to_one(
+                    digest(level - 1, start, new_leaves, empty_digests),
+                    digest(level - 1, start + half, new_leaves, empty_digests),
+                )
+            }
+        }
+
+        let siblings = old_proof
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(level, &old_sibling)| {
+                let sibling = (index >> level) ^ 1;
+                if sibling < index >> level {
+                    old_sibling
+                } else {
+                    // `new_leaves[0]` is leaf `index + 1`.
+                    let start = (sibling << level) - (index + 1);
+                    digest(level, start, new_leaves, &empty_digests)
+                }
+            })
+            .collect();
+        MerkleProof { siblings }
+    }
+
     // return a test state with a leave pointing to the user
     pub fn new_demo_state(
         prive_key: [GoldilocksField; 4],
         token_id: GoldilocksField,
-        balance: u64,
+        balance: TokenAmount,
         height: i32,
     ) -> (Self, usize) {
-        let leave = PoseidonHash::hash_no_pad(
-            &[
-                prive_key,
-                [
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    token_id,
-                    GoldilocksField::from_canonical_u64(balance),
-                ],
-            ]
-            .concat(),
-        )
-        .elements
-        .to_vec();
+        let leave = note_leaf(prive_key, Timelock::default(), token_id, balance.to_field())
+            .elements
+            .to_vec();
         info!("leave private hash {:?}", leave);
 
         let n = 1 << height;
-        let mut leaves: Vec<Vec<GoldilocksField>> = (0..n)
-            .map(|_| {
-                PoseidonHash::hash_no_pad(&[
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                ])
-                .elements
-                .to_vec()
-            })
-            .collect();
-        leaves[0] = leave;
-        let nulify_leaves: Vec<Vec<GoldilocksField>> = (0..n)
-            .map(|_| {
-                PoseidonHash::hash_no_pad(&[
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                    GoldilocksField::ZERO,
-                ])
-                .elements
-                .to_vec()
-            })
-            .collect();
         (
             Self {
-                private_utxo_tree: MerkleTree::<GoldilocksField, PoseidonHash>::new(leaves, 0),
+                private_utxo_tree: MerkleTree::new_with_padding(vec![leave], n, empty_leaf(), 0),
                 next_index_utxo: 1,
-                nullify_utxo_tree: MerkleTree::<GoldilocksField, PoseidonHash>::new(
-                    nulify_leaves,
-                    0,
-                ),
+                nullify_utxo_tree: MerkleTree::new_with_padding(vec![], n, empty_leaf(), 0),
                 next_index_nullify: 0,
                 merkle_cap_height: 0,
+                epoch_size: None,
+                epoch_tree: Self::new_epoch_tree(),
+                num_archived_epochs: 0,
+                archived_nullifiers: HashSet::new(),
             },
             0,
         )
@@ -144,16 +303,129 @@ impl State {
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use plonky2::hash::hash_types::HashOut;
+    use plonky2::hash::merkle_proofs::verify_merkle_proof;
     use plonky2_field::goldilocks_field::GoldilocksField;
-    use plonky2_field::types::Sample;
+    use plonky2_field::types::{Field, Sample};
 
-    use crate::state::State;
+    use crate::amount::TokenAmount;
+    use crate::state::{State, EPOCH_TREE_HEIGHT};
 
     #[test]
     fn test_demo_state() -> Result<()> {
         let prive_key: [GoldilocksField; 4] = GoldilocksField::rand_array();
-        let (demo, index) = State::new_demo_state(prive_key, GoldilocksField::rand(), 10000, 10);
+        let (demo, index) = State::new_demo_state(
+            prive_key,
+            GoldilocksField::rand(),
+            TokenAmount::new(10000)?,
+            10,
+        );
         let proof = demo.private_utxo_tree.prove(index);
         Ok(())
     }
+
+    #[test]
+    fn test_epochs() {
+        let (mut state, _) = State::new_demo_state(
+            GoldilocksField::rand_array(),
+            GoldilocksField::ONE,
+            TokenAmount::ZERO,
+            2,
+        );
+        state.set_epoch_size(Some(3));
+        let leaves = (0..5).map(|_| HashOut::rand()).collect::<Vec<_>>();
+        let indices = leaves
+            .iter()
+            .map(|&leaf| state.add_private_utxo(leaf).unwrap())
+            .collect::<Vec<_>>();
+        // Each epoch after the first starts with the checkpoint at index 0.
+        assert_eq!(indices, vec![1, 2, 1, 2, 1]);
+        assert_eq!(state.num_archived_epochs, 2);
+        assert_eq!(
+            state.private_utxo_tree.leaves[0],
+            state.epoch_tree.cap.0[0].elements.to_vec()
+        );
+        assert_eq!(
+            state.private_utxo_tree.leaves[1],
+            leaves[4].elements.to_vec()
+        );
+        let root = state.archived_root(1);
+        assert!(verify_merkle_proof(
+            root.elements.to_vec(),
+            1,
+            state.epoch_tree.cap.0[0],
+            &state.epoch_merkle_proof(1)
+        )
+        .is_ok());
+
+        // Nullifiers of replaced nullifier trees are still known.
+        for &leaf in &leaves {
+            state.add_nullify_utxo(leaf);
+        }
+        assert_eq!(state.next_index_nullify, 1);
+        assert!(leaves.iter().all(|&leaf| state.is_nullified(leaf)));
+        assert!(!state.is_nullified(HashOut::rand()));
+    }
+
+    #[test]
+    fn test_full_note_tree() {
+        let (mut state, _) = State::new_demo_state(
+            GoldilocksField::rand_array(),
+            GoldilocksField::ONE,
+            TokenAmount::ZERO,
+            2,
+        );
+        assert_eq!(state.remaining_capacity(), 3);
+        for _ in 0..3 {
+            state.add_private_utxo(HashOut::rand()).unwrap();
+        }
+        assert!(state.add_private_utxo(HashOut::rand()).is_err());
+
+        // With epochs, the tree of archived roots bounds the number of notes, each later epoch
+        // holding the checkpoint and one note.
+        let (mut state, _) = State::new_demo_state(
+            GoldilocksField::rand_array(),
+            GoldilocksField::ONE,
+            TokenAmount::ZERO,
+            2,
+        );
+        state.set_epoch_size(Some(2));
+        assert_eq!(state.remaining_capacity(), 1 + (1 << EPOCH_TREE_HEIGHT));
+        for _ in 0..state.remaining_capacity() {
+            state.add_private_utxo(HashOut::rand()).unwrap();
+        }
+        assert_eq!(state.num_archived_epochs, 1 << EPOCH_TREE_HEIGHT);
+        assert!(state.add_private_utxo(HashOut::rand()).is_err());
+    }
+
+    #[test]
+    fn test_refresh_merkle_proof() {
+        let (mut state, index) = State::new_demo_state(
+            GoldilocksField::rand_array(),
+            GoldilocksField::ONE,
+            TokenAmount::ZERO,
+            4,
+        );
+        let mut new_leaves = vec![];
+        let mut proof = state.private_utxo_merkle_proof(index);
+        for batch in [1, 4, 6] {
+            for _ in 0..batch {
+                let leaf = HashOut::rand();
+                state.add_private_utxo(leaf).unwrap();
+                new_leaves.push(leaf);
+            }
+            proof = State::refresh_merkle_proof(&proof, index, &new_leaves);
+            assert_eq!(proof, state.private_utxo_merkle_proof(index));
+        }
+
+        // A note in the middle of the tree keeps the siblings left of its path.
+        let middle = state.add_private_utxo(HashOut::rand()).unwrap();
+        let proof = state.private_utxo_merkle_proof(middle);
+        let leaf = HashOut::rand();
+        state.add_private_utxo(leaf).unwrap();
+        assert_eq!(
+            State::refresh_merkle_proof(&proof, middle, &[leaf]),
+            state.private_utxo_merkle_proof(middle)
+        );
+    }
 }
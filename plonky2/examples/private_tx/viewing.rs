@@ -0,0 +1,146 @@
+//! Viewing keys, with which a wallet can be monitored, e.g. by a custodian, without being able to
+//! spend from it.
+//!
+//! A note's leaf commits to the hash of its spend key, its owner tag, rather than the key itself,
+//! so whoever knows the owner tag and the rest of the note can recognize its leaf and prove what it
+//! holds, but only the spend key opens it in `private_tx_circuit`. For each note it takes on, the
+//! client publishes a `NoteMemo` on the server: the note's opening with its owner tag, encrypted
+//! under its viewing key with `duplex_encrypt`. A view-only client, see `Client::new_view_only`,
+//! decrypts the memos to find its notes, and checks them against the note tree.
+
+use core::num::NonZeroU32;
+
+use anyhow::{ensure, Result};
+use plonky2::hash::duplex::{duplex_decrypt, duplex_encrypt, DuplexCiphertext};
+use plonky2::hash::poseidon::PoseidonPermutation;
+use plonky2::prelude::*;
+
+use crate::amount::TokenAmount;
+use crate::circuit::{owned_note_leaf, Timelock};
+
+type F = GoldilocksField;
+
+/// The key under which a client's note memos are encrypted. It is derived from the long-term key,
+/// but doesn't reveal it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ViewingKey(HashOut<F>);
+
+impl ViewingKey {
+    pub fn from_private_key(priv_key: [F; 4]) -> Self {
+        // Hashing six elements sets it apart from the one-time keys, which hash five.
+        Self(PoseidonHash::hash_no_pad(
+            &[priv_key.as_slice(), &[F::ZERO, F::ONE]].concat(),
+        ))
+    }
+}
+
+/// A note, as its owner tag and what its leaf commits to along with it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoteOpening {
+    pub owner: HashOut<F>,
+    pub timelock: Timelock,
+    pub token_id: F,
+    pub token_amount: TokenAmount,
+}
+
+impl NoteOpening {
+    pub fn leaf(&self) -> HashOut<F> {
+        owned_note_leaf(
+            self.owner,
+            self.timelock,
+            self.token_id,
+            self.token_amount.to_field(),
+        )
+    }
+
+    fn to_elements(self) -> Vec<F> {
+        let mut elements = self.owner.elements.to_vec();
+        elements.extend(self.timelock.to_fields::<F>());
+        elements.extend([self.token_id, self.token_amount.to_field()]);
+        elements
+    }
+
+    fn from_elements(elements: &[F]) -> Result<Self> {
+        ensure!(elements.len() == 8, "a note opening has 8 elements");
+        let [valid_after, valid_before] = [elements[4], elements[5]].map(|x| x.to_canonical_u64());
+        ensure!(
+            valid_after <= u32::MAX as u64 && valid_before <= u32::MAX as u64,
+            "timelock out of range"
+        );
+        Ok(Self {
+            owner: HashOut::from_partial(&elements[..4]),
+            timelock: Timelock {
+                valid_after: valid_after as u32,
+                valid_before: NonZeroU32::new(valid_before as u32),
+            },
+            token_id: elements[6],
+            token_amount: TokenAmount::new(elements[7].to_canonical_u64())?,
+        })
+    }
+}
+
+/// A note's opening encrypted under its owner's viewing key, published along with its leaf.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NoteMemo {
+    pub leaf: HashOut<F>,
+    pub encrypted_note: DuplexCiphertext<F>,
+}
+
+impl NoteMemo {
+    pub fn new(viewing_key: &ViewingKey, note: &NoteOpening) -> Self {
+        let leaf = note.leaf();
+        // Leaves are unique, so a leaf element is a fine nonce: the same note is the only message
+        // ever encrypted with it.
+        let encrypted_note = duplex_encrypt::<F, PoseidonPermutation>(
+            viewing_key.0,
+            leaf.elements[0],
+            &note.to_elements(),
+        );
+        Self {
+            leaf,
+            encrypted_note,
+        }
+    }
+
+    /// The note, if the memo is encrypted under `viewing_key` and opens its leaf.
+    pub fn open(&self, viewing_key: &ViewingKey) -> Option<NoteOpening> {
+        let elements = duplex_decrypt::<F, PoseidonPermutation>(
+            viewing_key.0,
+            self.leaf.elements[0],
+            &self.encrypted_note,
+        )
+        .ok()?;
+        let note = NoteOpening::from_elements(&elements).ok()?;
+        (note.leaf() == self.leaf).then_some(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::note_owner;
+
+    #[test]
+    fn test_note_memo() {
+        let priv_key = F::rand_array();
+        let viewing_key = ViewingKey::from_private_key(priv_key);
+        let note = NoteOpening {
+            owner: note_owner(priv_key),
+            timelock: Timelock {
+                valid_after: 3,
+                valid_before: NonZeroU32::new(7),
+            },
+            token_id: F::ONE,
+            token_amount: TokenAmount::new(1000).unwrap(),
+        };
+        let memo = NoteMemo::new(&viewing_key, &note);
+        assert_eq!(memo.open(&viewing_key), Some(note));
+
+        // Other viewing keys see nothing, and the memo can't be moved to another leaf.
+        let other_key = ViewingKey::from_private_key(F::rand_array());
+        assert_eq!(memo.open(&other_key), None);
+        let mut moved = memo.clone();
+        moved.leaf.elements[1] += F::ONE;
+        assert_eq!(moved.open(&viewing_key), None);
+    }
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky2::plonk::proof::CompressedProofWithPublicInputs;
+use plonky2_fuzz::{fixture, C, D, F};
+
+fuzz_target!(|bytes: &[u8]| {
+    let fixture = fixture();
+    let _ = CompressedProofWithPublicInputs::<F, C, D>::from_bytes(bytes, &fixture.data.common);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2_fuzz::{fixture, C, D, F};
+
+fuzz_target!(|bytes: &[u8]| {
+    let fixture = fixture();
+    if let Ok(proof) = ProofWithPublicInputs::<F, C, D>::from_bytes(bytes, &fixture.data.common) {
+        // Each proof has a single encoding.
+        assert_eq!(proof.to_bytes(), bytes);
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky2::plonk::circuit_data::VerifierOnlyCircuitData;
+use plonky2_fuzz::{fixture, C, D};
+
+fuzz_target!(|bytes: &[u8]| {
+    let fixture = fixture();
+    if let Ok(data) = VerifierOnlyCircuitData::<C, D>::from_bytes(bytes, &fixture.data.common) {
+        assert_eq!(data.to_bytes(), bytes);
+    }
+});
@@ -0,0 +1,41 @@
+//! Shared setup for the deserialization fuzz targets.
+//!
+//! Proofs are deserialized against the `CommonCircuitData` of their circuit, so every target parses
+//! its input against the circuit built here. Run a target with e.g.
+//! `cargo +nightly fuzz run proof`, from the `plonky2` directory.
+
+use std::sync::OnceLock;
+
+use plonky2::field::types::Field;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+pub const D: usize = 2;
+pub type C = PoseidonGoldilocksConfig;
+pub type F = <C as GenericConfig<D>>::F;
+
+pub struct Fixture {
+    pub data: CircuitData<F, C, D>,
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+/// A small circuit with public inputs, and a valid proof for it, built on first use.
+pub fn fixture() -> &'static Fixture {
+    static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw).expect("Proving the fixture circuit failed");
+        Fixture { data, proof }
+    })
+}
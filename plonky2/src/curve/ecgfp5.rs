@@ -0,0 +1,277 @@
+//! The ecGFp5 elliptic curve, defined over the degree 5 extension of the Goldilocks field.
+//!
+//! ecGFp5 is the curve `y^2 = x (x^2 + 2 x + 263 z)` over `GF(p^5) = GF(p)[z] / (z^5 - 3)`, where
+//! `p` is the Goldilocks prime. Its order is `2 n` for a prime `n` of 319 bits, so its prime order
+//! subgroup gives about 160 bits of security. Since its base field is an extension of the native
+//! field of our circuits, point arithmetic is cheap to prove, unlike that of curves over foreign
+//! fields like secp256k1; see `gadgets::ecgfp5` for the circuit counterpart.
+//!
+//! Points are represented in affine coordinates of the equivalent short Weierstrass curve
+//! `y^2 = x^3 + A x + B`, obtained by substituting `x - 2/3` for `x`.
+//!
+//! Arithmetic here is not constant time, so it shouldn't be used with secret scalars where timing
+//! can be observed.
+
+use alloc::vec::Vec;
+
+use num::bigint::RandBigInt;
+use num::{BigUint, One, Zero};
+use rand::Rng;
+
+use crate::field::extension::quintic::QuinticExtension;
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::ops::Square;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+
+/// An element of `GF(p^5)`, the base field of ecGFp5.
+pub type GFp5<F> = QuinticExtension<F>;
+
+/// The order `n` of the prime order subgroup, in decimal.
+const GROUP_ORDER: &str = "1067993516717146951041484916571792702745057740581727230159139685185762082554198619328292418486241";
+
+/// The order `n` of the prime order subgroup, in which all points used by applications lie.
+pub fn group_order() -> BigUint {
+    BigUint::parse_bytes(GROUP_ORDER.as_bytes(), 10).unwrap()
+}
+
+/// A uniformly random scalar in `[1, n)`, e.g. a secret key.
+pub fn sample_scalar<R: Rng + ?Sized>(rng: &mut R) -> BigUint {
+    rng.gen_biguint_range(&BigUint::one(), &group_order())
+}
+
+/// The coefficient `A` of the short Weierstrass equation, `263 z - 4/3`.
+pub fn curve_a<F: RichField + Extendable<5>>() -> GFp5<F> {
+    let three = F::from_canonical_u64(3);
+    GFp5::<F>::from_basefield_array([
+        -F::from_canonical_u64(4) / three,
+        F::from_canonical_u64(263),
+        F::ZERO,
+        F::ZERO,
+        F::ZERO,
+    ])
+}
+
+/// The coefficient `B` of the short Weierstrass equation, `16/27 - 526/3 z`.
+pub fn curve_b<F: RichField + Extendable<5>>() -> GFp5<F> {
+    GFp5::<F>::from_basefield_array([
+        F::from_canonical_u64(16) / F::from_canonical_u64(27),
+        -F::from_canonical_u64(526) / F::from_canonical_u64(3),
+        F::ZERO,
+        F::ZERO,
+        F::ZERO,
+    ])
+}
+
+/// Returns a square root of `x`, if `x` is a square, using the Tonelli-Shanks algorithm.
+pub fn gfp5_sqrt<F: RichField + Extendable<5>>(x: GFp5<F>) -> Option<GFp5<F>> {
+    if x.is_zero() {
+        return Some(x);
+    }
+    // `p^5 - 1 = 2^s t` with `t` odd; `s` is the two-adicity of `p - 1`.
+    let s = GFp5::<F>::TWO_ADICITY;
+    let t = (GFp5::<F>::order() - 1u32) >> s;
+
+    // Euler's criterion.
+    if x.exp_biguint(&(&t << (s - 1))) != GFp5::<F>::ONE {
+        return None;
+    }
+
+    let mut z = GFp5::<F>::POWER_OF_TWO_GENERATOR;
+    let w = x.exp_biguint(&((&t - 1u32) >> 1));
+    let mut root = w * x;
+    let mut b = root * w;
+    let mut v = s;
+    while !b.is_one() {
+        let mut k = 0;
+        let mut b2k = b;
+        while !b2k.is_one() {
+            b2k = b2k.square();
+            k += 1;
+        }
+        let mut w = z;
+        for _ in 0..v - k - 1 {
+            w = w.square();
+        }
+        z = w.square();
+        b *= z;
+        root *= w;
+        v = k;
+    }
+    Some(root)
+}
+
+/// A point of ecGFp5, in affine short Weierstrass coordinates, or the identity.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CurvePoint<F: RichField + Extendable<5>> {
+    pub x: GFp5<F>,
+    pub y: GFp5<F>,
+    pub is_identity: bool,
+}
+
+impl<F: RichField + Extendable<5>> CurvePoint<F> {
+    pub const IDENTITY: Self = Self {
+        x: GFp5::<F>::ZERO,
+        y: GFp5::<F>::ZERO,
+        is_identity: true,
+    };
+
+    pub fn new(x: GFp5<F>, y: GFp5<F>) -> Self {
+        Self {
+            x,
+            y,
+            is_identity: false,
+        }
+    }
+
+    /// The conventional generator of the prime order subgroup: twice the point with the smallest
+    /// `x` in the base field, and the smaller of the two possible `y`, on the curve.
+    pub fn generator() -> Self {
+        (1..)
+            .find_map(|x| Self::lift_x(GFp5::<F>::from_canonical_u64(x)))
+            .unwrap()
+            .double()
+    }
+
+    /// A point with the given `x` coordinate, if there is one. Of the two such points, this
+    /// returns the one whose `y` has the lexicographically smaller coefficients.
+    pub fn lift_x(x: GFp5<F>) -> Option<Self> {
+        let y = gfp5_sqrt(x * x * x + curve_a::<F>() * x + curve_b::<F>())?;
+        let neg_y = -y;
+        let key = |y: &GFp5<F>| {
+            FieldExtension::<5>::to_basefield_array(y)
+                .iter()
+                .map(|c| c.to_canonical_u64())
+                .collect::<Vec<_>>()
+        };
+        Some(Self::new(x, if key(&y) <= key(&neg_y) { y } else { neg_y }))
+    }
+
+    /// A uniformly random point of the prime order subgroup, other than the identity.
+    pub fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::generator().mul(&sample_scalar(rng))
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        self.is_identity
+            || self.y.square()
+                == self.x * self.x * self.x + curve_a::<F>() * self.x + curve_b::<F>()
+    }
+
+    /// Whether this point lies in the prime order subgroup, i.e. `n P` is the identity.
+    pub fn is_in_subgroup(&self) -> bool {
+        self.is_on_curve() && self.mul(&group_order()).is_identity
+    }
+
+    pub fn neg(&self) -> Self {
+        Self {
+            y: -self.y,
+            ..*self
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        if self.is_identity || self.y.is_zero() {
+            return Self::IDENTITY;
+        }
+        let x_squared = self.x.square();
+        let lambda = (x_squared + x_squared + x_squared + curve_a::<F>()) / (self.y + self.y);
+        let x3 = lambda.square() - self.x - self.x;
+        let y3 = lambda * (self.x - x3) - self.y;
+        Self::new(x3, y3)
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        if self.is_identity {
+            return *rhs;
+        }
+        if rhs.is_identity {
+            return *self;
+        }
+        if self.x == rhs.x {
+            return if self.y == rhs.y {
+                self.double()
+            } else {
+                Self::IDENTITY
+            };
+        }
+        let lambda = (rhs.y - self.y) / (rhs.x - self.x);
+        let x3 = lambda.square() - self.x - rhs.x;
+        let y3 = lambda * (self.x - x3) - self.y;
+        Self::new(x3, y3)
+    }
+
+    /// Multiplies this point by `scalar`.
+    pub fn mul(&self, scalar: &BigUint) -> Self {
+        let mut result = Self::IDENTITY;
+        for i in (0..scalar.bits()).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    /// Multiplies this point by the scalar with little-endian bits `bits`.
+    pub fn mul_bits(&self, bits: &[bool]) -> Self {
+        let scalar = bits
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |acc, &bit| (acc << 1u32) + u32::from(bit));
+        self.mul(&scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::BigUint;
+    use rand::rngs::OsRng;
+
+    use crate::curve::ecgfp5::{gfp5_sqrt, group_order, sample_scalar, CurvePoint, GFp5};
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::ops::Square;
+    use crate::field::types::{Field, Sample};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_sqrt() {
+        for _ in 0..10 {
+            let x = GFp5::<F>::rand();
+            assert_eq!(gfp5_sqrt(x.square()).map(|r| r.square()), Some(x.square()));
+        }
+    }
+
+    #[test]
+    fn test_group_order() {
+        let g = CurvePoint::<F>::generator();
+        assert!(g.is_on_curve());
+        assert!(!g.is_identity);
+        assert!(g.is_in_subgroup());
+
+        // Any point has order dividing `2 n`.
+        let p = (2..)
+            .find_map(|x| CurvePoint::<F>::lift_x(GFp5::from_canonical_u64(x)))
+            .unwrap();
+        assert!(p.mul(&(group_order() * 2u32)).is_identity);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut rng = OsRng;
+        let g = CurvePoint::<F>::generator();
+        let a = sample_scalar(&mut rng);
+        let b = sample_scalar(&mut rng);
+
+        // Diffie-Hellman.
+        assert_eq!(g.mul(&a).mul(&b), g.mul(&b).mul(&a));
+        assert_eq!(g.mul(&a).add(&g.mul(&b)), g.mul(&(&a + &b)));
+        assert_eq!(g.add(&g), g.double());
+        assert!(g.add(&g.neg()).is_identity);
+        assert_eq!(
+            g.mul(&BigUint::from(5u32)),
+            g.mul_bits(&[true, false, true])
+        );
+    }
+}
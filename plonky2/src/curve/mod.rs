@@ -0,0 +1,2 @@
+pub mod ecgfp5;
+pub mod stealth;
@@ -0,0 +1,237 @@
+//! Stealth addresses over ecGFp5, so that payments to the same recipient can't be linked.
+//!
+//! A recipient publishes a `StealthAddress`, made of a viewing key `V = v G` and a spending key
+//! `S = s G`. To pay them, a sender samples an ephemeral scalar `r` and derives a one-time key
+//! `P = S + h G`, where `h` is the hash of the shared point `r V`, and publishes `P` along with
+//! `R = r G`. The recipient scans published pairs with their viewing secret, recomputing
+//! `h` from `v R = r V`, and the one-time secret `s + h` lets them spend what was sent to `P`.
+//! Observers who don't know `v` can't tell that two one-time keys belong to the same recipient.
+//!
+//! The shared point is also suited to derive the key of a memo encrypted with `hash::duplex`.
+
+use alloc::vec::Vec;
+
+use num::BigUint;
+use rand::Rng;
+
+use crate::curve::ecgfp5::{group_order, sample_scalar, CurvePoint};
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::gadgets::ecgfp5::CurvePointTarget;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// The coordinates of a point, as base field elements.
+fn point_elements<F: RichField + Extendable<5>>(point: &CurvePoint<F>) -> Vec<F> {
+    assert!(!point.is_identity, "The identity has no affine coordinates");
+    [
+        FieldExtension::<5>::to_basefield_array(&point.x),
+        FieldExtension::<5>::to_basefield_array(&point.y),
+    ]
+    .concat()
+}
+
+/// The hash of a point's coordinates, e.g. to commit to a one-time key in a Merkle leaf.
+pub fn hash_curve_point<F: RichField + Extendable<5>, H: AlgebraicHasher<F>>(
+    point: &CurvePoint<F>,
+) -> HashOut<F> {
+    H::hash_no_pad(&point_elements(point))
+}
+
+/// The scalar `h` of the one-time key `S + h G` derived from the shared point `r V = v R`: the hash
+/// of the shared point, read as a 256-bit little-endian integer, which is always less than the
+/// group order.
+pub fn stealth_tweak<F: RichField + Extendable<5>, H: AlgebraicHasher<F>>(
+    shared_point: &CurvePoint<F>,
+) -> BigUint {
+    let limbs = hash_curve_point::<F, H>(shared_point)
+        .elements
+        .iter()
+        .flat_map(|x| {
+            let x = x.to_canonical_u64();
+            [x as u32, (x >> 32) as u32]
+        })
+        .collect();
+    BigUint::new(limbs)
+}
+
+/// A recipient's secret keys.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StealthKeys {
+    /// Lets its holder recognize payments to this recipient, but not spend them.
+    pub view_secret: BigUint,
+    pub spend_secret: BigUint,
+}
+
+/// A recipient's public keys, which senders derive one-time keys from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StealthAddress<F: RichField + Extendable<5>> {
+    pub view_key: CurvePoint<F>,
+    pub spend_key: CurvePoint<F>,
+}
+
+/// A key derived from a `StealthAddress` for a single payment, with the ephemeral key which lets
+/// the recipient recognize it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OneTimeAddress<F: RichField + Extendable<5>> {
+    pub ephemeral_key: CurvePoint<F>,
+    pub one_time_key: CurvePoint<F>,
+}
+
+impl StealthKeys {
+    pub fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self {
+            view_secret: sample_scalar(rng),
+            spend_secret: sample_scalar(rng),
+        }
+    }
+
+    pub fn address<F: RichField + Extendable<5>>(&self) -> StealthAddress<F> {
+        let g = CurvePoint::generator();
+        StealthAddress {
+            view_key: g.mul(&self.view_secret),
+            spend_key: g.mul(&self.spend_secret),
+        }
+    }
+
+    /// If `address` was derived from this recipient's `StealthAddress`, returns the secret of its
+    /// one-time key, i.e. the scalar `x` with `x G = P`.
+    pub fn recover_one_time_secret<F: RichField + Extendable<5>, H: AlgebraicHasher<F>>(
+        &self,
+        address: &OneTimeAddress<F>,
+    ) -> Option<BigUint> {
+        let shared_point = address.ephemeral_key.mul(&self.view_secret);
+        if shared_point.is_identity {
+            return None;
+        }
+        let secret = (&self.spend_secret + stealth_tweak::<F, H>(&shared_point)) % group_order();
+        (CurvePoint::generator().mul(&secret) == address.one_time_key).then_some(secret)
+    }
+}
+
+impl<F: RichField + Extendable<5>> StealthAddress<F> {
+    /// Derives a fresh one-time address, with a random ephemeral key.
+    pub fn derive_one_time_address<H: AlgebraicHasher<F>, R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> OneTimeAddress<F> {
+        let ephemeral_secret = sample_scalar(rng);
+        let shared_point = self.view_key.mul(&ephemeral_secret);
+        let tweak = stealth_tweak::<F, H>(&shared_point);
+        OneTimeAddress {
+            ephemeral_key: CurvePoint::generator().mul(&ephemeral_secret),
+            one_time_key: self.spend_key.add(&CurvePoint::generator().mul(&tweak)),
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D> + Extendable<5>, const D: usize> CircuitBuilder<F, D> {
+    /// The hash of a point's coordinates, as in `hash_curve_point`.
+    pub fn hash_curve_point<H: AlgebraicHasher<F>>(
+        &mut self,
+        point: CurvePointTarget,
+    ) -> HashOutTarget {
+        let elements = [point.x.to_target_array(), point.y.to_target_array()].concat();
+        self.hash_n_to_hash_no_pad::<H>(elements)
+    }
+
+    /// Splits `x` into its 64 little-endian bits, which must be those of its canonical
+    /// representative; otherwise the prover could pick the bits of `x + p` for small `x`.
+    fn split_le_canonical_u64(&mut self, x: Target) -> Vec<BoolTarget> {
+        let bits = self.split_le(x, 64);
+        // `x + p < 2^64` only if `x < 2^32 - 1`, and then the high half of `x + p` is all ones.
+        let low = self.le_sum(bits[..32].iter());
+        let high = self.le_sum(bits[32..].iter());
+        let all_ones = self.constant(F::from_canonical_u32(u32::MAX));
+        let high_is_all_ones = self.is_equal(high, all_ones);
+        let low_if_all_ones = self.mul(high_is_all_ones.target, low);
+        self.assert_zero(low_if_all_ones);
+        bits
+    }
+
+    /// Computes the one-time key `S + h G` of a payment, where `h` is the tweak derived from
+    /// `shared_point` as in `stealth_tweak`. Together with `ecgfp5_scalar_mul` to compute the
+    /// shared point `r V` and the ephemeral key `r G`, this proves that a one-time key was derived
+    /// from a given stealth address.
+    pub fn ecgfp5_one_time_key<H: AlgebraicHasher<F>>(
+        &mut self,
+        spend_key: CurvePointTarget,
+        shared_point: CurvePointTarget,
+    ) -> CurvePointTarget {
+        let tweak = self.hash_curve_point::<H>(shared_point);
+        let tweak_bits = tweak
+            .elements
+            .iter()
+            .flat_map(|&x| self.split_le_canonical_u64(x))
+            .collect::<Vec<_>>();
+        let generator = self.constant_curve_point(CurvePoint::generator());
+        let tweak_point = self.ecgfp5_scalar_mul(generator, &tweak_bits);
+        self.ecgfp5_add(spend_key, tweak_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+
+    use crate::curve::ecgfp5::CurvePoint;
+    use crate::curve::stealth::StealthKeys;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = PoseidonHash;
+
+    #[test]
+    fn test_stealth_address() {
+        let recipient = StealthKeys::sample(&mut OsRng);
+        let other = StealthKeys::sample(&mut OsRng);
+        let address = recipient.address::<F>();
+
+        let first = address.derive_one_time_address::<H, _>(&mut OsRng);
+        let second = address.derive_one_time_address::<H, _>(&mut OsRng);
+        assert_ne!(first.one_time_key, second.one_time_key);
+
+        for one_time_address in [first, second] {
+            let secret = recipient
+                .recover_one_time_secret::<F, H>(&one_time_address)
+                .unwrap();
+            assert_eq!(
+                CurvePoint::generator().mul(&secret),
+                one_time_address.one_time_key
+            );
+            assert!(other
+                .recover_one_time_secret::<F, H>(&one_time_address)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_one_time_key_circuit() -> Result<()> {
+        let recipient = StealthKeys::sample(&mut OsRng);
+        let address = recipient.address::<F>();
+        let one_time_address = address.derive_one_time_address::<H, _>(&mut OsRng);
+        let shared_point = one_time_address.ephemeral_key.mul(&recipient.view_secret);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let spend_key = builder.add_virtual_curve_point_target();
+        let shared = builder.add_virtual_curve_point_target();
+        let one_time_key = builder.ecgfp5_one_time_key::<H>(spend_key, shared);
+        let expected = builder.add_virtual_curve_point_target();
+        builder.connect_curve_points(one_time_key, expected);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_curve_point_target(spend_key, address.spend_key);
+        pw.set_curve_point_target(shared, shared_point);
+        pw.set_curve_point_target(expected, one_time_address.one_time_key);
+        data.verify(data.prove(pw)?)
+    }
+}
@@ -1,15 +1,21 @@
+use alloc::vec::Vec;
+
+use keccak_hash::keccak;
+
 use crate::field::extension::Extendable;
 use crate::field::polynomial::PolynomialCoeffs;
 use crate::fri::proof::{FriChallenges, FriChallengesTarget};
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::FriConfig;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
-use crate::hash::hash_types::{MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::challenger::{Challenger, RecursiveChallenger};
 use crate::iop::target::Target;
+use crate::iop::transcript::{fri_challenges, fri_challenges_circuit};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::util::ceil_div_usize;
 
 impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     pub fn observe_openings<const D: usize>(&mut self, openings: &FriOpenings<F, D>)
@@ -21,7 +27,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         }
     }
 
-    pub fn fri_challenges<C: GenericConfig<D, F = F>, const D: usize>(
+    pub fn fri_challenges<C: GenericConfig<D, F = F, Hasher = H>, const D: usize>(
         &mut self,
         commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
         final_poly: &PolynomialCoeffs<F::Extension>,
@@ -32,36 +38,55 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     where
         F: RichField + Extendable<D>,
     {
-        let num_fri_queries = config.num_query_rounds;
-        let lde_size = 1 << (degree_bits + config.rate_bits);
-        // Scaling factor to combine polynomials.
-        let fri_alpha = self.get_extension_challenge::<D>();
-
-        // Recover the random betas used in the FRI reductions.
-        let fri_betas = commit_phase_merkle_caps
-            .iter()
-            .map(|cap| {
-                self.observe_cap(cap);
-                self.get_extension_challenge::<D>()
-            })
-            .collect();
-
-        self.observe_extension_elements(&final_poly.coeffs);
+        fri_challenges(
+            self,
+            commit_phase_merkle_caps,
+            final_poly,
+            pow_witness,
+            degree_bits,
+            config,
+        )
+    }
+}
 
-        self.observe_element(pow_witness);
-        let fri_pow_response = self.get_challenge();
+/// `keccak256(seed || pow_witness)`, with field elements encoded as little-endian `u64`s.
+pub(crate) fn keccak_pow_digest<F: RichField>(seed: HashOut<F>, pow_witness: F) -> [u8; 32] {
+    let bytes = seed
+        .elements
+        .iter()
+        .chain([&pow_witness])
+        .flat_map(|x| x.canonical_bytes())
+        .collect::<Vec<_>>();
+    keccak(bytes).0
+}
 
-        let fri_query_indices = (0..num_fri_queries)
-            .map(|_| self.get_challenge().to_canonical_u64() as usize % lde_size)
-            .collect();
+/// The PoW response for a Keccak PoW digest: its first 8 bytes as a big-endian integer, shifted
+/// right so that it is a canonical field element. It has as many leading zeros as required by
+/// `FriConfig::min_pow_response_leading_zeros` iff the digest has `proof_of_work_bits` of them.
+pub(crate) fn keccak_pow_response<F: RichField>(digest: &[u8; 32]) -> F {
+    let shift = 65 - F::order().bits();
+    F::from_canonical_u64(u64::from_be_bytes(digest[..8].try_into().unwrap()) >> shift)
+}
 
-        FriChallenges {
-            fri_alpha,
-            fri_betas,
-            fri_pow_response,
-            fri_query_indices,
-        }
-    }
+/// The query indices for a Keccak PoW digest. The `i`-th index is the `i % 4`-th little-endian
+/// `u64` of `keccak256(digest || i / 4)`, with `i / 4` encoded as a little-endian `u64`, reduced
+/// modulo `lde_size`.
+pub(crate) fn keccak_query_indices(
+    digest: &[u8; 32],
+    num_query_rounds: usize,
+    lde_size: usize,
+) -> Vec<usize> {
+    (0..ceil_div_usize(num_query_rounds, 4) as u64)
+        .flat_map(|block| {
+            let mut input = digest.to_vec();
+            input.extend(block.to_le_bytes());
+            keccak(input).0
+        })
+        .collect::<Vec<_>>()
+        .chunks_exact(8)
+        .take(num_query_rounds)
+        .map(|word| (u64::from_le_bytes(word.try_into().unwrap()) % lde_size as u64) as usize)
+        .collect()
 }
 
 impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
@@ -81,33 +106,13 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         pow_witness: Target,
         inner_fri_config: &FriConfig,
     ) -> FriChallengesTarget<D> {
-        let num_fri_queries = inner_fri_config.num_query_rounds;
-        // Scaling factor to combine polynomials.
-        let fri_alpha = self.get_extension_challenge(builder);
-
-        // Recover the random betas used in the FRI reductions.
-        let fri_betas = commit_phase_merkle_caps
-            .iter()
-            .map(|cap| {
-                self.observe_cap(cap);
-                self.get_extension_challenge(builder)
-            })
-            .collect();
-
-        self.observe_extension_elements(&final_poly.0);
-
-        self.observe_element(pow_witness);
-        let fri_pow_response = self.get_challenge(builder);
-
-        let fri_query_indices = (0..num_fri_queries)
-            .map(|_| self.get_challenge(builder))
-            .collect();
-
-        FriChallengesTarget {
-            fri_alpha,
-            fri_betas,
-            fri_pow_response,
-            fri_query_indices,
-        }
+        fri_challenges_circuit(
+            self,
+            builder,
+            commit_phase_merkle_caps,
+            final_poly,
+            pow_witness,
+            inner_fri_config,
+        )
     }
 }
@@ -1,15 +1,20 @@
 use alloc::vec::Vec;
 
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::hash::hash_types::RichField;
+use crate::util::{ceil_div_usize, log2_ceil};
 
-mod challenges;
+pub(crate) mod challenges;
 pub mod oracle;
 pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
 pub mod reduction_strategies;
 pub mod structure;
-mod validate_shape;
+pub(crate) mod validate_shape;
 pub mod verifier;
 pub mod witness_util;
 
@@ -23,12 +28,30 @@ pub struct FriConfig {
 
     pub proof_of_work_bits: u32,
 
+    /// The hash used to check the proof-of-work and derive the query indices from it.
+    pub pow_hash: FriPowHash,
+
     pub reduction_strategy: FriReductionStrategy,
 
     /// Number of query rounds to perform.
     pub num_query_rounds: usize,
 }
 
+/// The hash used in the grinding step of FRI, i.e. to check the proof-of-work witness and to derive
+/// the query indices.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FriPowHash {
+    /// The PoW witness is observed by the challenger, and the PoW response and query indices are
+    /// squeezed from it, like the other challenges.
+    #[default]
+    Field,
+    /// The PoW witness is hashed with Keccak-256 along with a seed squeezed from the challenger, and
+    /// the query indices are derived from the digest with Keccak-256 too. Grinding then relies on
+    /// the preimage resistance of a 256-bit hash rather than that of the field sponge, but the
+    /// proof can't be verified in a plonky2 circuit.
+    Keccak,
+}
+
 impl FriConfig {
     pub fn rate(&self) -> f64 {
         1.0 / ((1 << self.rate_bits) as f64)
@@ -52,6 +75,51 @@ impl FriConfig {
     pub fn num_cap_elements(&self) -> usize {
         1 << self.cap_height
     }
+
+    /// The number of leading zeros a valid PoW response has, when seen as a 64-bit integer.
+    pub fn min_pow_response_leading_zeros<F: RichField>(&self) -> u32 {
+        let field_bits = F::order().bits() as u32;
+        match self.pow_hash {
+            FriPowHash::Field => self.proof_of_work_bits + (64 - field_bits),
+            // The response keeps the top `field_bits - 1` bits of the digest, so it's canonical.
+            FriPowHash::Keccak => self.proof_of_work_bits + (65 - field_bits),
+        }
+    }
+
+    /// The conjectured security of FRI with this config, in bits: each query round contributes
+    /// `rate_bits` bits, and proof-of-work contributes `proof_of_work_bits`. This doesn't account
+    /// for other sources of soundness error, such as the size of the challenge field.
+    pub fn conjectured_security_bits(&self) -> usize {
+        self.rate_bits * self.num_query_rounds + self.proof_of_work_bits as usize
+    }
+
+    /// This config with the fewest query rounds giving at least `security_bits` bits of conjectured
+    /// security. Raising `rate_bits` or `proof_of_work_bits` first reduces the number of queries,
+    /// and hence the size of proofs and the cost of verifying them recursively. Fails if
+    /// `rate_bits` is zero, as query rounds then add no security.
+    pub fn with_security_bits(self, security_bits: usize) -> Result<Self> {
+        ensure!(
+            self.rate_bits > 0,
+            "rate_bits must be positive for query rounds to add security"
+        );
+        let query_bits = security_bits.saturating_sub(self.proof_of_work_bits as usize);
+        Ok(Self {
+            num_query_rounds: ceil_div_usize(query_bits, self.rate_bits),
+            ..self
+        })
+    }
+
+    /// A lower bound on the conjectured security, in bits, of a chain of recursive proofs whose
+    /// layers use `configs`. A forger only needs to break one layer, so the soundness errors of the
+    /// layers add up, costing up to `log2(configs.len())` bits relative to the weakest layer.
+    pub fn combined_security_bits(configs: &[FriConfig]) -> usize {
+        let weakest = configs
+            .iter()
+            .map(FriConfig::conjectured_security_bits)
+            .min()
+            .unwrap_or(0);
+        weakest.saturating_sub(log2_ceil(configs.len()))
+    }
 }
 
 /// FRI parameters, including generated parameters which are specific to an instance size, in
@@ -99,3 +167,36 @@ impl FriParams {
         1 << self.final_poly_bits()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::fri::FriConfig;
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    #[test]
+    fn test_security_bits() {
+        let standard = CircuitConfig::standard_recursion_config().fri_config;
+        assert_eq!(standard.conjectured_security_bits(), 100);
+        assert_eq!(standard.clone().with_security_bits(100).unwrap(), standard);
+
+        // More proof-of-work and a higher rate need fewer queries for the same security.
+        let wrap = FriConfig {
+            rate_bits: 4,
+            proof_of_work_bits: 20,
+            ..standard.clone()
+        }
+        .with_security_bits(100)
+        .unwrap();
+        assert_eq!(wrap.num_query_rounds, 20);
+        assert!(wrap.conjectured_security_bits() >= 100);
+
+        let no_redundancy = FriConfig {
+            rate_bits: 0,
+            ..standard.clone()
+        };
+        assert!(no_redundancy.with_security_bits(100).is_err());
+
+        let layers = [standard.clone(), standard, wrap];
+        assert_eq!(FriConfig::combined_security_bits(&layers), 98);
+    }
+}
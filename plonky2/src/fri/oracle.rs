@@ -1,8 +1,11 @@
-use alloc::format;
+use alloc::borrow::Cow;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 
 use itertools::Itertools;
 use maybe_rayon::*;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::field::extension::Extendable;
 use crate::field::fft::FftRootTable;
@@ -15,16 +18,65 @@ use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_tree::MerkleTree;
-use crate::iop::challenger::Challenger;
+use crate::iop::transcript::NativeTranscript;
 use crate::plonk::config::GenericConfig;
 use crate::timed;
 use crate::util::reducing::ReducingFactor;
 use crate::util::timing::TimingTree;
-use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place, transpose};
+#[cfg(feature = "zeroize")]
+use crate::util::zeroize::{zeroize_field_elements, zeroize_field_vecs};
+use crate::util::{log2_strict, reverse_bits};
 
 /// Four (~64 bit) field elements gives ~128 bit security.
 pub const SALT_SIZE: usize = 4;
 
+/// Scratch space for the LDEs of a batch of polynomials. Committing to a batch computes all its
+/// LDEs before transposing them into Merkle leaves, so reusing one buffer for batches of the same
+/// size avoids allocating, and page faulting in, that memory for each batch. It holds as much memory
+/// as the largest of them, so drop it once it's no longer needed.
+#[derive(Clone, Debug, Default)]
+pub struct LdeBuffer<F: Field> {
+    values: Vec<F>,
+}
+
+impl<F: Field> LdeBuffer<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `len` elements of scratch space, growing the buffer if needed. Their values are
+    /// unspecified.
+    fn get(&mut self, len: usize) -> &mut [F] {
+        if self.values.len() < len {
+            self.values.resize(len, F::ZERO);
+        }
+        &mut self.values[..len]
+    }
+
+    /// The number of field elements the buffer currently holds.
+    pub fn capacity(&self) -> usize {
+        self.values.len()
+    }
+}
+
+/// Transposes `columns`, laid out one after the other with length `column_len`, into rows, in
+/// bit-reversed order. This is the order Merkle leaves are committed in.
+fn transpose_columns_bit_reversed<F: Field>(columns: &[F], column_len: usize) -> Vec<Vec<F>> {
+    let lg_column_len = log2_strict(column_len);
+    (0..column_len)
+        .into_par_iter()
+        .map(|i| {
+            let row = reverse_bits(i, lg_column_len);
+            columns
+                .iter()
+                .skip(row)
+                .step_by(column_len)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
 /// Represents a FRI oracle, i.e. a batch of polynomials which have been Merklized.
 pub struct PolynomialBatch<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 {
@@ -35,6 +87,35 @@ pub struct PolynomialBatch<F: RichField + Extendable<D>, C: GenericConfig<D, F =
     pub blinding: bool,
 }
 
+/// Committed polynomials may be derived from secret witness data, so with the `zeroize` feature
+/// their coefficients and LDE leaves are erased when the batch is dropped.
+#[cfg(feature = "zeroize")]
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Zeroize
+    for PolynomialBatch<F, C, D>
+{
+    fn zeroize(&mut self) {
+        for poly in self.polynomials.iter_mut() {
+            zeroize_field_elements(&mut poly.coeffs);
+        }
+        zeroize_field_vecs(&mut self.merkle_tree.leaves);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Drop
+    for PolynomialBatch<F, C, D>
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> ZeroizeOnDrop
+    for PolynomialBatch<F, C, D>
+{
+}
+
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     PolynomialBatch<F, C, D>
 {
@@ -46,20 +127,46 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_values_with_buffer(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            &mut LdeBuffer::default(),
+        )
+    }
+
+    /// Like `from_values`, but computes the LDEs in `lde_buffer`, which can be reused across the
+    /// batches committed in one proof.
+    pub fn from_values_with_buffer(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        lde_buffer: &mut LdeBuffer<F>,
     ) -> Self {
         let coeffs = timed!(
             timing,
             "IFFT",
-            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
+            values
+                .into_par_iter()
+                .map(|v| v.ifft_with_options(fft_root_table))
+                .collect::<Vec<_>>()
         );
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_buffer(
             coeffs,
             rate_bits,
             blinding,
             cap_height,
             timing,
             fft_root_table,
+            lde_buffer,
         )
     }
 
@@ -71,16 +178,50 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_coeffs_with_buffer(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            &mut LdeBuffer::default(),
+        )
+    }
+
+    /// Like `from_coeffs`, but computes the LDEs in `lde_buffer`, which can be reused across the
+    /// batches committed in one proof.
+    pub fn from_coeffs_with_buffer(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        lde_buffer: &mut LdeBuffer<F>,
     ) -> Self {
         let degree = polynomials[0].len();
+        let lde_size = degree << rate_bits;
         let lde_values = timed!(
             timing,
             "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            Self::lde_values(
+                &polynomials,
+                rate_bits,
+                blinding,
+                fft_root_table,
+                lde_buffer
+            )
         );
 
-        let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
-        reverse_index_bits_in_place(&mut leaves);
+        let leaves = timed!(
+            timing,
+            "transpose LDEs",
+            transpose_columns_bit_reversed(lde_values, lde_size)
+        );
+        #[cfg(feature = "zeroize")]
+        zeroize_field_elements(lde_values);
         let merkle_tree = timed!(
             timing,
             "build Merkle tree",
@@ -96,31 +237,34 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         }
     }
 
-    fn lde_values(
+    /// Computes the LDEs of `polynomials`, followed by the salt columns if blinding, into
+    /// `lde_buffer`, and returns them as consecutive columns of length `degree << rate_bits`.
+    fn lde_values<'a>(
         polynomials: &[PolynomialCoeffs<F>],
         rate_bits: usize,
         blinding: bool,
         fft_root_table: Option<&FftRootTable<F>>,
-    ) -> Vec<Vec<F>> {
+        lde_buffer: &'a mut LdeBuffer<F>,
+    ) -> &'a mut [F] {
         let degree = polynomials[0].len();
+        let lde_size = degree << rate_bits;
 
         // If blinding, salt with two random elements to each leaf vector.
         let salt_size = if blinding { SALT_SIZE } else { 0 };
 
-        polynomials
-            .par_iter()
-            .map(|p| {
+        let values = lde_buffer.get((polynomials.len() + salt_size) * lde_size);
+        let (lde_columns, salt_columns) = values.split_at_mut(polynomials.len() * lde_size);
+        lde_columns
+            .par_chunks_exact_mut(lde_size)
+            .zip(polynomials)
+            .for_each(|(column, p)| {
                 assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
-                p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
-                    .values
-            })
-            .chain(
-                (0..salt_size)
-                    .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
-            )
-            .collect()
+                p.coset_lde_into(F::coset_shift(), rate_bits, fft_root_table, column);
+            });
+        salt_columns
+            .par_iter_mut()
+            .for_each(|salt| *salt = F::rand());
+        values
     }
 
     /// Fetches LDE values at the `index * step`th point.
@@ -158,15 +302,15 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     }
 
     /// Produces a batch opening proof.
-    pub fn prove_openings(
+    pub fn prove_openings<T: NativeTranscript<F, C::Hasher, D>>(
         instance: &FriInstanceInfo<F, D>,
         oracles: &[&Self],
-        challenger: &mut Challenger<F, C::Hasher>,
+        challenger: &mut T,
         fri_params: &FriParams,
         timing: &mut TimingTree,
     ) -> FriProof<F, C::Hasher, D> {
         assert!(D > 1, "Not implemented for D=1.");
-        let alpha = challenger.get_extension_challenge::<D>();
+        let alpha = challenger.get_extension_challenge(&mut ());
         let mut alpha = ReducingFactor::new(alpha);
 
         // Final low-degree polynomial that goes into FRI.
@@ -179,10 +323,21 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         // where the `k_i`s are chosen such that each power of `alpha` appears only once in the final sum.
         // There are usually two batches for the openings at `zeta` and `g * zeta`.
         // The oracles used in Plonky2 are given in `FRI_ORACLES` in `plonky2/src/plonk/plonk_common.rs`.
+        // Polynomials of oracles with a smaller degree bound are degree-adjusted, i.e. `f(X)` is
+        // replaced by `X^s f(X)`; see `FriOracleInfo::degree_adjustment`.
+        let degree_adjustments = instance
+            .oracles
+            .iter()
+            .map(|oracle| oracle.degree_adjustment(fri_params.degree_bits))
+            .collect::<Vec<_>>();
         for FriBatchInfo { point, polynomials } in &instance.batches {
             // Collect the coefficients of all the polynomials in `polynomials`.
             let polys_coeff = polynomials.iter().map(|fri_poly| {
-                &oracles[fri_poly.oracle_index].polynomials[fri_poly.polynomial_index]
+                let poly = &oracles[fri_poly.oracle_index].polynomials[fri_poly.polynomial_index];
+                match degree_adjustments[fri_poly.oracle_index] {
+                    0 => Cow::Borrowed(poly),
+                    shift => Cow::Owned(Self::degree_adjust(poly, shift)),
+                }
             });
             let composition_poly = timed!(
                 timing,
@@ -204,7 +359,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             lde_final_poly.coset_fft(F::coset_shift().into())
         );
 
-        let fri_proof = fri_proof::<F, C, D>(
+        let fri_proof = fri_proof::<F, C, T, D>(
             &oracles
                 .par_iter()
                 .map(|c| &c.merkle_tree)
@@ -218,4 +373,276 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 
         fri_proof
     }
+
+    /// Computes `X^shift poly(X)`, keeping the length of `poly`, which must have at least `shift`
+    /// zero leading coefficients.
+    fn degree_adjust(poly: &PolynomialCoeffs<F>, shift: usize) -> PolynomialCoeffs<F> {
+        let mut coeffs = vec![F::ZERO; shift];
+        coeffs.extend_from_slice(&poly.coeffs);
+        let mut adjusted = PolynomialCoeffs::new(coeffs);
+        adjusted
+            .trim_to_len(poly.len())
+            .expect("Polynomial exceeds its oracle's degree bound");
+        adjusted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::fri::proof::FriChallengesTarget;
+    use crate::fri::structure::{
+        FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningBatch, FriOpeningBatchTarget,
+        FriOpenings, FriOpeningsTarget, FriOracleInfo, FriPolynomialInfo,
+    };
+    use crate::fri::verifier::verify_fri_proof;
+    use crate::fri::witness_util::set_fri_proof_target;
+    use crate::iop::challenger::Challenger;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const DEGREE_BITS: usize = 8;
+    const TABLE_DEGREE_BITS: usize = 4;
+
+    fn mixed_degree_instance(
+        zeta: <F as Extendable<D>>::Extension,
+        table_degree_bits: usize,
+    ) -> FriInstanceInfo<F, D> {
+        FriInstanceInfo {
+            oracles: vec![
+                FriOracleInfo {
+                    num_polys: 3,
+                    blinding: false,
+                    degree_bits: None,
+                },
+                FriOracleInfo {
+                    num_polys: 2,
+                    blinding: false,
+                    degree_bits: Some(table_degree_bits),
+                },
+            ],
+            batches: vec![FriBatchInfo {
+                point: zeta,
+                polynomials: [
+                    FriPolynomialInfo::from_range(0, 0..3),
+                    FriPolynomialInfo::from_range(1, 0..2),
+                ]
+                .concat(),
+            }],
+        }
+    }
+
+    struct MixedDegreeProof {
+        oracles: [PolynomialBatch<F, C, D>; 2],
+        zeta: <F as Extendable<D>>::Extension,
+        openings: FriOpenings<F, D>,
+        proof: FriProof<F, <C as GenericConfig<D>>::Hasher, D>,
+        fri_params: FriParams,
+    }
+
+    /// Opens three polynomials of degree `< 2^DEGREE_BITS` and two of degree
+    /// `< 2^TABLE_DEGREE_BITS` in a single FRI proof.
+    fn prove_mixed_degree() -> MixedDegreeProof {
+        let fri_config = CircuitConfig::standard_recursion_config().fri_config;
+        let fri_params = fri_config.fri_params(DEGREE_BITS, false);
+        let mut timing = TimingTree::default();
+        let polys = (0..3)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << DEGREE_BITS)))
+            .collect::<Vec<_>>();
+        let table_polys = (0..2)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << TABLE_DEGREE_BITS)))
+            .map(|p| p.padded(1 << DEGREE_BITS))
+            .collect::<Vec<_>>();
+        let commit = |polys, timing: &mut TimingTree| {
+            PolynomialBatch::<F, C, D>::from_coeffs(
+                polys,
+                fri_config.rate_bits,
+                false,
+                fri_config.cap_height,
+                timing,
+                None,
+            )
+        };
+        let oracles = [commit(polys, &mut timing), commit(table_polys, &mut timing)];
+
+        let mut challenger = Challenger::new();
+        for oracle in &oracles {
+            challenger.observe_cap(&oracle.merkle_tree.cap);
+        }
+        let zeta = challenger.get_extension_challenge::<D>();
+        let openings = FriOpenings {
+            batches: vec![FriOpeningBatch {
+                values: oracles
+                    .iter()
+                    .flat_map(|oracle| &oracle.polynomials)
+                    .map(|p| p.to_extension::<D>().eval(zeta))
+                    .collect(),
+            }],
+        };
+        challenger.observe_openings(&openings);
+        let proof = PolynomialBatch::prove_openings(
+            &mixed_degree_instance(zeta, TABLE_DEGREE_BITS),
+            &[&oracles[0], &oracles[1]],
+            &mut challenger,
+            &fri_params,
+            &mut timing,
+        );
+        MixedDegreeProof {
+            oracles,
+            zeta,
+            openings,
+            proof,
+            fri_params,
+        }
+    }
+
+    fn verify_mixed_degree(table_degree_bits: usize) -> Result<()> {
+        let MixedDegreeProof {
+            oracles,
+            zeta,
+            openings,
+            proof,
+            fri_params,
+        } = prove_mixed_degree();
+        let caps = oracles
+            .iter()
+            .map(|oracle| oracle.merkle_tree.cap.clone())
+            .collect::<Vec<_>>();
+
+        let mut challenger = Challenger::<F, <C as GenericConfig<D>>::Hasher>::new();
+        for cap in &caps {
+            challenger.observe_cap(cap);
+        }
+        assert_eq!(challenger.get_extension_challenge::<D>(), zeta);
+        challenger.observe_openings(&openings);
+        let challenges = challenger.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            DEGREE_BITS,
+            &fri_params.config,
+        );
+        verify_fri_proof::<F, C, D>(
+            &mixed_degree_instance(zeta, table_degree_bits),
+            &openings,
+            &challenges,
+            &caps,
+            &proof,
+            &fri_params,
+        )
+    }
+
+    #[test]
+    fn test_mixed_degree_openings() -> Result<()> {
+        verify_mixed_degree(TABLE_DEGREE_BITS)
+    }
+
+    #[test]
+    fn test_mixed_degree_openings_wrong_degree() {
+        // The table polynomials don't have degree less than `2^(TABLE_DEGREE_BITS - 1)`.
+        assert!(verify_mixed_degree(TABLE_DEGREE_BITS - 1).is_err());
+    }
+
+    #[test]
+    fn test_mixed_degree_openings_recursive() -> Result<()> {
+        let MixedDegreeProof {
+            oracles,
+            zeta,
+            openings,
+            proof,
+            fri_params,
+        } = prove_mixed_degree();
+        let mut challenger = Challenger::<F, <C as GenericConfig<D>>::Hasher>::new();
+        for oracle in &oracles {
+            challenger.observe_cap(&oracle.merkle_tree.cap);
+        }
+        challenger.get_extension_challenge::<D>();
+        challenger.observe_openings(&openings);
+        let challenges = challenger.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            DEGREE_BITS,
+            &fri_params.config,
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let instance_info = mixed_degree_instance(zeta, TABLE_DEGREE_BITS);
+        let zeta_target = builder.add_virtual_extension_target();
+        let instance = FriInstanceInfoTarget {
+            oracles: instance_info.oracles.clone(),
+            batches: vec![FriBatchInfoTarget {
+                point: zeta_target,
+                polynomials: instance_info.batches[0].polynomials.clone(),
+            }],
+        };
+        let openings_target = FriOpeningsTarget {
+            batches: vec![FriOpeningBatchTarget {
+                values: builder.add_virtual_extension_targets(5),
+            }],
+        };
+        let challenges_target = FriChallengesTarget {
+            fri_alpha: builder.add_virtual_extension_target(),
+            fri_betas: builder.add_virtual_extension_targets(challenges.fri_betas.len()),
+            fri_pow_response: builder.add_virtual_target(),
+            fri_query_indices: builder.add_virtual_targets(challenges.fri_query_indices.len()),
+        };
+        let caps = oracles
+            .iter()
+            .map(|_| builder.add_virtual_cap(fri_params.config.cap_height))
+            .collect::<Vec<_>>();
+        let proof_target = builder.add_virtual_fri_proof(&[3, 2], &fri_params);
+        builder.verify_fri_proof::<C>(
+            &instance,
+            &openings_target,
+            &challenges_target,
+            &caps,
+            &proof_target,
+            &fri_params,
+        );
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_extension_target(zeta_target, zeta);
+        for (&t, &v) in openings_target.batches[0]
+            .values
+            .iter()
+            .zip(&openings.batches[0].values)
+        {
+            pw.set_extension_target(t, v);
+        }
+        pw.set_extension_target(challenges_target.fri_alpha, challenges.fri_alpha);
+        for (&t, &v) in challenges_target
+            .fri_betas
+            .iter()
+            .zip(&challenges.fri_betas)
+        {
+            pw.set_extension_target(t, v);
+        }
+        pw.set_target(
+            challenges_target.fri_pow_response,
+            challenges.fri_pow_response,
+        );
+        for (&t, &i) in challenges_target
+            .fri_query_indices
+            .iter()
+            .zip(&challenges.fri_query_indices)
+        {
+            pw.set_target(t, F::from_canonical_usize(i));
+        }
+        for (cap_target, oracle) in caps.iter().zip(&oracles) {
+            pw.set_cap_target(cap_target, &oracle.merkle_tree.cap);
+        }
+        set_fri_proof_target(&mut pw, &proof_target, &proof);
+        data.verify(data.prove(pw)?)
+    }
 }
@@ -371,6 +371,7 @@ pub struct FriChallenges<F: RichField + Extendable<D>, const D: usize> {
     // Betas used in the FRI commit phase reductions.
     pub fri_betas: Vec<F::Extension>,
 
+    // See `FriConfig::min_pow_response_leading_zeros`.
     pub fri_pow_response: F,
 
     // Indices at which the oracle is queried in FRI.
@@ -4,12 +4,14 @@ use maybe_rayon::*;
 
 use crate::field::extension::{flatten, unflatten, Extendable};
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::fri::challenges::{keccak_pow_digest, keccak_pow_response};
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
-use crate::fri::{FriConfig, FriParams};
-use crate::hash::hash_types::RichField;
+use crate::fri::{FriConfig, FriParams, FriPowHash};
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE};
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
+use crate::iop::transcript::NativeTranscript;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::timed;
@@ -17,13 +19,18 @@ use crate::util::reverse_index_bits_in_place;
 use crate::util::timing::TimingTree;
 
 /// Builds a FRI proof.
-pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+pub fn fri_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
     // Coefficients of the polynomial on which the LDT is performed. Only the first `1/rate` coefficients are non-zero.
     lde_polynomial_coeffs: PolynomialCoeffs<F::Extension>,
     // Evaluation of the polynomial on the large domain.
     lde_polynomial_values: PolynomialValues<F::Extension>,
-    challenger: &mut Challenger<F, C::Hasher>,
+    challenger: &mut T,
     fri_params: &FriParams,
     timing: &mut TimingTree,
 ) -> FriProof<F, C::Hasher, D> {
@@ -34,7 +41,7 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     let (trees, final_coeffs) = timed!(
         timing,
         "fold codewords in the commitment phase",
-        fri_committed_trees::<F, C, D>(
+        fri_committed_trees::<F, C, T, D>(
             lde_polynomial_coeffs,
             lde_polynomial_values,
             challenger,
@@ -46,12 +53,18 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     let pow_witness = timed!(
         timing,
         "find proof-of-work witness",
-        fri_proof_of_work::<F, C, D>(challenger, &fri_params.config)
+        challenger.fri_proof_of_work(&fri_params.config)
     );
 
     // Query phase
+    let (pow_response, x_indices) =
+        challenger.fri_pow_response_and_query_indices(pow_witness, n, &fri_params.config);
+    assert!(
+        pow_response.to_canonical_u64().leading_zeros()
+            >= fri_params.config.min_pow_response_leading_zeros::<F>()
+    );
     let query_round_proofs =
-        fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, challenger, n, fri_params);
+        fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, x_indices, fri_params);
 
     FriProof {
         commit_phase_merkle_caps: trees.iter().map(|t| t.cap.clone()).collect(),
@@ -66,10 +79,15 @@ type FriCommitedTrees<F, C, const D: usize> = (
     PolynomialCoeffs<<F as Extendable<D>>::Extension>,
 );
 
-fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+fn fri_committed_trees<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
     mut coeffs: PolynomialCoeffs<F::Extension>,
     mut values: PolynomialValues<F::Extension>,
-    challenger: &mut Challenger<F, C::Hasher>,
+    challenger: &mut T,
     fri_params: &FriParams,
 ) -> FriCommitedTrees<F, C, D> {
     let mut trees = Vec::new();
@@ -89,7 +107,7 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
         challenger.observe_cap(&tree.cap);
         trees.push(tree);
 
-        let beta = challenger.get_extension_challenge::<D>();
+        let beta = challenger.get_extension_challenge(&mut ());
         // P(x) = sum_{i<r} x^i * P_i(x^r) becomes sum_{i<r} beta^i * P_i(x).
         coeffs = PolynomialCoeffs::new(
             coeffs
@@ -111,55 +129,71 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     (trees, coeffs)
 }
 
-/// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness.
-fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-    challenger: &mut Challenger<F, C::Hasher>,
+/// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol for a `Challenger`.
+/// Returns the PoW witness. The challenger is left as it was;
+/// `NativeTranscript::fri_pow_response_and_query_indices` then takes the witness into account.
+pub(crate) fn challenger_proof_of_work<F: RichField, H: Hasher<F>>(
+    challenger: &Challenger<F, H>,
     config: &FriConfig,
 ) -> F {
-    let min_leading_zeros = config.proof_of_work_bits + (64 - F::order().bits()) as u32;
-
-    // The easiest implementation would be repeatedly clone our Challenger. With each clone, we'd
-    // observe an incrementing PoW witness, then get the PoW response. If it contained sufficient
-    // leading zeros, we'd end the search, and store this clone as our new challenger.
-    //
-    // However, performance is critical here. We want to avoid cloning Challenger, particularly
-    // since it stores vectors, which means allocations. We'd like a more compact state to clone.
-    //
-    // We know that a duplex will be performed right after we send the PoW witness, so we can ignore
-    // any output_buffer, which will be invalidated. We also know input_buffer.len() < SPONGE_WIDTH,
-    // an invariant of Challenger.
-    //
-    // We separate the duplex operation into two steps, one which can be performed now, and the
-    // other which depends on the PoW witness candidate. The first step is the overwrite our sponge
-    // state with any inputs (excluding the PoW witness candidate). The second step is to overwrite
-    // one more element of our sponge state with the candidate, then apply the permutation,
-    // obtaining our duplex's post-state which contains the PoW response.
-    let mut duplex_intermediate_state = challenger.sponge_state;
-    let witness_input_pos = challenger.input_buffer.len();
-    for (i, input) in challenger.input_buffer.iter().enumerate() {
-        duplex_intermediate_state[i] = *input;
+    match config.pow_hash {
+        FriPowHash::Field => {
+            // The easiest implementation would be repeatedly clone our Challenger. With each clone,
+            // we'd observe an incrementing PoW witness, then get the PoW response. If it contained
+            // sufficient leading zeros, we'd end the search.
+            //
+            // However, performance is critical here. We want to avoid cloning Challenger,
+            // particularly since it stores vectors, which means allocations. We'd like a more
+            // compact state to clone.
+            //
+            // We know that a duplex will be performed right after we send the PoW witness, so we
+            // can ignore any output_buffer, which will be invalidated. We also know
+            // input_buffer.len() < SPONGE_WIDTH, an invariant of Challenger.
+            //
+            // We separate the duplex operation into two steps, one which can be performed now, and
+            // the other which depends on the PoW witness candidate. The first step is the
+            // overwrite our sponge state with any inputs (excluding the PoW witness candidate). The
+            // second step is to overwrite one more element of our sponge state with the candidate,
+            // then apply the permutation, obtaining our duplex's post-state which contains the PoW
+            // response.
+            let mut duplex_intermediate_state = challenger.sponge_state;
+            let witness_input_pos = challenger.input_buffer.len();
+            for (i, input) in challenger.input_buffer.iter().enumerate() {
+                duplex_intermediate_state[i] = *input;
+            }
+
+            find_pow_witness(config, |candidate| {
+                let mut duplex_state = duplex_intermediate_state;
+                duplex_state[witness_input_pos] = candidate;
+                duplex_state = H::Permutation::permute(duplex_state);
+                duplex_state[SPONGE_RATE - 1]
+            })
+        }
+        FriPowHash::Keccak => keccak_proof_of_work(challenger.clone().get_hash(), config),
     }
+}
 
-    let pow_witness = (0..=F::NEG_ONE.to_canonical_u64())
+/// Finds a PoW witness whose Keccak PoW response, for the given seed, has enough leading zeros.
+pub(crate) fn keccak_proof_of_work<F: RichField>(seed: HashOut<F>, config: &FriConfig) -> F {
+    find_pow_witness(config, |candidate| {
+        keccak_pow_response(&keccak_pow_digest(seed, candidate))
+    })
+}
+
+/// Finds a PoW witness whose PoW response, as computed by `pow_response`, has enough leading zeros.
+pub(crate) fn find_pow_witness<F: RichField>(
+    config: &FriConfig,
+    pow_response: impl Fn(F) -> F + Sync,
+) -> F {
+    let min_leading_zeros = config.min_pow_response_leading_zeros::<F>();
+    (0..=F::NEG_ONE.to_canonical_u64())
         .into_par_iter()
         .find_any(|&candidate| {
-            let mut duplex_state = duplex_intermediate_state;
-            duplex_state[witness_input_pos] = F::from_canonical_u64(candidate);
-            duplex_state =
-                <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Permutation::permute(duplex_state);
-            let pow_response = duplex_state[SPONGE_RATE - 1];
-            let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
-            leading_zeros >= min_leading_zeros
+            let response = pow_response(F::from_canonical_u64(candidate));
+            response.to_canonical_u64().leading_zeros() >= min_leading_zeros
         })
         .map(F::from_canonical_u64)
-        .expect("Proof of work failed. This is highly unlikely!");
-
-    // Recompute pow_response using our normal Challenger code, and make sure it matches.
-    challenger.observe_element(pow_witness);
-    let pow_response = challenger.get_challenge();
-    let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
-    assert!(leading_zeros >= min_leading_zeros);
-    pow_witness
+        .expect("Proof of work failed. This is highly unlikely!")
 }
 
 fn fri_prover_query_rounds<
@@ -169,15 +203,12 @@ fn fri_prover_query_rounds<
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
     trees: &[MerkleTree<F, C::Hasher>],
-    challenger: &mut Challenger<F, C::Hasher>,
-    n: usize,
+    x_indices: Vec<usize>,
     fri_params: &FriParams,
 ) -> Vec<FriQueryRound<F, C::Hasher, D>> {
-    challenger
-        .get_n_challenges(fri_params.config.num_query_rounds)
+    x_indices
         .into_par_iter()
-        .map(|rand| {
-            let x_index = rand.to_canonical_u64() as usize % n;
+        .map(|x_index| {
             fri_prover_query_round::<F, C, D>(initial_merkle_trees, trees, x_index, fri_params)
         })
         .collect()
@@ -113,7 +113,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) {
         self.assert_leading_zeros(
             fri_pow_response,
-            config.proof_of_work_bits + (64 - F::order().bits()) as u32,
+            config.min_pow_response_leading_zeros::<F>(),
         );
     }
 
@@ -158,8 +158,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self,
             "precompute reduced evaluations",
             PrecomputedReducedOpeningsTarget::from_os_and_alpha(
+                instance,
                 openings,
                 challenges.fri_alpha,
+                params.degree_bits,
                 self
             )
         );
@@ -237,6 +239,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             params.config.cap_height + proof.evals_proofs[0].1.siblings.len()
                 - params.config.rate_bits
         );
+        // The powers of `x` degree-adjusted polynomials are multiplied by, for each oracle.
+        let degree_adjustments = instance
+            .oracles
+            .iter()
+            .map(|oracle| match oracle.degree_adjustment(degree_log) {
+                0 => None,
+                shift => Some(self.exp_u64(subgroup_x, shift as u64)),
+            })
+            .collect_vec();
         let subgroup_x = self.convert_to_ext(subgroup_x);
         let mut alpha = ReducingFactorTarget::new(alpha);
         let mut sum = self.zero_extension();
@@ -252,7 +263,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 .map(|p| {
                     let poly_blinding = instance.oracles[p.oracle_index].blinding;
                     let salted = params.hiding && poly_blinding;
-                    proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
+                    let eval = proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted);
+                    match degree_adjustments[p.oracle_index] {
+                        Some(x_shift) => self.mul(x_shift, eval),
+                        None => eval,
+                    }
                 })
                 .collect_vec();
             let reduced_evals = alpha.reduce_base(&evals, self);
@@ -486,14 +501,34 @@ struct PrecomputedReducedOpeningsTarget<const D: usize> {
 
 impl<const D: usize> PrecomputedReducedOpeningsTarget<D> {
     fn from_os_and_alpha<F: RichField + Extendable<D>>(
+        instance: &FriInstanceInfoTarget<D>,
         openings: &FriOpeningsTarget<D>,
         alpha: ExtensionTarget<D>,
+        degree_bits: usize,
         builder: &mut CircuitBuilder<F, D>,
     ) -> Self {
-        let reduced_openings_at_point = openings
+        let reduced_openings_at_point = instance
             .batches
             .iter()
-            .map(|batch| ReducingFactorTarget::new(alpha).reduce(&batch.values, builder))
+            .zip(&openings.batches)
+            .map(|(batch_info, batch)| {
+                let values = batch_info
+                    .polynomials
+                    .iter()
+                    .zip(&batch.values)
+                    .map(|(p, &v)| {
+                        match instance.oracles[p.oracle_index].degree_adjustment(degree_bits) {
+                            0 => v,
+                            shift => {
+                                let z_shift =
+                                    builder.exp_u64_extension(batch_info.point, shift as u64);
+                                builder.mul_extension(z_shift, v)
+                            }
+                        }
+                    })
+                    .collect_vec();
+                ReducingFactorTarget::new(alpha).reduce(&values, builder)
+            })
             .collect();
         Self {
             reduced_openings_at_point,
@@ -28,6 +28,27 @@ pub struct FriInstanceInfoTarget<const D: usize> {
 pub struct FriOracleInfo {
     pub num_polys: usize,
     pub blinding: bool,
+    /// The log of the degree bound of this oracle's polynomials, if it's smaller than that of the
+    /// instance, e.g. for an auxiliary table. These polynomials are committed over the same LDE
+    /// domain as the others, with zero coefficients past their degree bound, and opened in the same
+    /// FRI proof, which checks their degree bound by degree-adjusting them; see
+    /// `degree_adjustment`.
+    pub degree_bits: Option<usize>,
+}
+
+impl FriOracleInfo {
+    /// The power of `X` this oracle's polynomials are multiplied by, so that each `X^s f(X)` is
+    /// checked against the instance's degree bound `2^degree_bits`, which proves `f` has degree
+    /// less than `2^self.degree_bits`. Openings `f(z)` are adjusted to `z^s f(z)` accordingly.
+    pub fn degree_adjustment(&self, degree_bits: usize) -> usize {
+        self.degree_bits.map_or(0, |oracle_degree_bits| {
+            assert!(
+                oracle_degree_bits <= degree_bits,
+                "Oracle degree exceeds the FRI instance's degree"
+            );
+            (1 << degree_bits) - (1 << oracle_degree_bits)
+        })
+    }
 }
 
 /// A batch of openings at a particular point.
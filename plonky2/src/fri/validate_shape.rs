@@ -2,7 +2,7 @@ use anyhow::ensure;
 
 use crate::field::extension::Extendable;
 use crate::fri::proof::{FriProof, FriQueryRound, FriQueryStep};
-use crate::fri::structure::FriInstanceInfo;
+use crate::fri::structure::FriOracleInfo;
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
 use crate::plonk::config::GenericConfig;
@@ -10,7 +10,7 @@ use crate::plonk::plonk_common::salt_size;
 
 pub(crate) fn validate_fri_proof_shape<F, C, const D: usize>(
     proof: &FriProof<F, C::Hasher, D>,
-    instance: &FriInstanceInfo<F, D>,
+    oracles: &[FriOracleInfo],
     params: &FriParams,
 ) -> anyhow::Result<()>
 where
@@ -25,22 +25,20 @@ where
     } = proof;
 
     let cap_height = params.config.cap_height;
+    ensure!(commit_phase_merkle_caps.len() == params.reduction_arity_bits.len());
     for cap in commit_phase_merkle_caps {
-        ensure!(cap.height() == cap_height);
+        ensure!(cap.len() == 1 << cap_height);
     }
 
+    ensure!(query_round_proofs.len() == params.config.num_query_rounds);
     for query_round in query_round_proofs {
         let FriQueryRound {
             initial_trees_proof,
             steps,
         } = query_round;
 
-        ensure!(initial_trees_proof.evals_proofs.len() == instance.oracles.len());
-        for ((leaf, merkle_proof), oracle) in initial_trees_proof
-            .evals_proofs
-            .iter()
-            .zip(&instance.oracles)
-        {
+        ensure!(initial_trees_proof.evals_proofs.len() == oracles.len());
+        for ((leaf, merkle_proof), oracle) in initial_trees_proof.evals_proofs.iter().zip(oracles) {
             ensure!(leaf.len() == oracle.num_polys + salt_size(oracle.blinding && params.hiding));
             ensure!(merkle_proof.len() + cap_height == params.lde_bits());
         }
@@ -51,7 +51,7 @@ pub(crate) fn fri_verify_proof_of_work<F: RichField + Extendable<D>, const D: us
 ) -> Result<()> {
     ensure!(
         fri_pow_response.to_canonical_u64().leading_zeros()
-            >= config.proof_of_work_bits + (64 - F::order().bits()) as u32,
+            >= config.min_pow_response_leading_zeros::<F>(),
         "Invalid proof of work witness."
     );
 
@@ -70,7 +70,7 @@ pub fn verify_fri_proof<
     proof: &FriProof<F, C::Hasher, D>,
     params: &FriParams,
 ) -> Result<()> {
-    validate_fri_proof_shape::<F, C, D>(proof, instance, params)?;
+    validate_fri_proof_shape::<F, C, D>(proof, &instance.oracles, params)?;
 
     // Size of the LDE domain.
     let n = params.lde_size();
@@ -84,8 +84,12 @@ pub fn verify_fri_proof<
         "Number of query rounds does not match config."
     );
 
-    let precomputed_reduced_evals =
-        PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
+    let precomputed_reduced_evals = PrecomputedReducedOpenings::from_os_and_alpha(
+        instance,
+        openings,
+        challenges.fri_alpha,
+        params.degree_bits,
+    );
     for (&x_index, round_proof) in challenges
         .fri_query_indices
         .iter()
@@ -132,6 +136,17 @@ pub(crate) fn fri_combine_initial<
     params: &FriParams,
 ) -> F::Extension {
     assert!(D > 1, "Not implemented for D=1.");
+    // The powers of `x` degree-adjusted polynomials are multiplied by, for each oracle.
+    let degree_adjustments = instance
+        .oracles
+        .iter()
+        .map(
+            |oracle| match oracle.degree_adjustment(params.degree_bits) {
+                0 => None,
+                shift => Some(subgroup_x.exp_u64(shift as u64)),
+            },
+        )
+        .collect::<Vec<_>>();
     let subgroup_x = F::Extension::from_basefield(subgroup_x);
     let mut alpha = ReducingFactor::new(alpha);
     let mut sum = F::Extension::ZERO;
@@ -147,7 +162,11 @@ pub(crate) fn fri_combine_initial<
             .map(|p| {
                 let poly_blinding = instance.oracles[p.oracle_index].blinding;
                 let salted = params.hiding && poly_blinding;
-                proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
+                let eval = proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted);
+                match degree_adjustments[p.oracle_index] {
+                    Some(x_shift) => x_shift * eval,
+                    None => eval,
+                }
             })
             .map(F::Extension::from_basefield);
         let reduced_evals = alpha.reduce(evals);
@@ -249,11 +268,31 @@ pub(crate) struct PrecomputedReducedOpenings<F: RichField + Extendable<D>, const
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> PrecomputedReducedOpenings<F, D> {
-    pub(crate) fn from_os_and_alpha(openings: &FriOpenings<F, D>, alpha: F::Extension) -> Self {
-        let reduced_openings_at_point = openings
+    /// Openings of degree-adjusted polynomials, `f(z)`, are adjusted to `z^s f(z)` before being
+    /// reduced.
+    pub(crate) fn from_os_and_alpha(
+        instance: &FriInstanceInfo<F, D>,
+        openings: &FriOpenings<F, D>,
+        alpha: F::Extension,
+        degree_bits: usize,
+    ) -> Self {
+        let reduced_openings_at_point = instance
             .batches
             .iter()
-            .map(|batch| ReducingFactor::new(alpha).reduce(batch.values.iter()))
+            .zip(&openings.batches)
+            .map(|(batch_info, batch)| {
+                let values = batch_info
+                    .polynomials
+                    .iter()
+                    .zip(&batch.values)
+                    .map(|(p, &v)| {
+                        match instance.oracles[p.oracle_index].degree_adjustment(degree_bits) {
+                            0 => v,
+                            shift => batch_info.point.exp_u64(shift as u64) * v,
+                        }
+                    });
+                ReducingFactor::new(alpha).reduce(values)
+            })
             .collect();
         Self {
             reduced_openings_at_point,
@@ -11,6 +11,7 @@ use crate::iop::generator::{GeneratedValues, SimpleGenerator};
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::ceil_div_usize;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Computes `-x`.
@@ -235,8 +236,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         product
     }
 
-    // TODO: Test
     /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits.
+    ///
+    /// Exponents with more bits than fit in a single `ExponentiationGate` are handled in packed
+    /// rounds: the bits are split into chunks, each chunk is applied to a successively squared
+    /// base, and the partial results are multiplied together.
     pub fn exp_from_bits(
         &mut self,
         base: Target,
@@ -247,6 +251,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let num_power_bits = gate.num_power_bits;
         let mut exp_bits_vec: Vec<BoolTarget> =
             exponent_bits.into_iter().map(|b| *b.borrow()).collect();
+        if exp_bits_vec.len() > num_power_bits {
+            return self.exp_from_bits_packed(base, &exp_bits_vec, num_power_bits);
+        }
         while exp_bits_vec.len() < num_power_bits {
             exp_bits_vec.push(_false);
         }
@@ -260,7 +267,39 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         Target::wire(row, gate.wire_output())
     }
 
-    // TODO: Test
+    /// Splits `exponent_bits` into rounds of `num_power_bits - 1` bits. Each round uses one
+    /// `ExponentiationGate` for the round's contribution and, except for the last round, one more
+    /// gate to square the base `num_power_bits - 1` times. Using one bit fewer than the gate's
+    /// capacity lets the squaring exponent `2^(num_power_bits - 1)` fit in a single gate.
+    fn exp_from_bits_packed(
+        &mut self,
+        base: Target,
+        exponent_bits: &[BoolTarget],
+        num_power_bits: usize,
+    ) -> Target {
+        let bits_per_round = num_power_bits - 1;
+        assert!(
+            bits_per_round > 0,
+            "ExponentiationGate is too small for packed rounds"
+        );
+        let _false = self._false();
+        let _true = self._true();
+        let mut shift_bits = vec![_false; bits_per_round];
+        shift_bits.push(_true);
+
+        let num_rounds = ceil_div_usize(exponent_bits.len(), bits_per_round);
+        let mut round_base = base;
+        let mut product = self.one();
+        for (i, round_bits) in exponent_bits.chunks(bits_per_round).enumerate() {
+            let round_result = self.exp_from_bits(round_base, round_bits);
+            product = self.mul(product, round_result);
+            if i + 1 < num_rounds {
+                round_base = self.exp_from_bits(round_base, &shift_bits);
+            }
+        }
+        product
+    }
+
     /// Exponentiate `base` to the power of `exponent`, where `exponent < 2^num_bits`.
     pub fn exp(&mut self, base: Target, exponent: Target, num_bits: usize) -> Target {
         let exponent_bits = self.split_le(exponent, num_bits);
@@ -301,7 +340,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Exponentiate `base` to the power of a known `exponent`.
-    // TODO: Test
     pub fn exp_u64(&mut self, base: Target, mut exponent: u64) -> Target {
         let mut exp_bits = Vec::new();
         while exponent != 0 {
@@ -327,6 +365,39 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.inverse_extension(x_ext).0[0]
     }
 
+    /// Computes the quotient and remainder of the integer division of `x` by `y`, which must be at
+    /// most `num_bits` bits, e.g. after a `range_check`, as must the quotient, e.g. because `x` is
+    /// too. If `y = 0`, no quotient and remainder satisfy the constraints, so they are left unset
+    /// and witness generation fails with an error.
+    pub fn div_rem(&mut self, x: Target, y: Target, num_bits: usize) -> (Target, Target) {
+        // With at most 32 bits, `quotient * y + remainder < 2^64 - 2^32 < F::ORDER` can't wrap
+        // around, so the division is unique.
+        assert!(
+            num_bits <= 32,
+            "div_rem supports at most 32 bits, not {}",
+            num_bits
+        );
+        let quotient = self.add_virtual_target();
+        let remainder = self.add_virtual_target();
+        self.add_simple_generator(DivRemGenerator {
+            x,
+            y,
+            quotient,
+            remainder,
+        });
+
+        self.range_check(quotient, num_bits);
+        self.range_check(remainder, num_bits);
+        // `remainder < y`, i.e. `y - 1 - remainder` has `num_bits` bits rather than wrapping around.
+        let y_minus_one = self.add_const(y, F::NEG_ONE);
+        let gap = self.sub(y_minus_one, remainder);
+        self.range_check(gap, num_bits);
+        let product = self.mul_add(quotient, y, remainder);
+        self.connect(product, x);
+
+        (quotient, remainder)
+    }
+
     pub fn not(&mut self, b: BoolTarget) -> BoolTarget {
         let one = self.one();
         let res = self.sub(one, b.target);
@@ -388,6 +459,31 @@ impl<F: RichField> SimpleGenerator<F> for EqualityGenerator {
     }
 }
 
+#[derive(Debug)]
+struct DivRemGenerator {
+    x: Target,
+    y: Target,
+    quotient: Target,
+    remainder: Target,
+}
+
+impl<F: RichField> SimpleGenerator<F> for DivRemGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x, self.y]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = witness.get_target(self.x).to_canonical_u64();
+        let y = witness.get_target(self.y).to_canonical_u64();
+        if y == 0 {
+            return;
+        }
+
+        out_buffer.set_target(self.quotient, F::from_canonical_u64(x / y));
+        out_buffer.set_target(self.remainder, F::from_canonical_u64(x % y));
+    }
+}
+
 /// Represents a base arithmetic operation in the circuit. Used to memoize results.
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub(crate) struct BaseArithmeticOperation<F: Field64> {
@@ -397,3 +493,127 @@ pub(crate) struct BaseArithmeticOperation<F: Field64> {
     multiplicand_1: Target,
     addend: Target,
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::field::ops::Square;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    #[test]
+    fn test_exp() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let base = F::rand();
+        let exponent = OsRng.gen::<u32>() as u64;
+        let base_t = builder.add_virtual_target();
+        let exponent_t = builder.add_virtual_target();
+        pw.set_target(base_t, base);
+        pw.set_target(exponent_t, F::from_canonical_u64(exponent));
+
+        let result = builder.exp(base_t, exponent_t, 32);
+        let expected = builder.constant(base.exp_u64(exponent));
+        builder.connect(result, expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_exp_from_bits_packed() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // More bits than a single `ExponentiationGate` can handle.
+        let bits = (0..200).map(|_| OsRng.gen::<bool>()).collect::<Vec<_>>();
+        let base = F::rand();
+        let expected =
+            bits.iter().rev().fold(
+                F::ONE,
+                |acc, &b| if b { acc.square() * base } else { acc.square() },
+            );
+
+        let base_t = builder.add_virtual_target();
+        pw.set_target(base_t, base);
+        let bit_ts = bits
+            .iter()
+            .map(|&b| builder.constant_bool(b))
+            .collect::<Vec<_>>();
+        let result = builder.exp_from_bits(base_t, bit_ts);
+        let expected_t = builder.constant(expected);
+        builder.connect(result, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_div_rem() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let max = u32::MAX as u64;
+        for (x, y) in [(17, 5), (4, 7), (0, 1), (max, 1), (max, max), (max, 65536)] {
+            let x_t = builder.add_virtual_target();
+            let y_t = builder.add_virtual_target();
+            pw.set_target(x_t, F::from_canonical_u64(x));
+            pw.set_target(y_t, F::from_canonical_u64(y));
+            let (quotient, remainder) = builder.div_rem(x_t, y_t, 32);
+            let expected_quotient = builder.constant(F::from_canonical_u64(x / y));
+            let expected_remainder = builder.constant(F::from_canonical_u64(x % y));
+            builder.connect(quotient, expected_quotient);
+            builder.connect(remainder, expected_remainder);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_div_rem_by_zero() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        builder.div_rem(x, y, 32);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_target(x, F::from_canonical_u64(17));
+        pw.set_target(y, F::ZERO);
+        let err = data.check_witness(pw).unwrap_err();
+        assert!(err.to_string().contains("weren't run"), "{err}");
+        Ok(())
+    }
+}
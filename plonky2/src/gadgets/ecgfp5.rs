@@ -0,0 +1,214 @@
+//! Circuit gadgets for the ecGFp5 curve; see `curve::ecgfp5` for the native counterpart.
+
+use crate::curve::ecgfp5::{curve_a, curve_b, CurvePoint, GFp5};
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::gadgets::gfp5::GFp5Target;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A point of ecGFp5, in affine coordinates. We use incomplete arithmetic for efficiency, so these
+/// points can't be the identity; operations which would produce it, or hit an exceptional case of
+/// the addition formulas, make the circuit unsatisfiable rather than give a wrong result.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CurvePointTarget {
+    pub x: GFp5Target,
+    pub y: GFp5Target,
+}
+
+/// `ecgfp5_scalar_mul` starts its accumulator at an offset point, so that it's never the identity:
+/// twice the first point with an `x` coordinate from this one on. The offset's discrete log is
+/// unknown, so in practice no input makes the accumulator hit an exceptional case.
+const SCALAR_MUL_OFFSET_X: u64 = 0x65636766_70350001;
+
+fn scalar_mul_offset<F: RichField + Extendable<5>>() -> CurvePoint<F> {
+    (SCALAR_MUL_OFFSET_X..)
+        .find_map(|x| CurvePoint::lift_x(GFp5::<F>::from_canonical_u64(x)))
+        .unwrap()
+        .double()
+}
+
+impl<F: RichField + Extendable<D> + Extendable<5>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a virtual point, which isn't constrained to be on the curve; see `ecgfp5_assert_valid`.
+    pub fn add_virtual_curve_point_target(&mut self) -> CurvePointTarget {
+        CurvePointTarget {
+            x: self.add_virtual_gfp5_target(),
+            y: self.add_virtual_gfp5_target(),
+        }
+    }
+
+    pub fn constant_curve_point(&mut self, point: CurvePoint<F>) -> CurvePointTarget {
+        assert!(!point.is_identity, "The identity has no affine coordinates");
+        CurvePointTarget {
+            x: self.constant_gfp5(point.x),
+            y: self.constant_gfp5(point.y),
+        }
+    }
+
+    pub fn connect_curve_points(&mut self, p: CurvePointTarget, q: CurvePointTarget) {
+        self.connect_gfp5(p.x, q.x);
+        self.connect_gfp5(p.y, q.y);
+    }
+
+    /// Asserts that `p` is on the curve. It may still be outside the prime order subgroup, which
+    /// callers receiving points from untrusted parties should check natively.
+    pub fn ecgfp5_assert_valid(&mut self, p: CurvePointTarget) {
+        let a = self.constant_gfp5(curve_a());
+        let b = self.constant_gfp5(curve_b());
+        let y_squared = self.square_gfp5(p.y);
+        let x_squared = self.square_gfp5(p.x);
+        let x_squared_plus_a = self.add_gfp5(x_squared, a);
+        let x_cubed_plus_a_x = self.mul_gfp5(x_squared_plus_a, p.x);
+        let rhs = self.add_gfp5(x_cubed_plus_a_x, b);
+        self.connect_gfp5(y_squared, rhs);
+    }
+
+    pub fn ecgfp5_neg(&mut self, p: CurvePointTarget) -> CurvePointTarget {
+        CurvePointTarget {
+            x: p.x,
+            y: self.neg_gfp5(p.y),
+        }
+    }
+
+    /// The sum of `p` and a point with `x` coordinate `q_x`, given the slope `lambda` of the line
+    /// through them, which is the tangent at `p` when doubling.
+    fn ecgfp5_third_point(
+        &mut self,
+        lambda: GFp5Target,
+        p: CurvePointTarget,
+        q_x: GFp5Target,
+    ) -> CurvePointTarget {
+        let lambda_squared = self.square_gfp5(lambda);
+        let x_sum = self.add_gfp5(p.x, q_x);
+        let x = self.sub_gfp5(lambda_squared, x_sum);
+        let dx = self.sub_gfp5(p.x, x);
+        let lambda_dx = self.mul_gfp5(lambda, dx);
+        let y = self.sub_gfp5(lambda_dx, p.y);
+        CurvePointTarget { x, y }
+    }
+
+    /// Computes `2 p`. Results in an unsatisfiable instance if `p` has order 2.
+    pub fn ecgfp5_double(&mut self, p: CurvePointTarget) -> CurvePointTarget {
+        let a = self.constant_gfp5(curve_a());
+        let x_squared = self.square_gfp5(p.x);
+        let three_x_squared = self.scalar_mul_gfp5(F::from_canonical_u64(3), x_squared);
+        let numerator = self.add_gfp5(three_x_squared, a);
+        let two_y = self.add_gfp5(p.y, p.y);
+        let lambda = self.div_gfp5(numerator, two_y);
+        self.ecgfp5_third_point(lambda, p, p.x)
+    }
+
+    /// Computes `p + q`. Results in an unsatisfiable instance if `p` and `q` have the same `x`,
+    /// i.e. if `q = p` or `q = -p`.
+    pub fn ecgfp5_add(&mut self, p: CurvePointTarget, q: CurvePointTarget) -> CurvePointTarget {
+        let dy = self.sub_gfp5(q.y, p.y);
+        let dx = self.sub_gfp5(q.x, p.x);
+        let lambda = self.div_gfp5(dy, dx);
+        self.ecgfp5_third_point(lambda, p, q.x)
+    }
+
+    /// Selects `p` or `q` based on `b`, i.e., this returns `if b { p } else { q }`.
+    pub fn ecgfp5_select(
+        &mut self,
+        b: BoolTarget,
+        p: CurvePointTarget,
+        q: CurvePointTarget,
+    ) -> CurvePointTarget {
+        CurvePointTarget {
+            x: self.select_gfp5(b, p.x, q.x),
+            y: self.select_gfp5(b, p.y, q.y),
+        }
+    }
+
+    /// Computes `s p`, where `scalar_bits` are the little-endian bits of `s`, e.g. from
+    /// `split_le`. Results in an unsatisfiable instance if `s p` is the identity, e.g. if
+    /// `s = 0`.
+    pub fn ecgfp5_scalar_mul(
+        &mut self,
+        p: CurvePointTarget,
+        scalar_bits: &[BoolTarget],
+    ) -> CurvePointTarget {
+        // Starting from an offset point rather than the identity keeps the accumulator away from
+        // the exceptional cases of incomplete addition. The offset is removed at the end.
+        let offset = scalar_mul_offset::<F>();
+        let mut acc = self.constant_curve_point(offset);
+        for &bit in scalar_bits.iter().rev() {
+            acc = self.ecgfp5_double(acc);
+            let sum = self.ecgfp5_add(acc, p);
+            acc = self.ecgfp5_select(bit, sum, acc);
+        }
+        let final_offset = (0..scalar_bits.len()).fold(offset, |q, _| q.double());
+        let neg_final_offset = self.constant_curve_point(final_offset.neg());
+        self.ecgfp5_add(acc, neg_final_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::BigUint;
+    use rand::rngs::OsRng;
+
+    use crate::curve::ecgfp5::{sample_scalar, CurvePoint};
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_curve_add_double() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let p = builder.add_virtual_curve_point_target();
+        let q = builder.add_virtual_curve_point_target();
+        builder.ecgfp5_assert_valid(p);
+        builder.ecgfp5_assert_valid(q);
+        let sum = builder.ecgfp5_add(p, q);
+        let double = builder.ecgfp5_double(p);
+        let neg = builder.ecgfp5_neg(q);
+
+        let (pv, qv) = (
+            CurvePoint::sample(&mut OsRng),
+            CurvePoint::sample(&mut OsRng),
+        );
+        let expected_sum = builder.constant_curve_point(pv.add(&qv));
+        let expected_double = builder.constant_curve_point(pv.double());
+        let expected_neg = builder.constant_curve_point(qv.neg());
+        builder.connect_curve_points(sum, expected_sum);
+        builder.connect_curve_points(double, expected_double);
+        builder.connect_curve_points(neg, expected_neg);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_curve_point_target(p, pv);
+        pw.set_curve_point_target(q, qv);
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_curve_scalar_mul() -> Result<()> {
+        const SCALAR_BITS: usize = 32;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let p = builder.add_virtual_curve_point_target();
+        let scalar = builder.add_virtual_target();
+        let scalar_bits = builder.split_le(scalar, SCALAR_BITS);
+        let product = builder.ecgfp5_scalar_mul(p, &scalar_bits);
+        let expected = builder.add_virtual_curve_point_target();
+        builder.connect_curve_points(product, expected);
+        let data = builder.build::<C>();
+
+        let pv = CurvePoint::generator().mul(&sample_scalar(&mut OsRng));
+        let s = 0xdeadbeef_u64;
+        let mut pw = PartialWitness::new();
+        pw.set_curve_point_target(p, pv);
+        pw.set_target(scalar, F::from_canonical_u64(s));
+        pw.set_curve_point_target(expected, pv.mul(&BigUint::from(s)));
+        data.verify(data.prove(pw)?)
+    }
+}
@@ -0,0 +1,194 @@
+//! Arithmetic in prime fields of order less than `2^32`, e.g. BabyBear, emulated in circuits over a
+//! larger field such as Goldilocks: the groundwork for verifying proofs over such fields, see
+//! `RecursiveConfigPair`.
+//!
+//! An element of the emulated field is held in a single target as its canonical value. Sums and
+//! products of canonical values are less than `ORDER^2`, so they don't wrap around the circuit's
+//! field and are reduced with `div_rem`, whose quotient then fits in 32 bits.
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A prime field of order less than `2^32`, whose elements are emulated by `EmulatedTarget`s.
+pub trait EmulatedPrime: 'static + Copy + Clone + Debug + Send + Sync {
+    const ORDER: u64;
+}
+
+/// The BabyBear field, of order `15 * 2^27 + 1`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BabyBear;
+
+impl EmulatedPrime for BabyBear {
+    const ORDER: u64 = 15 * (1 << 27) + 1;
+}
+
+/// An element of the field `P`, as its canonical value.
+#[derive(Copy, Clone, Debug)]
+pub struct EmulatedTarget<P: EmulatedPrime> {
+    target: Target,
+    _phantom: PhantomData<P>,
+}
+
+impl<P: EmulatedPrime> EmulatedTarget<P> {
+    /// The target holding the element's canonical value, e.g. to set it in a witness.
+    pub fn target(&self) -> Target {
+        self.target
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns an element of `P` whose value is checked to be canonical.
+    pub fn add_virtual_emulated_target<P: EmulatedPrime>(&mut self) -> EmulatedTarget<P> {
+        let target = self.add_virtual_target();
+        self.assert_emulated_canonical::<P>(target);
+        EmulatedTarget {
+            target,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the element `value mod P::ORDER` of `P`.
+    pub fn constant_emulated<P: EmulatedPrime>(&mut self, value: u64) -> EmulatedTarget<P> {
+        let target = self.constant(F::from_canonical_u64(value % emulated_order::<P>()));
+        EmulatedTarget {
+            target,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn connect_emulated<P: EmulatedPrime>(
+        &mut self,
+        x: EmulatedTarget<P>,
+        y: EmulatedTarget<P>,
+    ) {
+        self.connect(x.target, y.target);
+    }
+
+    pub fn add_emulated<P: EmulatedPrime>(
+        &mut self,
+        x: EmulatedTarget<P>,
+        y: EmulatedTarget<P>,
+    ) -> EmulatedTarget<P> {
+        let sum = self.add(x.target, y.target);
+        self.reduce_emulated(sum)
+    }
+
+    pub fn sub_emulated<P: EmulatedPrime>(
+        &mut self,
+        x: EmulatedTarget<P>,
+        y: EmulatedTarget<P>,
+    ) -> EmulatedTarget<P> {
+        // `x + (ORDER - y)` is nonnegative, unlike `x - y`.
+        let order = F::from_canonical_u64(emulated_order::<P>());
+        let x_plus_order = self.add_const(x.target, order);
+        let difference = self.sub(x_plus_order, y.target);
+        self.reduce_emulated(difference)
+    }
+
+    pub fn mul_emulated<P: EmulatedPrime>(
+        &mut self,
+        x: EmulatedTarget<P>,
+        y: EmulatedTarget<P>,
+    ) -> EmulatedTarget<P> {
+        let product = self.mul(x.target, y.target);
+        self.reduce_emulated(product)
+    }
+
+    /// The element of `P` whose canonical value is `x mod P::ORDER`, for `x < P::ORDER^2`.
+    fn reduce_emulated<P: EmulatedPrime>(&mut self, x: Target) -> EmulatedTarget<P> {
+        let order = self.constant(F::from_canonical_u64(emulated_order::<P>()));
+        let (_, target) = self.div_rem(x, order, 32);
+        EmulatedTarget {
+            target,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Asserts that `x < P::ORDER`.
+    fn assert_emulated_canonical<P: EmulatedPrime>(&mut self, x: Target) {
+        let bits = self.split_le(x, 32);
+        self.assert_bits_le_constant(&bits, emulated_order::<P>() - 1);
+    }
+}
+
+fn emulated_order<P: EmulatedPrime>() -> u64 {
+    assert!(
+        P::ORDER < 1 << 32,
+        "Emulated fields must have order less than 2^32, not {}",
+        P::ORDER
+    );
+    P::ORDER
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{
+        EmulatedRecursion, GenericConfig, NativeRecursion, PoseidonGoldilocksConfig,
+        RecursiveConfigPair,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_emulated_arithmetic() -> Result<()> {
+        let p = BabyBear::ORDER;
+        let x = OsRng.gen_range(0..p);
+        let y = OsRng.gen_range(0..p);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xt = builder.add_virtual_emulated_target::<BabyBear>();
+        let yt = builder.add_virtual_emulated_target::<BabyBear>();
+        let sum = builder.add_emulated(xt, yt);
+        let difference = builder.sub_emulated(xt, yt);
+        let product = builder.mul_emulated(xt, yt);
+        for (result, expected) in [
+            (sum, (x + y) % p),
+            (difference, (x + p - y) % p),
+            (product, x * y % p),
+        ] {
+            let expected = builder.constant_emulated(expected);
+            builder.connect_emulated(result, expected);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(xt.target(), F::from_canonical_u64(x));
+        pw.set_target(yt.target(), F::from_canonical_u64(y));
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_emulated_non_canonical() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xt = builder.add_virtual_emulated_target::<BabyBear>();
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(xt.target(), F::from_canonical_u64(BabyBear::ORDER));
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    fn test_recursive_config_pairs() {
+        type Native = NativeRecursion<C>;
+        type Emulated = EmulatedRecursion<BabyBear, C>;
+        assert!(<Native as RecursiveConfigPair<D>>::is_native());
+        assert!(!<Emulated as RecursiveConfigPair<D>>::is_native());
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+
+use crate::curve::ecgfp5::GFp5;
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// An element of `GF(p^5) = GF(p)[z] / (z^5 - W)`, represented by its coefficients in base field
+/// targets. Unlike `ExtensionTarget`, this doesn't depend on the extension degree `D` of the
+/// circuit, so circuits over the usual quadratic extension can do arithmetic in `GF(p^5)`, e.g.
+/// on ecGFp5 points.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GFp5Target(pub [Target; 5]);
+
+impl GFp5Target {
+    pub fn to_target_array(&self) -> [Target; 5] {
+        self.0
+    }
+}
+
+impl<F: RichField + Extendable<D> + Extendable<5>, const D: usize> CircuitBuilder<F, D> {
+    pub fn add_virtual_gfp5_target(&mut self) -> GFp5Target {
+        GFp5Target(self.add_virtual_target_arr())
+    }
+
+    pub fn constant_gfp5(&mut self, c: GFp5<F>) -> GFp5Target {
+        GFp5Target(c.to_basefield_array().map(|x| self.constant(x)))
+    }
+
+    pub fn zero_gfp5(&mut self) -> GFp5Target {
+        self.constant_gfp5(GFp5::<F>::ZERO)
+    }
+
+    pub fn connect_gfp5(&mut self, x: GFp5Target, y: GFp5Target) {
+        for i in 0..5 {
+            self.connect(x.0[i], y.0[i]);
+        }
+    }
+
+    pub fn add_gfp5(&mut self, x: GFp5Target, y: GFp5Target) -> GFp5Target {
+        GFp5Target(core::array::from_fn(|i| self.add(x.0[i], y.0[i])))
+    }
+
+    pub fn sub_gfp5(&mut self, x: GFp5Target, y: GFp5Target) -> GFp5Target {
+        GFp5Target(core::array::from_fn(|i| self.sub(x.0[i], y.0[i])))
+    }
+
+    pub fn neg_gfp5(&mut self, x: GFp5Target) -> GFp5Target {
+        GFp5Target(x.0.map(|t| self.neg(t)))
+    }
+
+    /// Computes `c * x` for a base field constant `c`.
+    pub fn scalar_mul_gfp5(&mut self, c: F, x: GFp5Target) -> GFp5Target {
+        GFp5Target(x.0.map(|t| self.mul_const(c, t)))
+    }
+
+    /// Computes `x * y`, as the schoolbook product of the coefficients reduced with
+    /// `z^5 = W`, in 25 arithmetic operations.
+    pub fn mul_gfp5(&mut self, x: GFp5Target, y: GFp5Target) -> GFp5Target {
+        let w = <F as Extendable<5>>::W;
+        let zero = self.zero();
+        let mut res = [zero; 5];
+        for i in 0..5 {
+            for j in 0..5 {
+                let (k, c) = if i + j < 5 {
+                    (i + j, F::ONE)
+                } else {
+                    (i + j - 5, w)
+                };
+                res[k] = self.arithmetic(c, F::ONE, x.0[i], y.0[j], res[k]);
+            }
+        }
+        GFp5Target(res)
+    }
+
+    pub fn square_gfp5(&mut self, x: GFp5Target) -> GFp5Target {
+        self.mul_gfp5(x, x)
+    }
+
+    /// Computes `1 / x`. Results in an unsatisfiable instance if `x = 0`.
+    pub fn inverse_gfp5(&mut self, x: GFp5Target) -> GFp5Target {
+        let one = self.constant_gfp5(GFp5::<F>::ONE);
+        self.div_gfp5(one, x)
+    }
+
+    /// Computes `x / y`. Results in an unsatisfiable instance if `y = 0`, even if `x = 0`.
+    pub fn div_gfp5(&mut self, x: GFp5Target, y: GFp5Target) -> GFp5Target {
+        let inv = self.add_virtual_gfp5_target();
+        self.add_simple_generator(GFp5InverseGenerator {
+            denominator: y,
+            inverse: inv,
+        });
+        // Constraining `y * inv = 1` rather than `y * q = x` ensures `y != 0`.
+        let y_inv = self.mul_gfp5(y, inv);
+        let one = self.constant_gfp5(GFp5::<F>::ONE);
+        self.connect_gfp5(y_inv, one);
+        self.mul_gfp5(x, inv)
+    }
+
+    /// Selects `x` or `y` based on `b`, i.e., this returns `if b { x } else { y }`.
+    pub fn select_gfp5(&mut self, b: BoolTarget, x: GFp5Target, y: GFp5Target) -> GFp5Target {
+        GFp5Target(core::array::from_fn(|i| self.select(b, x.0[i], y.0[i])))
+    }
+}
+
+#[derive(Debug)]
+struct GFp5InverseGenerator {
+    denominator: GFp5Target,
+    inverse: GFp5Target,
+}
+
+impl<F: RichField + Extendable<5>> SimpleGenerator<F> for GFp5InverseGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        self.denominator.0.to_vec()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let denominator = witness.get_gfp5_target(self.denominator);
+        // A zero denominator makes the circuit unsatisfiable anyway; any value will do.
+        let inverse = denominator.try_inverse().unwrap_or(GFp5::<F>::ZERO);
+        out_buffer.set_gfp5_target(self.inverse, inverse);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::curve::ecgfp5::GFp5;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_gfp5_arithmetic() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_gfp5_target();
+        let y = builder.add_virtual_gfp5_target();
+        let product = builder.mul_gfp5(x, y);
+        let quotient = builder.div_gfp5(x, y);
+        let difference = builder.sub_gfp5(x, y);
+
+        let (xv, yv) = (GFp5::<F>::rand(), GFp5::<F>::rand());
+        let expected_product = builder.constant_gfp5(xv * yv);
+        let expected_quotient = builder.constant_gfp5(xv / yv);
+        let expected_difference = builder.constant_gfp5(xv - yv);
+        builder.connect_gfp5(product, expected_product);
+        builder.connect_gfp5(quotient, expected_quotient);
+        builder.connect_gfp5(difference, expected_difference);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_gfp5_target(x, xv);
+        pw.set_gfp5_target(y, yv);
+        data.verify(data.prove(pw)?)
+    }
+}
@@ -1,9 +1,10 @@
 use crate::field::extension::Extendable;
-use crate::hash::hash_types::{HashOutTarget, RichField};
+use crate::hash::hash_types::{HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::SPONGE_WIDTH;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::AlgebraicHasher;
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::public_inputs_layout::PublicInputSlot;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn permute<H: AlgebraicHasher<F>>(
@@ -25,10 +26,182 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         H::permute_swapped(inputs, swap, self)
     }
 
+    /// Compresses two digests into one, as `H::two_to_one` does natively, e.g. to hash the children
+    /// of a Merkle tree node.
+    pub fn two_to_one<H: AlgebraicHasher<F>>(
+        &mut self,
+        left: HashOutTarget,
+        right: HashOutTarget,
+    ) -> HashOutTarget {
+        let zero = self.zero();
+        let mut inputs = [zero; SPONGE_WIDTH];
+        inputs[..NUM_HASH_OUT_ELTS].copy_from_slice(&left.elements);
+        inputs[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS].copy_from_slice(&right.elements);
+        let outputs = self.permute::<H>(inputs);
+        HashOutTarget::from_vec(outputs[..NUM_HASH_OUT_ELTS].to_vec())
+    }
+
     pub fn public_inputs_hash<H: AlgebraicHasher<F>>(
         &mut self,
         inputs: Vec<Target>,
     ) -> HashOutTarget {
         H::public_inputs_hash(inputs, self)
     }
+
+    /// Hashes the public inputs registered so far, in the order they were registered. This is the
+    /// digest `build` exposes through the `PublicInputGate` when `H` is the config's `InnerHasher`,
+    /// and it matches `H::hash_public_inputs` applied natively to the public input values, in the
+    /// same order, as returned by `ProofWithPublicInputs::get_public_inputs_hash`.
+    ///
+    /// Public inputs registered after this call are not part of the returned digest.
+    pub fn hash_public_inputs<H: AlgebraicHasher<F>>(&mut self) -> HashOutTarget {
+        let inputs = self.public_inputs().to_vec();
+        self.public_inputs_hash::<H>(inputs)
+    }
+
+    /// Registers the digest of `values` as public inputs, in place of the values themselves, and
+    /// returns the slot of the digest. A proof of this circuit then has 4 public inputs for
+    /// `values` however many there are, so the cost of verifying it recursively doesn't grow with
+    /// them: the public inputs are hashed by the recursive verifier a sponge rate at a time, while
+    /// the values are hashed here once.
+    ///
+    /// The values must be passed to verifiers alongside the proof, to be checked against the digest
+    /// with `ProofWithPublicInputs::check_packed_public_inputs`, or in a circuit with
+    /// `connect_packed_public_inputs`. The digest is computed with `C::InnerHasher`, as in those
+    /// checks.
+    pub fn register_packed_public_inputs<C: GenericConfig<D, F = F>>(
+        &mut self,
+        values: &[Target],
+    ) -> PublicInputSlot {
+        let slot = PublicInputSlot {
+            offset: self.num_public_inputs(),
+            len: 4,
+        };
+        let digest = self.public_inputs_hash::<C::InnerHasher>(values.to_vec());
+        self.register_public_inputs_at(slot, &digest.elements);
+        slot
+    }
+
+    /// Checks that `values` are those packed at `slot` of `public_inputs` by
+    /// `register_packed_public_inputs`, e.g. where `public_inputs` are those of a proof verified
+    /// recursively.
+    pub fn connect_packed_public_inputs<C: GenericConfig<D, F = F>>(
+        &mut self,
+        public_inputs: &[Target],
+        slot: PublicInputSlot,
+        values: &[Target],
+    ) {
+        let digest = self.public_inputs_hash::<C::InnerHasher>(values.to_vec());
+        let packed = HashOutTarget::from_vec(public_inputs[slot.range()].to_vec());
+        self.connect_hashes(digest, packed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::HashOut;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_hash_public_inputs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        // More than one sponge rate's worth of inputs, so that absorption order matters.
+        let inputs = builder.add_virtual_targets(11);
+        builder.register_public_inputs(&inputs);
+        let hash = builder.hash_public_inputs::<H>();
+        let expected_hash = builder.add_virtual_hash();
+        builder.connect_hashes(hash, expected_hash);
+        let data = builder.build::<C>();
+
+        let values = F::rand_vec(inputs.len());
+        let mut pw = PartialWitness::new();
+        for (&t, &v) in inputs.iter().zip(&values) {
+            pw.set_target(t, v);
+        }
+        pw.set_hash_target(expected_hash, H::hash_public_inputs(&values));
+        let proof = data.prove(pw)?;
+
+        assert_eq!(proof.public_inputs, values);
+        assert_eq!(
+            proof.get_public_inputs_hash(),
+            H::hash_public_inputs(&values)
+        );
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_two_to_one() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let (left, right) = (HashOut::rand(), HashOut::rand());
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let left_t = builder.constant_hash(left);
+        let right_t = builder.constant_hash(right);
+        let hash = builder.two_to_one::<H>(left_t, right_t);
+        let expected = builder.constant_hash(H::two_to_one(left, right));
+        builder.connect_hashes(hash, expected);
+        let data = builder.build::<C>();
+        data.verify(data.prove(PartialWitness::new())?)
+    }
+
+    #[test]
+    fn test_packed_public_inputs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let first = builder.add_virtual_public_input();
+        let values = builder.add_virtual_targets(100);
+        let slot = builder.register_packed_public_inputs::<C>(&values);
+        let inner = builder.build::<C>();
+        assert_eq!(slot.range(), 1..5);
+        assert_eq!(inner.common.num_public_inputs, 5);
+
+        let value_values = F::rand_vec(values.len());
+        let mut pw = PartialWitness::new();
+        pw.set_target(first, F::rand());
+        for (&t, &v) in values.iter().zip(&value_values) {
+            pw.set_target(t, v);
+        }
+        let inner_proof = inner.prove(pw)?;
+        inner_proof.check_packed_public_inputs(slot, &value_values)?;
+        let mut wrong_values = value_values.clone();
+        wrong_values[99] += F::ONE;
+        assert!(inner_proof
+            .check_packed_public_inputs(slot, &wrong_values)
+            .is_err());
+
+        // A circuit verifying the proof recursively can unpack the values.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis::<C>(&inner.common);
+        let verifier_t = builder.constant_verifier_data(&inner.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_t, &inner.common);
+        let values_t = builder.add_virtual_targets(values.len());
+        builder.connect_packed_public_inputs::<C>(&proof_t.public_inputs, slot, &values_t);
+        let outer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &inner_proof);
+        for (&t, &v) in values_t.iter().zip(&value_values) {
+            pw.set_target(t, v);
+        }
+        outer.verify(outer.prove(pw)?)
+    }
 }
@@ -0,0 +1,378 @@
+//! Lookup tables given by their rows, e.g. the XOR of bytes, for gadgets which would otherwise
+//! compute such functions bit by bit.
+//!
+//! A table's rows are constants of the circuit, and are thus committed to with its other
+//! constants. Lookups are checked together when the circuit is built, with a log-derivative
+//! argument: each table gets a multiplicity per row, the number of times it is looked up, and the
+//! circuit checks that `sum_l 1 / (alpha - f_l) = sum_r m_r / (alpha - t_r)`, where `f_l` is the
+//! `l`th looked up row and `t_r` the `r`th row of the table, each compressed to a single element
+//! as `sum_i row[i] * beta^i`. The challenges `alpha` and `beta` are a hash of the looked up rows
+//! and the multiplicities. This costs about `(w + 2) / 10` gates per row and per lookup, for rows
+//! of `w` elements, with the standard recursion config, plus the gates hashing the lookups and the
+//! multiplicities, so a byte XOR table of 65536 rows suits circuits making many byte operations.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::gates::multiplication_extension::MulExtensionGate;
+use crate::hash::hash_types::RichField;
+use crate::hash::hashing::SPONGE_RATE;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+use crate::util::ceil_div_usize;
+
+/// A table added with `CircuitBuilder::add_lookup_table_from_slice`.
+#[derive(Clone, Debug)]
+pub(crate) struct LookupTable<F: RichField> {
+    pub(crate) name: String,
+    num_inputs: usize,
+    /// The rows of the table, each its inputs followed by its output.
+    rows: Arc<Vec<Vec<F>>>,
+    /// The index of the row of each tuple of inputs.
+    row_indices: Arc<HashMap<Vec<F>, usize>>,
+    /// The looked up rows, each the inputs of a lookup followed by its output.
+    lookups: Vec<Vec<Target>>,
+}
+
+impl<F: RichField> LookupTable<F> {
+    fn row_index(&self, inputs: &[F]) -> usize {
+        row_index(&self.name, &self.row_indices, inputs)
+    }
+}
+
+fn row_index<F: RichField>(
+    table_name: &str,
+    row_indices: &HashMap<Vec<F>, usize>,
+    inputs: &[F],
+) -> usize {
+    *row_indices.get(inputs).unwrap_or_else(|| {
+        panic!(
+            "{:?} isn't an input of the lookup table {}",
+            inputs, table_name
+        )
+    })
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a table mapping the inputs of each pair to its output, e.g. `[a, b]` to `a ^ b` for a
+    /// `u8_xor` table, and returns its ID for `lookup`. Inputs must be distinct.
+    pub fn add_lookup_table_from_slice<const N: usize>(
+        &mut self,
+        name: &str,
+        pairs: &[([u64; N], u64)],
+    ) -> usize {
+        assert!(!pairs.is_empty(), "The lookup table {} is empty", name);
+        assert!(
+            self.lookup_tables.iter().all(|table| table.name != name),
+            "There already is a lookup table {}",
+            name
+        );
+        let to_field = |x: u64| {
+            assert!(x < F::ORDER, "{} isn't a field element", x);
+            F::from_canonical_u64(x)
+        };
+
+        let rows = pairs
+            .iter()
+            .map(|(inputs, output)| {
+                inputs
+                    .iter()
+                    .chain([output])
+                    .map(|&x| to_field(x))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut row_indices = HashMap::with_capacity(rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            assert!(
+                row_indices.insert(row[..N].to_vec(), i).is_none(),
+                "The lookup table {} has repeated inputs",
+                name
+            );
+        }
+        self.lookup_tables.push(LookupTable {
+            name: name.to_string(),
+            num_inputs: N,
+            rows: Arc::new(rows),
+            row_indices: Arc::new(row_indices),
+            lookups: Vec::new(),
+        });
+        self.lookup_tables.len() - 1
+    }
+
+    /// The output of the row of the table `table_id` whose inputs are `inputs`. Proving fails if
+    /// there is no such row.
+    pub fn lookup(&mut self, table_id: usize, inputs: &[Target]) -> Target {
+        let output = self.add_virtual_target();
+        let table = &mut self.lookup_tables[table_id];
+        assert_eq!(
+            inputs.len(),
+            table.num_inputs,
+            "The lookup table {} has {} inputs",
+            table.name,
+            table.num_inputs
+        );
+        table
+            .lookups
+            .push(inputs.iter().copied().chain([output]).collect());
+        let generator = LookupOutputGenerator {
+            inputs: inputs.to_vec(),
+            output,
+            table_name: table.name.clone(),
+            rows: table.rows.clone(),
+            row_indices: table.row_indices.clone(),
+        };
+        self.add_simple_generator(generator);
+        output
+    }
+
+    /// Adds the log-derivative argument of each table which has been looked up, with challenges
+    /// drawn with the hasher `H`.
+    pub(crate) fn add_lookup_arguments<H: AlgebraicHasher<F>>(&mut self) {
+        for table in mem::take(&mut self.lookup_tables) {
+            if !table.lookups.is_empty() {
+                self.add_lookup_argument::<H>(table);
+            }
+        }
+    }
+
+    fn add_lookup_argument<H: AlgebraicHasher<F>>(&mut self, table: LookupTable<F>) {
+        let multiplicities = self.add_virtual_targets(table.rows.len());
+        self.add_simple_generator(LookupMultiplicitiesGenerator {
+            table: table.clone(),
+            multiplicities: multiplicities.clone(),
+        });
+
+        let hash_inputs = table
+            .lookups
+            .iter()
+            .flatten()
+            .chain(&multiplicities)
+            .copied()
+            .collect();
+        let challenges = self.hash_n_to_m_no_pad::<H>(hash_inputs, 2 * D);
+        let alpha = ExtensionTarget(challenges[..D].try_into().unwrap());
+        let beta = ExtensionTarget(challenges[D..].try_into().unwrap());
+        let mut beta_powers = self.powers(beta);
+        let beta_powers = (0..=table.num_inputs)
+            .map(|_| beta_powers.next(self))
+            .collect::<Vec<_>>();
+
+        let one = self.one_extension();
+        let mut lookups_sum = self.zero_extension();
+        for lookup in &table.lookups {
+            let denominator = self.lookup_denominator(alpha, &beta_powers, lookup);
+            lookups_sum = self.div_add_extension(one, denominator, lookups_sum);
+        }
+        let mut table_sum = self.zero_extension();
+        for (row, &multiplicity) in table.rows.iter().zip(&multiplicities) {
+            let row = row.iter().map(|&x| self.constant(x)).collect::<Vec<_>>();
+            let denominator = self.lookup_denominator(alpha, &beta_powers, &row);
+            let multiplicity = self.convert_to_ext(multiplicity);
+            table_sum = self.div_add_extension(multiplicity, denominator, table_sum);
+        }
+        self.connect_extension(lookups_sum, table_sum);
+    }
+
+    /// Returns `alpha - sum_i row[i] * beta_powers[i]`.
+    fn lookup_denominator(
+        &mut self,
+        alpha: ExtensionTarget<D>,
+        beta_powers: &[ExtensionTarget<D>],
+        row: &[Target],
+    ) -> ExtensionTarget<D> {
+        row.iter()
+            .zip(beta_powers)
+            .fold(alpha, |acc, (&x, &beta_power)| {
+                let x = self.convert_to_ext(x);
+                self.arithmetic_extension(F::NEG_ONE, F::ONE, x, beta_power, acc)
+            })
+    }
+
+    /// Roughly the number of gates `add_lookup_arguments` adds, assuming the hasher uses one gate
+    /// per permutation.
+    pub(crate) fn num_lookup_argument_gates(&self) -> usize {
+        let mut num_arithmetic_ops = 0;
+        let mut num_mul_ops = 0;
+        let mut num_permutations = 0;
+        for table in self.lookup_tables.iter().filter(|t| !t.lookups.is_empty()) {
+            // Each fraction takes an operation per element of its row to compress it, one to add
+            // it to the sum, and a multiplication to check the inverse of its denominator.
+            let num_fractions = table.rows.len() + table.lookups.len();
+            num_arithmetic_ops += num_fractions * (table.num_inputs + 2);
+            num_mul_ops += num_fractions;
+            let num_hash_inputs = table.lookups.len() * (table.num_inputs + 1) + table.rows.len();
+            num_permutations += ceil_div_usize(num_hash_inputs, SPONGE_RATE);
+        }
+        let num_mul_ops_per_gate = MulExtensionGate::<D>::new_from_config(&self.config).num_ops;
+        ceil_div_usize(num_arithmetic_ops, self.num_ext_arithmetic_ops_per_gate())
+            + ceil_div_usize(num_mul_ops, num_mul_ops_per_gate)
+            + num_permutations
+    }
+}
+
+#[derive(Debug)]
+struct LookupOutputGenerator<F: RichField> {
+    inputs: Vec<Target>,
+    output: Target,
+    table_name: String,
+    rows: Arc<Vec<Vec<F>>>,
+    row_indices: Arc<HashMap<Vec<F>, usize>>,
+}
+
+impl<F: RichField> SimpleGenerator<F> for LookupOutputGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.inputs.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let inputs = witness.get_targets(&self.inputs);
+        let row = &self.rows[row_index(&self.table_name, &self.row_indices, &inputs)];
+        out_buffer.set_target(self.output, *row.last().unwrap());
+    }
+}
+
+#[derive(Debug)]
+struct LookupMultiplicitiesGenerator<F: RichField> {
+    table: LookupTable<F>,
+    multiplicities: Vec<Target>,
+}
+
+impl<F: RichField> SimpleGenerator<F> for LookupMultiplicitiesGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.table
+            .lookups
+            .iter()
+            .flat_map(|lookup| &lookup[..self.table.num_inputs])
+            .copied()
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let mut multiplicities = vec![0; self.multiplicities.len()];
+        for lookup in &self.table.lookups {
+            let inputs = witness.get_targets(&lookup[..self.table.num_inputs]);
+            multiplicities[self.table.row_index(&inputs)] += 1;
+        }
+        for (&target, multiplicity) in self.multiplicities.iter().zip(multiplicities) {
+            out_buffer.set_target(target, F::from_canonical_usize(multiplicity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    fn xor_pairs(bits: usize) -> Vec<([u64; 2], u64)> {
+        let n = 1u64 << bits;
+        (0..n)
+            .flat_map(|a| (0..n).map(move |b| ([a, b], a ^ b)))
+            .collect()
+    }
+
+    #[test]
+    fn test_lookup() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let u4_xor = builder.add_lookup_table_from_slice("u4_xor", &xor_pairs(4));
+        let squares =
+            builder.add_lookup_table_from_slice("squares", &[([3], 9), ([5], 25), ([7], 49)]);
+        // Never looked up, so it adds nothing to the circuit.
+        builder.add_lookup_table_from_slice("cubes", &[([2], 8)]);
+
+        let mut pw = PartialWitness::new();
+        // The same rows may be looked up several times.
+        for (table, inputs, output) in [
+            (u4_xor, vec![0xa, 0x6], 0xc),
+            (u4_xor, vec![0x3, 0x3], 0x0),
+            (u4_xor, vec![0xa, 0x6], 0xc),
+            (squares, vec![7], 49),
+            (squares, vec![3], 9),
+        ] {
+            let xs = builder.add_virtual_targets(inputs.len());
+            let y = builder.lookup(table, &xs);
+            let expected = builder.constant(F::from_canonical_u64(output));
+            builder.connect(y, expected);
+            for (x, input) in xs.into_iter().zip(inputs) {
+                pw.set_target(x, F::from_canonical_u64(input));
+            }
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_u8_xor_lookup() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let u8_xor = builder.add_lookup_table_from_slice("u8_xor", &xor_pairs(8));
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let c = builder.lookup(u8_xor, &[a, b]);
+        builder.register_public_input(c);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(a, F::from_canonical_u64(0xa5));
+        pw.set_target(b, F::from_canonical_u64(0x0f));
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(0xaa)]);
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "The lookup table u4_xor has 2 inputs")]
+    fn test_lookup_wrong_number_of_inputs() {
+        const D: usize = 2;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let u4_xor = builder.add_lookup_table_from_slice("u4_xor", &xor_pairs(4));
+        let x = builder.add_virtual_target();
+        builder.lookup(u4_xor, &[x]);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't an input of the lookup table squares")]
+    fn test_lookup_missing_input() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let squares = builder.add_lookup_table_from_slice("squares", &[([3], 9), ([5], 25)]);
+        let x = builder.add_virtual_target();
+        builder.lookup(squares, &[x]);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(4));
+        let _ = data.prove(pw);
+    }
+}
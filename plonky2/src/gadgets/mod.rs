@@ -1,6 +1,10 @@
 pub mod arithmetic;
 pub mod arithmetic_extension;
+pub mod ecgfp5;
+pub mod emulated;
+pub mod gfp5;
 pub mod hash;
+pub mod lookup;
 pub mod polynomial;
 pub mod random_access;
 pub mod range_check;
@@ -4,7 +4,7 @@ use crate::field::extension::Extendable;
 use crate::gates::random_access::RandomAccessGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::target::Target;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::util::log2_strict;
 
@@ -51,6 +51,37 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         ExtensionTarget(v.try_into().unwrap())
     }
+
+    /// The largest number of index bits of a random access gate with this config.
+    pub(crate) fn max_random_access_bits(&self) -> usize {
+        (1..)
+            .take_while(|&bits| {
+                RandomAccessGate::<F, D>::new_from_config(&self.config, bits).num_copies > 0
+            })
+            .last()
+            .expect("The config can't fit a random access gate")
+    }
+
+    /// Like `random_access`, for lists too long for a single gate: the element at the index with
+    /// the little-endian bits `index_bits`, accessed `max_bits` bits at a time.
+    pub(crate) fn random_access_chunked(
+        &mut self,
+        index_bits: &[BoolTarget],
+        max_bits: usize,
+        v: Vec<Target>,
+    ) -> Target {
+        if index_bits.len() <= max_bits {
+            let index = self.le_sum(index_bits.iter());
+            return self.random_access(index, v);
+        }
+        let (low_bits, high_bits) = index_bits.split_at(max_bits);
+        let low_index = self.le_sum(low_bits.iter());
+        let chunks = v
+            .chunks(1 << max_bits)
+            .map(|chunk| self.random_access(low_index, chunk.to_vec()))
+            .collect();
+        self.random_access_chunked(high_bits, max_bits, chunks)
+    }
 }
 
 #[cfg(test)]
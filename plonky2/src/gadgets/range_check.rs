@@ -2,6 +2,7 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use crate::field::extension::Extendable;
+use crate::gates::random_access::RandomAccessGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
 use crate::iop::target::{BoolTarget, Target};
@@ -14,6 +15,40 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.split_le(x, n_log);
     }
 
+    /// Checks that `x < 2^n_log`, like `range_check`, but looks narrow values up in the table of
+    /// all `n_log`-bit values with random access gates, which fit several such checks in a row.
+    /// Wider values are decomposed into bits as `range_check` does. The resulting circuit differs
+    /// from the one `range_check` builds, so switching a circuit to this changes its digest.
+    pub fn range_check_packed(&mut self, x: Target, n_log: usize) {
+        if n_log == 0 {
+            let zero = self.zero();
+            self.connect(x, zero);
+        } else if self.range_checks_per_random_access_row(n_log) > 1 {
+            // The table is the identity, so `x` is its own index, and the random access gate
+            // checks that it has `n_log` bits.
+            let table = (0..1 << n_log)
+                .map(|i| self.constant(F::from_canonical_usize(i)))
+                .collect();
+            let row = self.random_access(x, table);
+            self.connect(row, x);
+        } else {
+            self.range_check(x, n_log);
+        }
+    }
+
+    /// How many lookups into a table of `n_log`-bit values fit in a row of random access gates, or
+    /// 0 if such a table doesn't fit in one.
+    fn range_checks_per_random_access_row(&self, n_log: usize) -> usize {
+        let fits = 1usize.checked_shl(n_log as u32).map_or(false, |table_len| {
+            table_len + 2 <= self.config.num_routed_wires
+        });
+        if fits {
+            RandomAccessGate::<F, D>::new_from_config(&self.config, n_log).num_copies
+        } else {
+            0
+        }
+    }
+
     /// Returns the first `num_low_bits` little-endian bits of `x`.
     pub fn low_bits(&mut self, x: Target, num_low_bits: usize, num_bits: usize) -> Vec<BoolTarget> {
         let mut res = self.split_le(x, num_bits);
@@ -73,3 +108,90 @@ impl<F: RichField> SimpleGenerator<F> for LowHighGenerator {
         out_buffer.set_target(self.high, F::from_canonical_u64(high));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_range_check() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        for n_log in [1, 4, 32, 63] {
+            for value in [0, (1u64 << n_log) - 1] {
+                let x = builder.add_virtual_target();
+                builder.range_check(x, n_log);
+                pw.set_target(x, F::from_canonical_u64(value));
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_range_check_packed() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        for n_log in [0, 1, 2, 4, 5, 6, 8, 32, 63] {
+            for value in [0, (1u64 << n_log) - 1] {
+                let x = builder.add_virtual_target();
+                builder.range_check_packed(x, n_log);
+                pw.set_target(x, F::from_canonical_u64(value));
+            }
+        }
+
+        // Four-bit checks are lookups, several to a row, rather than a row each.
+        let num_gates = builder.num_gates();
+        for value in 0..8 {
+            let x = builder.constant(F::from_canonical_u64(value));
+            builder.range_check_packed(x, 4);
+        }
+        assert!(builder.num_gates() - num_gates < 8);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    fn prove_range_check_of_2_pow_n_log(n_log: usize, packed: bool) {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        if packed {
+            builder.range_check_packed(x, n_log);
+        } else {
+            builder.range_check(x, n_log);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(1 << n_log));
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_check_out_of_range() {
+        prove_range_check_of_2_pow_n_log(8, false);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_check_packed_out_of_range() {
+        prove_range_check_of_2_pow_n_log(4, true);
+    }
+}
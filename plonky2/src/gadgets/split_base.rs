@@ -77,6 +77,31 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         Target::wire(row, BaseSumGate::<2>::WIRE_SUM)
     }
+
+    /// Like `le_sum`, but accepts up to `F::BITS` bits. With `F::BITS` bits, it checks that they
+    /// encode a value less than the field order, so that each element has a single
+    /// representation.
+    pub fn le_sum_canonical(
+        &mut self,
+        bits: impl Iterator<Item = impl Borrow<BoolTarget>>,
+    ) -> Target {
+        let bits = bits.map(|b| *b.borrow()).collect_vec();
+        if bits.len() < F::BITS {
+            return self.le_sum(bits.iter());
+        }
+        assert_eq!(
+            bits.len(),
+            F::BITS,
+            "{} bits may overflow the field",
+            bits.len()
+        );
+        self.assert_bits_le_constant(&bits, F::ORDER - 1);
+
+        let (low_bits, high_bits) = bits.split_at(F::BITS / 2);
+        let low = self.le_sum(low_bits.iter());
+        let high = self.le_sum(high_bits.iter());
+        self.mul_const_add(F::TWO.exp_u64(low_bits.len() as u64), high, low)
+    }
 }
 
 #[derive(Debug)]
@@ -111,6 +136,7 @@ mod tests {
     use rand::Rng;
 
     use super::*;
+    use crate::field::types::{Field64, Sample};
     use crate::iop::witness::PartialWitness;
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
@@ -178,4 +204,51 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_canonical_bits() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // `ORDER - 1` has the most bits in common with the non-canonical values.
+        for x in [F::rand(), F::NEG_ONE, F::ZERO] {
+            let xt = builder.add_virtual_target();
+            pw.set_target(xt, x);
+            let bits = builder.split_le_canonical(xt);
+            let y = builder.le_sum_canonical(bits.iter());
+            builder.connect(xt, y);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_non_canonical_bits() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // The bits of `ORDER`, which `le_sum` would take to zero.
+        let bits = (0..F::BITS)
+            .map(|i| {
+                let bit = builder.add_virtual_bool_target_safe();
+                pw.set_bool_target(bit, (F::ORDER >> i) & 1 == 1);
+                bit
+            })
+            .collect::<Vec<_>>();
+        builder.le_sum_canonical(bits.iter());
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
 }
@@ -53,6 +53,50 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         bits
     }
+
+    /// Like `split_le` with `F::BITS` bits, but also checks that the bits are the canonical
+    /// representation of `integer`, i.e. encode a value less than the field order. `split_le`
+    /// alone accepts the bits of `integer + F::ORDER` whenever they fit.
+    pub fn split_le_canonical(&mut self, integer: Target) -> Vec<BoolTarget> {
+        let bits = self.split_le(integer, F::BITS);
+        self.assert_bits_le_constant(&bits, F::ORDER - 1);
+        bits
+    }
+
+    /// Asserts that the little-endian `bits` encode a value of at most `c`.
+    pub(crate) fn assert_bits_le_constant(&mut self, bits: &[BoolTarget], c: u64) {
+        assert!(bits.len() <= 64, "{} bits don't fit in a u64", bits.len());
+        if bits.len() < 64 && c >> bits.len() != 0 {
+            return;
+        }
+
+        // Going from the most significant bit, while the bits are those of `c`, each bit at a
+        // position where `c` has a zero must be zero. Such bits are checked a run at a time, as
+        // the prefix can't change within a run.
+        let mut prefix_equal = self._true();
+        let mut zero_run = Vec::new();
+        for i in (0..bits.len()).rev() {
+            if (c >> i) & 1 == 1 {
+                self.assert_zero_run(prefix_equal, &zero_run);
+                zero_run.clear();
+                prefix_equal = self.and(prefix_equal, bits[i]);
+            } else {
+                zero_run.push(bits[i].target);
+            }
+        }
+        self.assert_zero_run(prefix_equal, &zero_run);
+    }
+
+    /// Asserts that the bits `run` are zero if `condition` holds.
+    fn assert_zero_run(&mut self, condition: BoolTarget, run: &[Target]) {
+        if run.is_empty() {
+            return;
+        }
+        // The sum of at most 64 bits is zero iff each of them is.
+        let sum = self.add_many(run);
+        let product = self.mul(condition.target, sum);
+        self.assert_zero(product);
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,141 @@
+//! Rebuilds gates from their IDs, which is how gates are serialized in `CommonCircuitData`.
+
+use alloc::format;
+
+use crate::field::extension::Extendable;
+use crate::gates::arithmetic_base::ArithmeticGate;
+use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+use crate::gates::base_sum::BaseSumGate;
+use crate::gates::constant::ConstantGate;
+use crate::gates::exponentiation::ExponentiationGate;
+use crate::gates::gate::GateRef;
+use crate::gates::high_degree_interpolation::HighDegreeInterpolationGate;
+use crate::gates::interpolation::InterpolationGate;
+use crate::gates::low_degree_interpolation::LowDegreeInterpolationGate;
+use crate::gates::multiplication_extension::MulExtensionGate;
+use crate::gates::noop::NoopGate;
+use crate::gates::poseidon::PoseidonGate;
+use crate::gates::poseidon_mds::PoseidonMdsGate;
+use crate::gates::public_input::PublicInputGate;
+use crate::gates::random_access::RandomAccessGate;
+use crate::gates::reducing::ReducingGate;
+use crate::gates::reducing_extension::ReducingExtensionGate;
+use crate::hash::hash_types::RichField;
+
+/// The value of the parameter `name` in a gate ID, e.g. `20` for `num_ops` in
+/// `ArithmeticGate { num_ops: 20 }`.
+fn param(id: &str, name: &str) -> Option<usize> {
+    let start = id.find(&format!(" {name}: "))? + name.len() + 3;
+    id[start..]
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Rebuilds the gate whose ID is `id`, if it is one of the gates defined in this crate, with a base
+/// of 2 or 4 for `BaseSumGate`. Gates defined elsewhere, e.g. in `plonky2_u32`, aren't known here.
+pub fn gate_from_id<F: RichField + Extendable<D>, const D: usize>(
+    id: &str,
+) -> Option<GateRef<F, D>> {
+    let name = id.split([' ', '(', '<']).next()?;
+    let gate = match name {
+        "ArithmeticGate" => GateRef::new(ArithmeticGate {
+            num_ops: param(id, "num_ops")?,
+        }),
+        "ArithmeticExtensionGate" => GateRef::new(ArithmeticExtensionGate::<D> {
+            num_ops: param(id, "num_ops")?,
+        }),
+        "BaseSumGate" => {
+            let num_limbs = param(id, "num_limbs")?;
+            match id.rsplit(" + Base: ").next()? {
+                "2" => GateRef::new(BaseSumGate::<2>::new(num_limbs)),
+                "4" => GateRef::new(BaseSumGate::<4>::new(num_limbs)),
+                _ => return None,
+            }
+        }
+        "ConstantGate" => GateRef::new(ConstantGate {
+            num_consts: param(id, "num_consts")?,
+        }),
+        "ExponentiationGate" => GateRef::new(ExponentiationGate::<F, D>::new(param(
+            id,
+            "num_power_bits",
+        )?)),
+        "HighDegreeInterpolationGate" => GateRef::new(HighDegreeInterpolationGate::<F, D>::new(
+            param(id, "subgroup_bits")?,
+        )),
+        "LowDegreeInterpolationGate" => GateRef::new(LowDegreeInterpolationGate::<F, D>::new(
+            param(id, "subgroup_bits")?,
+        )),
+        "MulExtensionGate" => GateRef::new(MulExtensionGate::<D> {
+            num_ops: param(id, "num_ops")?,
+        }),
+        "NoopGate" => GateRef::new(NoopGate),
+        "PoseidonGate" => GateRef::new(PoseidonGate::<F, D>::new()),
+        "PoseidonMdsGate" => GateRef::new(PoseidonMdsGate::<F, D>::new()),
+        "PublicInputGate" => GateRef::new(PublicInputGate),
+        "RandomAccessGate" => GateRef::new(RandomAccessGate::<F, D>::new(
+            param(id, "num_copies")?,
+            param(id, "bits")?,
+            param(id, "num_extra_constants")?,
+        )),
+        "ReducingGate" => GateRef::new(ReducingGate::<D>::new(param(id, "num_coeffs")?)),
+        "ReducingExtensionGate" => {
+            GateRef::new(ReducingExtensionGate::<D>::new(param(id, "num_coeffs")?))
+        }
+        _ => return None,
+    };
+    // The ID also encodes parameters we don't parse, e.g. `D` or the field of `PhantomData`s, so
+    // only accept gates which have exactly the ID we were given.
+    (gate.0.id() == id).then_some(gate)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gates::from_id::gate_from_id;
+    use crate::gates::noop::NoopGate;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitTarget};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_gate_from_id() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x]);
+        builder.split_le(x, 64);
+        builder.split_le_base::<4>(x, 16);
+        builder.exp(x, x, 20);
+        let list = builder.add_virtual_targets(16);
+        builder.random_access(x, list);
+        builder.add_gate(NoopGate, vec![]);
+        let inner = builder.build::<C>();
+
+        // Verifying a proof in a circuit uses most of the remaining gates.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof = builder.add_virtual_proof_with_pis::<C>(&inner.common);
+        let verifier_data = VerifierCircuitTarget {
+            constants_sigmas_cap: builder
+                .add_virtual_cap(inner.common.config.fri_config.cap_height),
+            circuit_digest: builder.add_virtual_hash(),
+        };
+        builder.verify_proof::<C>(&proof, &verifier_data, &inner.common);
+        let outer = builder.build::<C>();
+
+        for gate in inner.common.gates.iter().chain(&outer.common.gates) {
+            let id = gate.0.id();
+            assert_eq!(gate_from_id::<F, D>(&id).as_ref(), Some(gate), "{}", id);
+        }
+
+        assert!(gate_from_id::<F, D>("NoSuchGate").is_none());
+        assert!(gate_from_id::<F, D>("ArithmeticGate { num_ops: }").is_none());
+        assert!(gate_from_id::<F, D>("ArithmeticGate { num_ops: 20 } trailing").is_none());
+        assert!(gate_from_id::<F, D>("BaseSumGate { num_limbs: 63 } + Base: 3").is_none());
+    }
+}
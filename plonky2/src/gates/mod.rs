@@ -6,6 +6,7 @@ pub mod arithmetic_extension;
 pub mod base_sum;
 pub mod constant;
 pub mod exponentiation;
+pub mod from_id;
 pub mod gate;
 pub mod high_degree_interpolation;
 pub mod interpolation;
@@ -19,7 +20,7 @@ pub mod public_input;
 pub mod random_access;
 pub mod reducing;
 pub mod reducing_extension;
-pub(crate) mod selectors;
+pub mod selectors;
 pub mod util;
 
 // Can't use #[cfg(test)] here because it needs to be visible to other crates.
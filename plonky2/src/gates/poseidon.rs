@@ -90,7 +90,7 @@ impl<F: RichField + Extendable<D>, const D: usize> PoseidonGate<F, D> {
     }
 
     /// End of wire indices, exclusive.
-    fn end() -> usize {
+    pub(crate) fn end() -> usize {
         Self::START_FULL_1 + SPONGE_WIDTH * poseidon::HALF_N_FULL_ROUNDS
     }
 }
@@ -707,6 +707,26 @@ struct PoseidonGenerator<F: RichField + Extendable<D> + Poseidon, const D: usize
     _phantom: PhantomData<F>,
 }
 
+impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PoseidonGenerator<F, D> {
+    /// The wires set by this generator which depend only on the permutation's input state, i.e. all
+    /// but the swap deltas.
+    fn permutation_wires() -> impl Iterator<Item = usize> {
+        let full_sbox_0 = (1..poseidon::HALF_N_FULL_ROUNDS).flat_map(|r| {
+            (0..SPONGE_WIDTH).map(move |i| PoseidonGate::<F, D>::wire_full_sbox_0(r, i))
+        });
+        let partial_sbox =
+            (0..poseidon::N_PARTIAL_ROUNDS).map(|r| PoseidonGate::<F, D>::wire_partial_sbox(r));
+        let full_sbox_1 = (0..poseidon::HALF_N_FULL_ROUNDS).flat_map(|r| {
+            (0..SPONGE_WIDTH).map(move |i| PoseidonGate::<F, D>::wire_full_sbox_1(r, i))
+        });
+        let output = (0..SPONGE_WIDTH).map(|i| PoseidonGate::<F, D>::wire_output(i));
+        full_sbox_0
+            .chain(partial_sbox)
+            .chain(full_sbox_1)
+            .chain(output)
+    }
+}
+
 impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F>
     for PoseidonGenerator<F, D>
 {
@@ -743,6 +763,13 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F>
         }
 
         let mut state: [F; SPONGE_WIDTH] = state.try_into().unwrap();
+        if let Some(row) = out_buffer.poseidon_cache.get_or_insert(state, self.row) {
+            for column in Self::permutation_wires() {
+                let value = witness.get_wire(Wire { row, column });
+                out_buffer.set_wire(local_wire(column), value);
+            }
+            return;
+        }
         let mut round_ctr = 0;
 
         for r in 0..poseidon::HALF_N_FULL_ROUNDS {
@@ -806,12 +833,13 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::field::types::Field;
+    use crate::field::types::{Field, Sample};
     use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
     use crate::gates::poseidon::PoseidonGate;
     use crate::hash::hashing::SPONGE_WIDTH;
     use crate::hash::poseidon::Poseidon;
     use crate::iop::generator::generate_partial_witness;
+    use crate::iop::target::Target;
     use crate::iop::wire::Wire;
     use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -878,7 +906,8 @@ mod tests {
             );
         }
 
-        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+        let witness =
+            generate_partial_witness(inputs, &circuit.prover_only, &circuit.common).unwrap();
 
         let expected_outputs: [F; SPONGE_WIDTH] =
             F::poseidon(permutation_inputs.try_into().unwrap());
@@ -891,6 +920,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn repeated_permutations() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type Gate = PoseidonGate<F, D>;
+
+        // The same input state, unswapped, swapped, and permuted again, with a distinct one in between.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_targets(SPONGE_WIDTH);
+        let other = builder.add_virtual_targets(SPONGE_WIDTH);
+        let mut swapped = inputs.clone();
+        for i in 0..4 {
+            swapped.swap(i, i + 4);
+        }
+        let rows = [
+            (&inputs, false),
+            (&other, false),
+            (&swapped, true),
+            (&inputs, false),
+        ]
+        .map(|(inputs, swap)| {
+            let row = builder.add_gate(Gate::new(), vec![]);
+            for i in 0..SPONGE_WIDTH {
+                builder.connect(inputs[i], Target::wire(row, Gate::wire_input(i)));
+            }
+            let swap = builder.constant_bool(swap);
+            builder.connect(swap.target, Target::wire(row, Gate::WIRE_SWAP));
+            row
+        });
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        let values = F::rand_array::<SPONGE_WIDTH>();
+        for i in 0..SPONGE_WIDTH {
+            pw.set_target(inputs[i], values[i]);
+            pw.set_target(other[i], F::rand());
+        }
+        let witness = generate_partial_witness(pw.clone(), &data.prover_only, &data.common)?;
+
+        let expected = F::poseidon(values);
+        for row in [rows[0], rows[2], rows[3]] {
+            for i in 0..SPONGE_WIDTH {
+                let output = witness.get_wire(Wire {
+                    row,
+                    column: Gate::wire_output(i),
+                });
+                assert_eq!(output, expected[i]);
+            }
+        }
+        // The copied wires must satisfy the gate's constraints, as computed ones do.
+        data.verify(data.prove(pw)?)
+    }
+
     #[test]
     fn low_degree() {
         type F = GoldilocksField;
@@ -41,26 +41,6 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PoseidonMdsGate<F,
 
     // Following are methods analogous to ones in `Poseidon`, but for extension algebras.
 
-    /// Same as `mds_row_shf` for an extension algebra of `F`.
-    fn mds_row_shf_algebra(
-        r: usize,
-        v: &[ExtensionAlgebra<F::Extension, D>; SPONGE_WIDTH],
-    ) -> ExtensionAlgebra<F::Extension, D> {
-        debug_assert!(r < SPONGE_WIDTH);
-        let mut res = ExtensionAlgebra::ZERO;
-
-        for i in 0..SPONGE_WIDTH {
-            let coeff = F::Extension::from_canonical_u64(<F as Poseidon>::MDS_MATRIX_CIRC[i]);
-            res += v[(i + r) % SPONGE_WIDTH].scalar_mul(coeff);
-        }
-        {
-            let coeff = F::Extension::from_canonical_u64(<F as Poseidon>::MDS_MATRIX_DIAG[r]);
-            res += v[r].scalar_mul(coeff);
-        }
-
-        res
-    }
-
     /// Same as `mds_row_shf_recursive` for an extension algebra of `F`.
     fn mds_row_shf_algebra_circuit(
         builder: &mut CircuitBuilder<F, D>,
@@ -86,14 +66,23 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PoseidonMdsGate<F,
         res
     }
 
-    /// Same as `mds_layer` for an extension algebra of `F`.
+    /// Same as `mds_layer` for an extension algebra of `F`, applied to each coordinate.
     fn mds_layer_algebra(
         state: &[ExtensionAlgebra<F::Extension, D>; SPONGE_WIDTH],
     ) -> [ExtensionAlgebra<F::Extension, D>; SPONGE_WIDTH] {
-        let mut result = [ExtensionAlgebra::ZERO; SPONGE_WIDTH];
+        let mut coords = [[F::Extension::ZERO; SPONGE_WIDTH]; D];
+        for i in 0..SPONGE_WIDTH {
+            let x = state[i].to_basefield_array();
+            for j in 0..D {
+                coords[j][i] = x[j];
+            }
+        }
+        let coords = coords.map(|c| F::mds_layer_field(&c));
 
-        for r in 0..SPONGE_WIDTH {
-            result[r] = Self::mds_row_shf_algebra(r, state);
+        let mut result = [ExtensionAlgebra::ZERO; SPONGE_WIDTH];
+        for i in 0..SPONGE_WIDTH {
+            result[i] =
+                ExtensionAlgebra::from_basefield_array(core::array::from_fn(|j| coords[j][i]));
         }
 
         result
@@ -1,5 +1,6 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::cmp::max;
 use core::ops::Range;
 
 use crate::field::extension::Extendable;
@@ -10,6 +11,24 @@ use crate::hash::hash_types::RichField;
 /// Placeholder value to indicate that a gate doesn't use a selector polynomial.
 pub(crate) const UNUSED_SELECTOR: usize = u32::MAX as usize;
 
+/// How `CircuitBuilder::build` chooses the quotient degree factor, which bounds the degree of the
+/// filtered constraints and hence how many gates can share a selector polynomial.
+///
+/// Either way, a gate whose degree exceeds the config's `max_quotient_degree_factor` raises the
+/// factor to its degree, splitting the quotient into more chunks.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SelectorGrouping {
+    /// Use the config's `max_quotient_degree_factor`, packing the gates into as few selector
+    /// polynomials as it allows.
+    #[default]
+    Greedy,
+    /// Use the smallest quotient degree factor, up to the config's `max_quotient_degree_factor`,
+    /// for which the gates fit in at most `max_selectors` selector polynomials, or in as many as
+    /// `Greedy` would use if `None`. A smaller factor means fewer quotient polynomials to commit
+    /// to, at the cost of more partial products.
+    MinDegree { max_selectors: Option<usize> },
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SelectorsInfo {
     pub selector_indices: Vec<usize>,
@@ -41,12 +60,13 @@ pub(crate) fn selector_polynomials<F: RichField + Extendable<D>, const D: usize>
 ) -> (Vec<PolynomialValues<F>>, SelectorsInfo) {
     let n = instances.len();
     let num_gates = gates.len();
-    let max_gate_degree = gates.last().expect("No gates?").0.degree();
 
     let index = |id| gates.iter().position(|g| g.0.id() == id).unwrap();
 
+    let groups = selector_groups(gates, max_degree);
+
     // Special case if we can use only one selector polynomial.
-    if max_gate_degree + num_gates - 1 <= max_degree {
+    if groups.len() == 1 {
         return (
             vec![PolynomialValues::new(
                 instances
@@ -56,30 +76,11 @@ pub(crate) fn selector_polynomials<F: RichField + Extendable<D>, const D: usize>
             )],
             SelectorsInfo {
                 selector_indices: vec![0; num_gates],
-                groups: vec![0..num_gates],
+                groups,
             },
         );
     }
 
-    if max_gate_degree >= max_degree {
-        panic!(
-            "{} has too high degree. Consider increasing `quotient_degree_factor`.",
-            gates.last().unwrap().0.id()
-        );
-    }
-
-    // Greedily construct the groups.
-    let mut groups = Vec::new();
-    let mut start = 0;
-    while start < num_gates {
-        let mut size = 0;
-        while (start + size < gates.len()) && (size + gates[start + size].0.degree() < max_degree) {
-            size += 1;
-        }
-        groups.push(start..start + size);
-        start += size;
-    }
-
     let group = |i| groups.iter().position(|range| range.contains(&i)).unwrap();
 
     // `selector_indices[i] = j` iff the `i`-th gate uses the `j`-th selector polynomial.
@@ -110,3 +111,62 @@ pub(crate) fn selector_polynomials<F: RichField + Extendable<D>, const D: usize>
         },
     )
 }
+
+/// Returns the bound on the degree of filtered constraints, i.e. the quotient degree factor plus
+/// one, used to group `gates`, sorted by degree, according to `grouping`.
+pub(crate) fn selector_max_degree<F: RichField + Extendable<D>, const D: usize>(
+    gates: &[GateRef<F, D>],
+    grouping: SelectorGrouping,
+    max_quotient_degree_factor: usize,
+) -> usize {
+    let max_gate_degree = gates.last().expect("No gates?").0.degree();
+    let max_degree = max(max_quotient_degree_factor, max_gate_degree) + 1;
+    match grouping {
+        SelectorGrouping::Greedy => max_degree,
+        SelectorGrouping::MinDegree { max_selectors } => {
+            let max_selectors =
+                max_selectors.unwrap_or_else(|| selector_groups(gates, max_degree).len());
+            // Since the gates are sorted by degree, the greedy groups are optimal and their number
+            // can only decrease as the bound grows, so the first bound which fits is the smallest.
+            // The permutation argument needs a quotient degree factor of at least 2.
+            (max(max_gate_degree + 1, 3)..max_degree)
+                .find(|&d| selector_groups(gates, d).len() <= max_selectors)
+                .unwrap_or(max_degree)
+        }
+    }
+}
+
+/// Partitions the gates, sorted by degree, into the groups sharing a selector polynomial, as
+/// described in `selector_polynomials`.
+pub(crate) fn selector_groups<F: RichField + Extendable<D>, const D: usize>(
+    gates: &[GateRef<F, D>],
+    max_degree: usize,
+) -> Vec<Range<usize>> {
+    let num_gates = gates.len();
+    let max_gate_degree = gates.last().expect("No gates?").0.degree();
+
+    // Special case if we can use only one selector polynomial.
+    if max_gate_degree + num_gates - 1 <= max_degree {
+        return vec![0..num_gates];
+    }
+
+    if max_gate_degree >= max_degree {
+        panic!(
+            "{} has too high degree. Consider increasing `quotient_degree_factor`.",
+            gates.last().unwrap().0.id()
+        );
+    }
+
+    // Greedily construct the groups.
+    let mut groups = Vec::new();
+    let mut start = 0;
+    while start < num_gates {
+        let mut size = 0;
+        while (start + size < gates.len()) && (size + gates[start + size].0.degree() < max_degree) {
+            size += 1;
+        }
+        groups.push(start..start + size);
+        start += size;
+    }
+    groups
+}
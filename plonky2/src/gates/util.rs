@@ -1,3 +1,4 @@
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::marker::PhantomData;
 
 use crate::field::packed::PackedField;
@@ -6,6 +7,7 @@ use crate::field::packed::PackedField;
 /// Permits us to abstract the underlying memory layout. In particular, we can make a matrix of
 /// constraints where every column is an evaluation point and every row is a constraint index, with
 /// the matrix stored in row-contiguous form.
+#[cfg(not(feature = "forbid-unsafe"))]
 pub struct StridedConstraintConsumer<'a, P: PackedField> {
     // This is a particularly neat way of doing this, more so than a slice. We increase start by
     // stride at every step and terminate when it equals end.
@@ -15,6 +17,7 @@ pub struct StridedConstraintConsumer<'a, P: PackedField> {
     _phantom: PhantomData<&'a mut [P::Scalar]>,
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 impl<'a, P: PackedField> StridedConstraintConsumer<'a, P> {
     pub fn new(buffer: &'a mut [P::Scalar], stride: usize, offset: usize) -> Self {
         assert!(stride >= P::WIDTH);
@@ -61,3 +64,45 @@ impl<'a, P: PackedField> StridedConstraintConsumer<'a, P> {
             .for_each(|constraint| self.one(constraint));
     }
 }
+
+/// With the `forbid-unsafe` feature, the consumer indexes into the buffer instead of moving a
+/// pointer through it.
+#[cfg(feature = "forbid-unsafe")]
+pub struct StridedConstraintConsumer<'a, P: PackedField> {
+    buffer: &'a mut [P::Scalar],
+    // The index of the next constraint in `buffer`. We increase it by stride at every step and
+    // terminate when it passes the end of `buffer`.
+    index: usize,
+    stride: usize,
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl<'a, P: PackedField> StridedConstraintConsumer<'a, P> {
+    pub fn new(buffer: &'a mut [P::Scalar], stride: usize, offset: usize) -> Self {
+        assert!(stride >= P::WIDTH);
+        assert!(offset < stride);
+        assert_eq!(buffer.len() % stride, 0);
+        Self {
+            buffer,
+            index: offset,
+            stride,
+        }
+    }
+
+    /// Emit one constraint.
+    pub fn one(&mut self, constraint: P) {
+        if self.index < self.buffer.len() {
+            *P::from_slice_mut(&mut self.buffer[self.index..self.index + P::WIDTH]) = constraint;
+            self.index += self.stride;
+        } else {
+            panic!("gate produced too many constraints");
+        }
+    }
+
+    /// Convenience method that calls `.one()` multiple times.
+    pub fn many<I: IntoIterator<Item = P>>(&mut self, constraints: I) {
+        constraints
+            .into_iter()
+            .for_each(|constraint| self.one(constraint));
+    }
+}
@@ -1,5 +1,5 @@
 #[cfg(target_arch = "x86_64")]
 pub(crate) mod x86_64;
 
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(target_arch = "aarch64", not(feature = "forbid-unsafe")))]
 pub(crate) mod aarch64;
@@ -0,0 +1,192 @@
+//! Authenticated symmetric encryption of field elements with a duplex sponge.
+//!
+//! The sponge state is initialized with the key, a nonce and the message length, and permuted.
+//! Each chunk of `SPONGE_RATE` message elements is then encrypted by adding it to the rate part of
+//! the state, and the resulting ciphertext overwrites the rate before the next permutation, so that
+//! every ciphertext element depends on the key, the nonce and all previous elements. After the last
+//! chunk, one more permutation gives an authentication tag.
+//!
+//! Both sides are provided: `duplex_encrypt` and `duplex_decrypt` natively, and
+//! `CircuitBuilder::duplex_encrypt` in circuits, which lets a circuit prove that a published
+//! ciphertext, e.g. a memo attached to a transaction, is a correct encryption of some private
+//! values under a key, e.g. one shared with the recipient through a Diffie-Hellman exchange over
+//! ecGFp5.
+//!
+//! A (key, nonce) pair must never be used to encrypt two different messages.
+
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// A message encrypted with `duplex_encrypt`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplexCiphertext<F: RichField> {
+    /// The encrypted message, with as many elements as the plaintext.
+    pub ciphertext: Vec<F>,
+    /// Authenticates the ciphertext under the key and nonce.
+    pub tag: HashOut<F>,
+}
+
+/// The initial state: the key and nonce in the rate, and the message length in the capacity as a
+/// domain separator.
+fn initial_state<T: Copy>(key: [T; 4], nonce: T, len: T, zero: T) -> [T; SPONGE_WIDTH] {
+    let mut state = [zero; SPONGE_WIDTH];
+    state[..4].copy_from_slice(&key);
+    state[4] = nonce;
+    state[SPONGE_RATE] = len;
+    state
+}
+
+/// Encrypts `message` under `key`, with a `nonce` which must be unique for this key.
+pub fn duplex_encrypt<F: RichField, P: PlonkyPermutation<F>>(
+    key: HashOut<F>,
+    nonce: F,
+    message: &[F],
+) -> DuplexCiphertext<F> {
+    let len = F::from_canonical_usize(message.len());
+    let mut state = P::permute(initial_state(key.elements, nonce, len, F::ZERO));
+
+    let mut ciphertext = Vec::with_capacity(message.len());
+    for chunk in message.chunks(SPONGE_RATE) {
+        for (s, &m) in state.iter_mut().zip(chunk) {
+            *s += m;
+            ciphertext.push(*s);
+        }
+        state = P::permute(state);
+    }
+
+    DuplexCiphertext {
+        ciphertext,
+        tag: HashOut::from_partial(&state[..4]),
+    }
+}
+
+/// Decrypts a message encrypted with `duplex_encrypt`, or fails if its tag doesn't match, e.g.
+/// because the key or nonce are wrong or the ciphertext was tampered with.
+pub fn duplex_decrypt<F: RichField, P: PlonkyPermutation<F>>(
+    key: HashOut<F>,
+    nonce: F,
+    ciphertext: &DuplexCiphertext<F>,
+) -> Result<Vec<F>> {
+    let len = F::from_canonical_usize(ciphertext.ciphertext.len());
+    let mut state = P::permute(initial_state(key.elements, nonce, len, F::ZERO));
+
+    let mut message = Vec::with_capacity(ciphertext.ciphertext.len());
+    for chunk in ciphertext.ciphertext.chunks(SPONGE_RATE) {
+        for (s, &c) in state.iter_mut().zip(chunk) {
+            message.push(c - *s);
+            *s = c;
+        }
+        state = P::permute(state);
+    }
+
+    ensure!(
+        state[..4] == ciphertext.tag.elements[..],
+        "Invalid ciphertext tag"
+    );
+    Ok(message)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Encrypts `message` under `key` and `nonce`, as in `duplex_encrypt`, and returns the
+    /// ciphertext and tag. Registering them as public inputs proves that they are a correct
+    /// encryption of `message`, while keeping it private.
+    pub fn duplex_encrypt<H: AlgebraicHasher<F>>(
+        &mut self,
+        key: HashOutTarget,
+        nonce: Target,
+        message: &[Target],
+    ) -> (Vec<Target>, HashOutTarget) {
+        let zero = self.zero();
+        let len = self.constant(F::from_canonical_usize(message.len()));
+        let mut state = self.permute::<H>(initial_state(key.elements, nonce, len, zero));
+
+        let mut ciphertext = Vec::with_capacity(message.len());
+        for chunk in message.chunks(SPONGE_RATE) {
+            for (s, &m) in state.iter_mut().zip(chunk) {
+                *s = self.add(*s, m);
+                ciphertext.push(*s);
+            }
+            state = self.permute::<H>(state);
+        }
+
+        let tag = HashOutTarget::from_vec(state[..NUM_HASH_OUT_ELTS].to_vec());
+        (ciphertext, tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Sample};
+    use crate::hash::duplex::{duplex_decrypt, duplex_encrypt};
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type P = <PoseidonHash as Hasher<F>>::Permutation;
+
+    #[test]
+    fn test_duplex_roundtrip() -> Result<()> {
+        let key = HashOut::rand();
+        let nonce = F::rand();
+        for len in [0, 1, 8, 13] {
+            let message = F::rand_vec(len);
+            let encrypted = duplex_encrypt::<F, P>(key, nonce, &message);
+            assert_eq!(encrypted.ciphertext.len(), len);
+            assert_eq!(duplex_decrypt::<F, P>(key, nonce, &encrypted)?, message);
+
+            assert!(duplex_decrypt::<F, P>(HashOut::rand(), nonce, &encrypted).is_err());
+            assert!(duplex_decrypt::<F, P>(key, nonce + F::ONE, &encrypted).is_err());
+            if len > 0 {
+                let mut tampered = encrypted.clone();
+                tampered.ciphertext[len - 1] += F::ONE;
+                assert!(duplex_decrypt::<F, P>(key, nonce, &tampered).is_err());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplex_encrypt_circuit() -> Result<()> {
+        const LEN: usize = 11;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let key = builder.add_virtual_hash();
+        let nonce = builder.add_virtual_target();
+        let message = builder.add_virtual_targets(LEN);
+        let (ciphertext, tag) = builder.duplex_encrypt::<PoseidonHash>(key, nonce, &message);
+        builder.register_public_inputs(&ciphertext);
+        builder.register_public_inputs(&tag.elements);
+        let data = builder.build::<C>();
+
+        let (key_value, nonce_value) = (HashOut::rand(), F::rand());
+        let message_value = F::rand_vec(LEN);
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(key, key_value);
+        pw.set_target(nonce, nonce_value);
+        for (&t, &v) in message.iter().zip(&message_value) {
+            pw.set_target(t, v);
+        }
+        let proof = data.prove(pw)?;
+
+        let encrypted = duplex_encrypt::<F, P>(key_value, nonce_value, &message_value);
+        assert_eq!(proof.public_inputs[..LEN], encrypted.ciphertext);
+        assert_eq!(proof.public_inputs[LEN..], encrypted.tag.elements);
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,79 @@
+//! Running hash accumulators over lists of digests, e.g. nullifiers: starting from an accumulator
+//! `acc`, each digest `x` in turn gives `acc = H(acc || x)`. A proof can then expose the final
+//! accumulator in place of the list, and anyone holding the list checks it by recomputing the
+//! chain. Chains compose, so the accumulator of a list extends that of any of its prefixes.
+
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+
+/// Absorbs `items`, in order, into `accumulator`, as `CircuitBuilder::hash_chain` does.
+pub fn hash_chain<'a, F: RichField, H: AlgebraicHasher<F>>(
+    accumulator: HashOut<F>,
+    items: impl IntoIterator<Item = &'a HashOut<F>>,
+) -> HashOut<F> {
+    items.into_iter().fold(accumulator, |accumulator, item| {
+        H::hash_no_pad(&[accumulator.elements, item.elements].concat())
+    })
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Absorbs `items`, in order, into `accumulator`, as the native `hash_chain` does.
+    pub fn hash_chain<H: AlgebraicHasher<F>>(
+        &mut self,
+        accumulator: HashOutTarget,
+        items: &[HashOutTarget],
+    ) -> HashOutTarget {
+        items.iter().fold(accumulator, |accumulator, item| {
+            let inputs: Vec<_> = accumulator
+                .elements
+                .iter()
+                .chain(&item.elements)
+                .copied()
+                .collect();
+            self.hash_n_to_hash_no_pad::<H>(inputs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_hash_chain() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let items: Vec<HashOut<F>> = (0..5).map(|_| HashOut::rand()).collect();
+        let accumulator = hash_chain::<F, H>(HashOut::ZERO, &items);
+        let prefix = hash_chain::<F, H>(HashOut::ZERO, &items[..2]);
+        assert_eq!(hash_chain::<F, H>(prefix, &items[2..]), accumulator);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let start = builder.add_virtual_hash();
+        let items_t = builder.add_virtual_hashes(items.len());
+        let accumulator_t = builder.hash_chain::<H>(start, &items_t);
+        let expected = builder.constant_hash(accumulator);
+        builder.connect_hashes(accumulator_t, expected);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(start, HashOut::ZERO);
+        for (&t, &item) in items_t.iter().zip(&items) {
+            pw.set_hash_target(t, item);
+        }
+        data.verify(data.prove(pw)?)
+    }
+}
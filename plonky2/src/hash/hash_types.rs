@@ -1,6 +1,10 @@
 use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
 
-use anyhow::ensure;
+use anyhow::{anyhow, ensure};
+use keccak_hash::keccak;
+use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::field::goldilocks_field::GoldilocksField;
@@ -14,35 +18,40 @@ pub trait RichField: PrimeField64 + Poseidon {}
 
 impl RichField for GoldilocksField {}
 
+/// The number of field elements in the output of an algebraic hash, e.g. `HashOut`. This is a
+/// fixed size rather than a parameter of the hash types: digests of another size, e.g. full
+/// 32-byte Keccak digests, are `BytesHash`es rather than `HashOut`s.
+pub const NUM_HASH_OUT_ELTS: usize = 4;
+
 /// Represents a ~256 bit hash output.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(bound = "")]
 pub struct HashOut<F: Field> {
-    pub elements: [F; 4],
+    pub elements: [F; NUM_HASH_OUT_ELTS],
 }
 
 impl<F: Field> HashOut<F> {
     pub const ZERO: Self = Self {
-        elements: [F::ZERO; 4],
+        elements: [F::ZERO; NUM_HASH_OUT_ELTS],
     };
 
     // TODO: Switch to a TryFrom impl.
     pub fn from_vec(elements: Vec<F>) -> Self {
-        debug_assert!(elements.len() == 4);
+        debug_assert!(elements.len() == NUM_HASH_OUT_ELTS);
         Self {
             elements: elements.try_into().unwrap(),
         }
     }
 
     pub fn from_partial(elements_in: &[F]) -> Self {
-        let mut elements = [F::ZERO; 4];
+        let mut elements = [F::ZERO; NUM_HASH_OUT_ELTS];
         elements[0..elements_in.len()].copy_from_slice(elements_in);
         Self { elements }
     }
 }
 
-impl<F: Field> From<[F; 4]> for HashOut<F> {
-    fn from(elements: [F; 4]) -> Self {
+impl<F: Field> From<[F; NUM_HASH_OUT_ELTS]> for HashOut<F> {
+    fn from(elements: [F; NUM_HASH_OUT_ELTS]) -> Self {
         Self { elements }
     }
 }
@@ -51,7 +60,7 @@ impl<F: Field> TryFrom<&[F]> for HashOut<F> {
     type Error = anyhow::Error;
 
     fn try_from(elements: &[F]) -> Result<Self, Self::Error> {
-        ensure!(elements.len() == 4);
+        ensure!(elements.len() == NUM_HASH_OUT_ELTS);
         Ok(Self {
             elements: elements.try_into().unwrap(),
         })
@@ -68,12 +77,7 @@ where
         R: rand::RngCore + ?Sized,
     {
         Self {
-            elements: [
-                F::sample(rng),
-                F::sample(rng),
-                F::sample(rng),
-                F::sample(rng),
-            ],
+            elements: core::array::from_fn(|_| F::sample(rng)),
         }
     }
 }
@@ -82,7 +86,7 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
     fn to_bytes(&self) -> Vec<u8> {
         self.elements
             .into_iter()
-            .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+            .flat_map(|x| x.canonical_bytes())
             .collect()
     }
 
@@ -90,8 +94,9 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
         HashOut {
             elements: bytes
                 .chunks(8)
-                .take(4)
-                .map(|x| F::from_canonical_u64(u64::from_le_bytes(x.try_into().unwrap())))
+                .take(NUM_HASH_OUT_ELTS)
+                // Non-canonical elements are kept as they are, so that `to_bytes` tells them apart.
+                .map(|x| F::from_noncanonical_u64(u64::from_le_bytes(x.try_into().unwrap())))
                 .collect::<Vec<_>>()
                 .try_into()
                 .unwrap(),
@@ -109,30 +114,80 @@ impl<F: Field> Default for HashOut<F> {
     }
 }
 
+/// The number of bytes of Keccak-256 checksum which the `Display` of a `HashOut` ends with.
+const HASH_OUT_CHECKSUM_LEN: usize = 4;
+
+fn hash_out_checksum(bytes: &[u8]) -> [u8; HASH_OUT_CHECKSUM_LEN] {
+    keccak(bytes).0[..HASH_OUT_CHECKSUM_LEN].try_into().unwrap()
+}
+
+/// Formats the hash for operators, e.g. in logs and as arguments of command-line tools: its
+/// canonical bytes, as in `to_bytes`, followed by the first 4 bytes of their Keccak-256, in
+/// lowercase hex. `FromStr` parses it back, so a corrupted identifier fails to parse rather than
+/// being taken for another hash.
+impl<F: RichField> fmt::Display for HashOut<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = GenericHashOut::<F>::to_bytes(self);
+        for byte in bytes.iter().chain(&hash_out_checksum(&bytes)) {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the `Display` of a hash, case-insensitively and with an optional `0x` prefix.
+impl<F: RichField> FromStr for HashOut<F> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        let num_bytes = NUM_HASH_OUT_ELTS * 8 + HASH_OUT_CHECKSUM_LEN;
+        ensure!(
+            digits.len() == 2 * num_bytes && digits.bytes().all(|b| b.is_ascii_hexdigit()),
+            "A hash is {} hex digits",
+            2 * num_bytes
+        );
+        let bytes = (0..num_bytes)
+            .map(|i| u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+        let (bytes, checksum) = bytes.split_at(NUM_HASH_OUT_ELTS * 8);
+        ensure!(
+            checksum == hash_out_checksum(bytes),
+            "The hash's checksum doesn't match"
+        );
+        let elements = bytes
+            .chunks(8)
+            .map(|x| F::from_canonical_bytes(x.try_into().unwrap()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("The hash has a non-canonical element"))?;
+        Ok(Self::from_vec(elements))
+    }
+}
+
 /// Represents a ~256 bit hash output.
 #[derive(Copy, Clone, Debug)]
 pub struct HashOutTarget {
-    pub elements: [Target; 4],
+    pub elements: [Target; NUM_HASH_OUT_ELTS],
 }
 
 impl HashOutTarget {
     // TODO: Switch to a TryFrom impl.
     pub fn from_vec(elements: Vec<Target>) -> Self {
-        debug_assert!(elements.len() == 4);
+        debug_assert!(elements.len() == NUM_HASH_OUT_ELTS);
         Self {
             elements: elements.try_into().unwrap(),
         }
     }
 
     pub fn from_partial(elements_in: &[Target], zero: Target) -> Self {
-        let mut elements = [zero; 4];
+        let mut elements = [zero; NUM_HASH_OUT_ELTS];
         elements[0..elements_in.len()].copy_from_slice(elements_in);
         Self { elements }
     }
 }
 
-impl From<[Target; 4]> for HashOutTarget {
-    fn from(elements: [Target; 4]) -> Self {
+impl From<[Target; NUM_HASH_OUT_ELTS]> for HashOutTarget {
+    fn from(elements: [Target; NUM_HASH_OUT_ELTS]) -> Self {
         Self { elements }
     }
 }
@@ -141,7 +196,7 @@ impl TryFrom<&[Target]> for HashOutTarget {
     type Error = anyhow::Error;
 
     fn try_from(elements: &[Target]) -> Result<Self, Self::Error> {
-        ensure!(elements.len() == 4);
+        ensure!(elements.len() == NUM_HASH_OUT_ELTS);
         Ok(Self {
             elements: elements.try_into().unwrap(),
         })
@@ -189,20 +244,90 @@ impl<F: RichField, const N: usize> GenericHashOut<F> for BytesHash<N> {
     }
 }
 
+/// Serialized as a byte string, which formats without one, e.g. JSON, write as an array of bytes.
 impl<const N: usize> Serialize for BytesHash<N> {
-    fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        todo!()
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+struct BytesHashVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for BytesHashVisitor<N> {
+    type Value = BytesHash<N>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a digest of {N} bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        bytes
+            .try_into()
+            .map(BytesHash)
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(BytesHash(bytes))
     }
 }
 
 impl<'de, const N: usize> Deserialize<'de> for BytesHash<N> {
-    fn deserialize<D>(_deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        todo!()
+        deserializer.deserialize_bytes(BytesHashVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::{String, ToString};
+
+    use super::*;
+
+    #[test]
+    fn test_hash_out_display() {
+        type F = GoldilocksField;
+
+        let hash = HashOut::<F>::rand();
+        let displayed = hash.to_string();
+        assert_eq!(displayed.len(), 72);
+        assert_eq!(displayed.parse::<HashOut<F>>().unwrap(), hash);
+        assert_eq!(
+            format!("0x{}", displayed.to_uppercase())
+                .parse::<HashOut<F>>()
+                .unwrap(),
+            hash
+        );
+
+        // Changing any digit breaks the checksum.
+        for i in 0..displayed.len() {
+            let mut corrupted = displayed.clone().into_bytes();
+            corrupted[i] = if corrupted[i] == b'0' { b'1' } else { b'0' };
+            let corrupted = String::from_utf8(corrupted).unwrap();
+            assert!(corrupted.parse::<HashOut<F>>().is_err());
+        }
+        assert!(displayed[..70].parse::<HashOut<F>>().is_err());
+
+        // Non-canonical elements are rejected even with a valid checksum.
+        let mut bytes = [0xff; 32].to_vec();
+        bytes.extend(hash_out_checksum(&bytes));
+        let non_canonical = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        assert!(non_canonical.parse::<HashOut<F>>().is_err());
     }
 }
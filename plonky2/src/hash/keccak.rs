@@ -20,7 +20,7 @@ impl<F: RichField> PlonkyPermutation<F> for KeccakPermutation {
         let mut state = vec![0u8; SPONGE_WIDTH * size_of::<u64>()];
         for i in 0..SPONGE_WIDTH {
             state[i * size_of::<u64>()..(i + 1) * size_of::<u64>()]
-                .copy_from_slice(&input[i].to_canonical_u64().to_le_bytes());
+                .copy_from_slice(&input[i].canonical_bytes());
         }
 
         let hash_onion = iter::repeat_with(|| {
@@ -81,3 +81,61 @@ impl<F: RichField, const N: usize> Hasher<F> for KeccakHash<N> {
         BytesHash(arr)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::Sample;
+    use crate::hash::hash_types::BytesHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, GenericHashOut, Keccak256GoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+
+    #[test]
+    fn test_keccak_256_proof() -> Result<()> {
+        const D: usize = 2;
+        type C = Keccak256GoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+
+        // The full digests are kept, and observed as five elements of at most 7 bytes each.
+        let cap = &proof.proof.wires_cap;
+        assert_eq!(GenericHashOut::<F>::to_bytes(&cap.0[0]).len(), 32);
+        assert_eq!(GenericHashOut::<F>::to_vec(&cap.0[0]).len(), 5);
+
+        let read = ProofWithPublicInputs::<F, C, D>::from_bytes(proof.to_bytes(), &data.common)?;
+        assert_eq!(read, proof);
+        let json = serde_json::to_string(&proof)?;
+        assert_eq!(
+            serde_json::from_str::<ProofWithPublicInputs<F, C, D>>(&json)?,
+            proof
+        );
+        let cbor = serde_cbor::to_vec(&proof)?;
+        assert_eq!(
+            serde_cbor::from_slice::<ProofWithPublicInputs<F, C, D>>(&cbor)?,
+            proof
+        );
+
+        // Digests of the wrong length are rejected.
+        assert!(
+            serde_json::from_str::<BytesHash<32>>(&serde_json::to_string(&[0u8; 31])?).is_err()
+        );
+        assert_eq!(
+            serde_json::from_str::<BytesHash<4>>("[1, 2, 3, 4]")?,
+            BytesHash([1, 2, 3, 4])
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 
@@ -6,12 +8,13 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use crate::field::extension::Extendable;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::SPONGE_WIDTH;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::util::log2_strict;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -98,7 +101,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
-        let cap_index = self.le_sum(leaf_index_bits[proof.siblings.len()..].iter().copied());
+        let cap_index_bits = &leaf_index_bits[proof.siblings.len()..];
+        let max_bits = self.max_random_access_bits();
+        if cap_index_bits.len() > max_bits {
+            // The cap is too large for a single random access gate, so its elements are selected
+            // a chunk of the cap index's bits at a time.
+            assert_eq!(
+                1 << cap_index_bits.len(),
+                merkle_cap.0.len(),
+                "The index bits don't match the cap height"
+            );
+            let leaf_digest = self.hash_or_noop::<H>(leaf_data);
+            let state = self.merkle_path_digest::<H>(leaf_digest, leaf_index_bits, &proof.siblings);
+            for i in 0..NUM_HASH_OUT_ELTS {
+                let result = self.random_access_chunked(
+                    cap_index_bits,
+                    max_bits,
+                    merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+                );
+                self.connect(result, state.elements[i]);
+            }
+            return;
+        }
+
+        let cap_index = self.le_sum(cap_index_bits.iter().copied());
         self.verify_merkle_proof_to_cap_with_cap_index::<H>(
             leaf_data,
             leaf_index_bits,
@@ -108,6 +134,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
+    /// given cap, as proven by `MerkleTree::prove(leaf_index)`: the low bits of the index select
+    /// the path, and its top `log2(merkle_cap.len())` bits the element of the cap.
+    pub fn verify_merkle_proof_to_cap_at_index<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index: Target,
+        merkle_cap: &MerkleCapTarget,
+        proof: &MerkleProofTarget,
+    ) {
+        let cap_height = log2_strict(merkle_cap.0.len());
+        let leaf_index_bits = self.split_le(leaf_index, proof.siblings.len() + cap_height);
+        self.verify_merkle_proof_to_cap::<H>(leaf_data, &leaf_index_bits, merkle_cap, proof);
+    }
+
     /// Same as `verify_merkle_proof_to_cap`, except with the final "cap index" as separate parameter,
     /// rather than being contained in `leaf_index_bits`.
     pub(crate) fn verify_merkle_proof_to_cap_with_cap_index<H: AlgebraicHasher<F>>(
@@ -118,31 +159,180 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
+        let leaf_digest = self.hash_or_noop::<H>(leaf_data);
+        let state = self.merkle_path_digest::<H>(leaf_digest, leaf_index_bits, &proof.siblings);
+
+        for i in 0..NUM_HASH_OUT_ELTS {
+            let result = self.random_access(
+                cap_index,
+                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+            );
+            self.connect(result, state.elements[i]);
+        }
+    }
+
+    /// The digest of the node `siblings.len()` levels above `digest`, given the siblings on the way
+    /// and the little-endian bits of the index of `digest` among its level.
+    fn merkle_path_digest<H: AlgebraicHasher<F>>(
+        &mut self,
+        digest: HashOutTarget,
+        index_bits: &[BoolTarget],
+        siblings: &[HashOutTarget],
+    ) -> HashOutTarget {
+        let zero = self.zero();
+        let mut state = digest;
+        for (&bit, &sibling) in index_bits.iter().zip(siblings) {
+            let mut perm_inputs = [zero; SPONGE_WIDTH];
+            perm_inputs[..NUM_HASH_OUT_ELTS].copy_from_slice(&state.elements);
+            perm_inputs[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS]
+                .copy_from_slice(&sibling.elements);
+            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
+            let hash_outs = perm_outs[..NUM_HASH_OUT_ELTS].try_into().unwrap();
+            state = HashOutTarget {
+                elements: hash_outs,
+            };
+        }
+        state
+    }
+
+    /// Verifies that each of `leaves` is present at the corresponding index of `indices` in the
+    /// Merkle tree with the corresponding root of `roots`, as proven by the corresponding path of
+    /// `paths`. Several leaves may share a root.
+    ///
+    /// The indices are fixed when building the circuit, so that each node on the paths of several
+    /// leaves with the same root target is hashed once, and the digest of a node on a path is used
+    /// instead of the sibling given by another path through its parent. Opening `k` leaves of a
+    /// tree of height `h` thus costs between `h` and `k * h` hashes, depending on how many
+    /// ancestors they have in common. Siblings which aren't needed are ignored. All the paths to a
+    /// root must have the same length.
+    pub fn verify_merkle_proofs_shared<H: AlgebraicHasher<F>>(
+        &mut self,
+        roots: &[HashOutTarget],
+        leaves: Vec<Vec<Target>>,
+        indices: &[usize],
+        paths: &[MerkleProofTarget],
+    ) {
+        assert_eq!(leaves.len(), roots.len());
+        assert_eq!(leaves.len(), indices.len());
+        assert_eq!(leaves.len(), paths.len());
+
+        // The nodes of the current level which are on some path, by the first leaf with the same
+        // root and their index among the level, with their digest and the first leaf below them.
+        let mut nodes = BTreeMap::new();
+        for (i, leaf_data) in leaves.into_iter().enumerate() {
+            let tree = roots
+                .iter()
+                .position(|root| root.elements == roots[i].elements)
+                .unwrap();
+            let height = paths[tree].siblings.len();
+            assert_eq!(
+                paths[i].siblings.len(),
+                height,
+                "The paths to a root have different lengths"
+            );
+            assert!(
+                indices[i] >> height == 0,
+                "The index {} is too large",
+                indices[i]
+            );
+
+            let digest = self.hash_or_noop::<H>(leaf_data);
+            match nodes.entry((tree, indices[i])) {
+                Entry::Vacant(entry) => {
+                    entry.insert((digest, i));
+                }
+                Entry::Occupied(entry) => self.connect_hashes(entry.get().0, digest),
+            }
+        }
+
+        let mut level = 0;
+        while !nodes.is_empty() {
+            let mut parents = BTreeMap::new();
+            for (&(tree, index), &(digest, i)) in &nodes {
+                if level == paths[i].siblings.len() {
+                    self.connect_hashes(digest, roots[i]);
+                    continue;
+                }
+                let sibling = nodes.get(&(tree, index ^ 1));
+                if index & 1 == 1 && sibling.is_some() {
+                    // The parent is hashed along with the left child.
+                    continue;
+                }
+                let sibling = sibling.map_or(paths[i].siblings[level], |&(sibling, _)| sibling);
+                let parent = if index & 1 == 0 {
+                    self.two_to_one::<H>(digest, sibling)
+                } else {
+                    self.two_to_one::<H>(sibling, digest)
+                };
+                parents.insert((tree, index >> 1), (parent, i));
+            }
+            nodes = parents;
+            level += 1;
+        }
+    }
+
+    /// Verifies that the given leaf data is present at the given index in a Merkle tree with the
+    /// given root, whose height is the witness `height`, at most `proof.siblings.len()`. Only the
+    /// first `height` siblings and index bits are used, and the others must be zero, so that one
+    /// circuit serves trees of any height up to the maximum. The witness can be set with
+    /// `WitnessWrite::set_padded_merkle_proof_target`.
+    ///
+    /// Nothing binds the height to the root, so callers must constrain it, e.g. by registering it
+    /// as a public input along with the root: as leaves of at most 4 elements are their own
+    /// digest, an internal node of a tree could otherwise pass for a leaf of a shorter tree.
+    pub fn verify_variable_height_merkle_proof<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        height: Target,
+        merkle_root: HashOutTarget,
+        proof: &MerkleProofTarget,
+    ) {
+        let max_height = proof.siblings.len();
+        assert_eq!(leaf_index_bits.len(), max_height);
+
+        // Exactly one of these holds, which bounds the height.
+        let is_height = (0..=max_height)
+            .map(|h| {
+                let h = self.constant(F::from_canonical_usize(h));
+                self.is_equal(height, h)
+            })
+            .collect::<Vec<_>>();
+        let num_heights = self.add_many(is_height.iter().map(|b| b.target));
+        self.assert_one(num_heights);
+
         let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
+        // Whether the current level is at or above the height, i.e. is padding.
+        let mut is_padding = zero;
+        for ((&bit, &sibling), is_height) in
+            leaf_index_bits.iter().zip(&proof.siblings).zip(is_height)
+        {
+            is_padding = self.add(is_padding, is_height.target);
+            let padded_bit = self.mul(is_padding, bit.target);
+            self.assert_zero(padded_bit);
+            for x in sibling.elements {
+                let padded_x = self.mul(is_padding, x);
+                self.assert_zero(padded_x);
+            }
 
-        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
             let mut perm_inputs = [zero; SPONGE_WIDTH];
             perm_inputs[..4].copy_from_slice(&state.elements);
             perm_inputs[4..8].copy_from_slice(&sibling.elements);
             let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs[0..4].try_into().unwrap();
+            let is_padding = BoolTarget::new_unsafe(is_padding);
             state = HashOutTarget {
-                elements: hash_outs,
+                elements: core::array::from_fn(|i| {
+                    self.select(is_padding, state.elements[i], perm_outs[i])
+                }),
             };
         }
 
-        for i in 0..4 {
-            let result = self.random_access(
-                cap_index,
-                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
-            );
-            self.connect(result, state.elements[i]);
-        }
+        self.connect_hashes(state, merkle_root);
     }
 
     pub fn connect_hashes(&mut self, x: HashOutTarget, y: HashOutTarget) {
-        for i in 0..4 {
+        for i in 0..NUM_HASH_OUT_ELTS {
             self.connect(x.elements[i], y.elements[i]);
         }
     }
@@ -217,4 +407,181 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_merkle_proof_to_cap_at_index() -> Result<()> {
+        let log_n = 9;
+        let n = 1 << log_n;
+        let leaves = random_data::<F>(n, 7);
+        // A cap of 128 elements doesn't fit in a single random access gate.
+        for cap_height in [0, 2, 7] {
+            let tree = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+            let i: usize = OsRng.gen_range(0..n);
+            let proof = tree.prove(i);
+
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let mut pw = PartialWitness::new();
+            let proof_t = MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(proof.siblings.len()),
+            };
+            pw.set_merkle_proof_target(&proof_t, &proof);
+            let cap_t = builder.add_virtual_cap(cap_height);
+            pw.set_cap_target(&cap_t, &tree.cap);
+            let i_t = builder.add_virtual_target();
+            pw.set_target(i_t, F::from_canonical_usize(i));
+            let data_t = builder.add_virtual_targets(tree.leaves[i].len());
+            for (&t, &x) in data_t.iter().zip(&tree.leaves[i]) {
+                pw.set_target(t, x);
+            }
+
+            builder.verify_merkle_proof_to_cap_at_index::<H>(data_t, i_t, &cap_t, &proof_t);
+
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            verify(proof, &data.verifier_only, &data.common)?;
+        }
+        Ok(())
+    }
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::InnerHasher;
+    const MAX_HEIGHT: usize = 6;
+
+    /// Proves that the leaf at `index` of `tree` is in a tree of the claimed `height`, with a
+    /// circuit for trees of height up to `MAX_HEIGHT`.
+    fn prove_variable_height(tree: &MerkleTree<F, H>, index: usize, height: usize) -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let leaf_t = builder.add_virtual_targets(tree.leaves[0].len());
+        let index_t = builder.add_virtual_target();
+        let index_bits_t = builder.split_le(index_t, MAX_HEIGHT);
+        let height_t = builder.add_virtual_target();
+        let root_t = builder.add_virtual_hash();
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(MAX_HEIGHT),
+        };
+        builder.verify_variable_height_merkle_proof::<H>(
+            leaf_t.clone(),
+            &index_bits_t,
+            height_t,
+            root_t,
+            &proof_t,
+        );
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for (&t, &x) in leaf_t.iter().zip(&tree.leaves[index % tree.leaves.len()]) {
+            pw.set_target(t, x);
+        }
+        pw.set_target(index_t, F::from_canonical_usize(index));
+        pw.set_target(height_t, F::from_canonical_usize(height));
+        pw.set_hash_target(root_t, tree.cap.0[0]);
+        pw.set_padded_merkle_proof_target(&proof_t, &tree.prove(index % tree.leaves.len()));
+        data.verify(data.prove(pw)?)
+    }
+
+    const TREE_HEIGHT: usize = 8;
+
+    /// Proves that the leaves at `openings`, each given by the index of its tree among two trees
+    /// of height `TREE_HEIGHT` and its index in the tree, are in these trees with
+    /// `verify_merkle_proofs_shared`, claiming that they are at `claimed_indices`. Returns the
+    /// number of gates of the circuit.
+    fn prove_shared(openings: &[(usize, usize)], claimed_indices: &[usize]) -> Result<usize> {
+        let trees =
+            [(); 2].map(|_| MerkleTree::<F, H>::new(random_data::<F>(1 << TREE_HEIGHT, 7), 0));
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let roots_t = [(); 2].map(|_| builder.add_virtual_hash());
+        let leaves_t = openings
+            .iter()
+            .map(|_| builder.add_virtual_targets(7))
+            .collect::<Vec<_>>();
+        let paths_t = openings
+            .iter()
+            .map(|_| MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(TREE_HEIGHT),
+            })
+            .collect::<Vec<_>>();
+        builder.verify_merkle_proofs_shared::<H>(
+            &openings
+                .iter()
+                .map(|&(tree, _)| roots_t[tree])
+                .collect::<Vec<_>>(),
+            leaves_t.clone(),
+            claimed_indices,
+            &paths_t,
+        );
+        let num_gates = builder.num_gates();
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for (root_t, tree) in roots_t.into_iter().zip(&trees) {
+            pw.set_hash_target(root_t, tree.cap.0[0]);
+        }
+        for (j, &(tree, index)) in openings.iter().enumerate() {
+            for (&t, &x) in leaves_t[j].iter().zip(&trees[tree].leaves[index]) {
+                pw.set_target(t, x);
+            }
+            pw.set_merkle_proof_target(&paths_t[j], &trees[tree].prove(index));
+        }
+        data.verify(data.prove(pw)?)?;
+        Ok(num_gates)
+    }
+
+    #[test]
+    fn test_merkle_proofs_shared() -> Result<()> {
+        // Leaves 44 and 45 are siblings, 40 shares their ancestors from the third level up, and 200
+        // only the root. Leaf 45 is opened twice, and leaf 7 is in the other tree.
+        let openings = [(0, 40), (0, 44), (0, 45), (0, 200), (0, 45), (1, 7)];
+        let indices = openings.map(|(_, index)| index);
+        let shared_gates = prove_shared(&openings, &indices)?;
+        prove_shared(&[(1, 3)], &[3])?;
+
+        // Opening the same leaves separately takes more gates.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let root_t = builder.add_virtual_hash();
+        for _ in openings {
+            let leaf_t = builder.add_virtual_targets(7);
+            let index_t = builder.add_virtual_target();
+            let index_bits_t = builder.split_le(index_t, TREE_HEIGHT);
+            let proof_t = MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(TREE_HEIGHT),
+            };
+            builder.verify_merkle_proof::<H>(leaf_t, &index_bits_t, root_t, &proof_t);
+        }
+        assert!(shared_gates < builder.num_gates());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_merkle_proofs_shared_wrong_index() {
+        prove_shared(&[(0, 40), (0, 45)], &[40, 44]).unwrap();
+    }
+
+    #[test]
+    fn test_variable_height_merkle_proof() -> Result<()> {
+        for height in [0, 3, MAX_HEIGHT] {
+            let n = 1 << height;
+            let tree = MerkleTree::<F, H>::new(random_data::<F>(n, 7), 0);
+            prove_variable_height(&tree, OsRng.gen_range(0..n), height)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_variable_height_merkle_proof_wrong_height() {
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(8, 7), 0);
+        prove_variable_height(&tree, 5, 4).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_variable_height_merkle_proof_index_too_large() {
+        // The index bits above the height are padding, so they must be zero.
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(8, 7), 0);
+        prove_variable_height(&tree, 5 + 8, 3).unwrap();
+    }
 }
@@ -1,5 +1,9 @@
+#[cfg(feature = "forbid-unsafe")]
+use alloc::vec;
 use alloc::vec::Vec;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::mem::MaybeUninit;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::slice;
 
 use maybe_rayon::*;
@@ -9,6 +13,7 @@ use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::{GenericHashOut, Hasher};
 use crate::util::log2_strict;
+use crate::util::serialization::{Read, Write};
 
 /// The Merkle cap of height `h` of a Merkle tree is the `h`-th layer (from the root) of the tree.
 /// It can be used in place of the root to verify Merkle paths, which are `h` elements shorter.
@@ -32,11 +37,31 @@ impl<F: RichField, H: Hasher<F>> MerkleCap<F, H> {
     pub fn flatten(&self) -> Vec<F> {
         self.0.iter().flat_map(|&h| h.to_vec()).collect()
     }
+
+    /// Serializes the cap as its height followed by its digests, each `H::HASH_SIZE` bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_merkle_cap_with_height(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes a cap encoded by `to_bytes`, failing on trailing bytes.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let mut bytes = bytes.as_ref();
+        let cap = bytes
+            .read_merkle_cap_with_height()
+            .map_err(anyhow::Error::msg)?;
+        anyhow::ensure!(bytes.is_empty(), "Trailing bytes after the Merkle cap");
+        Ok(cap)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MerkleTree<F: RichField, H: Hasher<F>> {
-    /// The data in the leaves of the Merkle tree.
+    /// The data in the leaves of the Merkle tree. Empty if the tree was deserialized without its
+    /// leaves, which still allows `prove`.
     pub leaves: Vec<Vec<F>>,
 
     /// The digests in the tree. Consists of `cap.len()` sub-trees, each corresponding to one
@@ -53,6 +78,7 @@ pub struct MerkleTree<F: RichField, H: Hasher<F>> {
     pub cap: MerkleCap<F, H>,
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     assert!(v.capacity() >= len);
     let v_ptr = v.as_mut_ptr().cast::<MaybeUninit<T>>();
@@ -65,6 +91,7 @@ fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     }
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 fn fill_subtree<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     leaves: &[Vec<F>],
@@ -94,6 +121,7 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
     }
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
@@ -130,6 +158,63 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     );
 }
 
+/// The digests and the cap of the tree of height `cap_height` with leaves `leaves`, hashed into
+/// uninitialized buffers.
+#[cfg(not(feature = "forbid-unsafe"))]
+fn digests_and_cap<F: RichField, H: Hasher<F>>(
+    leaves: &[Vec<F>],
+    cap_height: usize,
+) -> (Vec<H::Hash>, Vec<H::Hash>) {
+    let num_digests = 2 * (leaves.len() - (1 << cap_height));
+    let mut digests = Vec::with_capacity(num_digests);
+
+    let len_cap = 1 << cap_height;
+    let mut cap = Vec::with_capacity(len_cap);
+
+    let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
+    let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
+    fill_digests_buf::<F, H>(digests_buf, cap_buf, leaves, cap_height);
+
+    unsafe {
+        // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
+        // `num_digests` and `len_cap`, resp.
+        digests.set_len(num_digests);
+        cap.set_len(len_cap);
+    }
+
+    (digests, cap)
+}
+
+/// The digests and the cap of the tree of height `cap_height` with leaves `leaves`. The digests
+/// are first filled with placeholders, which `update_subtree` then overwrites.
+#[cfg(feature = "forbid-unsafe")]
+fn digests_and_cap<F: RichField, H: Hasher<F>>(
+    leaves: &[Vec<F>],
+    cap_height: usize,
+) -> (Vec<H::Hash>, Vec<H::Hash>) {
+    let num_digests = 2 * (leaves.len() - (1 << cap_height));
+    if num_digests == 0 {
+        // The tree is all cap.
+        let cap = leaves
+            .par_iter()
+            .map(|leaf| H::hash_or_noop(leaf))
+            .collect();
+        return (Vec::new(), cap);
+    }
+
+    let mut digests = vec![H::hash_or_noop(&[]); num_digests];
+    let subtree_digests_len = num_digests >> cap_height;
+    let subtree_leaves_len = leaves.len() >> cap_height;
+    let cap = digests
+        .par_chunks_exact_mut(subtree_digests_len)
+        .zip(leaves.par_chunks_exact(subtree_leaves_len))
+        .map(|(subtree_digests, subtree_leaves)| {
+            update_subtree::<F, H>(subtree_digests, subtree_leaves)
+        })
+        .collect();
+    (digests, cap)
+}
+
 fn update_subtree<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [H::Hash],
     leaves: &[Vec<F>],
@@ -183,6 +268,37 @@ pub fn update_digests<F: RichField, H: Hasher<F>>(
     );
 }
 
+/// The digests of the layers `0..=num_layers` of a tree whose leaves are `leaves` followed by
+/// copies of `padding_leaf`, layer 0 being the leaf digests. Each layer only lists the digests
+/// covering some of `leaves`; the digest of a node covering padding only is the same across its
+/// layer, and is returned once per layer as the second component. Each layer is hashed in
+/// parallel, so uneven subtrees don't leave threads idle as in `fill_subtree`.
+fn layers_with_padding<F: RichField, H: Hasher<F>>(
+    leaves: &[Vec<F>],
+    padding_leaf: &[F],
+    num_layers: usize,
+) -> (Vec<Vec<H::Hash>>, Vec<H::Hash>) {
+    let mut layers = Vec::with_capacity(num_layers + 1);
+    let mut paddings = Vec::with_capacity(num_layers + 1);
+    let mut layer = leaves
+        .par_iter()
+        .map(|leaf| H::hash_or_noop(leaf))
+        .collect::<Vec<_>>();
+    let mut padding = H::hash_or_noop(padding_leaf);
+    for _ in 0..num_layers {
+        let next_layer = layer
+            .par_chunks(2)
+            .map(|pair| H::two_to_one(pair[0], pair.get(1).copied().unwrap_or(padding)))
+            .collect();
+        layers.push(core::mem::replace(&mut layer, next_layer));
+        paddings.push(padding);
+        padding = H::two_to_one(padding, padding);
+    }
+    layers.push(layer);
+    paddings.push(padding);
+    (layers, paddings)
+}
+
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
         let log2_leaves_len = log2_strict(leaves.len());
@@ -193,23 +309,68 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
             log2_leaves_len
         );
 
-        let num_digests = 2 * (leaves.len() - (1 << cap_height));
-        let mut digests = Vec::with_capacity(num_digests);
-
-        let len_cap = 1 << cap_height;
-        let mut cap = Vec::with_capacity(len_cap);
+        let (digests, cap) = digests_and_cap::<F, H>(&leaves, cap_height);
+        Self {
+            leaves,
+            digests,
+            cap: MerkleCap(cap),
+        }
+    }
 
-        let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
-        let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+    /// The tree of `num_leaves` leaves, a power of two, whose first leaves are `leaves` and the
+    /// others copies of `padding_leaf`, e.g. the empty slots of an append-only tree. It is the same
+    /// as `new` on the padded leaves, but digests covering padding only are hashed once per layer
+    /// rather than once per node, and a layer's digests are hashed in parallel once the layer
+    /// below is done, however unbalanced `leaves` makes the tree.
+    pub fn new_with_padding(
+        mut leaves: Vec<Vec<F>>,
+        num_leaves: usize,
+        padding_leaf: Vec<F>,
+        cap_height: usize,
+    ) -> Self {
+        let log2_leaves_len = log2_strict(num_leaves);
+        assert!(
+            leaves.len() <= num_leaves,
+            "{} leaves don't fit in a tree of {}",
+            leaves.len(),
+            num_leaves
+        );
+        assert!(
+            cap_height <= log2_leaves_len,
+            "cap_height={} should be at most log2(num_leaves)={}",
+            cap_height,
+            log2_leaves_len
+        );
+        let num_layers = log2_leaves_len - cap_height;
+        let (layers, paddings) = layers_with_padding::<F, H>(&leaves, &padding_leaf, num_layers);
+        let node = |layer: usize, index: usize| {
+            layers[layer].get(index).copied().unwrap_or(paddings[layer])
+        };
 
-        unsafe {
-            // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
-            // `num_digests` and `len_cap`, resp.
-            digests.set_len(num_digests);
-            cap.set_len(len_cap);
+        let mut digests = vec![paddings[0]; 2 * (num_leaves - (1 << cap_height))];
+        if num_layers > 0 {
+            let subtree_len = digests.len() >> cap_height;
+            digests
+                .par_chunks_exact_mut(subtree_len)
+                .enumerate()
+                .for_each(|(subtree_index, subtree_digests)| {
+                    // Node `j` of layer `i` of the subtree is stored by its parent, at the position
+                    // `prove` reads it from.
+                    for i in 0..num_layers {
+                        let layer_len = 1 << (num_layers - i);
+                        for j in 0..layer_len {
+                            let pair_index = ((j >> 1) << (i + 1)) + (1 << i) - 1;
+                            subtree_digests[2 * pair_index + (j & 1)] =
+                                node(i, subtree_index * layer_len + j);
+                        }
+                    }
+                });
         }
+        let cap = (0..1 << cap_height)
+            .map(|index| node(num_layers, index))
+            .collect();
 
+        leaves.resize(num_leaves, padding_leaf);
         Self {
             leaves,
             digests,
@@ -231,15 +392,38 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
         );
     }
 
-
     pub fn get(&self, i: usize) -> &[F] {
         &self.leaves[i]
     }
 
+    /// The number of leaves of the tree, even if `leaves` has been left out.
+    pub fn num_leaves(&self) -> usize {
+        self.digests.len() / 2 + self.cap.len()
+    }
+
+    /// Serializes the tree as described in `Write::write_merkle_tree`, with its leaves only if
+    /// `include_leaves`; the digests are enough to `prove`, e.g. for a light client.
+    pub fn to_bytes(&self, include_leaves: bool) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_merkle_tree(self, include_leaves)
+            .expect("The leaves of a Merkle tree must have the same length.");
+        buffer
+    }
+
+    /// Deserializes a tree encoded by `to_bytes`, failing on trailing bytes. The digests aren't
+    /// checked against the leaves.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let mut bytes = bytes.as_ref();
+        let tree = bytes.read_merkle_tree().map_err(anyhow::Error::msg)?;
+        anyhow::ensure!(bytes.is_empty(), "Trailing bytes after the Merkle tree");
+        Ok(tree)
+    }
+
     /// Create a Merkle proof from a leaf index.
     pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
         let cap_height = log2_strict(self.cap.len());
-        let num_layers = log2_strict(self.leaves.len()) - cap_height;
+        let num_layers = log2_strict(self.num_leaves()) - cap_height;
         debug_assert_eq!(leaf_index >> (cap_height + num_layers), 0);
 
         let digest_tree = {
@@ -281,6 +465,7 @@ mod tests {
 
     use super::*;
     use crate::field::extension::Extendable;
+    use crate::field::types::Sample;
     use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -347,4 +532,69 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_merkle_tree_with_padding() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let padding_leaf = F::rand_vec(7);
+        for (num_leaves, cap_height) in [(0, 0), (1, 2), (5, 0), (5, 1), (13, 3), (16, 2), (16, 4)]
+        {
+            let leaves = random_data::<F>(num_leaves, 7);
+            let tree = MerkleTree::<F, H>::new_with_padding(
+                leaves.clone(),
+                16,
+                padding_leaf.clone(),
+                cap_height,
+            );
+            let mut padded = leaves;
+            padded.resize(16, padding_leaf.clone());
+            let expected = MerkleTree::<F, H>::new(padded.clone(), cap_height);
+            assert_eq!(tree.leaves, expected.leaves);
+            assert_eq!(tree.digests, expected.digests);
+            assert_eq!(tree.cap, expected.cap);
+            for (i, leaf) in padded.into_iter().enumerate() {
+                verify_merkle_proof_to_cap(leaf, i, &tree.cap, &tree.prove(i))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialization() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(16, 7), 1);
+        let bytes = tree.to_bytes(true);
+        let read = MerkleTree::<F, H>::from_bytes(&bytes)?;
+        assert_eq!(read.leaves, tree.leaves);
+        assert_eq!(read.digests, tree.digests);
+        assert_eq!(read.cap, tree.cap);
+        assert_eq!(read.to_bytes(true), bytes);
+
+        // Without its leaves, the tree can still prove them.
+        let light = MerkleTree::<F, H>::from_bytes(tree.to_bytes(false))?;
+        assert!(light.leaves.is_empty());
+        assert_eq!(light.num_leaves(), 16);
+        for (i, leaf) in tree.leaves.iter().enumerate() {
+            assert_eq!(light.prove(i), tree.prove(i));
+            verify_merkle_proof_to_cap(leaf.clone(), i, &light.cap, &light.prove(i))?;
+        }
+
+        let cap_bytes = tree.cap.to_bytes();
+        assert_eq!(cap_bytes.len(), 4 + 2 * <H as Hasher<F>>::HASH_SIZE);
+        assert_eq!(MerkleCap::<F, H>::from_bytes(&cap_bytes)?, tree.cap);
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(MerkleTree::<F, H>::from_bytes(&trailing).is_err());
+        assert!(MerkleCap::<F, H>::from_bytes(&cap_bytes[..cap_bytes.len() - 1]).is_err());
+        Ok(())
+    }
 }
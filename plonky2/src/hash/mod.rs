@@ -1,4 +1,6 @@
 mod arch;
+pub mod duplex;
+pub mod hash_chain;
 pub mod hash_types;
 pub mod hashing;
 pub mod keccak;
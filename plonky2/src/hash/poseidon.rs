@@ -11,7 +11,7 @@ use crate::field::types::{Field, PrimeField64};
 use crate::gates::gate::Gate;
 use crate::gates::poseidon::PoseidonGate;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
-use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation, SPONGE_WIDTH};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
@@ -256,14 +256,24 @@ pub trait Poseidon: PrimeField64 {
         result
     }
 
-    /// Same as `mds_layer` for field extensions of `Self`.
+    /// Same as `mds_layer` for field extensions of `Self`. The entries of the MDS matrix are in
+    /// `Self`, so it is applied to each coordinate of the state with `mds_layer`, which avoids
+    /// multiplying extension elements.
     fn mds_layer_field<F: FieldExtension<D, BaseField = Self>, const D: usize>(
         state: &[F; WIDTH],
     ) -> [F; WIDTH] {
-        let mut result = [F::ZERO; WIDTH];
+        let mut coords = [[Self::ZERO; WIDTH]; D];
+        for i in 0..WIDTH {
+            let x = state[i].to_basefield_array();
+            for j in 0..D {
+                coords[j][i] = x[j];
+            }
+        }
+        let coords = coords.map(|c| Self::mds_layer(&c));
 
-        for r in 0..WIDTH {
-            result[r] = Self::mds_row_shf_field(r, state);
+        let mut result = [F::ZERO; WIDTH];
+        for i in 0..WIDTH {
+            result[i] = F::from_basefield_array(core::array::from_fn(|j| coords[j][i]));
         }
 
         result
@@ -481,9 +491,14 @@ pub trait Poseidon: PrimeField64 {
         for i in 0..12 {
             if i < WIDTH {
                 let round_constant = ALL_ROUND_CONSTANTS[i + WIDTH * round_ctr];
+                #[cfg(not(feature = "forbid-unsafe"))]
                 unsafe {
                     state[i] = state[i].add_canonical_u64(round_constant);
                 }
+                #[cfg(feature = "forbid-unsafe")]
+                {
+                    state[i] = state[i].add_canonical_u64(round_constant);
+                }
             }
         }
     }
@@ -583,9 +598,14 @@ pub trait Poseidon: PrimeField64 {
 
         for i in 0..N_PARTIAL_ROUNDS {
             state[0] = Self::sbox_monomial(state[0]);
+            #[cfg(not(feature = "forbid-unsafe"))]
             unsafe {
                 state[0] = state[0].add_canonical_u64(Self::FAST_PARTIAL_ROUND_CONSTANTS[i]);
             }
+            #[cfg(feature = "forbid-unsafe")]
+            {
+                state[0] = state[0].add_canonical_u64(Self::FAST_PARTIAL_ROUND_CONSTANTS[i]);
+            }
             *state = Self::mds_partial_layer_fast(state, i);
         }
         *round_ctr += N_PARTIAL_ROUNDS;
@@ -640,7 +660,7 @@ impl<F: RichField> PlonkyPermutation<F> for PoseidonPermutation {
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PoseidonHash;
 impl<F: RichField> Hasher<F> for PoseidonHash {
-    const HASH_SIZE: usize = 4 * 8;
+    const HASH_SIZE: usize = NUM_HASH_OUT_ELTS * 8;
     type Hash = HashOut<F>;
     type Permutation = PoseidonPermutation;
 
@@ -694,7 +714,9 @@ impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
     where
         F: RichField + Extendable<D>,
     {
-        HashOutTarget::from_vec(builder.hash_n_to_m_no_pad::<PoseidonHash>(inputs, 4))
+        HashOutTarget::from_vec(
+            builder.hash_n_to_m_no_pad::<PoseidonHash>(inputs, NUM_HASH_OUT_ELTS),
+        )
     }
 }
 
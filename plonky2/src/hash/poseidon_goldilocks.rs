@@ -5,6 +5,12 @@
 //! repository.
 
 use crate::field::goldilocks_field::GoldilocksField;
+#[cfg(not(all(
+    target_arch = "aarch64",
+    target_feature = "neon",
+    not(feature = "forbid-unsafe")
+)))]
+use crate::field::types::{Field, PrimeField64};
 use crate::hash::poseidon::{Poseidon, N_PARTIAL_ROUNDS};
 
 #[rustfmt::skip]
@@ -18,6 +24,7 @@ impl Poseidon for GoldilocksField {
     //  - FAST_PARTIAL_ROUND_VS
     //  - FAST_PARTIAL_ROUND_W_HATS
     //  - FAST_PARTIAL_ROUND_INITIAL_MATRIX
+    //  - MDS_FREQ_BLOCK_ONE, MDS_FREQ_BLOCK_TWO and MDS_FREQ_BLOCK_THREE
     const MDS_MATRIX_CIRC: [u64; 12] = [17, 15, 41, 16, 2, 28, 13, 13, 39, 18, 34, 20];
     const MDS_MATRIX_DIAG: [u64; 12] = [8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 
@@ -251,7 +258,7 @@ impl Poseidon for GoldilocksField {
     //     }
     // }
 
-    #[cfg(all(target_arch="aarch64", target_feature="neon"))]
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon", not(feature = "forbid-unsafe")))]
     #[inline(always)]
     fn sbox_layer(state: &mut [Self; 12]) {
         unsafe {
@@ -259,20 +266,178 @@ impl Poseidon for GoldilocksField {
         }
     }
 
-    #[cfg(all(target_arch="aarch64", target_feature="neon"))]
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon", not(feature = "forbid-unsafe")))]
     #[inline(always)]
     fn mds_layer(state: &[Self; 12]) -> [Self; 12] {
         unsafe {
             crate::hash::arch::aarch64::poseidon_goldilocks_neon::mds_layer(state)
         }
     }
+
+    #[cfg(not(all(target_arch = "aarch64", target_feature = "neon", not(feature = "forbid-unsafe"))))]
+    #[inline(always)]
+    fn mds_layer(state: &[Self; 12]) -> [Self; 12] {
+        // The circulant part is applied separately to the low and high 32-bit halves of the state,
+        // so that the frequency-domain computation fits in `i64`s.
+        let mut state_l = [0u64; 12];
+        let mut state_h = [0u64; 12];
+        for r in 0..12 {
+            let s = state[r].to_noncanonical_u64();
+            state_h[r] = s >> 32;
+            state_l[r] = (s as u32) as u64;
+        }
+
+        let state_l = mds_freq::mds_multiply_freq(state_l);
+        let state_h = mds_freq::mds_multiply_freq(state_h);
+
+        let mut result = [Self::ZERO; 12];
+        for r in 0..12 {
+            let s = state[r].to_noncanonical_u64() as u128;
+            let sum = (state_l[r] as u128)
+                + ((state_h[r] as u128) << 32)
+                + s * (Self::MDS_MATRIX_DIAG[r] as u128);
+            result[r] = Self::from_noncanonical_u96((sum as u64, (sum >> 64) as u32));
+        }
+        result
+    }
+}
+
+// Fast multiplication by the circulant part of the MDS matrix, as in Winterfell. The state is split
+// into three interleaved vectors of length 4, each transformed with a real FFT; the size 3 FFTs,
+// the products with the FFT of `MDS_MATRIX_CIRC` and the inverse transforms are folded into the
+// three blocks below, whose constants are small integers with the normalization already applied.
+// For inputs of at most 32 bits, every intermediate value fits in an `i64` and the outputs are
+// the exact (unreduced) products.
+#[cfg(not(all(
+    target_arch = "aarch64",
+    target_feature = "neon",
+    not(feature = "forbid-unsafe")
+)))]
+mod mds_freq {
+    const MDS_FREQ_BLOCK_ONE: [i64; 3] = [16, 32, 16];
+    const MDS_FREQ_BLOCK_TWO: [(i64, i64); 3] = [(2, -1), (-4, 1), (16, 1)];
+    const MDS_FREQ_BLOCK_THREE: [i64; 3] = [-1, -8, 2];
+
+    #[inline(always)]
+    pub(super) fn mds_multiply_freq(state: [u64; 12]) -> [u64; 12] {
+        let [s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11] = state;
+
+        let (u0, u1, u2) = fft4_real([s0, s3, s6, s9]);
+        let (u4, u5, u6) = fft4_real([s1, s4, s7, s10]);
+        let (u8, u9, u10) = fft4_real([s2, s5, s8, s11]);
+
+        let [v0, v4, v8] = block1([u0, u4, u8], MDS_FREQ_BLOCK_ONE);
+        let [v1, v5, v9] = block2([u1, u5, u9], MDS_FREQ_BLOCK_TWO);
+        let [v2, v6, v10] = block3([u2, u6, u10], MDS_FREQ_BLOCK_THREE);
+        // The fourth block is the complex conjugate of the second one, so with a real FFT it is
+        // redundant.
+
+        let [s0, s3, s6, s9] = ifft4_real_unreduced((v0, v1, v2));
+        let [s1, s4, s7, s10] = ifft4_real_unreduced((v4, v5, v6));
+        let [s2, s5, s8, s11] = ifft4_real_unreduced((v8, v9, v10));
+
+        [s0, s1, s2, s3, s4, s5, s6, s7, s8, s9, s10, s11]
+    }
+
+    #[inline(always)]
+    fn fft2_real(x: [u64; 2]) -> [i64; 2] {
+        [(x[0] as i64 + x[1] as i64), (x[0] as i64 - x[1] as i64)]
+    }
+
+    #[inline(always)]
+    fn ifft2_real_unreduced(y: [i64; 2]) -> [u64; 2] {
+        [(y[0] + y[1]) as u64, (y[0] - y[1]) as u64]
+    }
+
+    #[inline(always)]
+    fn fft4_real(x: [u64; 4]) -> (i64, (i64, i64), i64) {
+        let [z0, z2] = fft2_real([x[0], x[2]]);
+        let [z1, z3] = fft2_real([x[1], x[3]]);
+        let y0 = z0 + z1;
+        let y1 = (z2, -z3);
+        let y2 = z0 - z1;
+        (y0, y1, y2)
+    }
+
+    #[inline(always)]
+    fn ifft4_real_unreduced(y: (i64, (i64, i64), i64)) -> [u64; 4] {
+        let z0 = y.0 + y.2;
+        let z1 = y.0 - y.2;
+        let z2 = y.1 .0;
+        let z3 = -y.1 .1;
+
+        let [x0, x2] = ifft2_real_unreduced([z0, z2]);
+        let [x1, x3] = ifft2_real_unreduced([z1, z3]);
+
+        [x0, x1, x2, x3]
+    }
+
+    #[inline(always)]
+    fn block1(x: [i64; 3], y: [i64; 3]) -> [i64; 3] {
+        let [x0, x1, x2] = x;
+        let [y0, y1, y2] = y;
+        let z0 = x0 * y0 + x1 * y2 + x2 * y1;
+        let z1 = x0 * y1 + x1 * y0 + x2 * y2;
+        let z2 = x0 * y2 + x1 * y1 + x2 * y0;
+        [z0, z1, z2]
+    }
+
+    #[inline(always)]
+    fn block2(x: [(i64, i64); 3], y: [(i64, i64); 3]) -> [(i64, i64); 3] {
+        let [(x0r, x0i), (x1r, x1i), (x2r, x2i)] = x;
+        let [(y0r, y0i), (y1r, y1i), (y2r, y2i)] = y;
+        let x0s = x0r + x0i;
+        let x1s = x1r + x1i;
+        let x2s = x2r + x2i;
+        let y0s = y0r + y0i;
+        let y1s = y1r + y1i;
+        let y2s = y2r + y2i;
+
+        // Compute x0 y0 - i x1 y2 - i x2 y1, using Karatsuba for the complex products.
+        let m0 = (x0r * y0r, x0i * y0i);
+        let m1 = (x1r * y2r, x1i * y2i);
+        let m2 = (x2r * y1r, x2i * y1i);
+        let z0r = (m0.0 - m0.1) + (x1s * y2s - m1.0 - m1.1) + (x2s * y1s - m2.0 - m2.1);
+        let z0i = (x0s * y0s - m0.0 - m0.1) + (-m1.0 + m1.1) + (-m2.0 + m2.1);
+        let z0 = (z0r, z0i);
+
+        // Compute x0 y1 + x1 y0 - i x2 y2.
+        let m0 = (x0r * y1r, x0i * y1i);
+        let m1 = (x1r * y0r, x1i * y0i);
+        let m2 = (x2r * y2r, x2i * y2i);
+        let z1r = (m0.0 - m0.1) + (m1.0 - m1.1) + (x2s * y2s - m2.0 - m2.1);
+        let z1i = (x0s * y1s - m0.0 - m0.1) + (x1s * y0s - m1.0 - m1.1) + (-m2.0 + m2.1);
+        let z1 = (z1r, z1i);
+
+        // Compute x0 y2 + x1 y1 + x2 y0.
+        let m0 = (x0r * y2r, x0i * y2i);
+        let m1 = (x1r * y1r, x1i * y1i);
+        let m2 = (x2r * y0r, x2i * y0i);
+        let z2r = (m0.0 - m0.1) + (m1.0 - m1.1) + (m2.0 - m2.1);
+        let z2i = (x0s * y2s - m0.0 - m0.1) + (x1s * y1s - m1.0 - m1.1) + (x2s * y0s - m2.0 - m2.1);
+        let z2 = (z2r, z2i);
+
+        [z0, z1, z2]
+    }
+
+    #[inline(always)]
+    fn block3(x: [i64; 3], y: [i64; 3]) -> [i64; 3] {
+        let [x0, x1, x2] = x;
+        let [y0, y1, y2] = y;
+        let z0 = x0 * y0 - x1 * y2 - x2 * y1;
+        let z1 = x0 * y1 + x1 * y0 - x2 * y2;
+        let z2 = x0 * y2 + x1 * y1 + x2 * y0;
+        [z0, z1, z2]
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::field::extension::Extendable;
     use crate::field::goldilocks_field::GoldilocksField as F;
-    use crate::field::types::{Field, PrimeField64};
+    use crate::field::types::{Field, PrimeField64, Sample};
     use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
+    use crate::hash::poseidon::Poseidon;
 
     #[test]
     fn test_vectors() {
@@ -316,4 +481,22 @@ mod tests {
     fn consistency() {
         check_consistency::<F>();
     }
+
+    #[test]
+    fn mds_layer() {
+        for state in [[F::NEG_ONE; 12], F::rand_array()] {
+            let state_u64 = state.map(|x| x.to_noncanonical_u64());
+            let expected: [F; 12] = core::array::from_fn(|r| {
+                let sum = <F as Poseidon>::mds_row_shf(r, &state_u64);
+                F::from_noncanonical_u96((sum as u64, (sum >> 64) as u32))
+            });
+            assert_eq!(<F as Poseidon>::mds_layer(&state), expected);
+        }
+
+        type FE = <F as Extendable<2>>::Extension;
+        let state = FE::rand_array::<12>();
+        let expected: [FE; 12] =
+            core::array::from_fn(|r| <F as Poseidon>::mds_row_shf_field::<FE, 2>(r, &state));
+        assert_eq!(<F as Poseidon>::mds_layer_field::<FE, 2>(&state), expected);
+    }
 }
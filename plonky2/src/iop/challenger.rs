@@ -8,6 +8,7 @@ use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
+use crate::iop::transcript_log::{TranscriptEvent, TranscriptLog};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericHashOut, Hasher};
 
@@ -17,6 +18,7 @@ pub struct Challenger<F: RichField, H: Hasher<F>> {
     pub(crate) sponge_state: [F; SPONGE_WIDTH],
     pub(crate) input_buffer: Vec<F>,
     output_buffer: Vec<F>,
+    transcript_log: Option<TranscriptLog<F>>,
     _phantom: PhantomData<H>,
 }
 
@@ -34,14 +36,32 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             sponge_state: [F::ZERO; SPONGE_WIDTH],
             input_buffer: Vec::with_capacity(SPONGE_RATE),
             output_buffer: Vec::with_capacity(SPONGE_RATE),
+            transcript_log: None,
             _phantom: Default::default(),
         }
     }
 
+    /// Creates a challenger which records its transcript; see `take_transcript_log`.
+    pub fn new_with_log() -> Challenger<F, H> {
+        Challenger {
+            transcript_log: Some(TranscriptLog::new()),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the transcript recorded so far, if this challenger was created by `new_with_log`,
+    /// and stops recording.
+    pub fn take_transcript_log(&mut self) -> Option<TranscriptLog<F>> {
+        self.transcript_log.take()
+    }
+
     pub fn observe_element(&mut self, element: F) {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
 
+        if let Some(log) = &mut self.transcript_log {
+            log.push(TranscriptEvent::Observe(element));
+        }
         self.input_buffer.push(element);
 
         if self.input_buffer.len() == SPONGE_RATE {
@@ -88,9 +108,14 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             self.duplexing();
         }
 
-        self.output_buffer
+        let challenge = self
+            .output_buffer
             .pop()
-            .expect("Output buffer should be non-empty")
+            .expect("Output buffer should be non-empty");
+        if let Some(log) = &mut self.transcript_log {
+            log.push(TranscriptEvent::Challenge(challenge));
+        }
+        challenge
     }
 
     pub fn get_n_challenges(&mut self, n: usize) -> Vec<F> {
@@ -368,7 +393,8 @@ mod tests {
         }
         let circuit = builder.build::<C>();
         let inputs = PartialWitness::new();
-        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+        let witness =
+            generate_partial_witness(inputs, &circuit.prover_only, &circuit.common).unwrap();
         let recursive_output_values_per_round: Vec<Vec<F>> = recursive_outputs_per_round
             .iter()
             .map(|outputs| witness.get_targets(outputs))
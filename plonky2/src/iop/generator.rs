@@ -1,11 +1,41 @@
-use alloc::vec;
+//! Witness generators, which compute the values of targets from the values of other targets when
+//! a witness is generated.
+//!
+//! Most circuits only need the generators which gadgets add for them, but a circuit can also take
+//! nondeterministic advice, i.e. values which the prover computes outside the circuit and the
+//! circuit then checks, which is often much cheaper than computing them in the circuit. Simple
+//! advice can be computed by a closure passed to `CircuitBuilder::add_generator`:
+//!
+//! ```ignore
+//! // Proves that `x` is nonzero, by checking that it has an inverse.
+//! let inverse = builder.add_virtual_target();
+//! builder.add_generator(vec![x], vec![inverse], |values| vec![values[0].inverse()]);
+//! let product = builder.mul(x, inverse);
+//! builder.assert_one(product);
+//! ```
+//!
+//! Generators which need more control, e.g. which read extension targets or only set some of their
+//! outputs, implement `SimpleGenerator` and are added with `CircuitBuilder::add_simple_generator`.
+//! A generator must set each of its outputs at most once, and to the same value whenever it is
+//! given the same dependencies; a value which doesn't satisfy the circuit's constraints makes
+//! proving fail.
+
+use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::fmt::Debug;
 use core::marker::PhantomData;
 
+use anyhow::{anyhow, Result};
+use hashbrown::hash_map::Entry;
+use hashbrown::HashMap;
+use log::debug;
+
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
 use crate::hash::hash_types::RichField;
+use crate::hash::hashing::SPONGE_WIDTH;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::iop::wire::Wire;
@@ -15,7 +45,12 @@ use crate::plonk::config::GenericConfig;
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
 /// given set of generators.
-pub(crate) fn generate_partial_witness<
+///
+/// Returns an error if some generators could never run to completion, which happens when some of
+/// the targets they depend on are never set, either because they were not provided as inputs or
+/// because generators depend on each other cyclically. The error lists the generators of a cycle if
+/// there is one, otherwise the stuck generators, along with their unset dependencies.
+pub fn generate_partial_witness<
     'a,
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -24,7 +59,154 @@ pub(crate) fn generate_partial_witness<
     inputs: PartialWitness<F>,
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, D>,
-) -> PartitionWitness<'a, F> {
+) -> Result<PartitionWitness<'a, F>> {
+    let (witness, stuck_generators) = run_generators(inputs, prover_data, common_data)?;
+    if stuck_generators.is_empty() {
+        return Ok(witness);
+    }
+
+    let generators = &prover_data.generators;
+    let describe = |i: usize| {
+        let missing = unset_targets(&witness, generators[i].watch_list());
+        format!("\n- {:?} is missing {:?}", generators[i], missing)
+    };
+    if let Some(cycle) = find_generator_cycle(&witness, generators, &stuck_generators) {
+        return Err(anyhow!(
+            "{} generators weren't run; these generators depend on each other cyclically, each \
+             missing a target set by the next one:{}",
+            stuck_generators.len(),
+            cycle.into_iter().map(describe).collect::<String>()
+        ));
+    }
+
+    let mut details = String::new();
+    for &i in stuck_generators.iter().take(MAX_REPORTED_GENERATORS) {
+        details += &describe(i);
+    }
+    if stuck_generators.len() > MAX_REPORTED_GENERATORS {
+        details += &format!(
+            "\n- ... and {} more",
+            stuck_generators.len() - MAX_REPORTED_GENERATORS
+        );
+    }
+    Err(anyhow!(
+        "{} generators weren't run; some targets were never set, or generators depend on each \
+         other cyclically:{}",
+        stuck_generators.len(),
+        details
+    ))
+}
+
+/// Runs the witness generators on the given inputs, and returns the targets which the generators
+/// that could not complete were still waiting on. An empty result means witness generation
+/// succeeds for these inputs.
+pub fn ungenerated_targets<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<Vec<Target>> {
+    let (witness, stuck_generators) = run_generators(inputs, prover_data, common_data)?;
+    let mut missing = Vec::new();
+    for i in stuck_generators {
+        for target in unset_targets(&witness, prover_data.generators[i].watch_list()) {
+            if !missing.contains(&target) {
+                missing.push(target);
+            }
+        }
+    }
+    Ok(missing)
+}
+
+/// The maximum number of stuck generators described in a witness generation error.
+const MAX_REPORTED_GENERATORS: usize = 10;
+
+fn unset_targets<F: Field>(witness: &PartitionWitness<F>, targets: Vec<Target>) -> Vec<Target> {
+    targets
+        .into_iter()
+        .filter(|&t| witness.try_get_target(t).is_none())
+        .collect()
+}
+
+/// Looks for generators among `stuck_generators` which wait on each other in a cycle, each missing a
+/// target set by the next one, and returns their indices in that order. Only the outputs which
+/// generators declare through `WitnessGenerator::outputs` are known, so a cycle through a generator
+/// which doesn't declare its outputs goes unnoticed.
+fn find_generator_cycle<F: Field>(
+    witness: &PartitionWitness<F>,
+    generators: &[Box<dyn WitnessGenerator<F>>],
+    stuck_generators: &[usize],
+) -> Option<Vec<usize>> {
+    let rep = |target: Target| witness.representative_map[witness.target_index(target)];
+    let missing = stuck_generators
+        .iter()
+        .map(|&i| unset_targets(witness, generators[i].watch_list()))
+        .collect::<Vec<_>>();
+
+    // The stuck generators setting each partition, by representative index.
+    let mut setters: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (node, &i) in stuck_generators.iter().enumerate() {
+        for target in generators[i].outputs() {
+            setters.entry(rep(target)).or_default().push(node);
+        }
+    }
+    let waits_on = missing
+        .iter()
+        .map(|targets| {
+            targets
+                .iter()
+                .flat_map(|&target| setters.get(&rep(target)).into_iter().flatten().copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // A depth-first search, in which reaching a generator still on the path closes a cycle.
+    let mut visited = vec![false; stuck_generators.len()];
+    let mut on_path = vec![false; stuck_generators.len()];
+    for start in 0..stuck_generators.len() {
+        if visited[start] {
+            continue;
+        }
+        let mut path = vec![(start, 0)];
+        visited[start] = true;
+        on_path[start] = true;
+        while let Some((node, next_edge)) = path.last_mut() {
+            let node = *node;
+            let Some(&next) = waits_on[node].get(*next_edge) else {
+                on_path[node] = false;
+                path.pop();
+                continue;
+            };
+            *next_edge += 1;
+            if on_path[next] {
+                let cycle_start = path.iter().position(|&(n, _)| n == next).unwrap();
+                return Some(
+                    path[cycle_start..]
+                        .iter()
+                        .map(|&(n, _)| stuck_generators[n])
+                        .collect(),
+                );
+            }
+            if !visited[next] {
+                visited[next] = true;
+                on_path[next] = true;
+                path.push((next, 0));
+            }
+        }
+    }
+    None
+}
+
+/// Keeps running generators until no more progress can be made. Returns the resulting witness,
+/// along with the indices of generators which didn't finish.
+fn run_generators<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+) -> Result<(PartitionWitness<'a, F>, Vec<usize>)> {
     let config = &common_data.config;
     let generators = &prover_data.generators;
     let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
@@ -35,8 +217,8 @@ pub(crate) fn generate_partial_witness<
         &prover_data.representative_map,
     );
 
-    for (t, v) in inputs.target_values.into_iter() {
-        witness.set_target(t, v);
+    for (&t, &v) in &inputs.target_values {
+        witness.set_target_returning_rep(t, v)?;
     }
 
     // Build a list of "pending" generators which are queued to be run. Initially, all generators
@@ -45,12 +227,24 @@ pub(crate) fn generate_partial_witness<
 
     // We also track a list of "expired" generators which have already returned false.
     let mut generator_is_expired = vec![false; generators.len()];
-    let mut remaining_generators = generators.len();
 
     let mut buffer = GeneratedValues::empty();
 
+    // Every round after the first is triggered by at least one newly populated partition, so there
+    // can't be more rounds than partitions. Exceeding this indicates a misbehaving generator.
+    let max_rounds = witness.values.len() + 1;
+    let mut rounds = 0;
+
     // Keep running generators until we fail to make progress.
     while !pending_generator_indices.is_empty() {
+        rounds += 1;
+        if rounds > max_rounds {
+            return Err(anyhow!(
+                "Witness generation did not converge after {} rounds",
+                max_rounds
+            ));
+        }
+
         let mut next_pending_generator_indices = Vec::new();
 
         for &generator_idx in &pending_generator_indices {
@@ -61,18 +255,14 @@ pub(crate) fn generate_partial_witness<
             let finished = generators[generator_idx].run(&witness, &mut buffer);
             if finished {
                 generator_is_expired[generator_idx] = true;
-                remaining_generators -= 1;
             }
 
-            // Merge any generated values into our witness, and get a list of newly-populated
-            // targets' representatives.
-            let new_target_reps = buffer
-                .target_values
-                .drain(..)
-                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
-
-            // Enqueue unfinished generators that were watching one of the newly populated targets.
-            for watch in new_target_reps {
+            // Merge any generated values into our witness, and enqueue unfinished generators that
+            // were watching one of the newly populated targets.
+            for (t, v) in buffer.target_values.drain(..) {
+                let Some(watch) = witness.set_target_returning_rep(t, v)? else {
+                    continue;
+                };
                 let opt_watchers = generator_indices_by_watches.get(&watch);
                 if let Some(watchers) = opt_watchers {
                     for &watching_generator_idx in watchers {
@@ -87,13 +277,16 @@ pub(crate) fn generate_partial_witness<
         pending_generator_indices = next_pending_generator_indices;
     }
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
+    debug!(
+        "{} Poseidon permutations computed, {} copied from earlier gates",
+        buffer.poseidon_cache.misses(),
+        buffer.poseidon_cache.hits()
     );
 
-    witness
+    let stuck_generators = (0..generators.len())
+        .filter(|&i| !generator_is_expired[i])
+        .collect();
+    Ok((witness, stuck_generators))
 }
 
 /// A generator participates in the generation of the witness.
@@ -106,17 +299,67 @@ pub trait WitnessGenerator<F: Field>: 'static + Send + Sync + Debug {
     /// flag is true, the generator will never be run again, otherwise it will be queued for another
     /// run next time a target in its watch list is populated.
     fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool;
+
+    /// Targets which this generator may set. This is only used to diagnose generators which depend
+    /// on each other cyclically, so generators need not declare their outputs.
+    fn outputs(&self) -> Vec<Target> {
+        Vec::new()
+    }
 }
 
 /// Values generated by a generator invocation.
 #[derive(Debug)]
 pub struct GeneratedValues<F: Field> {
     pub(crate) target_values: Vec<(Target, F)>,
+    /// Permutations already computed by Poseidon gates during this witness generation. The buffer
+    /// passed to generators is reused for a whole witness generation, so this persists across
+    /// generators.
+    pub(crate) poseidon_cache: PoseidonCache<F>,
 }
 
 impl<F: Field> From<Vec<(Target, F)>> for GeneratedValues<F> {
     fn from(target_values: Vec<(Target, F)>) -> Self {
-        Self { target_values }
+        Self {
+            target_values,
+            poseidon_cache: PoseidonCache::default(),
+        }
+    }
+}
+
+/// The rows of the Poseidon gates whose permutations were computed, by input state (after any
+/// swap). Aggregation circuits permute the same states many times, e.g. when hashing the same inner
+/// Merkle caps for each proof of a batch; a Poseidon gate whose input state is already here copies
+/// the wires of the earlier gate instead of computing the permutation again.
+#[derive(Debug, Default)]
+pub(crate) struct PoseidonCache<F: Field> {
+    rows: HashMap<[F; SPONGE_WIDTH], usize>,
+    hits: usize,
+}
+
+impl<F: Field> PoseidonCache<F> {
+    /// Returns the row of an earlier gate which permuted `state`, or records that the gate at
+    /// `row` permutes it and returns `None`.
+    pub(crate) fn get_or_insert(&mut self, state: [F; SPONGE_WIDTH], row: usize) -> Option<usize> {
+        match self.rows.entry(state) {
+            Entry::Occupied(entry) => {
+                self.hits += 1;
+                Some(*entry.get())
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(row);
+                None
+            }
+        }
+    }
+
+    /// The number of permutations which were copied rather than computed.
+    pub(crate) fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of distinct permutations computed.
+    pub(crate) fn misses(&self) -> usize {
+        self.rows.len()
     }
 }
 
@@ -156,12 +399,21 @@ impl<F: Field> GeneratedValues<F> {
     }
 }
 
-/// A generator which runs once after a list of dependencies is present in the witness.
+/// A generator which runs once after a list of dependencies is present in the witness. This is the
+/// trait to implement for custom advice; see the module documentation.
 pub trait SimpleGenerator<F: Field>: 'static + Send + Sync + Debug {
+    /// The targets which must be set before this generator runs.
     fn dependencies(&self) -> Vec<Target>;
 
+    /// Reads the values of the dependencies from `witness`, and writes the values this generator
+    /// computes to `out_buffer`, e.g. with `WitnessWrite::set_target`.
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>);
 
+    /// The targets which this generator may set; see `WitnessGenerator::outputs`.
+    fn outputs(&self) -> Vec<Target> {
+        Vec::new()
+    }
+
     fn adapter(self) -> SimpleGeneratorAdapter<F, Self>
     where
         Self: Sized,
@@ -192,6 +444,53 @@ impl<F: Field, SG: SimpleGenerator<F>> WitnessGenerator<F> for SimpleGeneratorAd
             false
         }
     }
+
+    fn outputs(&self) -> Vec<Target> {
+        self.inner.outputs()
+    }
+}
+
+/// A generator which sets its outputs to a function of its dependencies. See
+/// `CircuitBuilder::add_generator`.
+pub(crate) struct FnGenerator<F: Field> {
+    pub(crate) dependencies: Vec<Target>,
+    pub(crate) outputs: Vec<Target>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) compute: Box<dyn Fn(&[F]) -> Vec<F> + Send + Sync>,
+}
+
+impl<F: Field> Debug for FnGenerator<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FnGenerator")
+            .field("dependencies", &self.dependencies)
+            .field("outputs", &self.outputs)
+            .finish()
+    }
+}
+
+impl<F: Field> SimpleGenerator<F> for FnGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.dependencies.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let values = witness.get_targets(&self.dependencies);
+        let outputs = (self.compute)(&values);
+        assert_eq!(
+            outputs.len(),
+            self.outputs.len(),
+            "Generator computed {} values for {} outputs",
+            outputs.len(),
+            self.outputs.len()
+        );
+        for (&target, value) in self.outputs.iter().zip(outputs) {
+            out_buffer.set_target(target, value);
+        }
+    }
+
+    fn outputs(&self) -> Vec<Target> {
+        self.outputs.clone()
+    }
 }
 
 /// A generator which copies one wire to another.
@@ -210,6 +509,10 @@ impl<F: Field> SimpleGenerator<F> for CopyGenerator {
         let value = witness.get_target(self.src);
         out_buffer.set_target(self.dst, value);
     }
+
+    fn outputs(&self) -> Vec<Target> {
+        vec![self.dst]
+    }
 }
 
 /// A generator for including a random value
@@ -278,3 +581,36 @@ impl<F: Field> SimpleGenerator<F> for ConstantGenerator<F> {
         out_buffer.set_target(Target::wire(self.row, self.wire_index), self.constant);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_generator_cycle() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let [x, y, z] = [(); 3].map(|_| builder.add_virtual_target());
+        builder.add_generator(vec![x, y], vec![z], |values| vec![values[0] + values[1]]);
+        builder.add_generator(vec![z], vec![y], |values| vec![values[0].double()]);
+        builder.register_public_input(z);
+        let data = builder.build::<C>();
+
+        // `y` and `z` wait on each other, whatever the value of `x`.
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+        let err = data.prove(pw).unwrap_err().to_string();
+        assert!(err.contains("cyclically"), "{}", err);
+        assert_eq!(err.matches("\n- ").count(), 2, "{}", err);
+        Ok(())
+    }
+}
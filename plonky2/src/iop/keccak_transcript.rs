@@ -0,0 +1,196 @@
+//! A Fiat-Shamir transcript based on Keccak, for proofs whose challenges must be cheap to derive
+//! outside of a field-friendly setting, e.g. by a verifier running on the EVM.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use keccak_hash::keccak;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::fri::prover::{find_pow_witness, keccak_proof_of_work};
+use crate::fri::{FriConfig, FriPowHash};
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::transcript::{NativeTranscript, Transcript};
+use crate::plonk::config::{GenericHashOut, Hasher};
+
+/// A transcript which absorbs prover messages as bytes and squeezes challenges with Keccak-256.
+///
+/// Field elements are absorbed as little-endian `u64`s, and hashes as their byte encoding. Each
+/// squeeze sets the state to `keccak256(state || inputs)`, starting from a zero state, and yields
+/// two challenges, reduced from the first then the second 16 bytes of the state, read as
+/// little-endian `u128`s. `H` is only used to type the hashes and Merkle caps it observes.
+#[derive(Clone, Debug)]
+pub struct KeccakTranscript<F: RichField, H: Hasher<F>> {
+    state: [u8; 32],
+    input_buffer: Vec<u8>,
+    output_buffer: Vec<F>,
+    _phantom: PhantomData<fn() -> H>,
+}
+
+impl<F: RichField, H: Hasher<F>> KeccakTranscript<F, H> {
+    pub fn new() -> Self {
+        Self {
+            state: [0; 32],
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn observe_bytes(&mut self, bytes: &[u8]) {
+        // Any buffered outputs are now invalid, since they wouldn't reflect this input.
+        self.output_buffer.clear();
+        self.input_buffer.extend_from_slice(bytes);
+    }
+
+    fn squeeze(&mut self) {
+        let mut input = self.state.to_vec();
+        input.append(&mut self.input_buffer);
+        self.state = keccak(input).0;
+
+        let [first, second] = [&self.state[..16], &self.state[16..]]
+            .map(|half| F::from_noncanonical_u128(u128::from_le_bytes(half.try_into().unwrap())));
+        // Challenges are popped from the end of the buffer.
+        self.output_buffer = vec![second, first];
+    }
+
+    fn next_challenge(&mut self) -> F {
+        if !self.input_buffer.is_empty() || self.output_buffer.is_empty() {
+            self.squeeze();
+        }
+        self.output_buffer
+            .pop()
+            .expect("Output buffer should be non-empty")
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> Default for KeccakTranscript<F, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> Transcript<F, D>
+    for KeccakTranscript<F, H>
+{
+    type Element = F;
+    type ExtensionElement = F::Extension;
+    type Hash = H::Hash;
+    type Cap = MerkleCap<F, H>;
+    type Context = ();
+
+    fn observe_element(&mut self, element: F) {
+        self.observe_bytes(&element.canonical_bytes());
+    }
+
+    fn observe_extension_element(&mut self, element: F::Extension) {
+        for x in element.to_basefield_array() {
+            self.observe_bytes(&x.canonical_bytes());
+        }
+    }
+
+    fn observe_hash(&mut self, hash: &H::Hash) {
+        self.observe_bytes(&hash.to_bytes());
+    }
+
+    fn observe_cap(&mut self, cap: &MerkleCap<F, H>) {
+        for hash in &cap.0 {
+            self.observe_bytes(&hash.to_bytes());
+        }
+    }
+
+    fn get_challenge(&mut self, _ctx: &mut ()) -> F {
+        self.next_challenge()
+    }
+
+    fn get_extension_challenge(&mut self, _ctx: &mut ()) -> F::Extension {
+        let mut arr = [F::ZERO; D];
+        arr.iter_mut().for_each(|x| *x = self.next_challenge());
+        F::Extension::from_basefield_array(arr)
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> NativeTranscript<F, H, D>
+    for KeccakTranscript<F, H>
+{
+    fn get_hash(&mut self) -> HashOut<F> {
+        HashOut {
+            elements: [(); 4].map(|_| self.next_challenge()),
+        }
+    }
+
+    fn fri_proof_of_work(&self, config: &FriConfig) -> F {
+        match config.pow_hash {
+            FriPowHash::Field => find_pow_witness(config, |candidate| {
+                let mut transcript = self.clone();
+                Transcript::<F, D>::observe_element(&mut transcript, candidate);
+                transcript.next_challenge()
+            }),
+            FriPowHash::Keccak => keccak_proof_of_work(
+                NativeTranscript::<F, H, D>::get_hash(&mut self.clone()),
+                config,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::challenger::Challenger;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_prove_and_verify_with_keccak_transcript() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let x_squared = builder.square(x);
+        builder.register_public_input(x_squared);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u32(7));
+        let proof = data.prove_with_transcript(pw, &mut KeccakTranscript::<F, H>::new())?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u32(49)]);
+
+        data.verify_with_transcript(proof.clone(), &mut KeccakTranscript::<F, H>::new())?;
+        // The challenges differ from those of the default transcript.
+        assert!(data
+            .verify_with_transcript(proof, &mut Challenger::<F, H>::new())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_challenges_depend_on_inputs() {
+        const D: usize = 2;
+        type F = crate::field::goldilocks_field::GoldilocksField;
+        type H = crate::hash::poseidon::PoseidonHash;
+
+        let mut transcript = KeccakTranscript::<F, H>::new();
+        Transcript::<F, D>::observe_element(&mut transcript, F::ONE);
+        let first = Transcript::<F, D>::get_n_challenges(&mut transcript, &mut (), 3);
+
+        let mut other = KeccakTranscript::<F, H>::new();
+        Transcript::<F, D>::observe_element(&mut other, F::TWO);
+        let second = Transcript::<F, D>::get_n_challenges(&mut other, &mut (), 3);
+
+        assert_ne!(first, second);
+        assert_ne!(first[0], first[1]);
+        assert_ne!(first[1], first[2]);
+    }
+}
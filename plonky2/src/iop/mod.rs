@@ -3,6 +3,10 @@
 pub mod challenger;
 pub mod ext_target;
 pub mod generator;
+pub mod keccak_transcript;
+pub mod observable;
 pub mod target;
+pub mod transcript;
+pub mod transcript_log;
 pub mod wire;
 pub mod witness;
@@ -0,0 +1,249 @@
+//! Values which can be absorbed into a Fiat-Shamir transcript as a whole, e.g. the messages of an
+//! application protocol, so that they are flattened the same way wherever they are observed.
+//!
+//! `Observable` is implemented for field elements and arrays of them, hashes and Merkle caps, and
+//! `ObservableTarget` for their targets; `impl_observable!` implements them for a struct by
+//! observing its fields in order.
+
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// A value which can be observed by a `Challenger`.
+pub trait Observable<F: RichField> {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>);
+}
+
+/// A value which can be observed by a `RecursiveChallenger`, in the same way as the corresponding
+/// `Observable` value is by a `Challenger`.
+pub trait ObservableTarget {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    );
+}
+
+impl<F: RichField> Observable<F> for F {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_element(*self);
+    }
+}
+
+impl<F: RichField> Observable<F> for [F] {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl<F: RichField, const N: usize> Observable<F> for [F; N] {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl<F: RichField> Observable<F> for Vec<F> {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl<F: RichField> Observable<F> for HashOut<F> {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_elements(&self.elements);
+    }
+}
+
+impl<F: RichField, OH: Hasher<F>> Observable<F> for MerkleCap<F, OH> {
+    fn observe<H: Hasher<F>>(&self, challenger: &mut Challenger<F, H>) {
+        challenger.observe_cap(self);
+    }
+}
+
+impl ObservableTarget for Target {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_elements(&[*self]);
+    }
+}
+
+impl ObservableTarget for [Target] {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl<const N: usize> ObservableTarget for [Target; N] {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl ObservableTarget for Vec<Target> {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_elements(self);
+    }
+}
+
+impl ObservableTarget for HashOutTarget {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_hash(self);
+    }
+}
+
+impl ObservableTarget for MerkleCapTarget {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_cap(self);
+    }
+}
+
+impl<const E: usize> ObservableTarget for ExtensionTarget<E> {
+    fn observe<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>(
+        &self,
+        challenger: &mut RecursiveChallenger<F, H, D>,
+    ) {
+        challenger.observe_elements(&self.0);
+    }
+}
+
+/// Implements `Observable<F>` for a struct, observing the given fields in order, e.g.
+///
+/// ```ignore
+/// impl_observable!(GoldilocksField, Vote { epoch, root });
+/// ```
+///
+/// or `ObservableTarget` for a struct of targets, observing its fields in the same order:
+///
+/// ```ignore
+/// impl_observable!(VoteTarget { epoch, root });
+/// ```
+#[macro_export]
+macro_rules! impl_observable {
+    ($field:ty, $ty:ty { $($member:ident),* $(,)? }) => {
+        impl $crate::iop::observable::Observable<$field> for $ty {
+            fn observe<H: $crate::plonk::config::Hasher<$field>>(
+                &self,
+                challenger: &mut $crate::iop::challenger::Challenger<$field, H>,
+            ) {
+                $($crate::iop::observable::Observable::<$field>::observe(
+                    &self.$member,
+                    challenger,
+                );)*
+            }
+        }
+    };
+    ($ty:ty { $($member:ident),* $(,)? }) => {
+        impl $crate::iop::observable::ObservableTarget for $ty {
+            fn observe<
+                F: $crate::hash::hash_types::RichField + $crate::field::extension::Extendable<D>,
+                H: $crate::plonk::config::AlgebraicHasher<F>,
+                const D: usize,
+            >(
+                &self,
+                challenger: &mut $crate::iop::challenger::RecursiveChallenger<F, H, D>,
+            ) {
+                $($crate::iop::observable::ObservableTarget::observe(&self.$member, challenger);)*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::InnerHasher;
+
+    struct Vote {
+        epoch: F,
+        root: HashOut<F>,
+        weights: [F; 3],
+    }
+
+    struct VoteTarget {
+        epoch: Target,
+        root: HashOutTarget,
+        weights: [Target; 3],
+    }
+
+    impl_observable!(
+        F,
+        Vote {
+            epoch,
+            root,
+            weights
+        }
+    );
+    impl_observable!(VoteTarget {
+        epoch,
+        root,
+        weights,
+    });
+
+    #[test]
+    fn test_observable_struct() {
+        let vote = Vote {
+            epoch: F::rand(),
+            root: HashOut::rand(),
+            weights: F::rand_array(),
+        };
+
+        let mut challenger = Challenger::<F, H>::new();
+        vote.observe(&mut challenger);
+        let challenges = challenger.get_n_challenges(2);
+
+        // The fields are observed in order, as if flattened by hand.
+        let mut manual = Challenger::<F, H>::new();
+        manual.observe_element(vote.epoch);
+        manual.observe_hash::<H>(vote.root);
+        manual.observe_elements(&vote.weights);
+        assert_eq!(manual.get_n_challenges(2), challenges);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let vote_t = VoteTarget {
+            epoch: builder.constant(vote.epoch),
+            root: builder.constant_hash(vote.root),
+            weights: vote.weights.map(|x| builder.constant(x)),
+        };
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        vote_t.observe(&mut recursive_challenger);
+        let challenges_t = recursive_challenger.get_n_challenges(&mut builder, 2);
+
+        let circuit = builder.build::<C>();
+        let witness =
+            generate_partial_witness(PartialWitness::new(), &circuit.prover_only, &circuit.common)
+                .unwrap();
+        assert_eq!(witness.get_targets(&challenges_t), challenges);
+    }
+}
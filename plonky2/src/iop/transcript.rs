@@ -0,0 +1,458 @@
+//! A common interface for Fiat-Shamir transcripts, so that the challenges of an IOP can be derived
+//! by a single piece of code whether they are computed natively or in a circuit.
+
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::fri::challenges::{keccak_pow_digest, keccak_pow_response, keccak_query_indices};
+use crate::fri::proof::{FriChallenges, FriChallengesTarget};
+use crate::fri::prover::challenger_proof_of_work;
+use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
+use crate::fri::{FriConfig, FriPowHash};
+use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// A Fiat-Shamir transcript, which observes prover messages and squeezes verifier challenges.
+///
+/// `Challenger` and `KeccakTranscript` implement it natively and `RecursiveChallenger` in a circuit.
+/// The prover and the native verifier are generic over `NativeTranscript`, and the recursive
+/// verifier over `RecursiveTranscript`, so a new sponge only needs to implement these traits.
+pub trait Transcript<F: RichField + Extendable<D>, const D: usize> {
+    /// A base field element, or a target holding one.
+    type Element: Copy;
+    /// An extension field element, or a target holding one.
+    type ExtensionElement: Copy;
+    /// A hash output of the transcript's hasher.
+    type Hash;
+    /// A Merkle cap of the transcript's hasher.
+    type Cap;
+    /// What is needed to squeeze challenges: nothing natively, the circuit builder in a circuit.
+    type Context;
+
+    fn observe_element(&mut self, element: Self::Element);
+
+    fn observe_elements(&mut self, elements: &[Self::Element]) {
+        for &element in elements {
+            self.observe_element(element);
+        }
+    }
+
+    fn observe_extension_element(&mut self, element: Self::ExtensionElement);
+
+    fn observe_extension_elements(&mut self, elements: &[Self::ExtensionElement]) {
+        for &element in elements {
+            self.observe_extension_element(element);
+        }
+    }
+
+    fn observe_hash(&mut self, hash: &Self::Hash);
+
+    fn observe_cap(&mut self, cap: &Self::Cap);
+
+    fn get_challenge(&mut self, ctx: &mut Self::Context) -> Self::Element;
+
+    fn get_n_challenges(&mut self, ctx: &mut Self::Context, n: usize) -> Vec<Self::Element> {
+        (0..n).map(|_| self.get_challenge(ctx)).collect()
+    }
+
+    fn get_extension_challenge(&mut self, ctx: &mut Self::Context) -> Self::ExtensionElement;
+}
+
+impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> Transcript<F, D>
+    for Challenger<F, H>
+{
+    type Element = F;
+    type ExtensionElement = F::Extension;
+    type Hash = H::Hash;
+    type Cap = MerkleCap<F, H>;
+    type Context = ();
+
+    fn observe_element(&mut self, element: F) {
+        Challenger::observe_element(self, element);
+    }
+
+    fn observe_extension_element(&mut self, element: F::Extension) {
+        Challenger::observe_extension_element::<D>(self, &element);
+    }
+
+    fn observe_hash(&mut self, hash: &H::Hash) {
+        Challenger::observe_hash::<H>(self, *hash);
+    }
+
+    fn observe_cap(&mut self, cap: &MerkleCap<F, H>) {
+        Challenger::observe_cap(self, cap);
+    }
+
+    fn get_challenge(&mut self, _ctx: &mut ()) -> F {
+        Challenger::get_challenge(self)
+    }
+
+    fn get_extension_challenge(&mut self, _ctx: &mut ()) -> F::Extension {
+        Challenger::get_extension_challenge::<D>(self)
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize> Transcript<F, D>
+    for RecursiveChallenger<F, H, D>
+{
+    type Element = Target;
+    type ExtensionElement = ExtensionTarget<D>;
+    type Hash = HashOutTarget;
+    type Cap = MerkleCapTarget;
+    type Context = CircuitBuilder<F, D>;
+
+    fn observe_element(&mut self, element: Target) {
+        RecursiveChallenger::observe_element(self, element);
+    }
+
+    fn observe_extension_element(&mut self, element: ExtensionTarget<D>) {
+        RecursiveChallenger::observe_extension_element(self, element);
+    }
+
+    fn observe_hash(&mut self, hash: &HashOutTarget) {
+        RecursiveChallenger::observe_hash(self, hash);
+    }
+
+    fn observe_cap(&mut self, cap: &MerkleCapTarget) {
+        RecursiveChallenger::observe_cap(self, cap);
+    }
+
+    fn get_challenge(&mut self, builder: &mut CircuitBuilder<F, D>) -> Target {
+        RecursiveChallenger::get_challenge(self, builder)
+    }
+
+    fn get_extension_challenge(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> ExtensionTarget<D> {
+        RecursiveChallenger::get_extension_challenge(self, builder)
+    }
+}
+
+/// A transcript computed natively, which the prover and the native verifier are generic over.
+///
+/// Besides the challenges of `Transcript`, it derives the FRI proof-of-work response and the query
+/// indices, and grinds the proof-of-work witness, which depend on how the transcript is hashed.
+pub trait NativeTranscript<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>:
+    Transcript<
+    F,
+    D,
+    Element = F,
+    ExtensionElement = F::Extension,
+    Hash = H::Hash,
+    Cap = MerkleCap<F, H>,
+    Context = (),
+>
+{
+    /// Squeezes a hash, which seeds the Keccak proof-of-work.
+    fn get_hash(&mut self) -> HashOut<F>;
+
+    /// Finds a FRI proof-of-work witness for the current state of the transcript, which is left as
+    /// it was; `fri_pow_response_and_query_indices` then takes the witness into account.
+    fn fri_proof_of_work(&self, config: &FriConfig) -> F;
+
+    fn observe_openings(&mut self, openings: &FriOpenings<F, D>) {
+        for batch in &openings.batches {
+            self.observe_extension_elements(&batch.values);
+        }
+    }
+
+    /// Derives the PoW response and the query indices from the PoW witness, once the final
+    /// polynomial has been observed.
+    fn fri_pow_response_and_query_indices(
+        &mut self,
+        pow_witness: F,
+        lde_size: usize,
+        config: &FriConfig,
+    ) -> (F, Vec<usize>) {
+        match config.pow_hash {
+            FriPowHash::Field => {
+                self.observe_element(pow_witness);
+                let fri_pow_response = self.get_challenge(&mut ());
+                let fri_query_indices = (0..config.num_query_rounds)
+                    .map(|_| self.get_challenge(&mut ()).to_canonical_u64() as usize % lde_size)
+                    .collect();
+                (fri_pow_response, fri_query_indices)
+            }
+            FriPowHash::Keccak => {
+                let digest = keccak_pow_digest(self.get_hash(), pow_witness);
+                (
+                    keccak_pow_response(&digest),
+                    keccak_query_indices(&digest, config.num_query_rounds, lde_size),
+                )
+            }
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> NativeTranscript<F, H, D>
+    for Challenger<F, H>
+{
+    fn get_hash(&mut self) -> HashOut<F> {
+        Challenger::get_hash(self)
+    }
+
+    fn fri_proof_of_work(&self, config: &FriConfig) -> F {
+        challenger_proof_of_work(self, config)
+    }
+}
+
+/// A transcript computed in a circuit, which the recursive verifier is generic over. Only
+/// proofs using the field sponge for proof-of-work can be verified in a circuit, so this doesn't
+/// need more than `Transcript`.
+pub trait RecursiveTranscript<F: RichField + Extendable<D>, const D: usize>:
+    Transcript<
+    F,
+    D,
+    Element = Target,
+    ExtensionElement = ExtensionTarget<D>,
+    Hash = HashOutTarget,
+    Cap = MerkleCapTarget,
+    Context = CircuitBuilder<F, D>,
+>
+{
+    fn observe_openings(&mut self, openings: &FriOpeningsTarget<D>) {
+        for batch in &openings.batches {
+            self.observe_extension_elements(&batch.values);
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize> RecursiveTranscript<F, D>
+    for RecursiveChallenger<F, H, D>
+{
+}
+
+/// The challenges of the Plonk IOP which precede FRI.
+pub(crate) struct PlonkChallenges<E, X> {
+    pub betas: Vec<E>,
+    pub gammas: Vec<E>,
+    pub alphas: Vec<E>,
+    pub zeta: X,
+}
+
+/// Observes the instance and the prover's commitments and squeezes the Plonk challenges, in the
+/// order in which the prover commits. The native verifier and the recursive verifier both derive
+/// their challenges this way.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn plonk_challenges<
+    T: Transcript<F, D>,
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    transcript: &mut T,
+    ctx: &mut T::Context,
+    num_challenges: usize,
+    circuit_digest: &T::Hash,
+    public_inputs_hash: &[T::Element],
+    wires_cap: &T::Cap,
+    plonk_zs_partial_products_cap: &T::Cap,
+    quotient_polys_cap: &T::Cap,
+) -> PlonkChallenges<T::Element, T::ExtensionElement> {
+    // Observe the instance.
+    transcript.observe_hash(circuit_digest);
+    transcript.observe_elements(public_inputs_hash);
+
+    transcript.observe_cap(wires_cap);
+    let betas = transcript.get_n_challenges(ctx, num_challenges);
+    let gammas = transcript.get_n_challenges(ctx, num_challenges);
+
+    transcript.observe_cap(plonk_zs_partial_products_cap);
+    let alphas = transcript.get_n_challenges(ctx, num_challenges);
+
+    transcript.observe_cap(quotient_polys_cap);
+    let zeta = transcript.get_extension_challenge(ctx);
+
+    PlonkChallenges {
+        betas,
+        gammas,
+        alphas,
+        zeta,
+    }
+}
+
+/// Squeezes the FRI scaling factor `alpha`, then observes each commit-phase Merkle cap and squeezes
+/// the corresponding `beta`, and finally observes the coefficients of the final polynomial. The
+/// native verifier and the recursive verifier both derive these challenges this way.
+pub(crate) fn fri_commit_phase_challenges<
+    T: Transcript<F, D>,
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    transcript: &mut T,
+    ctx: &mut T::Context,
+    commit_phase_merkle_caps: &[T::Cap],
+    final_poly_coeffs: &[T::ExtensionElement],
+) -> (T::ExtensionElement, Vec<T::ExtensionElement>) {
+    // Scaling factor to combine polynomials.
+    let fri_alpha = transcript.get_extension_challenge(ctx);
+
+    // Recover the random betas used in the FRI reductions.
+    let fri_betas = commit_phase_merkle_caps
+        .iter()
+        .map(|cap| {
+            transcript.observe_cap(cap);
+            transcript.get_extension_challenge(ctx)
+        })
+        .collect();
+
+    transcript.observe_extension_elements(final_poly_coeffs);
+
+    (fri_alpha, fri_betas)
+}
+
+/// Derives the FRI challenges from a native transcript, once the openings have been observed.
+pub(crate) fn fri_challenges<
+    T: NativeTranscript<F, H, D>,
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    const D: usize,
+>(
+    transcript: &mut T,
+    commit_phase_merkle_caps: &[MerkleCap<F, H>],
+    final_poly: &PolynomialCoeffs<F::Extension>,
+    pow_witness: F,
+    degree_bits: usize,
+    config: &FriConfig,
+) -> FriChallenges<F, D> {
+    let lde_size = 1 << (degree_bits + config.rate_bits);
+    let (fri_alpha, fri_betas) = fri_commit_phase_challenges::<_, F, D>(
+        transcript,
+        &mut (),
+        commit_phase_merkle_caps,
+        &final_poly.coeffs,
+    );
+
+    let (fri_pow_response, fri_query_indices) =
+        transcript.fri_pow_response_and_query_indices(pow_witness, lde_size, config);
+
+    FriChallenges {
+        fri_alpha,
+        fri_betas,
+        fri_pow_response,
+        fri_query_indices,
+    }
+}
+
+/// Like `fri_challenges`, in a circuit.
+pub(crate) fn fri_challenges_circuit<
+    T: RecursiveTranscript<F, D>,
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    transcript: &mut T,
+    builder: &mut CircuitBuilder<F, D>,
+    commit_phase_merkle_caps: &[MerkleCapTarget],
+    final_poly: &PolynomialCoeffsExtTarget<D>,
+    pow_witness: Target,
+    inner_fri_config: &FriConfig,
+) -> FriChallengesTarget<D> {
+    assert_eq!(
+        inner_fri_config.pow_hash,
+        FriPowHash::Field,
+        "Only proofs using the field sponge for proof-of-work can be verified in a circuit"
+    );
+    let (fri_alpha, fri_betas) = fri_commit_phase_challenges::<_, F, D>(
+        transcript,
+        builder,
+        commit_phase_merkle_caps,
+        &final_poly.0,
+    );
+
+    transcript.observe_element(pow_witness);
+    let fri_pow_response = transcript.get_challenge(builder);
+
+    let fri_query_indices = transcript.get_n_challenges(builder, inner_fri_config.num_query_rounds);
+
+    FriChallengesTarget {
+        fri_alpha,
+        fri_betas,
+        fri_pow_response,
+        fri_query_indices,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    /// Tests that `plonk_challenges` derives the same challenges with both transcripts.
+    #[test]
+    fn test_plonk_challenges_consistency() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let digest = HashOut::<F>::rand();
+        let public_inputs_hash = F::rand_vec(4);
+        let caps = (0..3)
+            .map(|_| MerkleCap::<F, H>((0..2).map(|_| HashOut::rand()).collect()))
+            .collect::<Vec<_>>();
+
+        let mut challenger = Challenger::<F, H>::new();
+        let challenges = plonk_challenges::<_, F, D>(
+            &mut challenger,
+            &mut (),
+            3,
+            &digest,
+            &public_inputs_hash,
+            &caps[0],
+            &caps[1],
+            &caps[2],
+        );
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let digest_target = builder.constant_hash(digest);
+        let public_inputs_hash_target = builder.constants(&public_inputs_hash);
+        let cap_targets = caps
+            .iter()
+            .map(|cap| builder.constant_merkle_cap(cap))
+            .collect::<Vec<_>>();
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        let recursive_challenges = plonk_challenges::<_, F, D>(
+            &mut recursive_challenger,
+            &mut builder,
+            3,
+            &digest_target,
+            &public_inputs_hash_target,
+            &cap_targets[0],
+            &cap_targets[1],
+            &cap_targets[2],
+        );
+        let circuit = builder.build::<C>();
+        let witness =
+            generate_partial_witness(PartialWitness::new(), &circuit.prover_only, &circuit.common)
+                .unwrap();
+
+        assert_eq!(
+            challenges.betas,
+            witness.get_targets(&recursive_challenges.betas)
+        );
+        assert_eq!(
+            challenges.gammas,
+            witness.get_targets(&recursive_challenges.gammas)
+        );
+        assert_eq!(
+            challenges.alphas,
+            witness.get_targets(&recursive_challenges.alphas)
+        );
+        assert_eq!(
+            challenges.zeta,
+            witness.get_extension_target(recursive_challenges.zeta)
+        );
+    }
+}
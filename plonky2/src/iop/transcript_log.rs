@@ -0,0 +1,256 @@
+//! Records of Fiat-Shamir transcripts, for localizing mismatches between verifier implementations.
+//!
+//! A `Challenger` with a log enabled records every element it observes and every challenge it
+//! produces, in order. Comparing the log of the prover with that of the native verifier, or with the
+//! values computed by an exported circom or Solidity verifier, shows the first step at which they
+//! disagree.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::field::types::PrimeField64;
+
+/// A step of a Fiat-Shamir transcript.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum TranscriptEvent<F: PrimeField64> {
+    /// An element absorbed into the sponge.
+    Observe(F),
+    /// A challenge squeezed from the sponge.
+    Challenge(F),
+}
+
+impl<F: PrimeField64> TranscriptEvent<F> {
+    const OBSERVE_TAG: u8 = 0;
+    const CHALLENGE_TAG: u8 = 1;
+
+    pub fn value(&self) -> F {
+        match *self {
+            Self::Observe(x) | Self::Challenge(x) => x,
+        }
+    }
+}
+
+/// Whether a `TranscriptFixtureEvent` is an observed element or a challenge.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptEventKind {
+    Observe,
+    Challenge,
+}
+
+/// A step of a transcript as written in the JSON test vectors read by the circom test suite
+/// (`circom/test/transcript.test.js`), e.g. `{"kind": "observe", "value": "42"}`. Values are canonical decimal strings, as in
+/// `proof.json`, since JavaScript numbers can't hold every field element.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptFixtureEvent {
+    pub kind: TranscriptEventKind,
+    pub value: String,
+}
+
+/// An ordered record of the elements observed and the challenges produced by a `Challenger`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TranscriptLog<F: PrimeField64> {
+    pub events: Vec<TranscriptEvent<F>>,
+}
+
+impl<F: PrimeField64> TranscriptLog<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, event: TranscriptEvent<F>) {
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The challenges produced, in order.
+    pub fn challenges(&self) -> impl Iterator<Item = F> + '_ {
+        self.events.iter().filter_map(|event| match *event {
+            TranscriptEvent::Challenge(x) => Some(x),
+            TranscriptEvent::Observe(_) => None,
+        })
+    }
+
+    /// The index of the first event at which `self` and `other` differ, or `None` if they are
+    /// identical. If one log is a prefix of the other, this is the length of the shorter one.
+    pub fn first_divergence(&self, other: &Self) -> Option<usize> {
+        let common = self
+            .events
+            .iter()
+            .zip(&other.events)
+            .position(|(a, b)| a != b);
+        common.or_else(|| (self.len() != other.len()).then(|| self.len().min(other.len())))
+    }
+
+    /// The log as test vectors for other verifier implementations, which should observe and
+    /// squeeze the same values in the same order. See `TranscriptFixtureEvent`.
+    pub fn to_fixture(&self) -> Vec<TranscriptFixtureEvent> {
+        self.events
+            .iter()
+            .map(|event| TranscriptFixtureEvent {
+                kind: match event {
+                    TranscriptEvent::Observe(_) => TranscriptEventKind::Observe,
+                    TranscriptEvent::Challenge(_) => TranscriptEventKind::Challenge,
+                },
+                value: event.value().to_canonical_u64().to_string(),
+            })
+            .collect()
+    }
+
+    /// Reads test vectors written by `to_fixture`.
+    pub fn from_fixture(fixture: &[TranscriptFixtureEvent]) -> Result<Self> {
+        let events = fixture
+            .iter()
+            .map(|event| {
+                let value = event
+                    .value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&x| x < F::ORDER)
+                    .ok_or_else(|| anyhow!("Invalid field element {}", event.value))?;
+                let value = F::from_canonical_u64(value);
+                Ok(match event.kind {
+                    TranscriptEventKind::Observe => TranscriptEvent::Observe(value),
+                    TranscriptEventKind::Challenge => TranscriptEvent::Challenge(value),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { events })
+    }
+
+    /// Encodes the log as a sequence of 9-byte records: a tag byte, 0 for an observed element and 1
+    /// for a challenge, followed by the canonical value as a little-endian `u64`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len() * 9);
+        for event in &self.events {
+            let tag = match event {
+                TranscriptEvent::Observe(_) => TranscriptEvent::<F>::OBSERVE_TAG,
+                TranscriptEvent::Challenge(_) => TranscriptEvent::<F>::CHALLENGE_TAG,
+            };
+            bytes.push(tag);
+            bytes.extend_from_slice(&event.value().canonical_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a log encoded by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let records = bytes.chunks_exact(9);
+        ensure!(
+            records.remainder().is_empty(),
+            "Transcript log length {} is not a multiple of 9",
+            bytes.len()
+        );
+        let events = records
+            .map(|record| {
+                let encoded = record[1..].try_into().unwrap();
+                let value = F::from_canonical_bytes(encoded).ok_or_else(|| {
+                    anyhow!(
+                        "Non-canonical field element {}",
+                        u64::from_le_bytes(encoded)
+                    )
+                })?;
+                match record[0] {
+                    TranscriptEvent::<F>::OBSERVE_TAG => Ok(TranscriptEvent::Observe(value)),
+                    TranscriptEvent::<F>::CHALLENGE_TAG => Ok(TranscriptEvent::Challenge(value)),
+                    tag => Err(anyhow!("Invalid transcript event tag {}", tag)),
+                }
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Field64};
+    use crate::iop::challenger::Challenger;
+    use crate::iop::transcript_log::{
+        TranscriptEvent, TranscriptEventKind, TranscriptFixtureEvent, TranscriptLog,
+    };
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::prover::prove_with_transcript_log;
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_challenger_log() -> Result<()> {
+        let mut challenger = Challenger::<F, <C as GenericConfig<D>>::Hasher>::new_with_log();
+        challenger.observe_elements(&[F::ONE, F::TWO]);
+        let challenge = challenger.get_challenge();
+
+        let log = challenger.take_transcript_log().unwrap();
+        assert_eq!(
+            log.events,
+            [
+                TranscriptEvent::Observe(F::ONE),
+                TranscriptEvent::Observe(F::TWO),
+                TranscriptEvent::Challenge(challenge),
+            ]
+        );
+        assert_eq!(TranscriptLog::from_bytes(&log.to_bytes())?, log);
+        assert!(TranscriptLog::<F>::from_bytes(&[2; 9]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prover_and_verifier_transcripts_match() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let (proof, prover_log) = prove_with_transcript_log(
+            &data.prover_only,
+            &data.common,
+            pw,
+            &mut TimingTree::default(),
+        )?;
+        data.verify(proof.clone())?;
+
+        let verifier_log =
+            proof.transcript_log(&data.verifier_only.circuit_digest, &data.common)?;
+        assert_eq!(prover_log.first_divergence(&verifier_log), None);
+        assert!(prover_log.challenges().count() > 0);
+
+        // The test vectors of the proof's transcript, as the circom test suite reads them.
+        let fixture = serde_json::to_string(&verifier_log.to_fixture())?;
+        assert!(fixture.starts_with(r#"[{"kind":"observe","value":""#));
+        let parsed: Vec<TranscriptFixtureEvent> = serde_json::from_str(&fixture)?;
+        assert_eq!(TranscriptLog::from_fixture(&parsed)?, verifier_log);
+        let non_canonical = [TranscriptFixtureEvent {
+            kind: TranscriptEventKind::Challenge,
+            value: F::ORDER.to_string(),
+        }];
+        assert!(TranscriptLog::<F>::from_fixture(&non_canonical).is_err());
+
+        let mut tampered = verifier_log.clone();
+        tampered.events.truncate(5);
+        assert_eq!(prover_log.first_divergence(&tampered), Some(5));
+        Ok(())
+    }
+}
@@ -1,14 +1,21 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
+use anyhow::{ensure, Result};
 use hashbrown::HashMap;
 use itertools::Itertools;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::curve::ecgfp5::{CurvePoint, GFp5};
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::types::Field;
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::witness_util::set_fri_proof_target;
+use crate::gadgets::ecgfp5::CurvePointTarget;
+use crate::gadgets::gfp5::GFp5Target;
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
@@ -16,6 +23,10 @@ use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{VerifierCircuitTarget, VerifierOnlyCircuitData};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{Proof, ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget};
+#[cfg(feature = "zeroize")]
+use crate::util::zeroize::{
+    zeroize_field_elements, zeroize_field_vecs, zeroize_optional_field_elements,
+};
 
 pub trait WitnessWrite<F: Field> {
     fn set_target(&mut self, target: Target, value: F);
@@ -39,6 +50,45 @@ pub trait WitnessWrite<F: Field> {
         }
     }
 
+    /// Sets the siblings of `pt` to those of `value`, which must have as many, e.g. to open a leaf
+    /// checked with `CircuitBuilder::verify_merkle_proof`.
+    fn set_merkle_proof_target<H: AlgebraicHasher<F>>(
+        &mut self,
+        pt: &MerkleProofTarget,
+        value: &MerkleProof<F, H>,
+    ) where
+        F: RichField,
+    {
+        assert_eq!(
+            pt.siblings.len(),
+            value.len(),
+            "Merkle proof has the wrong length"
+        );
+        for (&ht, &h) in pt.siblings.iter().zip(&value.siblings) {
+            self.set_hash_target(ht, h);
+        }
+    }
+
+    /// Sets the siblings of `value`, followed by zero hashes for the remaining siblings of `pt`,
+    /// as `CircuitBuilder::verify_variable_height_merkle_proof` expects.
+    fn set_padded_merkle_proof_target<H: AlgebraicHasher<F>>(
+        &mut self,
+        pt: &MerkleProofTarget,
+        value: &MerkleProof<F, H>,
+    ) where
+        F: RichField,
+    {
+        assert!(value.len() <= pt.siblings.len(), "Merkle proof is too long");
+        let padding = core::iter::repeat(HashOut::ZERO);
+        for (&ht, h) in pt
+            .siblings
+            .iter()
+            .zip(value.siblings.iter().copied().chain(padding))
+        {
+            self.set_hash_target(ht, h);
+        }
+    }
+
     fn set_extension_target<const D: usize>(&mut self, et: ExtensionTarget<D>, value: F::Extension)
     where
         F: RichField + Extendable<D>,
@@ -46,6 +96,23 @@ pub trait WitnessWrite<F: Field> {
         self.set_target_arr(et.0, value.to_basefield_array());
     }
 
+    fn set_gfp5_target(&mut self, t: GFp5Target, value: GFp5<F>)
+    where
+        F: RichField + Extendable<5>,
+    {
+        self.set_target_arr(t.0, value.to_basefield_array());
+    }
+
+    /// Sets the coordinates of `pt`; `value` can't be the identity, which has none.
+    fn set_curve_point_target(&mut self, pt: CurvePointTarget, value: CurvePoint<F>)
+    where
+        F: RichField + Extendable<5>,
+    {
+        assert!(!value.is_identity, "The identity has no affine coordinates");
+        self.set_gfp5_target(pt.x, value.x);
+        self.set_gfp5_target(pt.y, value.y);
+    }
+
     fn set_target_arr<const N: usize>(&mut self, targets: [Target; N], values: [F; N]) {
         (0..N).for_each(|i| {
             self.set_target(targets[i], values[i]);
@@ -198,6 +265,21 @@ pub trait Witness<F: Field>: WitnessWrite<F> {
         )
     }
 
+    fn get_gfp5_target(&self, t: GFp5Target) -> GFp5<F>
+    where
+        F: RichField + Extendable<5>,
+    {
+        let coeffs: [F; 5] = self.get_targets(&t.0).try_into().unwrap();
+        FieldExtension::<5>::from_basefield_array(coeffs)
+    }
+
+    fn get_curve_point_target(&self, pt: CurvePointTarget) -> CurvePoint<F>
+    where
+        F: RichField + Extendable<5>,
+    {
+        CurvePoint::new(self.get_gfp5_target(pt.x), self.get_gfp5_target(pt.y))
+    }
+
     fn get_extension_targets<const D: usize>(&self, ets: &[ExtensionTarget<D>]) -> Vec<F::Extension>
     where
         F: RichField + Extendable<D>,
@@ -241,17 +323,50 @@ pub trait Witness<F: Field>: WitnessWrite<F> {
     }
 }
 
+/// How a `MatrixWitness` stores the wire values.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum WitnessLayout {
+    /// One vector per wire column, from which the wire polynomials are read directly. Reading the
+    /// wires of a row, as the permutation argument does, is strided.
+    #[default]
+    ColumnMajor,
+    /// One vector per row, so that the permutation argument reads contiguous memory, which pays off
+    /// on wide circuits. The columns are transposed out to compute the wire polynomials.
+    RowMajor,
+}
+
 #[derive(Clone, Debug)]
 pub struct MatrixWitness<F: Field> {
     pub(crate) wire_values: Vec<Vec<F>>,
+    pub(crate) layout: WitnessLayout,
 }
 
 impl<F: Field> MatrixWitness<F> {
     pub fn get_wire(&self, gate: usize, input: usize) -> F {
-        self.wire_values[input][gate]
+        match self.layout {
+            WitnessLayout::ColumnMajor => self.wire_values[input][gate],
+            WitnessLayout::RowMajor => self.wire_values[gate][input],
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> Zeroize for MatrixWitness<F> {
+    fn zeroize(&mut self) {
+        zeroize_field_vecs(&mut self.wire_values);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> Drop for MatrixWitness<F> {
+    fn drop(&mut self) {
+        self.zeroize();
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<F: Field> ZeroizeOnDrop for MatrixWitness<F> {}
+
 #[derive(Clone, Debug, Default)]
 pub struct PartialWitness<F: Field> {
     pub(crate) target_values: HashMap<Target, F>,
@@ -284,6 +399,26 @@ impl<F: Field> Witness<F> for PartialWitness<F> {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<F: Field> Zeroize for PartialWitness<F> {
+    fn zeroize(&mut self) {
+        for value in self.target_values.values_mut() {
+            zeroize_field_elements(core::slice::from_mut(value));
+        }
+        self.target_values.clear();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> Drop for PartialWitness<F> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: Field> ZeroizeOnDrop for PartialWitness<F> {}
+
 /// `PartitionWitness` holds a disjoint-set forest of the targets respecting a circuit's copy constraints.
 /// The value of a target is defined to be the value of its root in the forest.
 #[derive(Clone)]
@@ -305,20 +440,26 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     }
 
     /// Set a `Target`. On success, returns the representative index of the newly-set target. If the
-    /// target was already set, returns `None`.
-    pub(crate) fn set_target_returning_rep(&mut self, target: Target, value: F) -> Option<usize> {
+    /// target was already set, returns `None`, or an error if it was set to a different value.
+    pub(crate) fn set_target_returning_rep(
+        &mut self,
+        target: Target,
+        value: F,
+    ) -> Result<Option<usize>> {
         let rep_index = self.representative_map[self.target_index(target)];
         let rep_value = &mut self.values[rep_index];
         if let Some(old_value) = *rep_value {
-            assert_eq!(
-                value, old_value,
+            ensure!(
+                value == old_value,
                 "Partition containing {:?} was set twice with different values: {} != {}",
-                target, old_value, value
+                target,
+                old_value,
+                value
             );
-            None
+            Ok(None)
         } else {
             *rep_value = Some(value);
-            Some(rep_index)
+            Ok(Some(rep_index))
         }
     }
 
@@ -327,23 +468,38 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     }
 
     pub fn full_witness(self) -> MatrixWitness<F> {
-        let mut wire_values = vec![vec![F::ZERO; self.degree]; self.num_wires];
+        self.full_witness_with_layout(WitnessLayout::default())
+    }
+
+    pub fn full_witness_with_layout(self, layout: WitnessLayout) -> MatrixWitness<F> {
+        let mut wire_values = match layout {
+            WitnessLayout::ColumnMajor => vec![vec![F::ZERO; self.degree]; self.num_wires],
+            WitnessLayout::RowMajor => vec![vec![F::ZERO; self.num_wires]; self.degree],
+        };
         for i in 0..self.degree {
             for j in 0..self.num_wires {
                 let t = Target::Wire(Wire { row: i, column: j });
                 if let Some(x) = self.try_get_target(t) {
-                    wire_values[j][i] = x;
+                    match layout {
+                        WitnessLayout::ColumnMajor => wire_values[j][i] = x,
+                        WitnessLayout::RowMajor => wire_values[i][j] = x,
+                    }
                 }
             }
         }
 
-        MatrixWitness { wire_values }
+        MatrixWitness {
+            wire_values,
+            layout,
+        }
     }
 }
 
 impl<'a, F: Field> WitnessWrite<F> for PartitionWitness<'a, F> {
     fn set_target(&mut self, target: Target, value: F) {
-        self.set_target_returning_rep(target, value);
+        if let Err(e) = self.set_target_returning_rep(target, value) {
+            panic!("{e}");
+        }
     }
 }
 
@@ -353,3 +509,20 @@ impl<'a, F: Field> Witness<F> for PartitionWitness<'a, F> {
         self.values[rep_index]
     }
 }
+
+#[cfg(feature = "zeroize")]
+impl<'a, F: Field> Zeroize for PartitionWitness<'a, F> {
+    fn zeroize(&mut self) {
+        zeroize_optional_field_elements(&mut self.values);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<'a, F: Field> Drop for PartitionWitness<'a, F> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<'a, F: Field> ZeroizeOnDrop for PartitionWitness<'a, F> {}
@@ -1,17 +1,23 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::needless_range_loop)]
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
 
 extern crate alloc;
 
 #[doc(inline)]
 pub use plonky2_field as field;
 
+pub mod curve;
 pub mod fri;
 pub mod gadgets;
 pub mod gates;
 pub mod hash;
 pub mod iop;
 pub mod plonk;
+pub mod prelude;
 pub mod recursion;
+// Can't use #[cfg(test)] here because it needs to be visible to other crates.
+#[cfg(any(feature = "test_utils", test))]
+pub mod test_utils;
 pub mod util;
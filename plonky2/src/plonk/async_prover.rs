@@ -0,0 +1,156 @@
+//! Asynchronous proving on top of tokio's blocking thread pool.
+//!
+//! Proof generation is CPU-bound and can take several seconds, so it must not run on an async
+//! runtime's worker threads. The helpers here move proving onto `spawn_blocking` threads, and
+//! `ProvingQueue` bounds how many proofs may be in flight at once.
+
+use alloc::sync::Arc;
+use core::future::Future;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Semaphore;
+use tokio::task;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::witness::PartialWitness;
+use crate::plonk::circuit_data::CircuitData;
+use crate::plonk::config::GenericConfig;
+use crate::plonk::proof::ProofWithPublicInputs;
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    CircuitData<F, C, D>
+{
+    /// Generates a proof on tokio's blocking thread pool, so that the calling runtime is not
+    /// blocked while proving. Must be called from within a tokio runtime.
+    pub async fn prove_async(
+        self: Arc<Self>,
+        inputs: PartialWitness<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        task::spawn_blocking(move || self.prove(inputs))
+            .await
+            .map_err(|e| anyhow!("Proving task failed: {}", e))?
+    }
+}
+
+/// A bounded queue of proving jobs for a single circuit.
+///
+/// At most `max_concurrent` proofs are generated at the same time, and at most `capacity` jobs
+/// (including the ones being proven) are accepted at once. Submissions beyond the capacity are
+/// rejected immediately rather than queued, so that callers can shed load.
+pub struct ProvingQueue<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    data: Arc<CircuitData<F, C, D>>,
+    /// Permits for proofs currently being generated.
+    workers: Arc<Semaphore>,
+    /// Permits for accepted jobs, whether waiting or being proven.
+    slots: Arc<Semaphore>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    ProvingQueue<F, C, D>
+{
+    pub fn new(data: Arc<CircuitData<F, C, D>>, max_concurrent: usize, capacity: usize) -> Self {
+        assert!(
+            max_concurrent > 0,
+            "A proving queue needs at least one worker"
+        );
+        assert!(
+            capacity >= max_concurrent,
+            "Queue capacity must be at least the number of workers"
+        );
+        Self {
+            data,
+            workers: Arc::new(Semaphore::new(max_concurrent)),
+            slots: Arc::new(Semaphore::new(capacity)),
+        }
+    }
+
+    pub fn circuit_data(&self) -> &Arc<CircuitData<F, C, D>> {
+        &self.data
+    }
+
+    /// The number of additional jobs that would currently be accepted.
+    pub fn available_capacity(&self) -> usize {
+        self.slots.available_permits()
+    }
+
+    /// Submits a proving job. Returns an error right away if the queue is full; otherwise returns
+    /// a future resolving to the proof. The job starts running as soon as a worker is free, even
+    /// if the returned future is not polled. Must be called from within a tokio runtime.
+    pub fn submit(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<impl Future<Output = Result<ProofWithPublicInputs<F, C, D>>>> {
+        let slot = Arc::clone(&self.slots)
+            .try_acquire_owned()
+            .map_err(|_| anyhow!("Proving queue is full"))?;
+        let workers = Arc::clone(&self.workers);
+        let data = Arc::clone(&self.data);
+
+        let handle = task::spawn(async move {
+            let _slot = slot;
+            let _worker = workers
+                .acquire_owned()
+                .await
+                .map_err(|e| anyhow!("Proving queue was closed: {}", e))?;
+            data.prove_async(inputs).await
+        });
+
+        Ok(async move {
+            handle
+                .await
+                .map_err(|e| anyhow!("Proving task failed: {}", e))?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::async_prover::ProvingQueue;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_proving_queue() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = Arc::new(builder.build::<C>());
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            let queue = ProvingQueue::new(Arc::clone(&data), 1, 2);
+            let witness = |v: u64| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(x, F::from_canonical_u64(v));
+                pw
+            };
+
+            let first = queue.submit(witness(2))?;
+            let second = queue.submit(witness(3))?;
+            assert!(queue.submit(witness(4)).is_err(), "queue should be full");
+
+            let proof = first.await?;
+            assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(4)]);
+            data.verify(proof)?;
+            let proof = second.await?;
+            assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(9)]);
+            data.verify(proof)?;
+
+            let proof = Arc::clone(&data).prove_async(witness(5)).await?;
+            data.verify(proof)
+        })
+    }
+}
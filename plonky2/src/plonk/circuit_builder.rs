@@ -1,8 +1,12 @@
 use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::any::TypeId;
 use core::cmp::max;
+use core::mem::size_of;
+use core::time::Duration;
 #[cfg(feature = "std")]
 use std::time::Instant;
 
@@ -14,25 +18,32 @@ use crate::field::cosets::get_unique_coset_shifts;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::fft::fft_root_table;
 use crate::field::polynomial::PolynomialValues;
-use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::FriParams;
 use crate::gadgets::arithmetic::BaseArithmeticOperation;
 use crate::gadgets::arithmetic_extension::ExtensionArithmeticOperation;
+use crate::gadgets::lookup::LookupTable;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
 use crate::gates::constant::ConstantGate;
 use crate::gates::gate::{CurrentSlot, Gate, GateInstance, GateRef};
 use crate::gates::noop::NoopGate;
+use crate::gates::poseidon::PoseidonGate;
 use crate::gates::public_input::PublicInputGate;
-use crate::gates::selectors::selector_polynomials;
-use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::gates::selectors::{
+    selector_groups, selector_max_degree, selector_polynomials, SelectorGrouping,
+};
+use crate::hash::hash_types::{
+    HashOut, HashOutTarget, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS,
+};
+use crate::hash::hashing::SPONGE_RATE;
 use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::{
-    ConstantGenerator, CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGenerator,
+    ConstantGenerator, CopyGenerator, FnGenerator, RandomValueGenerator, SimpleGenerator,
+    WitnessGenerator,
 };
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::wire::Wire;
@@ -40,15 +51,19 @@ use crate::plonk::circuit_data::{
     CircuitConfig, CircuitData, CommonCircuitData, ProverCircuitData, ProverOnlyCircuitData,
     VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
-use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use crate::plonk::copy_constraint::CopyConstraint;
+use crate::plonk::cost_estimate::{CircuitShape, ProvingProfile, RoutingReport, SizeEstimate};
+use crate::plonk::graphviz::circuit_to_graphviz;
 use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::prover::quotient_domain_rows;
+use crate::plonk::public_inputs_layout::PublicInputSlot;
 use crate::timed;
 use crate::util::context_tree::ContextTree;
 use crate::util::partial_products::num_partial_products;
 use crate::util::timing::TimingTree;
-use crate::util::{log2_ceil, log2_strict, transpose, transpose_poly_values};
+use crate::util::{ceil_div_usize, log2_ceil, log2_strict, transpose, transpose_poly_values};
 
 pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     pub config: CircuitConfig,
@@ -75,6 +90,9 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// A tree of named scopes, used for debugging.
     context_log: ContextTree,
 
+    /// Names given to targets with `name_target`, used to label them in `to_graphviz`.
+    target_names: HashMap<Target, String>,
+
     /// Generators used to generate the witness.
     generators: Vec<Box<dyn WitnessGenerator<F>>>,
 
@@ -93,6 +111,10 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// List of constant generators used to fill the constant wires.
     constant_generators: Vec<ConstantGenerator<F>>,
 
+    /// How the quotient degree factor, and hence the grouping of gates into selector polynomials,
+    /// is chosen.
+    selector_grouping: SelectorGrouping,
+
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
     /// This is used in cyclic recursion.
@@ -101,6 +123,9 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Optional verifier data that is registered as public inputs.
     /// This is used in cyclic recursion to hold the circuit's own verifier key.
     pub(crate) verifier_data_public_input: Option<VerifierCircuitTarget>,
+
+    /// Tables added with `add_lookup_table_from_slice`, indexed by their IDs.
+    pub(crate) lookup_tables: Vec<LookupTable<F>>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -114,6 +139,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             virtual_target_index: 0,
             copy_constraints: Vec::new(),
             context_log: ContextTree::new(),
+            target_names: HashMap::new(),
             generators: Vec::new(),
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
@@ -121,34 +147,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             arithmetic_results: HashMap::new(),
             current_slots: HashMap::new(),
             constant_generators: Vec::new(),
+            selector_grouping: SelectorGrouping::default(),
             goal_common_data: None,
             verifier_data_public_input: None,
+            lookup_tables: Vec::new(),
         };
         builder.check_config();
         builder
     }
 
     fn check_config(&self) {
-        let &CircuitConfig {
-            security_bits,
-            fri_config:
-                FriConfig {
-                    rate_bits,
-                    proof_of_work_bits,
-                    num_query_rounds,
-                    ..
-                },
-            ..
-        } = &self.config;
-
-        // Conjectured FRI security; see the ethSTARK paper.
-        let fri_field_bits = F::Extension::order().bits() as usize;
-        let fri_query_security_bits = num_query_rounds * rate_bits + proof_of_work_bits as usize;
-        let fri_security_bits = fri_field_bits.min(fri_query_security_bits);
-        assert!(
-            fri_security_bits >= security_bits,
-            "FRI params fall short of target security"
-        );
+        if let Err(e) = self.config.validate::<F, D>() {
+            panic!("Invalid CircuitConfig: {e}");
+        }
     }
 
     pub fn set_domain_separator(&mut self, separator: Vec<F>) {
@@ -156,6 +167,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.domain_separator = Some(separator);
     }
 
+    /// Sets how `build` chooses the quotient degree factor and groups gates into selector
+    /// polynomials. See `SelectorGrouping`.
+    pub fn set_selector_grouping(&mut self, grouping: SelectorGrouping) {
+        self.selector_grouping = grouping;
+    }
+
     pub fn num_gates(&self) -> usize {
         self.gate_instances.len()
     }
@@ -170,10 +187,34 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         targets.iter().for_each(|&t| self.register_public_input(t));
     }
 
+    /// Registers the given targets as public inputs, checking that they land at `slot`, i.e. that
+    /// exactly `slot.offset` public inputs have been registered before.
+    pub fn register_public_inputs_at(&mut self, slot: PublicInputSlot, targets: &[Target]) {
+        assert_eq!(
+            self.num_public_inputs(),
+            slot.offset,
+            "Public inputs for slot {:?} registered at public input {}",
+            slot.range(),
+            self.num_public_inputs()
+        );
+        assert_eq!(
+            targets.len(),
+            slot.len,
+            "Public inputs don't fit their slot {:?}",
+            slot.range()
+        );
+        self.register_public_inputs(targets);
+    }
+
     pub fn num_public_inputs(&self) -> usize {
         self.public_inputs.len()
     }
 
+    /// The targets registered as public inputs, in registration order.
+    pub fn public_inputs(&self) -> &[Target] {
+        &self.public_inputs
+    }
+
     /// Adds a new "virtual" target. This is not an actual wire in the witness, but just a target
     /// that help facilitate witness generation. In particular, a generator can assign a values to a
     /// virtual target, which can then be copied to other (virtual or concrete) targets. When we
@@ -193,7 +234,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn add_virtual_hash(&mut self) -> HashOutTarget {
-        HashOutTarget::from_vec(self.add_virtual_targets(4))
+        HashOutTarget::from_vec(self.add_virtual_targets(NUM_HASH_OUT_ELTS))
     }
 
     pub fn add_virtual_cap(&mut self, cap_height: usize) -> MerkleCapTarget {
@@ -306,6 +347,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     fn check_gate_compatibility<G: Gate<F, D>>(&self, gate: &G) {
+        // `PoseidonGate` routes more wires than most gates, so configs too narrow for it are only
+        // rejected once a circuit uses it.
+        if TypeId::of::<G>() == TypeId::of::<PoseidonGate<F, D>>() {
+            if let Err(e) = self.config.validate_poseidon::<F, D>() {
+                panic!("Invalid CircuitConfig: {e}");
+            }
+        }
         assert!(
             gate.num_wires() <= self.config.num_wires,
             "{:?} requires {} wires, but our CircuitConfig has only {}",
@@ -370,6 +418,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.generators.push(Box::new(generator.adapter()));
     }
 
+    /// Adds a generator which, once all of `dependencies` are set, sets `outputs` to `compute` of
+    /// their values, e.g. to provide advice which the circuit then checks. `compute` must return
+    /// one value per output. See the `iop::generator` module for generators which need more
+    /// control.
+    pub fn add_generator(
+        &mut self,
+        dependencies: Vec<Target>,
+        outputs: Vec<Target>,
+        compute: impl Fn(&[F]) -> Vec<F> + Send + Sync + 'static,
+    ) {
+        self.add_simple_generator(FnGenerator {
+            dependencies,
+            outputs,
+            compute: Box::new(compute),
+        });
+    }
+
     /// Returns a routable target with a value of 0.
     pub fn zero(&mut self) -> Target {
         self.constant(F::ZERO)
@@ -482,6 +547,36 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.context_log.pop(self.num_gates());
     }
 
+    /// Names `target`, so that `to_graphviz` labels it with `name`.
+    pub fn name_target(&mut self, target: Target, name: &str) {
+        self.target_names.insert(target, name.to_string());
+    }
+
+    /// Renders the gates and copy constraints added so far in the DOT language of Graphviz, e.g. for
+    /// `dot -Tsvg`. With `scope`, only the gates added in scopes with that name (see
+    /// `with_context!`) are included, along with the copy constraints involving them. `build` adds
+    /// further gates, e.g. for constants and public inputs, which this doesn't show.
+    pub fn to_graphviz(&self, scope: Option<&str>) -> String {
+        circuit_to_graphviz(
+            &self.gate_instances,
+            &self.copy_constraints,
+            &self.context_log,
+            &self.target_names,
+            scope,
+        )
+    }
+
+    /// Writes `to_graphviz(scope)` to `path`.
+    #[cfg(feature = "std")]
+    pub fn export_graphviz(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        scope: Option<&str>,
+    ) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_graphviz(scope))?;
+        Ok(())
+    }
+
     /// Find an available slot, of the form `(row, op)` for gate `G` using parameters `params`
     /// and constants `constants`. Parameters are any data used to differentiate which gate should be
     /// used for the given operation.
@@ -517,7 +612,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn fri_params(&self, degree_bits: usize) -> FriParams {
         self.config
             .fri_config
-            .fri_params(degree_bits, self.config.zero_knowledge)
+            .fri_params(degree_bits, self.config.is_hiding())
     }
 
     /// The number of (base field) `arithmetic` operations that can be performed in a single gate.
@@ -562,8 +657,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// The number of polynomial values that will be revealed per opening, both for the "regular"
     /// polynomials (which are opened at only one location) and for the Z polynomials (which are
     /// opened at two).
-    fn blinding_counts(&self) -> (usize, usize) {
-        let num_gates = self.gate_instances.len();
+    fn blinding_counts(&self, num_gates: usize) -> (usize, usize) {
         let mut degree_estimate = 1 << log2_ceil(num_gates);
 
         loop {
@@ -583,18 +677,52 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
-    fn blind_and_pad(&mut self) {
+    /// Blinds the witness if needed and pads the circuit to a power of two, returning the number of
+    /// rows before padding.
+    fn blind_and_pad(&mut self) -> usize {
         if self.config.zero_knowledge {
             self.blind();
         }
 
+        let num_unpadded_rows = self.gate_instances.len();
         while !self.gate_instances.len().is_power_of_two() {
             self.add_gate(NoopGate, vec![]);
         }
+        num_unpadded_rows
+    }
+
+    /// Logs how much of the padded circuit is padding, and which gate made the degree double, i.e.
+    /// the gate in the first row past the previous power of two.
+    fn log_padding(&self, num_gates: usize, num_unpadded_rows: usize) {
+        let degree = self.gate_instances.len();
+        let num_padding_gates = degree - num_unpadded_rows;
+        info!(
+            "{} of the {} rows ({:.1}%) are padding",
+            num_padding_gates,
+            degree,
+            100.0 * num_padding_gates as f64 / degree as f64
+        );
+        if degree > 1 {
+            let row = degree / 2;
+            if row < num_gates {
+                info!(
+                    "The degree was doubled to {} by {} at row {}, in {}",
+                    degree,
+                    self.gate_instances[row].gate_ref.0.id(),
+                    row,
+                    self.context_log.scope_of(row)
+                );
+            } else {
+                info!(
+                    "The degree was doubled to {} by the blinding gates, at row {}",
+                    degree, row
+                );
+            }
+        }
     }
 
     fn blind(&mut self) {
-        let (regular_poly_openings, z_openings) = self.blinding_counts();
+        let (regular_poly_openings, z_openings) = self.blinding_counts(self.gate_instances.len());
         info!(
             "Adding {} blinding terms for witness polynomials, and {}*2 for Z polynomials",
             regular_poly_openings, z_openings
@@ -720,6 +848,165 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Reports how the copy constraints added so far use the routed wires, to help choose
+    /// `num_routed_wires`.
+    pub fn routing_report(&self) -> RoutingReport {
+        let num_routed_wires = self.config.num_routed_wires;
+        let mut used_wires = HashSet::new();
+        let mut copy_constraints_by_highest_column = vec![0; num_routed_wires];
+        for &CopyConstraint { pair: (a, b), .. } in &self.copy_constraints {
+            let highest_column = [a, b]
+                .into_iter()
+                .filter_map(|target| match target {
+                    Target::Wire(wire) => {
+                        used_wires.insert(wire);
+                        Some(wire.column)
+                    }
+                    Target::VirtualTarget { .. } => None,
+                })
+                .max();
+            if let Some(column) = highest_column {
+                copy_constraints_by_highest_column[column] += 1;
+            }
+        }
+        let mut rows_used_per_column = vec![0; num_routed_wires];
+        for wire in used_wires {
+            rows_used_per_column[wire.column] += 1;
+        }
+
+        RoutingReport {
+            num_rows: self.num_gates(),
+            quotient_degree_factor: self.estimated_shape().quotient_degree_factor,
+            rows_used_per_column,
+            copy_constraints_by_highest_column,
+            num_copy_constraints: self.copy_constraints.len(),
+        }
+    }
+
+    /// Logs the utilization of each routed column at debug level, and suggestions for
+    /// `num_routed_wires` at info level. See `routing_report`.
+    pub fn print_routing_report(&self) {
+        let report = self.routing_report();
+        debug!("Routed wire utilization:");
+        for (column, &rows) in report.rows_used_per_column.iter().enumerate() {
+            debug!(
+                "- column {}: {} rows ({:.1}%), the highest wire of {} copy constraints",
+                column,
+                rows,
+                100.0 * report.utilization(column),
+                report.copy_constraints_by_highest_column[column]
+            );
+        }
+
+        let num_routed_wires = self.config.num_routed_wires;
+        let min_num_routed_wires = report.min_num_routed_wires();
+        if min_num_routed_wires < num_routed_wires {
+            info!(
+                "The copy constraints fit in {} of the {} routed wires; consider lowering \
+                `num_routed_wires`",
+                min_num_routed_wires, num_routed_wires
+            );
+        }
+        let suggested = report.suggested_num_routed_wires();
+        if suggested < min_num_routed_wires {
+            info!(
+                "Only {} of {} copy constraints use columns {} to {}; routing them differently \
+                would allow {} routed wires",
+                report.copy_constraints_beyond(suggested),
+                report.num_copy_constraints,
+                suggested,
+                min_num_routed_wires - 1,
+                suggested
+            );
+        }
+    }
+
+    /// The number of gates `build` adds before blinding and padding: the lookup arguments, the
+    /// public input hash and the gate exposing it, and the `ConstantGate`s needed to hold all
+    /// constants. This assumes the inner hasher uses one gate per permutation, as Poseidon does.
+    fn num_gates_added_by_build(&self) -> usize {
+        let num_permutations = ceil_div_usize(self.public_inputs.len(), SPONGE_RATE);
+        // Hashing the public inputs starts from a zero state.
+        let num_constants = self.constants_to_targets.len()
+            + usize::from(!self.constants_to_targets.contains_key(&F::ZERO));
+        let num_missing_generators = num_constants.saturating_sub(self.constant_generators.len());
+        let num_constant_gates = ceil_div_usize(num_missing_generators, self.config.num_constants);
+        self.num_lookup_argument_gates() + num_permutations + 1 + num_constant_gates
+    }
+
+    /// Estimates the size of the circuit `build` would produce from the gates added so far,
+    /// including the gates added by `build` itself, blinding and padding. Its
+    /// `num_padding_gates` tells how close the circuit is to the next power of two.
+    pub fn current_size_estimate(&self) -> SizeEstimate {
+        let num_gates = self.num_gates() + self.num_gates_added_by_build();
+        let num_blinding_gates = if self.config.zero_knowledge {
+            let (regular_poly_openings, z_openings) = self.blinding_counts(num_gates);
+            regular_poly_openings + 2 * z_openings
+        } else {
+            0
+        };
+        SizeEstimate {
+            num_gates,
+            num_blinding_gates,
+            degree: (num_gates + num_blinding_gates).next_power_of_two(),
+        }
+    }
+
+    /// Estimates the degree of the circuit `build` would produce from the gates added so far,
+    /// including the gates added by `build` itself, blinding and padding.
+    pub fn estimated_degree(&self) -> usize {
+        self.current_size_estimate().degree
+    }
+
+    fn estimated_shape(&self) -> CircuitShape {
+        let mut gates = self.gates.clone();
+        gates.insert(GateRef::new(PublicInputGate));
+        gates.insert(GateRef::new(NoopGate));
+        let mut gates = gates.into_iter().collect::<Vec<_>>();
+        gates.sort_unstable_by_key(|g| (g.0.degree(), g.0.id()));
+        let max_degree = selector_max_degree(
+            &gates,
+            self.selector_grouping,
+            self.config.max_quotient_degree_factor,
+        );
+        let quotient_degree_factor = max_degree - 1;
+        let num_selectors = selector_groups(&gates, max_degree).len();
+        let max_gate_constants = gates.iter().map(|g| g.0.num_constants()).max().unwrap();
+
+        CircuitShape {
+            degree_bits: log2_strict(self.estimated_degree()),
+            rate_bits: self.config.fri_config.rate_bits,
+            hiding: self.config.is_hiding(),
+            num_wires: self.config.num_wires,
+            num_routed_wires: self.config.num_routed_wires,
+            num_constants: num_selectors + max_gate_constants,
+            num_challenges: self.config.num_challenges,
+            num_partial_products: num_partial_products(
+                self.config.num_routed_wires,
+                quotient_degree_factor,
+            ),
+            quotient_degree_factor,
+            num_gate_constraints: gates.iter().map(|g| g.0.num_constraints()).sum(),
+            proof_of_work_bits: self.config.fri_config.proof_of_work_bits,
+            extension_degree: D,
+            field_bytes: size_of::<F>(),
+        }
+    }
+
+    /// Estimates the peak memory, in bytes, used to prove the circuit `build` would produce from
+    /// the gates added so far. This counts the polynomial batches the prover commits to, the
+    /// preprocessed constants and sigmas, the FRI layers and the witness.
+    pub fn estimated_prover_memory(&self) -> usize {
+        self.estimated_shape().prover_memory()
+    }
+
+    /// Estimates the time taken to prove the circuit `build` would produce from the gates added so
+    /// far, on a machine with the given profile. This only counts FFTs, hashing and constraint
+    /// evaluation, which dominate proving time for all but tiny circuits.
+    pub fn estimated_proving_time(&self, profile: &ProvingProfile) -> Duration {
+        self.estimated_shape().proving_time(profile)
+    }
+
     /// In PLONK's permutation argument, there's a slight chance of division by zero. We can
     /// mitigate this by randomizing some unused witness elements, so if proving fails with
     /// division by zero, the next attempt will have an (almost) independent chance of success.
@@ -733,18 +1020,38 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Builds a "full circuit", with both prover and verifier data.
-    pub fn build<C: GenericConfig<D, F = F>>(mut self) -> CircuitData<F, C, D> {
+    pub fn build<C: GenericConfig<D, F = F>>(self) -> CircuitData<F, C, D> {
+        let (prover_only, verifier_only, common) = self.build_components::<C>(true);
+        CircuitData {
+            prover_only: prover_only.expect("Prover data was requested"),
+            verifier_only,
+            common,
+        }
+    }
+
+    /// Builds the circuit's common and verifier-only data, plus the prover-only data if
+    /// `with_prover_data` is set. Skipping the prover-only data avoids collecting and indexing
+    /// witness generators and retaining the FFT root table and transposed sigma polynomials.
+    fn build_components<C: GenericConfig<D, F = F>>(
+        mut self,
+        with_prover_data: bool,
+    ) -> (
+        Option<ProverOnlyCircuitData<F, C, D>>,
+        VerifierOnlyCircuitData<C, D>,
+        CommonCircuitData<F, D>,
+    ) {
         let mut timing = TimingTree::new("preprocess", Level::Trace);
         #[cfg(feature = "std")]
         let start = Instant::now();
         let rate_bits = self.config.fri_config.rate_bits;
         let cap_height = self.config.fri_config.cap_height;
 
+        self.add_lookup_arguments::<C::InnerHasher>();
+
         // Hash the public inputs, and route them to a `PublicInputGate` which will enforce that
         // those hash wires match the claimed public inputs.
         let num_public_inputs = self.public_inputs.len();
-        let public_inputs_hash =
-            self.public_inputs_hash::<C::InnerHasher>(self.public_inputs.clone());
+        let public_inputs_hash = self.hash_public_inputs::<C::InnerHasher>();
         let pi_gate = self.add_gate(PublicInputGate, vec![]);
         for (&hash_part, wire) in public_inputs_hash
             .elements
@@ -784,13 +1091,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.add_simple_generator(const_gen);
         }
 
-        info!(
-            "Degree before blinding & padding: {}",
-            self.gate_instances.len()
-        );
-        self.blind_and_pad();
+        let num_gates = self.gate_instances.len();
+        info!("Degree before blinding & padding: {}", num_gates);
+        let num_unpadded_rows = self.blind_and_pad();
         let degree = self.gate_instances.len();
         info!("Degree after blinding & padding: {}", degree);
+        self.log_padding(num_gates, num_unpadded_rows);
         let degree_bits = log2_strict(degree);
         let fri_params = self.fri_params(degree_bits);
         assert!(
@@ -798,12 +1104,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             "FRI total reduction arity is too large.",
         );
 
-        let quotient_degree_factor = self.config.max_quotient_degree_factor;
         let mut gates = self.gates.iter().cloned().collect::<Vec<_>>();
         // Gates need to be sorted by their degrees (and ID to make the ordering deterministic) to compute the selector polynomials.
         gates.sort_unstable_by_key(|g| (g.0.degree(), g.0.id()));
+        let max_degree = selector_max_degree(
+            &gates,
+            self.selector_grouping,
+            self.config.max_quotient_degree_factor,
+        );
+        let quotient_degree_factor = max_degree - 1;
+        if quotient_degree_factor > self.config.max_quotient_degree_factor {
+            info!(
+                "Quotient degree factor raised to {} by a gate of that degree",
+                quotient_degree_factor
+            );
+        }
         let (mut constant_vecs, selectors_info) =
-            selector_polynomials(&gates, &self.gate_instances, quotient_degree_factor + 1);
+            selector_polynomials(&gates, &self.gate_instances, max_degree);
         constant_vecs.extend(self.constant_polys());
         let num_constants = constant_vecs.len();
 
@@ -816,59 +1133,36 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.sigma_vecs(&k_is, &subgroup)
         );
 
-        // Precompute FFT roots.
-        let max_fft_points = 1 << (degree_bits + max(rate_bits, log2_ceil(quotient_degree_factor)));
-        let fft_root_table = fft_root_table(max_fft_points);
+        // Precompute FFT roots. These are only kept around for the prover; when building verifier
+        // data only, the commitment below computes the (smaller) table it needs itself.
+        let fft_root_table = with_prover_data.then(|| {
+            let max_fft_points =
+                1 << (degree_bits + max(rate_bits, log2_ceil(quotient_degree_factor)));
+            fft_root_table(max_fft_points)
+        });
 
-        let constants_sigmas_vecs = [constant_vecs, sigma_vecs.clone()].concat();
+        let sigmas = with_prover_data.then(|| transpose_poly_values(sigma_vecs.clone()));
+        let constants_sigmas_vecs = [constant_vecs, sigma_vecs].concat();
         let constants_sigmas_commitment = PolynomialBatch::from_values(
             constants_sigmas_vecs,
             rate_bits,
             PlonkOracle::CONSTANTS_SIGMAS.blinding,
             cap_height,
             &mut timing,
-            Some(&fft_root_table),
+            fft_root_table.as_ref(),
         );
 
-        // Map between gates where not all generators are used and the gate's number of used generators.
-        let incomplete_gates = self
-            .current_slots
-            .values()
-            .flat_map(|current_slot| current_slot.current_slot.values().copied())
-            .collect::<HashMap<_, _>>();
-
-        // Add gate generators.
-        self.add_generators(
-            self.gate_instances
-                .iter()
-                .enumerate()
-                .flat_map(|(index, gate)| {
-                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
-                    // Remove unused generators, if any.
-                    if let Some(&op) = incomplete_gates.get(&index) {
-                        gens.drain(op..);
-                    }
-                    gens
-                })
-                .collect(),
-        );
+        let quotient_degree_bits = log2_ceil(quotient_degree_factor);
+        let constants_sigmas_quotient_values =
+            (with_prover_data && quotient_degree_bits > rate_bits).then(|| {
+                quotient_domain_rows(
+                    &constants_sigmas_commitment,
+                    quotient_degree_bits,
+                    fft_root_table.as_ref(),
+                )
+            });
 
-        // Index generator indices by their watched targets.
-        let mut generator_indices_by_watches = BTreeMap::new();
-        for (i, generator) in self.generators.iter().enumerate() {
-            for watch in generator.watch_list() {
-                let watch_index = forest.target_index(watch);
-                let watch_rep_index = forest.parents[watch_index];
-                generator_indices_by_watches
-                    .entry(watch_rep_index)
-                    .or_insert_with(Vec::new)
-                    .push(i);
-            }
-        }
-        for indices in generator_indices_by_watches.values_mut() {
-            indices.dedup();
-            indices.shrink_to_fit();
-        }
+        let prover_generators = with_prover_data.then(|| self.prover_generators(&forest));
 
         let num_gate_constraints = gates
             .iter()
@@ -881,17 +1175,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
         let domain_separator = self.domain_separator.unwrap_or_default();
-        let domain_separator_digest = C::Hasher::hash_pad(&domain_separator);
-        // TODO: This should also include an encoding of gate constraints.
-        let circuit_digest_parts = [
-            constants_sigmas_cap.flatten(),
-            domain_separator_digest.to_vec(),
-            vec![
-                F::from_canonical_usize(degree_bits),
-                /* Add other circuit data here */
-            ],
-        ];
-        let circuit_digest = C::Hasher::hash_no_pad(&circuit_digest_parts.concat());
+        let circuit_digest = VerifierOnlyCircuitData::<C, D>::compute_circuit_digest(
+            &constants_sigmas_cap,
+            &domain_separator,
+            degree_bits,
+        );
 
         let common = CommonCircuitData {
             config: self.config,
@@ -909,31 +1197,83 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             assert_eq!(goal_data, common, "The expected circuit data passed to cyclic recursion method did not match the actual circuit");
         }
 
-        let prover_only = ProverOnlyCircuitData {
-            generators: self.generators,
-            generator_indices_by_watches,
-            constants_sigmas_commitment,
-            sigmas: transpose_poly_values(sigma_vecs),
-            subgroup,
-            public_inputs: self.public_inputs,
-            representative_map: forest.parents,
-            fft_root_table: Some(fft_root_table),
-            circuit_digest,
-        };
-
         let verifier_only = VerifierOnlyCircuitData {
             constants_sigmas_cap,
             circuit_digest,
         };
 
+        let prover_only = prover_generators.map(|(generators, generator_indices_by_watches)| {
+            ProverOnlyCircuitData {
+                generators,
+                generator_indices_by_watches,
+                constants_sigmas_commitment,
+                constants_sigmas_quotient_values,
+                sigmas: sigmas.unwrap(),
+                subgroup,
+                public_inputs: self.public_inputs,
+                representative_map: forest.parents,
+                fft_root_table,
+                circuit_digest,
+            }
+        });
+
         timing.print();
         #[cfg(feature = "std")]
         debug!("Building circuit took {}s", start.elapsed().as_secs_f32());
-        CircuitData {
-            prover_only,
-            verifier_only,
-            common,
+        (prover_only, verifier_only, common)
+    }
+
+    /// Collects the witness generators of the circuit, including those of its gates, and indexes
+    /// them by the representative of each target they watch.
+    #[allow(clippy::type_complexity)]
+    fn prover_generators(
+        &mut self,
+        forest: &Forest,
+    ) -> (
+        Vec<Box<dyn WitnessGenerator<F>>>,
+        BTreeMap<usize, Vec<usize>>,
+    ) {
+        // Map between gates where not all generators are used and the gate's number of used generators.
+        let incomplete_gates = self
+            .current_slots
+            .values()
+            .flat_map(|current_slot| current_slot.current_slot.values().copied())
+            .collect::<HashMap<_, _>>();
+
+        // Add gate generators.
+        let mut generators = core::mem::take(&mut self.generators);
+        generators.extend(
+            self.gate_instances
+                .iter()
+                .enumerate()
+                .flat_map(|(index, gate)| {
+                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
+                    // Remove unused generators, if any.
+                    if let Some(&op) = incomplete_gates.get(&index) {
+                        gens.drain(op..);
+                    }
+                    gens
+                }),
+        );
+
+        // Index generator indices by their watched targets.
+        let mut generator_indices_by_watches = BTreeMap::new();
+        for (i, generator) in generators.iter().enumerate() {
+            for watch in generator.watch_list() {
+                let watch_index = forest.target_index(watch);
+                let watch_rep_index = forest.parents[watch_index];
+                generator_indices_by_watches
+                    .entry(watch_rep_index)
+                    .or_insert_with(Vec::new)
+                    .push(i);
+            }
         }
+        for indices in generator_indices_by_watches.values_mut() {
+            indices.dedup();
+            indices.shrink_to_fit();
+        }
+
+        (generators, generator_indices_by_watches)
     }
 
     /// Builds a "prover circuit", with data needed to generate proofs but not verify them.
@@ -944,9 +1284,233 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Builds a "verifier circuit", with data needed to verify proofs but not generate them.
+    ///
+    /// This skips the prover-only preprocessing (witness generators and their watch index, the FFT
+    /// root table, and the transposed sigma polynomials), so it is cheaper than `build` when only
+    /// the verifier key and common data are needed.
     pub fn build_verifier<C: GenericConfig<D, F = F>>(self) -> VerifierCircuitData<F, C, D> {
-        // TODO: Can skip parts of this.
-        let circuit_data = self.build();
-        circuit_data.verifier_data()
+        let (_, verifier_only, common) = self.build_components::<C>(false);
+        VerifierCircuitData {
+            verifier_only,
+            common,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use anyhow::Result;
+
+    use crate::field::types::{Field, PrimeField};
+    use crate::gates::noop::NoopGate;
+    use crate::gates::selectors::SelectorGrouping;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::cost_estimate::ProvingProfile;
+
+    /// Advice, as a downstream crate would implement it: the square root of `x`.
+    #[derive(Debug)]
+    struct SqrtGenerator {
+        x: Target,
+        sqrt: Target,
+    }
+
+    impl<F: PrimeField> SimpleGenerator<F> for SqrtGenerator {
+        fn dependencies(&self) -> Vec<Target> {
+            vec![self.x]
+        }
+
+        fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+            let x = witness.get_target(self.x);
+            out_buffer.set_target(self.sqrt, x.sqrt().expect("Not a square"));
+        }
+    }
+
+    #[test]
+    fn test_add_generator() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let inverse = builder.add_virtual_target();
+        builder.add_generator(vec![x], vec![inverse], |values| vec![values[0].inverse()]);
+        let product = builder.mul(x, inverse);
+        builder.assert_one(product);
+
+        let square = builder.square(x);
+        let sqrt = builder.add_virtual_target();
+        builder.add_simple_generator(SqrtGenerator { x: square, sqrt });
+        let sqrt_squared = builder.square(sqrt);
+        builder.connect(sqrt_squared, square);
+        builder.register_public_input(inverse);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(7));
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs[1], F::from_canonical_u64(7).inverse());
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_build_verifier_matches_build() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let build_circuit = || {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let x = builder.add_virtual_target();
+            let y = builder.square(x);
+            builder.register_public_input(y);
+            (builder, x)
+        };
+
+        let (builder, x) = build_circuit();
+        let data = builder.build::<C>();
+        let (builder, _) = build_circuit();
+        let verifier_data = builder.build_verifier::<C>();
+
+        assert_eq!(verifier_data.verifier_only, data.verifier_only);
+        assert_eq!(verifier_data.common, data.common);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+        verifier_data.verify(proof)
+    }
+
+    #[test]
+    fn test_selector_grouping() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let build_circuit = |grouping: SelectorGrouping| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            builder.set_selector_grouping(grouping);
+            let x = builder.add_virtual_target();
+            let y = builder.mul(x, x);
+            let h = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x, y]);
+            builder.register_public_inputs(&h.elements);
+            (builder.build::<C>(), x)
+        };
+
+        let (greedy, _) = build_circuit(SelectorGrouping::Greedy);
+        let (data, x) = build_circuit(SelectorGrouping::MinDegree {
+            max_selectors: None,
+        });
+        assert!(data.common.quotient_degree_factor < greedy.common.quotient_degree_factor);
+        assert_eq!(
+            data.common.selectors_info.num_selectors(),
+            greedy.common.selectors_info.num_selectors()
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_size_estimate_padding() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+
+        let estimate = builder.current_size_estimate();
+        assert_eq!(estimate.num_blinding_gates, 0);
+        for _ in 0..estimate.num_padding_gates() {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        assert_eq!(builder.current_size_estimate().num_padding_gates(), 0);
+        assert_eq!(builder.current_size_estimate().degree, estimate.degree);
+
+        builder.add_gate(NoopGate, vec![]);
+        assert_eq!(builder.current_size_estimate().degree, 2 * estimate.degree);
+        let data = builder.build::<C>();
+        assert_eq!(data.common.degree(), 2 * estimate.degree);
+    }
+
+    #[test]
+    fn test_routing_report() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+
+        // A single arithmetic operation only uses the first few routed columns of one row.
+        let report = builder.routing_report();
+        assert_eq!(report.num_rows, 1);
+        assert_eq!(report.rows_used_per_column.len(), config.num_routed_wires);
+        assert_eq!(report.utilization(0), 1.0);
+        let min_num_routed_wires = report.min_num_routed_wires();
+        assert!(min_num_routed_wires < config.num_routed_wires);
+        assert!(min_num_routed_wires > report.quotient_degree_factor);
+        assert_eq!(report.copy_constraints_beyond(min_num_routed_wires), 0);
+        assert!(report.suggested_num_routed_wires() <= min_num_routed_wires);
+        builder.print_routing_report();
+    }
+
+    #[test]
+    fn test_estimates_match_build() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let build_circuit = |config: CircuitConfig, num_squarings: usize| {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let mut x = builder.add_virtual_target();
+            builder.register_public_input(x);
+            for i in 0..num_squarings {
+                x = builder.square(x);
+                let c = builder.constant(F::from_canonical_usize(i));
+                x = builder.add(x, c);
+            }
+            builder.register_public_input(x);
+            builder
+        };
+
+        for config in [
+            CircuitConfig::standard_recursion_config(),
+            CircuitConfig::standard_recursion_zk_config(),
+        ] {
+            let mut memory = 0;
+            let mut time = Duration::ZERO;
+            for num_squarings in [0, 5000] {
+                let builder = build_circuit(config.clone(), num_squarings);
+                let estimated_degree = builder.estimated_degree();
+                let estimated_memory = builder.estimated_prover_memory();
+                let estimated_time = builder.estimated_proving_time(&ProvingProfile::default());
+                let data = builder.build::<C>();
+                assert_eq!(estimated_degree, data.common.degree());
+
+                assert!(estimated_memory > memory);
+                assert!(estimated_time > time);
+                memory = estimated_memory;
+                time = estimated_time;
+            }
+        }
     }
 }
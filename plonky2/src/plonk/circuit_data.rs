@@ -4,10 +4,12 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::{Range, RangeFrom};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use static_assertions::assert_impl_all;
 
 use crate::field::extension::Extendable;
 use crate::field::fft::FftRootTable;
+use crate::field::goldilocks_field::GoldilocksField;
 use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
 use crate::fri::reduction_strategies::FriReductionStrategy;
@@ -15,21 +17,28 @@ use crate::fri::structure::{
     FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
     FriPolynomialInfo,
 };
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::{FriConfig, FriParams, FriPowHash};
 use crate::gates::gate::GateRef;
+use crate::gates::poseidon::PoseidonGate;
 use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hashing::SPONGE_WIDTH;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::generator::WitnessGenerator;
+use crate::iop::generator::{ungenerated_targets, WitnessGenerator};
 use crate::iop::target::Target;
+use crate::iop::transcript::NativeTranscript;
 use crate::iop::witness::PartialWitness;
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher, PoseidonGoldilocksConfig};
+use crate::plonk::constraint_check::check_witness;
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
-use crate::plonk::verifier::verify;
+use crate::plonk::prover::{prove, prove_with_options, prove_with_transcript, ProverOptions};
+use crate::plonk::verifier::{
+    verify, verify_compressed_strict, verify_strict, verify_with_transcript,
+};
+use crate::util::serialization::{Read, Write};
 use crate::util::timing::TimingTree;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -44,9 +53,16 @@ pub struct CircuitConfig {
     /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
     /// `degree / |F|`.
     pub num_challenges: usize,
+    /// Whether to blind the witness polynomials with random gates and salt the Merkle leaves of
+    /// their commitments, for zero knowledge.
     pub zero_knowledge: bool,
+    /// Whether to salt the Merkle leaves of the commitments to witness polynomials (wires, `Z`s and
+    /// partial products, and quotient), which makes the commitments hiding even without
+    /// `zero_knowledge`. Implied by `zero_knowledge`.
+    pub hiding_commitments: bool,
     /// A cap on the quotient polynomial's degree factor. The actual degree factor is derived
-    /// systematically, but will never exceed this value.
+    /// systematically, and only exceeds this value when a gate has a higher degree, in which case
+    /// the quotient is split into as many chunks as that degree.
     pub max_quotient_degree_factor: usize,
     pub fri_config: FriConfig,
 }
@@ -62,6 +78,109 @@ impl CircuitConfig {
         self.num_wires - self.num_routed_wires
     }
 
+    /// Whether the Merkle leaves of witness polynomial commitments are salted.
+    pub fn is_hiding(&self) -> bool {
+        self.zero_knowledge || self.hiding_commitments
+    }
+
+    /// Checks that circuits over `F` can be built with this config, returning an error which names
+    /// the offending fields otherwise. `CircuitBuilder::new` panics with this error.
+    pub fn validate<F: RichField + Extendable<D>, const D: usize>(&self) -> Result<()> {
+        ensure!(
+            self.num_routed_wires <= self.num_wires,
+            "num_routed_wires ({}) exceeds num_wires ({})",
+            self.num_routed_wires,
+            self.num_wires
+        );
+        ensure!(
+            self.num_routed_wires > self.max_quotient_degree_factor,
+            "num_routed_wires ({}) must exceed max_quotient_degree_factor ({}) for the \
+            permutation argument",
+            self.num_routed_wires,
+            self.max_quotient_degree_factor
+        );
+        ensure!(
+            self.num_constants >= 2,
+            "num_constants ({}) is too small for the arithmetic gates, which take 2 constants",
+            self.num_constants
+        );
+        ensure!(self.num_challenges > 0, "num_challenges must be positive");
+
+        let fri_config = &self.fri_config;
+        ensure!(
+            fri_config.rate_bits > 0,
+            "fri_config.rate_bits must be positive, or the codewords have no redundancy"
+        );
+        ensure!(
+            fri_config.num_query_rounds > 0,
+            "fri_config.num_query_rounds must be positive"
+        );
+        match &fri_config.reduction_strategy {
+            FriReductionStrategy::Fixed(arity_bits) => ensure!(
+                !arity_bits.contains(&0),
+                "The FRI reduction arities {:?} include an arity of 2^0",
+                arity_bits
+            ),
+            &FriReductionStrategy::ConstantArityBits(arity_bits, _) => ensure!(
+                arity_bits > 0,
+                "The FRI reduction arity must be more than 2^0"
+            ),
+            &FriReductionStrategy::MinSize(max_arity_bits) => {
+                ensure!(
+                    max_arity_bits != Some(0),
+                    "The FRI reduction arity must be more than 2^0"
+                );
+                // `MinSize` ignores the cap height, and may reduce down to a layer of
+                // `2^rate_bits` leaves.
+                ensure!(
+                    fri_config.cap_height <= fri_config.rate_bits,
+                    "fri_config.cap_height ({}) exceeds fri_config.rate_bits ({}), so the last \
+                    FRI layer chosen by MinSize may be too small for the cap",
+                    fri_config.cap_height,
+                    fri_config.rate_bits
+                );
+            }
+        }
+
+        // Conjectured FRI security; see the ethSTARK paper.
+        let fri_field_bits = F::Extension::order().bits() as usize;
+        let fri_security_bits = fri_field_bits.min(fri_config.conjectured_security_bits());
+        ensure!(
+            fri_security_bits >= self.security_bits,
+            "FRI params fall short of target security: {} bits, for security_bits = {}",
+            fri_security_bits,
+            self.security_bits
+        );
+        Ok(())
+    }
+
+    /// Checks that `PoseidonGate` fits in circuits over `F` with this config. Poseidon, the hasher
+    /// of the built-in configs, permutes with this gate in circuits that hash their public inputs,
+    /// check Merkle proofs or verify proofs recursively. `CircuitBuilder::add_gate` panics with
+    /// this error when such a circuit adds the gate.
+    pub fn validate_poseidon<F: RichField + Extendable<D>, const D: usize>(&self) -> Result<()> {
+        let poseidon_wires = PoseidonGate::<F, D>::end();
+        ensure!(
+            self.num_wires >= poseidon_wires,
+            "num_wires ({}) is too small for PoseidonGate, which needs {} wires for a sponge of \
+            width {}",
+            self.num_wires,
+            poseidon_wires,
+            SPONGE_WIDTH
+        );
+        // Its inputs, outputs and swap flag are routed.
+        let poseidon_routed_wires = PoseidonGate::<F, D>::WIRE_SWAP + 1;
+        ensure!(
+            self.num_routed_wires >= poseidon_routed_wires,
+            "num_routed_wires ({}) is too small for PoseidonGate, which routes {} wires for a \
+            sponge of width {}",
+            self.num_routed_wires,
+            poseidon_routed_wires,
+            SPONGE_WIDTH
+        );
+        Ok(())
+    }
+
     /// A typical recursion config, without zero-knowledge, targeting ~100 bit security.
     pub fn standard_recursion_config() -> Self {
         Self {
@@ -72,11 +191,13 @@ impl CircuitConfig {
             security_bits: 100,
             num_challenges: 2,
             zero_knowledge: false,
+            hiding_commitments: false,
             max_quotient_degree_factor: 8,
             fri_config: FriConfig {
                 rate_bits: 3,
                 cap_height: 4,
                 proof_of_work_bits: 16,
+                pow_hash: FriPowHash::Field,
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 28,
             },
@@ -106,6 +227,10 @@ impl CircuitConfig {
 }
 
 /// Circuit data required by the prover or the verifier.
+///
+/// Proving and verifying only borrow the circuit data, and all of its components are `Send + Sync`,
+/// so a single instance can be wrapped in an `Arc` and used to generate proofs for several
+/// witnesses concurrently.
 pub struct CircuitData<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     pub prover_only: ProverOnlyCircuitData<F, C, D>,
     pub verifier_only: VerifierOnlyCircuitData<C, D>,
@@ -124,10 +249,87 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    /// Like `prove`, but running on the thread pool and with the witness layout given in `options`.
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            options,
+        )
+    }
+
+    /// Like `prove`, but deriving the Fiat-Shamir challenges from the given transcript, which must
+    /// be fresh. See `verify_with_transcript`.
+    pub fn prove_with_transcript<T: NativeTranscript<F, C::Hasher, D>>(
+        &self,
+        inputs: PartialWitness<F>,
+        transcript: &mut T,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_transcript(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            transcript,
+        )
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof generated with `prove_with_transcript`, deriving the Fiat-Shamir challenges
+    /// from the given transcript, which must be fresh and of the same kind as the prover's.
+    pub fn verify_with_transcript<T: NativeTranscript<F, C::Hasher, D>>(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        transcript: &mut T,
+    ) -> Result<()> {
+        verify_with_transcript(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            transcript,
+        )
+    }
+
+    /// Runs `check_public_inputs` on the proof's public inputs, then verifies the proof only if it
+    /// passes. Application-level checks, e.g. that a Merkle root is recent, are much cheaper than
+    /// verification, so this rejects invalid submissions early.
+    pub fn verify_with(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        check_public_inputs: impl FnOnce(&[F]) -> Result<()>,
+    ) -> Result<()> {
+        check_public_inputs(&proof_with_pis.public_inputs)?;
+        self.verify(proof_with_pis)
+    }
+
+    /// Decodes a proof encoded by `ProofWithPublicInputs::to_bytes` from `reader`, as it's read,
+    /// then verifies it. See `ProofWithPublicInputs::from_reader`.
+    #[cfg(feature = "std")]
+    pub fn verify_from_reader(&self, reader: impl std::io::Read) -> Result<()> {
+        self.verify(ProofWithPublicInputs::from_reader(reader, &self.common)?)
+    }
+
+    /// Runs witness generation on `inputs` without proving, and returns the targets which witness
+    /// generators were still waiting on. An empty result means `inputs` suffice to generate a proof.
+    pub fn ungenerated_targets(&self, inputs: PartialWitness<F>) -> Result<Vec<Target>> {
+        ungenerated_targets(inputs, &self.prover_only, &self.common)
+    }
+
+    /// Checks that `inputs` make a witness satisfying the circuit's constraints, without proving,
+    /// and returns the proof's public inputs. See `constraint_check`.
+    pub fn check_witness(&self, inputs: PartialWitness<F>) -> Result<Vec<F>> {
+        check_witness(inputs, &self.prover_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -135,6 +337,18 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
 
+    /// Decodes a proof encoded by `ProofWithPublicInputs::to_bytes` and verifies it, also rejecting
+    /// any encoding which isn't the canonical encoding of a valid proof. Systems which identify
+    /// proofs by their hash should use this, so that a third party can't submit a malleated copy.
+    pub fn verify_strict(&self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        verify_strict(bytes.as_ref(), &self.verifier_only, &self.common)
+    }
+
+    /// Like `verify_strict`, for a proof encoded by `CompressedProofWithPublicInputs::to_bytes`.
+    pub fn verify_compressed_strict(&self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        verify_compressed_strict(bytes.as_ref(), &self.verifier_only, &self.common)
+    }
+
     pub fn compress(
         &self,
         proof: ProofWithPublicInputs<F, C, D>,
@@ -201,6 +415,37 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    /// Like `prove`, but running on the thread pool and with the witness layout given in `options`.
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            options,
+        )
+    }
+
+    /// Like `prove`, but deriving the Fiat-Shamir challenges from the given transcript, which must
+    /// be fresh. See `verify_with_transcript`.
+    pub fn prove_with_transcript<T: NativeTranscript<F, C::Hasher, D>>(
+        &self,
+        inputs: PartialWitness<F>,
+        transcript: &mut T,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_transcript(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            transcript,
+        )
+    }
 }
 
 /// Circuit data required by the prover.
@@ -221,14 +466,111 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof generated with `prove_with_transcript`, deriving the Fiat-Shamir challenges
+    /// from the given transcript, which must be fresh and of the same kind as the prover's.
+    pub fn verify_with_transcript<T: NativeTranscript<F, C::Hasher, D>>(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        transcript: &mut T,
+    ) -> Result<()> {
+        verify_with_transcript(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            transcript,
+        )
+    }
+
+    /// Runs `check_public_inputs` on the proof's public inputs, then verifies the proof only if it
+    /// passes. Application-level checks, e.g. that a Merkle root is recent, are much cheaper than
+    /// verification, so this rejects invalid submissions early.
+    pub fn verify_with(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        check_public_inputs: impl FnOnce(&[F]) -> Result<()>,
+    ) -> Result<()> {
+        check_public_inputs(&proof_with_pis.public_inputs)?;
+        self.verify(proof_with_pis)
+    }
+
+    /// Decodes a proof encoded by `ProofWithPublicInputs::to_bytes` from `reader`, as it's read,
+    /// then verifies it. See `ProofWithPublicInputs::from_reader`.
+    #[cfg(feature = "std")]
+    pub fn verify_from_reader(&self, reader: impl std::io::Read) -> Result<()> {
+        self.verify(ProofWithPublicInputs::from_reader(reader, &self.common)?)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
     ) -> Result<()> {
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
+
+    /// Decodes a proof encoded by `ProofWithPublicInputs::to_bytes` and verifies it, also rejecting
+    /// any encoding which isn't the canonical encoding of a valid proof. Systems which identify
+    /// proofs by their hash should use this, so that a third party can't submit a malleated copy.
+    pub fn verify_strict(&self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        verify_strict(bytes.as_ref(), &self.verifier_only, &self.common)
+    }
+
+    /// Like `verify_strict`, for a proof encoded by `CompressedProofWithPublicInputs::to_bytes`.
+    pub fn verify_compressed_strict(&self, bytes: impl AsRef<[u8]>) -> Result<()> {
+        verify_compressed_strict(bytes.as_ref(), &self.verifier_only, &self.common)
+    }
+
+    /// Checks that `circuit_digest` is the digest of this circuit with domain separator
+    /// `domain_separator`, i.e. that it matches `constants_sigmas_cap` and the degree, rather than
+    /// trusting the digest stored next to them. Like the digest, this doesn't cover the rest of
+    /// `common`, e.g. the gates or the FRI parameters.
+    pub fn check_circuit_digest(&self, domain_separator: &[F]) -> Result<()> {
+        let circuit_digest = VerifierOnlyCircuitData::<C, D>::compute_circuit_digest(
+            &self.verifier_only.constants_sigmas_cap,
+            domain_separator,
+            self.common.degree_bits(),
+        );
+        ensure!(
+            circuit_digest == self.verifier_only.circuit_digest,
+            "The circuit digest doesn't match the circuit's constants, sigmas and degree"
+        );
+        Ok(())
+    }
+
+    /// Serializes the common data, then the verifier-only data, so that a proof can be verified
+    /// from these bytes alone.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_common_circuit_data(&self.common)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+            .write_verifier_only_circuit_data(&self.verifier_only)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes verifier data encoded by `to_bytes`. See `CommonCircuitData::from_bytes` for
+    /// which circuits can be read.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
+        let mut bytes = bytes.as_ref();
+        let common = bytes
+            .read_common_circuit_data()
+            .map_err(anyhow::Error::msg)?;
+        let verifier_only = bytes
+            .read_verifier_only_circuit_data(&common)
+            .map_err(anyhow::Error::msg)?;
+        Ok(Self {
+            verifier_only,
+            common,
+        })
+    }
 }
 
+// Proving takes the circuit data by shared reference, so sharing it across threads must be possible.
+assert_impl_all!(CircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>: Send, Sync);
+assert_impl_all!(ProverCircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>: Send, Sync);
+assert_impl_all!(VerifierCircuitData<GoldilocksField, PoseidonGoldilocksConfig, 2>: Send, Sync);
+
 /// Circuit data required by the prover, but not the verifier.
 pub struct ProverOnlyCircuitData<
     F: RichField + Extendable<D>,
@@ -241,6 +583,10 @@ pub struct ProverOnlyCircuitData<
     pub generator_indices_by_watches: BTreeMap<usize, Vec<usize>>,
     /// Commitments to the constants polynomials and sigma polynomials.
     pub constants_sigmas_commitment: PolynomialBatch<F, C, D>,
+    /// The values of the constants and sigma polynomials on the quotient domain, one row per point,
+    /// when it's larger than the LDE committed to, i.e. when the quotient degree exceeds the rate.
+    /// Every proof needs them, so they're computed once with the circuit rather than by each.
+    pub constants_sigmas_quotient_values: Option<Vec<Vec<F>>>,
     /// The transpose of the list of sigma polynomials.
     pub sigmas: Vec<Vec<F>>,
     /// Subgroup of order `degree`.
@@ -250,7 +596,8 @@ pub struct ProverOnlyCircuitData<
     /// A map from each `Target`'s index to the index of its representative in the disjoint-set
     /// forest.
     pub representative_map: Vec<usize>,
-    /// Pre-computed roots for faster FFT.
+    /// Pre-computed roots for faster FFT, for the largest size the prover needs. Smaller FFTs use a
+    /// prefix of the table.
     pub fft_root_table: Option<FftRootTable<F>>,
     /// A digest of the "circuit" (i.e. the instance, minus public inputs), which can be used to
     /// seed Fiat-Shamir.
@@ -258,7 +605,7 @@ pub struct ProverOnlyCircuitData<
 }
 
 /// Circuit data required by the verifier, but not the prover.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
     /// A commitment to each constant polynomial and each permutation polynomial.
     pub constants_sigmas_cap: MerkleCap<C::F, C::Hasher>,
@@ -267,6 +614,49 @@ pub struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
     pub circuit_digest: <<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
 }
 
+impl<C: GenericConfig<D>, const D: usize> VerifierOnlyCircuitData<C, D> {
+    /// The digest of the circuit whose constants and sigmas are committed to by
+    /// `constants_sigmas_cap`, with domain separator `domain_separator` and `2^degree_bits` rows,
+    /// as `circuit_digest` is computed when the circuit is built.
+    pub fn compute_circuit_digest(
+        constants_sigmas_cap: &MerkleCap<C::F, C::Hasher>,
+        domain_separator: &[C::F],
+        degree_bits: usize,
+    ) -> <<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash {
+        let domain_separator_digest = C::Hasher::hash_pad(domain_separator);
+        // TODO: This should also include an encoding of gate constraints.
+        let circuit_digest_parts = [
+            constants_sigmas_cap.flatten(),
+            domain_separator_digest.to_vec(),
+            vec![
+                C::F::from_canonical_usize(degree_bits),
+                /* Add other circuit data here */
+            ],
+        ];
+        C::Hasher::hash_no_pad(&circuit_digest_parts.concat())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_verifier_only_circuit_data(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes verifier data encoded by `to_bytes`, for a circuit with `common_data`.
+    /// Malformed input gives an error rather than a panic.
+    pub fn from_bytes(
+        bytes: impl AsRef<[u8]>,
+        common_data: &CommonCircuitData<C::F, D>,
+    ) -> Result<Self> {
+        let mut bytes = bytes.as_ref();
+        bytes
+            .read_verifier_only_circuit_data(common_data)
+            .map_err(anyhow::Error::msg)
+    }
+}
+
 /// Circuit data required by both the prover and the verifier.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct CommonCircuitData<F: RichField + Extendable<D>, const D: usize> {
@@ -299,6 +689,29 @@ pub struct CommonCircuitData<F: RichField + Extendable<D>, const D: usize> {
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
+    /// Serializes the circuit's parameters, with each gate encoded as its ID.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_common_circuit_data(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes common data encoded by `to_bytes`. This fails if the circuit uses gates which
+    /// `gate_from_id` doesn't know, e.g. gates defined outside this crate, or if any bytes remain.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
+        let mut bytes = bytes.as_ref();
+        let common_data = bytes
+            .read_common_circuit_data()
+            .map_err(anyhow::Error::msg)?;
+        ensure!(
+            bytes.is_empty(),
+            "Trailing bytes after the common circuit data"
+        );
+        Ok(common_data)
+    }
+
     pub const fn degree_bits(&self) -> usize {
         self.fri_params.degree_bits
     }
@@ -395,23 +808,27 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         }
     }
 
-    fn fri_oracles(&self) -> Vec<FriOracleInfo> {
+    pub(crate) fn fri_oracles(&self) -> Vec<FriOracleInfo> {
         vec![
             FriOracleInfo {
                 num_polys: self.num_preprocessed_polys(),
                 blinding: PlonkOracle::CONSTANTS_SIGMAS.blinding,
+                degree_bits: None,
             },
             FriOracleInfo {
                 num_polys: self.config.num_wires,
                 blinding: PlonkOracle::WIRES.blinding,
+                degree_bits: None,
             },
             FriOracleInfo {
                 num_polys: self.num_zs_partial_products_polys(),
                 blinding: PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
+                degree_bits: None,
             },
             FriOracleInfo {
                 num_polys: self.num_quotient_polys(),
                 blinding: PlonkOracle::QUOTIENT.blinding,
+                degree_bits: None,
             },
         ]
     }
@@ -478,3 +895,364 @@ pub struct VerifierCircuitTarget {
     /// seed Fiat-Shamir.
     pub circuit_digest: HashOutTarget,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use anyhow::{ensure, Result};
+
+    use crate::field::types::{Field, PrimeField64};
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::FriConfig;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, WitnessLayout, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::prover::ProverOptions;
+
+    #[test]
+    fn test_validate_config() {
+        const D: usize = 2;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+
+        let standard = CircuitConfig::standard_recursion_config();
+        for config in [
+            standard.clone(),
+            CircuitConfig::standard_recursion_zk_config(),
+            CircuitConfig::wide_ecc_config(),
+        ] {
+            config.validate::<F, D>().unwrap();
+            config.validate_poseidon::<F, D>().unwrap();
+        }
+
+        let error = |config: CircuitConfig| config.validate::<F, D>().unwrap_err().to_string();
+        assert!(error(CircuitConfig {
+            num_routed_wires: 136,
+            ..standard.clone()
+        })
+        .starts_with("num_routed_wires (136) exceeds num_wires (135)"));
+        assert!(error(CircuitConfig {
+            fri_config: FriConfig {
+                reduction_strategy: FriReductionStrategy::MinSize(None),
+                cap_height: 4,
+                rate_bits: 3,
+                ..standard.fri_config.clone()
+            },
+            ..standard.clone()
+        })
+        .contains("cap_height (4) exceeds fri_config.rate_bits (3)"));
+
+        // Configs too narrow for Poseidon are valid for circuits that don't use it.
+        let narrow = CircuitConfig {
+            num_routed_wires: 20,
+            ..standard.clone()
+        };
+        narrow.validate::<F, D>().unwrap();
+        let poseidon_error =
+            |config: CircuitConfig| config.validate_poseidon::<F, D>().unwrap_err().to_string();
+        assert!(poseidon_error(CircuitConfig {
+            num_wires: 100,
+            num_routed_wires: 80,
+            ..standard.clone()
+        })
+        .contains("too small for PoseidonGate"));
+        assert!(poseidon_error(narrow).contains("routes 25 wires"));
+
+        assert!(error(CircuitConfig {
+            security_bits: 200,
+            ..standard
+        })
+        .starts_with("FRI params fall short of target security"));
+    }
+
+    #[test]
+    fn test_narrow_config_without_poseidon() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig {
+            num_routed_wires: 20,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.cube(x);
+        let expected = builder.constant(F::from_canonical_u64(27));
+        builder.connect(y, expected);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    #[should_panic(expected = "routes 25 wires")]
+    fn test_narrow_config_with_poseidon() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig {
+            num_routed_wires: 20,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        builder.build::<C>();
+    }
+
+    #[test]
+    fn test_prove_with_options() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let options = ProverOptions::with_num_threads(2)?;
+        #[cfg(feature = "parallel")]
+        assert_eq!(options.num_threads(), 2);
+        for i in 0..2 {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(i));
+            data.verify(data.prove_with_options(pw, &options)?)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_witness_layouts() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        // The unused public input wires are randomized, so the witness is only generated once.
+        let partition_witness =
+            generate_partial_witness(pw.clone(), &data.prover_only, &data.common)?;
+        let column_major = partition_witness
+            .clone()
+            .full_witness_with_layout(WitnessLayout::ColumnMajor);
+        let row_major = partition_witness.full_witness_with_layout(WitnessLayout::RowMajor);
+        for gate in 0..data.common.degree() {
+            for input in 0..data.common.config.num_wires {
+                assert_eq!(
+                    column_major.get_wire(gate, input),
+                    row_major.get_wire(gate, input)
+                );
+            }
+        }
+
+        for witness_layout in [WitnessLayout::ColumnMajor, WitnessLayout::RowMajor] {
+            let options = ProverOptions {
+                witness_layout,
+                ..ProverOptions::default()
+            };
+            data.verify(data.prove_with_options(pw.clone(), &options)?)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ungenerated_targets() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        // Without a value for `x`, witness generation gets stuck and proving fails cleanly.
+        assert!(!data.ungenerated_targets(PartialWitness::new())?.is_empty());
+        assert!(data.prove(PartialWitness::new()).is_err());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        assert!(data.ungenerated_targets(pw.clone())?.is_empty());
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_prove_shared_across_threads() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = Arc::new(builder.build::<C>());
+
+        let handles = (0..4u64)
+            .map(|i| {
+                let data = Arc::clone(&data);
+                thread::spawn(move || {
+                    let mut pw = PartialWitness::new();
+                    pw.set_target(x, F::from_canonical_u64(i));
+                    data.prove(pw)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            let proof = handle.join().unwrap()?;
+            assert_eq!(
+                proof.public_inputs,
+                vec![F::from_canonical_usize(i * i * i)]
+            );
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_with() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+
+        let at_most = |max: u64| {
+            move |public_inputs: &[F]| {
+                let y = public_inputs[1].to_canonical_u64();
+                ensure!(y <= max, "Output {} exceeds {}", y, max);
+                Ok(())
+            }
+        };
+        data.verify_with(proof.clone(), at_most(10))?;
+
+        // A failing check rejects the proof without verifying it, even though it is valid.
+        assert!(data.verify_with(proof, at_most(5)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_strict() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+
+        let bytes = proof.to_bytes();
+        data.verify_strict(&bytes)?;
+        let mut extended = bytes.clone();
+        extended.extend_from_slice(&[0; 8]);
+        assert!(data.verify_strict(&extended).is_err());
+
+        let compressed = data.compress(proof)?;
+        data.verify_compressed_strict(compressed.to_bytes())?;
+
+        // Reordering the query indices of a compressed proof gives another proof which the
+        // non-strict verifier accepts.
+        let mut malleated = compressed;
+        let indices = &mut malleated.proof.opening_proof.query_round_proofs.indices;
+        let i = (1..indices.len())
+            .find(|&i| indices[i] != indices[0])
+            .unwrap();
+        indices.swap(0, i);
+        data.verify_compressed(malleated.clone())?;
+        assert!(data.verify_compressed_strict(malleated.to_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifier_circuit_data_serialization() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x]);
+        builder.register_public_inputs(&hash.elements);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+        let verifier_data = data.verifier_data();
+
+        let common_bytes = verifier_data.common.to_bytes();
+        assert_eq!(
+            CommonCircuitData::<F, D>::from_bytes(&common_bytes)?,
+            verifier_data.common
+        );
+        let mut extended = common_bytes.clone();
+        extended.push(0);
+        assert!(CommonCircuitData::<F, D>::from_bytes(&extended).is_err());
+
+        let bytes = verifier_data.to_bytes();
+        let decoded = VerifierCircuitData::<F, C, D>::from_bytes(&bytes)?;
+        assert_eq!(decoded.common, verifier_data.common);
+        assert_eq!(decoded.verifier_only, verifier_data.verifier_only);
+        decoded.verify(proof)?;
+
+        for len in (0..bytes.len()).step_by(7) {
+            assert!(VerifierCircuitData::<F, C, D>::from_bytes(&bytes[..len]).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_circuit_digest() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        builder.set_domain_separator(vec![F::ONE, F::TWO]);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let mut verifier_data = builder.build::<C>().verifier_data();
+
+        verifier_data.check_circuit_digest(&[F::ONE, F::TWO])?;
+        assert!(verifier_data.check_circuit_digest(&[]).is_err());
+
+        // A digest stored next to another circuit's cap is rejected.
+        verifier_data.verifier_only.constants_sigmas_cap.0[0] = Default::default();
+        assert!(verifier_data
+            .check_circuit_digest(&[F::ONE, F::TWO])
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Backwards-compatibility tests against golden fixtures: proofs of a fixed circuit, with its
+//! verifier data, serialized by earlier releases and committed under `fixtures/<version>/`.
+//!
+//! `test_golden_fixtures` checks that the current code still reads and verifies every committed
+//! fixture, so that an operator upgrading can rely on its stored proofs. A release which breaks a
+//! format lists the fixtures it can no longer read in `INCOMPATIBLE_FIXTURES`, with the reason,
+//! and the test then checks that they are indeed rejected rather than misread. Each release adds
+//! its own fixtures with the ignored `generate_golden_fixtures`, see `fixtures/README.md`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::field::types::Field;
+use crate::hash::poseidon::PoseidonHash;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData};
+use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The fixtures the current code can't read, as `(version, reason)`.
+const INCOMPATIBLE_FIXTURES: &[(&str, &str)] = &[];
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// A small circuit using the gates most applications do: it proves knowledge of a Poseidon
+/// preimage of its first public inputs, and of the cube root of its last one.
+fn golden_circuit() -> (CircuitData<F, C, D>, PartialWitness<F>) {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let preimage = builder.add_virtual_target_arr::<4>();
+    let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.to_vec());
+    builder.register_public_inputs(&hash.elements);
+    let x = builder.add_virtual_target();
+    let cube = builder.cube(x);
+    let seven = builder.constant(F::from_canonical_u64(7));
+    let cube_plus_seven = builder.add(cube, seven);
+    builder.register_public_input(cube_plus_seven);
+    let data = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    pw.set_target_arr(preimage, [1, 2, 3, 4].map(F::from_canonical_u64));
+    pw.set_target(x, F::from_canonical_u64(5));
+    (data, pw)
+}
+
+/// Reads the fixtures in `dir` and verifies them with the current code.
+fn verify_fixture(dir: &Path) -> Result<()> {
+    let read = |name: &str| {
+        fs::read(dir.join(name)).with_context(|| format!("Can't read {:?}", dir.join(name)))
+    };
+    let verifier_data = VerifierCircuitData::<F, C, D>::from_bytes(read("verifier_data.bin")?)?;
+    let proof = ProofWithPublicInputs::from_bytes(read("proof.bin")?, &verifier_data.common)?;
+    verifier_data.verify(proof)?;
+    let compressed_proof = CompressedProofWithPublicInputs::from_bytes(
+        read("compressed_proof.bin")?,
+        &verifier_data.common,
+    )?;
+    verifier_data.verify_compressed(compressed_proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, ensure};
+
+    use super::*;
+
+    #[test]
+    fn test_golden_fixtures() -> Result<()> {
+        let mut num_fixtures = 0;
+        for entry in fs::read_dir(fixtures_dir())? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let version = path.file_name().unwrap().to_string_lossy().into_owned();
+            let result = verify_fixture(&path);
+            match INCOMPATIBLE_FIXTURES.iter().find(|(v, _)| *v == version) {
+                Some((_, reason)) => assert!(
+                    result.is_err(),
+                    "Fixtures of {version} are listed as incompatible ({reason}), but still verify"
+                ),
+                None => {
+                    result.map_err(|e| anyhow!("Fixtures of {version} no longer verify: {e:#}"))?
+                }
+            }
+            num_fixtures += 1;
+        }
+        // An empty fixtures directory would otherwise pass.
+        ensure!(
+            num_fixtures > 0,
+            "No fixtures found in {:?}",
+            fixtures_dir()
+        );
+        Ok(())
+    }
+
+    /// Writes the fixtures of the current version. Run once per release, and commit the result.
+    #[test]
+    #[ignore]
+    fn generate_golden_fixtures() -> Result<()> {
+        let (data, pw) = golden_circuit();
+        let proof = data.prove(pw)?;
+        let compressed_proof = data.compress(proof.clone())?;
+        let verifier_data = data.verifier_data();
+
+        let dir = fixtures_dir().join(env!("CARGO_PKG_VERSION"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("verifier_data.bin"), verifier_data.to_bytes())?;
+        fs::write(dir.join("proof.bin"), proof.to_bytes())?;
+        fs::write(
+            dir.join("compressed_proof.bin"),
+            compressed_proof.to_bytes(),
+        )?;
+        verify_fixture(&dir)
+    }
+}
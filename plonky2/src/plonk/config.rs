@@ -1,13 +1,33 @@
+//! Hashers and the configs which pick them.
+//!
+//! `GenericHashOut`, `Hasher` and `AlgebraicHasher`, with `PlonkyPermutation`, are a stable
+//! interface for other crates to supply their own hash functions, e.g. a hardware-accelerated
+//! Poseidon, without forking this one. A config naming such a hasher as `Hasher` builds its Merkle
+//! trees with it, and as `InnerHasher` draws its challenges and hashes its public inputs with it,
+//! natively and in recursive verifier circuits. The traits change only with a major version, and
+//! implementations may rely on nothing but their methods, the items they name and the sponge
+//! parameters `SPONGE_WIDTH` and `SPONGE_RATE`.
+//!
+//! The native and circuit sides of a hasher must agree: `permute_swapped` must compute
+//! `Permutation::permute` of the swapped inputs, and `public_inputs_hash` must compute
+//! `hash_public_inputs`, or the proofs of circuits using the hasher won't verify recursively.
+//! `RecursiveConfigPair` is sealed, as recursion only supports the pairs of fields it lists.
+
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
+use core::marker::PhantomData;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::field::extension::cubic::CubicExtension;
 use crate::field::extension::quadratic::QuadraticExtension;
+use crate::field::extension::quintic::QuinticExtension;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::goldilocks_field::GoldilocksField;
+use crate::field::types::Field64;
+use crate::gadgets::emulated::EmulatedPrime;
 use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
 use crate::hash::keccak::KeccakHash;
@@ -15,16 +35,20 @@ use crate::hash::poseidon::PoseidonHash;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 
+/// The output of a hash function, as Merkle caps and proofs hold it.
 pub trait GenericHashOut<F: RichField>:
     Copy + Clone + Debug + Eq + PartialEq + Send + Sync + Serialize + DeserializeOwned
 {
+    /// The `HASH_SIZE` bytes of the hash, which `from_bytes` reads back.
     fn to_bytes(&self) -> Vec<u8>;
     fn from_bytes(bytes: &[u8]) -> Self;
 
+    /// The hash as field elements, as it's observed by the challenger.
     fn to_vec(&self) -> Vec<F>;
 }
 
-/// Trait for hash functions.
+/// Trait for hash functions. Implementations should keep the provided methods, which the circuit
+/// side of algebraic hashers mirrors.
 pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     /// Size of `Hash` in bytes.
     const HASH_SIZE: usize;
@@ -38,6 +62,9 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     /// Hash a message without any padding step. Note that this can enable length-extension attacks.
     /// However, it is still collision-resistant in cases where the input has a fixed length.
     fn hash_no_pad(input: &[F]) -> Self::Hash;
+
+    /// Hash the public inputs of a proof, in the order they were registered in the circuit. For
+    /// algebraic hashers, this matches `CircuitBuilder::hash_public_inputs`.
     fn hash_public_inputs(input: &[F]) -> Self::Hash;
 
     /// Pad the message using the `pad10*1` rule, then hash it.
@@ -51,14 +78,13 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
         Self::hash_no_pad(&padded_input)
     }
 
-    /// Hash the slice if necessary to reduce its length to ~256 bits. If it already fits, this is a
-    /// no-op.
+    /// Hash the slice if necessary to reduce its length to `HASH_SIZE` bytes. If it already fits,
+    /// as 8 bytes per element, this is a no-op.
     fn hash_or_noop(inputs: &[F]) -> Self::Hash {
-        if inputs.len() <= 4 {
+        if inputs.len() * 8 <= Self::HASH_SIZE {
             let mut inputs_bytes = vec![0u8; Self::HASH_SIZE];
             for i in 0..inputs.len() {
-                inputs_bytes[i * 8..(i + 1) * 8]
-                    .copy_from_slice(&inputs[i].to_canonical_u64().to_le_bytes());
+                inputs_bytes[i * 8..(i + 1) * 8].copy_from_slice(&inputs[i].canonical_bytes());
             }
             Self::Hash::from_bytes(&inputs_bytes)
         } else {
@@ -117,6 +143,23 @@ impl GenericConfig<2> for PoseidonGoldilocksConfig {
     type InnerHasher = PoseidonHash;
 }
 
+/// Draws challenges from the degree 3 extension, for more soundness than degree 2 at the cost of
+/// larger proofs and slower proving.
+impl GenericConfig<3> for PoseidonGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = CubicExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+}
+
+/// Like the degree 3 config, with challenges drawn from the degree 5 extension.
+impl GenericConfig<5> for PoseidonGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuinticExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+}
+
 /// Configuration using truncated Keccak over the Goldilocks field.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct KeccakGoldilocksConfig;
@@ -126,3 +169,192 @@ impl GenericConfig<2> for KeccakGoldilocksConfig {
     type Hasher = KeccakHash<25>;
     type InnerHasher = PoseidonHash;
 }
+
+/// Configuration using the full 32-byte Keccak-256 over the Goldilocks field, whose Merkle caps and
+/// circuit digest are the digests an EVM verifier computes with `keccak256`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Keccak256GoldilocksConfig;
+impl GenericConfig<2> for Keccak256GoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = KeccakHash<32>;
+    type InnerHasher = PoseidonHash;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The fields of a proof and of the circuit verifying it. Only `NativeRecursion`, where they are
+/// the same field, can be verified so far; `EmulatedRecursion` describes proofs over a small field,
+/// e.g. BabyBear, verified in a circuit over a larger one, whose elements the verifier would handle
+/// as `EmulatedTarget`s.
+pub trait RecursiveConfigPair<const D: usize>: sealed::Sealed {
+    /// The config of the verifying circuit.
+    type Outer: GenericConfig<D>;
+
+    /// The order of the field of the proofs being verified.
+    const INNER_ORDER: u64;
+
+    /// Whether the proofs are over the field of the verifying circuit, so that it can verify them
+    /// natively, e.g. with `CircuitBuilder::verify_proof`.
+    fn is_native() -> bool {
+        Self::INNER_ORDER == <<Self::Outer as GenericConfig<D>>::F as Field64>::ORDER
+    }
+}
+
+/// Proofs with the config `C`, verified in circuits with the same config.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NativeRecursion<C>(PhantomData<C>);
+
+impl<C> sealed::Sealed for NativeRecursion<C> {}
+
+impl<C: GenericConfig<D>, const D: usize> RecursiveConfigPair<D> for NativeRecursion<C> {
+    type Outer = C;
+    const INNER_ORDER: u64 = <C::F as Field64>::ORDER;
+}
+
+/// Proofs over the field `P`, verified in circuits with the config `C`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EmulatedRecursion<P, C>(PhantomData<(P, C)>);
+
+impl<P, C> sealed::Sealed for EmulatedRecursion<P, C> {}
+
+impl<P: EmulatedPrime, C: GenericConfig<D>, const D: usize> RecursiveConfigPair<D>
+    for EmulatedRecursion<P, C>
+{
+    type Outer = C;
+    const INNER_ORDER: u64 = P::ORDER;
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::{MerkleCapTarget, NUM_HASH_OUT_ELTS};
+    use crate::hash::hashing::{compress, hash_n_to_hash_no_pad};
+    use crate::hash::merkle_proofs::MerkleProofTarget;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    /// A permutation supplied by another crate, here Poseidon under another name.
+    struct ExternalPermutation;
+
+    impl<F: RichField> PlonkyPermutation<F> for ExternalPermutation {
+        fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+            F::poseidon(input)
+        }
+    }
+
+    /// A hasher implemented only with the stable interface, as another crate would.
+    #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    struct ExternalHash;
+
+    impl<F: RichField> Hasher<F> for ExternalHash {
+        const HASH_SIZE: usize = NUM_HASH_OUT_ELTS * 8;
+        type Hash = HashOut<F>;
+        type Permutation = ExternalPermutation;
+
+        fn hash_no_pad(input: &[F]) -> Self::Hash {
+            hash_n_to_hash_no_pad::<F, Self::Permutation>(input)
+        }
+
+        fn hash_public_inputs(input: &[F]) -> Self::Hash {
+            Self::hash_no_pad(input)
+        }
+
+        fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+            compress::<F, Self::Permutation>(left, right)
+        }
+    }
+
+    impl<F: RichField> AlgebraicHasher<F> for ExternalHash {
+        fn permute_swapped<const D: usize>(
+            inputs: [Target; SPONGE_WIDTH],
+            swap: BoolTarget,
+            builder: &mut CircuitBuilder<F, D>,
+        ) -> [Target; SPONGE_WIDTH]
+        where
+            F: RichField + Extendable<D>,
+        {
+            // The Poseidon gate computes the same permutation.
+            PoseidonHash::permute_swapped(inputs, swap, builder)
+        }
+
+        fn public_inputs_hash<const D: usize>(
+            inputs: Vec<Target>,
+            builder: &mut CircuitBuilder<F, D>,
+        ) -> HashOutTarget
+        where
+            F: RichField + Extendable<D>,
+        {
+            builder.hash_n_to_hash_no_pad::<Self>(inputs)
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    struct ExternalConfig;
+
+    impl GenericConfig<2> for ExternalConfig {
+        type F = GoldilocksField;
+        type FE = QuadraticExtension<Self::F>;
+        type Hasher = ExternalHash;
+        type InnerHasher = ExternalHash;
+    }
+
+    #[test]
+    fn test_external_hasher() -> Result<()> {
+        const D: usize = 2;
+        type C = ExternalConfig;
+        type F = GoldilocksField;
+
+        let leaves: Vec<Vec<F>> = (0..8).map(|_| F::rand_vec(6)).collect();
+        let tree = MerkleTree::<F, ExternalHash>::new(leaves.clone(), 0);
+        assert_eq!(
+            tree.cap.0,
+            MerkleTree::<F, PoseidonHash>::new(leaves.clone(), 0).cap.0
+        );
+
+        // A Merkle proof checked in a circuit built with the hasher, whose proof is verified with
+        // it, natively and recursively.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let leaf = builder.add_virtual_targets(6);
+        let index = builder.add_virtual_target();
+        let root = builder.add_virtual_hash();
+        builder.register_public_inputs(&root.elements);
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(3),
+        };
+        builder.verify_merkle_proof_to_cap_at_index::<ExternalHash>(
+            leaf.clone(),
+            index,
+            &MerkleCapTarget(vec![root]),
+            &proof_t,
+        );
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for (&t, &x) in leaf.iter().zip(&leaves[5]) {
+            pw.set_target(t, x);
+        }
+        pw.set_target(index, F::from_canonical_usize(5));
+        pw.set_hash_target(root, tree.cap.0[0]);
+        for (&sibling_t, &sibling) in proof_t.siblings.iter().zip(&tree.prove(5).siblings) {
+            pw.set_hash_target(sibling_t, sibling);
+        }
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_t = builder.add_virtual_proof_with_pis::<C>(&data.common);
+        let verifier_data = builder.constant_verifier_data(&data.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_data, &data.common);
+        let recursive_data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof);
+        recursive_data.verify(recursive_data.prove(pw)?)
+    }
+}
@@ -0,0 +1,121 @@
+//! A native check of a witness against a circuit's constraints, to find out whether proving would
+//! succeed, and with which public inputs, in a fraction of the time it takes.
+//!
+//! `check_witness` runs the witness generators as the prover does, which also enforces the copy
+//! constraints, since connected targets share one value. It then evaluates each gate's
+//! constraints at each row of the resulting wires, and reports the first which doesn't vanish.
+//! Witness generation failures, such as conflicting values or generators which can't run, are
+//! reported as errors as well.
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Result};
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::generate_partial_witness;
+use crate::iop::witness::{PartialWitness, Witness, WitnessLayout};
+use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::vars::EvaluationVars;
+
+/// Checks that `inputs` make a witness satisfying the circuit's constraints, without proving, and
+/// returns the public inputs the proof would have.
+pub fn check_witness<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    inputs: PartialWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<Vec<F>> {
+    let partition_witness = generate_partial_witness(inputs, prover_data, common_data)?;
+    let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
+    let public_inputs_hash = C::InnerHasher::hash_public_inputs(&public_inputs);
+    let wires = partition_witness.full_witness_with_layout(WitnessLayout::RowMajor);
+
+    // The constants polynomials aren't blinded, so their values at the rows are exact.
+    let constants: Vec<Vec<F>> = prover_data.constants_sigmas_commitment.polynomials
+        [common_data.constants_range()]
+    .iter()
+    .map(|poly| poly.clone().fft().values)
+    .collect();
+    let selectors = &common_data.selectors_info;
+    for (row, row_wires) in wires.wire_values.iter().enumerate() {
+        let local_constants: Vec<F::Extension> = constants
+            .iter()
+            .map(|values| F::Extension::from_basefield(values[row]))
+            .collect();
+        let local_wires: Vec<F::Extension> = row_wires
+            .iter()
+            .map(|&x| F::Extension::from_basefield(x))
+            .collect();
+        let vars = EvaluationVars {
+            local_constants: &local_constants,
+            local_wires: &local_wires,
+            public_inputs_hash: &public_inputs_hash,
+        };
+        for (i, gate) in common_data.gates.iter().enumerate() {
+            let selector_index = selectors.selector_indices[i];
+            let constraints = gate.0.eval_filtered(
+                vars,
+                i,
+                selector_index,
+                selectors.groups[selector_index].clone(),
+                selectors.num_selectors(),
+            );
+            if let Some(constraint) = constraints.iter().position(|c| !c.is_zero()) {
+                return Err(anyhow!(
+                    "Constraint {} of {} isn't satisfied at row {}",
+                    constraint,
+                    gate.0.id(),
+                    row
+                ));
+            }
+        }
+    }
+    Ok(public_inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    #[test]
+    fn test_check_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // x^3 + 7 = y, for a public y.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_public_input();
+        let cube = builder.cube(x);
+        let seven = builder.constant(F::from_canonical_u64(7));
+        let cube_plus_seven = builder.add(cube, seven);
+        builder.connect(cube_plus_seven, y);
+        let data = builder.build::<C>();
+
+        let witness = |x_value: u64, y_value: u64| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(x_value));
+            pw.set_target(y, F::from_canonical_u64(y_value));
+            pw
+        };
+        assert_eq!(
+            data.check_witness(witness(2, 15))?,
+            [F::from_canonical_u64(15)]
+        );
+        // The generators compute x^3 + 7 = 15, which conflicts with y.
+        assert!(data.check_witness(witness(2, 16)).is_err());
+
+        // Nor does it run without x.
+        let mut pw = PartialWitness::new();
+        pw.set_target(y, F::from_canonical_u64(15));
+        assert!(data.check_witness(pw).is_err());
+        Ok(())
+    }
+}
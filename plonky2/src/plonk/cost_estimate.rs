@@ -0,0 +1,326 @@
+//! Rough estimates of the cost of proving a circuit, computed from its shape before it is built.
+//!
+//! See `CircuitBuilder::current_size_estimate`, `CircuitBuilder::estimated_degree`,
+//! `CircuitBuilder::estimated_prover_memory`, `CircuitBuilder::estimated_proving_time` and
+//! `CircuitBuilder::routing_report`.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::hint::black_box;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "std")]
+use crate::field::fft::fft;
+#[cfg(feature = "std")]
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::fri::oracle::SALT_SIZE;
+#[cfg(feature = "std")]
+use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::NUM_HASH_OUT_ELTS;
+#[cfg(feature = "std")]
+use crate::hash::hashing::PlonkyPermutation;
+use crate::hash::hashing::SPONGE_RATE;
+#[cfg(feature = "std")]
+use crate::hash::hashing::SPONGE_WIDTH;
+#[cfg(feature = "std")]
+use crate::plonk::config::Hasher;
+use crate::util::{ceil_div_usize, log2_ceil};
+
+/// Timings of the primitive operations a prover spends most of its time on, describing the machine
+/// a circuit will be proven on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProvingProfile {
+    /// Time taken by one radix-2 butterfly of a base field FFT, in nanoseconds.
+    pub fft_butterfly_nanos: f64,
+    /// Time taken by one permutation of the hash used for Merkle trees, in nanoseconds.
+    pub permutation_nanos: f64,
+    /// Time taken to evaluate one gate constraint at one point, in nanoseconds.
+    pub constraint_nanos: f64,
+    /// The number of threads the prover runs on.
+    pub num_threads: usize,
+}
+
+impl Default for ProvingProfile {
+    /// Ballpark timings of a single core of a recent x86-64 machine proving over Goldilocks with
+    /// Poseidon.
+    fn default() -> Self {
+        Self {
+            fft_butterfly_nanos: 3.0,
+            permutation_nanos: 1500.0,
+            constraint_nanos: 20.0,
+            num_threads: 1,
+        }
+    }
+}
+
+impl ProvingProfile {
+    pub fn with_num_threads(self, num_threads: usize) -> Self {
+        assert!(num_threads > 0, "A prover needs at least one thread");
+        Self {
+            num_threads,
+            ..self
+        }
+    }
+
+    /// Measures the single-threaded FFT and permutation timings of the current machine, keeping the
+    /// default constraint timing. Use `with_num_threads` to account for a parallel prover.
+    #[cfg(feature = "std")]
+    pub fn measure<F: RichField, H: Hasher<F>>() -> Self {
+        const FFT_LOG_SIZE: usize = 14;
+        const NUM_PERMUTATIONS: usize = 1 << 10;
+
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(1 << FFT_LOG_SIZE));
+        let start = Instant::now();
+        let values = fft(coeffs);
+        let fft_nanos = start.elapsed().as_nanos() as f64;
+        let num_butterflies = (FFT_LOG_SIZE << FFT_LOG_SIZE) / 2;
+
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        state.copy_from_slice(&values.values[..SPONGE_WIDTH]);
+        let start = Instant::now();
+        for _ in 0..NUM_PERMUTATIONS {
+            state = H::Permutation::permute(black_box(state));
+        }
+        let permutation_nanos = start.elapsed().as_nanos() as f64;
+
+        Self {
+            fft_butterfly_nanos: fft_nanos / num_butterflies as f64,
+            permutation_nanos: permutation_nanos / NUM_PERMUTATIONS as f64,
+            ..Self::default()
+        }
+    }
+}
+
+/// The number of rows of the circuit `build` would produce from the gates added so far.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SizeEstimate {
+    /// The number of gates added so far, plus those `build` adds before blinding and padding.
+    pub num_gates: usize,
+    /// The number of `NoopGate`s added to blind the witness, with zero knowledge.
+    pub num_blinding_gates: usize,
+    /// The degree of the circuit, i.e. its number of rows after padding to a power of two.
+    pub degree: usize,
+}
+
+impl SizeEstimate {
+    /// The number of `NoopGate`s added to pad the circuit to `degree` rows. This is roughly how
+    /// many gates can still be added before the degree doubles; with zero knowledge the number of
+    /// blinding gates may grow too.
+    pub fn num_padding_gates(&self) -> usize {
+        self.degree - self.num_gates - self.num_blinding_gates
+    }
+
+    /// The percentage of rows which are padding.
+    pub fn padding_percentage(&self) -> f64 {
+        100.0 * self.num_padding_gates() as f64 / self.degree as f64
+    }
+}
+
+/// How the copy constraints of a circuit use its routed wires, to help choose `num_routed_wires`.
+/// Each routed wire adds a sigma polynomial and its share of the partial products, so routed
+/// columns used by few copy constraints are expensive.
+///
+/// Gates built from the config, e.g. `ArithmeticGate::new_from_config`, fit as many operations as
+/// the routed wires allow, so with fewer routed wires the gates and their copy constraints change
+/// too; the suggestions are a starting point to be checked by building again.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoutingReport {
+    /// The number of rows added so far, before the gates `build` adds.
+    pub num_rows: usize,
+    /// The quotient degree factor the circuit would have. The permutation argument needs more
+    /// routed wires than this.
+    pub quotient_degree_factor: usize,
+    /// For each routed column, the number of rows whose wire in that column is copy-constrained.
+    pub rows_used_per_column: Vec<usize>,
+    /// For each routed column, the number of copy constraints whose highest wire is in that
+    /// column. Copy constraints between virtual targets have no wire and aren't counted.
+    pub copy_constraints_by_highest_column: Vec<usize>,
+    /// The number of copy constraints, including those between virtual targets.
+    pub num_copy_constraints: usize,
+}
+
+impl RoutingReport {
+    /// The fraction of rows whose wire in `column` is copy-constrained.
+    pub fn utilization(&self, column: usize) -> f64 {
+        if self.num_rows == 0 {
+            return 0.0;
+        }
+        self.rows_used_per_column[column] as f64 / self.num_rows as f64
+    }
+
+    /// The number of copy constraints with a wire outside the first `num_routed_wires` columns,
+    /// which would have to be routed differently with only that many routed wires.
+    pub fn copy_constraints_beyond(&self, num_routed_wires: usize) -> usize {
+        self.copy_constraints_by_highest_column
+            .iter()
+            .skip(num_routed_wires)
+            .sum()
+    }
+
+    /// The fewest routed wires the copy constraints added so far fit in, and which the permutation
+    /// argument allows.
+    pub fn min_num_routed_wires(&self) -> usize {
+        let used = self
+            .copy_constraints_by_highest_column
+            .iter()
+            .rposition(|&n| n > 0)
+            .map_or(0, |column| column + 1);
+        used.max(self.quotient_degree_factor + 1)
+    }
+
+    /// The fewest routed wires such that at most 1% of the copy constraints are in the columns
+    /// past them. Those few copy constraints force the extra routed wires up to
+    /// `min_num_routed_wires`, so routing them differently, e.g. by spreading the gates using
+    /// those columns over more rows, may make this many routed wires enough.
+    pub fn suggested_num_routed_wires(&self) -> usize {
+        let max_rerouted = self.num_copy_constraints / 100;
+        (self.quotient_degree_factor + 1..self.min_num_routed_wires())
+            .find(|&n| self.copy_constraints_beyond(n) <= max_rerouted)
+            .unwrap_or_else(|| self.min_num_routed_wires())
+    }
+}
+
+/// The sizes of a circuit that drive the cost of proving it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CircuitShape {
+    pub degree_bits: usize,
+    pub rate_bits: usize,
+    /// Whether Merkle leaves are salted.
+    pub hiding: bool,
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    /// The number of constant polynomials, including selectors.
+    pub num_constants: usize,
+    pub num_challenges: usize,
+    pub num_partial_products: usize,
+    pub quotient_degree_factor: usize,
+    /// The number of constraints of all gate types together, which are all evaluated at each point.
+    pub num_gate_constraints: usize,
+    pub proof_of_work_bits: u32,
+    pub extension_degree: usize,
+    pub field_bytes: usize,
+}
+
+impl CircuitShape {
+    fn degree(&self) -> usize {
+        1 << self.degree_bits
+    }
+
+    fn lde_size(&self) -> usize {
+        1 << (self.degree_bits + self.rate_bits)
+    }
+
+    fn quotient_domain_size(&self) -> usize {
+        1 << (self.degree_bits + log2_ceil(self.quotient_degree_factor))
+    }
+
+    fn salt_size(&self) -> usize {
+        if self.hiding {
+            SALT_SIZE
+        } else {
+            0
+        }
+    }
+
+    fn num_zs_partial_products_polys(&self) -> usize {
+        self.num_challenges * (1 + self.num_partial_products)
+    }
+
+    fn num_quotient_polys(&self) -> usize {
+        self.num_challenges * self.quotient_degree_factor
+    }
+
+    /// The number of field elements held by a `PolynomialBatch` of `num_polys` polynomials: their
+    /// coefficients, their (salted) LDE leaves and the digests of the Merkle tree over them.
+    fn batch_elements(&self, num_polys: usize) -> usize {
+        let lde_size = self.lde_size();
+        num_polys * self.degree()
+            + (num_polys + self.salt_size()) * lde_size
+            + 2 * lde_size * NUM_HASH_OUT_ELTS
+    }
+
+    /// The number of permutations needed to build the Merkle tree of a batch of `num_polys`
+    /// polynomials: hashing each leaf, then compressing pairs of digests.
+    fn batch_permutations(&self, num_polys: usize) -> usize {
+        let leaf_len = num_polys + self.salt_size();
+        self.lde_size() * (ceil_div_usize(leaf_len, SPONGE_RATE) + 1)
+    }
+
+    /// The number of butterflies needed to interpolate `num_polys` polynomials from their values
+    /// (if `from_values`) and then compute their LDEs.
+    fn batch_butterflies(&self, num_polys: usize, from_values: bool) -> usize {
+        let ifft = if from_values {
+            (self.degree() / 2) * self.degree_bits
+        } else {
+            0
+        };
+        let lde = (self.lde_size() / 2) * (self.degree_bits + self.rate_bits);
+        num_polys * (ifft + lde)
+    }
+
+    pub fn prover_memory(&self) -> usize {
+        let wires = self.batch_elements(self.num_wires);
+        let constants_sigmas = self.batch_elements(self.num_constants + self.num_routed_wires);
+        let zs_partial_products = self.batch_elements(self.num_zs_partial_products_polys());
+        let quotient_values = self.num_challenges * self.quotient_domain_size();
+        // With a quotient degree above the rate, the committed polynomials are also evaluated on the
+        // larger quotient domain while the quotient is computed.
+        let extended_ldes = if self.quotient_domain_size() > self.lde_size() {
+            (self.num_wires
+                + self.num_constants
+                + self.num_routed_wires
+                + self.num_zs_partial_products_polys())
+                * self.quotient_domain_size()
+        } else {
+            0
+        };
+        let quotient = self.batch_elements(self.num_quotient_polys());
+        // The FRI layers fold the combined polynomial, so together they are bounded by twice the
+        // first layer, which holds an extension field element per LDE point.
+        let fri =
+            2 * self.extension_degree * self.lde_size() + 2 * self.lde_size() * NUM_HASH_OUT_ELTS;
+        // The wire values, plus the partition witness they are generated into.
+        let witness = 2 * self.num_wires * self.degree();
+
+        let total_elements = wires
+            + constants_sigmas
+            + zs_partial_products
+            + quotient_values
+            + extended_ldes
+            + quotient
+            + fri
+            + witness;
+        total_elements * self.field_bytes
+    }
+
+    pub fn proving_time(&self, profile: &ProvingProfile) -> Duration {
+        let num_zs_partial_products = self.num_zs_partial_products_polys();
+        let num_quotient_polys = self.num_quotient_polys();
+
+        let quotient_domain_bits = self.degree_bits + log2_ceil(self.quotient_degree_factor);
+        let quotient_ifft =
+            self.num_challenges * (self.quotient_domain_size() / 2) * quotient_domain_bits;
+        let butterflies = self.batch_butterflies(self.num_wires, true)
+            + self.batch_butterflies(num_zs_partial_products, true)
+            + quotient_ifft
+            + self.batch_butterflies(num_quotient_polys, false);
+
+        let permutations = self.batch_permutations(self.num_wires)
+            + self.batch_permutations(num_zs_partial_products)
+            + self.batch_permutations(num_quotient_polys)
+            + self.lde_size()
+            + (1 << self.proof_of_work_bits);
+
+        // Each point of the quotient domain evaluates every gate's constraints, plus the
+        // permutation argument's, which are linear in the number of routed wires.
+        let constraints = self.quotient_domain_size()
+            * (self.num_gate_constraints + self.num_challenges * self.num_routed_wires);
+
+        let nanos = butterflies as f64 * profile.fft_butterfly_nanos
+            + permutations as f64 * profile.permutation_nanos
+            + constraints as f64 * profile.constraint_nanos;
+        Duration::from_nanos((nanos / profile.num_threads as f64) as u64)
+    }
+}
@@ -10,11 +10,16 @@ use crate::fri::verifier::{compute_evaluation, fri_combine_initial, PrecomputedR
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
-use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::iop::challenger::Challenger;
 use crate::iop::target::Target;
+use crate::iop::transcript::{
+    fri_challenges, fri_challenges_circuit, plonk_challenges, NativeTranscript, PlonkChallenges,
+    RecursiveTranscript,
+};
+use crate::iop::transcript_log::TranscriptLog;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, FriInferredElements, OpeningSet,
     OpeningSetTarget, Proof, ProofChallenges, ProofChallengesTarget, ProofTarget,
@@ -22,7 +27,12 @@ use crate::plonk::proof::{
 };
 use crate::util::reverse_bits;
 
-fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+fn get_challenges<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
     public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
     wires_cap: &MerkleCap<F, C::Hasher>,
     plonk_zs_partial_products_cap: &MerkleCap<F, C::Hasher>,
@@ -33,25 +43,26 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
     pow_witness: F,
     circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
     common_data: &CommonCircuitData<F, D>,
+    challenger: &mut T,
 ) -> anyhow::Result<ProofChallenges<F, D>> {
     let config = &common_data.config;
     let num_challenges = config.num_challenges;
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
-
-    // Observe the instance.
-    challenger.observe_hash::<C::Hasher>(*circuit_digest);
-    challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
-
-    challenger.observe_cap(wires_cap);
-    let plonk_betas = challenger.get_n_challenges(num_challenges);
-    let plonk_gammas = challenger.get_n_challenges(num_challenges);
-
-    challenger.observe_cap(plonk_zs_partial_products_cap);
-    let plonk_alphas = challenger.get_n_challenges(num_challenges);
-
-    challenger.observe_cap(quotient_polys_cap);
-    let plonk_zeta = challenger.get_extension_challenge::<D>();
+    let PlonkChallenges {
+        betas: plonk_betas,
+        gammas: plonk_gammas,
+        alphas: plonk_alphas,
+        zeta: plonk_zeta,
+    } = plonk_challenges::<_, F, D>(
+        challenger,
+        &mut (),
+        num_challenges,
+        circuit_digest,
+        &public_inputs_hash.to_vec(),
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+    );
 
     challenger.observe_openings(&openings.to_fri_openings());
 
@@ -60,7 +71,8 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
         plonk_gammas,
         plonk_alphas,
         plonk_zeta,
-        fri_challenges: challenger.fri_challenges::<C, D>(
+        fri_challenges: fri_challenges(
+            challenger,
             commit_phase_merkle_caps,
             final_poly,
             pow_witness,
@@ -90,6 +102,43 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
         common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_with(
+            public_inputs_hash,
+            circuit_digest,
+            common_data,
+            &mut Challenger::new(),
+        )
+    }
+
+    /// Replays the verifier's Fiat-Shamir transcript for this proof, recording every element
+    /// observed and every challenge produced. Comparing it with the prover's log (see
+    /// `prove_with_transcript_log`), or with the values an exported verifier computes, shows where
+    /// two implementations diverge.
+    pub fn transcript_log(
+        &self,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<TranscriptLog<F>> {
+        let mut challenger = Challenger::new_with_log();
+        self.get_challenges_with(
+            self.get_public_inputs_hash(),
+            circuit_digest,
+            common_data,
+            &mut challenger,
+        )?;
+        Ok(challenger
+            .take_transcript_log()
+            .expect("Challenger was created with a log"))
+    }
+
+    /// Like `get_challenges`, but deriving the challenges from the given transcript.
+    pub(crate) fn get_challenges_with<T: NativeTranscript<F, C::Hasher, D>>(
+        &self,
+        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+        challenger: &mut T,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let Proof {
             wires_cap,
@@ -105,7 +154,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                 },
         } = &self.proof;
 
-        get_challenges::<F, C, D>(
+        get_challenges::<F, C, _, D>(
             public_inputs_hash,
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -116,6 +165,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             *pow_witness,
             circuit_digest,
             common_data,
+            challenger,
         )
     }
 }
@@ -144,7 +194,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                 },
         } = &self.proof;
 
-        get_challenges::<F, C, D>(
+        get_challenges::<F, C, _, D>(
             public_inputs_hash,
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -155,6 +205,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             *pow_witness,
             circuit_digest,
             common_data,
+            &mut Challenger::new(),
         )
     }
 
@@ -180,8 +231,10 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         let mut seen_indices_by_depth =
             vec![HashSet::new(); common_data.fri_params.reduction_arity_bits.len()];
         let precomputed_reduced_evals = PrecomputedReducedOpenings::from_os_and_alpha(
+            &common_data.get_fri_instance(*plonk_zeta),
             &self.proof.openings.to_fri_openings(),
             *fri_alpha,
+            common_data.fri_params.degree_bits,
         );
         let log_n = common_data.degree_bits() + common_data.config.fri_config.rate_bits;
         // Simulate the proof verification and collect the inferred elements.
@@ -235,8 +288,9 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    fn get_challenges<C: GenericConfig<D, F = F>>(
+    fn get_challenges<T: RecursiveTranscript<F, D>>(
         &mut self,
+        challenger: &mut T,
         public_inputs_hash: HashOutTarget,
         wires_cap: &MerkleCapTarget,
         plonk_zs_partial_products_cap: &MerkleCapTarget,
@@ -247,28 +301,25 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         pow_witness: Target,
         inner_circuit_digest: HashOutTarget,
         inner_common_data: &CommonCircuitData<F, D>,
-    ) -> ProofChallengesTarget<D>
-    where
-        C::Hasher: AlgebraicHasher<F>,
-    {
+    ) -> ProofChallengesTarget<D> {
         let config = &inner_common_data.config;
         let num_challenges = config.num_challenges;
 
-        let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(self);
-
-        // Observe the instance.
-        challenger.observe_hash(&inner_circuit_digest);
-        challenger.observe_hash(&public_inputs_hash);
-
-        challenger.observe_cap(wires_cap);
-        let plonk_betas = challenger.get_n_challenges(self, num_challenges);
-        let plonk_gammas = challenger.get_n_challenges(self, num_challenges);
-
-        challenger.observe_cap(plonk_zs_partial_products_cap);
-        let plonk_alphas = challenger.get_n_challenges(self, num_challenges);
-
-        challenger.observe_cap(quotient_polys_cap);
-        let plonk_zeta = challenger.get_extension_challenge(self);
+        let PlonkChallenges {
+            betas: plonk_betas,
+            gammas: plonk_gammas,
+            alphas: plonk_alphas,
+            zeta: plonk_zeta,
+        } = plonk_challenges::<_, F, D>(
+            challenger,
+            self,
+            num_challenges,
+            &inner_circuit_digest,
+            &public_inputs_hash.elements,
+            wires_cap,
+            plonk_zs_partial_products_cap,
+            quotient_polys_cap,
+        );
 
         challenger.observe_openings(&openings.to_fri_openings());
 
@@ -277,7 +328,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             plonk_gammas,
             plonk_alphas,
             plonk_zeta,
-            fri_challenges: challenger.fri_challenges::<C>(
+            fri_challenges: fri_challenges_circuit(
+                challenger,
                 self,
                 commit_phase_merkle_caps,
                 final_poly,
@@ -289,16 +341,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 }
 
 impl<const D: usize> ProofWithPublicInputsTarget<D> {
-    pub(crate) fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>>(
+    pub(crate) fn get_challenges<F: RichField + Extendable<D>, T: RecursiveTranscript<F, D>>(
         &self,
         builder: &mut CircuitBuilder<F, D>,
+        challenger: &mut T,
         public_inputs_hash: HashOutTarget,
         inner_circuit_digest: HashOutTarget,
         inner_common_data: &CommonCircuitData<F, D>,
-    ) -> ProofChallengesTarget<D>
-    where
-        C::Hasher: AlgebraicHasher<F>,
-    {
+    ) -> ProofChallengesTarget<D> {
         let ProofTarget {
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -313,7 +363,8 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
                 },
         } = &self.proof;
 
-        builder.get_challenges::<C>(
+        builder.get_challenges(
+            challenger,
             public_inputs_hash,
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -0,0 +1,185 @@
+//! Renders the gates and copy constraints of a circuit as a Graphviz graph, for reviewing its
+//! wiring. See `CircuitBuilder::to_graphviz`.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::gates::gate::GateInstance;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::copy_constraint::CopyConstraint;
+use crate::util::context_tree::ContextTree;
+
+/// Whether a scope stack, as described by `ContextTree::open_stack`, includes a scope named
+/// `scope`. Every stack matches `None`.
+fn in_scope(stack: &str, scope: Option<&str>) -> bool {
+    scope.map_or(true, |scope| stack.split(" > ").any(|name| name == scope))
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn node(target: Target) -> String {
+    match target {
+        Target::Wire(wire) => format!("g{}", wire.row),
+        Target::VirtualTarget { index } => format!("v{}", index),
+    }
+}
+
+/// Renders the gates added in `scope` (all gates if `None`), with the copy constraints involving
+/// them, in the DOT language. Gates are boxes labelled with their row, ID, scope and named wires;
+/// gates outside `scope` which are connected to it are dashed; virtual targets are ellipses.
+pub(crate) fn circuit_to_graphviz<F: RichField + Extendable<D>, const D: usize>(
+    gate_instances: &[GateInstance<F, D>],
+    copy_constraints: &[CopyConstraint],
+    context_log: &ContextTree,
+    target_names: &HashMap<Target, String>,
+    scope: Option<&str>,
+) -> String {
+    let scopes = (0..gate_instances.len())
+        .map(|row| context_log.scope_of(row))
+        .collect::<Vec<_>>();
+    let row_in_scope = |row: usize| in_scope(&scopes[row], scope);
+
+    let edges = copy_constraints
+        .iter()
+        .filter(|constraint| {
+            let (a, b) = constraint.pair;
+            in_scope(&constraint.name, scope)
+                || [a, b].into_iter().any(|t| match t {
+                    Target::Wire(wire) => row_in_scope(wire.row),
+                    Target::VirtualTarget { .. } => false,
+                })
+        })
+        .map(|constraint| constraint.pair)
+        .collect::<Vec<_>>();
+
+    let mut rows = (0..gate_instances.len())
+        .filter(|&row| row_in_scope(row))
+        .collect::<BTreeSet<_>>();
+    let mut virtual_targets = BTreeSet::new();
+    for &(a, b) in &edges {
+        for target in [a, b] {
+            match target {
+                Target::Wire(wire) => rows.insert(wire.row),
+                Target::VirtualTarget { index } => virtual_targets.insert(index),
+            };
+        }
+    }
+
+    let mut wire_names = HashMap::<usize, Vec<String>>::new();
+    let mut named_wires = target_names
+        .iter()
+        .filter_map(|(&target, name)| match target {
+            Target::Wire(wire) => Some((wire, name)),
+            Target::VirtualTarget { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    named_wires.sort_by_key(|&(wire, _)| (wire.row, wire.column));
+    for (wire, name) in named_wires {
+        wire_names
+            .entry(wire.row)
+            .or_default()
+            .push(format!("w{}: {}", wire.column, name));
+    }
+
+    let mut dot = String::from("graph circuit {\n    node [shape=box, fontname=\"monospace\"];\n");
+    for row in rows {
+        let mut label = format!(
+            "{}: {}\\n{}",
+            row,
+            escape(&gate_instances[row].gate_ref.0.id()),
+            escape(&scopes[row])
+        );
+        for name in wire_names.get(&row).into_iter().flatten() {
+            label += &format!("\\n{}", escape(name));
+        }
+        let style = if row_in_scope(row) {
+            ""
+        } else {
+            ", style=dashed"
+        };
+        writeln!(dot, "    g{} [label=\"{}\"{}];", row, label, style).unwrap();
+    }
+    for index in virtual_targets {
+        let label = target_names
+            .get(&Target::VirtualTarget { index })
+            .map_or_else(|| format!("v{}", index), |name| escape(name));
+        writeln!(dot, "    v{} [label=\"{}\", shape=ellipse];", index, label).unwrap();
+    }
+    for (a, b) in edges {
+        let ports = [a, b]
+            .into_iter()
+            .filter_map(|t| match t {
+                Target::Wire(wire) => Some(format!("w{}", wire.column)),
+                Target::VirtualTarget { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" - ");
+        let names = [a, b]
+            .into_iter()
+            .filter_map(|t| target_names.get(&t).map(|name| escape(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let label = match (names.is_empty(), ports.is_empty()) {
+            (true, _) => ports,
+            (false, true) => names,
+            (false, false) => format!("{}: {}", names, ports),
+        };
+        writeln!(dot, "    {} -- {} [label=\"{}\"];", node(a), node(b), label).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::with_context;
+
+    #[test]
+    fn test_graphviz() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        builder.name_target(x, "secret \"x\"");
+        let hash = with_context!(
+            builder,
+            "hash secret",
+            builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x])
+        );
+        let y = with_context!(builder, "square", builder.square(hash.elements[0]));
+        builder.name_target(y, "y");
+        builder.register_public_input(y);
+
+        let dot = builder.to_graphviz(None);
+        assert!(dot.starts_with("graph circuit {"));
+        assert!(dot.contains("\\nroot > hash secret\""));
+        assert!(dot.contains("\\nroot > square\\nw"));
+        assert!(dot.contains("[label=\"secret \\\"x\\\"\", shape=ellipse]"));
+
+        // Only the squaring gate is in the `square` scope; the Poseidon gate feeding it is shown
+        // dashed, and the virtual target `x` isn't connected to it.
+        let dot = builder.to_graphviz(Some("square"));
+        assert_eq!(dot.matches("style=dashed").count(), 1);
+        assert!(dot.contains("PoseidonGate"));
+        assert!(!dot.contains("shape=ellipse"));
+        assert_eq!(
+            builder.to_graphviz(Some("no such scope")).lines().count(),
+            3
+        );
+    }
+}
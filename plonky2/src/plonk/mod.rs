@@ -1,12 +1,24 @@
+#[cfg(feature = "tokio")]
+pub mod async_prover;
 pub mod circuit_builder;
 pub mod circuit_data;
+#[cfg(test)]
+mod compatibility;
 pub mod config;
+pub mod constraint_check;
 pub(crate) mod copy_constraint;
+pub mod cost_estimate;
 mod get_challenges;
+mod graphviz;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
+pub mod proof_format;
 pub mod prover;
+pub mod public_inputs_layout;
+#[cfg(any(feature = "reference_verifier", test))]
+pub mod reference_verifier;
+pub mod summary;
 mod validate_shape;
 pub(crate) mod vanishing_poly;
 pub mod vars;
@@ -19,11 +19,13 @@ use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
-use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::verifier::verify_with_challenges;
-use crate::util::serialization::Write;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::public_inputs_layout::{PublicInputSlot, PublicInputsLayout};
+use crate::plonk::validate_shape::validate_proof_with_pis_shape;
+use crate::plonk::verifier::{verify, verify_with_challenges};
 #[cfg(feature = "std")]
-use crate::util::serialization::{Buffer, Read};
+use crate::util::serialization::IoReader;
+use crate::util::serialization::{Read, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -97,12 +99,42 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         })
     }
 
-    pub(crate) fn get_public_inputs_hash(
+    /// Checks that every vector, Merkle cap and Merkle proof in this proof has the length a proof
+    /// for `common_data` has, without hashing anything. `verify` does this first; servers receiving
+    /// proofs from untrusted clients can also do it before any other work, so that malformed proofs
+    /// are rejected cheaply.
+    pub fn validate_shape(&self, common_data: &CommonCircuitData<F, D>) -> anyhow::Result<()> {
+        validate_proof_with_pis_shape(self, common_data)
+    }
+
+    /// Hashes the public inputs in order, giving the digest the proof commits to. This is the native
+    /// counterpart of `CircuitBuilder::hash_public_inputs`.
+    pub fn get_public_inputs_hash(
         &self,
     ) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
         C::InnerHasher::hash_public_inputs(&self.public_inputs)
     }
 
+    /// Decodes the public inputs as `L`, checking that there are exactly as many as `L` expects.
+    pub fn decode_public_inputs<L: PublicInputsLayout<F>>(&self) -> anyhow::Result<L> {
+        L::from_public_inputs(&self.public_inputs)
+    }
+
+    /// Checks that `values` are those whose digest was registered at `slot` by
+    /// `CircuitBuilder::register_packed_public_inputs`.
+    pub fn check_packed_public_inputs(
+        &self,
+        slot: PublicInputSlot,
+        values: &[F],
+    ) -> anyhow::Result<()> {
+        let digest = C::InnerHasher::hash_public_inputs(values);
+        ensure!(
+            slot.get(&self.public_inputs)? == digest.to_vec(),
+            "Packed public inputs don't match their digest"
+        );
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buffer = Vec::new();
         buffer
@@ -111,17 +143,46 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer
     }
 
-    #[cfg(feature = "std")]
+    /// Deserializes a proof encoded by `to_bytes`. Malformed input, e.g. from an untrusted client,
+    /// gives an error rather than a panic, and the memory allocated is bounded by the size of a
+    /// proof for `common_data` plus the length of `bytes`.
     pub fn from_bytes(
-        bytes: Vec<u8>,
+        bytes: impl AsRef<[u8]>,
         common_data: &CommonCircuitData<F, D>,
     ) -> anyhow::Result<Self> {
-        let mut buffer = Buffer::new(bytes);
-        let proof = buffer
+        let mut bytes = bytes.as_ref();
+        let proof = bytes
             .read_proof_with_public_inputs(common_data)
             .map_err(anyhow::Error::msg)?;
         Ok(proof)
     }
+
+    /// Deserializes a proof encoded by `to_bytes` from `reader`, decoding each part as it's read.
+    /// Only the decoded proof is held in memory, never its encoding, and the reads are driven by
+    /// the shape of a proof for `common_data`, so a malicious sender can't make the reader
+    /// allocate more than a well-formed proof needs. Fails if `reader` has bytes left after the
+    /// proof.
+    #[cfg(feature = "std")]
+    pub fn from_reader(
+        reader: impl std::io::Read,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let mut reader = IoReader::new(reader);
+        let proof = reader.read_proof(common_data).map_err(anyhow::Error::msg)?;
+        // Public inputs come last and aren't length-prefixed, so their count is taken from
+        // `common_data` rather than from the end of the input.
+        let public_inputs = reader
+            .read_field_vec(common_data.num_public_inputs)
+            .map_err(anyhow::Error::msg)?;
+        ensure!(
+            reader.is_at_end().map_err(anyhow::Error::msg)?,
+            "Trailing bytes after proof"
+        );
+        Ok(Self {
+            proof,
+            public_inputs,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -227,10 +288,12 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
-    pub(crate) fn get_public_inputs_hash(
+    /// Hashes the public inputs in order, giving the digest the proof commits to. This is the native
+    /// counterpart of `CircuitBuilder::hash_public_inputs`.
+    pub fn get_public_inputs_hash(
         &self,
     ) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
-        C::InnerHasher::hash_no_pad(&self.public_inputs)
+        C::InnerHasher::hash_public_inputs(&self.public_inputs)
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
@@ -241,19 +304,134 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer
     }
 
-    #[cfg(feature = "std")]
+    /// Deserializes a proof encoded by `to_bytes`. Malformed input, e.g. from an untrusted client,
+    /// gives an error rather than a panic, and the memory allocated is bounded by the size of a
+    /// proof for `common_data` plus the length of `bytes`.
     pub fn from_bytes(
-        bytes: Vec<u8>,
+        bytes: impl AsRef<[u8]>,
         common_data: &CommonCircuitData<F, D>,
     ) -> anyhow::Result<Self> {
-        let mut buffer = Buffer::new(bytes);
-        let proof = buffer
+        let mut bytes = bytes.as_ref();
+        let proof = bytes
             .read_compressed_proof_with_public_inputs(common_data)
             .map_err(anyhow::Error::msg)?;
         Ok(proof)
     }
 }
 
+/// A proof together with the data needed to verify it, e.g. as passed from one layer of recursion
+/// to the next.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProvenCircuitOutput<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+    pub verifier_only: VerifierOnlyCircuitData<C, D>,
+    pub common: CommonCircuitData<F, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProvenCircuitOutput<F, C, D>
+{
+    pub fn new(
+        proof: ProofWithPublicInputs<F, C, D>,
+        verifier_only: VerifierOnlyCircuitData<C, D>,
+        common: CommonCircuitData<F, D>,
+    ) -> Self {
+        Self {
+            proof,
+            verifier_only,
+            common,
+        }
+    }
+
+    pub fn proof(&self) -> &ProofWithPublicInputs<F, C, D> {
+        &self.proof
+    }
+
+    pub fn verifier_only(&self) -> &VerifierOnlyCircuitData<C, D> {
+        &self.verifier_only
+    }
+
+    pub fn common(&self) -> &CommonCircuitData<F, D> {
+        &self.common
+    }
+
+    pub fn public_inputs(&self) -> &[F] {
+        &self.proof.public_inputs
+    }
+
+    pub fn into_parts(
+        self,
+    ) -> (
+        ProofWithPublicInputs<F, C, D>,
+        VerifierOnlyCircuitData<C, D>,
+        CommonCircuitData<F, D>,
+    ) {
+        (self.proof, self.verifier_only, self.common)
+    }
+
+    pub fn verify(&self) -> anyhow::Result<()> {
+        verify(self.proof.clone(), &self.verifier_only, &self.common)
+    }
+
+    /// Serializes the verifier data followed by the proof. The common data isn't serialized, as
+    /// gates can't be, so it must be known to whoever reads this back.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = self.verifier_only.to_bytes();
+        buffer
+            .write_proof_with_public_inputs(&self.proof)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes an output encoded by `to_bytes`, for a circuit with `common`. Malformed input
+    /// gives an error rather than a panic.
+    pub fn from_bytes(
+        bytes: impl AsRef<[u8]>,
+        common: CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let mut bytes = bytes.as_ref();
+        let constants_sigmas_cap = bytes
+            .read_merkle_cap(common.config.fri_config.cap_height)
+            .map_err(anyhow::Error::msg)?;
+        let circuit_digest = bytes
+            .read_hash::<F, C::Hasher>()
+            .map_err(anyhow::Error::msg)?;
+        let proof = bytes
+            .read_proof_with_public_inputs(&common)
+            .map_err(anyhow::Error::msg)?;
+        Ok(Self {
+            proof,
+            verifier_only: VerifierOnlyCircuitData {
+                constants_sigmas_cap,
+                circuit_digest,
+            },
+            common,
+        })
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    From<(
+        ProofWithPublicInputs<F, C, D>,
+        VerifierOnlyCircuitData<C, D>,
+        CommonCircuitData<F, D>,
+    )> for ProvenCircuitOutput<F, C, D>
+{
+    fn from(
+        (proof, verifier_only, common): (
+            ProofWithPublicInputs<F, C, D>,
+            VerifierOnlyCircuitData<C, D>,
+            CommonCircuitData<F, D>,
+        ),
+    ) -> Self {
+        Self::new(proof, verifier_only, common)
+    }
+}
+
 pub(crate) struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
     /// Random values used in Plonk's permutation argument.
     pub plonk_betas: Vec<F>,
@@ -392,13 +570,14 @@ impl<const D: usize> OpeningSetTarget<D> {
 mod tests {
     use anyhow::Result;
 
-    use crate::field::types::Sample;
+    use crate::field::types::{Field, Sample};
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::gates::noop::NoopGate;
-    use crate::iop::witness::PartialWitness;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::{ProofWithPublicInputs, ProvenCircuitOutput};
     use crate::plonk::verifier::verify;
 
     #[test]
@@ -438,4 +617,107 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)?;
         data.verify_compressed(compressed_proof)
     }
+
+    #[test]
+    fn test_validate_shape() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+        proof.validate_shape(&data.common)?;
+
+        let mut bad = proof.clone();
+        bad.public_inputs.push(F::ONE);
+        assert!(bad.validate_shape(&data.common).is_err());
+
+        // A cap whose length isn't a power of two must be rejected, not panic.
+        let mut bad = proof.clone();
+        bad.proof.wires_cap.0.pop();
+        assert!(bad.validate_shape(&data.common).is_err());
+
+        let mut bad = proof.clone();
+        let round = bad.proof.opening_proof.query_round_proofs[0].clone();
+        bad.proof.opening_proof.query_round_proofs.push(round);
+        assert!(bad.validate_shape(&data.common).is_err());
+
+        let mut bad = proof.clone();
+        let siblings = &mut bad.proof.opening_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs[0]
+            .1
+            .siblings;
+        siblings.push(siblings[0]);
+        assert!(bad.validate_shape(&data.common).is_err());
+
+        let mut bad = proof.clone();
+        bad.proof
+            .opening_proof
+            .commit_phase_merkle_caps
+            .push(proof.proof.wires_cap);
+        assert!(bad.validate_shape(&data.common).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_verify_from_reader() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+
+        let bytes = proof.to_bytes();
+        let decoded = ProofWithPublicInputs::<F, C, D>::from_reader(&bytes[..], &data.common)?;
+        assert_eq!(decoded, proof);
+        data.verify_from_reader(&bytes[..])?;
+
+        assert!(data.verify_from_reader(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(data.verify_from_reader(&trailing[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_proven_circuit_output_serialization() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let output =
+            ProvenCircuitOutput::new(data.prove(pw)?, data.verifier_only, data.common.clone());
+        output.verify()?;
+
+        let bytes = output.to_bytes();
+        let decoded = ProvenCircuitOutput::<F, C, D>::from_bytes(&bytes, data.common.clone())?;
+        assert_eq!(decoded, output);
+        assert!(ProvenCircuitOutput::<F, C, D>::from_bytes(&bytes[1..], data.common).is_err());
+        decoded.verify()
+    }
 }
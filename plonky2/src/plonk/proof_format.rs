@@ -0,0 +1,274 @@
+//! A versioned header for serialized proofs, so that a reader rejects blobs in a format it doesn't
+//! know rather than misreading them, and helpers for peers to agree on a format version.
+//!
+//! `ProofWithPublicInputs::to_versioned_bytes` prepends a `ProofFormatHeader` to the bytes of
+//! `to_bytes`: a magic number, the format version and the features the blob uses, e.g. whether
+//! the proof is compressed. Readers check the header with `ProofFormatHeader::read` before decoding
+//! anything else. A peer advertises the versions it reads, and the writer picks one with
+//! `negotiate_version` and writes it with `to_versioned_bytes_as`.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::GenericConfig;
+use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+
+/// The version of the format written by this code.
+pub const PROOF_FORMAT_VERSION: u16 = 1;
+
+/// The oldest version of the format this code reads.
+pub const MIN_PROOF_FORMAT_VERSION: u16 = 1;
+
+/// Whether this code reads proofs in format `version`.
+pub fn supports(version: u16) -> bool {
+    supported_versions().contains(&version)
+}
+
+/// The format versions this code reads, as advertised to peers.
+pub fn supported_versions() -> RangeInclusive<u16> {
+    MIN_PROOF_FORMAT_VERSION..=PROOF_FORMAT_VERSION
+}
+
+/// The newest format version both this code and a peer reading `peer_versions` support, if any.
+pub fn negotiate_version(peer_versions: RangeInclusive<u16>) -> Option<u16> {
+    let newest = PROOF_FORMAT_VERSION.min(*peer_versions.end());
+    let oldest = MIN_PROOF_FORMAT_VERSION.max(*peer_versions.start());
+    (oldest <= newest).then_some(newest)
+}
+
+/// The header of a serialized proof.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ProofFormatHeader {
+    pub version: u16,
+    /// A bitmask of the features the proof uses, e.g. `COMPRESSED`.
+    pub features: u16,
+}
+
+impl ProofFormatHeader {
+    pub const MAGIC: [u8; 4] = *b"PLK2";
+
+    /// The length of the header in bytes.
+    pub const LEN: usize = 8;
+
+    /// The proof is a `CompressedProofWithPublicInputs`.
+    pub const COMPRESSED: u16 = 1;
+
+    /// The features this code knows of.
+    const KNOWN_FEATURES: u16 = Self::COMPRESSED;
+
+    /// A header for the current format version.
+    pub fn current(features: u16) -> Self {
+        Self {
+            version: PROOF_FORMAT_VERSION,
+            features,
+        }
+    }
+
+    /// A header for format `version`, e.g. one picked by `negotiate_version`. Fails if this code
+    /// doesn't write that version.
+    pub fn with_version(version: u16, features: u16) -> Result<Self> {
+        ensure!(
+            supports(version),
+            "Unsupported proof format version {}, expected {:?}",
+            version,
+            supported_versions()
+        );
+        Ok(Self { version, features })
+    }
+
+    pub fn has_feature(&self, feature: u16) -> bool {
+        self.features & feature == feature
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[..4].copy_from_slice(&Self::MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6..].copy_from_slice(&self.features.to_le_bytes());
+        bytes
+    }
+
+    /// Reads the header at the start of `bytes`, returning it and the bytes after it. Fails if
+    /// `bytes` doesn't start with a header, or if this code doesn't read its version or features.
+    pub fn read(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        ensure!(
+            bytes.len() >= Self::LEN && bytes[..4] == Self::MAGIC,
+            "Not a versioned proof"
+        );
+        let header = Self {
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            features: u16::from_le_bytes([bytes[6], bytes[7]]),
+        };
+        ensure!(
+            supports(header.version),
+            "Unsupported proof format version {}, expected {:?}",
+            header.version,
+            supported_versions()
+        );
+        ensure!(
+            header.features & !Self::KNOWN_FEATURES == 0,
+            "Unknown proof format features {:#x}",
+            header.features & !Self::KNOWN_FEATURES
+        );
+        Ok((header, &bytes[Self::LEN..]))
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProofWithPublicInputs<F, C, D>
+{
+    /// Serializes the proof as `to_bytes` does, after a `ProofFormatHeader`.
+    pub fn to_versioned_bytes(&self) -> Vec<u8> {
+        [
+            &ProofFormatHeader::current(0).to_bytes()[..],
+            &self.to_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Like `to_versioned_bytes`, in format `version`, e.g. the one `negotiate_version` agreed on
+    /// with the reader.
+    pub fn to_versioned_bytes_as(&self, version: u16) -> Result<Vec<u8>> {
+        let header = ProofFormatHeader::with_version(version, 0)?;
+        Ok([&header.to_bytes()[..], &self.to_bytes()].concat())
+    }
+
+    /// Deserializes a proof encoded by `to_versioned_bytes`, by this version or an older one which
+    /// this code still reads.
+    pub fn from_versioned_bytes(
+        bytes: impl AsRef<[u8]>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<Self> {
+        let (header, bytes) = ProofFormatHeader::read(bytes.as_ref())?;
+        ensure!(
+            !header.has_feature(ProofFormatHeader::COMPRESSED),
+            "The proof is compressed"
+        );
+        Self::from_bytes(bytes, common_data)
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CompressedProofWithPublicInputs<F, C, D>
+{
+    /// Serializes the proof as `to_bytes` does, after a `ProofFormatHeader`.
+    pub fn to_versioned_bytes(&self) -> Vec<u8> {
+        [
+            &ProofFormatHeader::current(ProofFormatHeader::COMPRESSED).to_bytes()[..],
+            &self.to_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Like `to_versioned_bytes`, in format `version`, e.g. the one `negotiate_version` agreed on
+    /// with the reader.
+    pub fn to_versioned_bytes_as(&self, version: u16) -> Result<Vec<u8>> {
+        let header = ProofFormatHeader::with_version(version, ProofFormatHeader::COMPRESSED)?;
+        Ok([&header.to_bytes()[..], &self.to_bytes()].concat())
+    }
+
+    /// Deserializes a proof encoded by `to_versioned_bytes`, by this version or an older one which
+    /// this code still reads.
+    pub fn from_versioned_bytes(
+        bytes: impl AsRef<[u8]>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<Self> {
+        let (header, bytes) = ProofFormatHeader::read(bytes.as_ref())?;
+        ensure!(
+            header.has_feature(ProofFormatHeader::COMPRESSED),
+            "The proof isn't compressed"
+        );
+        Self::from_bytes(bytes, common_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    #[test]
+    fn test_negotiate_version() {
+        assert!(supports(PROOF_FORMAT_VERSION));
+        assert!(!supports(PROOF_FORMAT_VERSION + 1));
+        assert_eq!(negotiate_version(0..=u16::MAX), Some(PROOF_FORMAT_VERSION));
+        assert_eq!(
+            negotiate_version(supported_versions()),
+            Some(PROOF_FORMAT_VERSION)
+        );
+        assert_eq!(
+            negotiate_version(PROOF_FORMAT_VERSION + 1..=PROOF_FORMAT_VERSION + 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_versioned_proofs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let one = builder.one();
+        builder.register_public_input(one);
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let compressed_proof = data.compress(proof.clone())?;
+
+        let bytes = proof.to_versioned_bytes();
+        let compressed_bytes = compressed_proof.to_versioned_bytes();
+        // Writing the negotiated version gives the same bytes, as it's the current one.
+        let version = negotiate_version(supported_versions()).unwrap();
+        assert_eq!(proof.to_versioned_bytes_as(version)?, bytes);
+        assert_eq!(
+            compressed_proof.to_versioned_bytes_as(version)?,
+            compressed_bytes
+        );
+        assert!(proof
+            .to_versioned_bytes_as(PROOF_FORMAT_VERSION + 1)
+            .is_err());
+        assert_eq!(
+            ProofWithPublicInputs::from_versioned_bytes(&bytes, &data.common)?,
+            proof
+        );
+        assert_eq!(
+            CompressedProofWithPublicInputs::from_versioned_bytes(&compressed_bytes, &data.common)?,
+            compressed_proof
+        );
+
+        // Neither kind of proof is mistaken for the other, nor are unversioned proofs read.
+        assert!(ProofWithPublicInputs::<F, C, D>::from_versioned_bytes(
+            &compressed_bytes,
+            &data.common
+        )
+        .is_err());
+        assert!(ProofWithPublicInputs::<F, C, D>::from_versioned_bytes(
+            proof.to_bytes(),
+            &data.common
+        )
+        .is_err());
+
+        // Nor are proofs in a newer format, or with features this code doesn't know.
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(PROOF_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(
+            ProofWithPublicInputs::<F, C, D>::from_versioned_bytes(&newer, &data.common).is_err()
+        );
+        let mut unknown_feature = bytes;
+        unknown_feature[6] |= 0x80;
+        assert!(ProofWithPublicInputs::<F, C, D>::from_versioned_bytes(
+            &unknown_feature,
+            &data.common
+        )
+        .is_err());
+        Ok(())
+    }
+}
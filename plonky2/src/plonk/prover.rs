@@ -1,19 +1,23 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
-use core::mem::swap;
+use core::mem::{size_of, swap};
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use maybe_rayon::*;
 
 use crate::field::extension::Extendable;
+use crate::field::fft::FftRootTable;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::Field;
 use crate::field::zero_poly_coset::ZeroPolyOnCoset;
-use crate::fri::oracle::PolynomialBatch;
+use crate::fri::oracle::{LdeBuffer, PolynomialBatch};
 use crate::hash::hash_types::RichField;
 use crate::iop::challenger::Challenger;
 use crate::iop::generator::generate_partial_witness;
-use crate::iop::witness::{MatrixWitness, PartialWitness, Witness};
+use crate::iop::transcript::NativeTranscript;
+use crate::iop::transcript_log::TranscriptLog;
+use crate::iop::witness::{MatrixWitness, PartialWitness, Witness, WitnessLayout};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
@@ -23,13 +27,152 @@ use crate::plonk::vars::EvaluationVarsBaseBatch;
 use crate::timed;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
 use crate::util::timing::TimingTree;
-use crate::util::{ceil_div_usize, log2_ceil, transpose};
+#[cfg(feature = "zeroize")]
+use crate::util::zeroize::{zeroize_field_elements, zeroize_field_vecs};
+use crate::util::{log2_ceil, transpose};
+
+/// Options controlling how `prove_with_options` runs.
+#[derive(Clone, Debug, Default)]
+pub struct ProverOptions {
+    /// The pool the prover runs on. All of its parallel work, including FFTs nested in LDE
+    /// computations and the recursive hashing nested in Merkle tree construction, shares this pool,
+    /// so the prover never uses more threads than it has. Defaults to the global pool.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// How the prover stores the witness. `WitnessLayout::RowMajor` speeds up the permutation
+    /// argument on circuits with many routed wires, at the cost of a transposition to compute the
+    /// wire polynomials; the `witness_layout` benchmark compares both.
+    pub witness_layout: WitnessLayout,
+}
+
+impl ProverOptions {
+    /// Options running the prover on a dedicated pool of `num_threads` threads, e.g. to keep it
+    /// within a CPU budget on a shared server. The pool is created once, and reused by every proof
+    /// generated with these options.
+    pub fn with_num_threads(num_threads: usize) -> Result<Self> {
+        let pool = ThreadPool::new(num_threads)
+            .map_err(|e| anyhow!("Failed to build the prover's thread pool: {}", e))?;
+        Ok(Self {
+            thread_pool: Some(Arc::new(pool)),
+            ..Self::default()
+        })
+    }
+
+    /// The number of threads the prover runs on with these options.
+    pub fn num_threads(&self) -> usize {
+        self.thread_pool
+            .as_ref()
+            .map_or_else(current_num_threads, |pool| pool.num_threads())
+    }
+}
+
+/// Like `prove`, but running on the thread pool and with the witness layout given in `options`.
+pub fn prove_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    options: &ProverOptions,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let run = || {
+        prove_with_challenger(
+            prover_data,
+            common_data,
+            inputs,
+            timing,
+            &mut Challenger::new(),
+            options.witness_layout,
+        )
+    };
+    match &options.thread_pool {
+        Some(pool) => pool.install(run),
+        None => run(),
+    }
+}
 
 pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
     inputs: PartialWitness<F>,
     timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    prove_with_challenger(
+        prover_data,
+        common_data,
+        inputs,
+        timing,
+        &mut Challenger::new(),
+        WitnessLayout::default(),
+    )
+}
+
+/// Like `prove`, but also returns the prover's Fiat-Shamir transcript, to compare with the
+/// verifier's (see `ProofWithPublicInputs::transcript_log`) when debugging a verifier mismatch.
+pub fn prove_with_transcript_log<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<(ProofWithPublicInputs<F, C, D>, TranscriptLog<F>)> {
+    let mut challenger = Challenger::new_with_log();
+    let proof = prove_with_challenger(
+        prover_data,
+        common_data,
+        inputs,
+        timing,
+        &mut challenger,
+        WitnessLayout::default(),
+    )?;
+    let log = challenger
+        .take_transcript_log()
+        .expect("Challenger was created with a log");
+    Ok((proof, log))
+}
+
+/// Like `prove`, but deriving the Fiat-Shamir challenges from the given transcript instead of a
+/// `Challenger`. The proof must be verified with a fresh transcript of the same kind, see
+/// `verify_with_transcript`.
+pub fn prove_with_transcript<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    transcript: &mut T,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    prove_with_challenger(
+        prover_data,
+        common_data,
+        inputs,
+        timing,
+        transcript,
+        WitnessLayout::default(),
+    )
+}
+
+fn prove_with_challenger<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    challenger: &mut T,
+    witness_layout: WitnessLayout,
 ) -> Result<ProofWithPublicInputs<F, C, D>> {
     let config = &common_data.config;
     let num_challenges = config.num_challenges;
@@ -39,7 +182,7 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     let partition_witness = timed!(
         timing,
         &format!("run {} generators", prover_data.generators.len()),
-        generate_partial_witness(inputs, prover_data, common_data)
+        generate_partial_witness(inputs, prover_data, common_data)?
     );
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
@@ -48,41 +191,50 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     let witness = timed!(
         timing,
         "compute full witness",
-        partition_witness.full_witness()
+        partition_witness.full_witness_with_layout(witness_layout)
     );
 
     let wires_values: Vec<PolynomialValues<F>> = timed!(
         timing,
         "compute wire polynomials",
-        witness
-            .wire_values
-            .par_iter()
-            .map(|column| PolynomialValues::new(column.clone()))
-            .collect()
+        match witness.layout {
+            WitnessLayout::ColumnMajor => witness
+                .wire_values
+                .par_iter()
+                .map(|column| PolynomialValues::new(column.clone()))
+                .collect(),
+            WitnessLayout::RowMajor => transpose(&witness.wire_values)
+                .into_par_iter()
+                .map(PolynomialValues::new)
+                .collect(),
+        }
     );
 
+    // The wires and the partial products batches have LDEs of the same size, so they share one
+    // buffer.
+    let mut lde_buffer = LdeBuffer::new();
+
     let wires_commitment = timed!(
         timing,
         "compute wires commitment",
-        PolynomialBatch::from_values(
+        PolynomialBatch::from_values_with_buffer(
             wires_values,
             config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::WIRES.blinding,
+            config.is_hiding() && PlonkOracle::WIRES.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
+            &mut lde_buffer,
         )
     );
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
-
     // Observe the instance.
-    challenger.observe_hash::<C::Hasher>(prover_data.circuit_digest);
-    challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
+    challenger.observe_hash(&prover_data.circuit_digest);
+    challenger.observe_elements(&public_inputs_hash.elements);
 
     challenger.observe_cap(&wires_commitment.merkle_tree.cap);
-    let betas = challenger.get_n_challenges(num_challenges);
-    let gammas = challenger.get_n_challenges(num_challenges);
+    let betas = challenger.get_n_challenges(&mut (), num_challenges);
+    let gammas = challenger.get_n_challenges(&mut (), num_challenges);
 
     assert!(
         common_data.quotient_degree_factor < common_data.config.num_routed_wires,
@@ -104,19 +256,23 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     let partial_products_and_zs_commitment = timed!(
         timing,
         "commit to partial products and Z's",
-        PolynomialBatch::from_values(
+        PolynomialBatch::from_values_with_buffer(
             zs_partial_products,
             config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
+            config.is_hiding() && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
+            &mut lde_buffer,
         )
     );
 
     challenger.observe_cap(&partial_products_and_zs_commitment.merkle_tree.cap);
+    // Computing the quotient polynomials is often the peak of the proof, so the buffer is freed
+    // first, and the quotient commitment allocates its own.
+    drop(lde_buffer);
 
-    let alphas = challenger.get_n_challenges(num_challenges);
+    let alphas = challenger.get_n_challenges(&mut (), num_challenges);
 
     let quotient_polys = timed!(
         timing,
@@ -144,7 +300,10 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
                     "Quotient has failed, the vanishing polynomial is not divisible by Z_H",
                 );
                 // Split quotient into degree-n chunks.
-                quotient_poly.chunks(degree)
+                let chunks = quotient_poly.chunks(degree);
+                #[cfg(feature = "zeroize")]
+                zeroize_field_elements(&mut quotient_poly.coeffs);
+                chunks
             })
             .collect()
     );
@@ -155,7 +314,7 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
         PolynomialBatch::from_coeffs(
             all_quotient_poly_chunks,
             config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
+            config.is_hiding() && PlonkOracle::QUOTIENT.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
@@ -164,7 +323,7 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
 
     challenger.observe_cap(&quotient_polys_commitment.merkle_tree.cap);
 
-    let zeta = challenger.get_extension_challenge::<D>();
+    let zeta = challenger.get_extension_challenge(&mut ());
     // To avoid leaking witness data, we want to ensure that our opening locations, `zeta` and
     // `g * zeta`, are not in our subgroup `H`. It suffices to check `zeta` only, since
     // `(g * zeta)^n = zeta^n`, where `n` is the order of `g`.
@@ -200,16 +359,16 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
                 &partial_products_and_zs_commitment,
                 &quotient_polys_commitment,
             ],
-            &mut challenger,
+            challenger,
             &common_data.fri_params,
             timing,
         )
     );
 
     let proof = Proof {
-        wires_cap: wires_commitment.merkle_tree.cap,
-        plonk_zs_partial_products_cap: partial_products_and_zs_commitment.merkle_tree.cap,
-        quotient_polys_cap: quotient_polys_commitment.merkle_tree.cap,
+        wires_cap: wires_commitment.merkle_tree.cap.clone(),
+        plonk_zs_partial_products_cap: partial_products_and_zs_commitment.merkle_tree.cap.clone(),
+        quotient_polys_cap: quotient_polys_commitment.merkle_tree.cap.clone(),
         openings,
         opening_proof,
     };
@@ -306,8 +465,126 @@ fn wires_permutation_partial_products_and_zs<
         .collect()
 }
 
+/// The number of points whose constraints are evaluated together, each gate's at once for the
+/// whole batch.
 const BATCH_SIZE: usize = 32;
 
+/// The size, in bytes, of the values a chunk of the quotient domain is evaluated from. A chunk is
+/// one parallel task, whose batches reuse the same buffers, so chunks should hold several batches
+/// while leaving enough of them to spread over the threads; the value isn't tuned otherwise.
+const QUOTIENT_CHUNK_BYTES: usize = 1 << 18;
+
+/// The number of points of each chunk of the quotient domain, a multiple of `BATCH_SIZE`, when
+/// each point is evaluated from `values_per_point` values.
+fn quotient_chunk_size<F: Field>(values_per_point: usize) -> usize {
+    let batch_bytes = BATCH_SIZE * values_per_point.max(1) * size_of::<F>();
+    BATCH_SIZE * (QUOTIENT_CHUNK_BYTES / batch_bytes).max(1)
+}
+
+/// Lays `rows`, of equal lengths, out column by column in `batch`, as `EvaluationVarsBaseBatch`
+/// takes them, reusing its allocation.
+fn transpose_into<F: Field>(rows: &[&[F]], batch: &mut Vec<F>) {
+    let num_rows = rows.len();
+    batch.clear();
+    batch.resize(num_rows * rows[0].len(), F::ZERO);
+    for (j, row) in rows.iter().enumerate() {
+        for (i, &x) in row.iter().enumerate() {
+            batch[i * num_rows + j] = x;
+        }
+    }
+}
+
+/// The values of the polynomials of a committed batch on the domain of the quotient polynomials.
+enum QuotientDomainValues<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// Every `step`th point of the batch's LDE, when its rate covers the quotient degree.
+    Committed {
+        batch: &'a PolynomialBatch<F, C, D>,
+        step: usize,
+    },
+    /// The values on the larger domain needed by gates of higher degree than the rate, one row per
+    /// point in natural order.
+    Extended(Vec<Vec<F>>),
+    /// The same, computed once with the circuit data, see
+    /// `ProverOnlyCircuitData::constants_sigmas_quotient_values`.
+    Precomputed(&'a [Vec<F>]),
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    QuotientDomainValues<'a, F, C, D>
+{
+    fn new(
+        batch: &'a PolynomialBatch<F, C, D>,
+        quotient_degree_bits: usize,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        if quotient_degree_bits <= batch.rate_bits {
+            return Self::Committed {
+                batch,
+                step: 1 << (batch.rate_bits - quotient_degree_bits),
+            };
+        }
+        Self::Extended(quotient_domain_rows(
+            batch,
+            quotient_degree_bits,
+            fft_root_table,
+        ))
+    }
+
+    /// The values at the `index`th point of the quotient domain.
+    fn get(&self, index: usize) -> &[F] {
+        match self {
+            Self::Committed { batch, step } => batch.get_lde_values(index, *step),
+            Self::Extended(rows) => &rows[index],
+            Self::Precomputed(rows) => &rows[index],
+        }
+    }
+}
+
+/// The values of the polynomials of `batch` on the quotient domain, of `2^quotient_degree_bits`
+/// points per row of the trace, one row per point in natural order.
+pub(crate) fn quotient_domain_rows<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    batch: &PolynomialBatch<F, C, D>,
+    quotient_degree_bits: usize,
+    fft_root_table: Option<&FftRootTable<F>>,
+) -> Vec<Vec<F>> {
+    let columns = batch
+        .polynomials
+        .par_iter()
+        .map(|poly| {
+            let mut values = vec![F::ZERO; poly.len() << quotient_degree_bits];
+            poly.coset_lde_into(
+                F::coset_shift(),
+                quotient_degree_bits,
+                fft_root_table,
+                &mut values,
+            );
+            values
+        })
+        .collect::<Vec<_>>();
+    transpose(&columns)
+}
+
+/// The extended values may be derived from the witness, like the batch they're computed from.
+#[cfg(feature = "zeroize")]
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Drop
+    for QuotientDomainValues<'_, F, C, D>
+{
+    fn drop(&mut self) {
+        if let Self::Extended(rows) = self {
+            zeroize_field_vecs(rows);
+        }
+    }
+}
+
 fn compute_quotient_polys<
     'a,
     F: RichField + Extendable<D>,
@@ -325,15 +602,27 @@ fn compute_quotient_polys<
 ) -> Vec<PolynomialCoeffs<F>> {
     let num_challenges = common_data.config.num_challenges;
     let quotient_degree_bits = log2_ceil(common_data.quotient_degree_factor);
-    assert!(
-        quotient_degree_bits <= common_data.config.fri_config.rate_bits,
-        "Having constraints of degree higher than the rate is not supported yet. \
-        If we need this in the future, we can precompute the larger LDE before computing the `PolynomialBatch`s."
-    );
 
     // We reuse the LDE computed in `PolynomialBatch` and extract every `step` points to get
-    // an LDE matching `max_filtered_constraint_degree`.
-    let step = 1 << (common_data.config.fri_config.rate_bits - quotient_degree_bits);
+    // an LDE matching `max_filtered_constraint_degree`, unless the quotient degree exceeds the
+    // rate, in which case the LDEs are recomputed on the larger domain; those of the constants and
+    // sigmas only once, with the circuit data.
+    let fft_root_table = prover_data.fft_root_table.as_ref();
+    let constants_sigmas_values = match &prover_data.constants_sigmas_quotient_values {
+        Some(rows) => QuotientDomainValues::Precomputed(rows),
+        None => QuotientDomainValues::new(
+            &prover_data.constants_sigmas_commitment,
+            quotient_degree_bits,
+            fft_root_table,
+        ),
+    };
+    let wires_values =
+        QuotientDomainValues::new(wires_commitment, quotient_degree_bits, fft_root_table);
+    let zs_partial_products_values = QuotientDomainValues::new(
+        zs_partial_products_commitment,
+        quotient_degree_bits,
+        fft_root_table,
+    );
     // When opening the `Z`s polys at the "next" point in Plonk, need to look at the point `next_step`
     // steps away since we work on an LDE of degree `max_filtered_constraint_degree`.
     let next_step = 1 << quotient_degree_bits;
@@ -343,112 +632,106 @@ fn compute_quotient_polys<
 
     let z_h_on_coset = ZeroPolyOnCoset::new(common_data.degree_bits(), quotient_degree_bits);
 
-    let points_batches = points.par_chunks(BATCH_SIZE);
-    let num_batches = ceil_div_usize(points.len(), BATCH_SIZE);
-    let quotient_values: Vec<Vec<F>> = points_batches
+    // The domain is split into chunks of several batches, evaluated in parallel. Within a chunk,
+    // the constraints are evaluated a batch of points at a time, each gate's for the whole batch
+    // at once, and the buffers the batches are laid out in are reused.
+    let values_per_point = constants_sigmas_values.get(0).len()
+        + wires_values.get(0).len()
+        + zs_partial_products_values.get(0).len();
+    let chunk_size = quotient_chunk_size::<F>(values_per_point);
+    let quotient_values: Vec<Vec<F>> = points
+        .par_chunks(chunk_size)
         .enumerate()
-        .flat_map(|(batch_i, xs_batch)| {
-            // Each batch must be the same size, except the last one, which may be smaller.
-            debug_assert!(
-                xs_batch.len() == BATCH_SIZE
-                    || (batch_i == num_batches - 1 && xs_batch.len() <= BATCH_SIZE)
-            );
-
-            let indices_batch: Vec<usize> =
-                (BATCH_SIZE * batch_i..BATCH_SIZE * batch_i + xs_batch.len()).collect();
-
-            let mut shifted_xs_batch = Vec::with_capacity(xs_batch.len());
-            let mut local_zs_batch = Vec::with_capacity(xs_batch.len());
-            let mut next_zs_batch = Vec::with_capacity(xs_batch.len());
-            let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
-            let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
-
-            let mut local_constants_batch_refs = Vec::with_capacity(xs_batch.len());
-            let mut local_wires_batch_refs = Vec::with_capacity(xs_batch.len());
-
-            for (&i, &x) in indices_batch.iter().zip(xs_batch) {
-                let shifted_x = F::coset_shift() * x;
-                let i_next = (i + next_step) % lde_size;
-                let local_constants_sigmas = prover_data
-                    .constants_sigmas_commitment
-                    .get_lde_values(i, step);
-                let local_constants = &local_constants_sigmas[common_data.constants_range()];
-                let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
-                let local_wires = wires_commitment.get_lde_values(i, step);
-                let local_zs_partial_products =
-                    zs_partial_products_commitment.get_lde_values(i, step);
-                let local_zs = &local_zs_partial_products[common_data.zs_range()];
-                let next_zs = &zs_partial_products_commitment.get_lde_values(i_next, step)
-                    [common_data.zs_range()];
-                let partial_products =
-                    &local_zs_partial_products[common_data.partial_products_range()];
-
-                debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
-                debug_assert_eq!(local_zs.len(), num_challenges);
-
-                local_constants_batch_refs.push(local_constants);
-                local_wires_batch_refs.push(local_wires);
-
-                shifted_xs_batch.push(shifted_x);
-                local_zs_batch.push(local_zs);
-                next_zs_batch.push(next_zs);
-                partial_products_batch.push(partial_products);
-                s_sigmas_batch.push(s_sigmas);
-            }
-
-            // NB (JN): I'm not sure how (in)efficient the below is. It needs measuring.
-            let mut local_constants_batch =
-                vec![F::ZERO; xs_batch.len() * local_constants_batch_refs[0].len()];
-            for i in 0..local_constants_batch_refs[0].len() {
-                for (j, constants) in local_constants_batch_refs.iter().enumerate() {
-                    local_constants_batch[i * xs_batch.len() + j] = constants[i];
+        .flat_map(|(chunk_i, xs_chunk)| {
+            let mut local_constants_batch = Vec::new();
+            let mut local_wires_batch = Vec::new();
+            let mut chunk_quotient_values = Vec::with_capacity(xs_chunk.len());
+            for (batch_i, xs_batch) in xs_chunk.chunks(BATCH_SIZE).enumerate() {
+                let start = chunk_i * chunk_size + batch_i * BATCH_SIZE;
+                // Each batch must be the same size, except the last one, which may be smaller.
+                debug_assert!(xs_batch.len() == BATCH_SIZE || start + xs_batch.len() == lde_size);
+                let indices_batch: Vec<usize> = (start..start + xs_batch.len()).collect();
+
+                let mut shifted_xs_batch = Vec::with_capacity(xs_batch.len());
+                let mut local_zs_batch = Vec::with_capacity(xs_batch.len());
+                let mut next_zs_batch = Vec::with_capacity(xs_batch.len());
+                let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
+                let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
+
+                let mut local_constants_batch_refs = Vec::with_capacity(xs_batch.len());
+                let mut local_wires_batch_refs = Vec::with_capacity(xs_batch.len());
+
+                for (&i, &x) in indices_batch.iter().zip(xs_batch) {
+                    let shifted_x = F::coset_shift() * x;
+                    let i_next = (i + next_step) % lde_size;
+                    let local_constants_sigmas = constants_sigmas_values.get(i);
+                    let local_constants = &local_constants_sigmas[common_data.constants_range()];
+                    let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
+                    let local_wires = wires_values.get(i);
+                    let local_zs_partial_products = zs_partial_products_values.get(i);
+                    let local_zs = &local_zs_partial_products[common_data.zs_range()];
+                    let next_zs = &zs_partial_products_values.get(i_next)[common_data.zs_range()];
+                    let partial_products =
+                        &local_zs_partial_products[common_data.partial_products_range()];
+
+                    debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
+                    debug_assert_eq!(local_zs.len(), num_challenges);
+
+                    local_constants_batch_refs.push(local_constants);
+                    local_wires_batch_refs.push(local_wires);
+
+                    shifted_xs_batch.push(shifted_x);
+                    local_zs_batch.push(local_zs);
+                    next_zs_batch.push(next_zs);
+                    partial_products_batch.push(partial_products);
+                    s_sigmas_batch.push(s_sigmas);
                 }
-            }
-
-            let mut local_wires_batch =
-                vec![F::ZERO; xs_batch.len() * local_wires_batch_refs[0].len()];
-            for i in 0..local_wires_batch_refs[0].len() {
-                for (j, wires) in local_wires_batch_refs.iter().enumerate() {
-                    local_wires_batch[i * xs_batch.len() + j] = wires[i];
-                }
-            }
 
-            let vars_batch = EvaluationVarsBaseBatch::new(
-                xs_batch.len(),
-                &local_constants_batch,
-                &local_wires_batch,
-                public_inputs_hash,
-            );
+                transpose_into(&local_constants_batch_refs, &mut local_constants_batch);
+                transpose_into(&local_wires_batch_refs, &mut local_wires_batch);
+                let vars_batch = EvaluationVarsBaseBatch::new(
+                    xs_batch.len(),
+                    &local_constants_batch,
+                    &local_wires_batch,
+                    public_inputs_hash,
+                );
 
-            let mut quotient_values_batch = eval_vanishing_poly_base_batch::<F, C, D>(
-                common_data,
-                &indices_batch,
-                &shifted_xs_batch,
-                vars_batch,
-                &local_zs_batch,
-                &next_zs_batch,
-                &partial_products_batch,
-                &s_sigmas_batch,
-                betas,
-                gammas,
-                alphas,
-                &z_h_on_coset,
-            );
+                let mut quotient_values_batch = eval_vanishing_poly_base_batch::<F, C, D>(
+                    common_data,
+                    &indices_batch,
+                    &shifted_xs_batch,
+                    vars_batch,
+                    &local_zs_batch,
+                    &next_zs_batch,
+                    &partial_products_batch,
+                    &s_sigmas_batch,
+                    betas,
+                    gammas,
+                    alphas,
+                    &z_h_on_coset,
+                );
 
-            for (&i, quotient_values) in indices_batch.iter().zip(quotient_values_batch.iter_mut())
-            {
-                let denominator_inv = z_h_on_coset.eval_inverse(i);
-                quotient_values
-                    .iter_mut()
-                    .for_each(|v| *v *= denominator_inv);
+                for (&i, quotient_values) in
+                    indices_batch.iter().zip(quotient_values_batch.iter_mut())
+                {
+                    let denominator_inv = z_h_on_coset.eval_inverse(i);
+                    quotient_values
+                        .iter_mut()
+                        .for_each(|v| *v *= denominator_inv);
+                }
+                chunk_quotient_values.extend(quotient_values_batch);
             }
-            quotient_values_batch
+            #[cfg(feature = "zeroize")]
+            zeroize_field_elements(&mut local_wires_batch);
+            chunk_quotient_values
         })
         .collect();
 
     transpose(&quotient_values)
         .into_par_iter()
         .map(PolynomialValues::new)
-        .map(|values| values.coset_ifft(F::coset_shift()))
+        .map(|values| {
+            values.coset_ifft_with_options(F::coset_shift(), prover_data.fft_root_table.as_ref())
+        })
         .collect()
 }
@@ -0,0 +1,190 @@
+//! Typed views of the flat public inputs vector.
+//!
+//! A circuit's public inputs are a flat list of field elements, which applications usually group
+//! into values like hashes or amounts. Rather than tracking the index of each value by hand, an
+//! application declares a `PublicInputSlot` per value, each placed after the previous one with
+//! `PublicInputSlot::after`, so that all offsets are computed at compile time:
+//!
+//! ```
+//! use plonky2::plonk::public_inputs_layout::PublicInputSlot;
+//!
+//! const ROOT: PublicInputSlot = PublicInputSlot::first(4);
+//! const AMOUNT: PublicInputSlot = ROOT.after(1);
+//! const NUM_PUBLIC_INPUTS: usize = AMOUNT.end();
+//! assert_eq!(AMOUNT.offset, 4);
+//! assert_eq!(NUM_PUBLIC_INPUTS, 5);
+//! ```
+//!
+//! The circuit registers each value with `CircuitBuilder::register_public_inputs_at`, which checks
+//! that the value lands at its slot, and the application implements `PublicInputsLayout` for its
+//! public inputs struct to convert it to and from the flat vector.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use anyhow::{ensure, Result};
+
+use crate::field::types::Field;
+
+/// The position of a value of `len` field elements in the public inputs vector.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PublicInputSlot {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl PublicInputSlot {
+    /// The slot of the first value, of `len` elements.
+    pub const fn first(len: usize) -> Self {
+        Self { offset: 0, len }
+    }
+
+    /// The slot of a value of `len` elements, placed right after this one.
+    pub const fn after(self, len: usize) -> Self {
+        Self {
+            offset: self.end(),
+            len,
+        }
+    }
+
+    /// The index just past this slot, i.e. the number of public inputs if this is the last slot.
+    pub const fn end(self) -> usize {
+        self.offset + self.len
+    }
+
+    pub fn range(self) -> Range<usize> {
+        self.offset..self.end()
+    }
+
+    /// The elements of `public_inputs` in this slot. Works on both public input values and public
+    /// input targets.
+    pub fn get<T>(self, public_inputs: &[T]) -> Result<&[T]> {
+        ensure!(
+            self.end() <= public_inputs.len(),
+            "Public input slot {:?} is out of bounds for {} public inputs",
+            self.range(),
+            public_inputs.len()
+        );
+        Ok(&public_inputs[self.range()])
+    }
+
+    /// Writes `values` to this slot of `public_inputs`.
+    pub fn set<T: Copy>(self, public_inputs: &mut [T], values: &[T]) {
+        assert_eq!(
+            values.len(),
+            self.len,
+            "Value doesn't fit its public input slot"
+        );
+        public_inputs[self.range()].copy_from_slice(values);
+    }
+}
+
+/// A struct which is encoded as the whole public inputs vector of a circuit, with each of its
+/// fields in a `PublicInputSlot`.
+pub trait PublicInputsLayout<F: Field>: Sized {
+    /// The number of public inputs, usually the `end` of the last slot.
+    const NUM_PUBLIC_INPUTS: usize;
+
+    /// Writes each field to its slot of `public_inputs`, which has `NUM_PUBLIC_INPUTS` elements.
+    fn write_public_inputs(&self, public_inputs: &mut [F]);
+
+    /// Reads each field from its slot of `public_inputs`.
+    fn read_public_inputs(public_inputs: &[F]) -> Result<Self>;
+
+    fn to_public_inputs(&self) -> Vec<F> {
+        let mut public_inputs = vec![F::ZERO; Self::NUM_PUBLIC_INPUTS];
+        self.write_public_inputs(&mut public_inputs);
+        public_inputs
+    }
+
+    /// Like `read_public_inputs`, but also checks that there are exactly `NUM_PUBLIC_INPUTS`.
+    fn from_public_inputs(public_inputs: &[F]) -> Result<Self> {
+        ensure!(
+            public_inputs.len() == Self::NUM_PUBLIC_INPUTS,
+            "Expected {} public inputs, got {}",
+            Self::NUM_PUBLIC_INPUTS,
+            public_inputs.len()
+        );
+        Self::read_public_inputs(public_inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::HashOut;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::public_inputs_layout::{PublicInputSlot, PublicInputsLayout};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Transfer {
+        root: HashOut<F>,
+        amount: F,
+    }
+
+    impl Transfer {
+        const ROOT: PublicInputSlot = PublicInputSlot::first(4);
+        const AMOUNT: PublicInputSlot = Self::ROOT.after(1);
+    }
+
+    impl PublicInputsLayout<F> for Transfer {
+        const NUM_PUBLIC_INPUTS: usize = Self::AMOUNT.end();
+
+        fn write_public_inputs(&self, public_inputs: &mut [F]) {
+            Self::ROOT.set(public_inputs, &self.root.elements);
+            Self::AMOUNT.set(public_inputs, &[self.amount]);
+        }
+
+        fn read_public_inputs(public_inputs: &[F]) -> Result<Self> {
+            Ok(Self {
+                root: HashOut::from_partial(Self::ROOT.get(public_inputs)?),
+                amount: Self::AMOUNT.get(public_inputs)?[0],
+            })
+        }
+    }
+
+    #[test]
+    fn test_public_inputs_layout() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let root = builder.add_virtual_hash();
+        let amount = builder.add_virtual_target();
+        builder.register_public_inputs_at(Transfer::ROOT, &root.elements);
+        builder.register_public_inputs_at(Transfer::AMOUNT, &[amount]);
+        let data = builder.build::<C>();
+
+        let transfer = Transfer {
+            root: HashOut::rand(),
+            amount: F::from_canonical_u64(100),
+        };
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(root, transfer.root);
+        pw.set_target(amount, transfer.amount);
+        let proof = data.prove(pw)?;
+
+        assert_eq!(proof.public_inputs, transfer.to_public_inputs());
+        assert_eq!(
+            Transfer::from_public_inputs(&proof.public_inputs)?,
+            transfer
+        );
+        assert!(Transfer::from_public_inputs(&proof.public_inputs[1..]).is_err());
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "registered at public input")]
+    fn test_register_out_of_order() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let amount = builder.add_virtual_target();
+        builder.register_public_inputs_at(Transfer::AMOUNT, &[amount]);
+    }
+}
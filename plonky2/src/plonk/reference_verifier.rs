@@ -0,0 +1,926 @@
+//! A slow, straightforward verifier for Goldilocks proofs with Poseidon, meant only to check the
+//! other verifiers against: the native verifier, the recursive verifier gadget, and the exported
+//! circom and Solidity verifiers, given the same proofs.
+//!
+//! It shares no arithmetic with them. Field elements are plain `u64`s reduced with `u128`
+//! remainders, as `addmod` and `mulmod` would in Solidity, Poseidon is the textbook permutation
+//! without the fast partial rounds, and every step follows the protocol literally, e.g. FRI folding
+//! evaluates a Lagrange interpolant directly. Only the constraints of each gate come from
+//! `Gate::eval_unfiltered`, as writing every gate twice would just move the drift elsewhere; the
+//! selectors and filters around them are recomputed here.
+//!
+//! It is only compiled in tests and with the `reference_verifier` feature, as nothing should rely
+//! on it to accept proofs.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use keccak_hash::keccak;
+
+use crate::field::extension::quadratic::QuadraticExtension;
+use crate::field::extension::FieldExtension;
+use crate::field::goldilocks_field::GoldilocksField;
+use crate::field::types::{Field, PrimeField64};
+use crate::fri::FriPowHash;
+use crate::gates::selectors::UNUSED_SELECTOR;
+use crate::hash::hash_types::HashOut;
+use crate::hash::merkle_proofs::MerkleProof;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::poseidon::{Poseidon, ALL_ROUND_CONSTANTS, HALF_N_FULL_ROUNDS, N_ROUNDS};
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use crate::plonk::proof::ProofWithPublicInputs;
+use crate::plonk::vars::EvaluationVars;
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+type H = <C as GenericConfig<D>>::Hasher;
+const D: usize = 2;
+
+/// The Goldilocks prime.
+const P: u64 = 0xFFFF_FFFF_0000_0001;
+/// The non-residue `W` of the quadratic extension `GF(p)[X] / (X^2 - W)`.
+const W: u64 = 7;
+/// The generator of the multiplicative group, whose coset the LDE domain is.
+const GENERATOR: u64 = 7;
+/// A generator of the subgroup of order `2^32`.
+const TWO_ADIC_GENERATOR: u64 = 1753635133440165772;
+const TWO_ADICITY: usize = 32;
+
+const SPONGE_WIDTH: usize = 12;
+const SPONGE_RATE: usize = 8;
+/// The number of elements salting Merkle leaves of blinded oracles in hiding proofs.
+const SALT_SIZE: usize = 4;
+
+fn add(a: u64, b: u64) -> u64 {
+    ((a as u128 + b as u128) % P as u128) as u64
+}
+
+fn sub(a: u64, b: u64) -> u64 {
+    add(a, P - b % P)
+}
+
+fn mul(a: u64, b: u64) -> u64 {
+    ((a as u128 * b as u128) % P as u128) as u64
+}
+
+fn exp(mut base: u64, mut power: u64) -> u64 {
+    let mut result = 1;
+    while power > 0 {
+        if power & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        power >>= 1;
+    }
+    result
+}
+
+fn inv(a: u64) -> u64 {
+    exp(a, P - 2)
+}
+
+/// A primitive `2^log_n`-th root of unity.
+fn root_of_unity(log_n: usize) -> u64 {
+    exp(TWO_ADIC_GENERATOR, 1 << (TWO_ADICITY - log_n))
+}
+
+/// An element `a + b X` of the quadratic extension.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Ext(u64, u64);
+
+impl Ext {
+    const ZERO: Self = Self(0, 0);
+    const ONE: Self = Self(1, 0);
+
+    fn base(a: u64) -> Self {
+        Self(a, 0)
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        Self(add(self.0, rhs.0), add(self.1, rhs.1))
+    }
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(sub(self.0, rhs.0), sub(self.1, rhs.1))
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(
+            add(mul(self.0, rhs.0), mul(W, mul(self.1, rhs.1))),
+            add(mul(self.0, rhs.1), mul(self.1, rhs.0)),
+        )
+    }
+
+    fn scale(self, c: u64) -> Self {
+        Self(mul(self.0, c), mul(self.1, c))
+    }
+
+    fn exp(self, mut power: u64) -> Self {
+        let mut base = self;
+        let mut result = Self::ONE;
+        while power > 0 {
+            if power & 1 == 1 {
+                result = result.mul(base);
+            }
+            base = base.mul(base);
+            power >>= 1;
+        }
+        result
+    }
+
+    /// `(a + b X)^-1 = (a - b X) / (a^2 - W b^2)`.
+    fn inv(self) -> Self {
+        let norm_inv = inv(sub(mul(self.0, self.0), mul(W, mul(self.1, self.1))));
+        Self(mul(self.0, norm_inv), mul(sub(0, self.1), norm_inv))
+    }
+
+    fn div(self, rhs: Self) -> Self {
+        self.mul(rhs.inv())
+    }
+
+    fn from_field(x: QuadraticExtension<F>) -> Self {
+        let [a, b] = x.to_basefield_array();
+        Self(a.to_canonical_u64(), b.to_canonical_u64())
+    }
+
+    fn to_field(self) -> QuadraticExtension<F> {
+        QuadraticExtension([F::from_canonical_u64(self.0), F::from_canonical_u64(self.1)])
+    }
+}
+
+fn elements(xs: &[F]) -> Vec<u64> {
+    xs.iter().map(|x| x.to_canonical_u64()).collect()
+}
+
+fn ext_elements(xs: &[QuadraticExtension<F>]) -> Vec<Ext> {
+    xs.iter().map(|&x| Ext::from_field(x)).collect()
+}
+
+fn hash_elements(hash: &HashOut<F>) -> [u64; 4] {
+    hash.elements.map(|x| x.to_canonical_u64())
+}
+
+/// `sum_i terms[i] alpha^i`.
+fn reduce(terms: &[Ext], alpha: Ext) -> Ext {
+    terms
+        .iter()
+        .rev()
+        .fold(Ext::ZERO, |acc, &term| acc.mul(alpha).add(term))
+}
+
+/// The Poseidon permutation, round by round as in its specification.
+fn poseidon(mut state: [u64; SPONGE_WIDTH]) -> [u64; SPONGE_WIDTH] {
+    for round in 0..N_ROUNDS {
+        for (i, s) in state.iter_mut().enumerate() {
+            *s = add(*s, ALL_ROUND_CONSTANTS[i + SPONGE_WIDTH * round]);
+        }
+        let is_partial_round = (HALF_N_FULL_ROUNDS..N_ROUNDS - HALF_N_FULL_ROUNDS).contains(&round);
+        let sbox_width = if is_partial_round { 1 } else { SPONGE_WIDTH };
+        for s in &mut state[..sbox_width] {
+            *s = exp(*s, 7);
+        }
+        state = core::array::from_fn(|r| {
+            let row = (0..SPONGE_WIDTH).fold(0, |acc, i| {
+                add(
+                    acc,
+                    mul(state[(i + r) % SPONGE_WIDTH], F::MDS_MATRIX_CIRC[i]),
+                )
+            });
+            add(row, mul(state[r], F::MDS_MATRIX_DIAG[r]))
+        });
+    }
+    state
+}
+
+fn hash_no_pad(inputs: &[u64]) -> [u64; 4] {
+    let mut state = [0; SPONGE_WIDTH];
+    for chunk in inputs.chunks(SPONGE_RATE) {
+        state[..chunk.len()].copy_from_slice(chunk);
+        state = poseidon(state);
+    }
+    state[..4].try_into().unwrap()
+}
+
+fn two_to_one(left: [u64; 4], right: [u64; 4]) -> [u64; 4] {
+    let mut state = [0; SPONGE_WIDTH];
+    state[..4].copy_from_slice(&left);
+    state[4..8].copy_from_slice(&right);
+    poseidon(state)[..4].try_into().unwrap()
+}
+
+fn verify_merkle_proof(
+    leaf: &[u64],
+    mut index: usize,
+    cap: &MerkleCap<F, H>,
+    proof: &MerkleProof<F, H>,
+) -> Result<()> {
+    // Leaves of at most 4 elements are their own digest.
+    let mut digest = if leaf.len() <= 4 {
+        let mut digest = [0; 4];
+        digest[..leaf.len()].copy_from_slice(leaf);
+        digest
+    } else {
+        hash_no_pad(leaf)
+    };
+    for sibling in &proof.siblings {
+        let sibling = hash_elements(sibling);
+        digest = if index & 1 == 1 {
+            two_to_one(sibling, digest)
+        } else {
+            two_to_one(digest, sibling)
+        };
+        index >>= 1;
+    }
+    ensure!(index < cap.0.len(), "Merkle proof is too short");
+    ensure!(
+        digest == hash_elements(&cap.0[index]),
+        "Invalid Merkle proof"
+    );
+    Ok(())
+}
+
+/// The Fiat-Shamir sponge, in overwrite mode. Challenges are squeezed from the end of the rate.
+struct Sponge {
+    state: [u64; SPONGE_WIDTH],
+    inputs: Vec<u64>,
+    outputs: Vec<u64>,
+}
+
+impl Sponge {
+    fn new() -> Self {
+        Self {
+            state: [0; SPONGE_WIDTH],
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    fn duplex(&mut self) {
+        for (s, &x) in self.state.iter_mut().zip(&self.inputs) {
+            *s = x;
+        }
+        self.inputs.clear();
+        self.state = poseidon(self.state);
+        self.outputs = self.state[..SPONGE_RATE].to_vec();
+    }
+
+    fn observe(&mut self, x: u64) {
+        self.outputs.clear();
+        self.inputs.push(x);
+        if self.inputs.len() == SPONGE_RATE {
+            self.duplex();
+        }
+    }
+
+    fn observe_all(&mut self, xs: &[u64]) {
+        for &x in xs {
+            self.observe(x);
+        }
+    }
+
+    fn observe_ext(&mut self, xs: &[Ext]) {
+        for x in xs {
+            self.observe_all(&[x.0, x.1]);
+        }
+    }
+
+    fn observe_cap(&mut self, cap: &MerkleCap<F, H>) {
+        for hash in &cap.0 {
+            self.observe_all(&hash_elements(hash));
+        }
+    }
+
+    fn challenge(&mut self) -> u64 {
+        if !self.inputs.is_empty() || self.outputs.is_empty() {
+            self.duplex();
+        }
+        self.outputs.pop().unwrap()
+    }
+
+    fn challenges(&mut self, n: usize) -> Vec<u64> {
+        (0..n).map(|_| self.challenge()).collect()
+    }
+
+    fn ext_challenge(&mut self) -> Ext {
+        let a = self.challenge();
+        let b = self.challenge();
+        Ext(a, b)
+    }
+}
+
+/// The Fiat-Shamir challenges of a proof, as computed by the reference verifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferenceChallenges {
+    pub plonk_betas: Vec<u64>,
+    pub plonk_gammas: Vec<u64>,
+    pub plonk_alphas: Vec<u64>,
+    pub plonk_zeta: [u64; 2],
+    pub fri_alpha: [u64; 2],
+    pub fri_betas: Vec<[u64; 2]>,
+    /// With `FriPowHash::Keccak`, the first 8 bytes of the digest as a big-endian integer, which,
+    /// unlike `FriChallenges::fri_pow_response`, isn't shifted to be a canonical field element.
+    pub fri_pow_response: u64,
+    pub fri_query_indices: Vec<usize>,
+}
+
+/// Computes the Fiat-Shamir challenges of `proof`.
+pub fn reference_challenges(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> ReferenceChallenges {
+    let num_challenges = common_data.config.num_challenges;
+    let openings = &proof.proof.openings;
+    let fri_proof = &proof.proof.opening_proof;
+    let mut sponge = Sponge::new();
+
+    sponge.observe_all(&hash_elements(&verifier_data.circuit_digest));
+    sponge.observe_all(&hash_no_pad(&elements(&proof.public_inputs)));
+
+    sponge.observe_cap(&proof.proof.wires_cap);
+    let plonk_betas = sponge.challenges(num_challenges);
+    let plonk_gammas = sponge.challenges(num_challenges);
+    sponge.observe_cap(&proof.proof.plonk_zs_partial_products_cap);
+    let plonk_alphas = sponge.challenges(num_challenges);
+    sponge.observe_cap(&proof.proof.quotient_polys_cap);
+    let plonk_zeta = sponge.ext_challenge();
+
+    for values in [
+        &openings.constants,
+        &openings.plonk_sigmas,
+        &openings.wires,
+        &openings.plonk_zs,
+        &openings.partial_products,
+        &openings.quotient_polys,
+        &openings.plonk_zs_next,
+    ] {
+        sponge.observe_ext(&ext_elements(values));
+    }
+
+    let fri_alpha = sponge.ext_challenge();
+    let fri_betas = fri_proof
+        .commit_phase_merkle_caps
+        .iter()
+        .map(|cap| {
+            sponge.observe_cap(cap);
+            let beta = sponge.ext_challenge();
+            [beta.0, beta.1]
+        })
+        .collect();
+    sponge.observe_ext(&ext_elements(&fri_proof.final_poly.coeffs));
+    let lde_size = common_data.lde_size() as u64;
+    let num_query_rounds = common_data.config.fri_config.num_query_rounds;
+    let pow_witness = fri_proof.pow_witness.to_canonical_u64();
+    let (fri_pow_response, fri_query_indices) = match common_data.config.fri_config.pow_hash {
+        FriPowHash::Field => {
+            sponge.observe(pow_witness);
+            let fri_pow_response = sponge.challenge();
+            let fri_query_indices = (0..num_query_rounds)
+                .map(|_| (sponge.challenge() % lde_size) as usize)
+                .collect();
+            (fri_pow_response, fri_query_indices)
+        }
+        FriPowHash::Keccak => {
+            let mut input = vec![];
+            for x in sponge.challenges(4).into_iter().chain([pow_witness]) {
+                input.extend(x.to_le_bytes());
+            }
+            let digest = keccak(input).0;
+            let fri_query_indices = (0..num_query_rounds)
+                .map(|i| {
+                    let mut input = digest.to_vec();
+                    input.extend((i as u64 / 4).to_le_bytes());
+                    let block = keccak(input).0;
+                    let word = u64::from_le_bytes(block[8 * (i % 4)..][..8].try_into().unwrap());
+                    (word % lde_size) as usize
+                })
+                .collect();
+            let fri_pow_response = u64::from_be_bytes(digest[..8].try_into().unwrap());
+            (fri_pow_response, fri_query_indices)
+        }
+    };
+
+    ReferenceChallenges {
+        plonk_betas,
+        plonk_gammas,
+        plonk_alphas,
+        plonk_zeta: [plonk_zeta.0, plonk_zeta.1],
+        fri_alpha: [fri_alpha.0, fri_alpha.1],
+        fri_betas,
+        fri_pow_response,
+        fri_query_indices,
+    }
+}
+
+/// Verifies `proof` from scratch. This is much slower than `CircuitData::verify`, and only meant
+/// for differential tests.
+pub fn reference_verify(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    check_shape(proof, common_data)?;
+    let challenges = reference_challenges(proof, verifier_data, common_data);
+    check_vanishing_poly(proof, common_data, &challenges)?;
+    check_fri(proof, verifier_data, common_data, &challenges)
+}
+
+/// Checks the lengths which the checks below rely on.
+fn check_shape(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    let config = &common_data.config;
+    let openings = &proof.proof.openings;
+    let fri_proof = &proof.proof.opening_proof;
+    let params = &common_data.fri_params;
+    ensure!(proof.public_inputs.len() == common_data.num_public_inputs);
+    ensure!(openings.constants.len() == common_data.num_constants);
+    ensure!(openings.plonk_sigmas.len() == config.num_routed_wires);
+    ensure!(openings.wires.len() == config.num_wires);
+    ensure!(openings.plonk_zs.len() == config.num_challenges);
+    ensure!(openings.plonk_zs_next.len() == config.num_challenges);
+    ensure!(
+        openings.partial_products.len() == config.num_challenges * common_data.num_partial_products
+    );
+    ensure!(
+        openings.quotient_polys.len() == config.num_challenges * common_data.quotient_degree_factor
+    );
+    ensure!(fri_proof.commit_phase_merkle_caps.len() == params.reduction_arity_bits.len());
+    ensure!(fri_proof.query_round_proofs.len() == params.config.num_query_rounds);
+    ensure!(fri_proof.final_poly.coeffs.len() == params.final_poly_len());
+
+    let salt = if params.hiding { SALT_SIZE } else { 0 };
+    let leaf_lens = [
+        common_data.num_constants + config.num_routed_wires,
+        config.num_wires + salt,
+        config.num_challenges * (1 + common_data.num_partial_products) + salt,
+        config.num_challenges * common_data.quotient_degree_factor + salt,
+    ];
+    for round in &fri_proof.query_round_proofs {
+        let initial = &round.initial_trees_proof.evals_proofs;
+        ensure!(initial.len() == leaf_lens.len());
+        for ((leaf, _), &len) in initial.iter().zip(&leaf_lens) {
+            ensure!(leaf.len() == len);
+        }
+        ensure!(round.steps.len() == params.reduction_arity_bits.len());
+        for (step, &arity_bits) in round.steps.iter().zip(&params.reduction_arity_bits) {
+            ensure!(step.evals.len() == 1 << arity_bits);
+        }
+    }
+    Ok(())
+}
+
+/// Checks `vanishing(zeta) = Z_H(zeta) quotient(zeta)` for each challenge.
+fn check_vanishing_poly(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    challenges: &ReferenceChallenges,
+) -> Result<()> {
+    let config = &common_data.config;
+    let openings = &proof.proof.openings;
+    let constants = ext_elements(&openings.constants);
+    let wires = ext_elements(&openings.wires);
+    let sigmas = ext_elements(&openings.plonk_sigmas);
+    let zs = ext_elements(&openings.plonk_zs);
+    let zs_next = ext_elements(&openings.plonk_zs_next);
+    let partial_products = ext_elements(&openings.partial_products);
+    let quotient_polys = ext_elements(&openings.quotient_polys);
+
+    let zeta = Ext(challenges.plonk_zeta[0], challenges.plonk_zeta[1]);
+    let n = common_data.degree() as u64;
+    let zeta_pow_n = zeta.exp(n);
+    let z_h_zeta = zeta_pow_n.sub(Ext::ONE);
+    // L_0(zeta) = (zeta^n - 1) / (n (zeta - 1)).
+    let l_0_zeta = z_h_zeta.div(zeta.sub(Ext::ONE).scale(n % P));
+
+    // Gate constraints, each filtered by its selector polynomial.
+    let selectors = &common_data.selectors_info;
+    let num_selectors = selectors.num_selectors();
+    let public_inputs_hash = HashOut {
+        elements: hash_no_pad(&elements(&proof.public_inputs)).map(F::from_canonical_u64),
+    };
+    let gate_constants = constants[num_selectors..]
+        .iter()
+        .map(|c| c.to_field())
+        .collect::<Vec<_>>();
+    let gate_wires = wires.iter().map(|w| w.to_field()).collect::<Vec<_>>();
+    let mut constraint_terms = vec![Ext::ZERO; common_data.num_gate_constraints];
+    for (row, gate) in common_data.gates.iter().enumerate() {
+        let selector_index = selectors.selector_indices[row];
+        let selector = constants[selector_index];
+        let filter = selectors.groups[selector_index]
+            .clone()
+            .filter(|&i| i != row)
+            .chain((num_selectors > 1).then_some(UNUSED_SELECTOR))
+            .fold(Ext::ONE, |acc, i| {
+                acc.mul(Ext::base(i as u64).sub(selector))
+            });
+        let vars = EvaluationVars {
+            local_constants: &gate_constants,
+            local_wires: &gate_wires,
+            public_inputs_hash: &public_inputs_hash,
+        };
+        let constraints = gate.0.eval_unfiltered(vars);
+        ensure!(constraints.len() <= constraint_terms.len());
+        for (term, c) in constraint_terms.iter_mut().zip(constraints) {
+            *term = term.add(filter.mul(Ext::from_field(c)));
+        }
+    }
+
+    let max_degree = common_data.quotient_degree_factor;
+    let num_prods = common_data.num_partial_products;
+    let mut z_1_terms = vec![];
+    let mut partial_product_terms = vec![];
+    for i in 0..config.num_challenges {
+        let beta = challenges.plonk_betas[i];
+        let gamma = Ext::base(challenges.plonk_gammas[i]);
+        z_1_terms.push(l_0_zeta.mul(zs[i].sub(Ext::ONE)));
+
+        // The permutation argument: the product of `numerators / denominators` over all routed
+        // wires is accumulated in chunks of `max_degree` from `Z(zeta)`, through the partial
+        // products, to `Z(g zeta)`.
+        let numerators = (0..config.num_routed_wires)
+            .map(|j| {
+                let s_id = zeta.scale(common_data.k_is[j].to_canonical_u64());
+                wires[j].add(s_id.scale(beta)).add(gamma)
+            })
+            .collect::<Vec<_>>();
+        let denominators = (0..config.num_routed_wires)
+            .map(|j| wires[j].add(sigmas[j].scale(beta)).add(gamma))
+            .collect::<Vec<_>>();
+        let mut accumulators = vec![zs[i]];
+        accumulators.extend_from_slice(&partial_products[i * num_prods..(i + 1) * num_prods]);
+        accumulators.push(zs_next[i]);
+        ensure!(accumulators.len() - 1 == numerators.chunks(max_degree).len());
+        for (k, (num_chunk, den_chunk)) in numerators
+            .chunks(max_degree)
+            .zip(denominators.chunks(max_degree))
+            .enumerate()
+        {
+            let num_product = num_chunk.iter().fold(Ext::ONE, |acc, &x| acc.mul(x));
+            let den_product = den_chunk.iter().fold(Ext::ONE, |acc, &x| acc.mul(x));
+            partial_product_terms.push(
+                accumulators[k]
+                    .mul(num_product)
+                    .sub(accumulators[k + 1].mul(den_product)),
+            );
+        }
+    }
+
+    let terms = [z_1_terms, partial_product_terms, constraint_terms].concat();
+    for (i, quotient_chunks) in quotient_polys.chunks(max_degree).enumerate() {
+        let vanishing = reduce(&terms, Ext::base(challenges.plonk_alphas[i]));
+        let quotient = reduce(quotient_chunks, zeta_pow_n);
+        ensure!(
+            vanishing == z_h_zeta.mul(quotient),
+            "Vanishing polynomial doesn't match the quotient"
+        );
+    }
+    Ok(())
+}
+
+/// Evaluates at `beta` the polynomial of degree less than `evals.len()` through the points
+/// `(xs[i], evals[i])`.
+fn lagrange_interpolate(xs: &[u64], evals: &[Ext], beta: Ext) -> Ext {
+    let mut result = Ext::ZERO;
+    for (i, (&x_i, &eval)) in xs.iter().zip(evals).enumerate() {
+        let mut numerator = Ext::ONE;
+        let mut denominator = 1;
+        for (j, &x_j) in xs.iter().enumerate() {
+            if j != i {
+                numerator = numerator.mul(beta.sub(Ext::base(x_j)));
+                denominator = mul(denominator, sub(x_i, x_j));
+            }
+        }
+        result = result.add(eval.mul(numerator).scale(inv(denominator)));
+    }
+    result
+}
+
+fn reverse_bits(x: usize, bits: usize) -> usize {
+    (0..bits).fold(0, |acc, i| (acc << 1) | ((x >> i) & 1))
+}
+
+/// Checks the FRI proof that the openings are those of polynomials of the committed degrees.
+fn check_fri(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    challenges: &ReferenceChallenges,
+) -> Result<()> {
+    let params = &common_data.fri_params;
+    let fri_proof = &proof.proof.opening_proof;
+    let openings = &proof.proof.openings;
+
+    let pow_bits = params.config.proof_of_work_bits;
+    ensure!(
+        challenges.fri_pow_response.leading_zeros() >= pow_bits,
+        "Invalid proof of work"
+    );
+
+    // Polynomials opened at `zeta`, in the order of their oracles, and those opened at `g zeta`.
+    let zeta = Ext(challenges.plonk_zeta[0], challenges.plonk_zeta[1]);
+    let zeta_next = zeta.scale(root_of_unity(common_data.degree_bits()));
+    let zeta_openings = ext_elements(
+        &[
+            openings.constants.as_slice(),
+            &openings.plonk_sigmas,
+            &openings.wires,
+            &openings.plonk_zs,
+            &openings.partial_products,
+            &openings.quotient_polys,
+        ]
+        .concat(),
+    );
+    let zeta_next_openings = ext_elements(&openings.plonk_zs_next);
+    let alpha = Ext(challenges.fri_alpha[0], challenges.fri_alpha[1]);
+    let zeta_reduced = reduce(&zeta_openings, alpha);
+    let zeta_next_reduced = reduce(&zeta_next_openings, alpha);
+
+    let caps = [
+        &verifier_data.constants_sigmas_cap,
+        &proof.proof.wires_cap,
+        &proof.proof.plonk_zs_partial_products_cap,
+        &proof.proof.quotient_polys_cap,
+    ];
+    // Only the constants and sigmas aren't blinded, and so never salted.
+    let salts = [0, SALT_SIZE, SALT_SIZE, SALT_SIZE].map(|s| if params.hiding { s } else { 0 });
+    let num_zs = common_data.config.num_challenges;
+    let log_n = params.lde_bits();
+
+    for (&query_index, round) in challenges
+        .fri_query_indices
+        .iter()
+        .zip(&fri_proof.query_round_proofs)
+    {
+        let mut zeta_evals = vec![];
+        let mut zeta_next_evals = vec![];
+        for (oracle, (((leaf, merkle_proof), cap), salt)) in round
+            .initial_trees_proof
+            .evals_proofs
+            .iter()
+            .zip(caps)
+            .zip(salts)
+            .enumerate()
+        {
+            let leaf = elements(leaf);
+            verify_merkle_proof(&leaf, query_index, cap, merkle_proof)?;
+            let evals = &leaf[..leaf.len() - salt];
+            zeta_evals.extend(evals.iter().map(|&e| Ext::base(e)));
+            // The Z polynomials come first in their oracle.
+            if oracle == 2 {
+                zeta_next_evals.extend(evals[..num_zs].iter().map(|&e| Ext::base(e)));
+            }
+        }
+
+        // The combined polynomial `sum_b alpha^.. (f_b(x) - f_b(z_b)) / (x - z_b)`, times `x` so
+        // that it has the degree the final polynomial is checked against.
+        let mut x = mul(
+            GENERATOR,
+            exp(
+                root_of_unity(log_n),
+                reverse_bits(query_index, log_n) as u64,
+            ),
+        );
+        let zeta_quotient = reduce(&zeta_evals, alpha)
+            .sub(zeta_reduced)
+            .div(Ext::base(x).sub(zeta));
+        let zeta_next_quotient = reduce(&zeta_next_evals, alpha)
+            .sub(zeta_next_reduced)
+            .div(Ext::base(x).sub(zeta_next));
+        let mut eval = zeta_quotient
+            .mul(alpha.exp(zeta_next_evals.len() as u64))
+            .add(zeta_next_quotient)
+            .scale(x);
+
+        let mut index = query_index;
+        for (i, (step, &arity_bits)) in round
+            .steps
+            .iter()
+            .zip(&params.reduction_arity_bits)
+            .enumerate()
+        {
+            let arity = 1 << arity_bits;
+            let coset_index = index >> arity_bits;
+            let index_in_coset = index & (arity - 1);
+            let evals = ext_elements(&step.evals);
+            ensure!(
+                evals[index_in_coset] == eval,
+                "Inconsistent FRI folding at step {}",
+                i
+            );
+
+            // `evals[j]` is the evaluation at `x_0 g^reverse_bits(j)`, where `x_0` is the first
+            // point of `x`'s coset and `g` a primitive `arity`-th root of unity.
+            let g = root_of_unity(arity_bits);
+            let x_0 = mul(
+                x,
+                inv(exp(g, reverse_bits(index_in_coset, arity_bits) as u64)),
+            );
+            let xs = (0..arity)
+                .map(|j| mul(x_0, exp(g, reverse_bits(j, arity_bits) as u64)))
+                .collect::<Vec<_>>();
+            let beta = Ext(challenges.fri_betas[i][0], challenges.fri_betas[i][1]);
+            eval = lagrange_interpolate(&xs, &evals, beta);
+
+            let leaf = evals.iter().flat_map(|e| [e.0, e.1]).collect::<Vec<_>>();
+            verify_merkle_proof(
+                &leaf,
+                coset_index,
+                &fri_proof.commit_phase_merkle_caps[i],
+                &step.merkle_proof,
+            )?;
+
+            x = exp(x, arity as u64);
+            index = coset_index;
+        }
+
+        let final_eval = ext_elements(&fri_proof.final_poly.coeffs)
+            .iter()
+            .rev()
+            .fold(Ext::ZERO, |acc, &c| acc.scale(x).add(c));
+        ensure!(final_eval == eval, "Final polynomial evaluation is invalid");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::extension::{Extendable, FieldExtension};
+    use crate::field::types::{Field, PrimeField64, Sample};
+    use crate::fri::{FriConfig, FriPowHash};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+    use crate::plonk::reference_verifier::{reference_challenges, reference_verify};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FE = <F as Extendable<D>>::Extension;
+
+    /// A proof of a circuit using several kinds of gates.
+    fn fixture(
+        config: CircuitConfig,
+    ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let xy = builder.mul(x, y);
+        let x_pow = builder.exp_u64(x, 12345);
+        let mut hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x, y, xy, x_pow]);
+        // Enough rows for FRI to have a reduction step.
+        for _ in 0..64 {
+            hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(hash.elements.to_vec());
+        }
+        let table = builder.add_virtual_targets(8);
+        let index = builder.constant(F::from_canonical_u64(5));
+        let entry = builder.random_access(index, table.clone());
+        builder.range_check(y, 32);
+        let x_ext = builder.convert_to_ext(x);
+        let x_ext_squared = builder.square_extension(x_ext);
+        builder.register_public_inputs(&hash.elements);
+        builder.register_public_input(entry);
+        builder.register_public_inputs(&x_ext_squared.0);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        pw.set_target(y, F::from_canonical_u64(0xdeadbeef));
+        for t in table {
+            pw.set_target(t, F::rand());
+        }
+        let proof = data.prove(pw)?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_reference_verifier() -> Result<()> {
+        let (data, proof) = fixture(CircuitConfig::standard_recursion_config())?;
+        reference_verify(&proof, &data.verifier_only, &data.common)?;
+
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let reference = reference_challenges(&proof, &data.verifier_only, &data.common);
+        let canonical = |xs: &[F]| xs.iter().map(|x| x.to_canonical_u64()).collect::<Vec<_>>();
+        assert_eq!(canonical(&challenges.plonk_betas), reference.plonk_betas);
+        assert_eq!(canonical(&challenges.plonk_gammas), reference.plonk_gammas);
+        assert_eq!(canonical(&challenges.plonk_alphas), reference.plonk_alphas);
+        let zeta: [F; D] = challenges.plonk_zeta.to_basefield_array();
+        assert_eq!(canonical(&zeta), reference.plonk_zeta);
+        assert_eq!(
+            challenges.fri_challenges.fri_query_indices,
+            reference.fri_query_indices
+        );
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_reference_verifier_hiding() -> Result<()> {
+        let config = CircuitConfig {
+            zero_knowledge: true,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let (data, proof) = fixture(config)?;
+        reference_verify(&proof, &data.verifier_only, &data.common)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_reference_verifier_keccak_pow() -> Result<()> {
+        let standard = CircuitConfig::standard_recursion_config();
+        let config = CircuitConfig {
+            fri_config: FriConfig {
+                pow_hash: FriPowHash::Keccak,
+                ..standard.fri_config.clone()
+            },
+            ..standard
+        };
+        let (data, proof) = fixture(config)?;
+        reference_verify(&proof, &data.verifier_only, &data.common)?;
+
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let reference = reference_challenges(&proof, &data.verifier_only, &data.common);
+        assert_eq!(
+            challenges.fri_challenges.fri_query_indices,
+            reference.fri_query_indices
+        );
+        assert_eq!(
+            challenges
+                .fri_challenges
+                .fri_pow_response
+                .to_canonical_u64(),
+            reference.fri_pow_response >> 1
+        );
+        assert!(reference.fri_pow_response.leading_zeros() >= 16);
+
+        let mut tampered = proof.clone();
+        tampered.proof.opening_proof.pow_witness += F::ONE;
+        assert!(reference_verify(&tampered, &data.verifier_only, &data.common).is_err());
+        assert!(data.verify(tampered).is_err());
+        data.verify(proof)
+    }
+
+    /// The recursive verifier gadget must accept what the reference verifier accepts, and its own
+    /// proofs must pass the reference verifier.
+    #[test]
+    fn test_reference_verifier_recursion() -> Result<()> {
+        let (inner, inner_proof) = fixture(CircuitConfig::standard_recursion_config())?;
+        reference_verify(&inner_proof, &inner.verifier_only, &inner.common)?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_target = builder.add_virtual_proof_with_pis::<C>(&inner.common);
+        let verifier_target = builder.constant_verifier_data(&inner.verifier_only);
+        builder.verify_proof::<C>(&proof_target, &verifier_target, &inner.common);
+        builder.register_public_inputs(&proof_target.public_inputs);
+        let outer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_target, &inner_proof);
+        let outer_proof = outer.prove(pw)?;
+        reference_verify(&outer_proof, &outer.verifier_only, &outer.common)?;
+        outer.verify(outer_proof)
+    }
+
+    /// Both verifiers must reject the same tampered proofs.
+    #[test]
+    fn test_reference_verifier_rejects() -> Result<()> {
+        let (data, proof) = fixture(CircuitConfig::standard_recursion_config())?;
+        let tamperings: [fn(&mut ProofWithPublicInputs<F, C, D>); 9] = [
+            |p| p.public_inputs[0] += F::ONE,
+            |p| p.proof.wires_cap.0[0].elements[0] += F::ONE,
+            |p| p.proof.openings.wires[0] += FE::ONE,
+            |p| p.proof.openings.plonk_zs_next[0] += FE::ONE,
+            |p| p.proof.openings.quotient_polys[1] += FE::ONE,
+            |p| p.proof.opening_proof.final_poly.coeffs[0] += FE::ONE,
+            |p| p.proof.opening_proof.pow_witness += F::ONE,
+            |p| {
+                let round = &mut p.proof.opening_proof.query_round_proofs[0];
+                round.initial_trees_proof.evals_proofs[1].0[0] += F::ONE;
+            },
+            |p| p.proof.opening_proof.query_round_proofs[0].steps[0].evals[0] += FE::ONE,
+        ];
+        for tamper in tamperings {
+            let mut tampered = proof.clone();
+            tamper(&mut tampered);
+            assert!(reference_verify(&tampered, &data.verifier_only, &data.common).is_err());
+            assert!(data.verify(tampered).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! Plain, serializable descriptions of circuits and proofs, for tools which inspect them without
+//! knowing their types, e.g. `plonky2-inspect`.
+//!
+//! Gates can't be deserialized, so `CommonCircuitData` can't be loaded back from a file; a
+//! `CircuitSummary` saved as JSON next to the circuit's other artifacts stands in for it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::fri::FriPowHash;
+use crate::hash::hash_types::RichField;
+use crate::hash::hashing::SPONGE_RATE;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::GenericConfig;
+use crate::plonk::proof::ProofWithPublicInputs;
+use crate::util::{ceil_div_usize, log2_ceil};
+
+/// A gate type used by a circuit.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GateSummary {
+    pub id: String,
+    pub degree: usize,
+    pub num_constraints: usize,
+}
+
+/// The FRI parameters of a circuit.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct FriSummary {
+    pub rate_bits: usize,
+    pub cap_height: usize,
+    pub proof_of_work_bits: u32,
+    pub pow_hash: FriPowHash,
+    pub num_query_rounds: usize,
+    pub reduction_arity_bits: Vec<usize>,
+    pub final_poly_len: usize,
+    pub hiding: bool,
+    pub conjectured_security_bits: usize,
+}
+
+/// The shape of a circuit, as described by its `CommonCircuitData`.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct CircuitSummary {
+    pub degree_bits: usize,
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    pub num_constants: usize,
+    pub num_public_inputs: usize,
+    pub num_challenges: usize,
+    pub quotient_degree_factor: usize,
+    pub num_partial_products: usize,
+    pub num_gate_constraints: usize,
+    pub zero_knowledge: bool,
+    pub fri: FriSummary,
+    pub gates: Vec<GateSummary>,
+}
+
+impl CircuitSummary {
+    pub fn new<F: RichField + Extendable<D>, const D: usize>(
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Self {
+        let config = &common_data.config;
+        let fri_params = &common_data.fri_params;
+        Self {
+            degree_bits: common_data.degree_bits(),
+            num_wires: config.num_wires,
+            num_routed_wires: config.num_routed_wires,
+            num_constants: common_data.num_constants,
+            num_public_inputs: common_data.num_public_inputs,
+            num_challenges: config.num_challenges,
+            quotient_degree_factor: common_data.quotient_degree_factor,
+            num_partial_products: common_data.num_partial_products,
+            num_gate_constraints: common_data.num_gate_constraints,
+            zero_knowledge: config.zero_knowledge,
+            fri: FriSummary {
+                rate_bits: fri_params.config.rate_bits,
+                cap_height: fri_params.config.cap_height,
+                proof_of_work_bits: fri_params.config.proof_of_work_bits,
+                pow_hash: fri_params.config.pow_hash,
+                num_query_rounds: fri_params.config.num_query_rounds,
+                reduction_arity_bits: fri_params.reduction_arity_bits.clone(),
+                final_poly_len: fri_params.final_poly_len(),
+                hiding: fri_params.hiding,
+                conjectured_security_bits: fri_params.config.conjectured_security_bits(),
+            },
+            gates: common_data
+                .gates
+                .iter()
+                .map(|gate| GateSummary {
+                    id: gate.0.id(),
+                    degree: gate.0.degree(),
+                    num_constraints: gate.0.num_constraints(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The shape of a proof, read from the proof alone.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ProofSummary {
+    /// The length of the proof encoded by `ProofWithPublicInputs::to_bytes`.
+    pub size_bytes: usize,
+    pub public_inputs: Vec<u64>,
+    pub cap_height: usize,
+    pub num_query_rounds: usize,
+    pub reduction_arity_bits: Vec<usize>,
+    pub final_poly_len: usize,
+    /// An estimate of the number of hash permutations a verifier computes, which dominates the
+    /// cost of verifying the proof in a circuit or on chain.
+    pub estimated_verifier_permutations: usize,
+}
+
+impl ProofSummary {
+    pub fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    ) -> Self {
+        let proof = &proof_with_pis.proof;
+        let fri_proof = &proof.opening_proof;
+        let reduction_arity_bits = fri_proof
+            .query_round_proofs
+            .first()
+            .map(|round| {
+                round
+                    .steps
+                    .iter()
+                    .map(|step| log2_ceil(step.evals.len()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Permutations hashing `n` elements; at most 4 elements are their own digest in Merkle
+        // trees, but we don't distinguish that here.
+        let hash_cost = |n: usize| ceil_div_usize(n, SPONGE_RATE);
+        let openings = &proof.openings;
+        let num_observed = 8
+            + 4 * (proof.wires_cap.0.len()
+                + proof.plonk_zs_partial_products_cap.0.len()
+                + proof.quotient_polys_cap.0.len())
+            + D * (openings.constants.len()
+                + openings.plonk_sigmas.len()
+                + openings.wires.len()
+                + openings.plonk_zs.len()
+                + openings.plonk_zs_next.len()
+                + openings.partial_products.len()
+                + openings.quotient_polys.len())
+            + fri_proof
+                .commit_phase_merkle_caps
+                .iter()
+                .map(|cap| 4 * cap.0.len())
+                .sum::<usize>()
+            + D * fri_proof.final_poly.coeffs.len()
+            + 1;
+        let num_merkle_permutations = fri_proof
+            .query_round_proofs
+            .iter()
+            .map(|round| {
+                let initial = round
+                    .initial_trees_proof
+                    .evals_proofs
+                    .iter()
+                    .map(|(leaf, proof)| hash_cost(leaf.len()) + proof.len())
+                    .sum::<usize>();
+                let steps = round
+                    .steps
+                    .iter()
+                    .map(|step| hash_cost(D * step.evals.len()) + step.merkle_proof.len())
+                    .sum::<usize>();
+                initial + steps
+            })
+            .sum::<usize>();
+
+        Self {
+            size_bytes: proof_with_pis.to_bytes().len(),
+            public_inputs: proof_with_pis
+                .public_inputs
+                .iter()
+                .map(|x| x.to_canonical_u64())
+                .collect(),
+            cap_height: log2_ceil(proof.wires_cap.0.len()),
+            num_query_rounds: fri_proof.query_round_proofs.len(),
+            reduction_arity_bits,
+            final_poly_len: fri_proof.final_poly.coeffs.len(),
+            estimated_verifier_permutations: hash_cost(proof_with_pis.public_inputs.len())
+                + hash_cost(num_observed)
+                + num_merkle_permutations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{PrimeField64, Sample};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::summary::{CircuitSummary, ProofSummary};
+
+    #[test]
+    fn test_summaries() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let mut hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(vec![x]);
+        for _ in 0..100 {
+            hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(hash.elements.to_vec());
+        }
+        builder.register_public_inputs(&hash.elements);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+
+        let circuit = CircuitSummary::new(&data.common);
+        assert_eq!(circuit.degree_bits, data.common.degree_bits());
+        assert_eq!(circuit.num_public_inputs, 4);
+        assert!(circuit
+            .gates
+            .iter()
+            .any(|gate| gate.id.contains("Poseidon")));
+        assert_eq!(
+            circuit.fri.num_query_rounds,
+            config.fri_config.num_query_rounds
+        );
+
+        let summary = ProofSummary::new(&proof);
+        assert_eq!(summary.size_bytes, proof.to_bytes().len());
+        assert_eq!(
+            summary.public_inputs,
+            proof
+                .public_inputs
+                .iter()
+                .map(|x| x.to_canonical_u64())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(summary.cap_height, circuit.fri.cap_height);
+        assert_eq!(summary.num_query_rounds, circuit.fri.num_query_rounds);
+        assert_eq!(
+            summary.reduction_arity_bits,
+            circuit.fri.reduction_arity_bits
+        );
+        assert_eq!(summary.final_poly_len, circuit.fri.final_poly_len);
+        assert!(summary.estimated_verifier_permutations > summary.num_query_rounds);
+
+        // Summaries are meant to be saved and read back by tools.
+        let json = serde_json::to_string(&circuit)?;
+        assert_eq!(serde_json::from_str::<CircuitSummary>(&json)?, circuit);
+        Ok(())
+    }
+}
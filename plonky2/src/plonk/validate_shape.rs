@@ -1,6 +1,7 @@
 use anyhow::ensure;
 
 use crate::field::extension::Extendable;
+use crate::fri::validate_shape::validate_fri_proof_shape;
 use crate::hash::hash_types::RichField;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::plonk::config::GenericConfig;
@@ -40,9 +41,7 @@ where
         plonk_zs_partial_products_cap,
         quotient_polys_cap,
         openings,
-        // The shape of the opening proof will be checked in the FRI verifier (see
-        // validate_fri_proof_shape), so we ignore it here.
-        opening_proof: _,
+        opening_proof,
     } = proof;
     let OpeningSet {
         constants,
@@ -54,9 +53,10 @@ where
         quotient_polys,
     } = openings;
     let cap_height = common_data.fri_params.config.cap_height;
-    ensure!(wires_cap.height() == cap_height);
-    ensure!(plonk_zs_partial_products_cap.height() == cap_height);
-    ensure!(quotient_polys_cap.height() == cap_height);
+    // Compare lengths rather than heights, which would panic on lengths that aren't powers of two.
+    ensure!(wires_cap.len() == 1 << cap_height);
+    ensure!(plonk_zs_partial_products_cap.len() == 1 << cap_height);
+    ensure!(quotient_polys_cap.len() == 1 << cap_height);
     ensure!(constants.len() == common_data.num_constants);
     ensure!(plonk_sigmas.len() == config.num_routed_wires);
     ensure!(wires.len() == config.num_wires);
@@ -64,5 +64,9 @@ where
     ensure!(plonk_zs_next.len() == config.num_challenges);
     ensure!(partial_products.len() == config.num_challenges * common_data.num_partial_products);
     ensure!(quotient_polys.len() == common_data.num_quotient_polys());
-    Ok(())
+    validate_fri_proof_shape::<F, C, D>(
+        opening_proof,
+        &common_data.fri_oracles(),
+        &common_data.fri_params,
+    )
 }
@@ -4,10 +4,14 @@ use crate::field::extension::Extendable;
 use crate::field::types::Field;
 use crate::fri::verifier::verify_fri_proof;
 use crate::hash::hash_types::RichField;
+use crate::iop::challenger::Challenger;
+use crate::iop::transcript::NativeTranscript;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::reduce_with_powers;
-use crate::plonk::proof::{Proof, ProofChallenges, ProofWithPublicInputs};
+use crate::plonk::proof::{
+    CompressedProofWithPublicInputs, Proof, ProofChallenges, ProofWithPublicInputs,
+};
 use crate::plonk::validate_shape::validate_proof_with_pis_shape;
 use crate::plonk::vanishing_poly::eval_vanishing_poly;
 use crate::plonk::vars::EvaluationVars;
@@ -16,15 +20,37 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     proof_with_pis: ProofWithPublicInputs<F, C, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    verify_with_transcript(
+        proof_with_pis,
+        verifier_data,
+        common_data,
+        &mut Challenger::new(),
+    )
+}
+
+/// Like `verify`, for a proof generated with `prove_with_transcript`. The transcript must be fresh
+/// and of the same kind as the prover's.
+pub(crate) fn verify_with_transcript<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: NativeTranscript<F, C::Hasher, D>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    transcript: &mut T,
 ) -> Result<()> {
     validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
 
     let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
 
-    let challenges = proof_with_pis.get_challenges(
+    let challenges = proof_with_pis.get_challenges_with(
         public_inputs_hash,
         &verifier_data.circuit_digest,
         common_data,
+        transcript,
     )?;
 
     verify_with_challenges(
@@ -36,6 +62,84 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     )
 }
 
+/// Decodes a proof encoded by `ProofWithPublicInputs::to_bytes` and verifies it, rejecting any
+/// encoding other than the one `to_bytes` gives for the decoded proof, so that a valid proof can't
+/// be altered into another valid one with a different hash.
+pub(crate) fn verify_strict<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    bytes: &[u8],
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    // Field elements out of range and partial trailing elements are rejected by the decoder, and
+    // extra trailing elements by the shape check, as they're read as public inputs.
+    let proof_with_pis = ProofWithPublicInputs::<F, C, D>::from_bytes(bytes, common_data)?;
+    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+    ensure!(
+        proof_with_pis.to_bytes() == bytes,
+        "Non-canonical proof encoding"
+    );
+    verify(proof_with_pis, verifier_data, common_data)
+}
+
+/// Like `verify_strict`, for a proof encoded by `CompressedProofWithPublicInputs::to_bytes`. The
+/// query indices must be those derived from the transcript, in order, and the proof must be
+/// exactly the compression of the proof it decompresses to.
+pub(crate) fn verify_compressed_strict<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    bytes: &[u8],
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    let compressed = CompressedProofWithPublicInputs::<F, C, D>::from_bytes(bytes, common_data)?;
+    ensure!(
+        compressed.public_inputs.len() == common_data.num_public_inputs,
+        "Number of public inputs doesn't match circuit data."
+    );
+    ensure!(
+        compressed.to_bytes() == bytes,
+        "Non-canonical proof encoding"
+    );
+
+    let public_inputs_hash = compressed.get_public_inputs_hash();
+    let challenges = compressed.get_challenges(
+        public_inputs_hash,
+        &verifier_data.circuit_digest,
+        common_data,
+    )?;
+    // Decompression only uses the indices derived from the transcript, so the ones sent in the
+    // proof would otherwise be unchecked.
+    let indices = &challenges.fri_challenges.fri_query_indices;
+    ensure!(
+        &compressed.proof.opening_proof.query_round_proofs.indices == indices,
+        "Query indices don't match the transcript"
+    );
+    let fri_inferred_elements = compressed.get_inferred_elements(&challenges, common_data);
+    let proof = compressed.proof.clone().decompress(
+        &challenges,
+        fri_inferred_elements,
+        &common_data.fri_params,
+    );
+    ensure!(
+        proof.clone().compress(indices, &common_data.fri_params) == compressed.proof,
+        "Non-canonical proof compression"
+    );
+
+    verify_with_challenges(
+        proof,
+        public_inputs_hash,
+        challenges,
+        verifier_data,
+        common_data,
+    )
+}
+
 pub(crate) fn verify_with_challenges<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -109,3 +213,61 @@ pub(crate) fn verify_with_challenges<
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_data::VerifierCircuitData;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::test_utils::{fast_test_config, fibonacci_proof};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A proof of `fibonacci_circuit` with `fast_test_config`, and its verifier data, written by
+    /// `generate_verifier_fixture`. They're included rather than read, as Miri isolates tests from
+    /// the file system.
+    const VERIFIER_DATA: &[u8] = include_bytes!("../../fixtures/fibonacci_verifier_data.bin");
+    const PROOF: &[u8] = include_bytes!("../../fixtures/fibonacci_proof.bin");
+
+    #[test]
+    fn test_verify() -> Result<()> {
+        let (data, proof) = fibonacci_proof::<F, C, D>(&fast_test_config(), 4)?;
+        data.verify(proof.clone())?;
+
+        let mut tampered = proof;
+        tampered.public_inputs[2] += F::ONE;
+        assert!(data.verify(tampered).is_err());
+        Ok(())
+    }
+
+    /// Only verifies, so that it's fast enough to run under Miri, which checks the verifier for
+    /// undefined behavior with the `forbid-unsafe` feature, see the README.
+    #[test]
+    fn test_verify_serialized_proof() -> Result<()> {
+        let data = VerifierCircuitData::<F, C, D>::from_bytes(VERIFIER_DATA)?;
+        let proof = ProofWithPublicInputs::from_bytes(PROOF, &data.common)?;
+        data.verify(proof.clone())?;
+
+        let mut tampered = proof;
+        tampered.public_inputs[2] += F::ONE;
+        assert!(data.verify(tampered).is_err());
+        Ok(())
+    }
+
+    /// Writes the fixtures of `test_verify_serialized_proof`. Run after a change to the proof
+    /// format, and commit the result.
+    #[test]
+    #[ignore]
+    fn generate_verifier_fixture() -> Result<()> {
+        let (data, proof) = fibonacci_proof::<F, C, D>(&fast_test_config(), 4)?;
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        std::fs::write(
+            dir.join("fibonacci_verifier_data.bin"),
+            data.verifier_data().to_bytes(),
+        )?;
+        std::fs::write(dir.join("fibonacci_proof.bin"), proof.to_bytes())?;
+        Ok(())
+    }
+}
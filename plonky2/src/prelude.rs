@@ -0,0 +1,40 @@
+//! The types and traits most circuits need, under names which we keep stable across versions:
+//!
+//! ```ignore
+//! use plonky2::prelude::*;
+//! ```
+//!
+//! Gadgets are methods of `CircuitBuilder` itself, e.g. `hash_n_to_hash_no_pad`,
+//! `verify_merkle_proof`, `range_check`, `select` or `random_access`, so importing the builder is
+//! enough to use them; this also brings in the targets they take and return, the witness traits
+//! which set and read those targets, and the configs, circuit data and proofs they produce. The
+//! generator trait and types are included for circuits which compute their own advice.
+//!
+//! Modules the prelude doesn't cover may still move between versions.
+
+pub use crate::field::extension::{Extendable, FieldExtension};
+pub use crate::field::goldilocks_field::GoldilocksField;
+pub use crate::field::types::{Field, PrimeField64, Sample};
+pub use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+pub use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
+pub use crate::hash::keccak::KeccakHash;
+pub use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+pub use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+pub use crate::hash::poseidon::PoseidonHash;
+pub use crate::iop::ext_target::ExtensionTarget;
+pub use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+pub use crate::iop::target::{BoolTarget, Target};
+pub use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+pub use crate::plonk::circuit_builder::CircuitBuilder;
+pub use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, ProverCircuitData, VerifierCircuitData,
+    VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
+pub use crate::plonk::config::{
+    AlgebraicHasher, GenericConfig, GenericHashOut, Hasher, Keccak256GoldilocksConfig,
+    KeccakGoldilocksConfig, PoseidonGoldilocksConfig,
+};
+pub use crate::plonk::proof::{
+    CompressedProofWithPublicInputs, Proof, ProofWithPublicInputs, ProofWithPublicInputsTarget,
+    ProvenCircuitOutput,
+};
@@ -0,0 +1,135 @@
+//! Composition of existing circuits into a product circuit, which verifies one proof of each of
+//! them, e.g. a transaction aggregate and an unrelated attestation, so that a single outer proof
+//! attests to both.
+//!
+//! Each call to `CircuitBuilder::embed_circuit` verifies a proof of one inner circuit, fixed by its
+//! verifier data, and re-registers the inner proof's public inputs after those already registered.
+//! The outer public inputs are thus the concatenation of the inner ones, in embedding order, and
+//! the `PublicInputSlot` of each embedded circuit tells where its public inputs are.
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::ProofWithPublicInputsTarget;
+use crate::plonk::public_inputs_layout::PublicInputSlot;
+
+/// The targets of an embedded circuit's proof, to be set with `set_proof_with_pis_target`.
+#[derive(Clone, Debug)]
+pub struct EmbeddedCircuitTarget<const D: usize> {
+    pub proof: ProofWithPublicInputsTarget<D>,
+    /// The inner circuit's verifier data, as constants.
+    pub verifier_data: VerifierCircuitTarget,
+    /// The slot of the inner proof's public inputs among the outer circuit's public inputs.
+    pub public_inputs: PublicInputSlot,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Verifies a proof of the circuit with `common_data` and `verifier_data`, and registers its
+    /// public inputs as the next public inputs of this circuit.
+    pub fn embed_circuit<C: GenericConfig<D, F = F>>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+    ) -> EmbeddedCircuitTarget<D>
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let proof = self.add_virtual_proof_with_pis::<C>(common_data);
+        let verifier_data = self.constant_verifier_data(verifier_data);
+        self.verify_proof::<C>(&proof, &verifier_data, common_data);
+
+        let public_inputs = PublicInputSlot {
+            offset: self.num_public_inputs(),
+            len: proof.public_inputs.len(),
+        };
+        self.register_public_inputs_at(public_inputs, &proof.public_inputs);
+        EmbeddedCircuitTarget {
+            proof,
+            verifier_data,
+            public_inputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A circuit proving knowledge of a Poseidon preimage of its public inputs.
+    fn preimage_circuit() -> (CircuitData<F, C, D>, [Target; 4]) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let preimage = builder.add_virtual_target_arr::<4>();
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.to_vec());
+        builder.register_public_inputs(&hash.elements);
+        (builder.build::<C>(), preimage)
+    }
+
+    /// A circuit proving that its first public input is the square of its second, of a different
+    /// shape than `preimage_circuit`.
+    fn square_circuit() -> (CircuitData<F, C, D>, Target) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_ecc_config());
+        let x = builder.add_virtual_target();
+        let square = builder.square(x);
+        builder.register_public_input(square);
+        builder.register_public_input(x);
+        (builder.build::<C>(), x)
+    }
+
+    #[test]
+    fn test_embed_circuits() -> Result<()> {
+        let (preimage_data, preimage_t) = preimage_circuit();
+        let preimage = F::rand_array();
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(preimage_t, preimage);
+        let preimage_proof = preimage_data.prove(pw)?;
+
+        let (square_data, x_t) = square_circuit();
+        let x = F::rand();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x_t, x);
+        let square_proof = square_data.prove(pw)?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let preimage_embedded =
+            builder.embed_circuit::<C>(&preimage_data.common, &preimage_data.verifier_only);
+        let square_embedded =
+            builder.embed_circuit::<C>(&square_data.common, &square_data.verifier_only);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&preimage_embedded.proof, &preimage_proof);
+        pw.set_proof_with_pis_target(&square_embedded.proof, &square_proof);
+        let proof = data.prove(pw)?;
+
+        // The outer public inputs are the inner ones, in embedding order.
+        assert_eq!(
+            preimage_embedded.public_inputs.get(&proof.public_inputs)?,
+            PoseidonHash::hash_no_pad(&preimage).elements
+        );
+        assert_eq!(
+            square_embedded.public_inputs.get(&proof.public_inputs)?,
+            [x * x, x]
+        );
+        assert_eq!(
+            proof.public_inputs.len(),
+            square_embedded.public_inputs.end()
+        );
+        data.verify(proof)
+    }
+}
@@ -1,5 +1,7 @@
 use crate::field::extension::Extendable;
 use crate::hash::hash_types::{HashOutTarget, RichField};
+use crate::iop::challenger::RecursiveChallenger;
+use crate::iop::transcript::RecursiveTranscript;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
@@ -21,6 +23,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         inner_common_data: &CommonCircuitData<F, D>,
     ) where
         C::Hasher: AlgebraicHasher<F>,
+    {
+        let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(self);
+        self.verify_proof_with_transcript::<C, _>(
+            proof_with_pis,
+            inner_verifier_data,
+            inner_common_data,
+            &mut challenger,
+        );
+    }
+
+    /// Like `verify_proof`, for an inner proof generated with `prove_with_transcript`. The
+    /// transcript must be fresh and compute the same challenges as the inner prover's.
+    pub fn verify_proof_with_transcript<C: GenericConfig<D, F = F>, T: RecursiveTranscript<F, D>>(
+        &mut self,
+        proof_with_pis: &ProofWithPublicInputsTarget<D>,
+        inner_verifier_data: &VerifierCircuitTarget,
+        inner_common_data: &CommonCircuitData<F, D>,
+        transcript: &mut T,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
     {
         assert_eq!(
             proof_with_pis.public_inputs.len(),
@@ -28,8 +50,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
         let public_inputs_hash =
             self.public_inputs_hash::<C::InnerHasher>(proof_with_pis.public_inputs.clone());
-        let challenges = proof_with_pis.get_challenges::<F, C>(
+        let challenges = proof_with_pis.get_challenges(
             self,
+            transcript,
             public_inputs_hash,
             inner_verifier_data.circuit_digest,
             inner_common_data,
@@ -188,12 +211,15 @@ mod tests {
     use log::{info, Level};
 
     use super::*;
+    use crate::field::types::Field;
+    use crate::fri::oracle::SALT_SIZE;
     use crate::fri::reduction_strategies::FriReductionStrategy;
-    use crate::fri::FriConfig;
+    use crate::fri::{FriConfig, FriPowHash};
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
     use crate::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::plonk_common::PlonkOracle;
     use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
     use crate::plonk::prover::prove;
     use crate::util::timing::TimingTree;
@@ -214,6 +240,84 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_hiding_commitments() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig {
+            hiding_commitments: true,
+            ..CircuitConfig::standard_recursion_config()
+        };
+
+        let (proof, vd, cd) = dummy_proof::<F, C, D>(&config, 4_000)?;
+        assert!(cd.fri_params.hiding);
+        // Wire leaves are salted, but not those of the preprocessed polynomials.
+        let initial_trees_proof =
+            &proof.proof.opening_proof.query_round_proofs[0].initial_trees_proof;
+        assert_eq!(
+            initial_trees_proof.evals_proofs[PlonkOracle::WIRES.index]
+                .0
+                .len(),
+            config.num_wires + SALT_SIZE
+        );
+        assert_eq!(
+            initial_trees_proof.evals_proofs[PlonkOracle::CONSTANTS_SIGMAS.index]
+                .0
+                .len(),
+            cd.num_preprocessed_polys()
+        );
+        test_serialization(&proof, &vd, &cd)?;
+
+        recursive_proof::<F, C, C, D>(
+            proof,
+            vd,
+            cd,
+            &CircuitConfig::standard_recursion_config(),
+            None,
+            false,
+            false,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_high_degree_gate() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        // `BaseSumGate<11>` has degree 11, above both the config's `max_quotient_degree_factor`
+        // and `2^rate_bits`, so the quotient is split into 11 chunks.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        builder.split_le_base::<11>(x, 4);
+        let data = builder.build::<C>();
+        assert_eq!(data.common.quotient_degree_factor, 11);
+        // The constants and sigmas on the larger quotient domain are computed once, with the
+        // circuit.
+        assert!(data.prover_only.constants_sigmas_quotient_values.is_some());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(1234));
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+
+        recursive_proof::<F, C, C, D>(
+            proof,
+            data.verifier_only,
+            data.common,
+            &config,
+            None,
+            false,
+            false,
+        )?;
+        Ok(())
+    }
+
     #[test]
     fn test_recursive_recursive_verifier() -> Result<()> {
         init_logger();
@@ -284,6 +388,7 @@ mod tests {
                 rate_bits: 8,
                 cap_height: 0,
                 proof_of_work_bits: 20,
+                pow_hash: FriPowHash::Field,
                 reduction_strategy: FriReductionStrategy::MinSize(None),
                 num_query_rounds: 10,
             },
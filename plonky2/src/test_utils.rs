@@ -0,0 +1,227 @@
+//! Helpers for tests of circuits built with plonky2: configs which prove quickly, sample circuits
+//! and ready-made proofs of them, e.g. to test recursion or serialization.
+//!
+//! The configs are far from secure and must only be used in tests.
+
+use alloc::vec::Vec;
+
+use anyhow::Result;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::{FriConfig, FriPowHash};
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::hash::merkle_tree::MerkleTree;
+use crate::hash::poseidon::PoseidonHash;
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+use crate::plonk::config::GenericConfig;
+use crate::plonk::proof::ProofWithPublicInputs;
+
+/// The standard recursion config with a single FRI query and no proof-of-work, which only has
+/// 3 bits of security but proves in a fraction of the time. Its circuits can still be verified
+/// recursively.
+pub fn fast_test_config() -> CircuitConfig {
+    CircuitConfig {
+        security_bits: 3,
+        fri_config: FriConfig {
+            rate_bits: 3,
+            cap_height: 0,
+            proof_of_work_bits: 0,
+            pow_hash: FriPowHash::Field,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+            num_query_rounds: 1,
+        },
+        ..CircuitConfig::standard_recursion_config()
+    }
+}
+
+/// Like `fast_test_config`, with zero knowledge.
+pub fn fast_test_zk_config() -> CircuitConfig {
+    CircuitConfig {
+        zero_knowledge: true,
+        ..fast_test_config()
+    }
+}
+
+/// The targets of `fibonacci_circuit`.
+pub struct FibonacciTargets {
+    pub initial_a: Target,
+    pub initial_b: Target,
+    pub result: Target,
+}
+
+/// A circuit computing the `n`th element of the Fibonacci sequence starting with `a` and `b`, whose
+/// public inputs are `a`, `b` and the result.
+pub fn fibonacci_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    n: usize,
+) -> (CircuitData<F, C, D>, FibonacciTargets) {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let initial_a = builder.add_virtual_target();
+    let initial_b = builder.add_virtual_target();
+    let mut prev = initial_a;
+    let mut cur = initial_b;
+    for _ in 1..n {
+        let next = builder.add(prev, cur);
+        prev = cur;
+        cur = next;
+    }
+    builder.register_public_input(initial_a);
+    builder.register_public_input(initial_b);
+    builder.register_public_input(cur);
+    (
+        builder.build::<C>(),
+        FibonacciTargets {
+            initial_a,
+            initial_b,
+            result: cur,
+        },
+    )
+}
+
+/// A proof of `fibonacci_circuit` with `a = 0` and `b = 1`, with its circuit.
+pub fn fibonacci_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    config: &CircuitConfig,
+    n: usize,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+    let (data, targets) = fibonacci_circuit::<F, C, D>(config, n);
+    let mut pw = PartialWitness::new();
+    pw.set_target(targets.initial_a, F::ZERO);
+    pw.set_target(targets.initial_b, F::ONE);
+    let proof = data.prove(pw)?;
+    Ok((data, proof))
+}
+
+/// The targets of `merkle_membership_circuit`.
+pub struct MerkleMembershipTargets {
+    pub leaf: Vec<Target>,
+    pub index: Target,
+    pub root: HashOutTarget,
+    pub proof: MerkleProofTarget,
+}
+
+/// A circuit proving that a private leaf of `leaf_len` elements is in a Poseidon Merkle tree of
+/// height `height`, whose root is the public input.
+pub fn merkle_membership_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    height: usize,
+    leaf_len: usize,
+) -> (CircuitData<F, C, D>, MerkleMembershipTargets) {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let leaf = builder.add_virtual_targets(leaf_len);
+    let index = builder.add_virtual_target();
+    let index_bits = builder.split_le(index, height);
+    let root = builder.add_virtual_hash();
+    builder.register_public_inputs(&root.elements);
+    let proof = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(height),
+    };
+    builder.verify_merkle_proof::<PoseidonHash>(leaf.clone(), &index_bits, root, &proof);
+    (
+        builder.build::<C>(),
+        MerkleMembershipTargets {
+            leaf,
+            index,
+            root,
+            proof,
+        },
+    )
+}
+
+/// Assigns an opening of leaf `index` of a tree with root `root` to the targets of
+/// `merkle_membership_circuit`.
+pub fn set_merkle_membership_targets<F: RichField>(
+    pw: &mut PartialWitness<F>,
+    targets: &MerkleMembershipTargets,
+    leaf: &[F],
+    index: usize,
+    root: HashOut<F>,
+    proof: &MerkleProof<F, PoseidonHash>,
+) {
+    for (&target, &value) in targets.leaf.iter().zip(leaf) {
+        pw.set_target(target, value);
+    }
+    pw.set_target(targets.index, F::from_canonical_usize(index));
+    pw.set_hash_target(targets.root, root);
+    pw.set_merkle_proof_target(&targets.proof, proof);
+}
+
+/// A proof of `merkle_membership_circuit` for leaf `index` of a tree of height `height` whose leaf
+/// `i` is `[i, i, i, i]`, with its circuit.
+pub fn merkle_membership_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    height: usize,
+    index: usize,
+) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+    let leaves = (0..1 << height)
+        .map(|i| [F::from_canonical_usize(i); 4].to_vec())
+        .collect::<Vec<_>>();
+    let tree = MerkleTree::<F, PoseidonHash>::new(leaves, 0);
+    let (data, targets) = merkle_membership_circuit::<F, C, D>(config, height, 4);
+    let mut pw = PartialWitness::new();
+    set_merkle_membership_targets(
+        &mut pw,
+        &targets,
+        &tree.leaves[index],
+        index,
+        tree.cap.0[0],
+        &tree.prove(index),
+    );
+    let proof = data.prove(pw)?;
+    Ok((data, proof))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_fibonacci_proof() -> Result<()> {
+        let (data, proof) = fibonacci_proof::<F, C, D>(&fast_test_config(), 10)?;
+        assert_eq!(proof.public_inputs[2], F::from_canonical_u64(55));
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_extension_degrees() -> Result<()> {
+        let (data, proof) = fibonacci_proof::<F, C, 3>(&fast_test_config(), 10)?;
+        data.verify(proof)?;
+        let (data, proof) = fibonacci_proof::<F, C, 5>(&fast_test_config(), 10)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_merkle_membership_proof() -> Result<()> {
+        let (data, proof) = merkle_membership_proof::<F, C, D>(&fast_test_zk_config(), 4, 5)?;
+        let leaves = (0..16)
+            .map(|i| [F::from_canonical_usize(i); 4].to_vec())
+            .collect::<Vec<_>>();
+        let tree = MerkleTree::<F, PoseidonHash>::new(leaves, 0);
+        assert_eq!(proof.public_inputs, tree.cap.0[0].elements.to_vec());
+        data.verify(proof)
+    }
+}
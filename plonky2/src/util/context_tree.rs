@@ -50,6 +50,23 @@ impl ContextTree {
         }
     }
 
+    /// The stack of scopes which were open when the gate at `row` was added, described as by
+    /// `open_stack`.
+    pub fn scope_of(&self, row: usize) -> String {
+        let mut stack = vec![self.name.clone()];
+        let mut tree = self;
+        while let Some(child) = tree.children.iter().rev().find(|c| c.contains(row)) {
+            stack.push(child.name.clone());
+            tree = child;
+        }
+        stack.join(" > ")
+    }
+
+    /// Whether the gate at `row` was added while this scope was open.
+    fn contains(&self, row: usize) -> bool {
+        self.enter_gate_count <= row && self.exit_gate_count.map_or(true, |exit| row < exit)
+    }
+
     pub fn push(&mut self, ctx: &str, mut level: log::Level, current_gate_count: usize) {
         assert!(self.is_open());
 
@@ -0,0 +1,256 @@
+//! Accounting of the memory used by the prover.
+//!
+//! Binaries which want to know where memory goes install a `TrackingAllocator` as their global
+//! allocator, which counts the bytes in use in `MEMORY`:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: TrackingAllocator<std::alloc::System> = TrackingAllocator::new(std::alloc::System);
+//! ```
+//!
+//! Every scope of a `TimingTree` then records the peak number of bytes in use while it was open,
+//! so a prover run with a timing tree reports the peak of each of its phases, e.g. the LDEs of the
+//! wires commitment or the quotient polynomials. Each timing tree measures its peaks with a
+//! `PeakWatch` of its own, so trees of concurrent provers don't reset each other's, though the
+//! bytes in use are those of the whole process. Without a tracking allocator, nothing is counted
+//! and no peaks are reported. `TrackingAllocator` is unavailable with the `forbid-unsafe` feature,
+//! as implementing `GlobalAlloc` is unsafe.
+
+#[cfg(not(feature = "forbid-unsafe"))]
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// The number of `PeakWatch`es a tracker can have open at once.
+pub const MAX_PEAK_WATCHES: usize = 64;
+
+/// Counts the bytes currently in use, and the peak of each open `PeakWatch`.
+#[derive(Debug)]
+pub struct MemoryTracker {
+    current: AtomicUsize,
+    active: AtomicBool,
+    /// A bit per slot of `peaks`, set while a watch holds it.
+    watched: AtomicU64,
+    peaks: [AtomicUsize; MAX_PEAK_WATCHES],
+}
+
+impl Default for MemoryTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The tracker updated by `TrackingAllocator`.
+pub static MEMORY: MemoryTracker = MemoryTracker::new();
+
+impl MemoryTracker {
+    pub const fn new() -> Self {
+        // A named constant, as array repeat expressions can't call `AtomicUsize::new` directly.
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            current: AtomicUsize::new(0),
+            active: AtomicBool::new(false),
+            watched: AtomicU64::new(0),
+            peaks: [ZERO; MAX_PEAK_WATCHES],
+        }
+    }
+
+    pub fn record_alloc(&self, bytes: usize) {
+        let current = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let mut watched = self.watched.load(Ordering::Relaxed);
+        while watched != 0 {
+            let slot = watched.trailing_zeros() as usize;
+            self.peaks[slot].fetch_max(current, Ordering::Relaxed);
+            watched &= watched - 1;
+        }
+        if !self.active.load(Ordering::Relaxed) {
+            self.active.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_dealloc(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    /// Whether any allocation was ever recorded, i.e. whether the figures below mean anything.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes currently in use.
+    pub fn current_bytes(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// Starts measuring the peak number of bytes in use, from those currently in use. Each watch
+    /// has its own peak, so that resetting it, e.g. in a timing tree of one thread, doesn't reset
+    /// the peaks measured by others. Returns `None` if `MAX_PEAK_WATCHES` are already open.
+    pub fn watch_peak(&'static self) -> Option<PeakWatch> {
+        let watched = self
+            .watched
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |watched| {
+                (watched != u64::MAX).then(|| watched | (watched + 1))
+            })
+            .ok()?;
+        let slot = (!watched).trailing_zeros() as usize;
+        self.peaks[slot].store(self.current_bytes(), Ordering::Relaxed);
+        Some(PeakWatch {
+            tracker: self,
+            slot,
+        })
+    }
+}
+
+/// A peak measurement of a `MemoryTracker`, which stops when dropped.
+#[derive(Debug)]
+pub struct PeakWatch {
+    tracker: &'static MemoryTracker,
+    slot: usize,
+}
+
+impl PeakWatch {
+    /// The largest number of bytes in use since the watch started or was last reset.
+    pub fn peak_bytes(&self) -> usize {
+        self.tracker.peaks[self.slot].load(Ordering::Relaxed)
+    }
+
+    /// Starts a new measurement from the bytes currently in use, and returns the peak of the
+    /// previous one.
+    pub fn reset(&self) -> usize {
+        self.tracker.peaks[self.slot].swap(self.tracker.current_bytes(), Ordering::Relaxed)
+    }
+}
+
+impl Drop for PeakWatch {
+    fn drop(&mut self) {
+        self.tracker
+            .watched
+            .fetch_and(!(1 << self.slot), Ordering::Relaxed);
+    }
+}
+
+/// A global allocator which forwards to `inner`, and counts the bytes in use in `MEMORY`.
+#[cfg(not(feature = "forbid-unsafe"))]
+#[derive(Debug)]
+pub struct TrackingAllocator<A> {
+    inner: A,
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(not(feature = "forbid-unsafe"))]
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            MEMORY.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            MEMORY.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        MEMORY.record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            // Count the new block before freeing the old one, as a moving reallocation holds both.
+            MEMORY.record_alloc(new_size);
+            MEMORY.record_dealloc(layout.size());
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::memory::{MemoryTracker, MAX_PEAK_WATCHES};
+    #[cfg(feature = "timing")]
+    use crate::util::timing::TimingTree;
+
+    #[test]
+    fn test_memory_tracker() {
+        static TRACKER: MemoryTracker = MemoryTracker::new();
+        assert!(!TRACKER.is_active());
+
+        let watch = TRACKER.watch_peak().unwrap();
+        TRACKER.record_alloc(100);
+        TRACKER.record_alloc(50);
+        TRACKER.record_dealloc(100);
+        assert!(TRACKER.is_active());
+        assert_eq!(TRACKER.current_bytes(), 50);
+        assert_eq!(watch.peak_bytes(), 150);
+
+        // Resetting a watch leaves the others alone.
+        let other = TRACKER.watch_peak().unwrap();
+        assert_eq!(watch.reset(), 150);
+        assert_eq!(watch.peak_bytes(), 50);
+        TRACKER.record_alloc(20);
+        TRACKER.record_dealloc(70);
+        assert_eq!(watch.reset(), 70);
+        assert_eq!(watch.peak_bytes(), 0);
+        assert_eq!(other.peak_bytes(), 70);
+
+        // The slots of dropped watches are reused.
+        drop(other);
+        let watches = (1..MAX_PEAK_WATCHES)
+            .map(|_| TRACKER.watch_peak().unwrap())
+            .collect::<Vec<_>>();
+        assert!(TRACKER.watch_peak().is_none());
+        drop(watches);
+        assert!(TRACKER.watch_peak().is_some());
+    }
+
+    #[cfg(feature = "timing")]
+    #[test]
+    fn test_peak_bytes_by_phase() {
+        // A tracker of its own, so that allocations of other threads aren't counted.
+        static TRACKER: MemoryTracker = MemoryTracker::new();
+        let mut timing = TimingTree::with_tracker("root", log::Level::Debug, &TRACKER);
+        timing.push("commit", log::Level::Debug);
+        TRACKER.record_alloc(1000);
+        timing.push("lde", log::Level::Debug);
+        TRACKER.record_alloc(3000);
+        TRACKER.record_dealloc(3000);
+        timing.pop();
+        TRACKER.record_dealloc(1000);
+        timing.pop();
+        timing.push("open", log::Level::Debug);
+        TRACKER.record_alloc(500);
+        TRACKER.record_dealloc(500);
+        timing.pop();
+
+        let phases = timing.peak_bytes_by_phase();
+        let names = phases
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "root",
+                "root > commit",
+                "root > commit > lde",
+                "root > open"
+            ]
+        );
+        let peaks = phases.iter().map(|&(_, peak)| peak).collect::<Vec<_>>();
+        assert_eq!(peaks, [4000, 4000, 4000, 500]);
+        assert_eq!(TRACKER.current_bytes(), 0);
+    }
+}
@@ -10,10 +10,13 @@ use crate::field::types::Field;
 pub(crate) mod context_tree;
 pub(crate) mod partial_products;
 
+pub mod memory;
 pub mod reducing;
 pub mod serialization;
 pub mod strided_view;
 pub mod timing;
+#[cfg(feature = "zeroize")]
+pub mod zeroize;
 
 pub(crate) fn transpose_poly_values<F: Field>(polys: Vec<PolynomialValues<F>>) -> Vec<Vec<F>> {
     let poly_values = polys.into_iter().map(|p| p.values).collect::<Vec<_>>();
@@ -24,15 +27,21 @@ pub fn transpose<F: Field>(matrix: &[Vec<F>]) -> Vec<Vec<F>> {
     let l = matrix.len();
     let w = matrix[0].len();
 
-    let mut transposed = vec![vec![]; w];
-    for i in 0..w {
-        transposed[i].reserve_exact(l);
-        unsafe {
-            // After .reserve_exact(l), transposed[i] will have capacity at least l. Hence, set_len
-            // will not cause the buffer to overrun.
-            transposed[i].set_len(l);
+    #[cfg(feature = "forbid-unsafe")]
+    let mut transposed = vec![vec![F::ZERO; l]; w];
+    #[cfg(not(feature = "forbid-unsafe"))]
+    let mut transposed = {
+        let mut transposed = vec![vec![]; w];
+        for i in 0..w {
+            transposed[i].reserve_exact(l);
+            unsafe {
+                // After .reserve_exact(l), transposed[i] will have capacity at least l. Hence,
+                // set_len will not cause the buffer to overrun.
+                transposed[i].set_len(l);
+            }
         }
-    }
+        transposed
+    };
 
     // Optimization: ensure the larger loop is outside.
     if w >= l {
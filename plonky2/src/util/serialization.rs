@@ -1,3 +1,4 @@
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::convert::Infallible;
@@ -13,15 +14,21 @@ use crate::fri::proof::{
     CompressedFriProof, CompressedFriQueryRounds, FriInitialTreeProof, FriProof, FriQueryRound,
     FriQueryStep,
 };
+use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::{FriConfig, FriParams, FriPowHash};
+use crate::gates::from_id::gate_from_id;
+use crate::gates::gate::GateRef;
+use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
-use crate::hash::merkle_tree::MerkleCap;
-use crate::plonk::circuit_data::CommonCircuitData;
+use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::plonk_common::salt_size;
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, OpeningSet, Proof, ProofWithPublicInputs,
 };
+use crate::util::log2_strict;
 
 /// A no_std compatible variant of `std::io::Error`
 #[derive(Debug)]
@@ -76,7 +83,7 @@ pub trait Read {
     {
         let mut buf = [0; size_of::<u64>()];
         self.read_exact(&mut buf)?;
-        Ok(F::from_canonical_u64(u64::from_le_bytes(buf)))
+        F::from_canonical_bytes(buf).ok_or(IoError)
     }
 
     /// Reads a vector of elements from the field `F` from `self`.
@@ -90,6 +97,21 @@ pub trait Read {
             .collect::<Result<Vec<_>, _>>()
     }
 
+    /// Reads all remaining bytes of `self` as a vector of elements from the field `F`, failing if
+    /// they are not a whole number of elements.
+    #[inline]
+    fn read_remaining_field_vec<F>(&mut self) -> IoResult<Vec<F>>
+    where
+        Self: Remaining,
+        F: Field64,
+    {
+        let length = self.remaining() / size_of::<u64>();
+        if length * size_of::<u64>() != self.remaining() {
+            return Err(IoError);
+        }
+        self.read_field_vec(length)
+    }
+
     /// Reads an element from the field extension of `F` from `self.`
     #[inline]
     fn read_field_ext<F, const D: usize>(&mut self) -> IoResult<F::Extension>
@@ -126,7 +148,12 @@ pub trait Read {
     {
         let mut buf = vec![0; H::HASH_SIZE];
         self.read_exact(&mut buf)?;
-        Ok(H::Hash::from_bytes(&buf))
+        let hash = H::Hash::from_bytes(&buf);
+        // Non-canonical field elements of a hash encode differently, which this rejects.
+        if hash.to_bytes() != buf {
+            return Err(IoError);
+        }
+        Ok(hash)
     }
 
     /// Reads a value of type [`MerkleCap`] from `self` with the given `cap_height`.
@@ -144,6 +171,54 @@ pub trait Read {
         ))
     }
 
+    /// Reads a [`MerkleCap`] preceded by its height, as written by `write_merkle_cap_with_height`.
+    #[inline]
+    fn read_merkle_cap_with_height<F, H>(&mut self) -> IoResult<MerkleCap<F, H>>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        let cap_height = self.read_usize()?;
+        if cap_height >= 32 {
+            return Err(IoError);
+        }
+        self.read_merkle_cap(cap_height)
+    }
+
+    /// Reads a [`MerkleTree`] written by `write_merkle_tree`. Its leaves are empty if they weren't
+    /// written.
+    #[inline]
+    fn read_merkle_tree<F, H>(&mut self) -> IoResult<MerkleTree<F, H>>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        let log2_num_leaves = self.read_usize()?;
+        let cap_height = self.read_usize()?;
+        if log2_num_leaves >= 32 || cap_height > log2_num_leaves {
+            return Err(IoError);
+        }
+        let num_leaves = 1 << log2_num_leaves;
+        let leaves = if self.read_bool()? {
+            let leaf_len = self.read_usize()?;
+            (0..num_leaves)
+                .map(|_| self.read_field_vec(leaf_len))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        let num_digests = 2 * (num_leaves - (1 << cap_height));
+        let digests = (0..num_digests)
+            .map(|_| self.read_hash::<F, H>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let cap = self.read_merkle_cap(cap_height)?;
+        Ok(MerkleTree {
+            leaves,
+            digests,
+            cap,
+        })
+    }
+
     /// Reads a value of type [`OpeningSet`] from `self` with the given `common_data`.
     #[inline]
     fn read_opening_set<F, C, const D: usize>(
@@ -340,13 +415,37 @@ pub trait Read {
         C: GenericConfig<D, F = F>,
     {
         let proof = self.read_proof(common_data)?;
-        let public_inputs = self.read_field_vec(self.remaining() / size_of::<u64>())?;
+        let public_inputs = self.read_remaining_field_vec()?;
         Ok(ProofWithPublicInputs {
             proof,
             public_inputs,
         })
     }
 
+    /// Reads a value of type [`VerifierOnlyCircuitData`] from `self` with `common_data`, failing if
+    /// any bytes remain.
+    #[inline]
+    fn read_verifier_only_circuit_data<F, C, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<VerifierOnlyCircuitData<C, D>>
+    where
+        Self: Remaining,
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let constants_sigmas_cap =
+            self.read_merkle_cap(common_data.config.fri_config.cap_height)?;
+        let circuit_digest = self.read_hash::<F, C::Hasher>()?;
+        if !self.is_empty() {
+            return Err(IoError);
+        }
+        Ok(VerifierOnlyCircuitData {
+            constants_sigmas_cap,
+            circuit_digest,
+        })
+    }
+
     /// Reads a value of type [`CompressedFriQueryRounds`] from `self` with `common_data`.
     #[inline]
     fn read_compressed_fri_query_rounds<F, C, const D: usize>(
@@ -459,12 +558,146 @@ pub trait Read {
         C: GenericConfig<D, F = F>,
     {
         let proof = self.read_compressed_proof(common_data)?;
-        let public_inputs = self.read_field_vec(self.remaining() / size_of::<u64>())?;
+        let public_inputs = self.read_remaining_field_vec()?;
         Ok(CompressedProofWithPublicInputs {
             proof,
             public_inputs,
         })
     }
+
+    /// Reads a `usize` value, encoded as a `u32`, from `self`.
+    #[inline]
+    fn read_usize(&mut self) -> IoResult<usize> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    /// Reads a vector of `usize` values, preceded by its length, from `self`.
+    #[inline]
+    fn read_usize_vec(&mut self) -> IoResult<Vec<usize>> {
+        let length = self.read_usize()?;
+        (0..length).map(|_| self.read_usize()).collect()
+    }
+
+    /// Reads a `bool` value from `self`, failing on bytes other than 0 and 1.
+    #[inline]
+    fn read_bool(&mut self) -> IoResult<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(IoError),
+        }
+    }
+
+    /// Reads a gate, encoded as its ID, from `self`. Only the gates known to [`gate_from_id`]
+    /// can be read.
+    #[inline]
+    fn read_gate<F, const D: usize>(&mut self) -> IoResult<GateRef<F, D>>
+    where
+        F: RichField + Extendable<D>,
+    {
+        let length = self.read_usize()?;
+        let mut bytes = vec![0; length];
+        self.read_exact(&mut bytes)?;
+        let id = String::from_utf8(bytes).map_err(|_| IoError)?;
+        gate_from_id(&id).ok_or(IoError)
+    }
+
+    /// Reads a value of type [`FriConfig`] from `self`.
+    #[inline]
+    fn read_fri_config(&mut self) -> IoResult<FriConfig> {
+        let rate_bits = self.read_usize()?;
+        let cap_height = self.read_usize()?;
+        let proof_of_work_bits = self.read_u32()?;
+        let pow_hash = match self.read_u8()? {
+            0 => FriPowHash::Field,
+            1 => FriPowHash::Keccak,
+            _ => return Err(IoError),
+        };
+        let reduction_strategy = match self.read_u8()? {
+            0 => FriReductionStrategy::Fixed(self.read_usize_vec()?),
+            1 => FriReductionStrategy::ConstantArityBits(self.read_usize()?, self.read_usize()?),
+            2 => FriReductionStrategy::MinSize(match self.read_bool()? {
+                true => Some(self.read_usize()?),
+                false => None,
+            }),
+            _ => return Err(IoError),
+        };
+        let num_query_rounds = self.read_usize()?;
+        Ok(FriConfig {
+            rate_bits,
+            cap_height,
+            proof_of_work_bits,
+            pow_hash,
+            reduction_strategy,
+            num_query_rounds,
+        })
+    }
+
+    /// Reads a value of type [`CircuitConfig`] from `self`.
+    #[inline]
+    fn read_circuit_config(&mut self) -> IoResult<CircuitConfig> {
+        Ok(CircuitConfig {
+            num_wires: self.read_usize()?,
+            num_routed_wires: self.read_usize()?,
+            num_constants: self.read_usize()?,
+            use_base_arithmetic_gate: self.read_bool()?,
+            security_bits: self.read_usize()?,
+            num_challenges: self.read_usize()?,
+            zero_knowledge: self.read_bool()?,
+            hiding_commitments: self.read_bool()?,
+            max_quotient_degree_factor: self.read_usize()?,
+            fri_config: self.read_fri_config()?,
+        })
+    }
+
+    /// Reads a value of type [`CommonCircuitData`] from `self`.
+    #[inline]
+    fn read_common_circuit_data<F, const D: usize>(&mut self) -> IoResult<CommonCircuitData<F, D>>
+    where
+        F: RichField + Extendable<D>,
+    {
+        let config = self.read_circuit_config()?;
+        let fri_params = FriParams {
+            config: config.fri_config.clone(),
+            hiding: self.read_bool()?,
+            degree_bits: self.read_usize()?,
+            reduction_arity_bits: self.read_usize_vec()?,
+        };
+        let num_gates = self.read_usize()?;
+        let gates = (0..num_gates)
+            .map(|_| self.read_gate())
+            .collect::<Result<Vec<_>, _>>()?;
+        let selector_indices = self.read_usize_vec()?;
+        let num_groups = self.read_usize()?;
+        let groups = (0..num_groups)
+            .map(|_| Ok(self.read_usize()?..self.read_usize()?))
+            .collect::<Result<Vec<_>, _>>()?;
+        if selector_indices.len() != gates.len() {
+            return Err(IoError);
+        }
+        let quotient_degree_factor = self.read_usize()?;
+        let num_gate_constraints = self.read_usize()?;
+        let num_constants = self.read_usize()?;
+        let num_public_inputs = self.read_usize()?;
+        let num_k_is = self.read_usize()?;
+        let k_is = self.read_field_vec(num_k_is)?;
+        let num_partial_products = self.read_usize()?;
+        Ok(CommonCircuitData {
+            config,
+            fri_params,
+            gates,
+            selectors_info: SelectorsInfo {
+                selector_indices,
+                groups,
+            },
+            quotient_degree_factor,
+            num_gate_constraints,
+            num_constants,
+            num_public_inputs,
+            k_is,
+            num_partial_products,
+        })
+    }
 }
 
 /// Writing
@@ -493,7 +726,7 @@ pub trait Write {
     where
         F: PrimeField64,
     {
-        self.write_all(&x.to_canonical_u64().to_le_bytes())
+        self.write_all(&x.canonical_bytes())
     }
 
     /// Writes a vector `v` of elements from the field `F` to `self`.
@@ -555,6 +788,55 @@ pub trait Write {
         Ok(())
     }
 
+    /// Writes `cap` preceded by its height, for when the reader doesn't know it.
+    #[inline]
+    fn write_merkle_cap_with_height<F, H>(&mut self, cap: &MerkleCap<F, H>) -> IoResult<()>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        self.write_usize(cap.height())?;
+        self.write_merkle_cap(cap)
+    }
+
+    /// Writes `tree`: the log2 of its number of leaves and its cap height, then, if
+    /// `include_leaves`, their common length and the leaves, then its digests and its cap. Digests
+    /// are fixed-width, so the encoding of a tree is unique. Fails if the leaves are included but
+    /// don't all have the same length.
+    #[inline]
+    fn write_merkle_tree<F, H>(
+        &mut self,
+        tree: &MerkleTree<F, H>,
+        include_leaves: bool,
+    ) -> IoResult<()>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        let cap_height = tree.cap.height();
+        let num_leaves = tree.num_leaves();
+        self.write_usize(log2_strict(num_leaves))?;
+        self.write_usize(cap_height)?;
+        self.write_bool(include_leaves)?;
+        if include_leaves {
+            if tree.leaves.len() != num_leaves {
+                return Err(IoError);
+            }
+            let leaf_len = tree.leaves[0].len();
+            if tree.leaves.iter().any(|leaf| leaf.len() != leaf_len) {
+                return Err(IoError);
+            }
+            self.write_usize(leaf_len)?;
+            for leaf in &tree.leaves {
+                self.write_field_vec(leaf)?;
+            }
+        }
+        for &digest in &tree.digests {
+            self.write_hash::<F, H>(digest)?;
+        }
+        self.write_merkle_cap(&tree.cap)
+    }
+
     /// Writes a value `os` of type [`OpeningSet`] to `self.`
     #[inline]
     fn write_opening_set<F, const D: usize>(&mut self, os: &OpeningSet<F, D>) -> IoResult<()>
@@ -769,6 +1051,152 @@ pub trait Write {
         self.write_compressed_proof(proof)?;
         self.write_field_vec(public_inputs)
     }
+
+    /// Writes a value `verifier_data` of type [`VerifierOnlyCircuitData`] to `self`.
+    #[inline]
+    fn write_verifier_only_circuit_data<C, const D: usize>(
+        &mut self,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+    ) -> IoResult<()>
+    where
+        C: GenericConfig<D>,
+        C::F: RichField + Extendable<D>,
+    {
+        self.write_merkle_cap(&verifier_data.constants_sigmas_cap)?;
+        self.write_hash::<C::F, C::Hasher>(verifier_data.circuit_digest)
+    }
+
+    /// Writes a `usize` value `x`, encoded as a `u32`, to `self`.
+    #[inline]
+    fn write_usize(&mut self, x: usize) -> IoResult<()> {
+        self.write_u32(x.try_into().expect("Value must fit in u32."))
+    }
+
+    /// Writes a vector `v` of `usize` values, preceded by its length, to `self`.
+    #[inline]
+    fn write_usize_vec(&mut self, v: &[usize]) -> IoResult<()> {
+        self.write_usize(v.len())?;
+        for &x in v {
+            self.write_usize(x)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a `bool` value `x` to `self`.
+    #[inline]
+    fn write_bool(&mut self, x: bool) -> IoResult<()> {
+        self.write_u8(x as u8)
+    }
+
+    /// Writes `gate`, encoded as its ID, to `self`.
+    #[inline]
+    fn write_gate<F, const D: usize>(&mut self, gate: &GateRef<F, D>) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+    {
+        let id = gate.0.id();
+        self.write_usize(id.len())?;
+        self.write_all(id.as_bytes())
+    }
+
+    /// Writes a value `config` of type [`FriConfig`] to `self`.
+    #[inline]
+    fn write_fri_config(&mut self, config: &FriConfig) -> IoResult<()> {
+        self.write_usize(config.rate_bits)?;
+        self.write_usize(config.cap_height)?;
+        self.write_u32(config.proof_of_work_bits)?;
+        self.write_u8(match config.pow_hash {
+            FriPowHash::Field => 0,
+            FriPowHash::Keccak => 1,
+        })?;
+        match &config.reduction_strategy {
+            FriReductionStrategy::Fixed(arity_bits) => {
+                self.write_u8(0)?;
+                self.write_usize_vec(arity_bits)?;
+            }
+            &FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits) => {
+                self.write_u8(1)?;
+                self.write_usize(arity_bits)?;
+                self.write_usize(final_poly_bits)?;
+            }
+            &FriReductionStrategy::MinSize(max_arity_bits) => {
+                self.write_u8(2)?;
+                self.write_bool(max_arity_bits.is_some())?;
+                if let Some(max_arity_bits) = max_arity_bits {
+                    self.write_usize(max_arity_bits)?;
+                }
+            }
+        }
+        self.write_usize(config.num_query_rounds)
+    }
+
+    /// Writes a value `config` of type [`CircuitConfig`] to `self`.
+    #[inline]
+    fn write_circuit_config(&mut self, config: &CircuitConfig) -> IoResult<()> {
+        self.write_usize(config.num_wires)?;
+        self.write_usize(config.num_routed_wires)?;
+        self.write_usize(config.num_constants)?;
+        self.write_bool(config.use_base_arithmetic_gate)?;
+        self.write_usize(config.security_bits)?;
+        self.write_usize(config.num_challenges)?;
+        self.write_bool(config.zero_knowledge)?;
+        self.write_bool(config.hiding_commitments)?;
+        self.write_usize(config.max_quotient_degree_factor)?;
+        self.write_fri_config(&config.fri_config)
+    }
+
+    /// Writes a value `common_data` of type [`CommonCircuitData`] to `self`. The FRI config is
+    /// only written once, as part of the circuit config.
+    #[inline]
+    fn write_common_circuit_data<F, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.write_circuit_config(&common_data.config)?;
+        self.write_bool(common_data.fri_params.hiding)?;
+        self.write_usize(common_data.fri_params.degree_bits)?;
+        self.write_usize_vec(&common_data.fri_params.reduction_arity_bits)?;
+        self.write_usize(common_data.gates.len())?;
+        for gate in &common_data.gates {
+            self.write_gate(gate)?;
+        }
+        self.write_usize_vec(&common_data.selectors_info.selector_indices)?;
+        self.write_usize(common_data.selectors_info.groups.len())?;
+        for group in &common_data.selectors_info.groups {
+            self.write_usize(group.start)?;
+            self.write_usize(group.end)?;
+        }
+        self.write_usize(common_data.quotient_degree_factor)?;
+        self.write_usize(common_data.num_gate_constraints)?;
+        self.write_usize(common_data.num_constants)?;
+        self.write_usize(common_data.num_public_inputs)?;
+        self.write_usize(common_data.k_is.len())?;
+        self.write_field_vec(&common_data.k_is)?;
+        self.write_usize(common_data.num_partial_products)
+    }
+}
+
+impl Remaining for &[u8] {
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Reading from a byte slice consumes it, like `std::io::Read` does.
+impl Read for &[u8] {
+    #[inline]
+    fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
+        if self.len() < bytes.len() {
+            return Err(IoError);
+        }
+        let (head, tail) = self.split_at(bytes.len());
+        bytes.copy_from_slice(head);
+        *self = tail;
+        Ok(())
+    }
 }
 
 impl Write for Vec<u8> {
@@ -823,3 +1251,157 @@ impl Read for Buffer {
         }
     }
 }
+
+/// Adapts a `std::io::Read`, e.g. a network stream, so that values can be decoded as their bytes
+/// arrive, without first buffering the whole encoding.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoReader<R: std::io::Read> {
+    reader: R,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> IoReader<R> {
+    /// Builds a new [`IoReader`] over `reader`.
+    #[inline]
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Returns the inner reader.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns whether `reader` is exhausted, consuming a byte if it isn't.
+    pub fn is_at_end(&mut self) -> IoResult<bool> {
+        let mut byte = [0u8];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(n) => return Ok(n == 0),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(_) => return Err(IoError),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> Read for IoReader<R> {
+    #[inline]
+    fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
+        self.reader.read_exact(bytes).map_err(|_| IoError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::field::types::{Field, Field64};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Deserializing corrupted or truncated bytes must fail gracefully rather than panic, since
+    /// proofs come from untrusted clients.
+    #[test]
+    fn test_deserialize_malformed() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.cube(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let compressed = proof
+            .clone()
+            .compress(&data.verifier_only.circuit_digest, &data.common)?;
+
+        let proof_bytes = proof.to_bytes();
+        let compressed_bytes = compressed.to_bytes();
+        let verifier_bytes = data.verifier_only.to_bytes();
+        assert_eq!(
+            ProofWithPublicInputs::<F, C, D>::from_bytes(&proof_bytes, &data.common)?,
+            proof
+        );
+        assert_eq!(
+            CompressedProofWithPublicInputs::<F, C, D>::from_bytes(
+                &compressed_bytes,
+                &data.common
+            )?,
+            compressed
+        );
+        assert_eq!(
+            VerifierOnlyCircuitData::<C, D>::from_bytes(&verifier_bytes, &data.common)?,
+            data.verifier_only
+        );
+
+        // Trailing bytes which don't make up a field element are rejected.
+        let mut extended = proof_bytes.clone();
+        extended.push(0);
+        assert!(ProofWithPublicInputs::<F, C, D>::from_bytes(&extended, &data.common).is_err());
+        let mut extended = verifier_bytes.clone();
+        extended.extend_from_slice(&[0; 8]);
+        assert!(VerifierOnlyCircuitData::<C, D>::from_bytes(&extended, &data.common).is_err());
+
+        // Non-canonical field elements are rejected.
+        let mut non_canonical = proof_bytes.clone();
+        let len = non_canonical.len();
+        non_canonical[len - 8..].copy_from_slice(&F::ORDER.to_le_bytes());
+        assert!(
+            ProofWithPublicInputs::<F, C, D>::from_bytes(&non_canonical, &data.common).is_err()
+        );
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..64 {
+            let truncated_len = rng.gen_range(0..verifier_bytes.len());
+            assert!(VerifierOnlyCircuitData::<C, D>::from_bytes(
+                &verifier_bytes[..truncated_len],
+                &data.common
+            )
+            .is_err());
+
+            let mut corrupted = proof_bytes.clone();
+            let i = rng.gen_range(0..corrupted.len());
+            corrupted[i] = rng.gen();
+            let _ = ProofWithPublicInputs::<F, C, D>::from_bytes(&corrupted, &data.common);
+
+            let mut corrupted = compressed_bytes.clone();
+            let i = rng.gen_range(0..corrupted.len());
+            corrupted[i] = rng.gen();
+            let _ =
+                CompressedProofWithPublicInputs::<F, C, D>::from_bytes(&corrupted, &data.common);
+        }
+
+        // Truncating the proof itself, rather than its public inputs, always fails.
+        let proof_len = proof_bytes.len() - proof.public_inputs.len() * 8;
+        for len in (0..proof_len).step_by(101) {
+            assert!(ProofWithPublicInputs::<F, C, D>::from_bytes(
+                &proof_bytes[..len],
+                &data.common
+            )
+            .is_err());
+        }
+        let compressed_len = compressed_bytes.len() - compressed.public_inputs.len() * 8;
+        for len in (0..compressed_len).step_by(101) {
+            assert!(CompressedProofWithPublicInputs::<F, C, D>::from_bytes(
+                &compressed_bytes[..len],
+                &data.common
+            )
+            .is_err());
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use core::marker::PhantomData;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::mem::size_of;
 use core::ops::{Index, Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
 
@@ -31,7 +32,12 @@ pub struct PackedStridedView<'a, P: PackedField> {
     // implement this. The alternative would be to replace `start_ptr` and `length` with one slice
     // (`&[P::Scalar]`). Unfortunately, with a slice, an empty view becomes an edge case that
     // necessitates separate handling. It _could_ be done but it would also be uglier.
+    #[cfg(not(feature = "forbid-unsafe"))]
     start_ptr: *const P::Scalar,
+    /// With the `forbid-unsafe` feature, the underlying data from the first accessible element
+    /// onwards, or an empty slice if there is no such element.
+    #[cfg(feature = "forbid-unsafe")]
+    data: &'a [P::Scalar],
     /// This is the total length of elements accessible through the view. In other words, valid
     /// indices are in `0..length`.
     length: usize,
@@ -71,12 +77,13 @@ impl<'a, P: PackedField> PackedStridedView<'a, P> {
             stride
         );
 
-        // See comment above. `start_ptr` will be more than one byte past the buffer if `data` has
-        // length 0 and `offset` is not 0.
-        let start_ptr = data.as_ptr().wrapping_add(offset);
-
         Self {
-            start_ptr,
+            // See comment above. `start_ptr` will be more than one byte past the buffer if `data`
+            // has length 0 and `offset` is not 0.
+            #[cfg(not(feature = "forbid-unsafe"))]
+            start_ptr: data.as_ptr().wrapping_add(offset),
+            #[cfg(feature = "forbid-unsafe")]
+            data: data.get(offset..).unwrap_or(&[]),
             length: data.len() / stride,
             stride,
             _phantom: PhantomData,
@@ -84,6 +91,7 @@ impl<'a, P: PackedField> PackedStridedView<'a, P> {
     }
 
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     pub fn get(&self, index: usize) -> Option<&'a P> {
         if index < self.length {
             // Cast scalar pointer to vector pointer.
@@ -95,6 +103,33 @@ impl<'a, P: PackedField> PackedStridedView<'a, P> {
         }
     }
 
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    pub fn get(&self, index: usize) -> Option<&'a P> {
+        if index < self.length {
+            let start = index * self.stride;
+            Some(P::from_slice(&self.data[start..start + P::WIDTH]))
+        } else {
+            None
+        }
+    }
+
+    /// The view of `length` elements starting at `start`, which must be in bounds.
+    #[inline]
+    fn sub_view(&self, start: usize, length: usize) -> Self {
+        Self {
+            // See comment at the top of the `impl`. `self.start_ptr` will point more than one byte
+            // past the end of the buffer if the offset is not 0 and the buffer has length 0.
+            #[cfg(not(feature = "forbid-unsafe"))]
+            start_ptr: self.start_ptr.wrapping_add(self.stride * start),
+            #[cfg(feature = "forbid-unsafe")]
+            data: self.data.get(self.stride * start..).unwrap_or(&[]),
+            length,
+            stride: self.stride,
+            _phantom: PhantomData,
+        }
+    }
+
     /// Take a range of `PackedStridedView` indices, as `PackedStridedView`.
     #[inline]
     pub fn view<I>(&self, index: I) -> Self
@@ -109,6 +144,7 @@ impl<'a, P: PackedField> PackedStridedView<'a, P> {
     }
 
     #[inline]
+    #[cfg(not(feature = "forbid-unsafe"))]
     pub fn iter(&self) -> PackedStridedViewIter<'a, P> {
         PackedStridedViewIter::new(
             self.start_ptr,
@@ -119,6 +155,12 @@ impl<'a, P: PackedField> PackedStridedView<'a, P> {
         )
     }
 
+    #[inline]
+    #[cfg(feature = "forbid-unsafe")]
+    pub fn iter(&self) -> PackedStridedViewIter<'a, P> {
+        PackedStridedViewIter { view: *self }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.length
@@ -166,6 +208,7 @@ impl<P: PackedField, const N: usize> TryInto<[P; N]> for PackedStridedView<'_, P
 }
 
 // Not deriving `Copy`. An implicit copy of an iterator is likely a bug.
+#[cfg(not(feature = "forbid-unsafe"))]
 #[derive(Clone, Debug)]
 pub struct PackedStridedViewIter<'a, P: PackedField> {
     // Again, a pair of pointers is a neater solution than a slice. `start` and `end` are always
@@ -182,6 +225,7 @@ pub struct PackedStridedViewIter<'a, P: PackedField> {
     _phantom: PhantomData<&'a [P::Scalar]>,
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 impl<'a, P: PackedField> PackedStridedViewIter<'a, P> {
     pub(self) fn new(start: *const P::Scalar, end: *const P::Scalar, stride: usize) -> Self {
         Self {
@@ -193,6 +237,7 @@ impl<'a, P: PackedField> PackedStridedViewIter<'a, P> {
     }
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 impl<'a, P: PackedField> Iterator for PackedStridedViewIter<'a, P> {
     type Item = &'a P;
     fn next(&mut self) -> Option<Self::Item> {
@@ -215,6 +260,7 @@ impl<'a, P: PackedField> Iterator for PackedStridedViewIter<'a, P> {
     }
 }
 
+#[cfg(not(feature = "forbid-unsafe"))]
 impl<'a, P: PackedField> DoubleEndedIterator for PackedStridedViewIter<'a, P> {
     fn next_back(&mut self) -> Option<Self::Item> {
         debug_assert_eq!(
@@ -235,6 +281,34 @@ impl<'a, P: PackedField> DoubleEndedIterator for PackedStridedViewIter<'a, P> {
     }
 }
 
+/// With the `forbid-unsafe` feature, the iterator shrinks a view instead of moving pointers.
+// Not deriving `Copy`. An implicit copy of an iterator is likely a bug.
+#[cfg(feature = "forbid-unsafe")]
+#[derive(Clone, Debug)]
+pub struct PackedStridedViewIter<'a, P: PackedField> {
+    view: PackedStridedView<'a, P>,
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl<'a, P: PackedField> Iterator for PackedStridedViewIter<'a, P> {
+    type Item = &'a P;
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.view.get(0)?;
+        self.view = self.view.sub_view(1, self.view.len() - 1);
+        Some(res)
+    }
+}
+
+#[cfg(feature = "forbid-unsafe")]
+impl<'a, P: PackedField> DoubleEndedIterator for PackedStridedViewIter<'a, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let last = self.view.len().checked_sub(1)?;
+        let res = self.view.get(last)?;
+        self.view.length = last;
+        Some(res)
+    }
+}
+
 pub trait Viewable<F> {
     // We cannot implement `Index` as `PackedStridedView` is a struct, not a reference.
     type View;
@@ -246,14 +320,7 @@ impl<'a, P: PackedField> Viewable<Range<usize>> for PackedStridedView<'a, P> {
     fn view(&self, range: Range<usize>) -> Self::View {
         assert!(range.start <= self.len(), "Invalid access");
         assert!(range.end <= self.len(), "Invalid access");
-        Self {
-            // See comment in `PackedStridedView`. `self.start_ptr` will point more than one byte
-            // past the end of the buffer if the offset is not 0 and the buffer has length 0.
-            start_ptr: self.start_ptr.wrapping_add(self.stride * range.start),
-            length: range.end - range.start,
-            stride: self.stride,
-            _phantom: PhantomData,
-        }
+        self.sub_view(range.start, range.end - range.start)
     }
 }
 
@@ -261,14 +328,7 @@ impl<'a, P: PackedField> Viewable<RangeFrom<usize>> for PackedStridedView<'a, P>
     type View = Self;
     fn view(&self, range: RangeFrom<usize>) -> Self::View {
         assert!(range.start <= self.len(), "Invalid access");
-        Self {
-            // See comment in `PackedStridedView`. `self.start_ptr` will point more than one byte
-            // past the end of the buffer if the offset is not 0 and the buffer has length 0.
-            start_ptr: self.start_ptr.wrapping_add(self.stride * range.start),
-            length: self.len() - range.start,
-            stride: self.stride,
-            _phantom: PhantomData,
-        }
+        self.sub_view(range.start, self.len() - range.start)
     }
 }
 
@@ -284,14 +344,7 @@ impl<'a, P: PackedField> Viewable<RangeInclusive<usize>> for PackedStridedView<'
     fn view(&self, range: RangeInclusive<usize>) -> Self::View {
         assert!(*range.start() <= self.len(), "Invalid access");
         assert!(*range.end() < self.len(), "Invalid access");
-        Self {
-            // See comment in `PackedStridedView`. `self.start_ptr` will point more than one byte
-            // past the end of the buffer if the offset is not 0 and the buffer has length 0.
-            start_ptr: self.start_ptr.wrapping_add(self.stride * range.start()),
-            length: range.end() - range.start() + 1,
-            stride: self.stride,
-            _phantom: PhantomData,
-        }
+        self.sub_view(*range.start(), range.end() - range.start() + 1)
     }
 }
 
@@ -299,12 +352,7 @@ impl<'a, P: PackedField> Viewable<RangeTo<usize>> for PackedStridedView<'a, P> {
     type View = Self;
     fn view(&self, range: RangeTo<usize>) -> Self::View {
         assert!(range.end <= self.len(), "Invalid access");
-        Self {
-            start_ptr: self.start_ptr,
-            length: range.end,
-            stride: self.stride,
-            _phantom: PhantomData,
-        }
+        self.sub_view(0, range.end)
     }
 }
 
@@ -312,11 +360,6 @@ impl<'a, P: PackedField> Viewable<RangeToInclusive<usize>> for PackedStridedView
     type View = Self;
     fn view(&self, range: RangeToInclusive<usize>) -> Self::View {
         assert!(range.end < self.len(), "Invalid access");
-        Self {
-            start_ptr: self.start_ptr,
-            length: range.end + 1,
-            stride: self.stride,
-            _phantom: PhantomData,
-        }
+        self.sub_view(0, range.end + 1)
     }
 }
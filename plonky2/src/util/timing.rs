@@ -3,6 +3,9 @@ use std::time::{Duration, Instant};
 
 use log::{log, Level};
 
+#[cfg(feature = "timing")]
+use crate::util::memory::{MemoryTracker, PeakWatch, MEMORY};
+
 /// The hierarchy of scopes, and the time consumed by each one. Useful for profiling.
 #[cfg(feature = "timing")]
 pub struct TimingTree {
@@ -14,6 +17,13 @@ pub struct TimingTree {
     enter_time: Instant,
     /// The time when this scope was destroyed, or None if it has not yet been destroyed.
     exit_time: Option<Instant>,
+    /// The largest number of bytes in use while this scope was open, as counted by `tracker`.
+    /// Open scopes are only updated when a scope is pushed or popped.
+    peak_bytes: usize,
+    tracker: &'static MemoryTracker,
+    /// The measurement of the peak since the last push or pop, held by the root; `None` for
+    /// other scopes, or if the tracker has no watch left.
+    watch: Option<PeakWatch>,
     /// Any child scopes.
     children: Vec<TimingTree>,
 }
@@ -38,11 +48,28 @@ impl Default for TimingTree {
 impl TimingTree {
     #[cfg(feature = "timing")]
     pub fn new(root_name: &str, level: Level) -> Self {
+        Self::with_tracker(root_name, level, &MEMORY)
+    }
+
+    /// A tree whose peaks are counted by `tracker` rather than by `MEMORY`.
+    #[cfg(feature = "timing")]
+    pub fn with_tracker(root_name: &str, level: Level, tracker: &'static MemoryTracker) -> Self {
         Self {
-            name: root_name.to_string(),
+            watch: tracker.watch_peak(),
+            ..Self::scope(root_name, level, tracker)
+        }
+    }
+
+    #[cfg(feature = "timing")]
+    fn scope(name: &str, level: Level, tracker: &'static MemoryTracker) -> Self {
+        Self {
+            name: name.to_string(),
             level,
             enter_time: Instant::now(),
             exit_time: None,
+            peak_bytes: tracker.current_bytes(),
+            tracker,
+            watch: None,
             children: vec![],
         }
     }
@@ -76,27 +103,46 @@ impl TimingTree {
         }
     }
 
+    /// Raises the peak of every open scope to `bytes`.
+    #[cfg(feature = "timing")]
+    fn record_peak(&mut self, bytes: usize) {
+        if self.is_open() {
+            self.peak_bytes = self.peak_bytes.max(bytes);
+            if let Some(last_child) = self.children.last_mut() {
+                last_child.record_peak(bytes);
+            }
+        }
+    }
+
+    /// Records the peak since the last push or pop in the open scopes, and starts a new one.
     #[cfg(feature = "timing")]
-    pub fn push(&mut self, ctx: &str, mut level: log::Level) {
+    fn reset_peak(&mut self) {
+        if let Some(peak) = self.watch.as_ref().map(PeakWatch::reset) {
+            self.record_peak(peak);
+        }
+    }
+
+    #[cfg(feature = "timing")]
+    pub fn push(&mut self, ctx: &str, level: log::Level) {
         assert!(self.is_open());
+        self.reset_peak();
+        self.push_helper(ctx, level);
+    }
 
+    #[cfg(feature = "timing")]
+    fn push_helper(&mut self, ctx: &str, mut level: log::Level) {
         // We don't want a scope's log level to be stronger than that of its parent.
         level = level.max(self.level);
 
         if let Some(last_child) = self.children.last_mut() {
             if last_child.is_open() {
-                last_child.push(ctx, level);
+                last_child.push_helper(ctx, level);
                 return;
             }
         }
 
-        self.children.push(TimingTree {
-            name: ctx.to_string(),
-            level,
-            enter_time: Instant::now(),
-            exit_time: None,
-            children: vec![],
-        })
+        self.children
+            .push(TimingTree::scope(ctx, level, self.tracker))
     }
 
     #[cfg(not(feature = "timing"))]
@@ -106,10 +152,15 @@ impl TimingTree {
     #[cfg(feature = "timing")]
     pub fn pop(&mut self) {
         assert!(self.is_open());
+        self.reset_peak();
+        self.pop_helper();
+    }
 
+    #[cfg(feature = "timing")]
+    fn pop_helper(&mut self) {
         if let Some(last_child) = self.children.last_mut() {
             if last_child.is_open() {
-                last_child.pop();
+                last_child.pop_helper();
                 return;
             }
         }
@@ -135,6 +186,9 @@ impl TimingTree {
             level: self.level,
             enter_time: self.enter_time,
             exit_time: self.exit_time,
+            peak_bytes: self.peak_bytes,
+            tracker: self.tracker,
+            watch: None,
             children: self
                 .children
                 .iter()
@@ -144,6 +198,38 @@ impl TimingTree {
         }
     }
 
+    /// The largest number of bytes in use while this scope was open, if a `TrackingAllocator` is
+    /// installed.
+    #[cfg(feature = "timing")]
+    pub fn peak_bytes(&self) -> Option<usize> {
+        self.tracker.is_active().then_some(self.peak_bytes)
+    }
+
+    /// The peak number of bytes in use in each scope, e.g. to find which phase of the prover
+    /// needs the most memory. Scopes are named by their path from the root, like `open_stack`, and
+    /// listed depth first. This is empty if no `TrackingAllocator` is installed.
+    #[cfg(feature = "timing")]
+    pub fn peak_bytes_by_phase(&self) -> Vec<(String, usize)> {
+        let mut phases = Vec::new();
+        if self.tracker.is_active() {
+            self.peak_bytes_helper("", &mut phases);
+        }
+        phases
+    }
+
+    #[cfg(feature = "timing")]
+    fn peak_bytes_helper(&self, prefix: &str, phases: &mut Vec<(String, usize)>) {
+        let path = if prefix.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} > {}", prefix, self.name)
+        };
+        phases.push((path.clone(), self.peak_bytes));
+        for child in &self.children {
+            child.peak_bytes_helper(&path, phases);
+        }
+    }
+
     #[cfg(feature = "timing")]
     pub fn print(&self) {
         self.print_helper(0);
@@ -160,13 +246,24 @@ impl TimingTree {
     #[cfg(feature = "timing")]
     fn print_helper(&self, depth: usize) {
         let prefix = "| ".repeat(depth);
-        log!(
-            self.level,
-            "{}{:.4}s to {}",
-            prefix,
-            self.duration().as_secs_f64(),
-            self.name
-        );
+        if self.tracker.is_active() {
+            log!(
+                self.level,
+                "{}{:.4}s, peak {:.1} MiB, to {}",
+                prefix,
+                self.duration().as_secs_f64(),
+                self.peak_bytes as f64 / (1 << 20) as f64,
+                self.name
+            );
+        } else {
+            log!(
+                self.level,
+                "{}{:.4}s to {}",
+                prefix,
+                self.duration().as_secs_f64(),
+                self.name
+            );
+        }
         for child in &self.children {
             child.print_helper(depth + 1);
         }
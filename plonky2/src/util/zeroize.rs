@@ -0,0 +1,88 @@
+//! Helpers to erase secret witness data from memory, used when the `zeroize` feature is enabled.
+//!
+//! Field elements are generic, so they can't implement `zeroize::Zeroize` themselves; these
+//! helpers overwrite them with volatile writes, as the `zeroize` crate does, so the compiler can't
+//! elide the writes to memory that is about to be freed. With the `forbid-unsafe` feature, they
+//! use plain writes followed by a compiler fence instead, which is a weaker guarantee.
+
+use alloc::vec::Vec;
+#[cfg(not(feature = "forbid-unsafe"))]
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::field::types::Field;
+
+/// Overwrites `values` with zeros.
+pub fn zeroize_field_elements<F: Field>(values: &mut [F]) {
+    for value in values.iter_mut() {
+        // Safety: `value` is a valid, aligned and exclusive reference.
+        #[cfg(not(feature = "forbid-unsafe"))]
+        unsafe {
+            ptr::write_volatile(value, F::ZERO)
+        };
+        #[cfg(feature = "forbid-unsafe")]
+        {
+            *value = F::ZERO;
+        }
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrites each of `vecs` with zeros, then clears them.
+pub fn zeroize_field_vecs<F: Field>(vecs: &mut [Vec<F>]) {
+    for v in vecs.iter_mut() {
+        zeroize_field_elements(v);
+        v.clear();
+    }
+}
+
+/// Overwrites `values` with zeros, then clears it. Setting an `Option` to `None` may leave its
+/// payload in memory, so each value is first overwritten with `Some(F::ZERO)`.
+pub fn zeroize_optional_field_elements<F: Field>(values: &mut Vec<Option<F>>) {
+    for value in values.iter_mut() {
+        // Safety: `value` is a valid, aligned and exclusive reference.
+        #[cfg(not(feature = "forbid-unsafe"))]
+        unsafe {
+            ptr::write_volatile(value, Some(F::ZERO))
+        };
+        #[cfg(feature = "forbid-unsafe")]
+        {
+            *value = Some(F::ZERO);
+        }
+    }
+    compiler_fence(Ordering::SeqCst);
+    values.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use zeroize::Zeroize;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
+    use crate::util::zeroize::{zeroize_field_elements, zeroize_optional_field_elements};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_zeroize_field_elements() {
+        let mut values = F::rand_vec(10);
+        zeroize_field_elements(&mut values);
+        assert!(values.iter().all(|v| v.is_zero()));
+
+        let mut values = F::rand_vec(10).into_iter().map(Some).collect();
+        zeroize_optional_field_elements(&mut values);
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_zeroize_partial_witness() {
+        let mut pw = PartialWitness::new();
+        let target = Target::VirtualTarget { index: 0 };
+        pw.set_target(target, F::rand());
+        pw.zeroize();
+        assert_eq!(pw.try_get_target(target), None);
+    }
+}
@@ -0,0 +1,365 @@
+// HACK: Ideally this would live in `benches/`, but `cargo bench` doesn't allow
+// custom CLI argument parsing (even with harness disabled).
+
+use core::num::ParseIntError;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context as _, Result};
+use log::{info, Level};
+use maybe_rayon::rayon;
+use plonky2::field::types::{Field, Sample};
+use plonky2::hash::hash_types::{HashOut, RichField};
+use plonky2::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use plonky2::hash::merkle_tree::MerkleTree;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProvenCircuitOutput;
+use plonky2::plonk::prover::prove;
+use plonky2::util::timing::TimingTree;
+use plonky2_field::extension::Extendable;
+use plonky2_field::goldilocks_field::GoldilocksField;
+use plonky2_recursive_tools::{
+    generate_circom_verifier, generate_proof_base64, generate_verifier_config, recursive_proof,
+    test_serialization, PublicInputsCommitment,
+};
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use structopt::StructOpt;
+
+#[derive(Clone, StructOpt, Debug)]
+pub struct BenchOptions {
+    /// Random seed for deterministic runs.
+    /// If not specified a new seed is generated from OS entropy.
+    #[structopt(long, parse(try_from_str = parse_hex_u64))]
+    seed: Option<u64>,
+
+    /// Number of compute threads to use. Defaults to number of cores. Can be a single
+    /// value or a rust style range.
+    #[structopt(long, parse(try_from_str = parse_range_usize))]
+    threads: Option<RangeInclusive<usize>>,
+}
+
+fn tree_height() -> usize {
+    20
+}
+
+fn semaphore_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    config: &CircuitConfig,
+    private_key: [F; 4],
+    rln: F,
+    topic: [F; 4],
+    public_key_index: usize,
+    merkle_proof: MerkleProof<F, PoseidonHash>,
+    merkle_root_value: HashOut<F>,
+) -> Result<ProvenCircuitOutput<F, C, D>> {
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+
+    let merkle_root_target = builder.add_virtual_hash();
+    info!("merkle root target is {:?}", merkle_root_target);
+    builder.register_public_inputs(&merkle_root_target.elements);
+    let nullifier_target = builder.add_virtual_hash();
+    builder.register_public_inputs(&nullifier_target.elements);
+    let topic_target: [Target; 4] = builder.add_virtual_targets(4).try_into().unwrap();
+    builder.register_public_inputs(&topic_target);
+
+    let new_leaf_target = builder.add_virtual_hash();
+    builder.register_public_inputs(&new_leaf_target.elements);
+
+    // Merkle proof
+    let merkle_proof_target = MerkleProofTarget {
+        siblings: builder.add_virtual_hashes(tree_height()),
+    };
+
+    // Verify public key Merkle proof.
+    let private_key_target: [Target; 4] = builder.add_virtual_targets(4).try_into().unwrap();
+    let rln_target = builder.add_virtual_target();
+    let new_rln_target = builder.add_virtual_target();
+    let public_key_index_target = builder.add_virtual_target();
+    let public_key_index_bits_target = builder.split_le(public_key_index_target, tree_height());
+    let zero_target = builder.zero();
+    let neg_one_target = builder.neg_one();
+
+    builder.verify_merkle_proof::<PoseidonHash>(
+        [
+            private_key_target,
+            [zero_target, zero_target, zero_target, rln_target],
+        ]
+        .concat(),
+        &public_key_index_bits_target,
+        merkle_root_target,
+        &merkle_proof_target,
+    );
+
+    // Check nullifier.
+    let should_be_nullifier_target =
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>([private_key_target, topic_target].concat());
+    for i in 0..4 {
+        builder.connect(
+            nullifier_target.elements[i],
+            should_be_nullifier_target.elements[i],
+        );
+    }
+
+    let should_be_new_rln_target = builder.add(rln_target, neg_one_target);
+    builder.connect(should_be_new_rln_target, new_rln_target);
+    builder.range_check(new_rln_target, 32);
+
+    // Check new leaf.
+    let should_be_new_leaf_target = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
+        [
+            private_key_target,
+            [zero_target, zero_target, zero_target, new_rln_target],
+        ]
+        .concat(),
+    );
+    for i in 0..4 {
+        builder.connect(
+            new_leaf_target.elements[i],
+            should_be_new_leaf_target.elements[i],
+        );
+    }
+
+    let data = builder.build::<C>();
+    let mut pw = PartialWitness::new();
+
+    pw.set_hash_target(merkle_root_target, merkle_root_value);
+    pw.set_target_arr(private_key_target, private_key);
+    pw.set_target(rln_target, rln);
+    pw.set_target(new_rln_target, rln.sub_one());
+    pw.set_target_arr(topic_target, topic);
+    pw.set_target(
+        public_key_index_target,
+        F::from_canonical_usize(public_key_index),
+    );
+
+    for (ht, h) in merkle_proof_target
+        .siblings
+        .into_iter()
+        .zip(merkle_proof.siblings.clone())
+    {
+        pw.set_hash_target(ht, h);
+    }
+
+    let mut timing = TimingTree::new("prove", Level::Debug);
+    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+    timing.print();
+    data.verify(proof.clone())?;
+
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only,
+        data.common,
+    ))
+}
+/// Aggregates four semaphore proofs in two layers of recursion and exports the result to the
+/// circom verifier, for each thread count in `options`.
+pub fn run(options: BenchOptions) -> Result<()> {
+    // Initialize randomness source
+    let rng_seed = options.seed.unwrap_or_else(|| OsRng::default().next_u64());
+    info!("Using random seed {rng_seed:16x}");
+    let _rng = ChaCha8Rng::seed_from_u64(rng_seed);
+    // TODO: Use `rng` to create deterministic runs
+
+    let num_cpus = num_cpus::get();
+    let threads = options.threads.unwrap_or(num_cpus..=num_cpus);
+
+    let config = CircuitConfig::standard_recursion_config();
+    let zk_config = CircuitConfig::standard_recursion_config();
+    for threads in threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .context("Failed to build thread pool.")?
+            .install(|| {
+                info!(
+                    "Using {} compute threads on {} cores",
+                    rayon::current_num_threads(),
+                    num_cpus
+                );
+                // Run the benchmark
+                benchmark(&config, &zk_config)
+            })?;
+    }
+
+    Ok(())
+}
+
+fn benchmark(config: &CircuitConfig, zk_config: &CircuitConfig) -> Result<()> {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = GoldilocksField;
+
+    // CREATE TREE WITH 2^n leaves
+    let n = 1 << tree_height();
+    let private_keys: Vec<[F; 4]> = (0..n).map(|_| F::rand_array()).collect();
+    let rlns: Vec<F> = (0..n).map(|_| F::ONE).collect();
+    let public_keys: Vec<Vec<F>> = private_keys
+        .iter()
+        .map(|&sk| {
+            PoseidonHash::hash_no_pad(&[sk, [F::ZERO, F::ZERO, F::ZERO, F::ONE]].concat())
+                .elements
+                .to_vec()
+        })
+        .collect();
+
+    let merkle_tree: MerkleTree<GoldilocksField, PoseidonHash> = MerkleTree::new(public_keys, 0);
+    let merkle_root_value = merkle_tree.cap.0[0];
+
+    let merkle_proof1 = merkle_tree.prove(1);
+    let topic1: [GoldilocksField; 4] = F::rand_array();
+    let inner1 = semaphore_proof(
+        zk_config,
+        private_keys[1],
+        rlns[1],
+        topic1,
+        1,
+        merkle_proof1,
+        merkle_root_value,
+    )?;
+    let cd1 = inner1.common();
+    info!(
+        "Initial proof 1 degree {} = 2^{}",
+        cd1.degree(),
+        cd1.degree_bits()
+    );
+
+    let merkle_proof2 = merkle_tree.prove(2);
+    let inner2 = semaphore_proof(
+        zk_config,
+        private_keys[2],
+        rlns[2],
+        topic1,
+        2,
+        merkle_proof2,
+        merkle_root_value,
+    )?;
+    let cd2 = inner2.common();
+    info!(
+        "Initial proof 2 degree {} = 2^{}",
+        cd2.degree(),
+        cd2.degree_bits()
+    );
+
+    // Recursively verify the proof
+    let middle1 = recursive_proof::<F, C, C, D>(&inner1, &inner2, config, None)?;
+    let cdm1 = middle1.common();
+    info!(
+        "Single recursion proof 1 degree {} = 2^{}",
+        cdm1.degree(),
+        cdm1.degree_bits()
+    );
+
+    let merkle_proof3 = merkle_tree.prove(3);
+    let inner3 = semaphore_proof(
+        zk_config,
+        private_keys[3],
+        rlns[3],
+        topic1,
+        3,
+        merkle_proof3,
+        merkle_root_value,
+    )?;
+    let cd3 = inner3.common();
+    info!(
+        "Initial proof 3 degree {} = 2^{}",
+        cd3.degree(),
+        cd3.degree_bits()
+    );
+
+    let merkle_proof4 = merkle_tree.prove(4);
+    let topic4: [GoldilocksField; 4] = F::rand_array();
+    let inner4 = semaphore_proof(
+        zk_config,
+        private_keys[4],
+        rlns[4],
+        topic4,
+        4,
+        merkle_proof4,
+        merkle_root_value,
+    )?;
+    let cd4 = inner4.common();
+    info!(
+        "Initial proof 4 degree {} = 2^{}",
+        cd4.degree(),
+        cd4.degree_bits()
+    );
+
+    // Recursively verify the proof
+    let middle2 = recursive_proof::<F, C, C, D>(&inner3, &inner4, config, None)?;
+    let cdm2 = middle2.common();
+    info!(
+        "Single recursion proof 2 degree {} = 2^{}",
+        cdm2.degree(),
+        cdm2.degree_bits()
+    );
+
+    // Add a second layer of recursion to shrink the proof size further
+    let outer = recursive_proof::<F, C, C, D>(&middle1, &middle2, config, None)?;
+    let (proof, vd, cd) = (outer.proof(), outer.verifier_only(), outer.common());
+    info!(
+        "Double recursion proof degree {} = 2^{}",
+        cd.degree(),
+        cd.degree_bits()
+    );
+
+    test_serialization(proof, vd, cd)?;
+
+    let conf = generate_verifier_config(&proof, PublicInputsCommitment::None)?;
+    let (circom_constants, circom_gates) = generate_circom_verifier(&conf, &cd, &vd)?;
+
+    let mut circom_file = File::create("./circom/circuits/constants.circom")?;
+    circom_file.write_all(circom_constants.as_bytes())?;
+    circom_file = File::create("./circom/circuits/gates.circom")?;
+    circom_file.write_all(circom_gates.as_bytes())?;
+
+    let proof_json = generate_proof_base64(&proof, &conf)?;
+
+    if !Path::new("./circom/test/data").is_dir() {
+        std::fs::create_dir("circom/test/data")?;
+    }
+    //input for snarkjs
+    let mut proof_file = File::create("./circom/test/data/proof.json")?;
+    proof_file.write_all(proof_json.as_bytes())?;
+
+    //input for snarkjs
+    let mut conf_file = File::create("./circom/test/data/conf.json")?;
+    conf_file.write_all(serde_json::to_string(&conf)?.as_ref())?;
+
+    //TODO: gen contract from plonky2 circom
+    Ok(())
+}
+
+fn parse_hex_u64(src: &str) -> Result<u64, ParseIntError> {
+    let src = src.strip_prefix("0x").unwrap_or(src);
+    u64::from_str_radix(src, 16)
+}
+
+fn parse_range_usize(src: &str) -> Result<RangeInclusive<usize>, ParseIntError> {
+    if let Some((left, right)) = src.split_once("..=") {
+        Ok(RangeInclusive::new(
+            usize::from_str(left)?,
+            usize::from_str(right)?,
+        ))
+    } else if let Some((left, right)) = src.split_once("..") {
+        Ok(RangeInclusive::new(
+            usize::from_str(left)?,
+            if right.is_empty() {
+                usize::MAX
+            } else {
+                usize::from_str(right)?.saturating_sub(1)
+            },
+        ))
+    } else {
+        let value = usize::from_str(src)?;
+        Ok(RangeInclusive::new(value, value))
+    }
+}
@@ -1,253 +1,83 @@
-// HACK: Ideally this would live in `benches/`, but `cargo bench` doesn't allow
-// custom CLI argument parsing (even with harness disabled). We could also have
-// put it in `src/bin/`, but then we wouldn't have access to
-// `[dev-dependencies]`.
+//! Aggregation of proofs and their export to the circom verifier, used by the private_tx example
+//! and by the `recursive-tools` binary, which runs these steps on proofs read from files.
 
-use core::num::ParseIntError;
-use core::ops::RangeInclusive;
 use core::str::FromStr;
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 
-use anyhow::{anyhow, Context as _, Result};
-use log::{info, Level, LevelFilter};
-use maybe_rayon::rayon;
-use plonky2::field::types::{Field, Sample};
+use anyhow::{anyhow, ensure, Result};
+use keccak_hash::keccak;
+use log::{info, Level};
+use num::BigUint;
+use plonky2::field::types::Field;
+use plonky2::fri::{FriConfig, FriPowHash};
 use plonky2::gates::noop::NoopGate;
-use plonky2::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
-use plonky2::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
-use plonky2::hash::merkle_tree::MerkleTree;
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::iop::target::Target;
-use plonky2::iop::witness::{PartialWitness, Witness, WitnessWrite};
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::{
     CircuitConfig, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
-use plonky2::plonk::config::{
-    AlgebraicHasher, GenericConfig, GenericHashOut, Hasher, PoseidonGoldilocksConfig,
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut};
+use plonky2::plonk::proof::{
+    CompressedProofWithPublicInputs, ProofWithPublicInputs, ProvenCircuitOutput,
 };
-use plonky2::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
 use plonky2::plonk::prover::prove;
 use plonky2::util::timing::TimingTree;
 use plonky2_field::extension::{Extendable, FieldExtension};
-use plonky2_field::goldilocks_field::GoldilocksField;
 use plonky2_util::log2_strict;
-use rand::rngs::OsRng;
-use rand::{RngCore, SeedableRng};
-use rand_chacha::ChaCha8Rng;
 use serde::Serialize;
-use structopt::StructOpt;
-
-type ProofTuple<F, C, const D: usize> = (
-    ProofWithPublicInputs<F, C, D>,
-    VerifierOnlyCircuitData<C, D>,
-    CommonCircuitData<F, D>,
-);
-
-#[derive(Clone, StructOpt, Debug)]
-#[structopt(name = "bench_recursion")]
-struct Options {
-    /// Verbose mode (-v, -vv, -vvv, etc.)
-    #[structopt(short, long, parse(from_occurrences))]
-    verbose: usize,
-
-    /// Apply an env_filter compatible log filter
-    #[structopt(long, env, default_value)]
-    log_filter: String,
-
-    /// Random seed for deterministic runs.
-    /// If not specified a new seed is generated from OS entropy.
-    #[structopt(long, parse(try_from_str = parse_hex_u64))]
-    seed: Option<u64>,
-
-    /// Number of compute threads to use. Defaults to number of cores. Can be a single
-    /// value or a rust style range.
-    #[structopt(long, parse(try_from_str = parse_range_usize))]
-    threads: Option<RangeInclusive<usize>>,
-
-    /// Log2 gate count of the inner proof. Can be a single value or a rust style
-    /// range.
-    #[structopt(long, default_value = "14", parse(try_from_str = parse_range_usize))]
-    size: RangeInclusive<usize>,
-}
-
-/// Creates a dummy proof which should have `2 ** log2_size` rows.
-fn dummy_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-    config: &CircuitConfig,
-    log2_size: usize,
-) -> Result<ProofTuple<F, C, D>> {
-    // 'size' is in degree, but we want number of noop gates. A non-zero amount of padding will be added and size will be rounded to the next power of two. To hit our target size, we go just under the previous power of two and hope padding is less than half the proof.
-    let num_dummy_gates = match log2_size {
-        0 => return Err(anyhow!("size must be at least 1")),
-        1 => 0,
-        2 => 1,
-        n => (1 << (n - 1)) + 1,
-    };
-    info!("Constructing inner proof with {} gates", num_dummy_gates);
-    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
-    for _ in 0..num_dummy_gates {
-        builder.add_gate(NoopGate, vec![]);
-    }
-    builder.print_gate_counts(0);
-
-    let data = builder.build::<C>();
-    let inputs = PartialWitness::new();
-
-    let mut timing = TimingTree::new("prove", Level::Debug);
-    let proof = prove(&data.prover_only, &data.common, inputs, &mut timing)?;
-    timing.print();
-    data.verify(proof.clone())?;
-
-    Ok((proof, data.verifier_only, data.common))
-}
-
-struct SemaphoreTargets {
-    merkle_root: HashOutTarget,
-    topic: [Target; 4],
-    merkle_proof: MerkleProofTarget,
-    private_key: [Target; 4],
-    public_key_index: Target,
-}
-
-fn tree_height() -> usize {
-    20
-}
-
-fn semaphore_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-    config: &CircuitConfig,
-    private_key: [F; 4],
-    rln: F,
-    topic: [F; 4],
-    public_key_index: usize,
-    merkle_proof: MerkleProof<F, PoseidonHash>,
-    merkle_root_value: HashOut<F>,
-) -> Result<ProofTuple<F, C, D>> {
-    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
-
-    let merkle_root_target = builder.add_virtual_hash();
-    info!("merkle root target is {:?}", merkle_root_target);
-    builder.register_public_inputs(&merkle_root_target.elements);
-    let nullifier_target = builder.add_virtual_hash();
-    builder.register_public_inputs(&nullifier_target.elements);
-    let topic_target: [Target; 4] = builder.add_virtual_targets(4).try_into().unwrap();
-    builder.register_public_inputs(&topic_target);
-
-    let new_leaf_target = builder.add_virtual_hash();
-    builder.register_public_inputs(&new_leaf_target.elements);
-
-    // Merkle proof
-    let merkle_proof_target = MerkleProofTarget {
-        siblings: builder.add_virtual_hashes(tree_height()),
-    };
-
-    // Verify public key Merkle proof.
-    let private_key_target: [Target; 4] = builder.add_virtual_targets(4).try_into().unwrap();
-    let rln_target = builder.add_virtual_target();
-    let new_rln_target = builder.add_virtual_target();
-    let public_key_index_target = builder.add_virtual_target();
-    let public_key_index_bits_target = builder.split_le(public_key_index_target, tree_height());
-    let zero_target = builder.zero();
-    let neg_one_target = builder.neg_one();
-
-    builder.verify_merkle_proof::<PoseidonHash>(
-        [
-            private_key_target,
-            [zero_target, zero_target, zero_target, rln_target],
-        ]
-        .concat(),
-        &public_key_index_bits_target,
-        merkle_root_target,
-        &merkle_proof_target,
-    );
-
-    // Check nullifier.
-    let should_be_nullifier_target =
-        builder.hash_n_to_hash_no_pad::<PoseidonHash>([private_key_target, topic_target].concat());
-    for i in 0..4 {
-        builder.connect(
-            nullifier_target.elements[i],
-            should_be_nullifier_target.elements[i],
-        );
-    }
-
-    let should_be_new_rln_target = builder.add(rln_target, neg_one_target);
-    builder.connect(should_be_new_rln_target, new_rln_target);
-    builder.range_check(new_rln_target, 32);
-
-    // Check new leaf.
-    let should_be_new_leaf_target = builder.hash_n_to_hash_no_pad::<PoseidonHash>(
-        [
-            private_key_target,
-            [zero_target, zero_target, zero_target, new_rln_target],
-        ]
-        .concat(),
-    );
-    for i in 0..4 {
-        builder.connect(
-            new_leaf_target.elements[i],
-            should_be_new_leaf_target.elements[i],
-        );
-    }
-
-    let data = builder.build::<C>();
-    let mut pw = PartialWitness::new();
-
-    pw.set_hash_target(merkle_root_target, merkle_root_value);
-    pw.set_target_arr(private_key_target, private_key);
-    pw.set_target(rln_target, rln);
-    pw.set_target(new_rln_target, rln.sub_one());
-    pw.set_target_arr(topic_target, topic);
-    pw.set_target(
-        public_key_index_target,
-        F::from_canonical_usize(public_key_index),
-    );
-
-    for (ht, h) in merkle_proof_target
-        .siblings
-        .into_iter()
-        .zip(merkle_proof.siblings.clone())
-    {
-        pw.set_hash_target(ht, h);
-    }
-
-    let mut timing = TimingTree::new("prove", Level::Debug);
-    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
-    timing.print();
-    data.verify(proof.clone())?;
-
-    Ok((proof, data.verifier_only, data.common))
+use sha2::{Digest, Sha256};
+
+/// The config of the final aggregation layer, whose proof is verified by the circom verifier: fewer
+/// query rounds than `config`, with a higher rate and more proof-of-work keeping the same security.
+pub fn circom_final_config(config: &CircuitConfig) -> Result<CircuitConfig> {
+    Ok(CircuitConfig {
+        fri_config: FriConfig {
+            rate_bits: 4,
+            proof_of_work_bits: 20,
+            ..config.fri_config.clone()
+        }
+        .with_security_bits(config.security_bits)?,
+        ..config.clone()
+    })
 }
 
-fn recursive_proof<
+/// Proves that `inner1` and `inner2` are valid proofs, exposing the circuit digests of their
+/// verifier data as public inputs, in order.
+pub fn recursive_proof<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     InnerC: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    inner1: &ProofTuple<F, InnerC, D>,
-    inner2: &ProofTuple<F, InnerC, D>,
+    inner1: &ProvenCircuitOutput<F, InnerC, D>,
+    inner2: &ProvenCircuitOutput<F, InnerC, D>,
     config: &CircuitConfig,
     min_degree_bits: Option<usize>,
-) -> Result<ProofTuple<F, C, D>>
+) -> Result<ProvenCircuitOutput<F, C, D>>
 where
     InnerC::Hasher: AlgebraicHasher<F>,
 {
-    let (inner_proof1, inner_vd1, inner_cd1) = inner1;
-    let (inner_proof2, inner_vd2, inner_cd2) = inner2;
+    let inner_cd1 = inner1.common();
+    let inner_cd2 = inner2.common();
 
     let mut builder = CircuitBuilder::<F, D>::new(config.clone());
     let pt1 = builder.add_virtual_proof_with_pis::<InnerC>(inner_cd1);
     let pt2 = builder.add_virtual_proof_with_pis::<InnerC>(inner_cd2);
 
-    let inner_data = VerifierCircuitTarget {
+    let vc1 = VerifierCircuitTarget {
         constants_sigmas_cap: builder.add_virtual_cap(inner_cd1.config.fri_config.cap_height),
         circuit_digest: builder.add_virtual_hash(),
     };
+    builder.register_public_inputs(&vc1.circuit_digest.elements);
+    let vc2 = VerifierCircuitTarget {
+        constants_sigmas_cap: builder.add_virtual_cap(inner_cd2.config.fri_config.cap_height),
+        circuit_digest: builder.add_virtual_hash(),
+    };
+    builder.register_public_inputs(&vc2.circuit_digest.elements);
 
-    builder.verify_proof::<InnerC>(&pt1, &inner_data, inner_cd1);
-    builder.verify_proof::<InnerC>(&pt2, &inner_data, inner_cd2);
+    builder.verify_proof::<InnerC>(&pt1, &vc1, inner_cd1);
+    builder.verify_proof::<InnerC>(&pt2, &vc2, inner_cd2);
     builder.print_gate_counts(0);
 
     if let Some(min_degree_bits) = min_degree_bits {
@@ -264,10 +94,10 @@ where
     let data = builder.build::<C>();
 
     let mut pw = PartialWitness::new();
-    pw.set_proof_with_pis_target(&pt1, inner_proof1);
-    pw.set_verifier_data_target(&inner_data, inner_vd1);
-    pw.set_proof_with_pis_target(&pt2, inner_proof2);
-    pw.set_verifier_data_target(&inner_data, inner_vd2);
+    pw.set_proof_with_pis_target(&pt1, inner1.proof());
+    pw.set_verifier_data_target(&vc1, inner1.verifier_only());
+    pw.set_proof_with_pis_target(&pt2, inner2.proof());
+    pw.set_verifier_data_target(&vc2, inner2.verifier_only());
 
     let mut timing = TimingTree::new("prove", Level::Debug);
     let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
@@ -275,7 +105,37 @@ where
 
     data.verify(proof.clone())?;
 
-    Ok((proof, data.verifier_only, data.common))
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        data.verifier_only,
+        data.common,
+    ))
+}
+
+/// How the public inputs of the proof are exposed by the circom verifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum PublicInputsCommitment {
+    /// Every public input is a public signal, verified by `plonky2.circom`.
+    #[default]
+    None,
+    /// The public inputs are private signals and the only public signal is their Keccak-256
+    /// digest, verified by `plonky2_digest.circom`. See `public_inputs_digest`.
+    Keccak,
+    /// Like `Keccak`, with SHA-256.
+    Sha256,
+}
+
+impl FromStr for PublicInputsCommitment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "keccak" => Ok(Self::Keccak),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(anyhow!("unknown public inputs commitment {s}")),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -322,6 +182,7 @@ pub struct VerifierConfig {
     num_fri_final_poly_ext_v: usize,
     // public inputs
     num_public_inputs: usize,
+    public_inputs_commitment: PublicInputsCommitment,
 }
 
 #[derive(Serialize)]
@@ -358,6 +219,29 @@ pub struct ProofForCircom {
     fri_pow_witness: String,
 
     public_inputs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_inputs_digest: Option<String>,
+}
+
+/// The digest of `public_inputs` which the circom verifier exposes with a public inputs commitment,
+/// as a decimal string: the hash of the public inputs as 8-byte big-endian integers, i.e. of
+/// `abi.encodePacked` of `uint64`s in Solidity, read as a big-endian integer with its 3 most
+/// significant bits cleared so that it fits in a BN254 scalar.
+pub fn public_inputs_digest<F: RichField>(
+    public_inputs: &[F],
+    commitment: PublicInputsCommitment,
+) -> Option<String> {
+    let bytes = public_inputs
+        .iter()
+        .flat_map(|x| x.to_canonical_u64().to_be_bytes())
+        .collect::<Vec<_>>();
+    let mut digest: [u8; 32] = match commitment {
+        PublicInputsCommitment::None => return None,
+        PublicInputsCommitment::Keccak => keccak(&bytes).0,
+        PublicInputsCommitment::Sha256 => Sha256::digest(&bytes).into(),
+    };
+    digest[0] &= 0x1f;
+    Some(BigUint::from_bytes_be(&digest).to_string())
 }
 
 // TODO: The input should be CommonCircuitData
@@ -367,6 +251,7 @@ pub fn generate_verifier_config<
     const D: usize,
 >(
     pwpi: &ProofWithPublicInputs<F, C, D>,
+    public_inputs_commitment: PublicInputsCommitment,
 ) -> anyhow::Result<VerifierConfig> {
     let proof = &pwpi.proof;
     assert_eq!(proof.opening_proof.query_round_proofs[0].steps.len(), 2);
@@ -417,6 +302,7 @@ pub fn generate_verifier_config<
         num_fri_final_poly_ext_v: proof.opening_proof.final_poly.coeffs.len(),
 
         num_public_inputs: pwpi.public_inputs.len(),
+        public_inputs_commitment,
     };
     Ok(conf)
 }
@@ -812,6 +698,10 @@ pub fn generate_proof_base64<
             .to_canonical_u64()
             .to_string(),
         public_inputs,
+        public_inputs_digest: public_inputs_digest(
+            &pwpi.public_inputs,
+            conf.public_inputs_commitment,
+        ),
     };
 
     let proof_bytes = pwpi.to_bytes();
@@ -821,6 +711,9 @@ pub fn generate_proof_base64<
     Ok(serde_json::to_string(&circom_proof).unwrap())
 }
 
+/// The directory of `template_constants.circom` and `template_gates.circom`, the repository root.
+pub const TEMPLATE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
 pub fn generate_circom_verifier<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -832,30 +725,16 @@ pub fn generate_circom_verifier<
 ) -> anyhow::Result<(String, String)> {
     assert_eq!(F::BITS, 64);
     assert_eq!(F::Extension::BITS, 128);
+    ensure!(
+        !common.fri_params.hiding,
+        "The circom verifier doesn't support salted Merkle leaves"
+    );
     println!("Generating Circom files ...");
 
-    /*
-    // TEST FILE PATH
-    let path = Path::new("lorem_ipsum.txt");
-    let display = path.display();
-
-    // Open a file in write-only mode, returns `io::Result<File>`
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
-
-    // Write the `LOREM_IPSUM` string to `file`, returns `io::Result<()>`
-    match file.write_all("LOREM_IPSUM".as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
-    }
-    // --------------
-    */
-
     // Load template contract
-    let mut constants = std::fs::read_to_string("template_constants.circom")
-        .expect("Something went wrong reading the file");
+    let mut constants =
+        std::fs::read_to_string(Path::new(TEMPLATE_DIR).join("template_constants.circom"))
+            .expect("Something went wrong reading the file");
 
     let k_is = &common.k_is;
     let mut k_is_str = "".to_owned();
@@ -884,6 +763,15 @@ pub fn generate_circom_verifier<
     );
 
     constants = constants.replace("$NUM_PUBLIC_INPUTS", &*conf.num_public_inputs.to_string());
+    let public_inputs_commitment = match conf.public_inputs_commitment {
+        PublicInputsCommitment::None => 0,
+        PublicInputsCommitment::Keccak => 1,
+        PublicInputsCommitment::Sha256 => 2,
+    };
+    constants = constants.replace(
+        "$PUBLIC_INPUTS_COMMITMENT",
+        &public_inputs_commitment.to_string(),
+    );
     constants = constants.replace("$NUM_WIRES_CAP", &*conf.num_wires_cap.to_string());
     constants = constants.replace(
         "$NUM_PLONK_ZS_PARTIAL_PRODUCTS_CAP",
@@ -1013,9 +901,17 @@ pub fn generate_circom_verifier<
     );
     constants = constants.replace(
         "$MIN_FRI_POW_RESPONSE",
-        &*(common.config.fri_config.proof_of_work_bits + (64 - F::order().bits()) as u32)
+        &*common
+            .config
+            .fri_config
+            .min_pow_response_leading_zeros::<F>()
             .to_string(),
     );
+    let fri_pow_hash = match common.config.fri_config.pow_hash {
+        FriPowHash::Field => 0,
+        FriPowHash::Keccak => 1,
+    };
+    constants = constants.replace("$FRI_POW_HASH", &fri_pow_hash.to_string());
     let g = F::Extension::primitive_root_of_unity(common.degree_bits());
     constants = constants.replace(
         "$G_FROM_DEGREE_BITS_0",
@@ -1050,8 +946,9 @@ pub fn generate_circom_verifier<
     constants = constants.replace("$G_ARITY_BITS_4", &g.to_string());
 
     // Load gate template
-    let mut gates_lib = std::fs::read_to_string("template_gates.circom")
-        .expect("Something went wrong reading the file");
+    let mut gates_lib =
+        std::fs::read_to_string(Path::new(TEMPLATE_DIR).join("template_gates.circom"))
+            .expect("Something went wrong reading the file");
 
     let num_selectors = common.selectors_info.num_selectors();
     constants = constants.replace("$NUM_SELECTORS", &num_selectors.to_string());
@@ -1218,237 +1115,3 @@ pub fn test_serialization<
 
     Ok(())
 }
-
-fn benchmark(
-    config: &CircuitConfig,
-    zk_config: &CircuitConfig,
-    log2_inner_size: usize,
-) -> Result<()> {
-    const D: usize = 2;
-    type C = PoseidonGoldilocksConfig;
-    type F = GoldilocksField;
-
-    // CREATE TREE WITH 2^n leaves
-    let n = 1 << tree_height();
-    let private_keys: Vec<[F; 4]> = (0..n).map(|_| F::rand_array()).collect();
-    let rlns: Vec<F> = (0..n).map(|_| F::ONE).collect();
-    let public_keys: Vec<Vec<F>> = private_keys
-        .iter()
-        .map(|&sk| {
-            PoseidonHash::hash_no_pad(&[sk, [F::ZERO, F::ZERO, F::ZERO, F::ONE]].concat())
-                .elements
-                .to_vec()
-        })
-        .collect();
-
-    let merkle_tree: MerkleTree<GoldilocksField, PoseidonHash> = MerkleTree::new(public_keys, 0);
-    let merkle_root_value = merkle_tree.cap.0[0];
-
-    // Start with a dummy proof of specified size
-    let merkle_proof1 = merkle_tree.prove(1);
-    let topic1: [GoldilocksField; 4] = F::rand_array();
-    let nullifier1 = PoseidonHash::hash_no_pad(&[private_keys[1], topic1].concat()).elements;
-    let inner1 = semaphore_proof(
-        zk_config,
-        private_keys[1],
-        rlns[1],
-        topic1,
-        1,
-        merkle_proof1,
-        merkle_root_value,
-    )?; //dummy_proof::<F, C, D>(config, log2_inner_size)?;
-    let (_, _, cd1) = &inner1;
-    info!(
-        "Initial proof 1 degree {} = 2^{}",
-        cd1.degree(),
-        cd1.degree_bits()
-    );
-
-    let merkle_proof2 = merkle_tree.prove(2);
-    let topic2: [GoldilocksField; 4] = F::rand_array();
-    let nullifier2 = PoseidonHash::hash_no_pad(&[private_keys[2], topic2].concat()).elements;
-    let inner2 = semaphore_proof(
-        zk_config,
-        private_keys[2],
-        rlns[2],
-        topic1,
-        2,
-        merkle_proof2,
-        merkle_root_value,
-    )?; //dummy_proof::<F, C, D>(config, log2_inner_size)?;
-        //let inner2 = dummy_proof::<F, C, D>(config, log2_inner_size)?;
-    let (_, _, cd2) = &inner2;
-    info!(
-        "Initial proof 2 degree {} = 2^{}",
-        cd2.degree(),
-        cd2.degree_bits()
-    );
-
-    // Recursively verify the proof
-    let middle1 = recursive_proof::<F, C, C, D>(&inner1, &inner2, config, None)?;
-    let (_, _, cdm1) = &middle1;
-    info!(
-        "Single recursion proof 1 degree {} = 2^{}",
-        cdm1.degree(),
-        cdm1.degree_bits()
-    );
-
-    let merkle_proof3 = merkle_tree.prove(3);
-    let topic3: [GoldilocksField; 4] = F::rand_array();
-    //let inner3 = dummy_proof::<F, C, D>(config, log2_inner_size)?;
-    let nullifier3 = PoseidonHash::hash_no_pad(&[private_keys[3], topic3].concat()).elements;
-    let inner3 = semaphore_proof(
-        zk_config,
-        private_keys[3],
-        rlns[3],
-        topic1,
-        3,
-        merkle_proof3,
-        merkle_root_value,
-    )?; //dummy_proof::<F, C, D>(config, log2_inner_size)?;
-    let (_, _, cd3) = &inner3;
-    info!(
-        "Initial proof 3 degree {} = 2^{}",
-        cd3.degree(),
-        cd3.degree_bits()
-    );
-
-    let merkle_proof4 = merkle_tree.prove(4);
-    let topic4: [GoldilocksField; 4] = F::rand_array();
-    let nullifier4 = PoseidonHash::hash_no_pad(&[private_keys[4], topic1].concat()).elements;
-    let inner4 = semaphore_proof(
-        zk_config,
-        private_keys[4],
-        rlns[4],
-        topic4,
-        4,
-        merkle_proof4,
-        merkle_root_value,
-    )?; //dummy_proof::<F, C, D>(config, log2_inner_size)?;
-        //let inner4 = dummy_proof::<F, C, D>(config, log2_inner_size)?;
-    let (_, _, cd4) = &inner4;
-    info!(
-        "Initial proof 4 degree {} = 2^{}",
-        cd4.degree(),
-        cd4.degree_bits()
-    );
-
-    // Recursively verify the proof
-    let middle2 = recursive_proof::<F, C, C, D>(&inner3, &inner4, config, None)?;
-    let (_, _, cdm2) = &middle2;
-    info!(
-        "Single recursion proof 2 degree {} = 2^{}",
-        cdm2.degree(),
-        cdm2.degree_bits()
-    );
-
-    // Add a second layer of recursion to shrink the proof size further
-    let outer = recursive_proof::<F, C, C, D>(&middle1, &middle2, config, None)?;
-    let (proof, vd, cd) = &outer;
-    info!(
-        "Double recursion proof degree {} = 2^{}",
-        cd.degree(),
-        cd.degree_bits()
-    );
-
-    test_serialization(proof, vd, cd)?;
-
-    let conf = generate_verifier_config(&proof)?;
-    let (circom_constants, circom_gates) = generate_circom_verifier(&conf, &cd, &vd)?;
-
-    let mut circom_file = File::create("./circom/circuits/constants.circom")?;
-    circom_file.write_all(circom_constants.as_bytes())?;
-    circom_file = File::create("./circom/circuits/gates.circom")?;
-    circom_file.write_all(circom_gates.as_bytes())?;
-
-    let proof_json = generate_proof_base64(&proof, &conf)?;
-
-    if !Path::new("./circom/test/data").is_dir() {
-        std::fs::create_dir("circom/test/data")?;
-    }
-    //input for snarkjs
-    let mut proof_file = File::create("./circom/test/data/proof.json")?;
-    proof_file.write_all(proof_json.as_bytes())?;
-
-    //input for snarkjs
-    let mut conf_file = File::create("./circom/test/data/conf.json")?;
-    conf_file.write_all(serde_json::to_string(&conf)?.as_ref())?;
-
-    //TODO: gen contract from plonky2 circom
-    Ok(())
-}
-
-fn main() -> Result<()> {
-    // Parse command line arguments, see `--help` for details.
-    let options = Options::from_args_safe()?;
-
-    // Initialize logging
-    let mut builder = env_logger::Builder::from_default_env();
-    builder.parse_filters(&options.log_filter);
-    builder.format_timestamp(None);
-    match options.verbose {
-        0 => &mut builder,
-        1 => builder.filter_level(LevelFilter::Info),
-        2 => builder.filter_level(LevelFilter::Debug),
-        _ => builder.filter_level(LevelFilter::Trace),
-    };
-    builder.try_init()?;
-
-    // Initialize randomness source
-    let rng_seed = options.seed.unwrap_or_else(|| OsRng::default().next_u64());
-    info!("Using random seed {rng_seed:16x}");
-    let _rng = ChaCha8Rng::seed_from_u64(rng_seed);
-    // TODO: Use `rng` to create deterministic runs
-
-    let num_cpus = num_cpus::get();
-    let threads = options.threads.unwrap_or(num_cpus..=num_cpus);
-
-    let config = CircuitConfig::standard_recursion_config();
-    let zk_config = CircuitConfig::standard_recursion_config();
-    for log2_inner_size in options.size {
-        // Since the `size` is most likely to be and unbounded range we make that the outer iterator.
-        for threads in threads.clone() {
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(threads)
-                .build()
-                .context("Failed to build thread pool.")?
-                .install(|| {
-                    info!(
-                        "Using {} compute threads on {} cores",
-                        rayon::current_num_threads(),
-                        num_cpus
-                    );
-                    // Run the benchmark
-                    benchmark(&config, &zk_config, log2_inner_size)
-                })?;
-        }
-    }
-
-    Ok(())
-}
-
-fn parse_hex_u64(src: &str) -> Result<u64, ParseIntError> {
-    let src = src.strip_prefix("0x").unwrap_or(src);
-    u64::from_str_radix(src, 16)
-}
-
-fn parse_range_usize(src: &str) -> Result<RangeInclusive<usize>, ParseIntError> {
-    if let Some((left, right)) = src.split_once("..=") {
-        Ok(RangeInclusive::new(
-            usize::from_str(left)?,
-            usize::from_str(right)?,
-        ))
-    } else if let Some((left, right)) = src.split_once("..") {
-        Ok(RangeInclusive::new(
-            usize::from_str(left)?,
-            if right.is_empty() {
-                usize::MAX
-            } else {
-                usize::from_str(right)?.saturating_sub(1)
-            },
-        ))
-    } else {
-        let value = usize::from_str(src)?;
-        Ok(RangeInclusive::new(value, value))
-    }
-}
@@ -0,0 +1,330 @@
+//! Aggregation of proofs and their export to the circom verifier, on proofs read from files rather
+//! than generated by the private_tx scenario. Proofs and verifier data use the encodings of
+//! `ProofWithPublicInputs::to_bytes` and `VerifierCircuitData::to_bytes`, as `plonky2-verify`
+//! does, so the output of `aggregate` can be passed to either tool. See the README.
+
+mod bench;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use log::{info, LevelFilter};
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProvenCircuitOutput};
+use plonky2_field::goldilocks_field::GoldilocksField;
+use plonky2_recursive_tools::{
+    circom_final_config, generate_circom_verifier, generate_proof_base64, generate_verifier_config,
+    recursive_proof, PublicInputsCommitment,
+};
+use serde_json::Value;
+use structopt::StructOpt;
+
+use crate::bench::BenchOptions;
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
+#[derive(Clone, StructOpt, Debug)]
+#[structopt(name = "recursive-tools")]
+struct Options {
+    /// Verbose mode (-v, -vv, -vvv, etc.)
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    verbose: usize,
+
+    /// Apply an env_filter compatible log filter
+    #[structopt(long, env, default_value, global = true)]
+    log_filter: String,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, StructOpt, Debug)]
+enum Command {
+    /// Aggregates proofs pairwise, layer by layer, into a single proof that all of them are valid.
+    Aggregate {
+        /// A proof to aggregate, in order. At least two are needed.
+        #[structopt(long = "proof", required = true, parse(from_os_str))]
+        proofs: Vec<PathBuf>,
+
+        /// The verifier data of each proof, in order, or a single one shared by all of them.
+        #[structopt(long = "verifier-data", required = true, parse(from_os_str))]
+        verifier_data: Vec<PathBuf>,
+
+        /// Prove the last layer with `circom_final_config`, for export to the circom verifier.
+        #[structopt(long = "final")]
+        circom_final: bool,
+
+        /// Where to write `proof.bin`, `verifier_data.bin` and `public_inputs.json`.
+        #[structopt(long, default_value = ".", parse(from_os_str))]
+        out_dir: PathBuf,
+    },
+
+    /// Writes the circom verifier of a proof's circuit, `constants.circom` and `gates.circom`, and
+    /// the proof as its input, `proof.json`.
+    ExportCircom {
+        #[structopt(parse(from_os_str))]
+        proof: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        verifier_data: PathBuf,
+
+        /// How the public inputs are exposed: none, keccak or sha256.
+        #[structopt(long, default_value = "none")]
+        public_inputs_commitment: PublicInputsCommitment,
+
+        /// The circom directory, whose `circuits` and `test/data` the files are written to.
+        #[structopt(long, default_value = "./circom", parse(from_os_str))]
+        circom_dir: PathBuf,
+    },
+
+    /// Writes the verifier config of a proof, `conf.json`, as read by the circom tests.
+    ExportConfig {
+        #[structopt(parse(from_os_str))]
+        proof: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        verifier_data: PathBuf,
+
+        /// How the public inputs are exposed: none, keccak or sha256.
+        #[structopt(long, default_value = "none")]
+        public_inputs_commitment: PublicInputsCommitment,
+
+        #[structopt(
+            long,
+            default_value = "./circom/test/data/conf.json",
+            parse(from_os_str)
+        )]
+        out: PathBuf,
+    },
+
+    /// Verifies a proof, and optionally that it has the given public inputs.
+    Verify {
+        #[structopt(parse(from_os_str))]
+        proof: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        verifier_data: PathBuf,
+
+        /// The expected public inputs, as a JSON array of integers or decimal strings.
+        #[structopt(long, parse(from_os_str))]
+        public_inputs: Option<PathBuf>,
+    },
+
+    /// Benchmarks the aggregation of semaphore proofs and their export to the circom verifier.
+    Bench(BenchOptions),
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Can't read {:?}", path))
+}
+
+fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Can't create {:?}", dir))?;
+    }
+    fs::write(path, contents).with_context(|| format!("Can't write {:?}", path))
+}
+
+fn read_verifier_data(path: &Path) -> Result<VerifierCircuitData<F, C, D>> {
+    VerifierCircuitData::from_bytes(read(path)?)
+        .with_context(|| format!("Can't parse the verifier data {:?}", path))
+}
+
+/// Reads a proof and the verifier data of its circuit.
+fn read_proven(proof: &Path, verifier_data: &Path) -> Result<ProvenCircuitOutput<F, C, D>> {
+    let verifier_data = read_verifier_data(verifier_data)?;
+    let proof = ProofWithPublicInputs::from_bytes(read(proof)?, &verifier_data.common)
+        .with_context(|| format!("Can't parse the proof {:?}", proof))?;
+    Ok(ProvenCircuitOutput::new(
+        proof,
+        verifier_data.verifier_only,
+        verifier_data.common,
+    ))
+}
+
+fn read_public_inputs(path: &Path) -> Result<Vec<F>> {
+    let values: Vec<Value> = serde_json::from_slice(&read(path)?)
+        .with_context(|| format!("Can't parse the public inputs {:?}", path))?;
+    values
+        .iter()
+        .map(|value| {
+            let x = match value {
+                Value::Number(x) => x.as_u64(),
+                Value::String(x) => x.parse().ok(),
+                _ => None,
+            }
+            .filter(|&x| x < F::ORDER)
+            .ok_or_else(|| anyhow!("{} isn't a field element", value))?;
+            Ok(F::from_canonical_u64(x))
+        })
+        .collect()
+}
+
+fn aggregate(
+    proofs: &[PathBuf],
+    verifier_data: &[PathBuf],
+    circom_final: bool,
+    out_dir: &Path,
+) -> Result<()> {
+    ensure!(proofs.len() >= 2, "At least two proofs are needed");
+    ensure!(
+        verifier_data.len() == 1 || verifier_data.len() == proofs.len(),
+        "Got {} proofs but {} verifier data files",
+        proofs.len(),
+        verifier_data.len()
+    );
+    let mut layer = proofs
+        .iter()
+        .enumerate()
+        .map(|(i, proof)| read_proven(proof, &verifier_data[i % verifier_data.len()]))
+        .collect::<Result<Vec<_>>>()?;
+    for proven in &layer {
+        proven.verify()?;
+    }
+
+    let config = CircuitConfig::standard_recursion_config();
+    let final_config = circom_final_config(&config)?;
+    while layer.len() > 1 {
+        let config = if circom_final && layer.len() == 2 {
+            &final_config
+        } else {
+            &config
+        };
+        // An odd proof out is carried over to the next layer.
+        let mut next = Vec::with_capacity((layer.len() + 1) / 2);
+        let mut pending = layer.into_iter();
+        while let Some(left) = pending.next() {
+            next.push(match pending.next() {
+                Some(right) => recursive_proof::<F, C, C, D>(&left, &right, config, None)?,
+                None => left,
+            });
+        }
+        info!(
+            "Aggregated into {} proofs of degree 2^{}",
+            next.len(),
+            next[0].common().degree_bits()
+        );
+        layer = next;
+    }
+
+    let (proof, verifier_only, common) = layer.pop().unwrap().into_parts();
+    let public_inputs: Vec<String> = proof
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64().to_string())
+        .collect();
+    write(&out_dir.join("proof.bin"), proof.to_bytes())?;
+    write(
+        &out_dir.join("verifier_data.bin"),
+        VerifierCircuitData {
+            verifier_only,
+            common,
+        }
+        .to_bytes(),
+    )?;
+    write(
+        &out_dir.join("public_inputs.json"),
+        serde_json::to_string(&public_inputs)?,
+    )
+}
+
+fn export_circom(
+    proof: &Path,
+    verifier_data: &Path,
+    public_inputs_commitment: PublicInputsCommitment,
+    circom_dir: &Path,
+) -> Result<()> {
+    let proven = read_proven(proof, verifier_data)?;
+    let conf = generate_verifier_config(proven.proof(), public_inputs_commitment)?;
+    let (constants, gates) =
+        generate_circom_verifier(&conf, proven.common(), proven.verifier_only())?;
+    write(&circom_dir.join("circuits/constants.circom"), constants)?;
+    write(&circom_dir.join("circuits/gates.circom"), gates)?;
+    write(
+        &circom_dir.join("test/data/proof.json"),
+        generate_proof_base64(proven.proof(), &conf)?,
+    )
+}
+
+fn export_config(
+    proof: &Path,
+    verifier_data: &Path,
+    public_inputs_commitment: PublicInputsCommitment,
+    out: &Path,
+) -> Result<()> {
+    let proven = read_proven(proof, verifier_data)?;
+    let conf = generate_verifier_config(proven.proof(), public_inputs_commitment)?;
+    write(out, serde_json::to_string(&conf)?)
+}
+
+fn verify(proof: &Path, verifier_data: &Path, public_inputs: Option<&Path>) -> Result<()> {
+    let proven = read_proven(proof, verifier_data)?;
+    if let Some(path) = public_inputs {
+        let expected = read_public_inputs(path)?;
+        ensure!(
+            proven.public_inputs() == expected,
+            "The proof's public inputs don't match {:?}",
+            path
+        );
+    }
+    proven.verify().context("The proof is invalid")?;
+    println!(
+        "The proof of circuit {} is valid",
+        proven.verifier_only().circuit_digest
+    );
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    // Parse command line arguments, see `--help` for details.
+    let options = Options::from_args_safe()?;
+
+    // Initialize logging
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.parse_filters(&options.log_filter);
+    builder.format_timestamp(None);
+    match options.verbose {
+        0 => &mut builder,
+        1 => builder.filter_level(LevelFilter::Info),
+        2 => builder.filter_level(LevelFilter::Debug),
+        _ => builder.filter_level(LevelFilter::Trace),
+    };
+    builder.try_init()?;
+
+    match options.command {
+        Command::Aggregate {
+            proofs,
+            verifier_data,
+            circom_final,
+            out_dir,
+        } => aggregate(&proofs, &verifier_data, circom_final, &out_dir),
+        Command::ExportCircom {
+            proof,
+            verifier_data,
+            public_inputs_commitment,
+            circom_dir,
+        } => export_circom(
+            &proof,
+            &verifier_data,
+            public_inputs_commitment,
+            &circom_dir,
+        ),
+        Command::ExportConfig {
+            proof,
+            verifier_data,
+            public_inputs_commitment,
+            out,
+        } => export_config(&proof, &verifier_data, public_inputs_commitment, &out),
+        Command::Verify {
+            proof,
+            verifier_data,
+            public_inputs,
+        } => verify(&proof, &verifier_data, public_inputs.as_deref()),
+        Command::Bench(options) => bench::run(options),
+    }
+}
@@ -1,5 +1,5 @@
 use plonky2::fri::reduction_strategies::FriReductionStrategy;
-use plonky2::fri::{FriConfig, FriParams};
+use plonky2::fri::{FriConfig, FriParams, FriPowHash};
 
 pub struct StarkConfig {
     pub security_bits: usize,
@@ -22,6 +22,7 @@ impl StarkConfig {
                 rate_bits: 1,
                 cap_height: 4,
                 proof_of_work_bits: 16,
+                pow_hash: FriPowHash::Field,
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 84,
             },
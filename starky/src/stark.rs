@@ -97,6 +97,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         oracles.push(FriOracleInfo {
             num_polys: Self::COLUMNS,
             blinding: false,
+            degree_bits: None,
         });
 
         let permutation_zs_info = if self.uses_permutation_args() {
@@ -105,6 +106,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             oracles.push(FriOracleInfo {
                 num_polys: num_z_polys,
                 blinding: false,
+                degree_bits: None,
             });
             polys
         } else {
@@ -116,6 +118,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         oracles.push(FriOracleInfo {
             num_polys: num_quotient_polys,
             blinding: false,
+            degree_bits: None,
         });
 
         let zeta_batch = FriBatchInfo {
@@ -150,6 +153,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         oracles.push(FriOracleInfo {
             num_polys: Self::COLUMNS,
             blinding: false,
+            degree_bits: None,
         });
 
         let permutation_zs_info = if self.uses_permutation_args() {
@@ -158,6 +162,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             oracles.push(FriOracleInfo {
                 num_polys: num_z_polys,
                 blinding: false,
+                degree_bits: None,
             });
             polys
         } else {
@@ -169,6 +174,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         oracles.push(FriOracleInfo {
             num_polys: num_quotient_polys,
             blinding: false,
+            degree_bits: None,
         });
 
         let zeta_batch = FriBatchInfoTarget {
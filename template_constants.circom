@@ -71,6 +71,7 @@ function PRIMITIVE_ROOT_OF_UNITY_LDE() { return $PRIMITIVE_ROOT_OF_UNITY_LDE; }
 function LOG_SIZE_OF_LDE_DOMAIN() { return $LOG_SIZE_OF_LDE_DOMAIN; }
 function NUM_CHALLENGES() { return $NUM_CHALLENGES; }
 function MIN_FRI_POW_RESPONSE() { return $MIN_FRI_POW_RESPONSE; }
+function FRI_POW_HASH() { return $FRI_POW_HASH; }
 function CIRCUIT_DIGEST() {
   var cd[4];
   $SET_CIRCUIT_DIGEST;
@@ -90,3 +91,4 @@ function K_IS(i) {
   return k_is[i];
 }
 function NUM_PUBLIC_INPUTS() { return $NUM_PUBLIC_INPUTS; }
+function PUBLIC_INPUTS_COMMITMENT() { return $PUBLIC_INPUTS_COMMITMENT; }
@@ -0,0 +1,326 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::BoolTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::gadgets::arithmetic_u32::{CircuitBuilderU32, U32Target};
+use crate::gadgets::multiple_comparison::list_le_u32_circuit;
+use crate::gadgets::range_check::range_check_u32_circuit;
+
+/// An unsigned integer of `N` 32-bit limbs, least significant first, e.g. a token amount which
+/// may not fit in a field element.
+#[derive(Clone, Copy, Debug)]
+pub struct UintTarget<const N: usize> {
+    pub limbs: [U32Target; N],
+}
+
+pub type U64Target = UintTarget<2>;
+
+pub type U128Target = UintTarget<4>;
+
+pub trait CircuitBuilderUint<F: RichField + Extendable<D>, const D: usize> {
+    /// Returns a `UintTarget` whose limbs aren't range-checked, see `range_check_uint`.
+    fn add_virtual_uint_target<const N: usize>(&mut self) -> UintTarget<N>;
+
+    /// Returns a `UintTarget` for the value `c`, which must fit in `N` limbs.
+    fn constant_uint<const N: usize>(&mut self, c: u128) -> UintTarget<N>;
+
+    fn connect_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>);
+
+    /// Checks that each limb of `a` fits in 32 bits.
+    fn range_check_uint<const N: usize>(&mut self, a: UintTarget<N>);
+
+    /// Returns `a + b` modulo `2^(32 * N)`, and whether it overflowed. The limbs of `a` and `b`
+    /// must be range-checked, see `range_check_uint`, for the sum to be meaningful.
+    fn add_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, BoolTarget);
+
+    /// Returns `a - b` modulo `2^(32 * N)`, and whether it underflowed, i.e. whether `b > a`.
+    fn sub_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, BoolTarget);
+
+    /// Returns `a * b` as its low and high halves. Supports up to four limbs.
+    fn mul_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, UintTarget<N>);
+
+    /// Returns `a + b`, which must not overflow. As with `add_uint`, the limbs of `a` and `b` must
+    /// be range-checked.
+    fn checked_add_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N>;
+
+    /// Returns `a - b`, which must not underflow.
+    fn checked_sub_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N>;
+
+    /// Returns `a * b`, which must not overflow.
+    fn checked_mul_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N>;
+
+    /// Returns whether `a <= b`. This range-checks its inputs.
+    fn le_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>) -> BoolTarget;
+
+    /// Returns whether `a < b`. This range-checks its inputs.
+    fn lt_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>) -> BoolTarget;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderUint<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_uint_target<const N: usize>(&mut self) -> UintTarget<N> {
+        UintTarget {
+            limbs: core::array::from_fn(|_| self.add_virtual_u32_target()),
+        }
+    }
+
+    fn constant_uint<const N: usize>(&mut self, c: u128) -> UintTarget<N> {
+        assert!(
+            N >= 4 || c >> (32 * N) == 0,
+            "{} doesn't fit in {} limbs",
+            c,
+            N
+        );
+        UintTarget {
+            limbs: core::array::from_fn(|i| {
+                let limb = c.checked_shr(32 * i as u32).unwrap_or(0) as u32;
+                self.constant_u32(limb)
+            }),
+        }
+    }
+
+    fn connect_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>) {
+        for i in 0..N {
+            self.connect_u32(a.limbs[i], b.limbs[i]);
+        }
+    }
+
+    fn range_check_uint<const N: usize>(&mut self, a: UintTarget<N>) {
+        range_check_u32_circuit(self, a.limbs.to_vec());
+    }
+
+    fn add_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, BoolTarget) {
+        let mut carry = self.zero_u32();
+        let limbs = core::array::from_fn(|i| {
+            let (limb, new_carry) = self.add_u32s_with_carry(&[a.limbs[i], b.limbs[i]], carry);
+            carry = new_carry;
+            limb
+        });
+        // The sum of two 32-bit limbs and a carry bit has a carry of at most one, but the limbs
+        // may not have been range-checked, so constrain it explicitly.
+        let carry = BoolTarget::new_unsafe(carry.0);
+        self.assert_bool(carry);
+        (UintTarget { limbs }, carry)
+    }
+
+    fn sub_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, BoolTarget) {
+        let mut borrow = self.zero_u32();
+        let limbs = core::array::from_fn(|i| {
+            let (limb, new_borrow) = self.sub_u32(a.limbs[i], b.limbs[i], borrow);
+            borrow = new_borrow;
+            limb
+        });
+        // `U32SubtractionGate` constrains its output borrow to be a bit.
+        (UintTarget { limbs }, BoolTarget::new_unsafe(borrow.0))
+    }
+
+    fn mul_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> (UintTarget<N>, UintTarget<N>) {
+        // Wider columns could have carries too large for `U32AddManyGate`.
+        assert!(N <= 4, "mul_uint supports up to four limbs");
+        let mut columns = vec![Vec::new(); 2 * N];
+        for i in 0..N {
+            for j in 0..N {
+                let (low, high) = self.mul_u32(a.limbs[i], b.limbs[j]);
+                columns[i + j].push(low);
+                columns[i + j + 1].push(high);
+            }
+        }
+
+        let mut carry = self.zero_u32();
+        let mut product = Vec::with_capacity(2 * N);
+        for column in columns {
+            let (limb, new_carry) = self.add_u32s_with_carry(&column, carry);
+            product.push(limb);
+            carry = new_carry;
+        }
+        self.assert_zero_u32(carry);
+
+        let low = UintTarget {
+            limbs: product[..N].try_into().unwrap(),
+        };
+        let high = UintTarget {
+            limbs: product[N..].try_into().unwrap(),
+        };
+        (low, high)
+    }
+
+    fn checked_add_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N> {
+        let (sum, overflow) = self.add_uint(a, b);
+        self.assert_zero(overflow.target);
+        sum
+    }
+
+    fn checked_sub_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N> {
+        let (difference, underflow) = self.sub_uint(a, b);
+        self.assert_zero(underflow.target);
+        difference
+    }
+
+    fn checked_mul_uint<const N: usize>(
+        &mut self,
+        a: UintTarget<N>,
+        b: UintTarget<N>,
+    ) -> UintTarget<N> {
+        let (low, high) = self.mul_uint(a, b);
+        for limb in high.limbs {
+            self.assert_zero_u32(limb);
+        }
+        low
+    }
+
+    fn le_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>) -> BoolTarget {
+        list_le_u32_circuit(self, a.limbs.to_vec(), b.limbs.to_vec())
+    }
+
+    fn lt_uint<const N: usize>(&mut self, a: UintTarget<N>, b: UintTarget<N>) -> BoolTarget {
+        let b_le_a = self.le_uint(b, a);
+        self.not(b_le_a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::witness::WitnessU32;
+
+    #[test]
+    fn test_uint_arithmetic() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let x: u128 = rng.gen();
+        let y: u128 = rng.gen();
+        let a = builder.add_virtual_uint_target::<4>();
+        let b = builder.add_virtual_uint_target::<4>();
+        builder.range_check_uint(a);
+        builder.range_check_uint(b);
+        pw.set_uint_target(a, x);
+        pw.set_uint_target(b, y);
+
+        let (sum, overflow) = builder.add_uint(a, b);
+        let expected_sum = builder.constant_uint(x.wrapping_add(y));
+        builder.connect_uint(sum, expected_sum);
+        let expected_overflow = builder.constant_bool(x.checked_add(y).is_none());
+        builder.connect(overflow.target, expected_overflow.target);
+
+        let (difference, underflow) = builder.sub_uint(a, b);
+        let expected_difference = builder.constant_uint(x.wrapping_sub(y));
+        builder.connect_uint(difference, expected_difference);
+        let expected_underflow = builder.constant_bool(y > x);
+        builder.connect(underflow.target, expected_underflow.target);
+
+        let lt = builder.lt_uint(a, b);
+        let expected_lt = builder.constant_bool(x < y);
+        builder.connect(lt.target, expected_lt.target);
+        let le = builder.le_uint(a, a);
+        let t = builder._true();
+        builder.connect(le.target, t.target);
+
+        // A full 64-bit product, and a 128-bit one which doesn't overflow.
+        let (x64, y64) = (x as u64, y as u64);
+        let c = builder.constant_uint::<2>(x64 as u128);
+        let d = builder.constant_uint::<2>(y64 as u128);
+        let (low, high) = builder.mul_uint(c, d);
+        let product = x64 as u128 * y64 as u128;
+        let expected_low = builder.constant_uint(product as u64 as u128);
+        let expected_high = builder.constant_uint(product >> 64);
+        builder.connect_uint(low, expected_low);
+        builder.connect_uint(high, expected_high);
+
+        let e = builder.constant_uint::<4>(x64 as u128);
+        let f = builder.constant_uint::<4>(y64 as u128);
+        let checked_product = builder.checked_mul_uint(e, f);
+        let expected_product = builder.constant_uint(product);
+        builder.connect_uint(checked_product, expected_product);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_add_uint_unchecked_carry() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Limbs of `2^33 - 1`, which aren't range-checked, would give a carry of three.
+        let a = builder.add_virtual_uint_target::<1>();
+        let b = builder.add_virtual_uint_target::<1>();
+        let big = F::from_canonical_u64((1 << 33) - 1);
+        pw.set_target(a.limbs[0].0, big);
+        pw.set_target(b.limbs[0].0, big);
+        builder.add_uint(a, b);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
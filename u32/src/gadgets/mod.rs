@@ -1,3 +1,4 @@
 pub mod arithmetic_u32;
+pub mod arithmetic_uint;
 pub mod multiple_comparison;
 pub mod range_check;
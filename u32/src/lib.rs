@@ -5,4 +5,5 @@ extern crate alloc;
 
 pub mod gadgets;
 pub mod gates;
+pub mod prelude;
 pub mod witness;
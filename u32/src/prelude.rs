@@ -0,0 +1,14 @@
+//! The gadgets of this crate, with the targets they take and the witness traits which set them:
+//!
+//! ```ignore
+//! use plonky2_u32::prelude::*;
+//! ```
+//!
+//! Most gadgets are methods of `CircuitBuilder`, through the `CircuitBuilderU32` and
+//! `CircuitBuilderUint` traits, e.g. `add_u32` or `checked_add_uint` on `U128Target`s.
+
+pub use crate::gadgets::arithmetic_u32::{CircuitBuilderU32, U32Target};
+pub use crate::gadgets::arithmetic_uint::{CircuitBuilderUint, U128Target, U64Target, UintTarget};
+pub use crate::gadgets::multiple_comparison::list_le_u32_circuit;
+pub use crate::gadgets::range_check::range_check_u32_circuit;
+pub use crate::witness::{GeneratedValuesU32, WitnessU32};
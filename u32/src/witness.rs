@@ -3,10 +3,15 @@ use plonky2::iop::generator::GeneratedValues;
 use plonky2::iop::witness::{Witness, WitnessWrite};
 
 use crate::gadgets::arithmetic_u32::U32Target;
+use crate::gadgets::arithmetic_uint::UintTarget;
 
 pub trait WitnessU32<F: PrimeField64>: Witness<F> {
     fn set_u32_target(&mut self, target: U32Target, value: u32);
     fn get_u32_target(&self, target: U32Target) -> (u32, u32);
+    /// Sets the limbs of `target` to those of `value`, which must fit in them.
+    fn set_uint_target<const N: usize>(&mut self, target: UintTarget<N>, value: u128);
+    /// The value of `target`, whose limbs must be 32-bit values of which at most four are nonzero.
+    fn get_uint_target<const N: usize>(&self, target: UintTarget<N>) -> u128;
 }
 
 impl<T: Witness<F>, F: PrimeField64> WitnessU32<F> for T {
@@ -20,6 +25,28 @@ impl<T: Witness<F>, F: PrimeField64> WitnessU32<F> for T {
         let high = (x_u64 >> 32) as u32;
         (low, high)
     }
+
+    fn set_uint_target<const N: usize>(&mut self, target: UintTarget<N>, value: u128) {
+        assert!(
+            N >= 4 || value >> (32 * N) == 0,
+            "{} doesn't fit in {} limbs",
+            value,
+            N
+        );
+        for (i, limb) in target.limbs.into_iter().enumerate() {
+            let limb_value = value.checked_shr(32 * i as u32).unwrap_or(0) as u32;
+            self.set_u32_target(limb, limb_value);
+        }
+    }
+
+    fn get_uint_target<const N: usize>(&self, target: UintTarget<N>) -> u128 {
+        target.limbs.iter().enumerate().fold(0, |acc, (i, &limb)| {
+            let (low, high) = self.get_u32_target(limb);
+            assert_eq!(high, 0, "limb {} isn't a 32-bit value", i);
+            assert!(i < 4 || low == 0, "the value doesn't fit in a u128");
+            acc | (low as u128).checked_shl(32 * i as u32).unwrap_or(0)
+        })
+    }
 }
 
 pub trait GeneratedValuesU32<F: Field> {
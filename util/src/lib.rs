@@ -4,17 +4,23 @@
 #![allow(clippy::len_without_is_empty)]
 #![allow(clippy::needless_range_loop)]
 #![allow(clippy::return_self_not_must_use)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
 #![no_std]
 
 extern crate alloc;
 
 use alloc::vec::Vec;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::hint::unreachable_unchecked;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::mem::size_of;
+#[cfg(not(feature = "forbid-unsafe"))]
 use core::ptr::{swap, swap_nonoverlapping};
 
+#[cfg(not(feature = "forbid-unsafe"))]
 use crate::transpose_util::transpose_in_place_square;
 
+#[cfg(not(feature = "forbid-unsafe"))]
 mod transpose_util;
 
 pub fn bits_u64(n: u64) -> usize {
@@ -111,7 +117,7 @@ fn reverse_index_bits_large<T: Copy>(arr: &[T], n_power: usize) -> Vec<T> {
 
 /// Bit-reverse the order of elements in `arr`.
 /// SAFETY: ensure that `arr.len() == 1 << lb_n`.
-#[cfg(not(target_arch = "aarch64"))]
+#[cfg(all(not(target_arch = "aarch64"), not(feature = "forbid-unsafe")))]
 unsafe fn reverse_index_bits_in_place_small<T>(arr: &mut [T], lb_n: usize) {
     if lb_n <= 6 {
         // BIT_REVERSE_6BIT holds 6-bit reverses. This shift makes them lb_n-bit reverses.
@@ -145,7 +151,7 @@ unsafe fn reverse_index_bits_in_place_small<T>(arr: &mut [T], lb_n: usize) {
 
 /// Bit-reverse the order of elements in `arr`.
 /// SAFETY: ensure that `arr.len() == 1 << lb_n`.
-#[cfg(target_arch = "aarch64")]
+#[cfg(all(target_arch = "aarch64", not(feature = "forbid-unsafe")))]
 unsafe fn reverse_index_bits_in_place_small<T>(arr: &mut [T], lb_n: usize) {
     // Aarch64 can reverse bits in one instruction, so the trivial version works best.
     for src in 0..arr.len() {
@@ -162,6 +168,7 @@ unsafe fn reverse_index_bits_in_place_small<T>(arr: &mut [T], lb_n: usize) {
 /// Split `arr` chunks and bit-reverse the order of the chunks. There are `1 << lb_num_chunks`
 /// chunks, each of length `1 << lb_chunk_size`.
 /// SAFETY: ensure that `arr.len() == 1 << lb_num_chunks + lb_chunk_size`.
+#[cfg(not(feature = "forbid-unsafe"))]
 unsafe fn reverse_index_bits_in_place_chunks<T>(
     arr: &mut [T],
     lb_num_chunks: usize,
@@ -183,8 +190,11 @@ unsafe fn reverse_index_bits_in_place_chunks<T>(
 }
 
 // Ensure that SMALL_ARR_SIZE >= 4 * BIG_T_SIZE.
+#[cfg(not(feature = "forbid-unsafe"))]
 const BIG_T_SIZE: usize = 1 << 14;
+#[cfg(not(feature = "forbid-unsafe"))]
 const SMALL_ARR_SIZE: usize = 1 << 16;
+#[cfg(not(feature = "forbid-unsafe"))]
 pub fn reverse_index_bits_in_place<T>(arr: &mut [T]) {
     let n = arr.len();
     let lb_n = log2_strict(n);
@@ -236,6 +246,20 @@ pub fn reverse_index_bits_in_place<T>(arr: &mut [T]) {
     }
 }
 
+/// Bit-reverse the order of elements in `arr`, with the trivial algorithm and no unchecked
+/// indexing. It is much less cache-friendly than the default one on large arrays.
+#[cfg(feature = "forbid-unsafe")]
+pub fn reverse_index_bits_in_place<T>(arr: &mut [T]) {
+    let lb_n = log2_strict(arr.len());
+    for src in 0..arr.len() {
+        // `wrapping_shr` handles the case when `arr.len() == 1`, as in the unsafe version.
+        let dst = src.reverse_bits().wrapping_shr(usize::BITS - lb_n as u32);
+        if src < dst {
+            arr.swap(src, dst);
+        }
+    }
+}
+
 // Lookup table of 6-bit reverses.
 // NB: 2^6=64 bytes is a cacheline. A smaller table wastes cache space.
 #[rustfmt::skip]
@@ -253,6 +277,7 @@ const BIT_REVERSE_6BIT: &[u8] = &[
 #[inline(always)]
 pub fn assume(p: bool) {
     debug_assert!(p);
+    #[cfg(not(feature = "forbid-unsafe"))]
     if !p {
         unsafe {
             unreachable_unchecked();
@@ -273,13 +298,16 @@ pub fn branch_hint() {
     // NOTE: These are the currently supported assembly architectures. See the
     // [nightly reference](https://doc.rust-lang.org/nightly/reference/inline-assembly.html) for
     // the most up-to-date list.
-    #[cfg(any(
-        target_arch = "aarch64",
-        target_arch = "arm",
-        target_arch = "riscv32",
-        target_arch = "riscv64",
-        target_arch = "x86",
-        target_arch = "x86_64",
+    #[cfg(all(
+        any(
+            target_arch = "aarch64",
+            target_arch = "arm",
+            target_arch = "riscv32",
+            target_arch = "riscv64",
+            target_arch = "x86",
+            target_arch = "x86_64",
+        ),
+        not(feature = "forbid-unsafe")
     ))]
     unsafe {
         core::arch::asm!("", options(nomem, nostack, preserves_flags));
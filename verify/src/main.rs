@@ -0,0 +1,162 @@
+//! Verifies a serialized proof against its circuit's verifier data and the public inputs it is
+//! expected to have, exiting with a nonzero status if any of them doesn't match. See the README
+//! for the formats.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::hash::hash_types::HashOut;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::{Hasher, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use serde_json::Value;
+use structopt::StructOpt;
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "plonky2-verify")]
+struct Options {
+    /// The proof, encoded by `ProofWithPublicInputs::to_bytes` or as JSON.
+    #[structopt(parse(from_os_str))]
+    proof: PathBuf,
+
+    /// The circuit's verifier data, encoded by `VerifierCircuitData::to_bytes`.
+    #[structopt(parse(from_os_str))]
+    verifier_data: PathBuf,
+
+    /// The expected public inputs, as a JSON array of integers or decimal strings.
+    #[structopt(parse(from_os_str))]
+    public_inputs: PathBuf,
+
+    /// The digest the circuit is expected to have, in the checksummed hex of `HashOut`'s `Display`.
+    /// It is recomputed from the verifier data, so this also checks the committed constants and
+    /// sigmas.
+    #[structopt(long)]
+    circuit_digest: Option<HashOut<F>>,
+
+    /// The circuit's domain separator, as comma-separated integers, needed to recompute its digest.
+    #[structopt(long, use_delimiter = true)]
+    domain_separator: Vec<u64>,
+
+    /// The digest the common circuit data, i.e. the config, FRI parameters and gates, is expected
+    /// to have, as printed along with the result.
+    #[structopt(long)]
+    common_data_digest: Option<HashOut<F>>,
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Can't read {:?}", path))
+}
+
+fn read_proof(
+    path: &Path,
+    verifier_data: &VerifierCircuitData<F, C, D>,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let bytes = read(path)?;
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let proof = if bytes[start..].starts_with(b"{") {
+        serde_json::from_slice(&bytes).map_err(anyhow::Error::from)
+    } else {
+        ProofWithPublicInputs::from_bytes(bytes, &verifier_data.common)
+    };
+    proof.with_context(|| format!("Can't parse the proof {:?}", path))
+}
+
+fn read_public_inputs(path: &Path) -> Result<Vec<F>> {
+    let values: Vec<Value> = serde_json::from_slice(&read(path)?)
+        .with_context(|| format!("Can't parse the public inputs {:?}", path))?;
+    values
+        .iter()
+        .map(|value| {
+            let x = match value {
+                Value::Number(x) => x.as_u64(),
+                Value::String(x) => x.parse().ok(),
+                _ => None,
+            }
+            .filter(|&x| x < F::ORDER)
+            .ok_or_else(|| anyhow!("{} isn't a field element", value))?;
+            Ok(F::from_canonical_u64(x))
+        })
+        .collect()
+}
+
+/// A Poseidon hash of `common.to_bytes()`, one byte per field element. The circuit digest doesn't
+/// cover the common data.
+fn common_data_digest(common: &CommonCircuitData<F, D>) -> HashOut<F> {
+    let elements: Vec<F> = common
+        .to_bytes()
+        .into_iter()
+        .map(F::from_canonical_u8)
+        .collect();
+    PoseidonHash::hash_no_pad(&elements)
+}
+
+fn main() -> Result<()> {
+    let options = Options::from_args();
+    let verifier_data_bytes = read(&options.verifier_data)?;
+    let verifier_data = VerifierCircuitData::<F, C, D>::from_bytes(verifier_data_bytes)
+        .with_context(|| format!("Can't parse the verifier data {:?}", options.verifier_data))?;
+    let circuit_digest = verifier_data.verifier_only.circuit_digest;
+    if let Some(expected) = options.circuit_digest {
+        ensure!(
+            circuit_digest == expected,
+            "The circuit digest is {}, but {} was expected",
+            circuit_digest,
+            expected
+        );
+        let domain_separator = options
+            .domain_separator
+            .iter()
+            .map(|&x| {
+                ensure!(x < F::ORDER, "{} isn't a field element", x);
+                Ok(F::from_canonical_u64(x))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        verifier_data.check_circuit_digest(&domain_separator)?;
+    }
+    let common_digest = common_data_digest(&verifier_data.common);
+    if let Some(expected) = options.common_data_digest {
+        ensure!(
+            common_digest == expected,
+            "The common data digest is {}, but {} was expected",
+            common_digest,
+            expected
+        );
+    }
+    let proof = read_proof(&options.proof, &verifier_data)?;
+    let expected = read_public_inputs(&options.public_inputs)?;
+
+    ensure!(
+        proof.public_inputs.len() == expected.len(),
+        "The proof has {} public inputs, but {} were expected",
+        proof.public_inputs.len(),
+        expected.len()
+    );
+    for (i, (&actual, &expected)) in proof.public_inputs.iter().zip(&expected).enumerate() {
+        ensure!(
+            actual == expected,
+            "Public input {} is {}, but {} was expected",
+            i,
+            actual.to_canonical_u64(),
+            expected.to_canonical_u64()
+        );
+    }
+    verifier_data
+        .verify(proof)
+        .context("The proof is invalid")?;
+    println!(
+        "The proof of circuit {} (common data {}) is valid",
+        circuit_digest, common_digest
+    );
+    Ok(())
+}